@@ -0,0 +1,64 @@
+#![feature(test)]
+extern crate test;
+
+use rand::prelude::random;
+use test::Bencher;
+
+use rdms::{core::Writer, llrb::Llrb};
+
+const N_KEYS: i64 = 1_000_000;
+
+#[bench]
+fn bench_set_sequential(b: &mut Bencher) {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("bench-llrb");
+    let mut key = 0;
+    b.iter(|| {
+        llrb.set(key, key).unwrap();
+        key += 1;
+    });
+}
+
+#[bench]
+fn bench_set_random(b: &mut Bencher) {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("bench-llrb");
+    b.iter(|| {
+        let key = random::<i64>() % N_KEYS;
+        llrb.set(key, key).unwrap();
+    });
+}
+
+#[bench]
+fn bench_set_lsm_sequential(b: &mut Bencher) {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("bench-llrb");
+    let mut key = 0;
+    b.iter(|| {
+        llrb.set(key, key).unwrap();
+        key += 1;
+    });
+}
+
+#[bench]
+fn bench_delete_sequential(b: &mut Bencher) {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("bench-llrb");
+    (0..N_KEYS).for_each(|key| {
+        llrb.set(key, key).unwrap();
+    });
+    let mut key = 0;
+    b.iter(|| {
+        llrb.delete(&(key % N_KEYS)).unwrap();
+        key += 1;
+    });
+}
+
+#[bench]
+fn bench_delete_lsm_sequential(b: &mut Bencher) {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("bench-llrb");
+    (0..N_KEYS).for_each(|key| {
+        llrb.set(key, key).unwrap();
+    });
+    let mut key = 0;
+    b.iter(|| {
+        llrb.delete(&(key % N_KEYS)).unwrap();
+        key += 1;
+    });
+}
@@ -2368,6 +2368,15 @@ where
         Rs::reverse(rs, range)
     }
 
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
@@ -13,11 +13,16 @@
 
 use std::{
     borrow::Borrow,
+    cmp,
+    collections::BTreeMap,
+    convert::TryInto,
     ffi, fmt,
-    hash::Hash,
+    hash::{Hash, Hasher},
     marker,
     ops::{Bound, RangeBounds},
     result,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time,
 };
 
 pub use crate::entry::Entry;
@@ -97,6 +102,223 @@ impl Cutoff {
     }
 }
 
+/// Compare-and-set policy, applicable to [set_cas][Writer::set_cas]
+/// operations. Index types, and [Wal] replay, shall honor the same
+/// policy so that an application's retry logic sees identical
+/// [InvalidCAS][Error::InvalidCAS] behavior irrespective of the index.
+/// Default policy is `Strict`.
+pub enum CasPolicy<V> {
+    /// CAS must match the last modified seqno of the entry. A missing
+    /// key is created only when CAS is ZERO, a deleted key is created
+    /// when CAS is ZERO or matches the seqno of the delete.
+    Strict,
+    /// Same as `Strict` for live entries, while a missing or deleted
+    /// key is created irrespective of the supplied CAS.
+    CreateIfAbsent,
+    /// CAS is compared with the hash of the entry's latest value,
+    /// computed using the supplied function. A missing or deleted key
+    /// is created only when CAS is ZERO. Note that the hash function
+    /// should be stable across restarts for [Wal] replay to work.
+    ValueHash(fn(&V) -> u64),
+}
+
+impl<V> Clone for CasPolicy<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for CasPolicy<V> {}
+
+impl<V> Default for CasPolicy<V> {
+    fn default() -> Self {
+        CasPolicy::Strict
+    }
+}
+
+impl<V> fmt::Debug for CasPolicy<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            CasPolicy::Strict => write!(f, "CasPolicy::Strict"),
+            CasPolicy::CreateIfAbsent => write!(f, "CasPolicy::CreateIfAbsent"),
+            CasPolicy::ValueHash(_) => write!(f, "CasPolicy::ValueHash"),
+        }
+    }
+}
+
+impl<V> CasPolicy<V> {
+    /// Check `cas` against the `entry` found in index, `entry` is None
+    /// if key is missing. Return [InvalidCAS][Error::InvalidCAS] along
+    /// with the expected CAS, if check fails.
+    pub(crate) fn check<K>(&self, entry: Option<&Entry<K, V>>, cas: u64) -> Result<()>
+    where
+        K: Clone + Ord,
+        V: Clone + Diff,
+    {
+        match (self, entry) {
+            (CasPolicy::CreateIfAbsent, None) => Ok(()),
+            (_, None) if cas == 0 => Ok(()),
+            (_, None) => Err(Error::InvalidCAS(0)),
+            (CasPolicy::CreateIfAbsent, Some(e)) if e.is_deleted() => Ok(()),
+            (CasPolicy::Strict, Some(e)) if e.is_deleted() => {
+                let seqno = e.to_seqno();
+                if cas == 0 || cas == seqno {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidCAS(seqno))
+                }
+            }
+            (CasPolicy::ValueHash(_), Some(e)) if e.is_deleted() => match cas {
+                0 => Ok(()),
+                _ => Err(Error::InvalidCAS(0)),
+            },
+            (CasPolicy::ValueHash(hash), Some(e)) => {
+                let h = e.to_native_value().map(|v| hash(&v)).unwrap_or(0);
+                if cas == h {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidCAS(h))
+                }
+            }
+            (_, Some(e)) if cas == e.to_seqno() => Ok(()),
+            (_, Some(e)) => Err(Error::InvalidCAS(e.to_seqno())),
+        }
+    }
+}
+
+/// Policy for values exceeding the maximum value size configured via
+/// [SizeLimit]. Keys exceeding the maximum key size are always rejected.
+/// Default policy is `Reject`.
+pub enum SizePolicy<V> {
+    /// Fail the write operation with [InvalidInput][Error::InvalidInput].
+    Reject,
+    /// Truncate the value using the supplied function, called with the
+    /// value and the maximum value size. Note that older versions of an
+    /// entry cannot be truncated without loosing their deltas, hence
+    /// disk index builds shall reject such entries.
+    Truncate(fn(V, usize) -> V),
+    /// Accept the value as is. Disk index builds expect value-log to be
+    /// configured, so that index blocks only hold a reference to the
+    /// value.
+    SpillToVlog,
+}
+
+impl<V> Clone for SizePolicy<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for SizePolicy<V> {}
+
+impl<V> Default for SizePolicy<V> {
+    fn default() -> Self {
+        SizePolicy::Reject
+    }
+}
+
+impl<V> fmt::Debug for SizePolicy<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match self {
+            SizePolicy::Reject => write!(f, "SizePolicy::Reject"),
+            SizePolicy::Truncate(_) => write!(f, "SizePolicy::Truncate"),
+            SizePolicy::SpillToVlog => write!(f, "SizePolicy::SpillToVlog"),
+        }
+    }
+}
+
+/// Maximum size of keys and values, applicable to write operations.
+/// For memory indexes, size is measured as inline size plus heap
+/// [footprint][Footprint], for disk index builds size is measured as
+/// serialized bytes. Default is no limit.
+pub struct SizeLimit<V> {
+    pub max_key: usize,
+    pub max_value: usize,
+    pub policy: SizePolicy<V>,
+}
+
+impl<V> Clone for SizeLimit<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for SizeLimit<V> {}
+
+impl<V> Default for SizeLimit<V> {
+    fn default() -> Self {
+        SizeLimit {
+            max_key: std::usize::MAX,
+            max_value: std::usize::MAX,
+            policy: Default::default(),
+        }
+    }
+}
+
+impl<V> fmt::Debug for SizeLimit<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(
+            f,
+            "SizeLimit {{ max_key: {}, max_value: {}, policy: {:?} }}",
+            self.max_key, self.max_value, self.policy
+        )
+    }
+}
+
+impl<V> SizeLimit<V> {
+    pub fn new(max_key: usize, max_value: usize, policy: SizePolicy<V>) -> SizeLimit<V> {
+        SizeLimit {
+            max_key,
+            max_value,
+            policy,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_key == std::usize::MAX && self.max_value == std::usize::MAX
+    }
+
+    pub(crate) fn check_key(&self, key_size: usize) -> Result<()> {
+        if key_size > self.max_key {
+            let msg = format!("key of {} bytes exceeds {}", key_size, self.max_key);
+            err_at!(InvalidInput, msg: msg)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Apply the policy on `value` of `value_size`, return the value
+    /// to be written.
+    pub(crate) fn apply_value(&self, value_size: usize, value: V) -> Result<V> {
+        if value_size <= self.max_value {
+            return Ok(value);
+        }
+        match self.policy {
+            SizePolicy::Reject => {
+                let msg = format!("value of {} bytes exceeds {}", value_size, self.max_value);
+                err_at!(InvalidInput, msg: msg)
+            }
+            SizePolicy::Truncate(truncate) => Ok(truncate(value, self.max_value)),
+            SizePolicy::SpillToVlog => Ok(value),
+        }
+    }
+
+    // limits for memory index, applied on `key` and `value` before
+    // they are written into the index.
+    pub(crate) fn apply_mem<K>(&self, key: &K, value: V) -> Result<V>
+    where
+        K: Footprint,
+        V: Footprint,
+    {
+        if self.is_unlimited() {
+            return Ok(value);
+        }
+        self.check_key(convert_at!(util::key_footprint(key)?)?)?;
+        let value_size = convert_at!(util::key_footprint(&value)?)?;
+        self.apply_value(value_size, value)
+    }
+}
+
 /// Trait for diffable values.
 ///
 /// Version control is a unique feature built into [rdms]. And this is possible
@@ -133,6 +355,12 @@ pub trait Diff: Sized + From<<Self as Diff>::D> {
 ///
 /// **Note: This can be an approximate measure.**
 ///
+/// For key-types and value-types, footprint shall count only the heap
+/// memory owned by the value, its inline size is accounted by the
+/// containing structure. Implementations are available for `String`,
+/// `Vec<T>`, `Option<T>`, `Rc<T>`, `Arc<T>` and tuples, user defined
+/// structs can use [impl_footprint][crate::impl_footprint] macro.
+///
 pub trait Footprint {
     /// Return the approximate size of the underlying type, when
     /// stored in memory or serialized on disk.
@@ -141,6 +369,173 @@ pub trait Footprint {
     fn footprint(&self) -> Result<isize>;
 }
 
+/// Trait to be implemented by key-types that are composed of one or
+/// more components, like (tenant, doc-id), to support prefix scans via
+/// [range_prefix][Reader::range_prefix].
+///
+/// Keys sharing a prefix are expected to be contiguous in sort order,
+/// which is true for lexicographically ordered keys.
+pub trait Prefixed: Sized {
+    type P: Ord;
+
+    /// Return the prefix made of first `len` components of this key.
+    /// If key is shorter than `len`, return the whole key as prefix.
+    fn prefix(&self, len: usize) -> Self::P;
+
+    /// Return the number of components in `prefix`.
+    fn prefix_len(prefix: &Self::P) -> usize;
+
+    /// Return the smallest key, in sort order, starting with `prefix`.
+    fn prefix_start(prefix: &Self::P) -> Self;
+}
+
+/// Trait to specify a custom sort order for key type `K`, to be used
+/// with [Ordered][crate::types::Ordered] keys.
+///
+/// Indexes like [Llrb], [Mvcc] and [Robt] sort their entries using
+/// `K: Ord`. Wrapping the key as `Ordered<K, S>` shall sort entries
+/// using `S` instead, say for case-insensitive or collation-aware
+/// ordering. Disk indexes persist the specification's [NAME][Self::NAME],
+/// so that an index built using one ordering is not opened using another.
+pub trait OrdSpec<K> {
+    /// Unique name for this ordering.
+    const NAME: &'static str;
+
+    /// Compare two keys as per this ordering.
+    fn cmp(a: &K, b: &K) -> cmp::Ordering;
+
+    /// Hash key, keys that compare equal shall hash to the same value.
+    fn hash<H: Hasher>(key: &K, state: &mut H);
+}
+
+/// Trait to resolve two entries for the same key, coming from different
+/// index levels or replicas, into a single entry.
+///
+/// Applicable to lsm iteration via [y_iter_merge][crate::lsm::y_iter_merge],
+/// whose output can be fed to compaction scans like
+/// [CompactScan][scans::CompactScan]. [LastWriteWins] is the behavior of
+/// plain [y_iter][crate::lsm::y_iter]. Disk indexes apply the policy
+/// while commiting and compacting, refer to [Index::set_merge_policy].
+pub trait MergePolicy<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Merge `newer` entry, from the newer index, with `older` entry,
+    /// from the older index.
+    fn merge(&mut self, newer: Entry<K, V>, older: Entry<K, V>) -> Result<Entry<K, V>>;
+}
+
+/// Merge policy that can be shared across index instances and threads,
+/// refer to [Index::set_merge_policy].
+pub type SharedMergePolicy<K, V> = Arc<Mutex<dyn MergePolicy<K, V> + Send>>;
+
+impl<K, V, P> MergePolicy<K, V> for Arc<Mutex<P>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    P: ?Sized + MergePolicy<K, V>,
+{
+    fn merge(&mut self, newer: Entry<K, V>, older: Entry<K, V>) -> Result<Entry<K, V>> {
+        match self.lock() {
+            Ok(mut policy) => policy.merge(newer, older),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+/// Merge policy, pick the entry with the larger seqno. If both entries
+/// have the same seqno pick the entry from newer index.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LastWriteWins;
+
+impl<K, V> MergePolicy<K, V> for LastWriteWins
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn merge(&mut self, newer: Entry<K, V>, older: Entry<K, V>) -> Result<Entry<K, V>> {
+        if older.to_seqno() > newer.to_seqno() {
+            Ok(older)
+        } else {
+            Ok(newer)
+        }
+    }
+}
+
+/// Merge policy, pick the entry with the larger value as per the
+/// supplied compare function. Deleted entries are smaller than any
+/// value, ties are resolved using [LastWriteWins].
+#[derive(Clone, Copy)]
+pub struct MaxBy<F>(pub F);
+
+impl<K, V, F> MergePolicy<K, V> for MaxBy<F>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    F: FnMut(&V, &V) -> cmp::Ordering,
+{
+    fn merge(&mut self, newer: Entry<K, V>, older: Entry<K, V>) -> Result<Entry<K, V>> {
+        let ord = match (newer.to_native_value(), older.to_native_value()) {
+            (Some(x), Some(y)) => (self.0)(&x, &y),
+            (Some(_), None) => cmp::Ordering::Greater,
+            (None, Some(_)) => cmp::Ordering::Less,
+            (None, None) => cmp::Ordering::Equal,
+        };
+        match ord {
+            cmp::Ordering::Greater => Ok(newer),
+            cmp::Ordering::Less => Ok(older),
+            cmp::Ordering::Equal => LastWriteWins.merge(newer, older),
+        }
+    }
+}
+
+/// Merge policy using an application defined function, say to merge
+/// CRDT values. Function is called as `f(newer, older)`.
+#[derive(Clone, Copy)]
+pub struct MergeFn<F>(pub F);
+
+impl<K, V, F> MergePolicy<K, V> for MergeFn<F>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    F: FnMut(Entry<K, V>, Entry<K, V>) -> Result<Entry<K, V>>,
+{
+    fn merge(&mut self, newer: Entry<K, V>, older: Entry<K, V>) -> Result<Entry<K, V>> {
+        (self.0)(newer, older)
+    }
+}
+
+/// Decision returned by [CompactionFilter] for an entry.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision<V> {
+    /// Retain the entry as is.
+    Keep,
+    /// Drop the entry along with all its versions.
+    Drop,
+    /// Replace the entry's latest value, retaining its seqno. Older
+    /// versions of the entry are dropped.
+    Modify(V),
+}
+
+/// Trait to drop or transform entries while compacting a disk index,
+/// so that cleanups like TTL expiry or erasing user data can piggyback
+/// on compaction, instead of a separate rewrite pass.
+///
+/// Filter is set per index instance, like [Robt::set_compaction_filter],
+/// hence each level of a multi-level index can have its own filter.
+/// Note that dropping an entry does not hide its older versions from
+/// lower levels, drop entries only from the last level or use
+/// [Decision::Modify] elsewhere.
+pub trait CompactionFilter<K, V>: Send + Sync
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Called for every entry that survives the compaction cutoff.
+    fn filter(&self, entry: &Entry<K, V>) -> Result<Decision<V>>;
+}
+
 /// Trait define methods to integrate index with [Wal] (Write-Ahead-Log).
 ///
 /// All the methods defined by this trait will be dispatched when
@@ -158,6 +553,12 @@ where
 
     /// Replay delete operation from wal-file onto index.
     fn delete_index(&mut self, key: K, index: u64) -> Result<()>;
+
+    /// Configure the compare-and-set policy, used while replaying
+    /// set-cas operations, to be same as the one used by [Wal]. Called
+    /// only when the policy is explicitly set on [Wal], refer to
+    /// [Wal::set_cas_policy].
+    fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<()>;
 }
 
 /// Trait define methods to integrate index with Wal (Write-Ahead-Log).
@@ -323,6 +724,30 @@ where
         C: CommitIterator<K, V>,
         F: Fn(Vec<u8>) -> Vec<u8>;
 
+    /// Same as [commit][Index::commit], but commit only those entries
+    /// whose seqno fall within `window`, irrespective of the window that
+    /// `scanner` was created with. Return the seqno window that is
+    /// covered by this commit, which can be narrower than `window`.
+    ///
+    /// Orchestration layer can retry a failed flush with the same
+    /// window, disk indexes shall skip seqnos that are already
+    /// persisted, so that mutations are flushed exactly once.
+    fn commit_within<C, F>(
+        &mut self,
+        scanner: CommitIter<K, V, C>,
+        window: (Bound<u64>, Bound<u64>),
+        mf: F,
+    ) -> Result<(Bound<u64>, Bound<u64>)>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        let scanner = scanner.clip_within(window);
+        let covered = scanner.to_within();
+        self.commit(scanner, mf)?;
+        Ok(covered)
+    }
+
     /// Compact index to reduce index-footprint. Though it takes mutable
     /// reference, there can be concurrent commit() call. It is upto the
     /// implementing type to synchronize the concurrent commit() and
@@ -332,6 +757,42 @@ where
     /// Return number of items in index.
     fn compact(&mut self, cutoff: Cutoff) -> Result<usize>;
 
+    /// Return the number of bytes that can be reclaimed by compacting
+    /// this index. If `cutoff` is false, count only the garbage that can
+    /// be reclaimed without a cutoff, like stale value-log. Indexes that
+    /// don't track garbage shall return ZERO.
+    fn to_reclaimable(&self, _cutoff: bool) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Return the cumulative number of bytes written to disk by commits
+    /// and compactions, since this index was created or opened. Used for
+    /// computing write amplification. Memory only indexes shall return
+    /// ZERO.
+    fn to_bytes_written(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Set the policy to resolve entries for the same key, while
+    /// commiting into, or compacting, this index. Merged entries don't
+    /// retain their older versions. If not set, entry with the larger
+    /// seqno is retained along with its versions. Policy is not
+    /// persisted, hence shall be set again after re-opening the index.
+    /// Indexes that don't merge entries shall return
+    /// [NotSupported][Error::NotSupported].
+    fn set_merge_policy(&mut self, _policy: Option<SharedMergePolicy<K, V>>) -> Result<()> {
+        err_at!(NotSupported, msg: format!("merge policy"))
+    }
+
+    /// Return a counter, shared with this index, that is incremented
+    /// every time the index swaps its levels, say by a commit or a
+    /// compaction from its background threads. Reader handles created
+    /// before a swap shall be refreshed by its holder. Indexes that
+    /// don't swap levels in background shall return None.
+    fn to_generation(&self) -> Result<Option<Arc<AtomicU64>>> {
+        Ok(None)
+    }
+
     /// End of index life-cycle. Persisted data (in disk) shall not be
     /// cleared. Refer [purge][Index::purge] for that.
     fn close(self) -> Result<()>;
@@ -389,6 +850,46 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash;
 
+    /// Same as [get][Reader::get], but give up with `Error::TimedOut`
+    /// if lookup could not complete within `timeout`. Disk indexes check
+    /// the deadline between block reads, memory indexes never block
+    /// on IO and fall back to plain `get()`.
+    fn get_deadline<Q>(&mut self, key: &Q, _timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get(key)
+    }
+
+    /// Return whether `key` is present in this index, that is, whether
+    /// [get][Reader::get] shall succeed for `key`. Implementations can
+    /// answer this without materializing the entry.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        match self.get(key) {
+            Ok(_) => Ok(true),
+            Err(Error::KeyNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Return the seqno of the latest range tombstone, in this index,
+    /// whose key-range includes `key`. Versions of `key` older than
+    /// this seqno, that are held in older indexes, shall be treated as
+    /// deleted. Default implementation is for indexes that don't
+    /// support range tombstones.
+    fn to_range_deleted<Q>(&mut self, _key: &Q) -> Result<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        Ok(None)
+    }
+
     /// Iterate over all entries in this index. Returned entry may not
     /// have all its previous versions, if it is costly to fetch from disk.
     fn iter(&mut self) -> Result<IndexIter<K, V>>;
@@ -409,6 +910,36 @@ where
         R: 'a + Clone + RangeBounds<Q>,
         Q: 'a + Ord + ?Sized;
 
+    /// Return the number of entries from lower bound to upper bound,
+    /// entries marked as deleted are not counted. Default implementation
+    /// return NotSupported, indexes can provide optimized implementations.
+    fn range_count<'a, R, Q>(&'a mut self, _range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        err_at!(NotSupported, msg: format!("range_count"))
+    }
+
+    /// Iterate over all entries whose key starts with `prefix`, from
+    /// low to high. Start bound is computed using [Prefixed] trait and
+    /// iteration stops at the first key that does not share `prefix`.
+    fn range_prefix<'a>(&'a mut self, prefix: K::P) -> Result<IndexIter<K, V>>
+    where
+        K: 'a + Prefixed,
+        V: 'a,
+        K::P: 'a,
+    {
+        let n = K::prefix_len(&prefix);
+        let start = Bound::Included(K::prefix_start(&prefix));
+        let iter = self.range((start, Bound::Unbounded))?;
+        Ok(Box::new(iter.take_while(move |item| match item {
+            Ok(entry) => entry.as_key().prefix(n) == prefix,
+            Err(_) => true,
+        })))
+    }
+
     /// Get `key` from index. Returned entry shall have all its
     /// previous versions, can be a costly call.
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
@@ -470,6 +1001,17 @@ where
     where
         K: Borrow<Q>,
         Q: ToOwned<Owned = K> + Ord + ?Sized;
+
+    /// Delete all keys from lower bound to upper bound, return the number
+    /// of entries deleted. Entries already marked as deleted are not
+    /// counted. Default implementation return NotSupported, indexes can
+    /// provide optimized implementations.
+    fn delete_range<R>(&mut self, _range: R) -> Result<usize>
+    where
+        R: Clone + RangeBounds<K>,
+    {
+        err_at!(NotSupported, msg: format!("delete_range"))
+    }
 }
 
 /// Trait to serialize key and value types.
@@ -504,9 +1046,30 @@ where
     /// This method is typically implemented by memory-only indexes. Also,
     /// returned entry may not have all its previous versions, if it is
     /// costly to fetch from disk.
+    ///
+    /// Read-lock, if any, is held until the returned iterator is dropped.
+    /// Callers are expected to bound each piece, both by entry-count and
+    /// by a byte-budget, and resume from the last key, like
+    /// [SkipScan][scans::SkipScan] does.
     fn pw_scan<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
     where
         G: Clone + RangeBounds<u64>;
+
+    /// Reverse counterpart of [pw_scan][PiecewiseScan::pw_scan], return an
+    /// iterator over entries that meet following properties
+    /// * Only entries less than from bound, in descending order.
+    /// * Only entries whose modified seqno is within seqno-range.
+    ///
+    /// Same as `pw_scan`, iterator can return ScanEntry::Retry, in which
+    /// case callers are expected to resume from the returned key, like
+    /// [SkipScan::new_rev][scans::SkipScan::new_rev] does. Default
+    /// implementation returns NotImplemented.
+    fn pw_scan_rev<G>(&mut self, _from: Bound<K>, _within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        err_at!(NotImplemented, msg: format!("pw_scan_rev"))
+    }
 }
 
 /// Trait to serialize an implementing type to JSON encoded string.
@@ -584,6 +1147,19 @@ where
         (self.start.clone(), self.end.clone())
     }
 
+    /// Narrow down the `within` argument of this iterator to its
+    /// intersection with `window`.
+    pub fn clip_within<G>(mut self, window: G) -> CommitIter<K, V, C>
+    where
+        G: RangeBounds<u64>,
+    {
+        let window = util::to_start_end(window);
+        let (start, end) = util::intersect_seqnos(self.to_within(), window);
+        self.start = start;
+        self.end = end;
+        self
+    }
+
     /// Calls underlying scanner's [scan][CommitIterator::scan] method
     /// along with `within` to generate the actual commitable iterator.
     pub fn scan(&mut self) -> Result<IndexIter<K, V>> {
@@ -626,6 +1202,253 @@ where
     }
 }
 
+/// Opaque continuation token, returned by [paginate], to fetch the
+/// next page. Tokens can be handed over to clients as bytes and
+/// converted back using `From<Vec<u8>>`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Token(Vec<u8>);
+
+// Token binary format:
+//
+// *------------------------------------*
+// |  flags (1-byte)  |  seqno (8-byte)  |
+// *------------------------------------*
+// |     last-key, if TOKEN_KEY flag     |
+// *------------------------------------*
+const TOKEN_KEY: u8 = 0x1;
+const TOKEN_SEQNO: u8 = 0x2;
+
+impl Token {
+    /// Create a token to start pagination over a snapshot at `seqno`.
+    /// Subsequent pages shall return entries as of this snapshot,
+    /// refer [paginate] for details.
+    pub fn new_snapshot(seqno: u64) -> Token {
+        let mut buf = vec![TOKEN_SEQNO];
+        buf.extend_from_slice(&seqno.to_be_bytes());
+        Token(buf)
+    }
+
+    fn new<K>(key: &K, seqno: Option<u64>) -> Result<Token>
+    where
+        K: Serialize,
+    {
+        let mut buf = match seqno {
+            Some(seqno) => {
+                let mut buf = vec![TOKEN_KEY | TOKEN_SEQNO];
+                buf.extend_from_slice(&seqno.to_be_bytes());
+                buf
+            }
+            None => {
+                let mut buf = vec![TOKEN_KEY];
+                buf.extend_from_slice(&0_u64.to_be_bytes());
+                buf
+            }
+        };
+        key.encode(&mut buf)?;
+        Ok(Token(buf))
+    }
+
+    /// Return the token as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Return the snapshot seqno pinned by this token, if any.
+    pub fn to_seqno(&self) -> Result<Option<u64>> {
+        match self.to_flags()? & TOKEN_SEQNO {
+            0 => Ok(None),
+            _ => Ok(Some(u64::from_be_bytes(array_at!(self.0[1..9])?))),
+        }
+    }
+
+    fn to_flags(&self) -> Result<u8> {
+        match self.0.len() {
+            n if n < 9 => err_at!(DecodeFail, msg: format!("token {}/9", n)),
+            _ => Ok(self.0[0]),
+        }
+    }
+
+    fn to_key<K>(&self) -> Result<Option<K>>
+    where
+        K: Default + Serialize,
+    {
+        match self.to_flags()? & TOKEN_KEY {
+            0 => Ok(None),
+            _ => {
+                let mut key: K = Default::default();
+                key.decode(&self.0[9..])?;
+                Ok(Some(key))
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Token {
+    fn from(bytes: Vec<u8>) -> Token {
+        Token(bytes)
+    }
+}
+
+impl From<Token> for Vec<u8> {
+    fn from(token: Token) -> Vec<u8> {
+        token.0
+    }
+}
+
+/// Fetch a page of atmost `limit` entries from `reader`, within `range`,
+/// starting after the last key in `token`. Return the page along with a
+/// continuation token, which is None after the last page.
+///
+/// If `token` pins a snapshot seqno, refer [Token::new_snapshot], entries
+/// are returned as of that snapshot. Entries without a version in that
+/// snapshot, either created after the snapshot or whose older versions
+/// are already compacted, are skipped. Without a snapshot seqno, latest
+/// version of each entry is returned.
+pub fn paginate<K, V, R, G>(
+    reader: &mut R,
+    range: G,
+    limit: usize,
+    token: Option<Token>,
+) -> Result<(Vec<Entry<K, V>>, Option<Token>)>
+where
+    K: Clone + Ord + Default + Serialize,
+    V: Clone + Diff,
+    R: Reader<K, V>,
+    G: RangeBounds<K>,
+{
+    if limit == 0 {
+        err_at!(InvalidInput, msg: format!("paginate limit 0"))?;
+    }
+
+    let (last_key, seqno) = match &token {
+        Some(token) => (token.to_key::<K>()?, token.to_seqno()?),
+        None => (None, None),
+    };
+    let (start, end) = util::to_start_end(range);
+    let start = match last_key {
+        Some(key) => Bound::Excluded(key),
+        None => start,
+    };
+
+    let mut entries = vec![];
+    let mut next_key = None;
+    let iter = match seqno {
+        Some(_) => reader.range_with_versions((start, end))?,
+        None => reader.range((start, end))?,
+    };
+    for entry in iter {
+        let entry = entry?;
+        let entry = match seqno {
+            Some(seqno) => {
+                let within = (Bound::Unbounded, Bound::Included(seqno));
+                match entry.filter_within(within.0, within.1) {
+                    Some(entry) => entry,
+                    None => continue,
+                }
+            }
+            None => entry,
+        };
+        if entries.len() == limit {
+            // there is atleast one more entry, for the next page.
+            next_key = entries.last().map(|e: &Entry<K, V>| e.to_key());
+            break;
+        }
+        entries.push(entry);
+    }
+
+    match next_key {
+        Some(key) => Ok((entries, Some(Token::new(&key, seqno)?))),
+        None => Ok((entries, None)),
+    }
+}
+
+/// Use a std [BTreeMap] of entries, indexed by their key, where a
+/// [Reader] is expected. Meant for unit tests and small tools, entries
+/// are returned as is, with all their versions, hence the `*_versions`
+/// variants behave the same as their counterparts. Inherent methods of
+/// BTreeMap shadow `get`, `iter` and `range`, call them via the trait,
+/// like `Reader::get(&mut map, &key)`.
+impl<K, V> Reader<K, V> for BTreeMap<K, Entry<K, V>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        match BTreeMap::get(self, key) {
+            Some(entry) => Ok(entry.clone()),
+            None => Err(Error::KeyNotFound),
+        }
+    }
+
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        Ok(Box::new(self.values().cloned().map(Ok)))
+    }
+
+    fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let iter = BTreeMap::range(self, range).map(|(_, e)| Ok(e.clone()));
+        Ok(Box::new(iter))
+    }
+
+    fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let iter = BTreeMap::range(self, range).rev();
+        Ok(Box::new(iter.map(|(_, e)| Ok(e.clone()))))
+    }
+
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let iter = BTreeMap::range(self, range);
+        Ok(iter.filter(|(_, e)| !e.is_deleted()).count())
+    }
+
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        Reader::get(self, key)
+    }
+
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        Reader::iter(self)
+    }
+
+    fn range_with_versions<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        Reader::range(self, range)
+    }
+
+    fn reverse_with_versions<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        Reader::reverse(self, range)
+    }
+}
+
 #[cfg(test)]
 #[path = "core_test.rs"]
 mod core_test;
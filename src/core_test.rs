@@ -46,6 +46,30 @@ fn test_value_new_upsert() {
     assert_eq!(value.is_deleted(), false);
 }
 
+#[test]
+fn test_value_new_upsert_value() {
+    let value: Value<i64> = Value::new_upsert_value(100, 200);
+    match &value {
+        Value::N { value, seqno } => assert_eq!((*value, *seqno), (100, 200)),
+        _ => unreachable!(),
+    }
+    assert_eq!(value.footprint().unwrap(), 0);
+    assert_eq!(value.mvcc_clone(false).to_native_value(), Some(100));
+    assert_eq!(value.to_native_value(), Some(100));
+    assert_eq!(value.to_seqno(), 200);
+    assert_eq!(value.is_reference(), false);
+
+    let value: Value<[u8; 20]> = Value::new_upsert_value([1; 20], 300);
+    match &value {
+        Value::U { value, seqno, .. } => {
+            assert_eq!(value.to_native_value(), Some([1; 20]));
+            assert_eq!(*seqno, 300);
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(value.footprint().unwrap(), 20);
+}
+
 #[test]
 fn test_value_new_delete() {
     let value: Value<i32> = Value::new_delete(300);
@@ -102,6 +126,40 @@ fn test_entry_new() {
     assert!(entry.is_none());
 }
 
+#[test]
+fn test_entry_to_version() {
+    let value = Value::new_upsert(Box::new(vlog::Value::new_native(10)), 1000);
+    let mut entry = Entry::new(100, value);
+    let value = Value::new_upsert(Box::new(vlog::Value::new_native(20)), 1001);
+    entry
+        .prepend_version(Entry::new(100, value), true /*lsm*/)
+        .unwrap();
+    entry.delete(1002).unwrap();
+    let value = Value::new_upsert(Box::new(vlog::Value::new_native(30)), 1004);
+    entry
+        .prepend_version(Entry::new(100, value), true /*lsm*/)
+        .unwrap();
+
+    let vers: Vec<(u64, Option<i32>)> = entry.versions_iter().collect();
+    assert_eq!(
+        vers,
+        vec![
+            (1004, Some(30)),
+            (1002, None),
+            (1001, Some(20)),
+            (1000, Some(10))
+        ]
+    );
+
+    assert_eq!(entry.to_version(999), None);
+    assert_eq!(entry.to_version(1000), Some(10));
+    assert_eq!(entry.to_version(1001), Some(20));
+    assert_eq!(entry.to_version(1002), None);
+    assert_eq!(entry.to_version(1003), None);
+    assert_eq!(entry.to_version(1004), Some(30));
+    assert_eq!(entry.to_version(u64::MAX), Some(30));
+}
+
 #[test]
 fn test_entry_new_lsm() {
     // testcase1 new
@@ -461,6 +519,110 @@ fn test_entry_filter_within() {
     assert!(entry.filter_within(start, end).is_none());
 }
 
+#[test]
+fn test_paginate() {
+    use crate::llrb::Llrb;
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-paginate");
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+
+    // pin the snapshot, mutations after this shall not be visible.
+    let seqno = index.to_seqno().unwrap();
+    let mut token = Some(Token::new_snapshot(seqno));
+    for key in 0..100 {
+        index.set(key, key * 100).unwrap();
+    }
+    index.set(1000, 1000).unwrap();
+
+    let mut keys = vec![];
+    loop {
+        let (entries, next) = paginate(index.as_mut(), 10..90, 7, token).unwrap();
+        assert!(entries.len() <= 7);
+        for entry in entries.into_iter() {
+            assert_eq!(entry.to_native_value(), Some(entry.to_key() * 10));
+            keys.push(entry.to_key());
+        }
+        token = match next {
+            Some(next) => Some(Token::from(Vec::<u8>::from(next))),
+            None => break,
+        };
+        assert_eq!(token.as_ref().unwrap().to_seqno().unwrap(), Some(seqno));
+    }
+    assert_eq!(keys, (10..90).collect::<Vec<i64>>());
+
+    // latest versions, without a snapshot.
+    let (entries, next) = paginate(index.as_mut(), .., 200, None).unwrap();
+    assert_eq!(entries.len(), 101);
+    assert_eq!(entries[5].to_native_value(), Some(500));
+    assert!(next.is_none());
+
+    assert!(paginate(index.as_mut(), .., 0, None).is_err());
+    assert!(Token::from(vec![1, 2]).to_seqno().is_err());
+}
+
+#[test]
+fn test_btree_reader() {
+    use crate::llrb::Llrb;
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-btree-reader");
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+    for key in (0..100).step_by(3) {
+        index.delete(&key).unwrap();
+    }
+    let to_version = |e: Entry<i64, i64>| {
+        let n = e.as_deltas().len();
+        (e.to_key(), e.to_native_value(), e.to_seqno(), n)
+    };
+
+    let mut map: BTreeMap<i64, Entry<i64, i64>> = index
+        .iter_with_versions()
+        .unwrap()
+        .map(|e| e.unwrap())
+        .map(|e| (e.to_key(), e))
+        .collect();
+
+    let entry = index.get_with_versions(&10).unwrap();
+    let map_entry = Reader::get(&mut map, &10).unwrap();
+    assert_eq!(to_version(map_entry), to_version(entry));
+    assert!(Reader::get(&mut map, &1000).is_err());
+    assert_eq!(map.contains(&10).unwrap(), true);
+    assert_eq!(map.contains(&1000).unwrap(), false);
+    assert_eq!(map.range_count(10..20).unwrap(), 7);
+
+    let entries: Vec<(i64, Option<i64>, u64, usize)> = index
+        .iter_with_versions()
+        .unwrap()
+        .map(|e| to_version(e.unwrap()))
+        .collect();
+    let items: Vec<(i64, Option<i64>, u64, usize)> = Reader::iter(&mut map)
+        .unwrap()
+        .map(|e| to_version(e.unwrap()))
+        .collect();
+    assert_eq!(items, entries);
+
+    let items: Vec<i64> = Reader::range(&mut map, 10..20)
+        .unwrap()
+        .map(|e| e.unwrap().to_key())
+        .collect();
+    assert_eq!(items, (10..20).collect::<Vec<i64>>());
+    let items: Vec<i64> = map
+        .reverse(10..20)
+        .unwrap()
+        .map(|e| e.unwrap().to_key())
+        .collect();
+    assert_eq!(items, (10..20).rev().collect::<Vec<i64>>());
+
+    // interop with rdms helpers.
+    let (entries, next) = paginate(&mut map, 50.., 10, None).unwrap();
+    assert_eq!(entries.len(), 10);
+    assert_eq!(entries[0].to_key(), 50);
+    assert!(next.is_some());
+}
+
 fn verify_version(
     e: &Entry<i32, i32>,
     key: i32,
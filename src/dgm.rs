@@ -18,19 +18,25 @@ use std::{
     marker, mem,
     ops::{Bound, DerefMut, RangeBounds},
     path, result,
-    sync::{mpsc, Arc, Mutex, MutexGuard},
+    sync::{
+        atomic::{AtomicU64, Ordering::SeqCst},
+        mpsc, Arc, Mutex, MutexGuard,
+    },
     thread, time,
 };
 
 use crate::{
-    core::{self, Cutoff, Validate, Writer},
+    core::{self, CasPolicy, Cutoff, SharedMergePolicy, Validate, WalWriter, Writer},
     core::{CommitIter, CommitIterator, Result, Serialize, WriteIndexFactory},
     core::{Diff, DiskIndexFactory, Entry, Footprint, Index, IndexIter, Reader},
     error::Error,
-    lsm, scans, thread as rt, util,
+    lsm,
+    rdms::{Component, Consistency},
+    scans, thread as rt, util,
 };
 
 const N_COMMITS: usize = 2;
+const DAY_SECS: u64 = 24 * 60 * 60;
 
 /// Configuration type for Dgm indexes.
 #[derive(Clone, Debug, PartialEq)]
@@ -41,6 +47,8 @@ pub struct Config {
     disk_ratio: f64,
     commit_interval: Option<time::Duration>,
     compact_interval: Option<time::Duration>,
+    compact_window: Option<(time::Duration, time::Duration)>,
+    compact_io_budget: Option<usize>,
 }
 
 impl Default for Config {
@@ -52,6 +60,8 @@ impl Default for Config {
             disk_ratio: Self::DISK_RATIO,
             commit_interval: Some(Self::COMMIT_INTERVAL),
             compact_interval: Some(Self::COMPACT_INTERVAL),
+            compact_window: None,
+            compact_io_budget: None,
         }
     }
 }
@@ -130,6 +140,38 @@ impl Config {
         self.compact_interval = Some(interval);
         Ok(self)
     }
+
+    /// Set maintenance window for auto compaction, `start` and `end` are
+    /// offsets from midnight, in UTC. Auto compaction thread shall skip
+    /// its periodic compaction outside this window. Window can wrap
+    /// around midnight, for example 22:00 to 04:00. Explicit calls to
+    /// [compact][Index::compact] are not constrained by this window.
+    pub fn set_compact_window(
+        &mut self,
+        start: time::Duration,
+        end: time::Duration,
+    ) -> Result<&mut Self> {
+        let day = time::Duration::from_secs(DAY_SECS);
+        if start >= day || end >= day || start == end {
+            let msg = format!("compact window {:?}..{:?}", start, end);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        self.compact_window = Some((start, end));
+        Ok(self)
+    }
+
+    /// Set IO budget, in bytes-per-second, for auto compaction. After
+    /// every periodic compaction, the thread shall pause long enough
+    /// for the bytes written by the compaction to fit within the budget.
+    /// Explicit calls to [compact][Index::compact] are not constrained
+    /// by this budget.
+    pub fn set_compact_io_budget(&mut self, bytes_per_sec: usize) -> Result<&mut Self> {
+        if bytes_per_sec == 0 {
+            err_at!(InvalidInput, msg: format!("compact io budget 0"))?;
+        }
+        self.compact_io_budget = Some(bytes_per_sec);
+        Ok(self)
+    }
 }
 
 impl From<Root> for Config {
@@ -141,6 +183,8 @@ impl From<Root> for Config {
             disk_ratio: root.disk_ratio,
             commit_interval: root.commit_interval,
             compact_interval: root.compact_interval,
+            compact_window: root.compact_window,
+            compact_io_budget: root.compact_io_budget,
         }
     }
 }
@@ -158,6 +202,8 @@ struct Root {
     disk_ratio: f64,
     commit_interval: Option<time::Duration>,  // in seconds.
     compact_interval: Option<time::Duration>, // in seconds.
+    compact_window: Option<(time::Duration, time::Duration)>, // in seconds.
+    compact_io_budget: Option<usize>,
 }
 
 impl From<Config> for Root {
@@ -174,6 +220,8 @@ impl From<Config> for Root {
             disk_ratio: config.disk_ratio,
             commit_interval: config.commit_interval,
             compact_interval: config.compact_interval,
+            compact_window: config.compact_window,
+            compact_io_budget: config.compact_io_budget,
         }
     }
 }
@@ -203,6 +251,17 @@ impl TryFrom<Root> for Vec<u8> {
                 Some(interval) => convert_at!(interval.as_secs())?,
                 None => -1,
             };
+            let (w_start, w_end): (i64, i64) = match root.compact_window {
+                Some((start, end)) => {
+                    let start = convert_at!(start.as_secs())?;
+                    (start, convert_at!(end.as_secs())?)
+                }
+                None => (-1, -1),
+            };
+            let io_budget: i64 = match root.compact_io_budget {
+                Some(io_budget) => convert_at!(io_budget)?,
+                None => -1,
+            };
 
             dict.insert("version".to_string(), Integer(version));
             dict.insert("levels".to_string(), Integer(levels));
@@ -213,6 +272,11 @@ impl TryFrom<Root> for Vec<u8> {
             dict.insert("disk_ratio".to_string(), Float(disk_ratio));
             dict.insert("commit_interval".to_string(), Integer(m_interval));
             dict.insert("compact_interval".to_string(), Integer(c_interval));
+            dict.insert(
+                "compact_window".to_string(),
+                Array(vec![Integer(w_start), Integer(w_end)]),
+            );
+            dict.insert("compact_io_budget".to_string(), Integer(io_budget));
 
             let (arg1, arg2) = match root.lsm_cutoff {
                 Some(cutoff) => match cutoff {
@@ -319,6 +383,30 @@ impl TryFrom<Vec<u8>> for Root {
             duration if duration < 0 => None,
             duration => Some(time::Duration::from_secs(convert_at!(duration)?)),
         };
+        // root files created by older versions won't have compaction
+        // window and io-budget.
+        root.compact_window = match dict.get("compact_window") {
+            Some(field) => match field.as_array().map(|a| a.as_slice()) {
+                Some([start, end]) => match (start.as_integer(), end.as_integer()) {
+                    (Some(start), Some(end)) if start < 0 || end < 0 => None,
+                    (Some(start), Some(end)) => {
+                        let start = time::Duration::from_secs(convert_at!(start)?);
+                        let end = time::Duration::from_secs(convert_at!(end)?);
+                        Some((start, end))
+                    }
+                    _ => err_at!(InvalidFile, msg: format!("compact_window:{}", field))?,
+                },
+                _ => err_at!(InvalidFile, msg: format!("compact_window:{}", field))?,
+            },
+            None => None,
+        };
+        root.compact_io_budget = match dict.get("compact_io_budget") {
+            Some(_) => match to_i64("compact_io_budget", dict)? {
+                io_budget if io_budget < 0 => None,
+                io_budget => Some(convert_at!(io_budget)?),
+            },
+            None => None,
+        };
         root.lsm_cutoff = match dict.get("lsm_cutoff") {
             Some(field) => match field.as_array() {
                 Some(array) => match array.as_slice() {
@@ -578,9 +666,14 @@ where
     n_high_compacts: usize,
     n_ccommits: usize,
     n_compacts: usize,
+    compact_bytes: usize,             // bytes written by the latest compaction.
+    bytes_written: usize,             // bytes written by commits and compactions.
     m0: Snapshot<K, V, M::I>,         // write index
     m1: Option<Snapshot<K, V, M::I>>, // flush index
     disks: Vec<Snapshot<K, V, D::I>>, // NLEVELS
+    cas_policy: CasPolicy<V>,
+    merge_policy: Option<SharedMergePolicy<K, V>>,
+    generation: Arc<AtomicU64>, // incremented on every level swap.
 
     writers: Vec<
         Arc<
@@ -617,6 +710,33 @@ where
         Ok(std::u64::MIN)
     }
 
+    // create a new set of snapshot-reader.
+    fn to_rs(&mut self) -> Result<Rs<K, V, <M::I as Index<K, V>>::R, <D::I as Index<K, V>>::R>> {
+        let r_m0 = self.m0.as_mut_m0()?.to_reader()?;
+
+        let r_m1 = match self.m1.as_mut() {
+            Some(m) => Some(m.as_mut_m1()?.to_reader()?),
+            None => None,
+        };
+
+        let mut r_disks = vec![];
+        for disk in self.disks.iter_mut() {
+            match disk.as_mut_disk()? {
+                Some(d) => r_disks.push(d.to_reader()?),
+                None => (),
+            }
+        }
+
+        Ok(Rs {
+            r_m0,
+            r_m1,
+            r_disks,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+        })
+    }
+
     fn to_disk_metadata(&self) -> Result<Vec<u8>> {
         for d in self.disks.iter() {
             match d.as_disk()? {
@@ -867,6 +987,43 @@ where
         }
     }
 
+    // pick the active level with highest reclaimable ratio, only the
+    // last level is compacted with cutoff. On a tie prefer the last level.
+    fn reclaimable_level(disks: &[Snapshot<K, V, D::I>]) -> Result<Option<usize>> {
+        let mut levels = Self::active_compact_levels(disks)?;
+        let last = match levels.pop() {
+            Some(last) => last,
+            None => return Ok(None),
+        };
+
+        let to_ratio = |level: usize| -> Result<f64> {
+            let cutoff = level == (Config::NLEVELS - 1);
+            let footprint = disks[level].footprint()?;
+            let reclaimable = match disks[level].as_disk()? {
+                Some(d) => d.to_reclaimable(cutoff)?,
+                None => 0,
+            };
+            match footprint {
+                n if n <= 0 => Ok(0.0),
+                n => Ok((reclaimable as f64) / (n as f64)),
+            }
+        };
+
+        let (mut level, mut ratio) = (last, to_ratio(last)?);
+        for l in levels.into_iter() {
+            let r = to_ratio(l)?;
+            if r > ratio {
+                level = l;
+                ratio = r;
+            }
+        }
+        debug!(
+            target: "dgm   ", "compact level {} reclaimable ratio {}", level, ratio
+        );
+
+        Ok(Some(level))
+    }
+
     fn compact_levels(
         &mut self, // return (levels, sources, target)
     ) -> Result<Option<(Vec<usize>, Vec<usize>, usize)>> {
@@ -877,7 +1034,7 @@ where
 
         match levels {
             None if self.n_ccommits < N_COMMITS => Ok(None),
-            None => match Self::active_compact_levels(&self.disks)?.pop() {
+            None => match Self::reclaimable_level(&self.disks)? {
                 None => Ok(None),
                 Some(d) => Ok(Some((vec![d], vec![], d))),
             },
@@ -952,6 +1109,7 @@ where
             }
         }
 
+        self.generation.fetch_add(1, SeqCst);
         Ok(())
     }
 
@@ -1253,9 +1411,14 @@ where
             n_high_compacts: Default::default(),
             n_ccommits: Default::default(),
             n_compacts: Default::default(),
+            compact_bytes: Default::default(),
+            bytes_written: Default::default(),
             m0,
             m1: None,
             disks,
+            cas_policy: Default::default(),
+            merge_policy: None,
+            generation: Default::default(),
 
             writers: Default::default(),
             readers: Default::default(),
@@ -1327,9 +1490,14 @@ where
                 n_high_compacts: Default::default(),
                 n_ccommits: Default::default(),
                 n_compacts: Default::default(),
+                compact_bytes: Default::default(),
+                bytes_written: Default::default(),
                 m0,
                 m1: None,
                 disks,
+                cas_policy: Default::default(),
+                merge_policy: None,
+                generation: Default::default(),
 
                 writers: Default::default(),
                 readers: Default::default(),
@@ -1438,6 +1606,26 @@ where
     M: WriteIndexFactory<K, V>,
     D: DiskIndexFactory<K, V>,
 {
+    /// Configure the compare-and-set policy for [set_cas][Writer::set_cas]
+    /// operations, refer to [CasPolicy] for details. Call this api, before
+    /// creating writer handles.
+    pub fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<&mut Self> {
+        {
+            let mut inner = self.as_inner()?;
+            inner.cleanup_writers()?;
+            match inner.writers.len() {
+                0 => inner.cas_policy = policy,
+                n => err_at!(APIMisuse, msg: format!("active-handles:{}", n))?,
+            }
+        }
+        Ok(self)
+    }
+
+    /// Return the compare-and-set policy configured for this index.
+    pub fn to_cas_policy(&self) -> Result<CasPolicy<V>> {
+        Ok(self.as_inner()?.cas_policy)
+    }
+
     fn as_inner(&self) -> Result<MutexGuard<InnerDgm<K, V, M, D>>> {
         match self.inner.lock() {
             Ok(value) => Ok(value),
@@ -1478,7 +1666,10 @@ where
 
             inn.move_to_commit(level)?;
 
-            let d = inn.disks[level].as_disk()?.unwrap().clone();
+            let mut d = inn.disks[level].as_disk()?.unwrap().clone();
+            if let Some(policy) = inn.merge_policy.clone() {
+                d.set_merge_policy(Some(policy))?;
+            }
 
             let r_m1 = match &mut inn.m1 {
                 Some(m1) => Some(m1.as_mut_m1()?.to_reader()?),
@@ -1488,6 +1679,8 @@ where
         };
         // println!("do_commit {}", level);
 
+        let _span = trace_span!("commit", level = level);
+        let written = d.to_bytes_written()?;
         let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
         match r_m1 {
             Some(r_m1) => {
@@ -1497,8 +1690,12 @@ where
             None => (),
         }
 
+        let written = d.to_bytes_written()? - written;
+        trace_event!("commit", seqno = d.to_seqno().unwrap_or(0), bytes = written);
+
         {
             let mut inn = to_inner_lock(inner)?;
+            inn.bytes_written += written;
             let disk = Snapshot::new_active(d);
             mem::replace(&mut inn.disks[level], disk);
             // don't drop _m1 before repopulate_readers().
@@ -1588,11 +1785,20 @@ where
             inn.disks[d_level].as_disk()?.unwrap().clone()
         };
 
+        let _span = trace_span!("compact", level = d_level);
+        let written = high_disk.to_bytes_written()?;
         let res = high_disk.compact(cutoff);
-
+        let written = high_disk.to_bytes_written()? - written;
+        trace_event!(
+            "compact",
+            count = *res.as_ref().unwrap_or(&0),
+            bytes = written
+        );
         {
             let mut inn = to_inner_lock(inner)?;
 
+            inn.compact_bytes = written;
+            inn.bytes_written += written;
             let disk = Snapshot::new_active(high_disk);
             mem::replace(&mut inn.disks[d_level], disk);
 
@@ -1621,14 +1827,17 @@ where
         s_levels: Vec<usize>,
         d_level: usize,
     ) -> Result<usize> {
-        let (s_disks, mut disk) = {
+        let (s_disks, mut disk, policy) = {
             let mut inn = to_inner_lock(inner)?;
 
             inn.move_to_compact(&levels)?;
 
             inn.root = inn.root.to_next();
-            let (s_disks, disk) = inn.do_compact_disks(&s_levels, d_level)?;
-            (s_disks, disk)
+            let (s_disks, mut disk) = inn.do_compact_disks(&s_levels, d_level)?;
+            if let Some(policy) = inn.merge_policy.clone() {
+                disk.set_merge_policy(Some(policy))?;
+            }
+            (s_disks, disk, inn.merge_policy.clone())
         };
         let metadata = match s_disks.first() {
             Some(s_disk) => s_disk.to_metadata()?,
@@ -1636,15 +1845,21 @@ where
         };
 
         let scanner = {
-            let scanner = CommitScanner::<K, V, D::I>::new(s_disks)?;
+            let scanner = CommitScanner::<K, V, D::I>::new(s_disks, policy)?;
             let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
             core::CommitIter::new(scanner, within)
         };
+        let _span = trace_span!("compact", level = d_level);
+        let written = disk.to_bytes_written()?;
         disk.commit(scanner, |_| metadata.clone())?;
+        let written = disk.to_bytes_written()? - written;
+        trace_event!("compact", count = 0, bytes = written);
 
         let compacted_disks = {
             let mut inn = to_inner_lock(inner)?;
 
+            inn.compact_bytes = written;
+            inn.bytes_written += written;
             let mut compacted_disks = vec![];
             for level in s_levels.clone().into_iter() {
                 let d = mem::replace(&mut inn.disks[level], Default::default());
@@ -1751,6 +1966,79 @@ where
     }
 }
 
+impl<K, V, M, D> Dgm<K, V, M, D>
+where
+    K: Clone + Ord + Hash + Serialize + Footprint + fmt::Debug,
+    V: Clone + Diff + Serialize + Footprint,
+    <V as Diff>::D: Serialize,
+    M: WriteIndexFactory<K, V>,
+    D: DiskIndexFactory<K, V>,
+{
+    // summarise each component and lookup every disk entry, refer to
+    // rdms::verify_consistency().
+    pub(crate) fn to_consistency(&mut self) -> Result<Consistency> {
+        let mut inner = self.as_inner()?;
+
+        let mem_seqno = inner.m0.as_m0()?.to_seqno()?;
+        let disk_seqno = inner.to_disk_seqno()?;
+
+        let mut components = vec![];
+        {
+            let mut r = inner.m0.as_mut_m0()?.to_reader()?;
+            let mut component = Component::new("m0".to_string());
+            for entry in r.iter()? {
+                component.add_entry(&entry?);
+            }
+            components.push(component);
+        }
+        if let Some(m1) = inner.m1.as_mut() {
+            let mut r = m1.as_mut_m1()?.to_reader()?;
+            let mut component = Component::new("m1".to_string());
+            for entry in r.iter()? {
+                component.add_entry(&entry?);
+            }
+            components.push(component);
+        }
+
+        let mut rs = inner.to_rs()?;
+        let name = inner.name.clone();
+        let mut n_missing = 0;
+        for (level, disk) in inner.disks.iter_mut().enumerate() {
+            let mut r = match disk.as_mut_disk()? {
+                Some(disk) => disk.to_reader()?,
+                None => continue,
+            };
+            let level_name: LevelName = (name.clone(), level).into();
+            let mut component = Component::new(level_name.to_string());
+            for entry in r.iter()? {
+                let entry = entry?;
+                component.add_entry(&entry);
+                match rs.get(entry.as_key()) {
+                    Ok(e) if e.to_seqno() >= entry.to_seqno() => (),
+                    Ok(_) | Err(Error::KeyNotFound) => {
+                        let (key, seqno) = (entry.to_key(), entry.to_seqno());
+                        error!(
+                            target: "dgm   ",
+                            "{:?}, missing {:?}/{} in {}", name, key, seqno, level_name
+                        );
+                        n_missing += 1;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            components.push(component);
+        }
+
+        Ok(Consistency {
+            mem_seqno,
+            disk_seqno,
+            wal_window: None,
+            components,
+            n_missing,
+        })
+    }
+}
+
 impl<K, V, M, D, A, B> Validate<Stats<A, B>> for Box<Dgm<K, V, M, D>>
 where
     K: Clone + Ord + Serialize + Footprint + fmt::Debug,
@@ -2034,6 +2322,18 @@ where
         self.as_mut().compact(cutoff)
     }
 
+    fn to_bytes_written(&self) -> Result<usize> {
+        self.as_ref().to_bytes_written()
+    }
+
+    fn set_merge_policy(&mut self, policy: Option<SharedMergePolicy<K, V>>) -> Result<()> {
+        self.as_mut().set_merge_policy(policy)
+    }
+
+    fn to_generation(&self) -> Result<Option<Arc<AtomicU64>>> {
+        self.as_ref().to_generation()
+    }
+
     fn close(self) -> Result<()> {
         (*self).close()
     }
@@ -2091,61 +2391,17 @@ where
         let mut inner = self.as_inner()?;
 
         let w = inner.m0.as_mut_m0()?.to_writer()?;
+        let rs = inner.to_rs()?;
 
-        let rs = {
-            let r_m0 = inner.m0.as_mut_m0()?.to_reader()?;
-            let r_m1 = match inner.m1.as_mut() {
-                Some(m) => Some(m.as_mut_m1()?.to_reader()?),
-                None => None,
-            };
-            let mut r_disks = vec![];
-            for disk in inner.disks.iter_mut() {
-                match disk.as_mut_disk()? {
-                    Some(d) => r_disks.push(d.to_reader()?),
-                    None => (),
-                }
-            }
-            Rs {
-                r_m0,
-                r_m1,
-                r_disks,
-
-                _phantom_key: marker::PhantomData,
-                _phantom_val: marker::PhantomData,
-            }
-        };
-
-        let arc_w = Arc::new(Mutex::new(Ws { w, rs }));
+        let cas_policy = inner.cas_policy;
+        let arc_w = Arc::new(Mutex::new(Ws { w, rs, cas_policy }));
         inner.writers.push(Arc::clone(&arc_w));
         Ok(DgmWriter::new(&inner.name, arc_w))
     }
 
     fn to_reader(&mut self) -> Result<Self::R> {
         let mut inner = self.as_inner()?;
-
-        let r_m0 = inner.m0.as_mut_m0()?.to_reader()?;
-
-        let r_m1 = match inner.m1.as_mut() {
-            Some(m) => Some(m.as_mut_m1()?.to_reader()?),
-            None => None,
-        };
-
-        let mut r_disks = vec![];
-        for disk in inner.disks.iter_mut() {
-            match disk.as_mut_disk()? {
-                Some(d) => r_disks.push(d.to_reader()?),
-                None => (),
-            }
-        }
-
-        let rs = Rs {
-            r_m0,
-            r_m1,
-            r_disks,
-
-            _phantom_key: marker::PhantomData,
-            _phantom_val: marker::PhantomData,
-        };
+        let rs = inner.to_rs()?;
 
         let arc_rs = Arc::new(Mutex::new(rs));
         inner.readers.push(Arc::clone(&arc_rs));
@@ -2170,6 +2426,23 @@ where
         Self::do_compact(&self.inner, cutoff)
     }
 
+    fn to_bytes_written(&self) -> Result<usize> {
+        Ok(self.as_inner()?.bytes_written)
+    }
+
+    /// Policy is applied while commiting into, and compacting, disk
+    /// levels. Disk index type shall support merge policy.
+    fn set_merge_policy(&mut self, policy: Option<SharedMergePolicy<K, V>>) -> Result<()> {
+        self.as_inner()?.merge_policy = policy;
+        Ok(())
+    }
+
+    /// Generation is incremented by commits and compactions, including
+    /// those from auto-commit and auto-compaction threads.
+    fn to_generation(&self) -> Result<Option<Arc<AtomicU64>>> {
+        Ok(Some(Arc::clone(&self.as_inner()?.generation)))
+    }
+
     fn close(mut self) -> Result<()> {
         do_close_purge!(self, close)
     }
@@ -2240,11 +2513,10 @@ where
     fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
         let mut w_rs = self.as_writer()?;
 
+        let policy = w_rs.cas_policy;
         match Rs::get(&mut w_rs.rs, &key) {
-            Ok(old) if cas == old.to_seqno() => Ok(()),
-            Err(Error::KeyNotFound) if cas == 0 => Ok(()),
-            Ok(old) => Err(Error::InvalidCAS(old.to_seqno())),
-            Err(Error::KeyNotFound) => Err(Error::InvalidCAS(0)),
+            Ok(old) => policy.check(Some(&old), cas),
+            Err(Error::KeyNotFound) => policy.check::<K>(None, cas),
             Err(err) => Err(err),
         }?;
 
@@ -2261,6 +2533,51 @@ where
     }
 }
 
+/// Write operations with explicit seqno, used while replaying the
+/// write-ahead-log into the memory index.
+impl<K, V, W, A, B> WalWriter<K, V> for DgmWriter<K, V, W, A, B>
+where
+    K: Clone + Ord + Hash + Serialize + Footprint,
+    V: Clone + Diff + Serialize + Footprint,
+    <V as Diff>::D: Serialize,
+    W: Writer<K, V> + WalWriter<K, V>,
+    A: Reader<K, V>,
+    B: Reader<K, V>,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<Option<Entry<K, V>>> {
+        let mut w_rs = self.as_writer()?;
+        w_rs.w.set_index(key, value, seqno)
+    }
+
+    fn set_cas_index(
+        &mut self,
+        key: K,
+        value: V,
+        cas: u64,
+        seqno: u64,
+    ) -> Result<Option<Entry<K, V>>> {
+        let mut w_rs = self.as_writer()?;
+
+        let policy = w_rs.cas_policy;
+        match Rs::get(&mut w_rs.rs, &key) {
+            Ok(old) => policy.check(Some(&old), cas),
+            Err(Error::KeyNotFound) => policy.check::<K>(None, cas),
+            Err(err) => Err(err),
+        }?;
+
+        w_rs.w.set_index(key, value, seqno)
+    }
+
+    fn delete_index<Q>(&mut self, key: &Q, seqno: u64) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let mut w_rs = self.as_writer()?;
+        w_rs.w.delete_index(key, seqno)
+    }
+}
+
 struct Ws<K, V, W, A, B>
 where
     K: Clone + Ord,
@@ -2271,6 +2588,7 @@ where
 {
     w: W,
     rs: Rs<K, V, A, B>,
+    cas_policy: CasPolicy<V>,
 }
 
 // type alias to reader associated type for each snapshot (aka disk-index)
@@ -2316,12 +2634,99 @@ where
             }
         }
 
+        let mut deleted = None;
         let mut iter = self.r_disks.iter_mut();
         loop {
             match iter.next() {
                 Some(disk) => match disk.get(key) {
-                    Ok(entry) => break Ok(entry),
-                    Err(Error::KeyNotFound) => (),
+                    Ok(entry) => break Self::range_deleted(entry, deleted, false),
+                    Err(Error::KeyNotFound) => {
+                        deleted = deleted.max(disk.to_range_deleted(key)?);
+                    }
+                    Err(err) => break Err(err),
+                },
+                None => break Err(Error::KeyNotFound),
+            }
+        }
+    }
+
+    // entry is from an older disk level, if a newer disk level has
+    // deleted the key via range tombstone, at seqno `deleted`, return
+    // a deleted entry.
+    fn range_deleted(
+        entry: Entry<K, V>,
+        deleted: Option<u64>,
+        versions: bool,
+    ) -> Result<Entry<K, V>> {
+        match deleted {
+            Some(seqno) if versions && entry.to_seqno() < seqno => {
+                let value = core::Value::new_delete(seqno);
+                Entry::new(entry.to_key(), value).xmerge(entry)
+            }
+            Some(seqno) if entry.to_seqno() <= seqno => {
+                let value = core::Value::new_delete(seqno);
+                Ok(Entry::new(entry.to_key(), value))
+            }
+            _ => Ok(entry),
+        }
+    }
+
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        if self.r_m0.contains(key)? {
+            return Ok(true);
+        }
+        if let Some(m1) = &mut self.r_m1 {
+            if m1.contains(key)? {
+                return Ok(true);
+            }
+        }
+        for disk in self.r_disks.iter_mut() {
+            if disk.contains(key)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_deadline<Q>(&mut self, key: &Q, timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let deadline = time::Instant::now() + timeout;
+        // remaining time budget for the next level, across the fan-out.
+        let remaining = || match deadline.checked_duration_since(time::Instant::now()) {
+            Some(timeout) => Ok(timeout),
+            None => Err(Error::TimedOut),
+        };
+
+        match self.r_m0.get_deadline(key, remaining()?) {
+            Ok(entry) => return Ok(entry),
+            Err(Error::KeyNotFound) => (),
+            Err(err) => return Err(err),
+        }
+
+        if let Some(m1) = &mut self.r_m1 {
+            match m1.get_deadline(key, remaining()?) {
+                Ok(entry) => return Ok(entry),
+                Err(Error::KeyNotFound) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut deleted = None;
+        let mut iter = self.r_disks.iter_mut();
+        loop {
+            match iter.next() {
+                Some(disk) => match disk.get_deadline(key, remaining()?) {
+                    Ok(entry) => break Self::range_deleted(entry, deleted, false),
+                    Err(Error::KeyNotFound) => {
+                        deleted = deleted.max(disk.to_range_deleted(key)?);
+                    }
                     Err(err) => break Err(err),
                 },
                 None => break Err(Error::KeyNotFound),
@@ -2424,14 +2829,18 @@ where
             None => Ok(m0_entry),
         }?;
 
+        let mut deleted = None;
         let mut iter = rs.r_disks.iter_mut();
         let entry = loop {
             entry = match iter.next() {
                 Some(disk) => match (disk.get_with_versions(key), entry) {
                     (Ok(e), Some(entry)) => Ok(Some(entry.xmerge(e)?)),
-                    (Ok(e), None) => Ok(Some(e)),
+                    (Ok(e), None) => Ok(Some(Self::range_deleted(e, deleted, true)?)),
                     (Err(Error::KeyNotFound), Some(entry)) => Ok(Some(entry)),
-                    (Err(Error::KeyNotFound), None) => Ok(None),
+                    (Err(Error::KeyNotFound), None) => {
+                        deleted = deleted.max(disk.to_range_deleted(key)?);
+                        Ok(None)
+                    }
                     (Err(err), _) => Err(err),
                 },
                 None => break entry,
@@ -2598,7 +3007,27 @@ where
         Q: Ord + ?Sized + Hash,
     {
         let mut rs = self.as_reader()?;
-        Rs::get(rs.deref_mut(), key)
+        let res = Rs::get(rs.deref_mut(), key);
+        trace_event!("get", seqno = res.as_ref().map_or(0, |e| e.to_seqno()));
+        res
+    }
+
+    fn get_deadline<Q>(&mut self, key: &Q, timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let mut rs = self.as_reader()?;
+        Rs::get_deadline(rs.deref_mut(), key, timeout)
+    }
+
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let mut rs = self.as_reader()?;
+        Rs::contains(rs.deref_mut(), key)
     }
 
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
@@ -2626,6 +3055,15 @@ where
         Rs::reverse(rs, range)
     }
 
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
@@ -2748,6 +3186,7 @@ where
 {
     src_disks: Vec<I>,
     rs: Vec<I::R>,
+    policy: Option<SharedMergePolicy<K, V>>,
 }
 
 impl<K, V, I> CommitScanner<K, V, I>
@@ -2756,7 +3195,10 @@ where
     V: Clone + Diff + Serialize + Footprint,
     I: Index<K, V> + Footprint + Clone,
 {
-    fn new(mut src_disks: Vec<I>) -> Result<CommitScanner<K, V, I>> {
+    fn new(
+        mut src_disks: Vec<I>,
+        policy: Option<SharedMergePolicy<K, V>>,
+    ) -> Result<CommitScanner<K, V, I>> {
         src_disks.reverse();
 
         let mut rs = vec![];
@@ -2764,7 +3206,28 @@ where
             rs.push(disk.to_reader()?);
         }
 
-        Ok(CommitScanner { src_disks, rs })
+        Ok(CommitScanner {
+            src_disks,
+            rs,
+            policy,
+        })
+    }
+
+    fn y_iter<'a>(
+        policy: &Option<SharedMergePolicy<K, V>>,
+        new_iter: IndexIter<'a, K, V>,
+        old_iter: IndexIter<'a, K, V>,
+    ) -> IndexIter<'a, K, V>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let no_reverse = false;
+
+        match policy.clone() {
+            Some(policy) => lsm::y_iter_merge(new_iter, old_iter, no_reverse, policy),
+            None => lsm::y_iter_versions(new_iter, old_iter, no_reverse),
+        }
     }
 }
 
@@ -2778,8 +3241,6 @@ where
     where
         G: Clone + RangeBounds<u64>,
     {
-        let no_reverse = false;
-
         match self.rs.len() {
             0 => err_at!(Fatal, msg: format!("unreachable")),
             1 => Ok(self.rs[0].iter_with_versions()?),
@@ -2795,7 +3256,7 @@ where
                         r.as_mut().unwrap()
                     };
                     let iter = r.iter_with_versions()?;
-                    y_iter = lsm::y_iter_versions(iter, y_iter, no_reverse);
+                    y_iter = Self::y_iter(&self.policy, iter, y_iter);
                 }
                 Ok(Box::new(scans::FilterScans::new(
                     vec![y_iter],
@@ -2809,8 +3270,6 @@ where
     where
         G: Clone + RangeBounds<u64>,
     {
-        let no_reverse = false;
-
         let mut result_iters = vec![];
         for disk in self.src_disks.iter_mut() {
             let iters = disk.scans(n_shards, within.clone())?;
@@ -2825,10 +3284,7 @@ where
                 };
                 result_iters = vec![];
                 for (new_iter, old_iter) in ziter {
-                    result_iters.push(
-                        //
-                        lsm::y_iter_versions(new_iter, old_iter, no_reverse),
-                    );
+                    result_iters.push(Self::y_iter(&self.policy, new_iter, old_iter));
                 }
             }
         }
@@ -2841,8 +3297,6 @@ where
         N: Clone + RangeBounds<K>,
         G: Clone + RangeBounds<u64>,
     {
-        let no_reverse = false;
-
         let mut result_iters = vec![];
         for disk in self.src_disks.iter_mut() {
             let iters = disk.range_scans(ranges.clone(), within.clone())?;
@@ -2857,10 +3311,7 @@ where
                 };
                 result_iters = vec![];
                 for (new_iter, old_iter) in ziter {
-                    result_iters.push(
-                        //
-                        lsm::y_iter_versions(new_iter, old_iter, no_reverse),
-                    );
+                    result_iters.push(Self::y_iter(&self.policy, new_iter, old_iter));
                 }
             }
         }
@@ -2967,19 +3418,21 @@ where
     <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
 {
     let compact_interval = root.compact_interval.unwrap();
+    let (window, io_budget) = (root.compact_window, root.compact_io_budget);
 
     info!(
         target: "dgm   ",
-        "{}, auto-compacting thread started with interval {:?}",
-        name, compact_interval,
+        "{}, auto-compacting thread started with interval {:?} window {:?} io-budget {:?}",
+        name, compact_interval, window, io_budget
     );
 
     let mut elapsed = time::Duration::new(0, 0);
+    let mut pause = time::Duration::new(0, 0);
     loop {
         let resp_tx = {
             let interval = {
-                let elapsed = cmp::min(compact_interval, elapsed);
-                compact_interval - elapsed
+                let interval = cmp::max(compact_interval, pause);
+                interval - cmp::min(interval, elapsed)
             };
             match rx.recv_timeout(interval) {
                 Ok((cmd, resp_tx)) if cmd == "do_compact" => resp_tx,
@@ -2989,16 +3442,35 @@ where
             }
         };
 
+        // explicit requests for compaction are not constrained by the
+        // maintenance window or io-budget.
+        if resp_tx.is_none() {
+            let now = err_at!(TimeFail, time::UNIX_EPOCH.elapsed())?;
+            if !is_compact_window(window, now) {
+                debug!(target: "dgm   ", "{:?}, outside compact window", name);
+                elapsed = time::Duration::new(0, 0);
+                pause = time::Duration::new(0, 0);
+                continue;
+            }
+        }
+
         let start = time::SystemTime::now();
 
         let res = Dgm::do_compact(&inner, Cutoff::new_lsm_empty());
 
         match resp_tx {
-            Some(tx) => err_at!(IPCFail, tx.send(res))?,
+            Some(tx) => {
+                err_at!(IPCFail, tx.send(res))?;
+                pause = time::Duration::new(0, 0);
+            }
             None => match res {
-                Ok(n) => info!(
-                    target: "dgm   ", "{:?}, compact done: {}", name, n
-                ),
+                Ok(n) => {
+                    info!(
+                        target: "dgm   ", "{:?}, compact done: {}", name, n
+                    );
+                    let bytes = to_inner_lock(&inner)?.compact_bytes;
+                    pause = to_compact_pause(io_budget, bytes);
+                }
                 Err(err) => {
                     info!(
                         target: "dgm   ", "{:?}, compact err, {:?}", name, err
@@ -3012,6 +3484,31 @@ where
     }
 }
 
+// `now` is the duration since UNIX_EPOCH, window is applied in UTC.
+fn is_compact_window(
+    window: Option<(time::Duration, time::Duration)>,
+    now: time::Duration,
+) -> bool {
+    let tod = now.as_secs() % DAY_SECS;
+    match window {
+        Some((start, end)) if start <= end => tod >= start.as_secs() && tod < end.as_secs(),
+        Some((start, end)) => tod >= start.as_secs() || tod < end.as_secs(),
+        None => true,
+    }
+}
+
+// time to wait, after a compaction that wrote `bytes`, so that the
+// io-rate stays within the budget.
+fn to_compact_pause(io_budget: Option<usize>, bytes: usize) -> time::Duration {
+    match io_budget {
+        Some(io_budget) => {
+            let secs = (bytes as f64) / (io_budget as f64);
+            time::Duration::from_secs_f64(secs)
+        }
+        None => time::Duration::new(0, 0),
+    }
+}
+
 fn to_inner_lock<K, V, M, D>(
     inner: &Arc<Mutex<InnerDgm<K, V, M, D>>>,
 ) -> Result<MutexGuard<InnerDgm<K, V, M, D>>>
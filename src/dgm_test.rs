@@ -2,8 +2,11 @@ use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
 use crate::nobitmap::NoBitmap;
 use crate::{
+    core::ToJson,
     mvcc::{self, MvccFactory},
+    rdms::{verify_consistency, Metrics, Rdms, WalConfig},
     robt::{self, RobtFactory},
+    wal::Wal,
 };
 
 use std::convert;
@@ -29,6 +32,8 @@ fn test_config_root() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        compact_window: None,
+        compact_io_budget: None,
     };
 
     let ref_config = Config {
@@ -38,6 +43,8 @@ fn test_config_root() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        compact_window: None,
+        compact_io_budget: None,
     };
     let root = ref_config.clone().into();
     assert_eq!(ref_root, root);
@@ -71,6 +78,8 @@ fn test_root1() {
             disk_ratio: 0.65,
             commit_interval: Some(time::Duration::from_secs(10)),
             compact_interval: Some(time::Duration::from_secs(10)),
+            compact_window: None,
+            compact_io_budget: None,
         };
         let bytes: Vec<u8> = ref_root.clone().try_into().unwrap();
         println!("{:?}", std::str::from_utf8(&bytes));
@@ -93,6 +102,8 @@ fn test_root2() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        compact_window: None,
+        compact_io_budget: None,
     };
     let root = root.to_next();
     let ref_root = Root {
@@ -107,6 +118,8 @@ fn test_root2() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        compact_window: None,
+        compact_io_budget: None,
     };
     assert_eq!(root, ref_root);
 }
@@ -125,6 +138,8 @@ fn test_root3() {
         disk_ratio: 0.65,
         commit_interval: Some(time::Duration::from_secs(10)),
         compact_interval: Some(time::Duration::from_secs(10)),
+        compact_window: None,
+        compact_io_budget: None,
     };
 
     let cutoffs = vec![
@@ -217,6 +232,43 @@ fn test_root3() {
     }
 }
 
+#[test]
+fn test_compact_window() {
+    let hour = |h: u64| time::Duration::from_secs(h * 3600);
+
+    let mut config: Config = Default::default();
+    assert!(config.set_compact_window(hour(2), hour(2)).is_err());
+    assert!(config.set_compact_window(hour(2), hour(24)).is_err());
+    assert!(config.set_compact_io_budget(0).is_err());
+    config.set_compact_window(hour(2), hour(5)).unwrap();
+    config.set_compact_io_budget(100 * 1024 * 1024).unwrap();
+
+    let root: Root = config.clone().into();
+    let bytes: Vec<u8> = root.clone().try_into().unwrap();
+    let root: Root = bytes.try_into().unwrap();
+    assert_eq!(root.compact_window, Some((hour(2), hour(5))));
+    assert_eq!(root.compact_io_budget, Some(100 * 1024 * 1024));
+    assert_eq!(Config::from(root), config);
+
+    let day = hour(24);
+    let window = Some((hour(2), hour(5)));
+    assert!(is_compact_window(None, hour(12)));
+    assert!(is_compact_window(window, hour(2)));
+    assert!(is_compact_window(window, day * 100 + hour(4)));
+    assert!(!is_compact_window(window, hour(5)));
+    assert!(!is_compact_window(window, day * 100 + hour(1)));
+
+    let window = Some((hour(22), hour(4)));
+    assert!(is_compact_window(window, hour(23)));
+    assert!(is_compact_window(window, day + hour(3)));
+    assert!(!is_compact_window(window, hour(4)));
+    assert!(!is_compact_window(window, hour(21)));
+
+    assert_eq!(to_compact_pause(None, 1000), time::Duration::new(0, 0));
+    let pause = to_compact_pause(Some(100), 250);
+    assert_eq!(pause, time::Duration::from_millis(2500));
+}
+
 #[test]
 fn test_root_file_name() {
     let s = "my-index-dgm-000.root".to_string();
@@ -268,6 +320,8 @@ fn test_dgm_crud() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
     };
 
     println!("seed: {}", seed);
@@ -451,6 +505,8 @@ fn test_dgm_non_lsm() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
     };
 
     let dir = {
@@ -544,6 +600,8 @@ fn test_dgm_cutoffs() {
         disk_ratio: 0.5,
         commit_interval: None,
         compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
     };
 
     let dir = {
@@ -626,6 +684,679 @@ fn test_dgm_cutoffs() {
     index.validate().unwrap();
 }
 
+#[test]
+fn test_verify_consistency() {
+    use std::collections::hash_map::RandomState;
+
+    let config = Config {
+        lsm: true,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: None,
+        compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-verify-consistency");
+        dir.into_os_string()
+    };
+    let mem_factory = mvcc::mvcc_factory(true /*lsm*/);
+    let disk_factory = {
+        let mut config: robt::Config = Default::default();
+        config.delta_ok = true;
+        config.value_in_vlog = true;
+        robt::robt_factory::<i64, i64, NoBitmap>(config)
+    };
+    let mut index = Dgm::new(
+        //
+        &dir,
+        "dgm-verify-consistency",
+        mem_factory,
+        disk_factory,
+        config.clone(),
+    )
+    .unwrap();
+
+    let no_wal: Option<&mut Wal<i64, i64, RandomState>> = None;
+
+    for i in 0..3 {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..100 {
+            index_w.set(key, key + i).unwrap();
+        }
+        index_w.delete(&(i * 10)).unwrap();
+        mem::drop(index_w);
+
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = CommitIter::new(vec![].into_iter(), within);
+        index.commit(scanner, convert::identity).unwrap()
+    }
+    index.compact(Cutoff::new_lsm_empty()).unwrap();
+
+    let mut index_w = index.to_writer().unwrap();
+    for key in 0..10 {
+        index_w.set(key, key).unwrap();
+    }
+    mem::drop(index_w);
+
+    let report = verify_consistency(&mut index, no_wal).unwrap();
+    println!("{}", report);
+    assert_eq!(report.mem_seqno, 313);
+    assert_eq!(report.disk_seqno, 303);
+    assert_eq!(report.n_missing, 0);
+    assert_eq!(report.components[0].n_entries, 10);
+    assert_eq!(report.components[0].min_seqno, Some(304));
+    assert_eq!(report.components[0].max_seqno, Some(313));
+    let n: usize = report.components[1..].iter().map(|c| c.n_entries).sum();
+    assert!(n >= 100, "{}", n);
+
+    // memory index lagging behind the disk levels.
+    index.set_seqno(1).unwrap();
+    let no_wal: Option<&mut Wal<i64, i64, RandomState>> = None;
+    match verify_consistency(&mut index, no_wal) {
+        Err(Error::Fatal(_)) => (),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_dgm_range_tombstone() {
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: None,
+        compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-range-tombstone");
+        dir.into_os_string()
+    };
+    let name = "dgm-range-tombstone";
+    let disk_config: robt::Config = Default::default();
+
+    // older level, with all the keys.
+    let mut index = Dgm::new(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config.clone()),
+        config,
+    )
+    .unwrap();
+    {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..100 {
+            index_w.set(key, key).unwrap();
+        }
+    }
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+    let scanner = CommitIter::new(vec![].into_iter(), within);
+    index.commit(scanner, convert::identity).unwrap();
+    mem::drop(index);
+
+    // newer level, range deleting keys that are only in older level.
+    {
+        let level_name: LevelName = (name.to_string(), Config::NLEVELS - 2).into();
+        let file_name = format!("{}-robt-000", level_name);
+        let mut b = robt::Builder::<i64, i64, NoBitmap>::initial(
+            //
+            &dir,
+            &file_name,
+            disk_config.clone(),
+        )
+        .unwrap();
+        let mut mindex = mvcc::Mvcc::new(name);
+        mindex.set_seqno(100).unwrap();
+        mindex.set(200, 200).unwrap();
+        b.delete_range(20..40, 101).unwrap();
+        b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    }
+
+    let mut index = Dgm::open(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config),
+    )
+    .unwrap();
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..100 {
+        let entry = index_r.get(&key).unwrap();
+        if key >= 20 && key < 40 {
+            assert!(entry.is_deleted(), "{}", key);
+            assert_eq!(entry.to_seqno(), 101);
+        } else {
+            assert_eq!(entry.to_native_value(), Some(key));
+        }
+    }
+    assert_eq!(index_r.get(&200).unwrap().to_native_value(), Some(200));
+    let entry = index_r.get_with_versions(&25).unwrap();
+    assert!(entry.is_deleted());
+    assert_eq!(entry.versions().count(), 2);
+}
+
+#[test]
+fn test_rdms_close_wait() {
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: Some(time::Duration::from_secs(3600)),
+        compact_interval: Some(time::Duration::from_secs(3600)),
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-rdms-close-wait");
+        dir.into_os_string()
+    };
+    let name = "dgm-rdms-close-wait";
+    let disk_config: robt::Config = Default::default();
+
+    let mut index = Dgm::new(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config.clone()),
+        config,
+    )
+    .unwrap();
+    {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..100 {
+            index_w.set(key, key).unwrap();
+        }
+    }
+
+    let rdms = Rdms::new(name, index).unwrap();
+    let res = rdms
+        .close_wait(true /*flush*/, time::Duration::from_secs(60))
+        .unwrap();
+    assert!(res.is_ok(), "{:?}", res);
+    assert!(res.flush.is_some());
+
+    let mut index = Dgm::open(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config),
+    )
+    .unwrap();
+    assert_eq!(index.to_seqno().unwrap(), 100);
+    let mut index_r = index.to_reader().unwrap();
+    for key in 0..100 {
+        assert_eq!(index_r.get(&key).unwrap().to_native_value(), Some(key));
+    }
+}
+
+#[test]
+fn test_rdms_open() {
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: Some(time::Duration::from_secs(3600)),
+        compact_interval: Some(time::Duration::from_secs(3600)),
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let (dir, wal_dir) = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-rdms-open");
+        let mut wal_dir = std::env::temp_dir();
+        wal_dir.push("test-dgm-rdms-open-wal");
+        (dir.into_os_string(), wal_dir.into_os_string())
+    };
+    fs::remove_dir_all(&wal_dir).ok();
+    let name = "dgm-rdms-open";
+    let disk_config: robt::Config = Default::default();
+
+    // first 50 mutations are persisted on disk, and logged.
+    let index = Dgm::new(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config.clone()),
+        config,
+    )
+    .unwrap();
+    let mut rdms = Rdms::new(name, index).unwrap();
+    assert_eq!(rdms.set_wal(WalConfig::new(&wal_dir)).unwrap(), 0);
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        for key in 0..50 {
+            w.set(key, key * 10).unwrap();
+        }
+    }
+    let res = rdms
+        .close_wait(true /*flush*/, time::Duration::from_secs(60))
+        .unwrap();
+    assert!(res.is_ok(), "{:?}", res);
+
+    // next 50 mutations are only logged.
+    let mut rdms = Rdms::open(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config.clone()),
+        WalConfig::new(&wal_dir),
+    )
+    .unwrap();
+    assert_eq!(rdms.to_seqno().unwrap(), 50);
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        for key in 50..100 {
+            w.set(key, key * 10).unwrap();
+        }
+        w.delete(&10).unwrap();
+    }
+    rdms.close().unwrap();
+
+    let mut rdms = Rdms::open(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config),
+        WalConfig::new(&wal_dir),
+    )
+    .unwrap();
+    assert_eq!(rdms.to_seqno().unwrap(), 101);
+    {
+        let mut r = rdms.to_reader().unwrap();
+        for key in 0..100 {
+            let value = r.get(&key).ok().map(|e| e.to_native_value()).flatten();
+            match key {
+                10 => assert_eq!(value, None),
+                key => assert_eq!(value, Some(key * 10)),
+            }
+        }
+    }
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        w.set(100, 1000).unwrap();
+    }
+    assert_eq!(rdms.to_seqno().unwrap(), 102);
+    rdms.purge().unwrap();
+}
+
+#[test]
+fn test_dgm_cas_policy() {
+    use crate::core::CasPolicy;
+
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: Some(time::Duration::from_secs(3600)),
+        compact_interval: Some(time::Duration::from_secs(3600)),
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let (dir, wal_dir) = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-cas-policy");
+        let mut wal_dir = std::env::temp_dir();
+        wal_dir.push("test-dgm-cas-policy-wal");
+        (dir.into_os_string(), wal_dir.into_os_string())
+    };
+    fs::remove_dir_all(&wal_dir).ok();
+    let name = "dgm-cas-policy";
+    let disk_config: robt::Config = Default::default();
+
+    let new_index = || {
+        let mut index = Dgm::new(
+            &dir,
+            name,
+            mvcc::mvcc_factory(false /*lsm*/),
+            robt::robt_factory::<i64, i64, NoBitmap>(disk_config.clone()),
+            config.clone(),
+        )
+        .unwrap();
+        index.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+        index
+    };
+
+    // valid only with CasPolicy::CreateIfAbsent.
+    let mut rdms = Rdms::new(name, new_index()).unwrap();
+    assert_eq!(rdms.set_wal(WalConfig::new(&wal_dir)).unwrap(), 0);
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        assert!(w.set_cas(10, 100, 5).unwrap().is_none());
+        assert_eq!(w.set_cas(10, 200, 0).err(), Some(Error::InvalidCAS(1)));
+    }
+    rdms.close().unwrap();
+
+    // replay shall use the policy configured on the index.
+    let mut rdms = Rdms::new(name, new_index()).unwrap();
+    assert_eq!(rdms.set_wal(WalConfig::new(&wal_dir)).unwrap(), 2);
+    {
+        let mut r = rdms.to_reader().unwrap();
+        let entry = r.get(&10).unwrap();
+        assert_eq!(entry.to_native_value(), Some(100));
+        assert_eq!(entry.to_seqno(), 1);
+    }
+    rdms.purge().unwrap();
+}
+
+#[test]
+fn test_dgm_merge_policy() {
+    use crate::core::{MaxBy, SharedMergePolicy};
+
+    // commit into same level, and commit into new level followed by
+    // compaction.
+    for (mem_ratio, n_levels) in vec![(0.0, 1), (1000.0, 2)].into_iter() {
+        let config = Config {
+            lsm: false,
+            m0_limit: None,
+            mem_ratio,
+            disk_ratio: 0.0,
+            commit_interval: None,
+            compact_interval: None,
+            compact_window: None,
+            compact_io_budget: None,
+        };
+        let dir = {
+            let mut dir = std::env::temp_dir();
+            dir.push("test-dgm-merge-policy");
+            dir.into_os_string()
+        };
+        let mut index = Dgm::new(
+            &dir,
+            "dgm-merge-policy",
+            mvcc::mvcc_factory(false /*lsm*/),
+            robt::robt_factory::<i64, i64, NoBitmap>(Default::default()),
+            config,
+        )
+        .unwrap();
+        let policy: SharedMergePolicy<i64, i64> = {
+            let policy = MaxBy(|x: &i64, y: &i64| x.cmp(y));
+            Arc::new(Mutex::new(policy))
+        };
+        index.set_merge_policy(Some(policy)).unwrap();
+
+        // larger values followed by smaller values.
+        for values in vec![vec![100, 200, 300], vec![10, 20, 30]].into_iter() {
+            {
+                let mut w = index.to_writer().unwrap();
+                for (key, value) in values.into_iter().enumerate() {
+                    w.set(key as i64, value).unwrap();
+                }
+            }
+            index
+                .commit(CommitIter::new_empty(), convert::identity)
+                .unwrap();
+        }
+        {
+            let inner = index.as_inner().unwrap();
+            let n = inner.disks.iter().filter(|d| d.is_active()).count();
+            assert_eq!(n, n_levels);
+        }
+
+        index.compact(Cutoff::new_lsm_empty()).unwrap();
+        {
+            let inner = index.as_inner().unwrap();
+            let n = inner.disks.iter().filter(|d| d.is_active()).count();
+            assert_eq!(n, 1);
+        }
+
+        {
+            let mut r = index.to_reader().unwrap();
+            for (key, value) in vec![100, 200, 300].into_iter().enumerate() {
+                let entry = r.get(&(key as i64)).unwrap();
+                assert_eq!(entry.to_native_value(), Some(value));
+                assert_eq!(entry.to_seqno(), (key as u64) + 1);
+            }
+        }
+        index.purge().unwrap();
+    }
+}
+
+#[test]
+fn test_dgm_compact_io_budget() {
+    let io_budget = 1024 * 1024;
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 1000.0,
+        disk_ratio: 0.0,
+        commit_interval: None,
+        compact_interval: None,
+        compact_window: None,
+        compact_io_budget: Some(io_budget),
+    };
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-compact-io-budget");
+        dir.into_os_string()
+    };
+    let disk_factory = {
+        let mut config: robt::Config = Default::default();
+        config.value_in_vlog = true;
+        robt::robt_factory::<i64, i64, NoBitmap>(config)
+    };
+    let mut index = Dgm::new(
+        &dir,
+        "dgm-compact-io-budget",
+        mvcc::mvcc_factory(false /*lsm*/),
+        disk_factory,
+        config,
+    )
+    .unwrap();
+
+    // every compaction merges a new level into the last level, which
+    // retains the stale values of earlier compactions.
+    let mut pauses = vec![];
+    for (i, n_keys) in vec![1000, 1000, 10].into_iter().enumerate() {
+        {
+            let mut w = index.to_writer().unwrap();
+            for key in 0..n_keys {
+                w.set(key, key * 10).unwrap();
+            }
+        }
+        index
+            .commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+        if i == 0 {
+            continue;
+        }
+
+        let written = index.to_bytes_written().unwrap();
+        index.compact(Cutoff::new_lsm_empty()).unwrap();
+        let written = index.to_bytes_written().unwrap() - written;
+
+        let inner = index.as_inner().unwrap();
+        let footprint = inner.disks[Config::NLEVELS - 1].footprint().unwrap();
+        assert!(written > 0);
+        assert!((written as isize) < footprint, "{} {}", written, footprint);
+        assert_eq!(inner.compact_bytes, written);
+
+        let pause = to_compact_pause(Some(io_budget), inner.compact_bytes);
+        let secs = (written as f64) / (io_budget as f64);
+        assert_eq!(pause, time::Duration::from_secs_f64(secs));
+        pauses.push(pause);
+    }
+    assert!(pauses[1] < pauses[0], "{:?}", pauses);
+
+    index.purge().unwrap();
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_dgm_trace_events() {
+    use std::sync::atomic::{AtomicU64, Ordering::SeqCst};
+    use tracing::{field, span, Event, Metadata, Subscriber};
+
+    // capture op and bytes of every event.
+    struct Capture {
+        events: Arc<Mutex<Vec<(String, u64)>>>,
+        next_id: AtomicU64,
+    }
+
+    struct Visitor(String, u64);
+
+    impl field::Visit for Visitor {
+        fn record_str(&mut self, field: &field::Field, value: &str) {
+            if field.name() == "op" {
+                self.0 = value.to_string();
+            }
+        }
+
+        fn record_u64(&mut self, field: &field::Field, value: u64) {
+            if field.name() == "bytes" {
+                self.1 = value;
+            }
+        }
+
+        fn record_debug(&mut self, _field: &field::Field, _value: &dyn fmt::Debug) {}
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(self.next_id.fetch_add(1, SeqCst) + 1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut visitor = Visitor(String::default(), 0);
+            event.record(&mut visitor);
+            self.events.lock().unwrap().push((visitor.0, visitor.1));
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 1000.0,
+        disk_ratio: 0.0,
+        commit_interval: None,
+        compact_interval: None,
+        compact_window: None,
+        compact_io_budget: None,
+    };
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-trace-events");
+        dir.into_os_string()
+    };
+    let mut index = Dgm::new(
+        &dir,
+        "dgm-trace-events",
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(Default::default()),
+        config,
+    )
+    .unwrap();
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let subscriber = Capture {
+        events: Arc::clone(&events),
+        next_id: AtomicU64::new(0),
+    };
+    // explicit commit and compaction, no auto threads.
+    tracing::subscriber::with_default(subscriber, || {
+        for n_keys in vec![1000, 10].into_iter() {
+            {
+                let mut w = index.to_writer().unwrap();
+                for key in 0..n_keys {
+                    w.set(key, key).unwrap();
+                }
+            }
+            index
+                .commit(CommitIter::new_empty(), convert::identity)
+                .unwrap();
+        }
+        index.compact(Cutoff::new_lsm_empty()).unwrap();
+    });
+
+    let events = events.lock().unwrap().clone();
+    let ops: Vec<&str> = events.iter().map(|(op, _)| op.as_str()).collect();
+    assert_eq!(ops, vec!["commit", "commit", "compact"]);
+    assert!(events.iter().all(|(_, bytes)| *bytes > 0), "{:?}", events);
+    // bytes are those written to disk, not the index footprint.
+    let bytes: u64 = events.iter().map(|(_, bytes)| *bytes).sum();
+    assert_eq!(bytes, index.to_bytes_written().unwrap() as u64);
+
+    index.purge().unwrap();
+}
+
+#[test]
+fn test_rdms_metrics() {
+    let config = Config {
+        lsm: false,
+        m0_limit: None,
+        mem_ratio: 0.5,
+        disk_ratio: 0.5,
+        commit_interval: Some(time::Duration::from_secs(3600)),
+        compact_interval: Some(time::Duration::from_secs(3600)),
+        compact_window: None,
+        compact_io_budget: None,
+    };
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-dgm-rdms-metrics");
+        dir.into_os_string()
+    };
+    let name = "dgm-rdms-metrics";
+    let disk_config: robt::Config = Default::default();
+
+    let mut index = Dgm::new(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(disk_config),
+        config,
+    )
+    .unwrap();
+    {
+        let mut index_w = index.to_writer().unwrap();
+        for key in 0..100 {
+            index_w.set(key, key).unwrap();
+        }
+    }
+
+    let rdms = Rdms::new(name, index).unwrap();
+    let metrics = rdms.to_metrics().unwrap();
+    assert_eq!(metrics.name, name);
+    assert_eq!(metrics.seqno, 100);
+    assert!(metrics.footprint > 0, "{}", metrics);
+
+    let js = metrics.to_json();
+    assert!(js.starts_with(r#"{ "schema_version": 1, "#), "{}", js);
+    let out: Metrics = js.parse().unwrap();
+    assert_eq!(out, metrics);
+
+    rdms.purge().unwrap();
+}
+
 fn verify_read(
     key_max: i64,
     ref_index: &mut mvcc::Mvcc<i64, i64>,
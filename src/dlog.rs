@@ -11,6 +11,8 @@
 //! * Entries are appended into a journal file and automatically rotated
 //!   when `journal_limit` is exceeded.
 //! * Durability guarantee is controlled via `fsync` parameter.
+//! * On linux, with `io-uring` feature, journal appends and fdatasync are
+//!   chained and submitted together, reducing syscalls for small batches.
 //!
 //! **Shards**:
 //!
@@ -39,7 +41,7 @@ use std::{
         atomic::{AtomicU64, Ordering::SeqCst},
         Arc,
     },
-    vec,
+    time, vec,
 };
 
 #[doc(hidden)]
@@ -68,6 +70,7 @@ where
 
     pub(crate) seqno: Arc<AtomicU64>,
     pub(crate) shards: Vec<Shard<S, T>>,
+    pub(crate) state: S, // persisted with the latest batch.
 }
 
 impl<S, T> fmt::Debug for Dlog<S, T>
@@ -125,6 +128,7 @@ where
 
             seqno: dlog_seqno,
             shards,
+            state: Default::default(),
         })
     }
 
@@ -162,6 +166,16 @@ where
 
         dlog_seqno.store(last_seqno, SeqCst);
 
+        // state from the latest batch, across shards.
+        let mut state: Option<(u64, S)> = None;
+        for shard in shards.iter() {
+            state = match (state, shard.to_last_state()?) {
+                (Some((a, x)), Some((b, _))) if a >= b => Some((a, x)),
+                (state, None) => state,
+                (_, state) => state,
+            };
+        }
+
         debug!(target: "dlog  ", "load existing dlog from {:?}/{}", dir, name);
 
         Ok(Dlog {
@@ -170,6 +184,7 @@ where
 
             seqno: dlog_seqno,
             shards,
+            state: state.map(|(_, state)| state).unwrap_or_default(),
         })
     }
 
@@ -186,6 +201,8 @@ where
 pub(crate) enum OpRequest<T> {
     Op { op: T },
     PurgeTill { before: Bound<u64> },
+    Retention { retention: Retention },
+    CommitHook { hook: Option<CommitHook> },
 }
 
 impl<T> OpRequest<T> {
@@ -196,12 +213,52 @@ impl<T> OpRequest<T> {
     pub(crate) fn new_purge_till(before: Bound<u64>) -> OpRequest<T> {
         OpRequest::PurgeTill { before }
     }
+
+    pub(crate) fn new_retention(retention: Retention) -> OpRequest<T> {
+        OpRequest::Retention { retention }
+    }
+
+    pub(crate) fn new_commit_hook(hook: Option<CommitHook>) -> OpRequest<T> {
+        OpRequest::CommitHook { hook }
+    }
+}
+
+/// Summary of a batch committed to a shard's journal, refer to
+/// [Wal::set_commit_hook].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchSummary {
+    /// Shard that committed the batch.
+    pub shard_id: usize,
+    /// Seqno of the first entry in the batch.
+    pub start_seqno: u64,
+    /// Seqno of the last entry in the batch.
+    pub last_seqno: u64,
+    /// Number of bytes appended to the journal.
+    pub bytes: usize,
+    /// Time taken to serialize, append and, if configured, fsync the
+    /// batch.
+    pub latency: time::Duration,
+}
+
+/// Observer called after every batch is committed to disk, refer to
+/// [Wal::set_commit_hook].
+pub type CommitHook = Arc<dyn Fn(&BatchSummary) + Send + Sync>;
+
+// Retention policy for journals in a shard. Oldest journals are purged
+// when `bytes` or `age` is exceeded, provided all its entries are at
+// or before `checkpoint`.
+#[derive(Clone)]
+pub(crate) struct Retention {
+    pub(crate) bytes: Option<u64>,
+    pub(crate) age: Option<time::Duration>,
+    pub(crate) checkpoint: Arc<AtomicU64>,
 }
 
 #[derive(PartialEq)]
 pub(crate) enum OpResponse {
     Seqno(u64),
     Purged(Bound<u64>),
+    Ok,
 }
 
 impl OpResponse {
@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 
 use std::{
     convert::TryInto,
-    fmt, fs,
+    ffi, fmt, fs,
     io::{self, Read, Seek},
     result,
 };
@@ -11,12 +11,18 @@ use crate::{
     core::{Result, Serialize},
     dlog::DlogState,
     error::Error,
+    util,
 };
 
 include!("dlog_marker.rs");
 
 // default size for flush buffer.
 const FLUSH_SIZE: usize = 1 * 1024 * 1024;
+// batch format version, persisted in the upper 16 bits of length-prefix
+// and length-suffix. Version 0 batches don't have state-length, their
+// state-bytes are always empty.
+const BATCH_VERSION: u64 = 1;
+const BATCH_LENGTH_MASK: u64 = 0xFFFF_FFFF_FFFF;
 
 #[derive(Clone)]
 pub(crate) enum Batch<S, T> {
@@ -163,15 +169,44 @@ impl<S, T> Batch<S, T> {
         }
     }
 
-    pub(crate) fn into_active(mut self, fd: &mut fs::File) -> Result<Batch<S, T>>
+    pub(crate) fn into_state(self) -> Result<S> {
+        match self {
+            Batch::Active { state, .. } => Ok(state),
+            Batch::Refer { .. } => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
+    pub(crate) fn set_state(&mut self, value: S) -> Result<()> {
+        match self {
+            Batch::Active { state, .. } => {
+                *state = value;
+                Ok(())
+            }
+            Batch::Refer { .. } => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
+    pub(crate) fn into_active(
+        mut self,
+        fd: &mut fs::File,
+        file: &ffi::OsStr, // log-file, opened as `fd`
+    ) -> Result<Batch<S, T>>
     where
         S: Default + Serialize,
         T: Default + Serialize,
     {
         match self {
-            Batch::Refer { fpos, length, .. } => {
+            Batch::Refer {
+                fpos,
+                length,
+                start_seqno,
+                ..
+            } => {
                 let n: u64 = convert_at!(length)?;
-                let buf = read_file!(fd, fpos, n, "fetching batch")?;
+                let buf = read_file!(fd, fpos, n, "fetching batch").map_err(|err| {
+                    let file = Some(file.to_os_string());
+                    err.into_file_fail(file, Some(fpos), Some(start_seqno))
+                })?;
                 self.decode_active(&buf)?;
 
                 Ok(self)
@@ -187,7 +222,7 @@ impl<S, T> Batch<S, T> {
     {
         match self {
             Batch::Active { .. } => {
-                let mut buffer = Vec::with_capacity(FLUSH_SIZE);
+                let mut buffer = util::get_buffer(FLUSH_SIZE);
                 let batch = {
                     let length = self.encode_active(&mut buffer)?;
                     let a = self.to_first_seqno().unwrap();
@@ -201,13 +236,15 @@ impl<S, T> Batch<S, T> {
     }
 }
 
-// +----------------------------------------------------------------+
-// |                              length                            |
-// +----------------------------------------------------------------+
+// +-------------+--------------------------------------------------+
+// |   version   |                    length                        |
+// +-------------+--------------------------------------------------+
 // |                            start_seqno                         |
 // +----------------------------------------------------------------+
 // |                            last_seqno                          |
 // +----------------------------------------------------------------+
+// |                           state-length                         |
+// +----------------------------------------------------------------+
 // |                            state-bytes                         |
 // +----------------------------------------------------------------+
 // |                             n-entries                          |
@@ -215,11 +252,12 @@ impl<S, T> Batch<S, T> {
 // |                              entries                           |
 // +--------------------------------+-------------------------------+
 // |                         DLOG_BATCH_MARKER                      |
-// +----------------------------------------------------------------+
-// |                              length                            |
-// +----------------------------------------------------------------+
+// +-------------+--------------------------------------------------+
+// |   version   |                    length                        |
+// +-------------+--------------------------------------------------+
 //
 // NOTE: `length` value includes 8-byte length-prefix and 8-byte length-suffix.
+// NOTE: version 0 batches don't have `state-length` and `state-bytes`.
 impl<S, T> Batch<S, T>
 where
     S: Serialize,
@@ -243,7 +281,14 @@ where
                 buf.extend_from_slice(&last_seqno.to_be_bytes());
                 n += 16;
 
-                n += state.encode(buf)?;
+                let m = buf.len();
+                buf.resize(m + 8, 0); // adjust for state-length
+                let slen = state.encode(buf)?;
+                {
+                    let slen: u64 = convert_at!(slen)?;
+                    buf[m..m + 8].copy_from_slice(&slen.to_be_bytes());
+                }
+                n += 8 + slen;
 
                 let nentries: u64 = convert_at!(entries.len())?;
                 buf.extend_from_slice(&nentries.to_be_bytes());
@@ -258,6 +303,7 @@ where
                 n += 8; // suffix length
 
                 let length: u64 = convert_at!(n)?;
+                let length = (BATCH_VERSION << 48) | length;
                 buf[..8].copy_from_slice(&length.to_be_bytes());
                 buf.extend_from_slice(&length.to_be_bytes());
 
@@ -270,7 +316,7 @@ where
     pub(crate) fn decode_refer(&mut self, buf: &[u8], fpos: u64) -> Result<usize> {
         check_remaining!(buf, 24, "dlog-batch-refer-hdr")?;
 
-        let length = Self::validate(buf)?;
+        let (length, _version) = Self::validate(buf)?;
         let start_seqno = u64::from_be_bytes(array_at!(buf[8..16])?);
         let last_seqno = u64::from_be_bytes(array_at!(buf[16..24])?);
 
@@ -291,11 +337,23 @@ where
     {
         check_remaining!(buf, 24, "dlog-batch-active-hdr")?;
 
-        let length = Self::validate(buf)?;
+        let (length, version) = Self::validate(buf)?;
         let mut n = 24;
 
         let mut state: S = Default::default();
-        n += state.decode(buf)?;
+        match version {
+            0 => {
+                state.decode(&buf[n..n])?;
+            }
+            _ => {
+                let slen = u64::from_be_bytes(array_at!(buf[n..n + 8])?);
+                let slen: usize = convert_at!(slen)?;
+                n += 8;
+                check_remaining!(buf, n + slen, "dlog-batch-state")?;
+                state.decode(&buf[n..n + slen])?;
+                n += slen;
+            }
+        }
 
         let nentries = u64::from_be_bytes(array_at!(buf[n..n + 8])?);
         n += 8;
@@ -316,21 +374,66 @@ where
         Ok(length)
     }
 
-    fn validate(buf: &[u8]) -> Result<usize> {
-        let (a, z): (usize, usize) = {
-            let n = convert_at!(u64::from_be_bytes(array_at!(buf[..8])?))?;
-            (
-                n,
-                convert_at!(u64::from_be_bytes(array_at!(buf[n - 8..n])?))?,
-            )
+    // check the batch at `fpos`, without loading it, for a length-prefix
+    // matching its length-suffix and DLOG_BATCH_MARKER. Return batch
+    // length if batch is structurally valid, else None.
+    pub(crate) fn validate_at(fd: &mut fs::File, fpos: u64, till: u64) -> Result<Option<u64>> {
+        let m: u64 = convert_at!(DLOG_BATCH_MARKER.len())?;
+        if till < (fpos + 8) {
+            return Ok(None);
+        }
+        let buf = read_file!(fd, fpos, 8, "dlog-batch-prefix")?;
+        let word = u64::from_be_bytes(array_at!(buf[..8])?);
+        let length = word & BATCH_LENGTH_MASK;
+        if (word >> 48) > BATCH_VERSION || length < (32 + m + 8) || length > (till - fpos) {
+            return Ok(None);
+        }
+
+        let buf = read_file!(fd, fpos + length - 8 - m, m + 8, "dlog-batch-suffix")?;
+        let (a, z): (usize, usize) = (convert_at!(m)?, convert_at!(m + 8)?);
+        let suffix = u64::from_be_bytes(array_at!(buf[a..z])?);
+        if &buf[..a] == DLOG_BATCH_MARKER.as_slice() && suffix == word {
+            Ok(Some(length))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // number of entries in batch, read from the batch header in `buf`,
+    // if the header is available.
+    pub(crate) fn to_nentries(buf: &[u8]) -> Option<usize> {
+        let word = u64::from_be_bytes(buf.get(..8)?.try_into().ok()?);
+        let n = match word >> 48 {
+            0 => 24,
+            _ => {
+                let slen = u64::from_be_bytes(buf.get(24..32)?.try_into().ok()?);
+                let slen: usize = slen.try_into().ok()?;
+                32 + slen
+            }
         };
-        if a != z {
-            return err_at!(Fatal, msg: format!("{} != {}", a, z));
+        let nentries = u64::from_be_bytes(buf.get(n..n + 8)?.try_into().ok()?);
+        nentries.try_into().ok()
+    }
+
+    // return the batch length and its format version.
+    fn validate(buf: &[u8]) -> Result<(usize, u64)> {
+        let word = u64::from_be_bytes(array_at!(buf[..8])?);
+        let (version, a): (u64, usize) = {
+            let length = word & BATCH_LENGTH_MASK;
+            (word >> 48, convert_at!(length)?)
+        };
+        if version > BATCH_VERSION {
+            return err_at!(InvalidFile, msg: format!("batch version {}", version));
+        }
+        check_remaining!(buf, a, "dlog-batch")?;
+        let z = u64::from_be_bytes(array_at!(buf[a - 8..a])?);
+        if word != z {
+            return err_at!(Fatal, msg: format!("{} != {}", word, z));
         }
 
         let (m, n) = (a - 8 - DLOG_BATCH_MARKER.len(), a - 8);
         if DLOG_BATCH_MARKER.as_slice() == &buf[m..n] {
-            Ok(a)
+            Ok((a, version))
         } else {
             err_at!(Fatal, msg: format!("{:?}", &buf[m..n]))
         }
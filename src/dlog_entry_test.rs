@@ -75,6 +75,11 @@ fn test_batch2() {
 
     let mut buf = vec![];
     let length = batch.encode_active(&mut buf).unwrap();
+    assert_eq!(length, 4135);
+
+    // batch in baseline format shall continue to load.
+    let buf = to_legacy_batch(&buf);
+    let length = buf.len();
     assert_eq!(length, 4099);
 
     let file = {
@@ -91,7 +96,7 @@ fn test_batch2() {
         0, length, 1, 100,
     );
     let mut fd = fs::File::open(&file).unwrap();
-    let abatch = rbatch.into_active(&mut fd).unwrap();
+    let abatch = rbatch.into_active(&mut fd, &file).unwrap();
     validate(abatch);
 
     let mut batch = Batch::<wal::State, wal::Op<i64, i64>>::default_active();
@@ -113,3 +118,58 @@ fn test_batch2() {
         _ => unreachable!(),
     }
 }
+
+#[test]
+fn test_batch_state() {
+    use crate::wal;
+
+    type WalBatch = Batch<wal::State, wal::Op<i64, i64>>;
+
+    let mut batch = WalBatch::default_active();
+    for i in 0..10 {
+        let op = wal::Op::new_set(10, 20);
+        batch.add_entry(DEntry::new(i + 1, op)).unwrap();
+    }
+    let state = wal::State {
+        term: 10,
+        committed: 8,
+        persisted: 5,
+        config: vec!["n1".to_string(), "n2".to_string()],
+        votedfor: "n2".to_string(),
+    };
+    batch.set_state(state.clone()).unwrap();
+
+    let mut buf = vec![];
+    let length = batch.encode_active(&mut buf).unwrap();
+    assert_eq!(length, 545);
+    assert_eq!(WalBatch::to_nentries(&buf), Some(10));
+
+    let mut batch = WalBatch::default_active();
+    batch.decode_active(&buf).unwrap();
+    assert_eq!(batch.len().unwrap(), 10);
+    assert_eq!(batch.into_state().unwrap(), state);
+
+    let buf = to_legacy_batch(&buf);
+    assert_eq!(buf.len(), 499);
+    assert_eq!(WalBatch::to_nentries(&buf), Some(10));
+
+    let mut batch = WalBatch::default_active();
+    batch.decode_active(&buf).unwrap();
+    assert_eq!(batch.len().unwrap(), 10);
+    assert_eq!(batch.into_state().unwrap(), wal::State::default());
+}
+
+// strip state-length and state-bytes from a batch, as persisted before
+// batches were versioned.
+fn to_legacy_batch(buf: &[u8]) -> Vec<u8> {
+    let length = buf.len();
+    let slen = u64::from_be_bytes(buf[24..32].try_into().unwrap()) as usize;
+    let n = ((length - 8 - slen) as u64).to_be_bytes();
+
+    let mut out = vec![];
+    out.extend_from_slice(&n);
+    out.extend_from_slice(&buf[8..24]);
+    out.extend_from_slice(&buf[32 + slen..length - 8]);
+    out.extend_from_slice(&n);
+    out
+}
@@ -1,4 +1,4 @@
-use log::debug;
+use log::{debug, warn};
 
 use std::{
     cmp,
@@ -10,23 +10,30 @@ use std::{
     path, result,
     sync::{
         atomic::{AtomicU64, Ordering::SeqCst},
-        mpsc, Arc,
+        mpsc, Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{self, Duration},
     vec,
 };
 
 use crate::{
     core::{Result, Serialize},
-    dlog::{DlogState, OpRequest, OpResponse},
-    dlog_entry::{Batch, DEntry},
+    dlog::{BatchSummary, CommitHook, DlogState, OpRequest, OpResponse, Retention},
+    dlog_entry::{Batch, DEntry, DLOG_INDEX_MARKER},
     error::Error,
     thread as rt, util,
 };
 
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::dlog_uring;
+
 // default block size while loading the Dlog/Journal batches.
 const DLOG_BLOCK_SIZE: usize = 10 * 1024 * 1024;
+// minimum interval between two retention sweeps on a shard.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(1);
+// sparse index, in journal trailer, shall index every nth batch.
+const JOURNAL_INDEX_INTERVAL: usize = 16;
 
 #[derive(Clone)]
 pub(crate) struct JournalFile(ffi::OsString);
@@ -118,6 +125,10 @@ pub(crate) struct Shard<S, T> {
     dlog_seqno: Arc<AtomicU64>,
     journals: Vec<Journal<S, T>>,
     active: Journal<S, T>,
+    retention: Option<Retention>,
+    retention_at: time::Instant,
+    commit_hook: Option<CommitHook>,
+    state: Option<Arc<Mutex<S>>>,
 }
 
 impl<S, T> Shard<S, T>
@@ -163,6 +174,10 @@ where
             dlog_seqno: seqno,
             journals: vec![],
             active,
+            retention: None,
+            retention_at: time::Instant::now(),
+            commit_hook: None,
+            state: None,
         })
     }
 
@@ -230,6 +245,10 @@ where
                 dlog_seqno: seqno,
                 journals,
                 active,
+                retention: None,
+                retention_at: time::Instant::now(),
+                commit_hook: None,
+                state: None,
             },
         ))
     }
@@ -268,10 +287,39 @@ where
             dlog_seqno: self.dlog_seqno,
             journals,
             active: self.active,
+            retention: self.retention,
+            retention_at: self.retention_at,
+            commit_hook: self.commit_hook,
+            state: self.state,
         })
     }
 
-    pub(crate) fn close(self) -> Result<()> {
+    // state shared with the application, stamped on every batch before
+    // it is flushed.
+    pub(crate) fn set_state(&mut self, state: Arc<Mutex<S>>) {
+        self.state = Some(state)
+    }
+
+    // return the state persisted with the last batch, along with the
+    // batch's last seqno, from the latest journal having batches.
+    pub(crate) fn to_last_state(&self) -> Result<Option<(u64, S)>> {
+        for journal in self.journals.iter().rev() {
+            match journal.to_last_state()? {
+                Some(state) => {
+                    let seqno = journal.to_last_seqno()?.unwrap_or(0);
+                    return Ok(Some((seqno, state)));
+                }
+                None => (),
+            }
+        }
+        Ok(None)
+    }
+
+    pub(crate) fn close(mut self) -> Result<()> {
+        // entries are flushed after every batch of commands, but fsync is
+        // optional, make sure the active journal is durable on close.
+        self.active.sync()?;
+
         debug!(
             target: "dlogsd",
             "shard:{} {:?}/{} closed", self.shard_id, self.dir, self.name,
@@ -311,7 +359,7 @@ where
 {
     pub(crate) fn into_thread(self) -> rt::Thread<OpRequest<T>, OpResponse, Shard<S, T>>
     where
-        S: DlogState<T>,
+        S: Clone + DlogState<T>,
     {
         let seqno = self.dlog_seqno.load(SeqCst);
         debug!(
@@ -331,7 +379,7 @@ where
 {
     fn routine(mut self, rx: rt::Rx<OpRequest<T>, OpResponse>) -> Result<Self>
     where
-        S: DlogState<T>,
+        S: Clone + DlogState<T>,
     {
         'outer: loop {
             let mut cmds = vec![];
@@ -366,7 +414,7 @@ where
         cmds: Vec<(OpRequest<T>, Option<mpsc::Sender<OpResponse>>)>,
     ) -> Result<bool>
     where
-        S: DlogState<T>,
+        S: Clone + DlogState<T>,
     {
         use std::sync::atomic::Ordering::AcqRel;
 
@@ -381,21 +429,110 @@ where
                     let before = self.do_purge_till(before)?;
                     err_at!(IPCFail, caller.send(OpResponse::new_purged(before)))?;
                 }
+                (OpRequest::Retention { retention }, Some(caller)) => {
+                    self.retention = Some(retention);
+                    self.do_retention()?;
+                    err_at!(IPCFail, caller.send(OpResponse::Ok))?;
+                }
+                (OpRequest::CommitHook { hook }, Some(caller)) => {
+                    self.commit_hook = hook;
+                    err_at!(IPCFail, caller.send(OpResponse::Ok))?;
+                }
                 _ => err_at!(Fatal, msg: format!("unreachable"))?,
             }
         }
 
-        match self.active.flush1(self.journal_limit, self.fsync)? {
-            None => (),
-            Some((buffer, batch)) => {
+        if let Some(state) = &self.state {
+            let state = match state.lock() {
+                Ok(state) => state.clone(),
+                Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err))?,
+            };
+            self.active.set_state(state)?;
+        }
+
+        let start = time::Instant::now();
+        let flushed = match self.active.flush1(self.journal_limit, self.fsync)? {
+            Flush::None => None,
+            Flush::Done(seqnos, bytes) => Some((seqnos, bytes)),
+            Flush::Rotate(buffer, batch) => {
                 self.rotate_journal()?;
-                self.active.flush2(&buffer, batch, self.fsync)?;
+                let flushed = self.active.flush2(&buffer, batch, self.fsync)?;
+                util::put_buffer(buffer);
+                Some(flushed)
             }
+        };
+        match (&self.commit_hook, flushed) {
+            (Some(hook), Some(((start_seqno, last_seqno), bytes))) => hook(&BatchSummary {
+                shard_id: self.shard_id,
+                start_seqno,
+                last_seqno,
+                bytes,
+                latency: start.elapsed(),
+            }),
+            _ => (),
+        }
+
+        if self.retention_at.elapsed() > RETENTION_INTERVAL {
+            self.do_retention()?;
         }
 
         Ok(false)
     }
 
+    // purge oldest journals, while the shard exceeds its retention
+    // limits, stop at the first journal having entries beyond the
+    // checkpoint. Return the number of journals purged.
+    fn do_retention(&mut self) -> Result<usize> {
+        self.retention_at = time::Instant::now();
+        let retention = match &self.retention {
+            Some(retention) => retention.clone(),
+            None => return Ok(0),
+        };
+        let checkpoint = retention.checkpoint.load(SeqCst);
+
+        let mut total = {
+            let file_path = self.active.to_file_path();
+            err_at!(IoError, fs::metadata(&file_path))?.len()
+        };
+        let mut metas = vec![];
+        for journal in self.journals.iter() {
+            let meta = err_at!(IoError, fs::metadata(&journal.to_file_path()))?;
+            total += meta.len();
+            metas.push((meta.len(), err_at!(IoError, meta.modified())?));
+        }
+
+        let now = time::SystemTime::now();
+        let mut n = 0;
+        for (len, modified) in metas.into_iter() {
+            let over_size = retention.bytes.map_or(false, |bytes| total > bytes);
+            let over_age = match (retention.age, now.duration_since(modified)) {
+                (Some(age), Ok(elapsed)) => elapsed > age,
+                _ => false,
+            };
+            if !(over_size || over_age) || self.journals[0].is_cold() {
+                break;
+            }
+            match self.journals[0].to_last_seqno()? {
+                Some(last_seqno) if last_seqno <= checkpoint => {
+                    self.journals.remove(0).purge()?;
+                    total -= len;
+                    n += 1;
+                }
+                _ => break,
+            }
+        }
+
+        if n > 0 {
+            debug!(
+                target: "dlogsd",
+                "shard:{} {:?}/{} retention purged {} journals",
+                self.shard_id, self.dir, self.name, n
+            );
+        }
+
+        Ok(n)
+    }
+
     // return seqno or io::Error.
     fn do_purge_till(&mut self, before: Bound<u64>) -> Result<Bound<u64>> {
         for _ in 0..self.journals.len() {
@@ -450,12 +587,16 @@ enum InnerJournal<S, T> {
         fd: fs::File,
         batches: Vec<Batch<S, T>>,
         active: Batch<S, T>,
+        // logical end of journal, file may be preallocated beyond this.
+        till: u64,
     },
     // All journals except lastest journal are archives, which means only
-    // the metadata for each batch shall be stored.
+    // the metadata for each batch shall be stored. If archive carries a
+    // sparse index, batches are loaded lazily from the indexed position.
     Archive {
         file_path: ffi::OsString,
         batches: Vec<Batch<S, T>>,
+        index: Option<JournalIndex>,
     },
     // Cold journals are colder than archives, that is, they are not
     // required by the application, may be as frozen-backup.
@@ -508,7 +649,7 @@ where
 
         let fd = {
             let mut opts = fs::OpenOptions::new();
-            err_at!(IoError, opts.append(true).create_new(true).open(&fpath))?
+            err_at!(IoError, opts.write(true).create_new(true).open(&fpath))?
         };
 
         debug!(target: "dlogjn", "New active journal {:?}", fpath);
@@ -522,6 +663,7 @@ where
                 fd: fd,
                 batches: Default::default(),
                 active: Batch::default_active(),
+                till: 0,
             },
         })
     }
@@ -549,31 +691,23 @@ where
             fp.into_os_string()
         };
 
-        let mut batches = vec![];
         let mut fd = util::open_file_r(&file_path).ok()?;
-        let mut fpos = 0_usize;
-        let till: usize = convert_at!(fd.metadata().ok()?.len()).ok()?;
-
-        while fpos < till {
-            let n = cmp::min(DLOG_BLOCK_SIZE, till - fpos) as u64;
-            let fpos_u64: u64 = convert_at!(fpos).ok()?;
-            let block = read_file!(&mut fd, fpos_u64, n, "journal corrupted").ok()?;
-
-            let mut m = 0_usize;
-            while m < block.len() {
-                let mut batch: Batch<S, T> = Batch::default_active();
-                m += batch
-                    .decode_refer(&block[m..], convert_at!((fpos + m)).ok()?)
-                    .ok()?;
-                batches.push(batch);
+        let len = fd.metadata().ok()?.len();
+        let (batches, index) = match JournalIndex::read(&mut fd, len).ok()? {
+            Some(index) => (vec![], Some(index)),
+            None => {
+                // journal was not archived, may be torn by a crash.
+                let (n, _) = Self::truncate_to_last_good(&file_path).ok()?;
+                (Self::load_batches(&mut fd, 0, len - n).ok()?, None)
             }
-            fpos += block.len();
-        }
+        };
 
         debug!(
             target: "dlogjn",
-            "Opening archive journal {:?}, loaded {} batches",
-            file_path, batches.len()
+            "Opening archive journal {:?}, loaded {} batches, indexed:{}",
+            file_path,
+            batches.len(),
+            index.is_some()
         );
 
         Some(Journal {
@@ -583,10 +717,80 @@ where
             inner: InnerJournal::Archive {
                 file_path: file_path.clone(),
                 batches,
+                index,
             },
         })
     }
 
+    // load batch references from `fpos` till `till`. Preallocated
+    // journals, or a crash, can leave a zero-filled tail, an all-zero
+    // batch header is treated as end-of-journal.
+    fn load_batches(fd: &mut fs::File, fpos: u64, till: u64) -> Result<Vec<Batch<S, T>>> {
+        let mut batches = vec![];
+        let (mut fpos, till): (usize, usize) = (convert_at!(fpos)?, convert_at!(till)?);
+
+        while fpos < till {
+            let n = cmp::min(DLOG_BLOCK_SIZE, till - fpos) as u64;
+            let fpos_u64: u64 = convert_at!(fpos)?;
+            let block = read_file!(fd, fpos_u64, n, "journal corrupted")?;
+
+            let mut m = 0_usize;
+            while m < block.len() {
+                let hdr = &block[m..cmp::min(m + 24, block.len())];
+                if hdr.iter().all(|b| *b == 0) {
+                    debug!(target: "dlogjn", "zero-filled journal tail at {}", fpos + m);
+                    return Ok(batches);
+                }
+                let mut batch: Batch<S, T> = Batch::default_active();
+                m += batch.decode_refer(&block[m..], convert_at!((fpos + m))?)?;
+                batches.push(batch);
+            }
+            fpos += block.len();
+        }
+
+        Ok(batches)
+    }
+
+    // Repair a journal torn by a crash. Walk the batches from start of
+    // the file, validating each batch's length-prefix with its marker
+    // and length-suffix, and truncate the file after the last batch
+    // that is structurally valid. Return the number of bytes discarded
+    // and the number of entries lost in the torn batch, if the torn
+    // batch's header survived.
+    pub(crate) fn truncate_to_last_good(file_path: &ffi::OsStr) -> Result<(u64, usize)> {
+        let mut fd = util::open_file_r(file_path)?;
+        let len = err_at!(IoError, fd.metadata())?.len();
+
+        let mut fpos = 0;
+        while let Some(n) = Batch::<S, T>::validate_at(&mut fd, fpos, len)? {
+            fpos += n;
+        }
+        if fpos == len {
+            return Ok((0, 0));
+        }
+
+        let entries = {
+            let n = cmp::min(len - fpos, DLOG_BLOCK_SIZE as u64);
+            let buf = read_file!(fd, fpos, n, "journal torn batch")?;
+            Batch::<S, T>::to_nentries(&buf).unwrap_or(0)
+        };
+
+        let fd = util::open_file_pw(file_path)?;
+        err_at!(IoError, fd.set_len(fpos))?;
+        err_at!(IoError, fd.sync_all())?;
+
+        warn!(
+            target: "dlogjn",
+            "Truncated journal {:?} at {}, discarded {} bytes, {} entries",
+            file_path,
+            fpos,
+            len - fpos,
+            entries
+        );
+
+        Ok((len - fpos, entries))
+    }
+
     // don't load the batches. use this only for purging the journal.
     fn new_cold(
         dir: ffi::OsString,
@@ -637,9 +841,27 @@ where
 
         match self.inner {
             Active {
-                file_path, batches, ..
+                file_path,
+                mut fd,
+                batches,
+                till,
+                ..
             } => {
-                self.inner = Archive { file_path, batches };
+                // append sparse index as trailer, and drop preallocated
+                // tail, if any, so that the trailer ends the file.
+                let index = JournalIndex::new(&batches, till)?;
+                let trailer = index.encode()?;
+                err_at!(IoError, fd.seek(io::SeekFrom::Start(till)))?;
+                write_file!(fd, &trailer, file_path.clone(), "journal-index")?;
+                let n: u64 = convert_at!(trailer.len())?;
+                err_at!(IoError, fd.set_len(till + n))?;
+                err_at!(IoError, fd.sync_all())?;
+
+                self.inner = Archive {
+                    file_path,
+                    batches,
+                    index: Some(index),
+                };
                 Ok(self)
             }
             Cold { file_path } => {
@@ -674,9 +896,10 @@ impl<S, T> Journal<S, T> {
                     None => Ok(None),
                 },
             },
-            Archive { batches, .. } => match batches.last() {
-                Some(last) => Ok(last.to_last_seqno()),
-                None => Ok(None),
+            Archive { batches, index, .. } => match (batches.last(), index) {
+                (Some(last), _) => Ok(last.to_last_seqno()),
+                (None, Some(index)) => Ok(index.last_seqno),
+                (None, None) => Ok(None),
             },
             _ => err_at!(Fatal, msg: format!("unreachable"))?,
         }
@@ -699,23 +922,6 @@ impl<S, T> Journal<S, T> {
         }
     }
 
-    pub(crate) fn into_batches(self) -> Result<Vec<Batch<S, T>>> {
-        let batches = match self.inner {
-            InnerJournal::Active {
-                mut batches,
-                mut active,
-                ..
-            } => {
-                batches.push(mem::replace(&mut active, Default::default()));
-                batches
-            }
-            InnerJournal::Archive { batches, .. } => batches,
-            _ => err_at!(Fatal, msg: format!("unreachable"))?,
-        };
-
-        Ok(batches)
-    }
-
     pub(crate) fn add_entry(&mut self, entry: DEntry<T>) -> Result<()>
     where
         S: DlogState<T>,
@@ -726,6 +932,13 @@ impl<S, T> Journal<S, T> {
         }
     }
 
+    pub(crate) fn set_state(&mut self, state: S) -> Result<()> {
+        match &mut self.inner {
+            InnerJournal::Active { active, .. } => active.set_state(state),
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     pub(crate) fn into_cold(mut self) -> Result<Self> {
         use InnerJournal::{Active, Archive, Cold};
 
@@ -736,6 +949,13 @@ impl<S, T> Journal<S, T> {
         };
         Ok(self)
     }
+
+    pub(crate) fn sync(&mut self) -> Result<()> {
+        match &mut self.inner {
+            InnerJournal::Active { fd, .. } => err_at!(IoError, fd.sync_all()),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<S, T> Journal<S, T>
@@ -743,64 +963,317 @@ where
     S: Default + Serialize,
     T: Serialize,
 {
+    pub(crate) fn into_batches(self) -> Result<Vec<Batch<S, T>>> {
+        self.into_batches_from(0)
+    }
+
+    // return the state persisted with the last batch of an archive.
+    fn to_last_state(&self) -> Result<Option<S>>
+    where
+        T: Default,
+    {
+        let (file_path, batch) = match &self.inner {
+            InnerJournal::Archive {
+                file_path,
+                batches,
+                index,
+            } => match (batches.last(), index) {
+                (Some(batch), _) => match batch {
+                    Batch::Refer {
+                        fpos,
+                        length,
+                        start_seqno,
+                        last_seqno,
+                    } => {
+                        let batch = Batch::new_refer(*fpos, *length, *start_seqno, *last_seqno);
+                        (file_path, Some(batch))
+                    }
+                    Batch::Active { .. } => err_at!(Fatal, msg: format!("unreachable"))?,
+                },
+                (None, Some(index)) => {
+                    let mut fd = util::open_file_r(file_path)?;
+                    let fpos = index.to_fpos(u64::MAX);
+                    let mut batches = Self::load_batches(&mut fd, fpos, index.till)?;
+                    (file_path, batches.pop())
+                }
+                (None, None) => (file_path, None),
+            },
+            _ => return Ok(None),
+        };
+
+        match batch {
+            Some(batch) => {
+                let mut fd = util::open_file_r(file_path)?;
+                let batch = batch.into_active(&mut fd, file_path)?;
+                Ok(Some(batch.into_state()?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // return batches that may contain entries after `seqno`, batches
+    // before that may also be returned. Archives having a sparse index
+    // shall seek directly to the batch.
+    pub(crate) fn into_batches_from(self, seqno: u64) -> Result<Vec<Batch<S, T>>> {
+        let batches = match self.inner {
+            InnerJournal::Active {
+                mut batches,
+                mut active,
+                ..
+            } => {
+                batches.push(mem::replace(&mut active, Default::default()));
+                batches
+            }
+            InnerJournal::Archive {
+                batches,
+                index: Some(index),
+                file_path,
+            } if batches.is_empty() => {
+                let mut fd = util::open_file_r(&file_path)?;
+                Self::load_batches(&mut fd, index.to_fpos(seqno), index.till)?
+            }
+            InnerJournal::Archive { batches, .. } => batches,
+            _ => err_at!(Fatal, msg: format!("unreachable"))?,
+        };
+
+        Ok(batches)
+    }
+
     // periodically flush journal entries from memory to disk.
-    fn flush1(
-        &mut self,
-        journal_limit: usize,
-        fsync: bool,
-    ) -> Result<Option<(Vec<u8>, Batch<S, T>)>> {
-        let (file_path, fd, batches, active, rotate) = match &mut self.inner {
+    fn flush1(&mut self, journal_limit: usize, fsync: bool) -> Result<Flush<S, T>> {
+        let (file_path, fd, batches, active, till, rotate) = match &mut self.inner {
             InnerJournal::Active {
                 file_path,
                 fd,
                 batches,
                 active,
+                till,
             } => {
                 let limit: u64 = convert_at!(journal_limit)?;
-                let rotate = err_at!(IoError, fd.metadata())?.len() > limit;
-                Ok((file_path, fd, batches, active, rotate))
+                let rotate = *till > limit;
+                Ok((file_path, fd, batches, active, till, rotate))
             }
             _ => err_at!(Fatal, msg: format!("unreachable")),
         }?;
 
         match rotate {
-            true if active.len()? > 0 => Ok(Some(active.to_refer(0)?)),
+            true if active.len()? > 0 => {
+                let (buffer, batch) = active.to_refer(0)?;
+                Ok(Flush::Rotate(buffer, batch))
+            }
             false if active.len()? > 0 => {
-                let (buffer, batch) = {
-                    let fpos = err_at!(IoError, fd.metadata())?.len();
-                    active.to_refer(fpos)?
-                };
+                let (buffer, batch) = active.to_refer(*till)?;
+                let seqnos = to_seqnos(&batch);
                 batches.push(batch);
-                write_file!(fd, &buffer, file_path.clone(), "wal-flush1")?;
+                write_journal(fd, file_path, *till, &buffer, fsync, "wal-flush1")?;
+                let bytes = buffer.len();
+                let n: u64 = convert_at!(bytes)?;
+                util::put_buffer(buffer);
+                *till += n;
                 if fsync {
-                    err_at!(IoError, fd.sync_all())?;
+                    trace_event!(
+                        "wal_fsync",
+                        seqno = batches.last().and_then(|b| b.to_last_seqno()).unwrap_or(0),
+                        bytes = n,
+                    );
                 }
                 *active = Batch::default_active();
-                Ok(None)
+                Ok(Flush::Done(seqnos, bytes))
             }
-            _ => Ok(None),
+            _ => Ok(Flush::None),
         }
     }
 
-    fn flush2(&mut self, buffer: &[u8], batch: Batch<S, T>, fsync: bool) -> Result<()> {
-        let (file_path, fd, batches, active) = match &mut self.inner {
+    fn flush2(
+        &mut self,
+        buffer: &[u8],
+        batch: Batch<S, T>,
+        fsync: bool,
+    ) -> Result<((u64, u64), usize)> {
+        let (file_path, fd, batches, active, till) = match &mut self.inner {
             InnerJournal::Active {
                 file_path,
                 fd,
                 batches,
                 active,
-            } => Ok((file_path, fd, batches, active)),
+                till,
+            } => Ok((file_path, fd, batches, active, till)),
             _ => err_at!(Fatal, msg: format!("unreachable")),
         }?;
 
-        write_file!(fd, &buffer, file_path.clone(), "wal-flush2")?;
+        write_journal(fd, file_path, *till, buffer, fsync, "wal-flush2")?;
+        let n: u64 = convert_at!(buffer.len())?;
+        *till += n;
         if fsync {
-            err_at!(IoError, fd.sync_all())?;
+            trace_event!(
+                "wal_fsync",
+                seqno = batch.to_last_seqno().unwrap_or(0),
+                bytes = n,
+            );
         }
+        let seqnos = to_seqnos(&batch);
         batches.push(batch);
         *active = Batch::default_active();
 
-        Ok(())
+        Ok((seqnos, buffer.len()))
+    }
+}
+
+// outcome of flushing the active batch, on `Done` carries the seqno
+// range and the number of bytes flushed.
+enum Flush<S, T> {
+    None,
+    Done((u64, u64), usize),
+    Rotate(Vec<u8>, Batch<S, T>),
+}
+
+// return (start_seqno, last_seqno) of a flushed batch.
+fn to_seqnos<S, T>(batch: &Batch<S, T>) -> (u64, u64) {
+    let start_seqno = batch.to_first_seqno().unwrap_or(0);
+    (start_seqno, batch.to_last_seqno().unwrap_or(start_seqno))
+}
+
+// append `buffer` at `fpos` and sync the journal file if `fsync` is true.
+// With `io-uring` feature, on linux, write and fdatasync are chained and
+// submitted together, falling back to regular file-io if io_uring is
+// not supported by the kernel.
+fn write_journal(
+    fd: &mut fs::File,
+    file_path: &ffi::OsString,
+    fpos: u64,
+    buffer: &[u8],
+    fsync: bool,
+    msg: &str,
+) -> Result<()> {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    {
+        if let Some(res) = dlog_uring::write_at(fd, fpos, buffer, fsync) {
+            return res;
+        }
+    }
+
+    err_at!(IoError, fd.seek(io::SeekFrom::Start(fpos)))?;
+    write_file!(fd, buffer, file_path.clone(), msg)?;
+    if fsync {
+        err_at!(IoError, fd.sync_all())?;
+    }
+    Ok(())
+}
+
+// Sparse index over batches in a journal, persisted as trailer when the
+// journal is archived. Indexes the start_seqno of every
+// JOURNAL_INDEX_INTERVAL batch to its file-position.
+//
+// +----------------------------------------------------------------+
+// |                 n x {start_seqno, fpos} entries                |
+// +----------------------------------------------------------------+
+// |                            last_seqno                          |
+// +----------------------------------------------------------------+
+// |                till, file-position where batches end           |
+// +----------------------------------------------------------------+
+// |                                n                               |
+// +----------------------------------------------------------------+
+// |                         DLOG_INDEX_MARKER                      |
+// +----------------------------------------------------------------+
+// |                              length                            |
+// +----------------------------------------------------------------+
+//
+// NOTE: `length` value includes the 8-byte length-suffix.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct JournalIndex {
+    entries: Vec<(u64, u64)>,
+    last_seqno: Option<u64>,
+    till: u64,
+}
+
+impl JournalIndex {
+    fn new<S, T>(batches: &[Batch<S, T>], till: u64) -> Result<JournalIndex> {
+        let mut entries = vec![];
+        for batch in batches.iter().step_by(JOURNAL_INDEX_INTERVAL) {
+            match batch {
+                Batch::Refer {
+                    fpos, start_seqno, ..
+                } => entries.push((*start_seqno, *fpos)),
+                _ => err_at!(Fatal, msg: format!("unreachable"))?,
+            }
+        }
+        let last_seqno = batches.last().and_then(|b| b.to_last_seqno());
+
+        Ok(JournalIndex {
+            entries,
+            last_seqno,
+            till,
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        for (seqno, fpos) in self.entries.iter() {
+            buf.extend_from_slice(&seqno.to_be_bytes());
+            buf.extend_from_slice(&fpos.to_be_bytes());
+        }
+        buf.extend_from_slice(&self.last_seqno.unwrap_or(0).to_be_bytes());
+        buf.extend_from_slice(&self.till.to_be_bytes());
+        let n: u64 = convert_at!(self.entries.len())?;
+        buf.extend_from_slice(&n.to_be_bytes());
+        buf.extend_from_slice(DLOG_INDEX_MARKER.as_ref());
+
+        let length: u64 = convert_at!(buf.len() + 8)?;
+        buf.extend_from_slice(&length.to_be_bytes());
+        Ok(buf)
+    }
+
+    // read trailer from journal file of `len` bytes, return None if
+    // journal is not indexed.
+    fn read(fd: &mut fs::File, len: u64) -> Result<Option<JournalIndex>> {
+        let m: u64 = convert_at!(32 + DLOG_INDEX_MARKER.len())?;
+        if len < m {
+            return Ok(None);
+        }
+        let buf = read_file!(fd, len - 8, 8, "journal index length")?;
+        let length = u64::from_be_bytes(array_at!(buf[..8])?);
+        if length < m || length > len {
+            return Ok(None);
+        }
+
+        let buf = read_file!(fd, len - length, length, "journal index")?;
+        let z = buf.len() - 8;
+        let (a, z) = (z - DLOG_INDEX_MARKER.len(), z);
+        if &buf[a..z] != DLOG_INDEX_MARKER.as_slice() {
+            return Ok(None);
+        }
+
+        let n: usize = convert_at!(u64::from_be_bytes(array_at!(buf[a - 8..a])?))?;
+        let till = u64::from_be_bytes(array_at!(buf[a - 16..a - 8])?);
+        let last_seqno = u64::from_be_bytes(array_at!(buf[a - 24..a - 16])?);
+        if (n * 16) != (a - 24) || till != (len - length) {
+            return Ok(None);
+        }
+
+        let mut entries = vec![];
+        for i in 0..n {
+            let x = i * 16;
+            let seqno = u64::from_be_bytes(array_at!(buf[x..x + 8])?);
+            let fpos = u64::from_be_bytes(array_at!(buf[x + 8..x + 16])?);
+            entries.push((seqno, fpos));
+        }
+        let last_seqno = if n > 0 { Some(last_seqno) } else { None };
+
+        Ok(Some(JournalIndex {
+            entries,
+            last_seqno,
+            till,
+        }))
+    }
+
+    // file-position of the last indexed batch starting at or before
+    // `seqno`, batches before it carry entries only upto `seqno`.
+    fn to_fpos(&self, seqno: u64) -> u64 {
+        let iter = self.entries.iter().take_while(|(s, _)| *s <= seqno);
+        match iter.last() {
+            Some((_, fpos)) => *fpos,
+            None => self.entries.first().map(|(_, fpos)| *fpos).unwrap_or(0),
+        }
     }
 }
 
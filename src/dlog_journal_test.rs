@@ -56,7 +56,13 @@ fn test_journal() {
             journal.add_entry(DEntry::new(seqno, op)).unwrap();
         }
         let fsync: bool = rng.gen();
-        assert_eq!(journal.flush1(limit, fsync).unwrap().is_none(), true);
+        match journal.flush1(limit, fsync).unwrap() {
+            Flush::Done(seqnos, _) => {
+                let start_seqno = (i * 1000) as u64 + 1;
+                assert_eq!(seqnos, (start_seqno, start_seqno + 999));
+            }
+            _ => panic!("expected flush"),
+        }
     }
 
     assert_eq!(journal.to_last_seqno().unwrap(), Some(100_000));
@@ -69,13 +75,13 @@ fn test_journal() {
     );
     assert_eq!(journal.is_cold(), false);
 
+    let file_path = journal.to_file_path();
     let mut fd = {
-        let file_path = journal.to_file_path();
         let mut opts = fs::OpenOptions::new();
         opts.read(true).open(&file_path).unwrap()
     };
     for (i, batch) in journal.into_batches().unwrap().into_iter().enumerate() {
-        let batch = batch.into_active(&mut fd).unwrap();
+        let batch = batch.into_active(&mut fd, &file_path).unwrap();
         for (j, entry) in batch.into_entries().unwrap().into_iter().enumerate() {
             let (seqno, op) = entry.into_seqno_op();
             let ref_seqno = (i * 1000 + j) as u64 + 1;
@@ -87,6 +93,203 @@ fn test_journal() {
     }
 }
 
+#[test]
+fn test_journal_index() {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push("test-journal-index");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "journal".to_string();
+    let mut journal: Journal<wal::State, wal::Op<i64, i64>> =
+        Journal::new_active(dir.clone(), name.clone(), 1, 1).unwrap();
+    for i in 0..100 {
+        for j in 0..10 {
+            let op = wal::Op::<i64, i64>::new_set(10 * i + j, 20 + i);
+            let seqno = (i * 10 + j) as u64 + 1;
+            journal.add_entry(DEntry::new(seqno, op)).unwrap();
+        }
+        match journal.flush1(1_000_000_000, false).unwrap() {
+            Flush::Rotate(_, _) => panic!("unexpected rotate"),
+            _ => (),
+        }
+    }
+    let journal = journal.into_archive().unwrap();
+    let fname = {
+        let file_path = journal.to_file_path();
+        let fname = path::Path::new(&file_path).file_name().unwrap();
+        fname.to_os_string()
+    };
+
+    let load = || {
+        let (d, n) = (dir.clone(), name.clone());
+        Journal::<wal::State, wal::Op<i64, i64>>::new_archive(d, n, 1, fname.clone()).unwrap()
+    };
+    let to_seqnos = |journal: Journal<wal::State, wal::Op<i64, i64>>, seqno: u64| {
+        let file_path = journal.to_file_path();
+        let mut fd = {
+            let mut opts = fs::OpenOptions::new();
+            opts.read(true).open(&file_path).unwrap()
+        };
+        let mut seqnos = vec![];
+        for batch in journal.into_batches_from(seqno).unwrap().into_iter() {
+            let batch = batch.into_active(&mut fd, &file_path).unwrap();
+            for entry in batch.into_entries().unwrap().into_iter() {
+                seqnos.push(entry.into_seqno_op().0);
+            }
+        }
+        seqnos
+    };
+
+    let journal = load();
+    match &journal.inner {
+        InnerJournal::Archive { batches, index, .. } => {
+            assert!(batches.is_empty());
+            assert_eq!(index.as_ref().unwrap().entries.len(), 7);
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(journal.to_last_seqno().unwrap(), Some(1000));
+
+    let seqnos = to_seqnos(journal, 0);
+    assert_eq!(seqnos, (1..=1000).collect::<Vec<u64>>());
+
+    // seek shall skip all batches before the indexed batch.
+    let seqnos = to_seqnos(load(), 555);
+    assert_eq!(seqnos[0], 481);
+    assert_eq!(seqnos.last().cloned(), Some(1000));
+}
+
+#[test]
+fn test_journal_zero_tail() {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push("test-journal-zero-tail");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "journal".to_string();
+    let preallocate = |file_path: &ffi::OsString, n: u64| {
+        let mut opts = fs::OpenOptions::new();
+        let fd = opts.write(true).open(file_path).unwrap();
+        fd.set_len(n).unwrap();
+    };
+    let to_seqnos = |journal: Journal<wal::State, wal::Op<i64, i64>>| {
+        let file_path = journal.to_file_path();
+        let mut fd = util::open_file_r(&file_path).unwrap();
+        let mut seqnos = vec![];
+        for batch in journal.into_batches().unwrap().into_iter() {
+            let batch = batch.into_active(&mut fd, &file_path).unwrap();
+            for entry in batch.into_entries().unwrap().into_iter() {
+                seqnos.push(entry.into_seqno_op().0);
+            }
+        }
+        seqnos
+    };
+
+    for (num, archive) in [(1, true), (2, false)].iter() {
+        let mut journal: Journal<wal::State, wal::Op<i64, i64>> =
+            Journal::new_active(dir.clone(), name.clone(), 1, *num).unwrap();
+        let file_path = journal.to_file_path();
+        preallocate(&file_path, 1024 * 1024);
+
+        for i in 0..10 {
+            for j in 0..10 {
+                let op = wal::Op::<i64, i64>::new_set(10 * i + j, 20 + i);
+                let seqno = (i * 10 + j) as u64 + 1;
+                journal.add_entry(DEntry::new(seqno, op)).unwrap();
+            }
+            match journal.flush1(1_000_000_000, false).unwrap() {
+                Flush::Rotate(_, _) => panic!("unexpected rotate"),
+                _ => (),
+            }
+        }
+
+        if *archive {
+            // trailer shall end the file, dropping the preallocated tail.
+            journal.into_archive().unwrap();
+            let len = fs::metadata(&file_path).unwrap().len();
+            assert!(len < 1024 * 1024, "{}", len);
+        } else {
+            // crash, leaving behind the preallocated tail.
+            mem::drop(journal);
+            let len = fs::metadata(&file_path).unwrap().len();
+            assert_eq!(len, 1024 * 1024);
+        }
+
+        let fname = path::Path::new(&file_path).file_name().unwrap();
+        let (d, n, f) = (dir.clone(), name.clone(), fname.to_os_string());
+        let journal = Journal::<wal::State, wal::Op<i64, i64>>::new_archive(d, n, 1, f).unwrap();
+        assert_eq!(journal.to_last_seqno().unwrap(), Some(100));
+        assert_eq!(to_seqnos(journal), (1..=100).collect::<Vec<u64>>());
+    }
+}
+
+#[test]
+fn test_journal_torn_batch() {
+    let dir = {
+        let mut dir = path::PathBuf::new();
+        dir.push(std::env::temp_dir());
+        dir.push("test-journal-torn-batch");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "journal".to_string();
+    let mut journal: Journal<wal::State, wal::Op<i64, i64>> =
+        Journal::new_active(dir.clone(), name.clone(), 1, 1).unwrap();
+    let file_path = journal.to_file_path();
+
+    let mut good_len = 0;
+    for i in 0..10 {
+        good_len = fs::metadata(&file_path).unwrap().len();
+        for j in 0..10 {
+            let op = wal::Op::<i64, i64>::new_set(10 * i + j, 20 + i);
+            let seqno = (i * 10 + j) as u64 + 1;
+            journal.add_entry(DEntry::new(seqno, op)).unwrap();
+        }
+        match journal.flush1(1_000_000_000, false).unwrap() {
+            Flush::Rotate(_, _) => panic!("unexpected rotate"),
+            _ => (),
+        }
+    }
+    mem::drop(journal);
+
+    // nothing to repair.
+    type J = Journal<wal::State, wal::Op<i64, i64>>;
+    assert_eq!(J::truncate_to_last_good(&file_path).unwrap(), (0, 0));
+
+    // crash while flushing the last batch.
+    let len = fs::metadata(&file_path).unwrap().len();
+    {
+        let fd = util::open_file_pw(&file_path).unwrap();
+        fd.set_len(len - 5).unwrap();
+    }
+    let (n, entries) = J::truncate_to_last_good(&file_path).unwrap();
+    assert_eq!(n, len - 5 - good_len);
+    assert_eq!(entries, 10);
+    assert_eq!(fs::metadata(&file_path).unwrap().len(), good_len);
+
+    // torn batch is repaired on load.
+    {
+        let mut fd = util::open_file_w(&file_path).unwrap();
+        fd.write_all(&[0xAB; 100]).unwrap();
+    }
+    let fname = path::Path::new(&file_path).file_name().unwrap();
+    let (d, n, f) = (dir.clone(), name.clone(), fname.to_os_string());
+    let journal = J::new_archive(d, n, 1, f).unwrap();
+    assert_eq!(journal.to_last_seqno().unwrap(), Some(90));
+    assert_eq!(fs::metadata(&file_path).unwrap().len(), good_len);
+}
+
 #[test]
 fn test_shard() {
     let seed: u128 = random();
@@ -182,13 +385,13 @@ fn test_shard() {
 
         let mut entries = vec![];
         for journal in journals.into_iter() {
+            let file_path = journal.to_file_path();
             let mut fd = {
-                let file_path = journal.to_file_path();
                 let mut opts = fs::OpenOptions::new();
                 opts.read(true).open(&file_path).unwrap()
             };
             for batch in journal.into_batches().unwrap().into_iter() {
-                let batch = batch.into_active(&mut fd).unwrap();
+                let batch = batch.into_active(&mut fd, &file_path).unwrap();
                 for entry in batch.into_entries().unwrap().into_iter() {
                     entries.push(entry);
                 }
@@ -3,4 +3,8 @@ lazy_static! {
         let marker = "செய்வன திருந்தச் செய்";
         marker.as_bytes().to_vec()
     };
+    pub(crate) static ref DLOG_INDEX_MARKER: Vec<u8> = {
+        let marker = "எண்ணித் துணிக கருமம்";
+        marker.as_bytes().to_vec()
+    };
 }
@@ -0,0 +1,89 @@
+// Module implement io_uring backend for journal appends, compiled only
+// with `io-uring` feature on linux. Append and fdatasync are chained,
+// using IO_LINK, and submitted to the kernel with a single syscall.
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+use std::{cell::RefCell, convert::TryInto, fs, io, os::unix::io::AsRawFd};
+
+use crate::{core::Result, error::Error};
+
+// submission queue depth, a flush needs at the most two entries.
+const RING_DEPTH: u32 = 8;
+
+const WRITE_DATA: u64 = 1;
+const SYNC_DATA: u64 = 2;
+
+thread_local! {
+    // journals are flushed from their shard thread, ring is setup once
+    // per thread. None if kernel does not support io_uring.
+    static RING: RefCell<Option<Option<IoUring>>> = RefCell::new(None);
+}
+
+// Write `buffer` at `fpos` and if `fsync` is true, fdatasync the file.
+// Return None if io_uring is not available, caller shall fallback to
+// regular file-io.
+pub(crate) fn write_at(fd: &fs::File, fpos: u64, buffer: &[u8], fsync: bool) -> Option<Result<()>> {
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        let ring = ring.get_or_insert_with(|| IoUring::new(RING_DEPTH).ok());
+        ring.as_mut()
+            .map(|ring| do_write_at(ring, fd, fpos, buffer, fsync))
+    })
+}
+
+fn do_write_at(
+    ring: &mut IoUring,
+    fd: &fs::File,
+    fpos: u64,
+    buffer: &[u8],
+    fsync: bool,
+) -> Result<()> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let len: u32 = convert_at!(buffer.len())?;
+    let offset = convert_at!(fpos)?;
+
+    let write = opcode::Write::new(fd, buffer.as_ptr(), len)
+        .offset(offset)
+        .build()
+        .user_data(WRITE_DATA);
+    let entries = if fsync {
+        let sync = opcode::Fsync::new(fd)
+            .flags(types::FsyncFlags::DATASYNC)
+            .build()
+            .user_data(SYNC_DATA);
+        vec![write.flags(squeue::Flags::IO_LINK), sync]
+    } else {
+        vec![write]
+    };
+    for entry in entries.iter() {
+        // buffer outlives the submission, we wait for completion below.
+        if unsafe { ring.submission().push(entry) }.is_err() {
+            err_at!(Fatal, msg: format!("io_uring submission queue full"))?;
+        }
+    }
+    let n_entries = entries.len();
+    err_at!(IoError, ring.submit_and_wait(n_entries))?;
+
+    // drain all completions, before reporting error, so that the ring
+    // is left clean for the next flush.
+    let cqes: Vec<(u64, i32)> = ring
+        .completion()
+        .map(|cqe| (cqe.user_data(), cqe.result()))
+        .collect();
+    if cqes.len() != n_entries {
+        let msg = format!("io_uring completions {}/{}", cqes.len(), n_entries);
+        err_at!(Fatal, msg: msg)?;
+    }
+    for (user_data, res) in cqes.into_iter() {
+        if res < 0 {
+            let err = io::Error::from_raw_os_error(-res);
+            err_at!(IoError, Err(err))?;
+        } else if user_data == WRITE_DATA && (res as u32) != len {
+            let msg = format!("io_uring journal write, {}/{}", len, res);
+            err_at!(Fatal, msg: msg)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use std::{
     borrow::Borrow,
     convert::TryInto,
-    fs,
+    ffi, fs,
     mem::ManuallyDrop,
     ops::Bound,
     sync::atomic::{AtomicBool, Ordering::SeqCst},
@@ -17,7 +17,7 @@ use crate::{
     scans,
     wal::Wal,
 };
-use crate::{error::Error, vlog};
+use crate::{error::Error, robt_cipher::FileCipher, robt_compress::ValueDict, vlog};
 
 #[derive(Clone)]
 pub(crate) struct Delta<V>
@@ -162,6 +162,11 @@ where
 }
 
 pub(crate) enum Value<V> {
+    // native value held inline, refer to Value::INLINE_SIZE.
+    N {
+        value: V,
+        seqno: u64,
+    },
     U {
         value: ManuallyDrop<Box<vlog::Value<V>>>,
         is_reclaim: AtomicBool,
@@ -187,6 +192,10 @@ where
                 is_reclaim: AtomicBool::new(is_reclaim.load(SeqCst)),
                 seqno: *seqno,
             },
+            Value::N { value, seqno } => Value::N {
+                value: value.clone(),
+                seqno: *seqno,
+            },
             Value::D { seqno } => Value::D { seqno: *seqno },
         }
     }
@@ -210,6 +219,10 @@ impl<V> Value<V>
 where
     V: Clone,
 {
+    /// Values whose size is no more than a pointer are held inline in the
+    /// entry, avoiding a heap allocation for every create/update.
+    pub(crate) const INLINE_SIZE: usize = std::mem::size_of::<usize>();
+
     pub(crate) fn new_upsert(v: Box<vlog::Value<V>>, seqno: u64) -> Value<V> {
         Value::U {
             value: ManuallyDrop::new(v),
@@ -219,6 +232,9 @@ where
     }
 
     pub(crate) fn new_upsert_value(value: V, seqno: u64) -> Value<V> {
+        if std::mem::size_of::<V>() <= Self::INLINE_SIZE {
+            return Value::N { value, seqno };
+        }
         Value::U {
             value: ManuallyDrop::new(Box::new(vlog::Value::new_native(value))),
             is_reclaim: AtomicBool::new(true),
@@ -258,6 +274,7 @@ where
 {
     pub(crate) fn to_native_value(&self) -> Option<V> {
         match &self {
+            Value::N { value, .. } => Some(value.clone()),
             Value::U { value, .. } => value.to_native_value(),
             Value::D { .. } => None,
         }
@@ -265,6 +282,7 @@ where
 
     pub(crate) fn to_seqno(&self) -> u64 {
         match self {
+            Value::N { seqno, .. } => *seqno,
             Value::U { seqno, .. } => *seqno,
             Value::D { seqno } => *seqno,
         }
@@ -272,7 +290,7 @@ where
 
     pub(crate) fn is_deleted(&self) -> bool {
         match self {
-            Value::U { .. } => false,
+            Value::N { .. } | Value::U { .. } => false,
             Value::D { .. } => true,
         }
     }
@@ -293,6 +311,8 @@ where
         use std::mem::size_of;
 
         Ok(match self {
+            // inline value is accounted as part of the entry.
+            Value::N { value, .. } => value.footprint()?,
             Value::U { value, .. } => {
                 let size: isize = convert_at!(size_of::<V>())?;
                 size + value.footprint()?
@@ -316,6 +336,8 @@ where
     key: K,
     value: Value<V>,
     deltas: Vec<Delta<V>>,
+    // application defined flags, persisted in disk index.
+    flags: u8,
 }
 
 impl<K, V> Borrow<K> for Entry<K, V>
@@ -364,6 +386,7 @@ where
             key,
             value,
             deltas: vec![],
+            flags: 0,
         }
     }
 
@@ -372,12 +395,21 @@ where
             key: self.key.clone(),
             value: self.value.mvcc_clone(copyval),
             deltas: self.deltas.clone(),
+            flags: self.flags,
         }
     }
 
+    pub(crate) fn set_key(&mut self, key: K) {
+        self.key = key;
+    }
+
     pub(crate) fn set_deltas(&mut self, deltas: Vec<Delta<V>>) {
         self.deltas = deltas;
     }
+
+    pub(crate) fn set_flags(&mut self, flags: u8) {
+        self.flags = flags;
+    }
 }
 
 // Entry accessor methods.
@@ -412,32 +444,27 @@ where
     fn prepend_version_lsm(&mut self, nentry: Self) -> Result<isize> {
         let delta = match &self.value {
             Value::D { seqno } => Ok(Delta::new_delete(*seqno)),
-            Value::U { value, seqno, .. } if !value.is_reference() => {
+            value if !value.is_reference() => {
                 // compute delta
+                let (old, seqno) = (value.to_native_value().unwrap(), value.to_seqno());
                 match &nentry.value {
                     Value::D { .. } => {
-                        let diff: <V as Diff>::D = {
-                            let v = value.to_native_value().unwrap();
-                            From::from(v)
-                        };
+                        let diff: <V as Diff>::D = From::from(old);
                         {
                             let v = vlog::Delta::new_native(diff);
-                            Ok(Delta::new_upsert(v, *seqno))
+                            Ok(Delta::new_upsert(v, seqno))
                         }
                     }
-                    Value::U { value: nvalue, .. } => {
-                        let dff = nvalue
-                            .to_native_value()
-                            .unwrap()
-                            .diff(&value.to_native_value().unwrap());
+                    nvalue => {
+                        let dff = nvalue.to_native_value().unwrap().diff(&old);
                         {
                             let v = vlog::Delta::new_native(dff);
-                            Ok(Delta::new_upsert(v, *seqno))
+                            Ok(Delta::new_upsert(v, seqno))
                         }
                     }
                 }
             }
-            Value::U { .. } => {
+            _ => {
                 //
                 err_at!(Fatal, msg: format!("Entry.prepend_version_lsm()"))
             }
@@ -464,15 +491,16 @@ where
                 self.deltas.insert(0, Delta::new_delete(*seqno));
                 Ok(())
             }
-            Value::U { value, seqno, .. } if !value.is_reference() => {
+            value if !value.is_reference() => {
                 let delta = {
                     let d: <V as Diff>::D = From::from(value.to_native_value().unwrap());
                     vlog::Delta::new_native(d)
                 };
-                self.deltas.insert(0, Delta::new_upsert(delta, *seqno));
+                let seqno = value.to_seqno();
+                self.deltas.insert(0, Delta::new_upsert(delta, seqno));
                 Ok(())
             }
-            Value::U { .. } => err_at!(Fatal, msg: format!("Entry.delete()")),
+            _ => err_at!(Fatal, msg: format!("Entry.delete()")),
         }?;
 
         self.value = Value::new_delete(seqno);
@@ -612,11 +640,29 @@ where
                 key: self.key.clone(),
                 value: self.value.clone(),
                 deltas: Default::default(),
+                flags: self.flags,
             }),
             curval: None,
             deltas: Some(self.to_deltas().into_iter()),
         }
     }
+
+    /// Return an iterator of `(seqno, value)` for all existing versions
+    /// of this entry, from newest to oldest. Deltas are applied in order
+    /// and value is None for versions that mark a delete.
+    pub fn versions_iter(&self) -> impl Iterator<Item = (u64, Option<V>)> {
+        self.versions().map(|e| (e.to_seqno(), e.to_native_value()))
+    }
+
+    /// Materialize the value as of `seqno`, that is, the value of the
+    /// newest version whose seqno is less than or equal to `seqno`.
+    /// Return None if entry was deleted as of `seqno`, or if entry did
+    /// not exist, or if that version is not available in this entry.
+    pub fn to_version(&self, seqno: u64) -> Option<V> {
+        self.versions_iter()
+            .find(|(s, _)| *s <= seqno)
+            .and_then(|(_, value)| value)
+    }
 }
 
 impl<K, V> Entry<K, V>
@@ -648,6 +694,7 @@ where
             // println!("xmerge {} {}", ne.to_seqno(), ne.is_deleted());
             b.prepend_version(ne, true /* lsm */)?;
         }
+        b.flags = a.flags;
         Ok(b)
     }
 
@@ -691,32 +738,50 @@ where
     V: Default + Clone + Diff + Serialize,
     <V as Diff>::D: Default + Serialize,
 {
-    pub(crate) fn fetch_value(&mut self, fd: &mut fs::File) -> Result<()> {
+    // return the number of values fetched from value-log.
+    pub(crate) fn fetch_value(
+        &mut self,
+        file: &ffi::OsStr,
+        fds: &[fs::File],
+        cipher: Option<&FileCipher>,
+        dict: Option<&ValueDict>,
+    ) -> Result<usize> {
         Ok(match &self.value {
             Value::U { value, seqno, .. } => match value.to_reference() {
                 Some((fpos, len, _seqno)) => {
-                    self.value =
-                        Value::new_upsert(Box::new(vlog::fetch_value(fpos, len, fd)?), *seqno);
+                    let value = vlog::fetch_value(fpos, len, file, fds, cipher, dict)?;
+                    self.value = Value::new_upsert(Box::new(value), *seqno);
+                    1
                 }
-                _ => (),
+                _ => 0,
             },
-            _ => (),
+            _ => 0,
         })
     }
 
-    pub(crate) fn fetch_deltas(&mut self, fd: &mut fs::File) -> Result<()> {
+    // return the number of deltas fetched from value-log.
+    pub(crate) fn fetch_deltas(
+        &mut self,
+        file: &ffi::OsStr,
+        fds: &[fs::File],
+        cipher: Option<&FileCipher>,
+        dict: Option<&ValueDict>,
+    ) -> Result<usize> {
+        let mut n = 0;
         for delta in self.deltas.iter_mut() {
             match delta.data {
                 InnerDelta::U {
                     delta: vlog::Delta::Reference { fpos, length, .. },
                     seqno,
                 } => {
-                    *delta = Delta::new_upsert(vlog::fetch_delta(fpos, length, fd)?, seqno);
+                    let diff = vlog::fetch_delta(fpos, length, file, fds, cipher, dict)?;
+                    *delta = Delta::new_upsert(diff, seqno);
+                    n += 1;
                 }
                 _ => (),
             }
         }
-        Ok(())
+        Ok(n)
     }
 }
 
@@ -766,6 +831,7 @@ where
     #[inline]
     pub fn to_seqno(&self) -> u64 {
         match self.value {
+            Value::N { seqno, .. } => seqno,
             Value::U { seqno, .. } => seqno,
             Value::D { seqno, .. } => seqno,
         }
@@ -777,6 +843,7 @@ where
     #[inline]
     pub fn to_seqno_state(&self) -> (bool, u64) {
         match self.value {
+            Value::N { seqno, .. } => (true, seqno),
             Value::U { seqno, .. } => (true, seqno),
             Value::D { seqno, .. } => (false, seqno),
         }
@@ -787,6 +854,14 @@ where
     pub fn is_deleted(&self) -> bool {
         self.value.is_deleted()
     }
+
+    /// Return application defined flags for this entry. Flags are
+    /// computed by the hook supplied to
+    /// [set_flags_hook][crate::robt::Builder::set_flags_hook]
+    /// and read back from the disk index, zero otherwise.
+    pub fn to_flags(&self) -> u8 {
+        self.flags
+    }
 }
 
 /// Iterate from newest to oldest _available_ version for this entry.
@@ -857,13 +932,13 @@ where
         (None, InnerDelta::U { delta, seqno }) => {
             // previous entry was a delete.
             let nv: V = From::from(delta.into_native_delta().unwrap());
-            let value = Value::new_upsert(Box::new(vlog::Value::new_native(nv.clone())), seqno);
+            let value = Value::new_upsert_value(nv.clone(), seqno);
             (value, Some(nv))
         }
         (Some(curval), InnerDelta::U { delta, seqno }) => {
             // this and previous entry are create/update.
             let nv = curval.merge(&delta.into_native_delta().unwrap());
-            let value = Value::new_upsert(Box::new(vlog::Value::new_native(nv.clone())), seqno);
+            let value = Value::new_upsert_value(nv.clone(), seqno);
             (value, Some(nv))
         }
     }
@@ -2,8 +2,13 @@
 //!
 //! Convertion traits, from other error types, like from std-lib, to `rdms`
 //! error are implemented in this module.
+//!
+//! [Error] implements the [std::error::Error] trait. Errors that involve
+//! file access can be wrapped as [Error::FileFail], carrying file-path,
+//! file-position and seqno, whichever is known, along with the
+//! underlying error as its `source()`.
 
-use std::ffi;
+use std::{error, ffi, fmt, result};
 
 /// Error enumerates over all possible errors cases in `rdms` package.
 #[derive(Debug)]
@@ -12,6 +17,9 @@ pub enum Error {
     Fatal(String),
     /// API / function not supported
     NotImplemented(String),
+    /// Operation is not supported by this index type, like an optional
+    /// trait method that is not implemented by the index.
+    NotSupported(String),
     /// Error because the value was not initialized as expected.
     UnInitialized(String),
     /// TimeFail, std time related API failed.
@@ -30,15 +38,34 @@ pub enum Error {
     DecodeFail(String),
     /// Returned by disk index or dlog that provide durability support.
     InvalidFile(String),
+    /// Meta-items at the tip of an index file are corrupted, like
+    /// header lengths that overflow the file or out of limits.
+    CorruptedMeta(String),
+    /// Block at file-position `fpos` could not be read or decoded,
+    /// returned by iterators in skip-corrupt mode, refer to
+    /// [with_skip_corrupt][crate::robt::Iter::with_skip_corrupt].
+    CorruptBlock { fpos: u64 },
     /// Error converting from one type to another.
     ConversionFail(String),
     /// IO error from std::io
     IoError(String),
+    /// Failure while accessing a file, along with the file's path,
+    /// file-position and seqno, whichever is known. Underlying error
+    /// is available as `source`.
+    FileFail {
+        file: Option<ffi::OsString>,
+        fpos: Option<u64>,
+        seqno: Option<u64>,
+        source: Box<Error>,
+    },
 
     /// Supplied key is not found in the index.
     KeyNotFound,
     /// Index is empty
     EmptyIndex,
+    /// Operation could not complete within the caller supplied
+    /// deadline, refer to [get_deadline][crate::core::Reader::get_deadline].
+    TimedOut,
     /// Can be returned by set_cas() API when:
     /// * In non-lsm mode, requested entry is missing but specified
     ///   CAS is not ZERO. Note that this combination is an alias for
@@ -48,6 +75,9 @@ pub enum Error {
     ///   last modified sequence-number.
     /// * Requested entry's last modified sequence-number does not
     ///   match with specified CAS.
+    ///
+    /// Above conditions apply for the default policy, refer to
+    /// [CasPolicy][crate::core::CasPolicy] for other policies.
     InvalidCAS(u64),
     /// Key size, after serializing, has exceeded the configured,
     /// (or hard coded) limit.
@@ -60,21 +90,103 @@ pub enum Error {
     /// Return list of files that needs to be purged.
     PurgeFiles(Vec<ffi::OsString>),
 
+    // internal control flow, never returned by public APIs.
     #[doc(hidden)]
-    // internal error, given key is less than the entire data set.
-    __LessThan,
-    // internal error, z-block of robt index has overflowed.
-    #[doc(hidden)]
-    __ZBlockOverflow(usize),
-    // inernal error, m-block of robt index has overflowed.
-    #[doc(hidden)]
-    __MBlockOverflow(usize),
-    // internal error, iteration exhausted in robt index's m-block entries.
-    #[doc(hidden)]
-    __MBlockExhausted(usize),
-    // internal error, iteration exhausted in robt index's z-block entries.
-    #[doc(hidden)]
-    __ZBlockExhausted(usize),
+    Internal(Internal),
+}
+
+/// Internal control flow between modules, shall not be matched upon
+/// by applications.
+#[doc(hidden)]
+#[derive(Debug, PartialEq)]
+pub enum Internal {
+    // given key is less than the entire data set.
+    LessThan,
+    // z-block of robt index has overflowed.
+    ZBlockOverflow(usize),
+    // m-block of robt index has overflowed.
+    MBlockOverflow(usize),
+    // iteration exhausted in robt index's m-block entries.
+    MBlockExhausted(usize),
+    // iteration exhausted in robt index's z-block entries.
+    ZBlockExhausted(usize),
+}
+
+impl Error {
+    // wrap this error with file context, refer to Error::FileFail.
+    pub(crate) fn into_file_fail(
+        self,
+        file: Option<ffi::OsString>,
+        fpos: Option<u64>,
+        seqno: Option<u64>,
+    ) -> Error {
+        Error::FileFail {
+            file,
+            fpos,
+            seqno,
+            source: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        use Error::*;
+
+        match self {
+            Fatal(msg) => write!(f, "Fatal: {}", msg),
+            NotImplemented(msg) => write!(f, "NotImplemented: {}", msg),
+            NotSupported(msg) => write!(f, "NotSupported: {}", msg),
+            UnInitialized(msg) => write!(f, "UnInitialized: {}", msg),
+            TimeFail(msg) => write!(f, "TimeFail: {}", msg),
+            IPCFail(msg) => write!(f, "IPCFail: {}", msg),
+            SystemFail(msg) => write!(f, "SystemFail: {}", msg),
+            InvalidInput(msg) => write!(f, "InvalidInput: {}", msg),
+            APIMisuse(msg) => write!(f, "APIMisuse: {}", msg),
+            DecodeFail(msg) => write!(f, "DecodeFail: {}", msg),
+            InvalidFile(msg) => write!(f, "InvalidFile: {}", msg),
+            CorruptedMeta(msg) => write!(f, "CorruptedMeta: {}", msg),
+            CorruptBlock { fpos } => write!(f, "CorruptBlock: at fpos {}", fpos),
+            ConversionFail(msg) => write!(f, "ConversionFail: {}", msg),
+            IoError(msg) => write!(f, "IoError: {}", msg),
+            FileFail {
+                file,
+                fpos,
+                seqno,
+                source,
+            } => {
+                write!(f, "FileFail:")?;
+                if let Some(file) = file {
+                    write!(f, " file:{:?}", file)?;
+                }
+                if let Some(fpos) = fpos {
+                    write!(f, " fpos:{}", fpos)?;
+                }
+                if let Some(seqno) = seqno {
+                    write!(f, " seqno:{}", seqno)?;
+                }
+                write!(f, ", {}", source)
+            }
+            KeyNotFound => write!(f, "KeyNotFound"),
+            EmptyIndex => write!(f, "EmptyIndex"),
+            TimedOut => write!(f, "TimedOut"),
+            InvalidCAS(seqno) => write!(f, "InvalidCAS: {}", seqno),
+            KeySizeExceeded(n) => write!(f, "KeySizeExceeded: {}", n),
+            ValueSizeExceeded(n) => write!(f, "ValueSizeExceeded: {}", n),
+            DiffSizeExceeded(n) => write!(f, "DiffSizeExceeded: {}", n),
+            PurgeFiles(files) => write!(f, "PurgeFiles: {:?}", files),
+            Error::Internal(val) => write!(f, "Internal: {:?}", val),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::FileFail { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 #[macro_export]
@@ -135,12 +247,12 @@ macro_rules! array_at {
 
 impl PartialEq for Error {
     fn eq(&self, other: &Error) -> bool {
-        use Error::InvalidCAS;
-        use Error::InvalidFile;
+        use Error::{InvalidCAS, InvalidFile};
 
         match (self, other) {
             (InvalidCAS(x), InvalidCAS(y)) => x == y,
             (InvalidFile(s1), InvalidFile(s2)) => s1 == s2,
+            (Error::Internal(x), Error::Internal(y)) => x == y,
             _ => false,
         }
     }
@@ -0,0 +1,388 @@
+//! Module `heatmap` implement approximate tracking of hot keys.
+//!
+//! Access counts are maintained in a [count-min sketch][cms], which
+//! over-estimates but never under-estimates the count for a key. Along
+//! with the sketch, a bounded set of candidate keys with the highest
+//! estimated counts are retained, which is the top-K answered by
+//! [Heatmap::to_hot_keys].
+//!
+//! Tracking is disabled by default, it can be toggled at runtime via
+//! [Heatmap::set_enabled], even while readers are actively recording
+//! into the heatmap. Read operations can be tracked by wrapping any
+//! [Reader] implementation with [HeatReader].
+//!
+//! [cms]: https://en.wikipedia.org/wiki/Count%E2%80%93min_sketch
+
+use std::{
+    borrow::Borrow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    marker,
+    ops::RangeBounds,
+    sync::{
+        self,
+        atomic::{AtomicBool, Ordering::SeqCst},
+        Arc, MutexGuard,
+    },
+    time,
+};
+
+use crate::{
+    core::{Diff, Entry, IndexIter, Reader, Result},
+    error::Error,
+};
+
+/// Default number of counters in each row of the sketch.
+pub const WIDTH: usize = 4096;
+/// Default number of rows in the sketch, each row is indexed by an
+/// independent hash of the key.
+pub const DEPTH: usize = 4;
+/// Default number of candidate keys tracked for top-K.
+pub const CAPACITY: usize = 128;
+
+/// Approximate access counter for keys, refer module documentation
+/// for details.
+pub struct Heatmap<K> {
+    enabled: AtomicBool,
+    inner: sync::Mutex<Inner<K>>,
+}
+
+struct Inner<K> {
+    width: usize,
+    rows: Vec<Vec<u64>>,
+    capacity: usize,
+    hot: Vec<(K, u64)>,
+}
+
+impl<K> Default for Heatmap<K> {
+    fn default() -> Self {
+        Heatmap::new(WIDTH, DEPTH, CAPACITY)
+    }
+}
+
+impl<K> Heatmap<K> {
+    /// Create a new heatmap, with a sketch of `depth` rows each having
+    /// `width` counters, tracking upto `capacity` hot keys. Heatmap is
+    /// created in disabled state.
+    pub fn new(width: usize, depth: usize, capacity: usize) -> Heatmap<K> {
+        let width = std::cmp::max(width, 1);
+        let depth = std::cmp::max(depth, 1);
+        Heatmap {
+            enabled: AtomicBool::new(false),
+            inner: sync::Mutex::new(Inner {
+                width,
+                rows: vec![vec![0; width]; depth],
+                capacity,
+                hot: Vec::with_capacity(capacity),
+            }),
+        }
+    }
+
+    /// Enable or disable tracking. Counts collected so far are retained,
+    /// use [Heatmap::reset] to clear them.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, SeqCst);
+    }
+
+    /// Return whether tracking is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(SeqCst)
+    }
+
+    /// Clear all counts and tracked keys.
+    pub fn reset(&self) -> Result<()> {
+        let mut inner = self.as_inner()?;
+        inner
+            .rows
+            .iter_mut()
+            .for_each(|row| row.iter_mut().for_each(|c| *c = 0));
+        inner.hot.truncate(0);
+        Ok(())
+    }
+
+    fn as_inner(&self) -> Result<MutexGuard<Inner<K>>> {
+        match self.inner.lock() {
+            Ok(inner) => Ok(inner),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+impl<K> Heatmap<K>
+where
+    K: Clone + Ord,
+{
+    /// Record an access for `key`. `found` is the key as present in the
+    /// index, only found keys are candidates for top-K, while every
+    /// access is counted. NOOP if heatmap is disabled.
+    pub fn touch<Q>(&self, key: &Q, found: Option<&K>) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let mut inner = self.as_inner()?;
+        let count = inner.increment(key);
+        match found {
+            Some(found) => inner.admit(found, count),
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// Return the estimated access count for `key`.
+    pub fn to_count<Q>(&self, key: &Q) -> Result<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let inner = self.as_inner()?;
+        Ok(inner.estimate(key))
+    }
+
+    /// Return upto `k` hottest keys along with their estimated access
+    /// count, sorted from hottest to coldest.
+    pub fn to_hot_keys(&self, k: usize) -> Result<Vec<(K, u64)>> {
+        let inner = self.as_inner()?;
+        let mut hot = inner.hot.clone();
+        hot.sort_by(|x, y| y.1.cmp(&x.1).then_with(|| x.0.cmp(&y.0)));
+        hot.truncate(k);
+        Ok(hot)
+    }
+}
+
+impl<K> Inner<K>
+where
+    K: Clone + Ord,
+{
+    fn to_slot<Q>(&self, row: usize, key: &Q) -> usize
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        (hasher.finish() % (self.width as u64)) as usize
+    }
+
+    fn increment<Q>(&mut self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut count = u64::MAX;
+        for row in 0..self.rows.len() {
+            let slot = self.to_slot(row, key);
+            let c = &mut self.rows[row][slot];
+            *c = c.saturating_add(1);
+            count = std::cmp::min(count, *c);
+        }
+        count
+    }
+
+    fn estimate<Q>(&self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized,
+    {
+        (0..self.rows.len())
+            .map(|row| self.rows[row][self.to_slot(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    // admit `key` into the top-K candidates, evicting the coldest
+    // candidate if `key` is hotter.
+    fn admit(&mut self, key: &K, count: u64) {
+        if let Some(item) = self.hot.iter_mut().find(|item| item.0.eq(key)) {
+            item.1 = count;
+            return;
+        }
+        if self.hot.len() < self.capacity {
+            self.hot.push((key.clone(), count));
+            return;
+        }
+        let coldest = self
+            .hot
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, item)| item.1)
+            .map(|(i, item)| (i, item.1));
+        match coldest {
+            Some((i, c)) if c < count => self.hot[i] = (key.clone(), count),
+            _ => (),
+        }
+    }
+}
+
+/// Wrap a [Reader] handle and record key lookups into a shared
+/// [Heatmap]. Only point lookups are recorded, iterations are not.
+pub struct HeatReader<K, V, R>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    R: Reader<K, V>,
+{
+    heatmap: Arc<Heatmap<K>>,
+    reader: R,
+
+    _value: marker::PhantomData<V>,
+}
+
+impl<K, V, R> HeatReader<K, V, R>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    R: Reader<K, V>,
+{
+    /// Wrap `reader` to record lookups into `heatmap`.
+    pub fn new(reader: R, heatmap: Arc<Heatmap<K>>) -> HeatReader<K, V, R> {
+        HeatReader {
+            heatmap,
+            reader,
+
+            _value: marker::PhantomData,
+        }
+    }
+
+    /// Return the underlying reader handle.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
+    fn record<Q>(&self, key: &Q, res: &Result<Entry<K, V>>) -> Result<()>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        match res {
+            Ok(entry) => self.heatmap.touch(key, Some(entry.as_key())),
+            Err(Error::KeyNotFound) => self.heatmap.touch(key, None),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl<K, V, R> Reader<K, V> for HeatReader<K, V, R>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    R: Reader<K, V>,
+{
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let res = self.reader.get(key);
+        self.record(key, &res)?;
+        res
+    }
+
+    fn get_deadline<Q>(&mut self, key: &Q, timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let res = self.reader.get_deadline(key, timeout);
+        self.record(key, &res)?;
+        res
+    }
+
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        // entry is not materialized, access is counted but the key is
+        // not a candidate for top-K.
+        let res = self.reader.contains(key);
+        if res.is_ok() {
+            self.heatmap.touch(key, None)?;
+        }
+        res
+    }
+
+    fn to_range_deleted<Q>(&mut self, key: &Q) -> Result<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        // like contains(), access is counted but the key is not a
+        // candidate for top-K.
+        let res = self.reader.to_range_deleted(key);
+        if res.is_ok() {
+            self.heatmap.touch(key, None)?;
+        }
+        res
+    }
+
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        self.reader.iter()
+    }
+
+    fn range<'a, R1, Q>(&'a mut self, range: R1) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R1: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reader.range(range)
+    }
+
+    fn reverse<'a, R1, Q>(&'a mut self, range: R1) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R1: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reader.reverse(range)
+    }
+
+    fn range_count<'a, R1, Q>(&'a mut self, range: R1) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R1: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        // only point lookups are recorded, same as range().
+        self.reader.range_count(range)
+    }
+
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let res = self.reader.get_with_versions(key);
+        self.record(key, &res)?;
+        res
+    }
+
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.reader.iter_with_versions()
+    }
+
+    fn range_with_versions<'a, R1, Q>(&'a mut self, range: R1) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R1: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reader.range_with_versions(range)
+    }
+
+    fn reverse_with_versions<'a, R1, Q>(&'a mut self, range: R1) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R1: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reader.reverse_with_versions(range)
+    }
+}
+
+#[cfg(test)]
+#[path = "heatmap_test.rs"]
+mod heatmap_test;
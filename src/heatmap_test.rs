@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use super::*;
+use crate::{
+    core::{Index, Writer},
+    llrb::Llrb,
+};
+
+#[test]
+fn test_heatmap() {
+    let heatmap: Heatmap<i64> = Heatmap::new(1024, 4, 4);
+
+    // disabled by default.
+    heatmap.touch(&10, Some(&10)).unwrap();
+    assert_eq!(heatmap.to_count(&10).unwrap(), 0);
+    assert!(heatmap.to_hot_keys(10).unwrap().is_empty());
+
+    heatmap.set_enabled(true);
+    for key in 0..10_i64 {
+        for _ in 0..((key + 1) * 10) {
+            heatmap.touch(&key, Some(&key)).unwrap();
+        }
+    }
+    // count-min sketch never under-estimates.
+    for key in 0..10_i64 {
+        assert!(heatmap.to_count(&key).unwrap() >= ((key as u64) + 1) * 10);
+    }
+
+    let hot = heatmap.to_hot_keys(3).unwrap();
+    let keys: Vec<i64> = hot.iter().map(|(key, _)| *key).collect();
+    assert_eq!(keys, vec![9, 8, 7]);
+    assert!(hot[0].1 >= 100);
+    assert_eq!(heatmap.to_hot_keys(100).unwrap().len(), 4);
+
+    // missing keys are counted, but not tracked.
+    for _ in 0..1000 {
+        heatmap.touch(&100, None).unwrap();
+    }
+    assert!(heatmap.to_count(&100).unwrap() >= 1000);
+    assert!(heatmap
+        .to_hot_keys(4)
+        .unwrap()
+        .iter()
+        .all(|(k, _)| *k != 100));
+
+    heatmap.reset().unwrap();
+    assert_eq!(heatmap.to_count(&9).unwrap(), 0);
+    assert!(heatmap.to_hot_keys(10).unwrap().is_empty());
+}
+
+#[test]
+fn test_heat_reader() {
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new("test-heat-reader");
+    for key in 0..100 {
+        index.set(key, key).unwrap();
+    }
+
+    let heatmap = Arc::new(Heatmap::default());
+    let mut r = HeatReader::new(index.to_reader().unwrap(), Arc::clone(&heatmap));
+
+    r.get(&1).unwrap();
+    assert!(heatmap.to_hot_keys(10).unwrap().is_empty());
+
+    heatmap.set_enabled(true);
+    for _ in 0..5 {
+        r.get(&1).unwrap();
+        r.get_with_versions(&2).unwrap();
+    }
+    r.get(&2).unwrap();
+    let timeout = std::time::Duration::from_secs(10);
+    r.get_deadline(&2, timeout).unwrap();
+    assert!(r.get(&1000).is_err());
+    assert!(r.contains(&3).unwrap());
+    r.iter().unwrap().for_each(drop);
+
+    let hot = heatmap.to_hot_keys(10).unwrap();
+    assert_eq!(hot, vec![(2, 7), (1, 5)]);
+    assert_eq!(heatmap.to_count(&1000).unwrap(), 1);
+    assert_eq!(heatmap.to_count(&3).unwrap(), 1);
+    assert_eq!(heatmap.to_count(&50).unwrap(), 0);
+
+    heatmap.set_enabled(false);
+    r.get(&1).unwrap();
+    assert_eq!(heatmap.to_count(&1).unwrap(), 5);
+}
+
+#[test]
+fn test_heat_reader_forward() {
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new("test-heat-reader-forward");
+    for key in 0..100 {
+        index.set(key, key).unwrap();
+    }
+
+    let heatmap = Arc::new(Heatmap::default());
+    heatmap.set_enabled(true);
+    let mut r = HeatReader::new(index.to_reader().unwrap(), Arc::clone(&heatmap));
+
+    assert_eq!(r.range_count(10..20).unwrap(), 10);
+    assert_eq!(r.range_count(90..).unwrap(), 10);
+    assert_eq!(heatmap.to_count(&10).unwrap(), 0);
+
+    for _ in 0..3 {
+        assert_eq!(r.to_range_deleted(&5).unwrap(), None);
+    }
+    assert_eq!(heatmap.to_count(&5).unwrap(), 3);
+    assert!(heatmap.to_hot_keys(10).unwrap().is_empty());
+}
@@ -94,6 +94,7 @@ pub mod error;
 pub mod core;
 mod entry;
 pub mod panic;
+pub mod schema;
 pub mod spinlock;
 pub mod sync;
 mod sync_writer;
@@ -107,6 +108,9 @@ mod vlog;
 pub mod dlog;
 mod dlog_entry;
 mod dlog_journal;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod dlog_uring;
+pub mod heatmap;
 pub mod lsm;
 pub mod scans;
 
@@ -125,8 +129,12 @@ pub mod shllrb;
 pub mod dgm;
 pub mod nodisk;
 pub mod robt;
+mod robt_cipher;
+mod robt_compress;
 mod robt_entry;
+mod robt_export;
 mod robt_index;
+mod robt_sort;
 pub mod shrobt;
 // pub mod backup; TODO
 
@@ -46,14 +46,15 @@ use std::{
 
 #[allow(unused_imports)]
 use crate::{
-    core::{CommitIter, Replay, Result, ScanEntry, ScanIter, Value, WalWriter},
-    core::{CommitIterator, Cutoff, ToJson, Validate, WriteIndexFactory, Writer},
-    core::{Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
+    core::{Bloom, Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
+    core::{CasPolicy, CommitIterator, Cutoff, ToJson, Validate, WriteIndexFactory, Writer},
+    core::{CommitIter, Replay, Result, ScanEntry, ScanIter, SizeLimit, Value, WalWriter},
     error::Error,
     llrb_node::Node,
     mvcc::{Mvcc, Snapshot},
     scans,
     spinlock::{self, RWSpinlock},
+    types::{ArcKey, Interner},
     util,
 };
 // re-export
@@ -159,6 +160,10 @@ where
     lsm: bool,
     sticky: bool,
     spin: bool,
+    cas_policy: CasPolicy<V>,
+    size_limit: SizeLimit<V>,
+    bitmap: Option<Arc<dyn util::KeyBitmap<K>>>,
+    interner: Option<Arc<dyn util::KeyIntern<K>>>,
 
     root: Option<Box<Node<K, V>>>,
     seqno: u64,
@@ -208,6 +213,21 @@ where
     node.right.take().map(|right| drop_tree(right));
 }
 
+// intern keys in the sub-tree, in-place.
+fn intern_keys<K, V>(node: Option<&mut Node<K, V>>, interner: &dyn util::KeyIntern<K>) -> Result<()>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    if let Some(node) = node {
+        intern_keys(node.left.as_mut().map(DerefMut::deref_mut), interner)?;
+        let key = interner.intern(node.entry.to_key())?;
+        node.entry.set_key(key);
+        intern_keys(node.right.as_mut().map(DerefMut::deref_mut), interner)?;
+    }
+    Ok(())
+}
+
 impl<K, V> TryFrom<Mvcc<K, V>> for Box<Llrb<K, V>>
 where
     K: Clone + Ord,
@@ -222,6 +242,8 @@ where
         };
         index.set_sticky(mvcc_index.is_sticky()).ok(); // can't be error
         index.set_spinlatch(mvcc_index.is_spin()).ok(); // can't be error
+        index.set_cas_policy(mvcc_index.to_cas_policy()).ok(); // can't be error
+        index.set_size_limit(mvcc_index.to_size_limit()).ok(); // can't be error
 
         let debris = mvcc_index.squash()?;
         index.root = debris.root;
@@ -254,6 +276,10 @@ where
             lsm: false,
             sticky: false,
             spin: true,
+            cas_policy: Default::default(),
+            size_limit: Default::default(),
+            bitmap: None,
+            interner: None,
 
             root: None,
             seqno: Default::default(),
@@ -281,6 +307,10 @@ where
             lsm: true,
             sticky: false,
             spin: true,
+            cas_policy: Default::default(),
+            size_limit: Default::default(),
+            bitmap: None,
+            interner: None,
 
             root: None,
             seqno: Default::default(),
@@ -295,6 +325,39 @@ where
         })
     }
 
+    /// Rebuild an Llrb index from an iterator of entries, like a full
+    /// table scan over a disk index or replay of write-ahead-log.
+    /// Seqno, deleted state and older versions of each entry are
+    /// preserved as is. Set `lsm` to true, if entries carry older
+    /// versions, so that duplicate keys are merged instead of replaced.
+    pub fn from_entries<S, I>(name: S, lsm: bool, iter: I) -> Result<Box<Llrb<K, V>>>
+    where
+        K: Footprint,
+        V: Footprint,
+        S: AsRef<str>,
+        I: Iterator<Item = Result<Entry<K, V>>>,
+    {
+        let index = if lsm {
+            Llrb::new_lsm(name)
+        } else {
+            Llrb::new(name)
+        };
+
+        let count = {
+            let _latch = index.latch.acquire_write(index.spin);
+
+            let mut count = 0;
+            for entry in iter {
+                index.set_index_entry(entry?)?;
+                count += 1;
+            }
+            count
+        };
+
+        info!(target: "llrb  ", "{}, loaded {} entries", index.name, count);
+        Ok(index)
+    }
+
     /// Configure behaviour of spin-latch. If `spin` is true, calling
     /// thread shall spin until a latch is acquired or released, if false
     /// calling thread will yield to scheduler. Call this api, before
@@ -325,6 +388,54 @@ where
         }
     }
 
+    /// Configure the compare-and-set policy for [set_cas][Writer::set_cas]
+    /// operations, refer to [CasPolicy] for details. Call this api, before
+    /// creating reader and/or writer handles.
+    pub fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.cas_policy = policy;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Configure maximum size of keys and values, and the policy for
+    /// oversized values, refer to [SizeLimit] for details. Call this
+    /// api, before creating reader and/or writer handles.
+    pub fn set_size_limit(&mut self, limit: SizeLimit<V>) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.size_limit = limit;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Configure a bitmap, like the one built by the last flush into a
+    /// disk index, to fail fast on [get][Reader::get] and
+    /// [contains][Reader::contains] for keys known to be missing,
+    /// without walking the tree. Keys already in this index, and keys
+    /// written after this call, are added to the bitmap. Call this api,
+    /// before creating reader and/or writer handles.
+    pub fn set_bitmap<B>(&mut self, bitmap: B) -> Result<&mut Self>
+    where
+        K: 'static + Hash,
+        B: 'static + Send + Sync + Bloom,
+    {
+        let n = self.multi_rw();
+        if n == 0 {
+            let bitmap = util::new_key_bitmap(bitmap);
+            add_bitmap_keys(self.root.as_ref().map(Deref::deref), bitmap.as_ref());
+            self.bitmap = Some(bitmap);
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
     /// Squash this index and return the root and its book-keeping.
     pub(crate) fn squash(mut self) -> Result<SquashDebris<K, V>> {
         debug!(
@@ -358,6 +469,10 @@ where
             lsm: self.lsm,
             sticky: self.sticky,
             spin: self.spin,
+            cas_policy: self.cas_policy,
+            size_limit: self.size_limit,
+            bitmap: self.bitmap.clone(),
+            interner: self.interner.clone(),
 
             root: self.root.clone(),
             seqno: self.seqno,
@@ -371,6 +486,74 @@ where
             writers: Arc::new(0xC0FFEE),
         })
     }
+
+    /// Move entries in this index into an immutable [FrozenLlrb], and
+    /// continue with an empty tree for new writes. Seqno continues from
+    /// where the frozen index left off. Frozen index can be shared across
+    /// threads, without latching, and shall typically be used as the
+    /// flush source while this index takes new writes. Bitmap, if any,
+    /// moves to the frozen index, while interner, if any, is shared. Call
+    /// this api when there are no active reader and/or writer handles.
+    pub fn freeze(&mut self) -> Result<FrozenLlrb<K, V>> {
+        let n = self.multi_rw();
+        if n > 0 {
+            return err_at!(APIMisuse, msg: format!("active-handles:{}", n));
+        }
+
+        debug!(
+            target: "llrb  ", "{}, freezing seqno:{} len:{}",
+            self.name, self.seqno, self.n_count
+        );
+
+        let index = Llrb {
+            name: self.name.clone(),
+            lsm: self.lsm,
+            sticky: self.sticky,
+            spin: self.spin,
+            cas_policy: self.cas_policy,
+            size_limit: self.size_limit,
+            bitmap: self.bitmap.take(),
+            interner: self.interner.clone(),
+
+            root: self.root.take(),
+            seqno: self.seqno,
+            metadata: self.metadata.clone(),
+            n_count: mem::replace(&mut self.n_count, 0),
+            n_deleted: mem::replace(&mut self.n_deleted, 0),
+            latch: RWSpinlock::new(),
+            key_footprint: mem::replace(&mut self.key_footprint, 0),
+            tree_footprint: mem::replace(&mut self.tree_footprint, 0),
+            readers: Arc::new(0xC0FFEE),
+            writers: Arc::new(0xC0FFEE),
+        };
+        Ok(FrozenLlrb {
+            index: Arc::new(index),
+        })
+    }
+}
+
+/// Key interning for Llrb index.
+impl<T, V> Llrb<ArcKey<T>, V>
+where
+    T: 'static + Send + Sync + Hash + Ord + Footprint,
+    V: Clone + Diff,
+{
+    /// Configure an [Interner], that can be shared with other indexes,
+    /// so that repeated keys share a single allocation. Keys already in
+    /// this index, and keys written after this call, are interned. Call
+    /// this api, before creating reader and/or writer handles.
+    pub fn set_interner(&mut self, interner: Arc<Interner<T>>) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            let interner: Arc<dyn util::KeyIntern<ArcKey<T>>> = interner;
+            let root = self.root.as_mut().map(DerefMut::deref_mut);
+            intern_keys(root, interner.as_ref())?;
+            self.interner = Some(interner);
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
 }
 
 impl<K, V> Llrb<K, V>
@@ -451,6 +634,53 @@ where
             }
         }
     }
+
+    /// Rebuild the tree into freshly allocated nodes, in key order, and
+    /// release the older allocations. Keys and values are cloned, hence
+    /// their excess capacity, if any, is also released. Meant to be
+    /// called during maintenance window, say after a large wave of
+    /// deletes, and fails if there are active read/write handles.
+    ///
+    /// Return the number of bytes reclaimed, as accounted by footprint.
+    pub fn compact_memory(&mut self) -> Result<isize> {
+        let n = self.multi_rw();
+        if n > 0 {
+            return err_at!(APIMisuse, msg: format!("active-handles:{}", n));
+        }
+
+        let old_footprint = self.tree_footprint;
+        let mut tree_footprint = 0;
+        self.root = Self::do_compact_memory(self.root.take(), &mut tree_footprint)?;
+        self.tree_footprint = tree_footprint;
+
+        let reclaimed = old_footprint - tree_footprint;
+        info!(
+            target: "llrb  ",
+            "{}, compacted memory len:{} reclaimed:{}", self.name, self.n_count, reclaimed
+        );
+        Ok(reclaimed)
+    }
+
+    fn do_compact_memory(
+        node: Option<Box<Node<K, V>>>,
+        tree_footprint: &mut isize,
+    ) -> Result<Option<Box<Node<K, V>>>> {
+        match node {
+            None => Ok(None),
+            Some(mut node) => {
+                let left = Self::do_compact_memory(node.left.take(), tree_footprint)?;
+                let mut new_node = Box::new(node.clone_detach());
+                new_node.dirty = node.dirty;
+                new_node.left = left;
+                let right = node.right.take();
+                // children are detached, older node is dropped here.
+                mem::drop(node);
+                new_node.right = Self::do_compact_memory(right, tree_footprint)?;
+                *tree_footprint += new_node.footprint()?;
+                Ok(Some(new_node))
+            }
+        }
+    }
 }
 
 /// Maintanence API.
@@ -475,6 +705,16 @@ where
         self.spin
     }
 
+    /// Return the compare-and-set policy configured for this index.
+    pub fn to_cas_policy(&self) -> CasPolicy<V> {
+        self.cas_policy
+    }
+
+    /// Return the size limits configured for this index.
+    pub fn to_size_limit(&self) -> SizeLimit<V> {
+        self.size_limit
+    }
+
     /// Return number of entries in this index.
     #[inline]
     pub fn len(&self) -> usize {
@@ -526,6 +766,21 @@ where
         })
     }
 
+    /// Return an iterator over deleted entries in this index, in sort
+    /// order. Seqno of the returned entry is the seqno at which it was
+    /// deleted. Applicable only in lsm mode, in non-lsm mode deleted
+    /// entries are removed from the index.
+    pub fn iter_deleted(&mut self) -> Result<IndexIter<K, V>> {
+        let _latch = Some(self.latch.acquire_read(self.spin));
+
+        let node = self.root.as_ref().map(Deref::deref);
+        Ok(Box::new(IterDeleted {
+            _latch,
+            _arc: Default::default(),
+            paths: Some(build_iter(IFlag::Left, node, vec![])?),
+        }))
+    }
+
     fn multi_rw(&self) -> usize {
         Arc::strong_count(&self.readers) + Arc::strong_count(&self.writers) - 2
     }
@@ -749,6 +1004,8 @@ where
     }
 }
 
+/// Footprint is accounted incrementally for every write operation,
+/// including key, value, deltas and node overhead. Reading it is O(1).
 impl<K, V> Footprint for Llrb<K, V>
 where
     K: Clone + Ord,
@@ -791,6 +1048,17 @@ where
     size: isize, // difference in footprint
 }
 
+// Child link taken from a parent node, while walking down the tree.
+#[derive(Clone, Copy)]
+enum Child {
+    Left,
+    Right,
+}
+
+// Parent nodes, from root to the current node, detached from their
+// child link that is being walked down.
+type Path<K, V> = Vec<(Box<Node<K, V>>, Child)>;
+
 /// Create/Update/Delete operations on Llrb index.
 impl<K, V> Llrb<K, V>
 where
@@ -810,6 +1078,7 @@ where
         seqno: Option<u64>,
     ) -> Result<(u64, Option<Entry<K, V>>)> {
         let _latch = self.latch.acquire_write(self.spin);
+        let value = self.size_limit.apply_mem(&key, value)?;
         let entry = {
             let seqno = match seqno {
                 Some(seqno) => seqno,
@@ -826,6 +1095,34 @@ where
         Ok((seqno, old_entry))
     }
 
+    /// Return the entry for `key`, if present, else insert the value
+    /// computed by `f` and return the new entry. Lookup and insert are
+    /// done under the same write latch, hence `f` is called at the most
+    /// once and concurrent writers cannot race between them. Entries
+    /// marked as deleted are treated as missing.
+    ///
+    /// *LSM mode*: Add a new version for the key, if it was deleted.
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> Result<Entry<K, V>>
+    where
+        F: FnOnce() -> V,
+    {
+        let _latch = self.latch.acquire_write(self.spin);
+        let res = get(self.root.as_ref().map(Deref::deref), &key);
+        match res {
+            Ok(entry) if !entry.is_deleted() => return Ok(entry),
+            Ok(_) | Err(Error::KeyNotFound) => (),
+            Err(err) => return Err(err),
+        }
+
+        let value = self.size_limit.apply_mem(&key, f())?;
+        let entry = {
+            let seqno = self.seqno + 1;
+            Entry::new(key.clone(), Value::new_upsert_value(value, seqno))
+        };
+        self.set_index_entry(entry)?;
+        get(self.root.as_ref().map(Deref::deref), &key)
+    }
+
     /// Similar to set, but succeeds only when CAS matches with entry's
     /// Set {key, value} in index if an older entry exists with the
     /// same ``cas`` value. To create a fresh entry, pass ``cas`` as ZERO.
@@ -842,18 +1139,24 @@ where
         seqno: Option<u64>,
     ) -> Result<(u64, Result<Option<Entry<K, V>>>)> {
         let _latch = self.latch.acquire_write(self.spin);
+        let value = self.size_limit.apply_mem(&key, value)?;
         let seqno = match seqno {
             Some(seqno) => seqno,
             None => self.seqno + 1,
         };
 
+        let key = self.intern_key(key)?;
         let key_footprint = util::key_footprint(&key)?;
+        if let Some(bitmap) = &self.bitmap {
+            bitmap.add_key(&key);
+        }
         let new_entry = {
             let value = Value::new_upsert_value(value, seqno);
             Entry::new(key, value)
         };
         self.seqno = seqno;
-        match Llrb::upsert_cas(self.root.take(), new_entry, cas, self.lsm)? {
+        let policy = self.cas_policy;
+        match Llrb::upsert_cas(self.root.take(), new_entry, cas, policy, self.lsm)? {
             UpsertCasResult {
                 node: root,
                 err: Some(err),
@@ -906,13 +1209,17 @@ where
             None => self.seqno + 1,
         };
 
-        let key_footprint = util::key_footprint(&key.to_owned())?;
+        let key = self.intern_key(key.to_owned())?;
+        let key_footprint = util::key_footprint(&key)?;
+        if let Some(bitmap) = &self.bitmap {
+            bitmap.add_key(&key);
+        }
 
         if self.lsm || self.sticky {
             let res = if self.lsm {
-                Llrb::delete_lsm(self.root.take(), key, seqno)?
+                Llrb::delete_lsm(self.root.take(), &key, seqno)?
             } else {
-                let res = Llrb::delete_sticky(self.root.take(), key, seqno)?;
+                let res = Llrb::delete_sticky(self.root.take(), &key, seqno)?;
                 if cfg!(debug_assertions) {
                     match &res.old_entry {
                         Some(oe) => assert_eq!(oe.as_deltas().len(), 0),
@@ -942,7 +1249,7 @@ where
             };
         } else {
             // in non-lsm mode remove the entry from the tree.
-            let res = match Llrb::do_delete(self.root.take(), key)? {
+            let res = match Llrb::do_delete(self.root.take(), &key)? {
                 res @ DeleteResult { node: None, .. } => res,
                 mut res => {
                     res.node.as_mut().map(|node| node.set_black());
@@ -962,6 +1269,23 @@ where
             }
         }
     }
+
+    /// Delete key from index, similar to [delete_index][Llrb::delete_index],
+    /// but return the entry, as it was before this delete, with all its
+    /// older versions. Useful for audit logs to record what was logically
+    /// removed.
+    ///
+    /// *LSM mode*: if key is missing, or already deleted, delete shall
+    /// still be ingested as a new version, and returned entry shall be
+    /// None or a deleted entry respectively.
+    pub fn delete_get_versions<Q>(&mut self, key: &Q) -> Result<(u64, Option<Entry<K, V>>)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let (seqno, entry) = self.delete_index(key, None)?;
+        Ok((seqno, entry?))
+    }
 }
 
 /// Create/Update/Delete operations on Llrb index.
@@ -1009,6 +1333,28 @@ where
         let (_seqno, entry) = self.delete_index(key, None)?;
         entry
     }
+
+    /// Delete all keys from lower bound to upper bound, return the number
+    /// of entries deleted. Keys are collected before deleting them, so
+    /// that the range iterator does not overlap with the mutations.
+    ///
+    /// *LSM mode*: Entries are marked as deleted, entries already marked
+    /// as deleted are skipped.
+    fn delete_range<R>(&mut self, range: R) -> Result<usize>
+    where
+        R: Clone + RangeBounds<K>,
+    {
+        let keys = util::live_keys(self.range(range)?)?;
+        let mut n = 0;
+        for key in keys.iter() {
+            let (_seqno, entry) = self.delete_index(key, None)?;
+            match entry? {
+                Some(entry) if !entry.is_deleted() => n += 1,
+                _ => (),
+            }
+        }
+        Ok(n)
+    }
 }
 
 /// Create/Update/Delete operations on Llrb index.
@@ -1017,131 +1363,108 @@ where
     K: Clone + Ord + Footprint,
     V: Clone + Diff + Footprint,
 {
+    // Mutations walk down the tree iteratively, remembering the parent
+    // nodes in `path`, and rebalance on the way up via `unwind`. This
+    // keeps the stack usage constant, irrespective of the tree depth.
     fn upsert(
         node: Option<Box<Node<K, V>>>,
         nentry: Entry<K, V>,
         lsm: bool, // preserve old entries
     ) -> Result<UpsertResult<K, V>> {
-        match node {
-            None => {
-                let mut node: Box<Node<K, V>> = Box::new(From::from(nentry));
-                node.dirty = false;
-                let size: isize = node.footprint()?;
-                return Ok(UpsertResult {
-                    node: Some(node),
-                    old_entry: None,
-                    size,
-                });
-            }
-            Some(mut node) => {
-                node = Llrb::walkdown_rot23(node);
-                match node.as_key().cmp(nentry.as_key()) {
-                    Ordering::Greater => {
-                        let mut r = Llrb::upsert(node.left.take(), nentry, lsm)?;
-                        node.left = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Less => {
-                        let mut r = Llrb::upsert(node.right.take(), nentry, lsm)?;
-                        node.right = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Equal if lsm => {
-                        let size = node.footprint()?;
-                        let (old_entry, entry) = {
-                            let entry = node.entry.clone();
-                            (Some(entry.clone()), entry)
-                        };
-                        node.entry = entry.xmerge(nentry)?;
-                        let size = node.footprint()? - size;
-                        Ok(UpsertResult {
-                            node: Some(Llrb::walkuprot_23(node)),
-                            old_entry,
-                            size,
-                        })
-                    }
-                    Ordering::Equal => {
-                        let old_entry = Some(node.entry.clone());
-                        let size = node.prepend_version(nentry, lsm)?;
-                        Ok(UpsertResult {
-                            node: Some(Llrb::walkuprot_23(node)),
-                            old_entry,
-                            size,
-                        })
-                    }
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        let (node, old_entry, size) = loop {
+            let mut n = match node.take() {
+                None => {
+                    let mut n: Box<Node<K, V>> = Box::new(From::from(nentry));
+                    n.dirty = false;
+                    let size: isize = n.footprint()?;
+                    break (n, None, size);
+                }
+                Some(n) => Llrb::walkdown_rot23(n),
+            };
+            match n.as_key().cmp(nentry.as_key()) {
+                Ordering::Greater => {
+                    node = n.left.take();
+                    path.push((n, Child::Left));
+                }
+                Ordering::Less => {
+                    node = n.right.take();
+                    path.push((n, Child::Right));
+                }
+                Ordering::Equal if lsm => {
+                    let size = n.footprint()?;
+                    let (old_entry, entry) = {
+                        let entry = n.entry.clone();
+                        (Some(entry.clone()), entry)
+                    };
+                    n.entry = entry.xmerge(nentry)?;
+                    let size = n.footprint()? - size;
+                    break (Llrb::walkuprot_23(n), old_entry, size);
+                }
+                Ordering::Equal => {
+                    let old_entry = Some(n.entry.clone());
+                    let size = n.prepend_version(nentry, lsm)?;
+                    break (Llrb::walkuprot_23(n), old_entry, size);
                 }
             }
-        }
+        };
+
+        Ok(UpsertResult {
+            node: Llrb::unwind(path, Some(node), Llrb::walkuprot_23),
+            old_entry,
+            size,
+        })
     }
 
     fn upsert_cas(
         node: Option<Box<Node<K, V>>>,
         nentry: Entry<K, V>,
         cas: u64,
+        policy: CasPolicy<V>,
         lsm: bool,
     ) -> Result<UpsertCasResult<K, V>> {
-        let mut node = match node {
-            None if cas > 0 => {
-                return Ok(UpsertCasResult {
-                    node: None,
-                    old_entry: None,
-                    size: 0,
-                    err: Some(Error::InvalidCAS(0)),
-                });
-            }
-            None => {
-                let mut node: Box<Node<K, V>> = Box::new(From::from(nentry));
-                node.dirty = false;
-                let size: isize = node.footprint()?;
-                return Ok(UpsertCasResult {
-                    node: Some(node),
-                    old_entry: None,
-                    size,
-                    err: None,
-                });
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        let (node, old_entry, size, err) = loop {
+            let mut n = match node.take() {
+                None => match policy.check::<K>(None, cas) {
+                    Err(err) => break (None, None, 0, Some(err)),
+                    Ok(()) => {
+                        let mut n: Box<Node<K, V>> = Box::new(From::from(nentry));
+                        n.dirty = false;
+                        let size: isize = n.footprint()?;
+                        break (Some(n), None, size, None);
+                    }
+                },
+                Some(n) => Llrb::walkdown_rot23(n),
+            };
+            match n.as_key().cmp(nentry.as_key()) {
+                Ordering::Greater => {
+                    node = n.left.take();
+                    path.push((n, Child::Left));
+                }
+                Ordering::Less => {
+                    node = n.right.take();
+                    path.push((n, Child::Right));
+                }
+                Ordering::Equal => match policy.check(Some(&n.entry), cas) {
+                    Err(err) => break (Some(Llrb::walkuprot_23(n)), None, 0, Some(err)),
+                    Ok(()) => {
+                        let old_entry = Some(n.entry.clone());
+                        let size = n.prepend_version(nentry, lsm)?;
+                        break (Some(Llrb::walkuprot_23(n)), old_entry, size, None);
+                    }
+                },
             }
-            Some(node) => node,
         };
 
-        node = Llrb::walkdown_rot23(node);
-        match node.as_key().cmp(nentry.as_key()) {
-            Ordering::Greater => {
-                let mut r = Llrb::upsert_cas(node.left.take(), nentry, cas, lsm)?;
-                node.left = r.node;
-                r.node = Some(Llrb::walkuprot_23(node));
-                Ok(r)
-            }
-            Ordering::Less => {
-                let mut r = Llrb::upsert_cas(node.right.take(), nentry, cas, lsm)?;
-                node.right = r.node;
-                r.node = Some(Llrb::walkuprot_23(node));
-                Ok(r)
-            }
-            Ordering::Equal => {
-                let seqno = node.to_seqno();
-                let p = node.is_deleted() && cas != 0 && cas != seqno;
-                let p = p || (!node.is_deleted() && cas != seqno);
-                if p {
-                    Ok(UpsertCasResult {
-                        node: Some(Llrb::walkuprot_23(node)),
-                        old_entry: None,
-                        size: 0,
-                        err: Some(Error::InvalidCAS(seqno)),
-                    })
-                } else {
-                    let old_entry = Some(node.entry.clone());
-                    let size = node.prepend_version(nentry, lsm)?;
-                    Ok(UpsertCasResult {
-                        node: Some(Llrb::walkuprot_23(node)),
-                        old_entry,
-                        size,
-                        err: None,
-                    })
-                }
-            }
-        }
+        Ok(UpsertCasResult {
+            node: Llrb::unwind(path, node, Llrb::walkuprot_23),
+            old_entry,
+            size,
+            err,
+        })
     }
 
     fn delete_lsm<Q>(
@@ -1153,45 +1476,41 @@ where
         K: Borrow<Q>,
         Q: ToOwned<Owned = K> + Ord + ?Sized,
     {
-        match node {
-            None => {
-                // insert and mark as delete
-                let mut node = Node::new_deleted(key.to_owned(), seqno);
-                node.dirty = false;
-                let size: isize = node.footprint()?;
-                Ok(DeleteResult {
-                    node: Some(node),
-                    old_entry: None,
-                    size,
-                })
-            }
-            Some(mut node) => {
-                node = Llrb::walkdown_rot23(node);
-                match node.as_key().borrow().cmp(&key) {
-                    Ordering::Greater => {
-                        let mut r = Llrb::delete_lsm(node.left.take(), key, seqno)?;
-                        node.left = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Less => {
-                        let mut r = Llrb::delete_lsm(node.right.take(), key, seqno)?;
-                        node.right = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Equal => {
-                        let entry = node.entry.clone();
-                        let size = node.delete(seqno)?;
-                        Ok(DeleteResult {
-                            node: Some(Llrb::walkuprot_23(node)),
-                            old_entry: Some(entry),
-                            size,
-                        })
-                    }
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        let (node, old_entry, size) = loop {
+            let mut n = match node.take() {
+                None => {
+                    // insert and mark as delete
+                    let mut n = Node::new_deleted(key.to_owned(), seqno);
+                    n.dirty = false;
+                    let size: isize = n.footprint()?;
+                    break (n, None, size);
+                }
+                Some(n) => Llrb::walkdown_rot23(n),
+            };
+            match n.as_key().borrow().cmp(&key) {
+                Ordering::Greater => {
+                    node = n.left.take();
+                    path.push((n, Child::Left));
+                }
+                Ordering::Less => {
+                    node = n.right.take();
+                    path.push((n, Child::Right));
+                }
+                Ordering::Equal => {
+                    let entry = n.entry.clone();
+                    let size = n.delete(seqno)?;
+                    break (Llrb::walkuprot_23(n), Some(entry), size);
                 }
             }
-        }
+        };
+
+        Ok(DeleteResult {
+            node: Llrb::unwind(path, Some(node), Llrb::walkuprot_23),
+            old_entry,
+            size,
+        })
     }
 
     fn delete_sticky<Q>(
@@ -1203,49 +1522,45 @@ where
         K: Borrow<Q>,
         Q: ToOwned<Owned = K> + Ord + ?Sized,
     {
-        match node {
-            None => {
-                // insert and mark as delete
-                let mut node = Node::new_deleted(key.to_owned(), seqno);
-                node.dirty = false;
-                let size: isize = node.footprint()?;
-                Ok(DeleteResult {
-                    node: Some(node),
-                    old_entry: None,
-                    size,
-                })
-            }
-            Some(mut node) => {
-                node = Llrb::walkdown_rot23(node);
-                match node.as_key().borrow().cmp(&key) {
-                    Ordering::Greater => {
-                        let mut r = Llrb::delete_sticky(node.left.take(), key, seqno)?;
-                        node.left = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Less => {
-                        let mut r = Llrb::delete_sticky(node.right.take(), key, seqno)?;
-                        node.right = r.node;
-                        r.node = Some(Llrb::walkuprot_23(node));
-                        Ok(r)
-                    }
-                    Ordering::Equal => {
-                        let mut size = node.footprint()?;
-                        let entry = node.entry.clone();
-                        node.delete(seqno)?;
-                        let cutoff = Cutoff::new_lsm(Bound::Included(entry.to_seqno()));
-                        node.entry = node.entry.clone().purge(cutoff).unwrap();
-                        size = node.footprint()? - size; // TODO
-                        Ok(DeleteResult {
-                            node: Some(Llrb::walkuprot_23(node)),
-                            old_entry: Some(entry),
-                            size,
-                        })
-                    }
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        let (node, old_entry, size) = loop {
+            let mut n = match node.take() {
+                None => {
+                    // insert and mark as delete
+                    let mut n = Node::new_deleted(key.to_owned(), seqno);
+                    n.dirty = false;
+                    let size: isize = n.footprint()?;
+                    break (n, None, size);
+                }
+                Some(n) => Llrb::walkdown_rot23(n),
+            };
+            match n.as_key().borrow().cmp(&key) {
+                Ordering::Greater => {
+                    node = n.left.take();
+                    path.push((n, Child::Left));
+                }
+                Ordering::Less => {
+                    node = n.right.take();
+                    path.push((n, Child::Right));
+                }
+                Ordering::Equal => {
+                    let mut size = n.footprint()?;
+                    let entry = n.entry.clone();
+                    n.delete(seqno)?;
+                    let cutoff = Cutoff::new_lsm(Bound::Included(entry.to_seqno()));
+                    n.entry = n.entry.clone().purge(cutoff).unwrap();
+                    size = n.footprint()? - size; // TODO
+                    break (Llrb::walkuprot_23(n), Some(entry), size);
                 }
             }
-        }
+        };
+
+        Ok(DeleteResult {
+            node: Llrb::unwind(path, Some(node), Llrb::walkuprot_23),
+            old_entry,
+            size,
+        })
     }
 
     // this is the non-lsm path.
@@ -1254,98 +1569,108 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        let mut node = match node {
-            None => {
-                return Ok(DeleteResult {
-                    node: None,
-                    old_entry: None,
-                    size: 0,
-                })
-            }
-            Some(node) => node,
-        };
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        let (node, old_entry, size) = loop {
+            let mut n = match node.take() {
+                None => break (None, None, 0),
+                Some(n) => n,
+            };
 
-        if node.as_key().borrow().gt(key) {
-            if node.left.is_none() {
-                Ok(DeleteResult {
-                    node: Some(node),
-                    old_entry: None,
-                    size: 0,
-                })
-            } else {
-                let ok = !is_red(node.as_left_deref());
-                if ok && !is_red(node.left.as_ref().unwrap().as_left_deref()) {
-                    node = Llrb::move_red_left(node);
+            if n.as_key().borrow().gt(key) {
+                if n.left.is_none() {
+                    break (Some(n), None, 0);
+                }
+                let ok = !is_red(n.as_left_deref());
+                if ok && !is_red(n.left.as_ref().unwrap().as_left_deref()) {
+                    n = Llrb::move_red_left(n);
                 }
-                let mut r = Llrb::do_delete(node.left.take(), key)?;
-                node.left = r.node;
-                r.node = Some(Llrb::fixup(node));
-                Ok(r)
+                node = n.left.take();
+                path.push((n, Child::Left));
+                continue;
             }
-        } else {
-            if is_red(node.as_left_deref()) {
-                node = Llrb::rotate_right(node);
+
+            if is_red(n.as_left_deref()) {
+                n = Llrb::rotate_right(n);
             }
 
-            if !node.as_key().borrow().lt(key) && node.right.is_none() {
-                return Ok(DeleteResult {
-                    node: None,
-                    old_entry: Some(node.entry.clone()),
-                    size: -node.footprint()?,
-                });
+            if !n.as_key().borrow().lt(key) && n.right.is_none() {
+                break (None, Some(n.entry.clone()), -n.footprint()?);
             }
 
-            let ok = node.right.is_some() && !is_red(node.as_right_deref());
-            if ok && !is_red(node.right.as_ref().unwrap().as_left_deref()) {
-                node = Llrb::move_red_right(node);
+            let ok = n.right.is_some() && !is_red(n.as_right_deref());
+            if ok && !is_red(n.right.as_ref().unwrap().as_left_deref()) {
+                n = Llrb::move_red_right(n);
             }
 
-            if !node.as_key().borrow().lt(key) {
+            if !n.as_key().borrow().lt(key) {
                 // node == key
-                let (right, mut res_node) = Llrb::delete_min(node.right.take());
-                node.right = right;
+                let (right, mut res_node) = Llrb::delete_min(n.right.take());
+                n.right = right;
                 if res_node.is_none() {
                     return err_at!(Fatal, msg: format!("call-the-programmer"));
                 }
                 let subdel = res_node.take().unwrap();
                 let mut newnode = Box::new(subdel.clone_detach());
-                newnode.left = node.left.take();
-                newnode.right = node.right.take();
-                newnode.black = node.black;
+                newnode.left = n.left.take();
+                newnode.right = n.right.take();
+                newnode.black = n.black;
                 newnode.dirty = false;
-                let size: isize = node.footprint()?;
-                Ok(DeleteResult {
-                    node: Some(Llrb::fixup(newnode)),
-                    old_entry: Some(node.entry.clone()),
-                    size: -size,
-                })
-            } else {
-                let mut r = Llrb::do_delete(node.right.take(), key)?;
-                node.right = r.node;
-                r.node = Some(Llrb::fixup(node));
-                Ok(r)
+                let size: isize = n.footprint()?;
+                break (Some(Llrb::fixup(newnode)), Some(n.entry.clone()), -size);
             }
-        }
+
+            node = n.right.take();
+            path.push((n, Child::Right));
+        };
+
+        Ok(DeleteResult {
+            node: Llrb::unwind(path, node, Llrb::fixup),
+            old_entry,
+            size,
+        })
     }
 
     // return [node, old_node]
     fn delete_min(
         node: Option<Box<Node<K, V>>>, // root node
     ) -> (Option<Box<Node<K, V>>>, Option<Node<K, V>>) {
-        match node {
-            None => (None, None),
-            Some(node) if node.left.is_none() => (None, Some(*node)),
-            Some(mut node) => {
-                let left = node.as_left_deref();
-                if !is_red(left) && !is_red(left.unwrap().as_left_deref()) {
-                    node = Llrb::move_red_left(node);
+        let mut path: Path<K, V> = vec![];
+        let mut node = node;
+        loop {
+            match node.take() {
+                None => break (Llrb::unwind(path, None, Llrb::fixup), None),
+                Some(n) if n.left.is_none() => {
+                    break (Llrb::unwind(path, None, Llrb::fixup), Some(*n));
+                }
+                Some(mut n) => {
+                    let left = n.as_left_deref();
+                    if !is_red(left) && !is_red(left.unwrap().as_left_deref()) {
+                        n = Llrb::move_red_left(n);
+                    }
+                    node = n.left.take();
+                    path.push((n, Child::Left));
                 }
-                let (left, old_node) = Llrb::delete_min(node.left.take());
-                node.left = left;
-                (Some(Llrb::fixup(node)), old_node)
             }
         }
     }
+
+    // attach `node` to its parent, from the bottom of `path` to the root,
+    // rebalancing each parent using `fix`. Return the new root.
+    fn unwind(
+        mut path: Path<K, V>,
+        mut node: Option<Box<Node<K, V>>>,
+        fix: fn(Box<Node<K, V>>) -> Box<Node<K, V>>,
+    ) -> Option<Box<Node<K, V>>> {
+        while let Some((mut parent, child)) = path.pop() {
+            match child {
+                Child::Left => parent.left = node,
+                Child::Right => parent.right = node,
+            }
+            node = Some(fix(parent));
+        }
+        node
+    }
 }
 
 impl<K, V> Llrb<K, V>
@@ -1353,13 +1678,27 @@ where
     K: Clone + Ord + Footprint,
     V: Clone + Diff + Footprint,
 {
+    fn intern_key(&self, key: K) -> Result<K> {
+        match &self.interner {
+            Some(interner) => interner.intern(key),
+            None => Ok(key),
+        }
+    }
+
     fn set_index_entry(&self, entry: Entry<K, V>) -> Result<(u64, Option<Entry<K, V>>)> {
         let mself = unsafe {
             // caller hold a write latch.
             (self as *const Self as *mut Self).as_mut().unwrap()
         };
 
+        let mut entry = entry;
+        if mself.interner.is_some() {
+            entry.set_key(mself.intern_key(entry.to_key())?);
+        }
         let key_footprint = util::key_footprint(entry.as_key())?;
+        if let Some(bitmap) = &mself.bitmap {
+            bitmap.add_key(entry.as_key());
+        }
         let (seqno, deleted) = (entry.to_seqno(), entry.is_deleted());
         match Llrb::upsert(mself.root.take(), entry, mself.lsm)? {
             UpsertResult {
@@ -1520,10 +1859,26 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash,
     {
+        if !util::bitmap_contains(&self.bitmap, key) {
+            return Err(Error::KeyNotFound);
+        }
         let _latch = self.latch.acquire_read(self.spin);
         get(self.root.as_ref().map(Deref::deref), key)
     }
 
+    /// Check whether `key` is present, without cloning its entry.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        if !util::bitmap_contains(&self.bitmap, key) {
+            return Ok(false);
+        }
+        let _latch = self.latch.acquire_read(self.spin);
+        Ok(contains_key(self.root.as_ref().map(Deref::deref), key))
+    }
+
     /// Return an iterator over all entries in this index.
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let _latch = Some(self.latch.acquire_read(self.spin));
@@ -1585,6 +1940,17 @@ where
         }))
     }
 
+    /// Count entries from lower bound to upper bound, entries marked as
+    /// deleted are skipped.
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     /// Short circuited to get().
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
@@ -1645,6 +2011,11 @@ where
         self.delete_index(&key, Some(seqno))?.1?;
         Ok(())
     }
+
+    fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<()> {
+        self.set_cas_policy(policy)?;
+        Ok(())
+    }
 }
 
 impl<K, V> CommitIterator<K, V> for Box<Llrb<K, V>>
@@ -1819,6 +2190,32 @@ where
             paths,
         }))
     }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than from bound, in descending order,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let _latch = Some(self.latch.acquire_read(self.spin));
+
+        // similar to reverse pre-processing
+        let root = self.root.as_ref().map(Deref::deref);
+        let paths = match from {
+            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
+            Bound::Included(high) => Some(find_end(root, high.borrow(), true, vec![])),
+            Bound::Excluded(high) => Some(find_end(root, high.borrow(), false, vec![])),
+        };
+        let (start, end) = util::to_start_end(within);
+        Ok(Box::new(IterPWScanRev {
+            _latch,
+            _arc: Default::default(),
+            start,
+            end,
+            paths,
+        }))
+    }
 }
 
 impl<K, V> Validate<Stats> for Box<Llrb<K, V>>
@@ -2087,6 +2484,16 @@ where
         index.get(key)
     }
 
+    /// Check whether ``key`` is present in index.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let index: &mut Llrb<K, V> = self.as_mut();
+        index.contains(key)
+    }
+
     /// Iterate over all entries in this index.
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let index: &mut Llrb<K, V> = self.as_mut();
@@ -2115,6 +2522,17 @@ where
         index.reverse(range)
     }
 
+    /// Count entries from lower bound to upper bound.
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let index: &mut Llrb<K, V> = self.as_mut();
+        index.range_count(range)
+    }
+
     /// Short circuited to get().
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
@@ -2202,6 +2620,415 @@ where
         let index: &mut Llrb<K, V> = self.as_mut();
         index.pw_scan(from, within)
     }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than from bound, in descending order,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Llrb<K, V> = self.as_mut();
+        index.pw_scan_rev(from, within)
+    }
+}
+
+/// Immutable, read-only, [Llrb] index, refer [Llrb::freeze]. Cloning a
+/// frozen index is cheap, all clones share the same tree. Reads don't
+/// latch the tree, hence can proceed in parallel from several threads.
+pub struct FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    index: Arc<Llrb<K, V>>,
+}
+
+impl<K, V> Clone for FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn clone(&self) -> Self {
+        FrozenLlrb {
+            index: Arc::clone(&self.index),
+        }
+    }
+}
+
+impl<K, V> FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Identify this index, same as the index it was frozen from.
+    pub fn to_name(&self) -> String {
+        self.index.to_name()
+    }
+
+    /// Return whether this index support lsm mode.
+    pub fn is_lsm(&self) -> bool {
+        self.index.lsm
+    }
+
+    /// Return number of entries in this index.
+    pub fn len(&self) -> usize {
+        self.index.n_count
+    }
+
+    /// Return the seqno of the last mutation, before freezing.
+    pub fn to_seqno(&self) -> u64 {
+        self.index.seqno
+    }
+
+    /// Return application metadata, as of freezing.
+    pub fn to_metadata(&self) -> Vec<u8> {
+        self.index.metadata.clone()
+    }
+
+    /// Return quickly with basic statisics, refer [Llrb::to_stats].
+    pub fn to_stats(&self) -> Result<Stats> {
+        self.index.to_stats()
+    }
+
+    fn as_root(&self) -> Option<&Node<K, V>> {
+        self.index.root.as_ref().map(Deref::deref)
+    }
+}
+
+impl<K, V> Reader<K, V> for FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Get the entry for `key`.
+    fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        if !util::bitmap_contains(&self.index.bitmap, key) {
+            return Err(Error::KeyNotFound);
+        }
+        get(self.as_root(), key)
+    }
+
+    /// Check whether `key` is present, without cloning its entry.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        if !util::bitmap_contains(&self.index.bitmap, key) {
+            return Ok(false);
+        }
+        Ok(contains_key(self.as_root(), key))
+    }
+
+    /// Return an iterator over all entries in this index.
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        Ok(Box::new(Iter {
+            _latch: None,
+            _arc: Default::default(),
+            paths: Some(build_iter(IFlag::Left, self.as_root(), vec![])?),
+        }))
+    }
+
+    /// Range over all entries from low to high.
+    fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let root = self.as_root();
+        let paths = match range.start_bound() {
+            Bound::Unbounded => Some(build_iter(IFlag::Left, root, vec![])?),
+            Bound::Included(low) => Some(find_start(root, low, true, vec![])),
+            Bound::Excluded(low) => Some(find_start(root, low, false, vec![])),
+        };
+        Ok(Box::new(Range {
+            _latch: None,
+            _arc: Default::default(),
+            range,
+            paths,
+            high: marker::PhantomData,
+        }))
+    }
+
+    /// Reverse range over all entries from high to low.
+    fn reverse<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let root = self.as_root();
+        let paths = match range.end_bound() {
+            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
+            Bound::Included(high) => Some(find_end(root, high, true, vec![])),
+            Bound::Excluded(high) => Some(find_end(root, high, false, vec![])),
+        };
+        Ok(Box::new(Reverse {
+            _latch: None,
+            _arc: Default::default(),
+            range,
+            paths,
+            low: marker::PhantomData,
+        }))
+    }
+
+    /// Count entries from lower bound to upper bound, entries marked as
+    /// deleted are skipped.
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
+    /// Short circuited to get().
+    fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.get(key)
+    }
+
+    /// Short circuited to iter().
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.iter()
+    }
+
+    /// Short circuited to range().
+    fn range_with_versions<'a, R, Q>(&'a mut self, r: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.range(r)
+    }
+
+    /// Short circuited to reverse()
+    fn reverse_with_versions<'a, R, Q>(&'a mut self, r: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.reverse(r)
+    }
+}
+
+impl<K, V> PiecewiseScan<K, V> for FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries greater than from bound,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let root = self.as_root();
+        let paths = match from {
+            Bound::Unbounded => Some(build_iter(IFlag::Left, root, vec![])?),
+            Bound::Included(low) => Some(find_start(root, low.borrow(), true, vec![])),
+            Bound::Excluded(low) => Some(find_start(root, low.borrow(), false, vec![])),
+        };
+        let (start, end) = util::to_start_end(within);
+        Ok(Box::new(IterPWScan {
+            _latch: None,
+            _arc: Default::default(),
+            start,
+            end,
+            paths,
+        }))
+    }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than from bound, in descending order,
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let root = self.as_root();
+        let paths = match from {
+            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
+            Bound::Included(high) => Some(find_end(root, high.borrow(), true, vec![])),
+            Bound::Excluded(high) => Some(find_end(root, high.borrow(), false, vec![])),
+        };
+        let (start, end) = util::to_start_end(within);
+        Ok(Box::new(IterPWScanRev {
+            _latch: None,
+            _arc: Default::default(),
+            start,
+            end,
+            paths,
+        }))
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for FrozenLlrb<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut ss = Box::new(scans::SkipScan::new(self.clone()));
+        ss.set_seqno_range(within)?;
+        Ok(ss)
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        use std::convert::identity;
+
+        match n_shards {
+            0 => return Ok(vec![]),
+            1 => return Ok(vec![self.scan(within)?]),
+            _ => (),
+        }
+
+        // tree is immutable, shard keys are split points for the ranges.
+        let mut keys = vec![];
+        do_shards(self.as_root(), n_shards - 1, &mut keys);
+        let mut high_keys: Vec<Bound<K>> = keys
+            .into_iter()
+            .filter_map(identity)
+            .map(Bound::Excluded)
+            .collect();
+        high_keys.push(Bound::Unbounded);
+
+        let ranges = util::high_keys_to_ranges(high_keys);
+        let mut scans = self.range_scans(ranges, within)?;
+
+        // If there are not enough shards push empty iterators.
+        for _ in scans.len()..n_shards {
+            let ss = vec![];
+            scans.push(Box::new(ss.into_iter()));
+        }
+
+        Ok(scans)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        N: Clone + RangeBounds<K>,
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut scans: Vec<IndexIter<K, V>> = vec![];
+        for range in ranges {
+            let mut ss = Box::new(scans::SkipScan::new(self.clone()));
+            ss.set_key_range(range)?.set_seqno_range(within.clone())?;
+            scans.push(ss);
+        }
+        Ok(scans)
+    }
+}
+
+impl<K, V> Llrb<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    /// Return a [Cursor] positioned at `key`, whose first call to `next()`
+    /// shall return entry for `key`, or the entry just after `key`. In
+    /// lsm mode deleted entries are returned as well.
+    pub fn cursor_at(&mut self, key: &K) -> Result<Cursor<K, V>> {
+        Ok(Cursor {
+            reader: self.to_reader()?,
+            key: key.clone(),
+            done: false,
+        })
+    }
+}
+
+/// Bidirectional cursor into [Llrb] index, refer to [Llrb::cursor_at].
+///
+/// Cursor does not hold the index latch between calls, instead it
+/// remembers the key of last returned entry and re-seeks from that
+/// key on every call. Hence writes can be interleaved with cursor
+/// movement, and cursor shall observe the latest state of the index
+/// around its position.
+pub struct Cursor<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    reader: LlrbReader<K, V>,
+    key: K,     // position of the cursor.
+    done: bool, // whether entry at key is already returned.
+}
+
+impl<K, V> Cursor<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Return the key at which cursor is positioned.
+    pub fn to_key(&self) -> K {
+        self.key.clone()
+    }
+
+    /// Move the cursor backward, and return the entry just before its
+    /// current position. Return None if there is no such entry.
+    pub fn prev(&mut self) -> Option<Result<Entry<K, V>>> {
+        let index: &mut Llrb<K, V> = self.reader.as_mut();
+        let range = (Bound::Unbounded, Bound::Excluded(self.key.clone()));
+        let entry = match index.reverse(range) {
+            Ok(mut iter) => iter.next(),
+            Err(err) => Some(Err(err)),
+        };
+        self.move_to(entry)
+    }
+
+    fn move_to(&mut self, entry: Option<Result<Entry<K, V>>>) -> Option<Result<Entry<K, V>>> {
+        match entry {
+            Some(Ok(entry)) => {
+                self.key = entry.to_key();
+                self.done = true;
+                Some(Ok(entry))
+            }
+            res => res,
+        }
+    }
+}
+
+impl<K, V> Iterator for Cursor<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    type Item = Result<Entry<K, V>>;
+
+    /// Move the cursor forward, and return the entry just after its
+    /// current position.
+    fn next(&mut self) -> Option<Self::Item> {
+        let index: &mut Llrb<K, V> = self.reader.as_mut();
+        let low = if self.done {
+            Bound::Excluded(self.key.clone())
+        } else {
+            Bound::Included(self.key.clone())
+        };
+        let entry = match index.range((low, Bound::Unbounded)) {
+            Ok(mut iter) => iter.next(),
+            Err(err) => Some(Err(err)),
+        };
+        self.move_to(entry)
+    }
 }
 
 /// Write handle into [Llrb] index.
@@ -2359,6 +3186,16 @@ where
         let (_seqno, entry) = index.delete_index(key, None)?;
         entry
     }
+
+    /// Delete all keys from lower bound to upper bound, return the number
+    /// of entries deleted.
+    fn delete_range<R>(&mut self, range: R) -> Result<usize>
+    where
+        R: Clone + RangeBounds<K>,
+    {
+        let index: &mut Llrb<K, V> = self.as_mut();
+        index.delete_range(range)
+    }
 }
 
 /// Create/Update/Delete operations on Llrb index.
@@ -52,6 +52,19 @@ where
     }
 }
 
+// add all keys under `node` into bitmap.
+fn add_bitmap_keys<K, V>(node: Option<&Node<K, V>>, bitmap: &dyn util::KeyBitmap<K>)
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    if let Some(nref) = node {
+        add_bitmap_keys(nref.as_left_deref(), bitmap);
+        bitmap.add_key(nref.as_key());
+        add_bitmap_keys(nref.as_right_deref(), bitmap);
+    }
+}
+
 /// Get the latest version for key.
 fn get<K, V, Q>(node: Option<&Node<K, V>>, key: &Q) -> Result<Entry<K, V>>
 where
@@ -69,6 +82,23 @@ where
     }
 }
 
+/// Check whether key is present, without cloning the entry.
+fn contains_key<K, V, Q>(mut node: Option<&Node<K, V>>, key: &Q) -> bool
+where
+    K: Clone + Ord + Borrow<Q>,
+    V: Clone + Diff,
+    Q: Ord + ?Sized,
+{
+    while let Some(nref) = node {
+        node = match nref.as_key().borrow().cmp(key) {
+            Ordering::Less => nref.as_right_deref(),
+            Ordering::Greater => nref.as_left_deref(),
+            Ordering::Equal => return true,
+        };
+    }
+    false
+}
+
 // list of validation done by this function
 // * Verify the sort order between a node and its left/right child.
 // * No node which has RIGHT RED child and LEFT BLACK child (or NULL child).
@@ -210,6 +240,65 @@ where
     }
 }
 
+/// Iterator type, to iterate over deleted entries, for both [Llrb] and
+/// [Mvcc] index. Only applicable in lsm mode, where deleted entries are
+/// preserved as tombstones.
+pub struct IterDeleted<'a, K, V>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    _latch: Option<spinlock::Reader<'a>>, // only used for latching
+    _arc: Arc<Snapshot<K, V>>,            // only used for MVCC-snapshot refcount.
+    paths: Option<Vec<Fragment<'a, K, V>>>,
+}
+
+impl<'a, K, V> Iterator for IterDeleted<'a, K, V>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut paths = match self.paths.take() {
+                Some(paths) => paths,
+                None => break None,
+            };
+
+            match paths.pop() {
+                None => break None,
+                Some(mut path) => match (path.flag, path.nref) {
+                    (IFlag::Left, nref) => {
+                        self.paths = {
+                            path.flag = IFlag::Center;
+                            paths.push(path);
+                            Some(paths)
+                        };
+                        // clone only the tombstones.
+                        if nref.is_deleted() {
+                            break Some(Ok(nref.entry.clone()));
+                        }
+                    }
+                    (IFlag::Center, nref) => {
+                        self.paths = {
+                            path.flag = IFlag::Right;
+                            paths.push(path);
+                            let rnref = nref.as_right_deref();
+                            match build_iter(IFlag::Left, rnref, paths) {
+                                Ok(paths) => Some(paths),
+                                Err(err) => break Some(Err(err)),
+                            }
+                        };
+                    }
+                    (_, _) => self.paths = Some(paths),
+                },
+            }
+        }
+    }
+}
+
 /// Iterator type, to do piece-wise full table scan, for both [Llrb] and
 /// [Mvcc] index.
 pub struct IterPWScan<'a, K, V>
@@ -289,6 +378,83 @@ where
     }
 }
 
+/// Iterator type, to do piece-wise reverse table scan, for both [Llrb] and
+/// [Mvcc] index.
+pub struct IterPWScanRev<'a, K, V>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    _latch: Option<spinlock::Reader<'a>>,
+    _arc: Arc<Snapshot<K, V>>, // only used for ref-count-ing MVCC-snapshot.
+    start: Bound<u64>,
+    end: Bound<u64>,
+    paths: Option<Vec<Fragment<'a, K, V>>>,
+}
+
+impl<'a, K, V> Iterator for IterPWScanRev<'a, K, V>
+where
+    K: Ord + Clone,
+    V: Clone + Diff,
+{
+    type Item = Result<ScanEntry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // loop for a maximum of 1000 entries, same as IterPWScan.
+        let mut limit = 1000;
+        let mut key: Option<K> = None;
+        loop {
+            let mut paths = match self.paths.take() {
+                Some(paths) => paths,
+                None => {
+                    break None;
+                }
+            };
+            limit -= 1;
+            if limit < 0 {
+                if let Some(key) = key {
+                    break Some(Ok(ScanEntry::Retry(key)));
+                }
+            }
+
+            match paths.pop() {
+                None => {
+                    break None;
+                }
+                Some(mut path) => match (path.flag, path.nref) {
+                    (IFlag::Right, nref) => {
+                        self.paths = {
+                            path.flag = IFlag::Center;
+                            paths.push(path);
+                            Some(paths)
+                        };
+                        // include if entry was within the visible time-range
+                        let (a, z) = (self.start.clone(), self.end.clone());
+                        match nref.entry.filter_within(a, z) {
+                            Some(entry) => break Some(Ok(ScanEntry::Found(entry))),
+                            None => {
+                                key = Some(nref.entry.to_key());
+                            }
+                        }
+                    }
+                    (IFlag::Center, nref) => {
+                        self.paths = {
+                            path.flag = IFlag::Left;
+                            paths.push(path);
+                            let lnref = nref.as_left_deref();
+                            match build_iter(IFlag::Right, lnref, paths) {
+                                Ok(paths) => Some(paths),
+                                Err(err) => break Some(Err(err)),
+                            }
+                        };
+                    }
+                    (_, _) => self.paths = Some(paths),
+                },
+            };
+        }
+    }
+}
+
 /// Iterator type, to do range scan between a _lower-bound_ and
 /// _higher-bound_, for both [`Llrb`] and [Mvcc] index.
 pub struct Range<'a, K, V, R, Q>
@@ -375,7 +541,7 @@ where
 /// _lower-bound_ for both [`Llrb`] and [Mvcc] index.
 pub struct Reverse<'a, K, V, R, Q>
 where
-    K: Ord + Clone,
+    K: Ord + Clone + Borrow<Q>,
     V: Clone + Diff,
     R: RangeBounds<Q>,
     Q: Ord + ?Sized,
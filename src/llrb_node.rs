@@ -15,6 +15,11 @@ use crate::{
 use crate::llrb::Llrb;
 
 /// Node corresponds to a single entry in Llrb instance.
+///
+/// Key is stored inline, hence fixed size keys like `u64`, `u128` and
+/// `[u8; 16]` cost no allocation other than the node itself. Values no
+/// larger than a pointer are also held inline, larger values are boxed
+/// so that Mvcc snapshots can share them across node copies.
 #[derive(Clone)]
 pub(crate) struct Node<K, V>
 where
@@ -377,6 +377,309 @@ fn test_empty_set_cas() {
     }
 }
 
+#[test]
+fn test_cas_policy() {
+    use crate::core::CasPolicy;
+
+    // create-if-absent
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    index.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+    assert!(index.set_cas(10, 100, 10).unwrap().is_none());
+    assert_eq!(index.set_cas(10, 200, 0).err(), Some(Error::InvalidCAS(1)));
+    assert_eq!(index.set_cas(10, 200, 1).unwrap().unwrap().to_seqno(), 1);
+    index.delete(&10).unwrap();
+    assert!(index.set_cas(10, 300, 100).unwrap().unwrap().is_deleted());
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(300));
+
+    // value-hash
+    fn hash(value: &i64) -> u64 {
+        (*value as u64) + 1
+    }
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    index.set_cas_policy(CasPolicy::ValueHash(hash)).unwrap();
+    assert_eq!(index.set_cas(10, 100, 10).err(), Some(Error::InvalidCAS(0)));
+    assert!(index.set_cas(10, 100, 0).unwrap().is_none());
+    assert_eq!(
+        index.set_cas(10, 200, 1).err(),
+        Some(Error::InvalidCAS(101))
+    );
+    assert!(index.set_cas(10, 200, 101).unwrap().is_some());
+    index.delete(&10).unwrap();
+    assert_eq!(index.set_cas(10, 300, 3).err(), Some(Error::InvalidCAS(0)));
+    assert!(index.set_cas(10, 300, 0).unwrap().is_some());
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(300));
+}
+
+#[test]
+fn test_size_limit() {
+    use crate::core::{SizeLimit, SizePolicy};
+
+    fn truncate(mut value: Vec<u8>, n: usize) -> Vec<u8> {
+        value.truncate(n / 2);
+        value
+    }
+
+    // reject
+    let mut index: Box<Llrb<i64, Vec<u8>>> = Llrb::new_lsm("test-llrb");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::Reject))
+        .unwrap();
+    assert!(index.set(1, vec![0; 10]).unwrap().is_none());
+    match index.set(2, vec![0; 200]) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+    match index.set_cas(1, vec![0; 200], 1) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+    assert_eq!(index.len(), 1);
+
+    // oversized keys are always rejected.
+    let mut index: Box<Llrb<i64, Vec<u8>>> = Llrb::new_lsm("test-llrb");
+    index
+        .set_size_limit(SizeLimit::new(4, 100, SizePolicy::SpillToVlog))
+        .unwrap();
+    match index.set(1, vec![0; 10]) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+
+    // truncate
+    let mut index: Box<Llrb<i64, Vec<u8>>> = Llrb::new_lsm("test-llrb");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::Truncate(truncate)))
+        .unwrap();
+    index.set(1, vec![0; 10]).unwrap();
+    index.set(2, vec![0; 200]).unwrap();
+    assert_eq!(index.get(&1).unwrap().to_native_value().unwrap().len(), 10);
+    assert_eq!(index.get(&2).unwrap().to_native_value().unwrap().len(), 50);
+
+    // spill to vlog
+    let mut index: Box<Llrb<i64, Vec<u8>>> = Llrb::new_lsm("test-llrb");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::SpillToVlog))
+        .unwrap();
+    index.set(2, vec![0; 200]).unwrap();
+    assert_eq!(index.get(&2).unwrap().to_native_value().unwrap().len(), 200);
+    assert_eq!(index.to_size_limit().max_value, 100);
+}
+
+#[test]
+fn test_bitmap() {
+    use crate::croaring::CRoaring;
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+    index.set_bitmap(CRoaring::create()).unwrap();
+    index.set(1000, 10000).unwrap();
+    index.delete(&2000).unwrap();
+
+    for key in (0..100).chain(vec![1000].into_iter()) {
+        assert_eq!(index.get(&key).unwrap().to_native_value(), Some(key * 10));
+        assert!(index.contains(&key).unwrap());
+    }
+    assert!(index.get(&2000).unwrap().is_deleted());
+    for key in 100..200 {
+        match index.get(&key) {
+            Err(Error::KeyNotFound) => (),
+            res => panic!("{:?}", res.map(|e| e.to_seqno())),
+        }
+        assert!(!index.contains(&key).unwrap());
+    }
+
+    let _r = index.to_reader().unwrap();
+    match index.set_bitmap(CRoaring::create()) {
+        Err(Error::APIMisuse(_)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_interner() {
+    use crate::types::{ArcKey, Interner};
+    use std::sync::Arc;
+
+    let key = |i: i64| ArcKey::new(format!("key-{:090}", i).into_bytes());
+    let fp = key(0).footprint().unwrap();
+
+    let interner: Arc<Interner<Vec<u8>>> = Arc::new(Interner::new());
+    let mut index: Box<Llrb<ArcKey<Vec<u8>>, i64>> = Llrb::new_lsm("test-llrb");
+    for i in 0..100 {
+        index.set(key(i), i).unwrap();
+    }
+    index.set_interner(Arc::clone(&interner)).unwrap();
+    let stats = interner.to_stats().unwrap();
+    assert_eq!((stats.n_lookups, stats.n_hits, stats.n_keys), (100, 0, 100));
+
+    // repeated keys, in fresh allocations, are dropped.
+    for i in 0..100 {
+        index.delete(&key(i)).unwrap();
+        index.set(key(i), i * 10).unwrap();
+    }
+    index.set(key(100), 1000).unwrap();
+    let stats = interner.to_stats().unwrap();
+    assert_eq!(
+        (stats.n_lookups, stats.n_hits, stats.n_keys),
+        (301, 200, 101)
+    );
+    assert_eq!(stats.saved_bytes, fp * 100);
+
+    // indexes sharing the interner share the key.
+    let mut other: Box<Llrb<ArcKey<Vec<u8>>, i64>> = Llrb::new_lsm("test-other");
+    other.set_interner(Arc::clone(&interner)).unwrap();
+    other.set(key(0), 1).unwrap();
+    let k1 = index.get(&key(0)).unwrap().to_key();
+    let k2 = other.get(&key(0)).unwrap().to_key();
+    assert!(Arc::ptr_eq(&k1.to_arc(), &k2.to_arc()));
+    assert_eq!(interner.to_stats().unwrap().saved_bytes, fp * 101);
+    mem::drop((k1, k2));
+
+    assert_eq!(interner.purge().unwrap(), 0);
+    mem::drop(index);
+    assert_eq!(interner.purge().unwrap(), 100);
+    assert_eq!(interner.to_stats().unwrap().n_keys, 1);
+
+    let _r = other.to_reader().unwrap();
+    match other.set_interner(Arc::clone(&interner)) {
+        Err(Error::APIMisuse(_)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_range_count() {
+    for lsm in vec![false, true].into_iter() {
+        let mut index: Box<Llrb<i64, i64>> = if lsm {
+            Llrb::new_lsm("test-llrb")
+        } else {
+            Llrb::new("test-llrb")
+        };
+        for key in 0..100 {
+            index.set(key, key * 10).unwrap();
+        }
+        index.delete(&10).unwrap();
+        index.delete(&20).unwrap();
+
+        assert_eq!(index.range_count(..).unwrap(), 98, "lsm:{}", lsm);
+        assert_eq!(index.range_count(10..30).unwrap(), 18, "lsm:{}", lsm);
+        let range = (Bound::Excluded(50), Bound::Included(60));
+        assert_eq!(index.range_count(range).unwrap(), 10, "lsm:{}", lsm);
+        assert_eq!(index.range_count(200..).unwrap(), 0, "lsm:{}", lsm);
+
+        assert_eq!(index.delete_range(25..35).unwrap(), 10, "lsm:{}", lsm);
+        {
+            let mut w = index.to_writer().unwrap();
+            assert_eq!(w.delete_range(20..40).unwrap(), 9, "lsm:{}", lsm);
+            assert_eq!(w.delete_range(20..40).unwrap(), 0, "lsm:{}", lsm);
+        }
+        {
+            let mut r = index.to_reader().unwrap();
+            assert_eq!(r.range_count(..).unwrap(), 79, "lsm:{}", lsm);
+            assert_eq!(r.range_count(..=40).unwrap(), 20, "lsm:{}", lsm);
+        }
+        for key in 20..40 {
+            match index.get(&key) {
+                Ok(entry) if lsm => assert!(entry.is_deleted()),
+                Err(Error::KeyNotFound) if !lsm => (),
+                res => panic!("{:?}", res.map(|e| e.to_seqno())),
+            }
+        }
+        index.validate().unwrap();
+    }
+}
+
+#[test]
+fn test_freeze() {
+    use std::thread;
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+    index.delete(&5).unwrap();
+
+    let frozen = index.freeze().unwrap();
+    assert_eq!(frozen.len(), 100);
+    assert_eq!(frozen.to_seqno(), 101);
+    assert_eq!(index.len(), 0);
+    index.set(1000, 10000).unwrap();
+    assert_eq!(index.to_seqno().unwrap(), 102);
+    match index.get(&10) {
+        Err(Error::KeyNotFound) => (),
+        res => panic!("{:?}", res.map(|e| e.to_seqno())),
+    }
+
+    let mut handles = vec![];
+    for _ in 0..4 {
+        let mut frozen = frozen.clone();
+        handles.push(thread::spawn(move || {
+            for key in (0..100).filter(|key| *key != 5) {
+                let entry = frozen.get(&key).unwrap();
+                assert_eq!(entry.to_native_value(), Some(key * 10));
+            }
+            assert!(frozen.get(&5).unwrap().is_deleted());
+            assert!(!frozen.contains(&1000).unwrap());
+            assert_eq!(frozen.iter().unwrap().count(), 100);
+            assert_eq!(frozen.range(10..20).unwrap().count(), 10);
+            assert_eq!(frozen.reverse(..=20).unwrap().count(), 21);
+            assert_eq!(frozen.range_count(..).unwrap(), 99);
+        }));
+    }
+    for handle in handles.into_iter() {
+        handle.join().unwrap();
+    }
+
+    let mut frozen = frozen;
+    assert_eq!(frozen.scan(..).unwrap().count(), 100);
+    let scans = frozen.scans(4, ..).unwrap();
+    assert_eq!(scans.len(), 4);
+    let keys: Vec<i64> = scans
+        .into_iter()
+        .flat_map(|iter| iter.map(|e| e.unwrap().to_key()))
+        .collect();
+    assert_eq!(keys, (0..100).collect::<Vec<i64>>());
+
+    let _r = index.to_reader().unwrap();
+    match index.freeze() {
+        Err(Error::APIMisuse(_)) => (),
+        res => panic!("{:?}", res.map(|f| f.len())),
+    }
+}
+
+#[test]
+fn test_get_or_insert_with() {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    llrb.set(10, 100).unwrap();
+
+    let mut calls = 0;
+    let entry = llrb
+        .get_or_insert_with(10, || {
+            calls += 1;
+            200
+        })
+        .unwrap();
+    assert_eq!(calls, 0);
+    assert_eq!(entry.to_native_value(), Some(100));
+    assert_eq!(entry.to_seqno(), 1);
+
+    let entry = llrb.get_or_insert_with(20, || 300).unwrap();
+    assert_eq!(entry.to_native_value(), Some(300));
+    assert_eq!(entry.to_seqno(), 2);
+    assert_eq!(llrb.to_seqno().unwrap(), 2);
+    assert_eq!(llrb.len(), 2);
+
+    llrb.delete(&10).unwrap();
+    let entry = llrb.get_or_insert_with(10, || 400).unwrap();
+    assert_eq!(entry.to_native_value(), Some(400));
+    assert_eq!(entry.to_seqno(), 4);
+    assert_eq!(entry.versions_iter().count(), 3);
+    assert_eq!(llrb.get(&10).unwrap().to_native_value(), Some(400));
+    assert!(llrb.validate().is_ok());
+}
+
 #[test]
 fn test_set() {
     let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
@@ -412,7 +715,10 @@ fn test_set() {
         let entry = llrb.get(&i);
         let refn = refns.get(i);
         check_node(entry.ok(), refn);
+        assert!(llrb.contains(&i).unwrap());
     }
+    assert!(!llrb.contains(&10).unwrap());
+    assert!(!llrb.contains(&-1).unwrap());
     // test iter
     {
         let (mut iter, mut iter_ref) = (llrb.iter().unwrap(), refns.iter());
@@ -573,6 +879,81 @@ fn test_delete() {
     assert!(llrb.iter().unwrap().next().is_none());
 }
 
+#[test]
+fn test_delete_get_versions() {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+
+    let (seqno, entry) = llrb.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 1);
+    assert!(entry.is_none());
+    for i in 0..3 {
+        llrb.set(1, 100 + i).unwrap();
+    }
+    llrb.set(2, 200).unwrap();
+
+    let (seqno, entry) = llrb.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 6);
+    let entry = entry.unwrap();
+    assert_eq!(entry.is_deleted(), false);
+    let versions: Vec<(u64, Option<i64>)> = entry.versions_iter().collect();
+    assert_eq!(
+        versions,
+        vec![(4, Some(102)), (3, Some(101)), (2, Some(100)), (1, None)]
+    );
+
+    let (seqno, entry) = llrb.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 7);
+    let entry = entry.unwrap();
+    assert_eq!(entry.is_deleted(), true);
+    assert_eq!(entry.versions_iter().count(), 5);
+    assert!(llrb.validate().is_ok());
+}
+
+#[test]
+fn test_sequential_small_stack() {
+    let n = 100_000_i64;
+    let handle = std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(move || {
+            for (lsm, sticky) in vec![(false, false), (true, false), (false, true)] {
+                let mut llrb: Box<Llrb<i64, i64>> = if lsm {
+                    Llrb::new_lsm("test-llrb")
+                } else {
+                    Llrb::new("test-llrb")
+                };
+                llrb.set_sticky(sticky).unwrap();
+
+                for key in 0..n {
+                    assert!(llrb.set(key, key).unwrap().is_none());
+                }
+                for key in 0..n {
+                    llrb.set_cas(key, key + 1, (key + 1) as u64).unwrap();
+                }
+                for key in (0..n).step_by(2) {
+                    let entry = llrb.delete(&key).unwrap().unwrap();
+                    assert_eq!(entry.to_native_value(), Some(key + 1));
+                }
+                assert!(llrb.validate().is_ok(), "lsm:{} sticky:{}", lsm, sticky);
+
+                let n_deleted = (n / 2) as usize;
+                match lsm || sticky {
+                    true => assert_eq!(llrb.len(), n as usize),
+                    false => assert_eq!(llrb.len(), n as usize - n_deleted),
+                }
+                for key in 0..n {
+                    let entry = llrb.get(&key).ok();
+                    match (key % 2, lsm || sticky) {
+                        (0, true) => assert!(entry.unwrap().is_deleted()),
+                        (0, false) => assert!(entry.is_none()),
+                        _ => assert_eq!(entry.unwrap().to_native_value(), Some(key + 1)),
+                    }
+                }
+            }
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
+
 #[test]
 fn test_iter() {
     let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
@@ -748,6 +1129,42 @@ fn test_range() {
 //    assert!(iter.next().is_none());
 //}
 
+#[test]
+fn test_range_prefix() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let mut llrb: Box<Llrb<Vec<u8>, i64>> = Llrb::new_lsm("test-llrb");
+    let mut keys = vec![];
+    for _ in 0..1000 {
+        let key = format!("{:02x}/{}", rng.gen::<u8>() % 16, rng.gen::<u16>());
+        let key = key.as_bytes().to_vec();
+        llrb.set(key.clone(), rng.gen()).unwrap();
+        keys.push(key);
+    }
+    keys.sort();
+    keys.dedup();
+
+    for tenant in 0..17_u8 {
+        let prefix = format!("{:02x}/", tenant).as_bytes().to_vec();
+        let ref_keys: Vec<Vec<u8>> = keys
+            .iter()
+            .filter(|k| k.starts_with(&prefix))
+            .cloned()
+            .collect();
+        let keys: Vec<Vec<u8>> = llrb
+            .range_prefix(prefix)
+            .unwrap()
+            .map(|e| e.unwrap().to_key())
+            .collect();
+        assert_eq!(keys, ref_keys);
+    }
+
+    let n = llrb.range_prefix(vec![]).unwrap().count();
+    assert_eq!(n, keys.len());
+}
+
 #[test]
 fn test_crud() {
     let size = 1000;
@@ -924,6 +1341,44 @@ fn test_crud_lsm() {
     }
 }
 
+#[test]
+fn test_iter_deleted() {
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+
+    let mut ref_seqnos = vec![];
+    for key in 0..1000_i64 {
+        llrb.set(key, key * 10).unwrap();
+    }
+    for key in (0..1000_i64).step_by(7) {
+        llrb.delete(&key).unwrap();
+        ref_seqnos.push((key, llrb.to_seqno().unwrap()));
+    }
+    // tombstones that are set again are not deleted anymore.
+    for key in (0..1000_i64).step_by(21) {
+        llrb.set(key, key).unwrap();
+    }
+    ref_seqnos.retain(|(key, _)| key % 21 != 0);
+
+    let items: Vec<(i64, u64)> = {
+        let iter = llrb.iter_deleted().unwrap();
+        iter.map(|e| e.unwrap())
+            .map(|e| {
+                assert!(e.is_deleted());
+                (e.to_key(), e.to_seqno())
+            })
+            .collect()
+    };
+    assert_eq!(items, ref_seqnos);
+
+    // in non-lsm mode deleted entries are removed.
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-llrb");
+    for key in 0..100_i64 {
+        llrb.set(key, key * 10).unwrap();
+    }
+    llrb.delete(&10).unwrap();
+    assert_eq!(llrb.iter_deleted().unwrap().count(), 0);
+}
+
 #[test]
 fn test_pw_scan() {
     let mut llrb: Box<Llrb<i32, i32>> = Llrb::new_lsm("test-llrb");
@@ -1076,6 +1531,50 @@ fn test_pw_scan() {
     assert!(llrb.validate().is_ok());
 }
 
+#[test]
+fn test_pw_scan_rev() {
+    let mut llrb: Box<Llrb<i32, i32>> = Llrb::new_lsm("test-llrb");
+
+    for key in 0..10000 {
+        let value = (key + 1) * 100;
+        assert!(llrb.set(key, value).unwrap().is_none());
+    }
+    let seqno1 = llrb.to_seqno().unwrap();
+    for key in (0..1000).step_by(3) {
+        let value = (key + 1) * 1000;
+        assert!(llrb.set(key, value).unwrap().is_some());
+    }
+
+    // descending scan, rolled back to seqno1, stitched in small batches.
+    let mut iter = scans::SkipScan::new_rev(llrb.to_reader().unwrap());
+    iter.set_seqno_range(..=seqno1).unwrap();
+    iter.set_key_range(100..=5000).unwrap();
+    iter.set_batch_size(7).unwrap();
+    let keys: Vec<i32> = (100..=5000).rev().collect();
+    let mut n = 0;
+    for (entry, key) in iter.zip(keys.iter()) {
+        let entry = entry.unwrap();
+        assert_eq!(entry.to_key(), *key);
+        assert_eq!(entry.to_native_value().unwrap(), (key + 1) * 100);
+        n += 1;
+    }
+    assert_eq!(n, keys.len());
+
+    // only the injected keys, most of the scan is filtered out and retried.
+    let mut iter = scans::SkipScan::new_rev(llrb.to_reader().unwrap());
+    iter.set_seqno_range((Bound::Excluded(seqno1), Bound::Unbounded))
+        .unwrap();
+    let keys: Vec<i32> = iter.map(|e| e.unwrap().to_key()).collect();
+    let ref_keys: Vec<i32> = (0..1000).step_by(3).rev().collect();
+    assert_eq!(keys, ref_keys);
+
+    // latest N items under the upper-bound.
+    let mut iter = scans::SkipScan::new_rev(llrb.to_reader().unwrap());
+    iter.set_key_range(..500).unwrap();
+    let keys: Vec<i32> = iter.take(3).map(|e| e.unwrap().to_key()).collect();
+    assert_eq!(keys, vec![499, 498, 497]);
+}
+
 #[test]
 fn test_mvcc_conversion() {
     let seed: u128 = random();
@@ -1214,6 +1713,48 @@ fn test_split() {
     }
 }
 
+#[test]
+fn test_compact_memory() {
+    let mut index: Box<Llrb<i64, Vec<u8>>> = Llrb::new("test-llrb");
+    for key in 0..1000_i64 {
+        let mut value = Vec::with_capacity(1024);
+        value.extend_from_slice(&key.to_be_bytes());
+        index.set(key, value).unwrap();
+    }
+    for key in (0..1000_i64).filter(|key| key % 3 != 0) {
+        index.delete(&key).unwrap();
+    }
+
+    {
+        let _r = index.to_reader().unwrap();
+        match index.compact_memory() {
+            Err(Error::APIMisuse(_)) => (),
+            res => panic!("expected APIMisuse {:?}", res),
+        }
+    }
+
+    let footprint = index.footprint().unwrap();
+    let reclaimed = index.compact_memory().unwrap();
+    assert!(reclaimed >= 334 * (1024 - 8), "{}", reclaimed);
+    assert_eq!(index.footprint().unwrap(), footprint - reclaimed);
+    // nothing more to reclaim on a second sweep.
+    assert_eq!(index.compact_memory().unwrap(), 0);
+    assert!(index.validate().is_ok());
+
+    assert_eq!(index.len(), 334);
+    for key in 0..1000_i64 {
+        match index.get(&key) {
+            Ok(entry) if key % 3 == 0 => {
+                assert_eq!(entry.to_native_value(), Some(key.to_be_bytes().to_vec()));
+            }
+            Err(Error::KeyNotFound) if key % 3 != 0 => (),
+            res => panic!("key:{} {:?}", key, res.map(|e| e.to_native_value())),
+        }
+    }
+    index.set(1, vec![1]).unwrap();
+    assert_eq!(index.len(), 335);
+}
+
 #[test]
 fn test_commit1() {
     let mut index1: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-index1");
@@ -1334,6 +1875,28 @@ fn test_commit3() {
     }
 }
 
+#[test]
+fn test_from_entries() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+
+    for lsm in [false, true].iter() {
+        let mut index: Box<Llrb<i64, i64>> = if *lsm {
+            Llrb::new_lsm("test-llrb")
+        } else {
+            Llrb::new("test-llrb")
+        };
+        random_llrb(10_000, 1_000, seed, &mut index);
+
+        let mut rindex = {
+            let iter = index.iter_with_versions().unwrap();
+            Llrb::from_entries("test-from-entries", *lsm, iter).unwrap()
+        };
+        assert!(rindex.validate().is_ok());
+        check_commit_nodes(&mut index, &mut rindex);
+    }
+}
+
 fn check_commit_nodes(index: &mut Llrb<i64, i64>, rindex: &mut Llrb<i64, i64>) {
     // verify root index
     assert_eq!(index.seqno, rindex.seqno);
@@ -1766,3 +2329,44 @@ fn check_node1(entry: &Entry<i64, i64>, ref_entry: &Entry<i64, i64>) {
         }
     }
 }
+
+#[test]
+fn test_cursor() {
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-cursor");
+    for key in (0..100).step_by(10) {
+        index.set(key, key * 10).unwrap();
+    }
+
+    let mut cursor = index.cursor_at(&25).unwrap();
+    assert_eq!(cursor.next().unwrap().unwrap().to_key(), 30);
+    assert_eq!(cursor.next().unwrap().unwrap().to_key(), 40);
+
+    // writes interleaved with cursor movement.
+    index.set(45, 450).unwrap();
+    index.delete(&50).unwrap();
+    assert_eq!(cursor.next().unwrap().unwrap().to_key(), 45);
+    let entry = cursor.next().unwrap().unwrap();
+    assert_eq!(entry.to_key(), 50);
+    assert!(entry.is_deleted());
+    assert_eq!(cursor.to_key(), 50);
+
+    assert_eq!(cursor.prev().unwrap().unwrap().to_key(), 45);
+    index.delete(&40).unwrap();
+    index.set(35, 350).unwrap();
+    let entry = cursor.prev().unwrap().unwrap();
+    assert_eq!(entry.to_key(), 40);
+    assert!(entry.is_deleted());
+    assert_eq!(cursor.prev().unwrap().unwrap().to_key(), 35);
+
+    let keys: Vec<i64> = cursor.map(|e| e.unwrap().to_key()).collect();
+    assert_eq!(keys, vec![40, 45, 50, 60, 70, 80, 90]);
+
+    // cursor at the edges.
+    let mut cursor = index.cursor_at(&0).unwrap();
+    assert!(cursor.prev().is_none());
+    assert_eq!(cursor.next().unwrap().unwrap().to_key(), 0);
+    assert!(cursor.prev().is_none());
+    let mut cursor = index.cursor_at(&100).unwrap();
+    assert!(cursor.next().is_none());
+    assert_eq!(cursor.prev().unwrap().unwrap().to_key(), 90);
+}
@@ -4,7 +4,7 @@
 use std::{borrow::Borrow, cmp, hash::Hash};
 
 use crate::{
-    core::{Diff, Entry, Footprint, IndexIter, Reader, Result},
+    core::{Diff, Entry, Footprint, IndexIter, LastWriteWins, MergePolicy, Reader, Result},
     error::Error,
 };
 
@@ -63,13 +63,31 @@ where
 ///
 /// NOTE: Iterator `x` contains newer mutations than iterator `y`.
 pub fn y_iter<'a, K, V>(
+    x: IndexIter<'a, K, V>, // newer
+    y: IndexIter<'a, K, V>, // older
+    reverse: bool,
+) -> IndexIter<'a, K, V>
+where
+    K: 'a + Clone + Ord,
+    V: 'a + Clone + Diff,
+{
+    y_iter_merge(x, y, reverse, LastWriteWins)
+}
+
+/// Same as [y_iter], but entries from both iterators, for the same key,
+/// are resolved using the supplied merge `policy`.
+///
+/// NOTE: Iterator `x` contains newer mutations than iterator `y`.
+pub fn y_iter_merge<'a, K, V, P>(
     mut x: IndexIter<'a, K, V>, // newer
     mut y: IndexIter<'a, K, V>, // older
     reverse: bool,
+    policy: P,
 ) -> IndexIter<'a, K, V>
 where
     K: 'a + Clone + Ord,
     V: 'a + Clone + Diff,
+    P: 'a + MergePolicy<K, V>,
 {
     let x_entry = x.next();
     let y_entry = y.next();
@@ -79,11 +97,12 @@ where
         x_entry,
         y_entry,
         reverse,
+        policy,
     })
 }
 
-/// Iterator type, returned by [y_iter].
-pub struct YIter<'a, K, V>
+/// Iterator type, returned by [y_iter] and [y_iter_merge].
+pub struct YIter<'a, K, V, P = LastWriteWins>
 where
     K: 'a + Clone + Ord,
     V: 'a + Clone + Diff,
@@ -93,12 +112,14 @@ where
     x_entry: Option<Result<Entry<K, V>>>,
     y_entry: Option<Result<Entry<K, V>>>,
     reverse: bool,
+    policy: P,
 }
 
-impl<'a, K, V> Iterator for YIter<'a, K, V>
+impl<'a, K, V, P> Iterator for YIter<'a, K, V, P>
 where
     K: 'a + Clone + Ord,
     V: 'a + Clone + Diff,
+    P: MergePolicy<K, V>,
 {
     type Item = Result<Entry<K, V>>;
 
@@ -125,11 +146,7 @@ where
                     cmp::Ordering::Equal => {
                         self.x_entry = self.x.next();
                         self.y_entry = self.y.next();
-                        match xe.to_seqno().cmp(&ye.to_seqno()) {
-                            cmp::Ordering::Less => Some(Ok(ye)),
-                            cmp::Ordering::Greater => Some(Ok(xe)),
-                            cmp::Ordering::Equal => Some(Ok(xe)),
-                        }
+                        Some(self.policy.merge(xe, ye))
                     }
                 }
             }
@@ -1536,6 +1536,72 @@ fn test_lsm_reverse_versions2() {
     assert!(mvcc.validate().is_ok());
 }
 
+#[test]
+fn test_lsm_iter_merge() {
+    use crate::core::{LastWriteWins, MaxBy, MergeFn, Value};
+
+    let mut y: Box<Llrb<i64, i64>> = Llrb::new("test-merge-older");
+    for key in 5..15 {
+        y.set(key, 1000 + key).unwrap();
+    }
+    let mut x: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-merge-newer");
+    x.set_seqno(y.to_seqno().unwrap()).unwrap();
+    for key in 0..10 {
+        x.set(key, 10 * key).unwrap();
+    }
+    x.delete(&9).unwrap();
+
+    let to_values = |iter: IndexIter<i64, i64>| -> Vec<(i64, Option<i64>)> {
+        iter.map(|e| e.unwrap())
+            .map(|e| (e.to_key(), e.to_native_value()))
+            .collect()
+    };
+
+    let iter = {
+        let (xi, yi) = (x.iter().unwrap(), y.iter().unwrap());
+        y_iter_merge(xi, yi, false, LastWriteWins)
+    };
+    let refs: Vec<(i64, Option<i64>)> = (0..15)
+        .map(|key| match key {
+            9 => (key, None),
+            key if key < 10 => (key, Some(10 * key)),
+            key => (key, Some(1000 + key)),
+        })
+        .collect();
+    assert_eq!(to_values(iter), refs);
+
+    let iter = {
+        let (xi, yi) = (x.iter().unwrap(), y.iter().unwrap());
+        y_iter_merge(xi, yi, false, MaxBy(|a: &i64, b: &i64| a.cmp(b)))
+    };
+    let refs: Vec<(i64, Option<i64>)> = (0..15)
+        .map(|key| match key {
+            key if key < 5 => (key, Some(10 * key)),
+            key => (key, Some(1000 + key)),
+        })
+        .collect();
+    assert_eq!(to_values(iter), refs);
+
+    let iter = {
+        let (xi, yi) = (x.iter().unwrap(), y.iter().unwrap());
+        let counter = |n: Entry<i64, i64>, o: Entry<i64, i64>| -> Result<Entry<i64, i64>> {
+            let value = n.to_native_value().unwrap_or(0) + o.to_native_value().unwrap_or(0);
+            let value = Value::new_upsert_value(value, n.to_seqno());
+            Ok(Entry::new(n.to_key(), value))
+        };
+        y_iter_merge(xi, yi, false, MergeFn(counter))
+    };
+    let refs: Vec<(i64, Option<i64>)> = (0..15)
+        .map(|key| match key {
+            key if key < 5 => (key, Some(10 * key)),
+            9 => (key, Some(1009)),
+            key if key < 10 => (key, Some(1000 + key + 10 * key)),
+            key => (key, Some(1000 + key)),
+        })
+        .collect();
+    assert_eq!(to_values(iter), refs);
+}
+
 fn random_llrb(
     n_ops: i64,
     key_max: i64,
@@ -27,6 +27,14 @@
 //! *seqno*, application can set the beginning sequence number before
 //! ingesting data into the index.
 //!
+//! *commit_interval*, by default every mutation is published to readers
+//! as a new snapshot. Mutations can be batched into a single snapshot
+//! using _set_commit_interval()_, refer to [CommitInterval].
+//!
+//! *large values*, iterating over the index clones every entry and its
+//! value. Wrap large values with [ArcValue][crate::types::ArcValue] so
+//! that iteration yields shared handles instead of deep copies.
+//!
 //! [llrb]: https://en.wikipedia.org/wiki/Left-leaning_red-black_tree
 //! [mvcc]: https://en.wikipedia.org/wiki/Multiversion_concurrency_control
 //! [LSM mode]: https://en.wikipedia.org/wiki/Log-structured_merge-tree
@@ -48,13 +56,13 @@ use std::{
         atomic::{AtomicIsize, AtomicPtr, AtomicUsize, Ordering::SeqCst},
         Arc,
     },
-    thread,
+    thread, time,
 };
 
 use crate::{
-    core::{CommitIter, Cutoff, Result, ScanEntry, ScanIter, Value, WalWriter},
-    core::{CommitIterator, ToJson, Validate, WriteIndexFactory, Writer},
-    core::{Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
+    core::{Bloom, Diff, Entry, Footprint, Index, IndexIter, PiecewiseScan, Reader},
+    core::{CasPolicy, CommitIterator, Replay, ToJson, Validate, WriteIndexFactory, Writer},
+    core::{CommitIter, Cutoff, Result, ScanEntry, ScanIter, SizeLimit, Value, WalWriter},
     error::Error,
     llrb::Llrb,
     llrb_node::{LlrbDepth, Node},
@@ -150,6 +158,30 @@ where
     }
 }
 
+/// Write-batch mode for [Mvcc] index, refer to [Mvcc::set_commit_interval].
+///
+/// By default, every mutation shifts a new snapshot, allocating a new
+/// snapshot and swapping it with the current one. Under write bursts of
+/// small values, this can dominate the cost of a mutation. In batch mode,
+/// mutations from writers are applied on a pending tree and shifted as a
+/// single snapshot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommitInterval {
+    /// Shift a new snapshot for every mutation.
+    Immediate,
+    /// Shift a new snapshot once for every `n` mutations.
+    Ops(usize),
+    /// Shift a new snapshot on the first mutation after the interval,
+    /// in micro-seconds, has elapsed since the batch started.
+    Micros(u64),
+}
+
+impl Default for CommitInterval {
+    fn default() -> CommitInterval {
+        CommitInterval::Immediate
+    }
+}
+
 /// A [Mvcc][mvcc] variant of [LLRB][llrb] index for concurrent readers,
 /// serialized writers.
 ///
@@ -164,8 +196,13 @@ where
     lsm: bool,
     sticky: bool,
     spin: bool,
+    cas_policy: CasPolicy<V>,
+    size_limit: SizeLimit<V>,
+    bitmap: Option<Arc<dyn util::KeyBitmap<K>>>,
+    commit_interval: CommitInterval,
 
     snapshot: OuterSnapshot<K, V>,
+    batch: Option<Batch<K, V>>,
     latch: RWSpinlock,
     key_footprint: isize,
     tree_footprint: isize,
@@ -193,6 +230,9 @@ where
             );
         }
 
+        // mutations in a pending write-batch are part of the tree.
+        self.shift_batch();
+
         // NOTE: Means all references to mvcc are gone and ownership is
         // going out of scope. This also implies that there are only
         // TWO Arc<snapshots>. One is held by self.snapshot and another
@@ -248,7 +288,9 @@ where
         };
         mvcc_index
             .set_sticky(llrb_index.is_sticky())?
-            .set_spinlatch(llrb_index.is_spin())?;
+            .set_spinlatch(llrb_index.is_spin())?
+            .set_cas_policy(llrb_index.to_cas_policy())?
+            .set_size_limit(llrb_index.to_size_limit())?;
         mvcc_index
             .snapshot
             .n_nodes
@@ -289,8 +331,13 @@ where
             lsm: false,
             sticky: false,
             spin: true,
+            cas_policy: Default::default(),
+            size_limit: Default::default(),
+            bitmap: None,
+            commit_interval: Default::default(),
 
             snapshot: OuterSnapshot::new(),
+            batch: None,
             latch: RWSpinlock::new(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -310,8 +357,13 @@ where
             lsm: true,
             sticky: false,
             spin: true,
+            cas_policy: Default::default(),
+            size_limit: Default::default(),
+            bitmap: None,
+            commit_interval: Default::default(),
 
             snapshot: OuterSnapshot::new(),
+            batch: None,
             latch: RWSpinlock::new(),
             key_footprint: Default::default(),
             tree_footprint: Default::default(),
@@ -352,10 +404,88 @@ where
         }
     }
 
+    /// Configure the compare-and-set policy for [set_cas][Writer::set_cas]
+    /// operations, refer to [CasPolicy] for details. Call this api, before
+    /// creating reader and/or writer handles.
+    pub fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.cas_policy = policy;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Configure maximum size of keys and values, and the policy for
+    /// oversized values, refer to [SizeLimit] for details. Call this
+    /// api, before creating reader and/or writer handles.
+    pub fn set_size_limit(&mut self, limit: SizeLimit<V>) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.size_limit = limit;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Configure a bitmap, like the one built by the last flush into a
+    /// disk index, to fail fast on [get][Reader::get] and
+    /// [contains][Reader::contains] for keys known to be missing,
+    /// without walking the tree. Keys already in this index, and keys
+    /// written after this call, are added to the bitmap. Call this api,
+    /// before creating reader and/or writer handles.
+    pub fn set_bitmap<B>(&mut self, bitmap: B) -> Result<&mut Self>
+    where
+        K: 'static + Hash,
+        B: 'static + Send + Sync + Bloom,
+    {
+        let n = self.multi_rw();
+        if n == 0 {
+            let bitmap = util::new_key_bitmap(bitmap);
+            let s: Arc<Snapshot<K, V>> = OuterSnapshot::clone(&self.snapshot);
+            let root = match &self.batch {
+                Some(batch) => batch.root.as_ref().map(Deref::deref),
+                None => s.as_root(),
+            };
+            add_bitmap_keys(root, bitmap.as_ref());
+            self.bitmap = Some(bitmap);
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Configure write-batch mode, refer to [CommitInterval] for details.
+    /// Mutations in a batch are visible to readers only after the batch
+    /// is shifted into a new snapshot, or after the batch is published
+    /// via [publish][Mvcc::publish]. Call this api, before creating
+    /// reader and/or writer handles.
+    pub fn set_commit_interval(&mut self, interval: CommitInterval) -> Result<&mut Self> {
+        let n = self.multi_rw();
+        if n == 0 {
+            self.shift_batch();
+            self.commit_interval = interval;
+            Ok(self)
+        } else {
+            err_at!(APIMisuse, msg: format!("active-handles:{}", n))
+        }
+    }
+
+    /// Publish mutations in the pending write-batch, if any, as a new
+    /// snapshot.
+    pub fn publish(&mut self) -> Result<()> {
+        let _w = self.latch.acquire_write(self.spin);
+        self.shift_batch();
+        Ok(())
+    }
+
     /// Squash this index and return the root and its book-keeping.
     pub(crate) fn squash(mut self) -> Result<SquashDebris<K, V>> {
         let n = self.multi_rw();
         if n == 0 {
+            self.shift_batch();
             let snapshot = {
                 let snap_inner = self.snapshot.inner.load(SeqCst);
                 Arc::get_mut(unsafe { snap_inner.as_mut().unwrap() }).unwrap()
@@ -401,8 +531,13 @@ where
             lsm: self.lsm,
             sticky: self.sticky,
             spin: self.spin,
+            cas_policy: self.cas_policy,
+            size_limit: self.size_limit,
+            bitmap: self.bitmap.clone(),
+            commit_interval: self.commit_interval,
 
             snapshot: OuterSnapshot::new(),
+            batch: None,
             latch: RWSpinlock::new(),
             key_footprint: self.key_footprint,
             tree_footprint: self.tree_footprint,
@@ -413,13 +548,16 @@ where
         });
 
         let s: Arc<Snapshot<K, V>> = OuterSnapshot::clone(&self.snapshot);
-        let seqno = OuterSnapshot::clone(&self.snapshot).seqno;
-        let n_count = self.to_stats().unwrap().entries;
+        let (seqno, n_count) = self.to_latest();
         cloned
             .snapshot
             .n_nodes
             .store(n_count.try_into().unwrap(), SeqCst);
-        let root_node = match s.as_root() {
+        let root = match &self.batch {
+            Some(batch) => batch.root.as_ref().map(Deref::deref),
+            None => s.as_root(),
+        };
+        let root_node = match root {
             None => None,
             Some(n) => Some(Box::new(n.clone())),
         };
@@ -429,6 +567,70 @@ where
 
         Ok(cloned)
     }
+
+    /// Rebuild the latest snapshot into freshly allocated nodes, in key
+    /// order. Older nodes are reclaimed along with the older snapshot.
+    /// Fails if there are active read/write handles, refer to
+    /// [Llrb::compact_memory] for details.
+    ///
+    /// Return the number of bytes reclaimed, as accounted by footprint.
+    pub fn compact_memory(&mut self) -> Result<isize>
+    where
+        K: Footprint,
+        V: Footprint,
+    {
+        let n = self.multi_rw();
+        if n > 0 {
+            return err_at!(APIMisuse, msg: format!("active-handles:{}", n));
+        }
+
+        let _latch = self.latch.acquire_write(self.spin);
+        self.shift_batch();
+
+        let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
+        let (seqno, n_count) = (snapshot.seqno, snapshot.n_count);
+        let old_footprint = self.tree_footprint;
+        let mut tree_footprint = 0;
+        let mut reclaim = Vec::with_capacity(n_count);
+        let root = self.do_compact_memory(snapshot.as_root(), &mut tree_footprint, &mut reclaim)?;
+        self.n_reclaimed += reclaim.len();
+        self.tree_footprint = tree_footprint;
+        self.snapshot.shift_snapshot(root, seqno, n_count, reclaim);
+
+        let reclaimed = old_footprint - tree_footprint;
+        info!(
+            target: "mvcc  ",
+            "{}, compacted memory len:{} reclaimed:{}", self.name, n_count, reclaimed
+        );
+        Ok(reclaimed)
+    }
+
+    fn do_compact_memory(
+        &self,
+        node: Option<&Node<K, V>>,
+        tree_footprint: &mut isize,
+        reclaim: &mut Vec<Box<Node<K, V>>>,
+    ) -> Result<Option<Box<Node<K, V>>>>
+    where
+        K: Footprint,
+        V: Footprint,
+    {
+        match node {
+            None => Ok(None),
+            Some(node) => {
+                let left = self.do_compact_memory(node.as_left_deref(), tree_footprint, reclaim)?;
+                let mut new_node = self.node_from_entry(node.entry.clone());
+                new_node.black = node.black;
+                new_node.dirty = node.dirty;
+                new_node.left = left;
+                new_node.right =
+                    self.do_compact_memory(node.as_right_deref(), tree_footprint, reclaim)?;
+                *tree_footprint += new_node.footprint()?;
+                reclaim.push(node.duplicate());
+                Ok(Some(new_node))
+            }
+        }
+    }
 }
 
 /// Maintanence API.
@@ -451,6 +653,21 @@ where
         self.sticky
     }
 
+    /// Return the compare-and-set policy configured for this index.
+    pub fn to_cas_policy(&self) -> CasPolicy<V> {
+        self.cas_policy
+    }
+
+    /// Return the size limits configured for this index.
+    pub fn to_size_limit(&self) -> SizeLimit<V> {
+        self.size_limit
+    }
+
+    /// Return the write-batch mode configured for this index.
+    pub fn to_commit_interval(&self) -> CommitInterval {
+        self.commit_interval
+    }
+
     /// Return number of entries in this instance.
     #[inline]
     pub fn len(&self) -> usize {
@@ -477,19 +694,166 @@ where
         stats.n_reclaimed = self.n_reclaimed;
         stats.rw_latch = self.latch.to_stats()?;
         stats.snapshot_latch = self.snapshot.ulatch.to_stats()?;
+        let (n_pinned, oldest_reader_seqno) = self.snapshot.to_pinned();
+        stats.n_pinned = n_pinned;
+        stats.oldest_reader_seqno = oldest_reader_seqno;
         Ok(stats)
     }
 
+    /// Return the number of snapshots pinned by active readers and
+    /// iterators, along with seqno of the oldest pinned snapshot.
+    pub fn to_pinned_snapshots(&self) -> (usize, Option<u64>) {
+        let _r = self.latch.acquire_read(self.spin);
+        self.snapshot.to_pinned()
+    }
+
+    /// Return seqno of the oldest snapshot pinned by an active reader
+    /// or iterator, None if no snapshot is pinned. Versions newer than
+    /// this seqno can still be observed by readers, hence compaction
+    /// and tombstone purge shall not cross this seqno.
+    pub fn to_oldest_reader_seqno(&self) -> Option<u64> {
+        self.to_pinned_snapshots().1
+    }
+
+    /// Return an iterator over deleted entries in the latest snapshot, in
+    /// sort order. Seqno of the returned entry is the seqno at which it
+    /// was deleted. Applicable only in lsm mode, in non-lsm mode deleted
+    /// entries are removed from the index. Like `iter()`, iteration
+    /// won't block writers.
+    pub fn iter_deleted(&mut self) -> Result<IndexIter<K, V>> {
+        let mut iter = Box::new(IterDeleted {
+            _latch: Default::default(),
+            _arc: OuterSnapshot::clone(&self.snapshot),
+            paths: Default::default(),
+        });
+        let root = iter
+            ._arc
+            .as_ref()
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+        iter.paths = Some(build_iter(IFlag::Left, root, vec![])?);
+        Ok(iter)
+    }
+
     fn multi_rw(&self) -> usize {
         Arc::strong_count(&self.readers) + Arc::strong_count(&self.writers) - 2
     }
 }
 
+// Mutations that are applied on the tree, but are yet to be shifted
+// into a snapshot.
+struct Batch<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    root: Option<Box<Node<K, V>>>,
+    seqno: u64,
+    n_count: usize,
+    reclaim: Vec<Box<Node<K, V>>>,
+    n_ops: usize,
+    since: time::Instant,
+}
+
 impl<K, V> Mvcc<K, V>
 where
     K: Clone + Ord,
     V: Clone + Diff,
 {
+    // return seqno and n_count of the latest tree, including the pending
+    // write-batch, if any.
+    fn to_latest(&self) -> (u64, usize) {
+        match &self.batch {
+            Some(batch) => (batch.seqno, batch.n_count),
+            None => {
+                let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
+                (snapshot.seqno, snapshot.n_count)
+            }
+        }
+    }
+
+    // similar to Snapshot::root_duplicate, but from the latest tree.
+    // Caller shall hold the write latch.
+    fn root_duplicate(&self) -> Option<Box<Node<K, V>>> {
+        match &self.batch {
+            Some(batch) => batch.root.as_ref().map(|root| root.duplicate()),
+            None => self.snapshot.as_ref().root_duplicate(),
+        }
+    }
+
+    // lookup `key` in the latest tree, including the pending write-batch.
+    // Caller shall hold the write latch.
+    fn get_latest<Q>(&self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let root = self.root_duplicate();
+        let res = get(root.as_ref().map(Deref::deref), key);
+        root.map(Box::leak);
+        res
+    }
+
+    // shift a new snapshot for this mutation, or add it to the pending
+    // write-batch, based on the configured commit interval.
+    fn shift_or_batch(
+        &mut self,
+        root: Option<Box<Node<K, V>>>,
+        seqno: u64,
+        n_count: usize,
+        mut reclaim: Vec<Box<Node<K, V>>>,
+    ) {
+        let mut batch = match (self.commit_interval, self.batch.take()) {
+            (CommitInterval::Immediate, None) => {
+                self.snapshot.shift_snapshot(root, seqno, n_count, reclaim);
+                return;
+            }
+            (_, Some(mut batch)) => {
+                // older root is either part of the new tree, or reclaimed.
+                batch.root.take().map(Box::leak);
+                batch
+            }
+            (_, None) => Batch {
+                root: None,
+                seqno,
+                n_count,
+                reclaim: Vec::with_capacity(RECLAIM_CAP),
+                n_ops: 0,
+                since: time::Instant::now(),
+            },
+        };
+
+        batch.root = root;
+        batch.seqno = seqno;
+        batch.n_count = n_count;
+        batch.reclaim.append(&mut reclaim);
+        batch.n_ops += 1;
+
+        let ok = match self.commit_interval {
+            CommitInterval::Immediate => true,
+            CommitInterval::Ops(n) => batch.n_ops >= n,
+            CommitInterval::Micros(n) => batch.since.elapsed() >= time::Duration::from_micros(n),
+        };
+        self.batch = Some(batch);
+        if ok {
+            self.shift_batch();
+        }
+    }
+
+    // shift the pending write-batch, if any, into a new snapshot.
+    fn shift_batch(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            let Batch {
+                root,
+                seqno,
+                n_count,
+                reclaim,
+                ..
+            } = batch;
+            self.snapshot.shift_snapshot(root, seqno, n_count, reclaim);
+        }
+    }
+
     fn node_new_deleted(&self, key: K, seqno: u64) -> Box<Node<K, V>> {
         self.snapshot.n_nodes.fetch_add(1, SeqCst);
         Node::new_deleted(key, seqno)
@@ -597,6 +961,7 @@ where
     fn set_seqno(&mut self, seqno: u64) -> Result<()> {
         let n = self.multi_rw();
         if n == 0 {
+            self.shift_batch();
             let s = OuterSnapshot::clone(&self.snapshot);
             let root = s.root_duplicate();
             self.snapshot.shift_snapshot(root, seqno, s.n_count, vec![]);
@@ -646,6 +1011,7 @@ where
                 self.set_index_entry(entry?)?;
                 count += 1;
             }
+            self.shift_batch();
             count
         };
 
@@ -679,6 +1045,7 @@ where
         let count = loop {
             let (seen, limit) = {
                 let _latch = self.latch.acquire_write(self.spin);
+                self.shift_batch();
 
                 let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
                 let root = snapshot.root_duplicate();
@@ -733,6 +1100,8 @@ where
     }
 }
 
+/// Footprint is accounted incrementally for every write operation,
+/// including key, value, deltas and node overhead. Reading it is O(1).
 impl<K, V> Footprint for Mvcc<K, V>
 where
     K: Clone + Ord,
@@ -757,12 +1126,12 @@ where
         seqno: Option<u64>,
     ) -> Result<(u64, Option<Entry<K, V>>)> {
         let _w = self.latch.acquire_write(self.spin);
+        let value = self.size_limit.apply_mem(&key, value)?;
 
         let entry = {
-            let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
             let seqno = match seqno {
                 Some(seqno) => seqno,
-                None => snapshot.seqno + 1,
+                None => self.to_latest().0 + 1,
             };
             Entry::new(key, Value::new_upsert_value(value, seqno))
         };
@@ -775,6 +1144,34 @@ where
         Ok((seqno, old_entry))
     }
 
+    /// Return the entry for `key`, if present, else insert the value
+    /// computed by `f` and return the new entry. Lookup and insert are
+    /// done under the same write latch, hence `f` is called at the most
+    /// once and concurrent writers cannot race between them. Entries
+    /// marked as deleted are treated as missing.
+    ///
+    /// *LSM mode*: Add a new version for the key, if it was deleted.
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> Result<Entry<K, V>>
+    where
+        F: FnOnce() -> V,
+    {
+        let _w = self.latch.acquire_write(self.spin);
+        let res = self.get_latest(&key);
+        match res {
+            Ok(entry) if !entry.is_deleted() => return Ok(entry),
+            Ok(_) | Err(Error::KeyNotFound) => (),
+            Err(err) => return Err(err),
+        }
+
+        let value = self.size_limit.apply_mem(&key, f())?;
+        let entry = {
+            let seqno = self.to_latest().0 + 1;
+            Entry::new(key.clone(), Value::new_upsert_value(value, seqno))
+        };
+        self.set_index_entry(entry)?;
+        self.get_latest(&key)
+    }
+
     pub fn set_cas_index(
         &mut self,
         key: K,
@@ -783,20 +1180,23 @@ where
         seqno: Option<u64>, // seqno for this mutation
     ) -> Result<(u64, Result<Option<Entry<K, V>>>)> {
         let _w = self.latch.acquire_write(self.spin);
+        let value = self.size_limit.apply_mem(&key, value)?;
 
-        let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
+        let (latest_seqno, mut n_count) = self.to_latest();
 
         let seqno = match seqno {
             Some(seqno) => seqno,
-            None => snapshot.seqno + 1,
+            None => latest_seqno + 1,
         };
         let lsm = self.lsm;
         let key_footprint = util::key_footprint(&key)?;
+        if let Some(bitmap) = &self.bitmap {
+            bitmap.add_key(&key);
+        }
 
         let new_entry = Entry::new(key, Value::new_upsert_value(value, seqno));
 
-        let mut n_count = snapshot.n_count;
-        let root = snapshot.root_duplicate();
+        let root = self.root_duplicate();
         let mut rclm: Vec<Box<Node<K, V>>> = Vec::with_capacity(RECLAIM_CAP);
         let s = match self.upsert_cas(root, new_entry, cas, lsm, &mut rclm)? {
             UpsertCasResult {
@@ -841,7 +1241,7 @@ where
 
         // TODO: can we optimize this for no-op cases (err cases) ?
         self.n_reclaimed += rclm.len();
-        self.snapshot.shift_snapshot(root, seqno, n_count, rclm);
+        self.shift_or_batch(root, seqno, n_count, rclm);
         Ok((seqno, entry))
     }
 
@@ -857,15 +1257,17 @@ where
     {
         let _w = self.latch.acquire_write(self.spin);
 
-        let snapshot: &Arc<Snapshot<K, V>> = self.snapshot.as_ref();
+        let (latest_seqno, mut n_count) = self.to_latest();
         let seqno = match seqno {
             Some(seqno) => seqno,
-            None => snapshot.seqno + 1,
+            None => latest_seqno + 1,
         };
         let key_footprint = util::key_footprint(&key.to_owned())?;
+        if let Some(bitmap) = &self.bitmap {
+            bitmap.add_key(&key.to_owned());
+        }
 
-        let mut n_count = snapshot.n_count;
-        let root = snapshot.root_duplicate();
+        let root = self.root_duplicate();
         let mut rclm: Vec<Box<Node<K, V>>> = Vec::with_capacity(RECLAIM_CAP);
         let (seqno, root, old_entry) = if self.lsm || self.sticky {
             let res = if self.lsm {
@@ -930,9 +1332,26 @@ where
         };
 
         self.n_reclaimed += rclm.len();
-        self.snapshot.shift_snapshot(root, seqno, n_count, rclm);
+        self.shift_or_batch(root, seqno, n_count, rclm);
         Ok((seqno, Ok(old_entry)))
     }
+
+    /// Delete key from index, similar to [delete_index][Mvcc::delete_index],
+    /// but return the entry, as it was before this delete, with all its
+    /// older versions. Useful for audit logs to record what was logically
+    /// removed.
+    ///
+    /// *LSM mode*: if key is missing, or already deleted, delete shall
+    /// still be ingested as a new version, and returned entry shall be
+    /// None or a deleted entry respectively.
+    pub fn delete_get_versions<Q>(&mut self, key: &Q) -> Result<(u64, Option<Entry<K, V>>)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let (seqno, entry) = self.delete_index(key, None)?;
+        Ok((seqno, entry?))
+    }
 }
 
 /// Create/Update/Delete operations on Mvcc instance.
@@ -981,6 +1400,28 @@ where
         let (_seqno, entry) = self.delete_index(key, None)?;
         entry
     }
+
+    /// Delete all keys from lower bound to upper bound, return the number
+    /// of entries deleted. Keys are collected before deleting them, so
+    /// that the range iterator does not overlap with the mutations.
+    ///
+    /// *LSM mode*: Entries are marked as deleted, entries already marked
+    /// as deleted are skipped.
+    fn delete_range<R>(&mut self, range: R) -> Result<usize>
+    where
+        R: Clone + RangeBounds<K>,
+    {
+        let keys = util::live_keys(self.range(range)?)?;
+        let mut n = 0;
+        for key in keys.iter() {
+            let (_seqno, entry) = self.delete_index(key, None)?;
+            match entry? {
+                Some(entry) if !entry.is_deleted() => n += 1,
+                _ => (),
+            }
+        }
+        Ok(n)
+    }
 }
 
 struct UpsertResult<K, V>
@@ -1107,15 +1548,16 @@ where
         lsm: bool,
         reclaim: &mut Vec<Box<Node<K, V>>>,
     ) -> Result<UpsertCasResult<K, V>> {
-        if node.is_none() && cas > 0 {
-            return Ok(UpsertCasResult {
-                node: None,
-                new_node: None,
-                old_entry: None,
-                size: 0,
-                err: Some(Error::InvalidCAS(0)),
-            });
-        } else if node.is_none() {
+        if node.is_none() {
+            if let Err(err) = self.cas_policy.check::<K>(None, cas) {
+                return Ok(UpsertCasResult {
+                    node: None,
+                    new_node: None,
+                    old_entry: None,
+                    size: 0,
+                    err: Some(err),
+                });
+            }
             let node: Box<Node<K, V>> = self.node_from_entry(nentry);
             let n = node.duplicate();
             let size: isize = node.footprint()?;
@@ -1144,23 +1586,14 @@ where
             newnd.right = r.node;
             r.node = Some(self.walkuprot_23(newnd, reclaim));
             r
-        } else if node.is_deleted() && cas != 0 && cas != node.to_seqno() {
-            let newnd = self.node_mvcc_clone(&node, reclaim, true);
-            UpsertCasResult {
-                node: Some(newnd),
-                new_node: None,
-                old_entry: None,
-                size: 0,
-                err: Some(Error::InvalidCAS(node.to_seqno())),
-            }
-        } else if !node.is_deleted() && cas != node.to_seqno() {
+        } else if let Err(err) = self.cas_policy.check(Some(&node.entry), cas) {
             let newnd = self.node_mvcc_clone(&node, reclaim, true);
             UpsertCasResult {
                 node: Some(newnd),
                 new_node: None,
                 old_entry: None,
                 size: 0,
-                err: Some(Error::InvalidCAS(node.to_seqno())),
+                err: Some(err),
             }
         } else {
             let mut newnd = self.node_mvcc_clone(&node, reclaim, true);
@@ -1439,12 +1872,14 @@ where
             (self as *const Self as *mut Self).as_mut().unwrap()
         };
 
-        let snapshot: &Arc<Snapshot<K, V>> = mself.snapshot.as_ref();
         let key_footprint = util::key_footprint(entry.as_key())?;
+        if let Some(bitmap) = &mself.bitmap {
+            bitmap.add_key(entry.as_key());
+        }
         let (seqno, deleted) = (entry.to_seqno(), entry.is_deleted());
 
-        let mut n_count = snapshot.n_count;
-        let root = snapshot.root_duplicate();
+        let (latest_seqno, mut n_count) = mself.to_latest();
+        let root = mself.root_duplicate();
         let mut rclm: Vec<Box<Node<K, V>>> = Vec::with_capacity(RECLAIM_CAP);
         match mself.upsert(root, entry, mself.lsm, &mut rclm)? {
             UpsertResult {
@@ -1474,10 +1909,8 @@ where
                 root.set_black();
                 n.dirty = false;
                 Box::leak(n);
-                let seqno = cmp::max(snapshot.seqno, seqno);
-                mself
-                    .snapshot
-                    .shift_snapshot(Some(root), seqno, n_count, rclm);
+                let seqno = cmp::max(latest_seqno, seqno);
+                mself.shift_or_batch(Some(root), seqno, n_count, rclm);
                 Ok((seqno, old_entry))
             }
             _ => err_at!(Fatal, msg: format!("call-the-programmer")),
@@ -1648,11 +2081,28 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash,
     {
+        if !util::bitmap_contains(&self.bitmap, key) {
+            return Err(Error::KeyNotFound);
+        }
         let snapshot: Arc<Snapshot<K, V>> = OuterSnapshot::clone(&self.snapshot);
         let res = get(snapshot.as_root(), key);
         res
     }
 
+    /// Check whether key is present in the latest snapshot, without
+    /// cloning its entry.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        if !util::bitmap_contains(&self.bitmap, key) {
+            return Ok(false);
+        }
+        let snapshot: Arc<Snapshot<K, V>> = OuterSnapshot::clone(&self.snapshot);
+        Ok(contains_key(snapshot.as_root(), key))
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let mut iter = Box::new(Iter {
             _latch: Default::default(),
@@ -1720,6 +2170,17 @@ where
         Ok(r)
     }
 
+    /// Count entries from lower bound to upper bound, entries marked as
+    /// deleted are skipped.
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     /// Short circuited to get().
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
@@ -1761,6 +2222,32 @@ where
     }
 }
 
+impl<K, V> Replay<K, V> for Mvcc<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<()> {
+        self.set_index(key, value, Some(seqno))?;
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, seqno: u64) -> Result<()> {
+        self.set_cas_index(key, value, cas, Some(seqno))?.1?;
+        Ok(())
+    }
+
+    fn delete_index(&mut self, key: K, seqno: u64) -> Result<()> {
+        self.delete_index(&key, Some(seqno))?.1?;
+        Ok(())
+    }
+
+    fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<()> {
+        self.set_cas_policy(policy)?;
+        Ok(())
+    }
+}
+
 impl<K, V> CommitIterator<K, V> for Box<Mvcc<K, V>>
 where
     K: Clone + Ord + Footprint,
@@ -1934,6 +2421,79 @@ where
         };
         Ok(iter)
     }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than range.end_bound(), in descending order.
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let (start, end) = util::to_start_end(within);
+        // similar to reverse pre-processing
+        let mut iter = Box::new(IterPWScanRev {
+            _latch: Default::default(),
+            _arc: OuterSnapshot::clone(&self.snapshot),
+            start,
+            end,
+            paths: Default::default(),
+        });
+        let root = iter
+            ._arc
+            .as_ref()
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+        iter.paths = match from {
+            Bound::Unbounded => Some(build_iter(IFlag::Right, root, vec![])?),
+            Bound::Included(high) => Some(find_end(root, high.borrow(), true, vec![])),
+            Bound::Excluded(high) => Some(find_end(root, high.borrow(), false, vec![])),
+        };
+        Ok(iter)
+    }
+}
+
+impl<K, V> Mvcc<K, V>
+where
+    K: Clone + Ord + Debug,
+    V: Clone + Diff,
+{
+    /// Return a [Validator] over the latest snapshot. Snapshot is pinned
+    /// for the life-time of the validator, hence validation does not
+    /// latch the index and writers can proceed concurrently. Use this
+    /// for health checks on a live index, validating a bounded number of
+    /// nodes per call.
+    pub fn to_validator(&self) -> Result<Validator<K, V>> {
+        let snapshot = OuterSnapshot::clone(&self.snapshot);
+        let root = snapshot
+            .root_duplicate()
+            .map(|n| Box::leak(n) as &Node<K, V>);
+
+        let mut validator = Validator {
+            name: self.name.clone(),
+            n_count: snapshot.n_count,
+            _arc: snapshot,
+            stack: vec![],
+            blacks: None,
+            depths: Default::default(),
+            n_nodes: 0,
+            n_deleted: 0,
+        };
+        match root {
+            Some(root) if is_red(Some(root)) => {
+                let msg = format!("validate, root node must be black: {}", self.name);
+                err_at!(Fatal, msg: msg)
+            }
+            Some(root) => {
+                validator.stack.push((root, 0, 0));
+                Ok(validator)
+            }
+            None => {
+                validator.depths.sample(0);
+                validator.blacks = Some(0);
+                Ok(validator)
+            }
+        }
+    }
 }
 
 impl<K, V> Validate<Stats> for Box<Mvcc<K, V>>
@@ -1960,43 +2520,150 @@ where
     /// * Make sure that the maximum depth do not exceed 100.
     ///
     /// Additionally return full statistics on the tree. Refer to [`Stats`]
-    /// for more information.
+    /// for more information. Validation is done on a pinned snapshot,
+    /// refer to [Mvcc::to_validator].
     fn validate(&mut self) -> Result<Stats> {
-        let arc_mvcc = OuterSnapshot::clone(&self.snapshot);
-
-        let root = arc_mvcc.as_root();
-        let (red, depth) = (is_red(root), 0);
-        let mut depths: LlrbDepth = Default::default();
+        let mut validator = self.to_validator()?;
+        let mut stats = loop {
+            if let Some(stats) = validator.validate_nodes(usize::MAX)? {
+                break stats;
+            }
+        };
 
-        if red {
-            let msg = format!("validate, root node must be black: {}", self.name);
+        // n_deleted is book-kept by writers, on the latest tree, compare
+        // only when there are no active handles and no pending batch.
+        let n_deleted = stats.n_deleted;
+        if self.multi_rw() == 0 && self.batch.is_none() && n_deleted != self.n_deleted {
+            let msg = format!("validate, n_deleted {} != {}", n_deleted, self.n_deleted);
             return err_at!(Fatal, msg: msg);
         }
 
-        let ss = (0, 0); // (blacks, n_deleted);
-        let ss = validate_tree(root, red, ss, depth, &mut depths)?;
-        if ss.1 != self.n_deleted {
-            let msg = format!("validate, n_deleted {} != {}", ss.1, self.n_deleted);
-            return err_at!(Fatal, msg: msg);
+        stats.key_footprint = self.key_footprint;
+        stats.tree_footprint = self.tree_footprint;
+        stats.n_reclaimed = self.n_reclaimed;
+        stats.rw_latch = self.latch.to_stats()?;
+        stats.snapshot_latch = self.snapshot.ulatch.to_stats()?;
+        Ok(stats)
+    }
+}
+
+/// Validator type, to validate a pinned snapshot of [Mvcc] index
+/// incrementally.
+///
+/// Created via [Mvcc::to_validator], the tree is walked depth first,
+/// with the same rules as that of [Validate::validate].
+pub struct Validator<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    name: String,
+    _arc: Arc<Snapshot<K, V>>, // only used for MVCC-snapshot refcount.
+    stack: Vec<(&'a Node<K, V>, usize, usize)>, // (node, depth, blacks)
+    blacks: Option<usize>,
+    depths: LlrbDepth,
+    n_count: usize, // number of entries in the pinned snapshot.
+    n_nodes: usize,
+    n_deleted: usize,
+}
+
+impl<'a, K, V> Validator<'a, K, V>
+where
+    K: Clone + Ord + Debug,
+    V: Clone + Diff,
+{
+    /// Validate upto `limit` number of nodes. Return statistics, similar
+    /// to [Validate::validate], once the entire tree is validated, else
+    /// return None.
+    pub fn validate_nodes(&mut self, limit: usize) -> Result<Option<Stats>> {
+        let mut n = 0;
+        while n < limit {
+            let (node, depth, blacks) = match self.stack.pop() {
+                Some(item) => item,
+                None => break,
+            };
+            self.validate_node(node, depth, blacks)?;
+            n += 1;
         }
 
-        if depths.to_max() > MAX_TREE_DEPTH {
-            let msg = format!("validate, tree exceeds max_depth {}", depths.to_max());
+        if self.stack.len() > 0 {
+            return Ok(None);
+        }
+
+        if self.n_nodes != self.n_count {
+            let msg = format!("validate, n_count {} != {}", self.n_nodes, self.n_count);
+            return err_at!(Fatal, msg: msg);
+        }
+        if self.depths.to_max() > MAX_TREE_DEPTH {
+            let msg = format!("validate, tree exceeds max_depth {}", self.depths.to_max());
             return err_at!(Fatal, msg: msg);
         }
 
         let mut stats = Stats::new(&self.name);
-        stats.entries = self.len();
-        stats.key_footprint = self.key_footprint;
-        stats.tree_footprint = self.tree_footprint;
+        stats.entries = self.n_count;
         stats.n_deleted = self.n_deleted;
-        stats.n_reclaimed = self.n_reclaimed;
         stats.node_size = mem::size_of::<Node<K, V>>();
-        stats.rw_latch = self.latch.to_stats()?;
-        stats.snapshot_latch = self.snapshot.ulatch.to_stats()?;
-        stats.blacks = Some(ss.0);
-        stats.depths = Some(depths);
-        Ok(stats)
+        stats.blacks = self.blacks;
+        stats.depths = Some(self.depths.clone());
+        Ok(Some(stats))
+    }
+
+    fn validate_node(&mut self, node: &'a Node<K, V>, depth: usize, blacks: usize) -> Result<()> {
+        let red = is_red(Some(node));
+        let (left, right) = (node.as_left_deref(), node.as_right_deref());
+        if node.dirty {
+            let msg = "validation, llrb has dirty node".to_string();
+            return err_at!(Fatal, msg: msg);
+        } else if red && (is_red(left) || is_red(right)) {
+            let msg = "validate, llrb has consecutive reds".to_string();
+            return err_at!(Fatal, msg: msg);
+        }
+
+        // confirm sort order in the tree.
+        if let Some(left) = left {
+            if left.as_key().ge(node.as_key()) {
+                let msg = format!(
+                    "validate, llrb sort error left:{:?} parent:{:?}",
+                    left.as_key(),
+                    node.as_key()
+                );
+                return err_at!(Fatal, msg: msg);
+            }
+        }
+        if let Some(right) = right {
+            if right.as_key().le(node.as_key()) {
+                let msg = format!(
+                    "validate, llrb sort error right:{:?} parent:{:?}",
+                    right.as_key(),
+                    node.as_key()
+                );
+                return err_at!(Fatal, msg: msg);
+            }
+        }
+
+        self.n_nodes += 1;
+        if node.is_deleted() {
+            self.n_deleted += 1;
+        }
+
+        let blacks = if red { blacks } else { blacks + 1 };
+        for child in vec![right, left].into_iter() {
+            match child {
+                Some(child) => self.stack.push((child, depth + 1, blacks)),
+                None => {
+                    self.depths.sample(depth + 1);
+                    match self.blacks {
+                        Some(n) if n != blacks => {
+                            let msg = format!("validate, unbalanced blacks {} != {}", n, blacks);
+                            return err_at!(Fatal, msg: msg);
+                        }
+                        Some(_) => (),
+                        None => self.blacks = Some(blacks),
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
 
@@ -2173,6 +2840,23 @@ where
     }
 }
 
+// Memory ordering for snapshot publish and consume:
+//
+// * Writer builds the new tree, off the latest snapshot, and publishes it
+//   by storing a fresh `Box<Arc<Snapshot>>` into `inner`, holding `ulatch`
+//   for write. Readers load `inner` and clone the Arc, holding `ulatch`
+//   for read. The spinlock's SeqCst compare-and-swap, along with SeqCst
+//   store/load on `inner`, gives a happens-before edge from node writes
+//   to reads, which is sufficient on weakly ordered targets like ARM.
+// * Previous `Box<Arc<Snapshot>>` is dropped only after the store, by
+//   when no reader can be holding a reference to the box, readers that
+//   loaded the old pointer would have blocked the write latch.
+// * Loads without `ulatch`, via AsRef, are made only by the writer which
+//   is serialized by the index latch.
+//
+// All atomics use SeqCst, do not relax them to Acquire/Release without
+// revisiting the above argument, counters `n_nodes` and `n_active` are
+// used for reclaim decisions.
 struct OuterSnapshot<K, V>
 where
     K: Clone + Ord,
@@ -2301,6 +2985,27 @@ where
         self.inner.store(Box::leak(next_s), SeqCst);
     }
 
+    // walk the snapshot chain, from latest to oldest, and count the
+    // references held by readers and iterators. Caller shall hold the
+    // index latch, so that writers do not shift the chain meanwhile.
+    fn to_pinned(&self) -> (usize, Option<u64>) {
+        let (mut n_pinned, mut oldest) = (0, None);
+        let mut snap: Option<&Arc<Snapshot<K, V>>> = Some(self.as_ref());
+        while let Some(s) = snap {
+            // one reference is held by this OuterSnapshot or by the
+            // newer snapshot in the chain.
+            match Arc::strong_count(s) - 1 {
+                0 => (),
+                n => {
+                    n_pinned += n;
+                    oldest = Some(s.seqno);
+                }
+            }
+            snap = s.next.as_ref();
+        }
+        (n_pinned, oldest)
+    }
+
     fn try_free_snapshot(
         mut snap: Arc<Snapshot<K, V>>, // current.next.take().unwrap()
     ) -> Option<Arc<Snapshot<K, V>>> {
@@ -2517,6 +3222,16 @@ where
         index.get(key)
     }
 
+    /// Check whether ``key`` is present in index.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.contains(key)
+    }
+
     /// Iterate over all entries in this index.
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let index: &mut Mvcc<K, V> = self.as_mut();
@@ -2545,6 +3260,17 @@ where
         index.reverse(range)
     }
 
+    /// Count entries from lower bound to upper bound.
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.range_count(range)
+    }
+
     /// Short circuited to get().
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
@@ -2632,6 +3358,17 @@ where
         let index: &mut Mvcc<K, V> = self.as_mut();
         index.pw_scan(from, within)
     }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than range.end_bound(), in descending order.
+    /// * Only entries whose modified seqno is within seqno-range.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.pw_scan_rev(from, within)
+    }
 }
 
 /// Write handle into [Mvcc] index.
@@ -2676,6 +3413,7 @@ where
     fn drop(&mut self) {
         let id = self.id;
         let index: &mut Mvcc<K, V> = self.as_mut();
+        index.publish().ok();
         debug!(target: "mvcc  ", "{}, dropping writer {}", index.name, id);
 
         // leak this index, it is only a reference
@@ -2703,6 +3441,12 @@ where
     K: Clone + Ord + Footprint,
     V: Clone + Diff + Footprint,
 {
+    /// Refer Mvcc::publish() for more details.
+    pub fn publish(&mut self) -> Result<()> {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.publish()
+    }
+
     /// Refer Llrb::set_index() for more details.
     pub fn set_index(
         &mut self,
@@ -2788,6 +3532,43 @@ where
         let (_seqno, entry) = index.delete_index(key, None)?;
         entry
     }
+
+    /// Delete all keys from lower bound to upper bound, return the number
+    /// of entries deleted.
+    fn delete_range<R>(&mut self, range: R) -> Result<usize>
+    where
+        R: Clone + RangeBounds<K>,
+    {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.delete_range(range)
+    }
+}
+
+impl<K, V> Replay<K, V> for MvccWriter<K, V>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<()> {
+        self.set_index(key, value, Some(seqno))?;
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, seqno: u64) -> Result<()> {
+        self.set_cas_index(key, value, cas, Some(seqno))?.1?;
+        Ok(())
+    }
+
+    fn delete_index(&mut self, key: K, seqno: u64) -> Result<()> {
+        self.delete_index(&key, Some(seqno))?.1?;
+        Ok(())
+    }
+
+    fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<()> {
+        let index: &mut Mvcc<K, V> = self.as_mut();
+        index.set_cas_policy(policy)?;
+        Ok(())
+    }
 }
 
 impl<K, V> WalWriter<K, V> for MvccWriter<K, V>
@@ -2846,6 +3627,10 @@ pub struct Stats {
     pub snapshot_latch: spinlock::Stats,
     pub blacks: Option<usize>,
     pub depths: Option<LlrbDepth>,
+    /// Number of snapshots pinned by active readers and iterators.
+    pub n_pinned: usize,
+    /// Seqno of the oldest snapshot pinned by readers and iterators.
+    pub oldest_reader_seqno: Option<u64>,
 }
 
 impl Stats {
@@ -2862,6 +3647,8 @@ impl Stats {
             snapshot_latch: Default::default(),
             blacks: None,
             depths: None,
+            n_pinned: Default::default(),
+            oldest_reader_seqno: None,
         }
     }
 }
@@ -2871,6 +3658,10 @@ impl fmt::Display for Stats {
         let none = "none".to_string();
         let b = self.blacks.as_ref().map_or(none.clone(), |x| x.to_string());
         let d = self.depths.as_ref().map_or(none.clone(), |x| x.to_string());
+        let o = self
+            .oldest_reader_seqno
+            .as_ref()
+            .map_or(none.clone(), |x| x.to_string());
         write!(f, "mvcc.name = {}\n", self.name)?;
         write!(
             f,
@@ -2884,6 +3675,11 @@ impl fmt::Display for Stats {
         )?;
         write!(f, "mvcc.rw_latch = {}\n", self.rw_latch)?;
         write!(f, "mvcc.snap_latch = {}\n", self.snapshot_latch)?;
+        write!(
+            f,
+            "mvcc = {{ n_pinned={}, oldest_reader_seqno={} }}\n",
+            self.n_pinned, o,
+        )?;
         write!(f, "mvcc.depths = {}\n", d)
     }
 }
@@ -2899,7 +3695,8 @@ impl ToJson for Stats {
                 r#""n_deleted": {}, "n_reclaimed": {}, "#,
                 r#""key_footprint": {}, "tree_footprint": {}, "#,
                 r#""node_size": {}, "rw_latch": {}, "#,
-                r#""snap_latch": {}, "blacks": {}, "depths": {} }} }}"#,
+                r#""snap_latch": {}, "blacks": {}, "depths": {}, "#,
+                r#""n_pinned": {}, "oldest_reader_seqno": {} }} }}"#,
             ),
             self.name,
             self.entries,
@@ -2914,6 +3711,10 @@ impl ToJson for Stats {
                 .as_ref()
                 .map_or(null.clone(), |x| format!("{}", x)),
             self.depths.as_ref().map_or(null.clone(), |x| x.to_json()),
+            self.n_pinned,
+            self.oldest_reader_seqno
+                .as_ref()
+                .map_or(null.clone(), |x| format!("{}", x)),
         )
     }
 }
@@ -193,6 +193,338 @@ fn test_empty_set_cas() {
     }
 }
 
+#[test]
+fn test_cas_policy() {
+    use crate::core::CasPolicy;
+
+    // create-if-absent
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+    index.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+    assert!(index.set_cas(10, 100, 10).unwrap().is_none());
+    assert_eq!(index.set_cas(10, 200, 0).err(), Some(Error::InvalidCAS(1)));
+    assert_eq!(index.set_cas(10, 200, 1).unwrap().unwrap().to_seqno(), 1);
+    index.delete(&10).unwrap();
+    assert!(index.set_cas(10, 300, 100).unwrap().unwrap().is_deleted());
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(300));
+
+    // value-hash
+    fn hash(value: &i64) -> u64 {
+        (*value as u64) + 1
+    }
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+    index.set_cas_policy(CasPolicy::ValueHash(hash)).unwrap();
+    assert_eq!(index.set_cas(10, 100, 10).err(), Some(Error::InvalidCAS(0)));
+    assert!(index.set_cas(10, 100, 0).unwrap().is_none());
+    assert_eq!(
+        index.set_cas(10, 200, 1).err(),
+        Some(Error::InvalidCAS(101))
+    );
+    assert!(index.set_cas(10, 200, 101).unwrap().is_some());
+    index.delete(&10).unwrap();
+    assert_eq!(index.set_cas(10, 300, 3).err(), Some(Error::InvalidCAS(0)));
+    assert!(index.set_cas(10, 300, 0).unwrap().is_some());
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(300));
+}
+
+#[test]
+fn test_size_limit() {
+    use crate::core::{SizeLimit, SizePolicy};
+
+    fn truncate(mut value: Vec<u8>, n: usize) -> Vec<u8> {
+        value.truncate(n / 2);
+        value
+    }
+
+    // reject
+    let mut index: Box<Mvcc<i64, Vec<u8>>> = Mvcc::new_lsm("test-mvcc");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::Reject))
+        .unwrap();
+    assert!(index.set(1, vec![0; 10]).unwrap().is_none());
+    match index.set(2, vec![0; 200]) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+    match index.set_cas(1, vec![0; 200], 1) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+    assert_eq!(index.len(), 1);
+
+    // oversized keys are always rejected.
+    let mut index: Box<Mvcc<i64, Vec<u8>>> = Mvcc::new_lsm("test-mvcc");
+    index
+        .set_size_limit(SizeLimit::new(4, 100, SizePolicy::SpillToVlog))
+        .unwrap();
+    match index.set(1, vec![0; 10]) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.map(|e| e.is_some())),
+    }
+
+    // truncate
+    let mut index: Box<Mvcc<i64, Vec<u8>>> = Mvcc::new_lsm("test-mvcc");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::Truncate(truncate)))
+        .unwrap();
+    index.set(1, vec![0; 10]).unwrap();
+    index.set(2, vec![0; 200]).unwrap();
+    assert_eq!(index.get(&1).unwrap().to_native_value().unwrap().len(), 10);
+    assert_eq!(index.get(&2).unwrap().to_native_value().unwrap().len(), 50);
+
+    // spill to vlog
+    let mut index: Box<Mvcc<i64, Vec<u8>>> = Mvcc::new_lsm("test-mvcc");
+    index
+        .set_size_limit(SizeLimit::new(16, 100, SizePolicy::SpillToVlog))
+        .unwrap();
+    index.set(2, vec![0; 200]).unwrap();
+    assert_eq!(index.get(&2).unwrap().to_native_value().unwrap().len(), 200);
+    assert_eq!(index.to_size_limit().max_value, 100);
+}
+
+#[test]
+fn test_commit_interval() {
+    use crate::mvcc::CommitInterval;
+
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    assert_eq!(index.to_commit_interval(), CommitInterval::Immediate);
+    index.set_commit_interval(CommitInterval::Ops(10)).unwrap();
+
+    // mutations are published once for every 10 ops.
+    for key in 0..25 {
+        index.set(key, key * 10).unwrap();
+    }
+    assert_eq!(index.to_seqno().unwrap(), 20);
+    assert_eq!(index.len(), 20);
+    assert!(index.get(&21).is_err());
+    // pending batch is the base for new mutations.
+    assert_eq!(index.set(21, 0).unwrap().unwrap().to_seqno(), 22);
+    assert_eq!(index.set_cas(22, 0, 23).unwrap().unwrap().to_seqno(), 23);
+    assert_eq!(index.delete(&23).unwrap().unwrap().to_seqno(), 24);
+
+    index.publish().unwrap();
+    assert_eq!(index.to_seqno().unwrap(), 28);
+    assert_eq!(index.len(), 24);
+    assert_eq!(index.get(&21).unwrap().to_native_value(), Some(0));
+    assert!(index.get(&23).is_err());
+    assert!(index.validate().is_ok());
+
+    // mutations are published once the interval elapses.
+    let interval = CommitInterval::Micros(3600 * 1_000_000);
+    index.set_commit_interval(interval).unwrap();
+    {
+        let mut w = index.to_writer().unwrap();
+        w.set(100, 100).unwrap();
+        w.set(101, 101).unwrap();
+        assert_eq!(index.len(), 24);
+        // dropping the writer publishes the batch.
+    }
+    assert_eq!(index.to_seqno().unwrap(), 30);
+    assert_eq!(index.len(), 26);
+
+    // pending batch is published before switching the interval, and
+    // carried by the clone.
+    index.set(102, 102).unwrap();
+    let cloned = index.clone().unwrap();
+    assert_eq!(cloned.to_seqno().unwrap(), 31);
+    assert_eq!(cloned.len(), 27);
+    assert_eq!(cloned.to_commit_interval(), interval);
+    index
+        .set_commit_interval(CommitInterval::Immediate)
+        .unwrap();
+    assert_eq!(index.to_seqno().unwrap(), 31);
+    assert_eq!(index.len(), 27);
+    assert!(index.validate().is_ok());
+}
+
+#[test]
+fn test_compact_memory() {
+    let mut index: Box<Mvcc<i64, Vec<u8>>> = Mvcc::new("test-mvcc");
+    for key in 0..1000_i64 {
+        let mut value = Vec::with_capacity(1024);
+        value.extend_from_slice(&key.to_be_bytes());
+        index.set(key, value).unwrap();
+    }
+    for key in (0..1000_i64).filter(|key| key % 3 != 0) {
+        index.delete(&key).unwrap();
+    }
+
+    {
+        let _r = index.to_reader().unwrap();
+        match index.compact_memory() {
+            Err(Error::APIMisuse(_)) => (),
+            res => panic!("expected APIMisuse {:?}", res),
+        }
+    }
+
+    let footprint = index.footprint().unwrap();
+    let reclaimed = index.compact_memory().unwrap();
+    assert!(reclaimed >= 334 * (1024 - 8), "{}", reclaimed);
+    assert_eq!(index.footprint().unwrap(), footprint - reclaimed);
+    // nothing more to reclaim on a second sweep.
+    assert_eq!(index.compact_memory().unwrap(), 0);
+    assert!(index.validate().is_ok());
+
+    assert_eq!(index.len(), 334);
+    for key in 0..1000_i64 {
+        match index.get(&key) {
+            Ok(entry) if key % 3 == 0 => {
+                assert_eq!(entry.to_native_value(), Some(key.to_be_bytes().to_vec()));
+            }
+            Err(Error::KeyNotFound) if key % 3 != 0 => (),
+            res => panic!("key:{} {:?}", key, res.map(|e| e.to_native_value())),
+        }
+    }
+    index.set(1, vec![1]).unwrap();
+    assert_eq!(index.len(), 335);
+}
+
+#[test]
+fn test_bitmap() {
+    use crate::croaring::CRoaring;
+
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+    index.set_bitmap(CRoaring::create()).unwrap();
+    index.set(1000, 10000).unwrap();
+    index.delete(&2000).unwrap();
+
+    for key in (0..100).chain(vec![1000].into_iter()) {
+        assert_eq!(index.get(&key).unwrap().to_native_value(), Some(key * 10));
+        assert!(index.contains(&key).unwrap());
+    }
+    assert!(index.get(&2000).unwrap().is_deleted());
+    for key in 100..200 {
+        match index.get(&key) {
+            Err(Error::KeyNotFound) => (),
+            res => panic!("{:?}", res.map(|e| e.to_seqno())),
+        }
+        assert!(!index.contains(&key).unwrap());
+    }
+
+    let _r = index.to_reader().unwrap();
+    match index.set_bitmap(CRoaring::create()) {
+        Err(Error::APIMisuse(_)) => (),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_validator() {
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+    for key in 0..1000 {
+        index.set(key, key * 10).unwrap();
+    }
+    for key in (0..1000).step_by(3) {
+        index.delete(&key).unwrap();
+    }
+    let full = index.validate().unwrap();
+    assert_eq!((full.entries, full.n_deleted), (1000, 334));
+
+    let mut w = index.to_writer().unwrap();
+    let mut validator = index.to_validator().unwrap();
+    let mut n_calls = 0;
+    let stats = loop {
+        n_calls += 1;
+        if let Some(stats) = validator.validate_nodes(10).unwrap() {
+            break stats;
+        }
+        // writers are not blocked by the validator.
+        w.set(1000 + n_calls, n_calls).unwrap();
+    };
+    assert_eq!(n_calls, 100);
+    assert_eq!((stats.entries, stats.n_deleted), (1000, 334));
+    assert_eq!(stats.blacks, full.blacks);
+    let (d1, d2) = (stats.depths.unwrap(), full.depths.unwrap());
+    assert_eq!(d1.to_max(), d2.to_max());
+
+    mem::drop(validator);
+    mem::drop(w);
+    let stats = index.validate().unwrap();
+    assert_eq!((stats.entries, stats.n_deleted), (1099, 334));
+
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    let mut validator = index.to_validator().unwrap();
+    let stats = validator.validate_nodes(0).unwrap().unwrap();
+    assert_eq!((stats.entries, stats.blacks), (0, Some(0)));
+    mem::drop(validator);
+    assert!(index.validate().is_ok());
+}
+
+#[test]
+fn test_range_count() {
+    for lsm in vec![false, true].into_iter() {
+        let mut index: Box<Mvcc<i64, i64>> = if lsm {
+            Mvcc::new_lsm("test-mvcc")
+        } else {
+            Mvcc::new("test-mvcc")
+        };
+        for key in 0..100 {
+            index.set(key, key * 10).unwrap();
+        }
+        index.delete(&10).unwrap();
+        index.delete(&20).unwrap();
+
+        assert_eq!(index.range_count(..).unwrap(), 98, "lsm:{}", lsm);
+        assert_eq!(index.range_count(10..30).unwrap(), 18, "lsm:{}", lsm);
+        let range = (Bound::Excluded(50), Bound::Included(60));
+        assert_eq!(index.range_count(range).unwrap(), 10, "lsm:{}", lsm);
+        assert_eq!(index.range_count(200..).unwrap(), 0, "lsm:{}", lsm);
+
+        assert_eq!(index.delete_range(25..35).unwrap(), 10, "lsm:{}", lsm);
+        {
+            let mut w = index.to_writer().unwrap();
+            assert_eq!(w.delete_range(20..40).unwrap(), 9, "lsm:{}", lsm);
+            assert_eq!(w.delete_range(20..40).unwrap(), 0, "lsm:{}", lsm);
+        }
+        {
+            let mut r = index.to_reader().unwrap();
+            assert_eq!(r.range_count(..).unwrap(), 79, "lsm:{}", lsm);
+            assert_eq!(r.range_count(..=40).unwrap(), 20, "lsm:{}", lsm);
+        }
+        for key in 20..40 {
+            match index.get(&key) {
+                Ok(entry) if lsm => assert!(entry.is_deleted()),
+                Err(Error::KeyNotFound) if !lsm => (),
+                res => panic!("{:?}", res.map(|e| e.to_seqno())),
+            }
+        }
+        index.validate().unwrap();
+    }
+}
+
+#[test]
+fn test_get_or_insert_with() {
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+    mvcc.set(10, 100).unwrap();
+
+    let mut calls = 0;
+    let entry = mvcc
+        .get_or_insert_with(10, || {
+            calls += 1;
+            200
+        })
+        .unwrap();
+    assert_eq!(calls, 0);
+    assert_eq!(entry.to_native_value(), Some(100));
+    assert_eq!(entry.to_seqno(), 1);
+
+    let entry = mvcc.get_or_insert_with(20, || 300).unwrap();
+    assert_eq!(entry.to_native_value(), Some(300));
+    assert_eq!(entry.to_seqno(), 2);
+    assert_eq!(mvcc.to_seqno().unwrap(), 2);
+    assert_eq!(mvcc.len(), 2);
+
+    mvcc.delete(&10).unwrap();
+    let entry = mvcc.get_or_insert_with(10, || 400).unwrap();
+    assert_eq!(entry.to_native_value(), Some(400));
+    assert_eq!(entry.to_seqno(), 4);
+    assert_eq!(entry.versions_iter().count(), 3);
+    assert_eq!(mvcc.get(&10).unwrap().to_native_value(), Some(400));
+    assert!(mvcc.validate().is_ok());
+}
+
 #[test]
 fn test_set() {
     let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
@@ -383,6 +715,36 @@ fn test_delete() {
     assert!(mvcc.iter().unwrap().next().is_none());
 }
 
+#[test]
+fn test_delete_get_versions() {
+    let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+
+    let (seqno, entry) = mvcc.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 1);
+    assert!(entry.is_none());
+    for i in 0..3 {
+        mvcc.set(1, 100 + i).unwrap();
+    }
+    mvcc.set(2, 200).unwrap();
+
+    let (seqno, entry) = mvcc.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 6);
+    let entry = entry.unwrap();
+    assert_eq!(entry.is_deleted(), false);
+    let versions: Vec<(u64, Option<i64>)> = entry.versions_iter().collect();
+    assert_eq!(
+        versions,
+        vec![(4, Some(102)), (3, Some(101)), (2, Some(100)), (1, None)]
+    );
+
+    let (seqno, entry) = mvcc.delete_get_versions(&1).unwrap();
+    assert_eq!(seqno, 7);
+    let entry = entry.unwrap();
+    assert_eq!(entry.is_deleted(), true);
+    assert_eq!(entry.versions_iter().count(), 5);
+    assert!(mvcc.validate().is_ok());
+}
+
 #[test]
 fn test_iter() {
     let mut mvcc: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
@@ -494,6 +856,40 @@ fn test_range() {
     }
 }
 
+#[test]
+fn test_range_borrowed() {
+    // generic over Reader, range and reverse on borrowed key type.
+    fn collect<I>(r: &mut I, low: &[u8], high: &[u8]) -> (Vec<Vec<u8>>, Vec<Vec<u8>>)
+    where
+        I: Reader<Vec<u8>, i64>,
+    {
+        let range = (Bound::Included(low), Bound::Excluded(high));
+        let fwd = r.range::<_, [u8]>(range).unwrap();
+        let fwd: Vec<Vec<u8>> = fwd.map(|e| e.unwrap().to_key()).collect();
+        let range = (Bound::Included(low), Bound::Excluded(high));
+        let rev = r.reverse::<_, [u8]>(range).unwrap();
+        let rev: Vec<Vec<u8>> = rev.map(|e| e.unwrap().to_key()).collect();
+        (fwd, rev)
+    }
+
+    let mut mvcc: Box<Mvcc<Vec<u8>, i64>> = Mvcc::new("test-mvcc");
+    for (i, key) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+        mvcc.set(key.as_bytes().to_vec(), i as i64).unwrap();
+    }
+
+    let refs = vec![b"b".to_vec(), b"c".to_vec()];
+    let (fwd, mut rev) = collect(mvcc.as_mut(), b"b", b"d");
+    assert_eq!(fwd, refs);
+    rev.reverse();
+    assert_eq!(rev, refs);
+
+    let mut r = mvcc.to_reader().unwrap();
+    let (fwd, mut rev) = collect(&mut r, b"b", b"d");
+    assert_eq!(fwd, refs);
+    rev.reverse();
+    assert_eq!(rev, refs);
+}
+
 #[test]
 fn test_crud() {
     let size = 1000;
@@ -668,6 +1064,78 @@ fn test_crud_lsm() {
     }
 }
 
+#[test]
+fn test_reader_registry() {
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    assert_eq!(index.to_oldest_reader_seqno(), None);
+    assert_eq!(index.to_pinned_snapshots(), (0, None));
+
+    for key in 0..100 {
+        index.set(key, key * 10).unwrap();
+    }
+    let iter1 = index.iter().unwrap();
+    assert_eq!(index.to_oldest_reader_seqno(), Some(100));
+
+    for key in 100..200 {
+        index.set(key, key * 10).unwrap();
+    }
+    assert_eq!(index.to_pinned_snapshots(), (1, Some(100)));
+
+    let iter2 = index.iter().unwrap();
+    let iter3 = index.iter().unwrap();
+    index.set(200, 2000).unwrap();
+    assert_eq!(index.to_pinned_snapshots(), (3, Some(100)));
+    assert_eq!(index.to_stats().unwrap().n_pinned, 3);
+
+    assert_eq!(iter1.count(), 100);
+    assert_eq!(index.to_pinned_snapshots(), (2, Some(200)));
+    assert_eq!(iter2.count(), 200);
+    assert_eq!(iter3.count(), 200);
+    assert_eq!(index.to_oldest_reader_seqno(), None);
+
+    let stats = index.to_stats().unwrap();
+    assert_eq!(stats.n_pinned, 0);
+    assert_eq!(stats.oldest_reader_seqno, None);
+}
+
+#[test]
+fn test_iter_deleted() {
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-mvcc");
+
+    let mut ref_seqnos = vec![];
+    for key in 0..1000_i64 {
+        index.set(key, key * 10).unwrap();
+    }
+    for key in (0..1000_i64).step_by(7) {
+        index.delete(&key).unwrap();
+        ref_seqnos.push((key, index.to_seqno().unwrap()));
+    }
+    // tombstones that are set again are not deleted anymore.
+    for key in (0..1000_i64).step_by(21) {
+        index.set(key, key).unwrap();
+    }
+    ref_seqnos.retain(|(key, _)| key % 21 != 0);
+
+    let items: Vec<(i64, u64)> = {
+        let iter = index.iter_deleted().unwrap();
+        iter.map(|e| e.unwrap())
+            .map(|e| {
+                assert!(e.is_deleted());
+                (e.to_key(), e.to_seqno())
+            })
+            .collect()
+    };
+    assert_eq!(items, ref_seqnos);
+
+    // in non-lsm mode deleted entries are removed.
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new("test-mvcc");
+    for key in 0..100_i64 {
+        index.set(key, key * 10).unwrap();
+    }
+    index.delete(&10).unwrap();
+    assert_eq!(index.iter_deleted().unwrap().count(), 0);
+}
+
 #[test]
 fn test_pw_scan() {
     let mut index: Box<Mvcc<i32, i32>> = Mvcc::new_lsm("test-mvcc");
@@ -820,6 +1288,50 @@ fn test_pw_scan() {
     assert!(index.validate().is_ok());
 }
 
+#[test]
+fn test_pw_scan_rev() {
+    let mut index: Box<Mvcc<i32, i32>> = Mvcc::new_lsm("test-mvcc");
+
+    for key in 0..10000 {
+        let value = (key + 1) * 100;
+        assert!(index.set(key, value).unwrap().is_none());
+    }
+    let seqno1 = index.to_seqno().unwrap();
+    for key in (0..1000).step_by(3) {
+        let value = (key + 1) * 1000;
+        assert!(index.set(key, value).unwrap().is_some());
+    }
+
+    // descending scan, rolled back to seqno1, stitched in small batches.
+    let mut iter = scans::SkipScan::new_rev(index.to_reader().unwrap());
+    iter.set_seqno_range(..=seqno1).unwrap();
+    iter.set_key_range(100..=5000).unwrap();
+    iter.set_batch_size(7).unwrap();
+    let keys: Vec<i32> = (100..=5000).rev().collect();
+    let mut n = 0;
+    for (entry, key) in iter.zip(keys.iter()) {
+        let entry = entry.unwrap();
+        assert_eq!(entry.to_key(), *key);
+        assert_eq!(entry.to_native_value().unwrap(), (key + 1) * 100);
+        n += 1;
+    }
+    assert_eq!(n, keys.len());
+
+    // only the injected keys, most of the scan is filtered out and retried.
+    let mut iter = scans::SkipScan::new_rev(index.to_reader().unwrap());
+    iter.set_seqno_range((Bound::Excluded(seqno1), Bound::Unbounded))
+        .unwrap();
+    let keys: Vec<i32> = iter.map(|e| e.unwrap().to_key()).collect();
+    let ref_keys: Vec<i32> = (0..1000).step_by(3).rev().collect();
+    assert_eq!(keys, ref_keys);
+
+    // latest N items under the upper-bound.
+    let mut iter = scans::SkipScan::new_rev(index.to_reader().unwrap());
+    iter.set_key_range(..500).unwrap();
+    let keys: Vec<i32> = iter.take(3).map(|e| e.unwrap().to_key()).collect();
+    assert_eq!(keys, vec![499, 498, 497]);
+}
+
 #[test]
 fn test_commit1() {
     let mut index1: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("test-index1");
@@ -1,12 +1,18 @@
 //! Module `nodisk` define a dummy disk index.
 
-use std::{borrow::Borrow, ffi, hash::Hash, marker, ops::RangeBounds};
+use std::{
+    borrow::Borrow,
+    ffi,
+    hash::Hash,
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     core::{CommitIter, CommitIterator, Cutoff, Result, Serialize},
     core::{Diff, DiskIndexFactory, Entry, Footprint, Index, IndexIter, Reader},
     error::Error,
-    panic::Panic,
+    panic::Unsupported,
 };
 
 /// Factory type, to construct NoDisk indexes.
@@ -41,27 +47,119 @@ where
     }
 }
 
+/// Operation captured by [NoDisk] in recording mode, refer to
+/// [NoDisk::new_recorder].
+#[derive(Clone)]
+pub enum Record<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    /// Entries, upserts and deletes, along with the metadata supplied
+    /// to [commit][Index::commit] or [commit_within][Index::commit_within].
+    Commit {
+        entries: Vec<Entry<K, V>>,
+        metadata: Vec<u8>,
+    },
+    /// Cutoff supplied to [compact][Index::compact].
+    Compact(Cutoff),
+}
+
 /// Index type, for empty Disk type. Can be used with mem-only storage.
 ///
 /// Applications can use this type while instantiating `rdms-index` in
-/// mem-only mode.
+/// mem-only mode. Test cases can use [NoDisk::new_recorder] as a mock
+/// disk index, that captures committed entries in memory.
 #[derive(Clone)]
-pub struct NoDisk<K, V> {
-    phantom_key: marker::PhantomData<K>,
-    phantom_val: marker::PhantomData<V>,
+pub struct NoDisk<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    // shared across clones, so that a test can hold on to a clone.
+    records: Option<Arc<Mutex<Vec<Record<K, V>>>>>,
 }
 
-impl<K, V> NoDisk<K, V> {
+impl<K, V> NoDisk<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
     #[inline]
     fn new() -> NoDisk<K, V> {
+        NoDisk { records: None }
+    }
+
+    /// Create a NoDisk index in recording mode. Commit and compact calls
+    /// are captured in memory, shared by all clones of this index,
+    /// refer to [NoDisk::to_records].
+    pub fn new_recorder() -> NoDisk<K, V> {
         NoDisk {
-            phantom_key: marker::PhantomData,
-            phantom_val: marker::PhantomData,
+            records: Some(Arc::new(Mutex::new(vec![]))),
+        }
+    }
+
+    /// Return operations captured so far, in the order they were called.
+    /// Empty if not in recording mode.
+    pub fn to_records(&self) -> Result<Vec<Record<K, V>>> {
+        match &self.records {
+            Some(records) => match records.lock() {
+                Ok(records) => Ok(records.clone()),
+                Err(e) => err_at!(Fatal, msg: format!("poisoned lock {}", e)),
+            },
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Return the number of entries committed so far, that is, the number
+    /// of set and delete operations received by the disk index.
+    pub fn to_writes(&self) -> Result<usize> {
+        let mut n = 0;
+        for record in self.to_records()?.into_iter() {
+            match record {
+                Record::Commit { entries, .. } => n += entries.len(),
+                Record::Compact(_) => (),
+            }
+        }
+        Ok(n)
+    }
+
+    fn record(&self, record: Record<K, V>) -> Result<()> {
+        match &self.records {
+            Some(records) => match records.lock() {
+                Ok(mut records) => {
+                    records.push(record);
+                    Ok(())
+                }
+                Err(e) => err_at!(Fatal, msg: format!("poisoned lock {}", e)),
+            },
+            None => Ok(()),
         }
     }
+
+    fn record_commit<C, F>(&self, mut scanner: CommitIter<K, V, C>, metadb: F) -> Result<()>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        if self.records.is_none() {
+            return Ok(());
+        }
+
+        let mut entries = vec![];
+        for entry in scanner.scan()? {
+            entries.push(entry?);
+        }
+        let metadata = metadb(vec![]);
+        self.record(Record::Commit { entries, metadata })
+    }
 }
 
-impl<K, V> Footprint for NoDisk<K, V> {
+impl<K, V> Footprint for NoDisk<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
     #[inline]
     fn footprint(&self) -> Result<isize> {
         Ok(0)
@@ -73,8 +171,8 @@ where
     K: Clone + Ord + Footprint,
     V: Clone + Diff + Footprint,
 {
-    type R = Panic;
-    type W = Panic;
+    type R = Unsupported;
+    type W = Unsupported;
 
     #[inline]
     fn to_name(&self) -> Result<String> {
@@ -99,25 +197,44 @@ where
 
     #[inline]
     fn to_reader(&mut self) -> Result<Self::R> {
-        Ok(Panic::new("nodisk"))
+        Ok(Unsupported::new("nodisk"))
     }
 
     #[inline]
     fn to_writer(&mut self) -> Result<Self::W> {
-        Ok(Panic::new("nodisk"))
+        Ok(Unsupported::new("nodisk"))
     }
 
     #[inline]
-    fn commit<C, F>(&mut self, _: CommitIter<K, V, C>, _metadb: F) -> Result<()>
+    fn commit<C, F>(&mut self, scanner: CommitIter<K, V, C>, metadb: F) -> Result<()>
     where
         C: CommitIterator<K, V>,
         F: Fn(Vec<u8>) -> Vec<u8>,
     {
-        Ok(())
+        self.record_commit(scanner, metadb)
     }
 
     #[inline]
-    fn compact(&mut self, _: Cutoff) -> Result<usize> {
+    fn commit_within<C, F>(
+        &mut self,
+        scanner: CommitIter<K, V, C>,
+        window: (Bound<u64>, Bound<u64>),
+        metadb: F,
+    ) -> Result<(Bound<u64>, Bound<u64>)>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        // nothing to persist, entire window is treated as committed.
+        let scanner = scanner.clip_within(window);
+        let within = scanner.to_within();
+        self.record_commit(scanner, metadb)?;
+        Ok(within)
+    }
+
+    #[inline]
+    fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
+        self.record(Record::Compact(cutoff))?;
         Ok(0)
     }
 
@@ -145,6 +262,14 @@ where
         Err(Error::KeyNotFound)
     }
 
+    fn contains<Q>(&mut self, _key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        Ok(false)
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let empty: Vec<Result<Entry<K, V>>> = vec![];
         Ok(Box::new(empty.into_iter()))
@@ -1,4 +1,10 @@
 //! Module `panic` handles unimplemented features.
+//!
+//! [Panic] fails every operation with `NotImplemented` error, while
+//! [Unsupported] fails every operation with `NotSupported` error that
+//! carries the name of the operation. Index types that do not support
+//! reading from or writing into, like [NoDisk][crate::nodisk::NoDisk],
+//! shall use [Unsupported] as their read/write handle.
 
 use std::{borrow::Borrow, ops::RangeBounds};
 
@@ -132,3 +138,137 @@ where
         err_at!(NotImplemented, msg:self.0)
     }
 }
+
+/// Placeholder type, for read/write handles of index types that do not
+/// support the operation. Every operation returns
+/// [NotSupported][Error::NotSupported] naming the index and the operation,
+/// like `"nodisk.set"`, so that misconfiguration can be handled as a
+/// recoverable error.
+pub struct Unsupported(String);
+
+impl Unsupported {
+    pub fn new(name: &str) -> Unsupported {
+        Unsupported(name.to_string())
+    }
+
+    fn to_error<T>(&self, op: &str) -> Result<T> {
+        err_at!(NotSupported, msg: format!("{}.{}", self.0, op))
+    }
+}
+
+// Write methods
+impl<K, V> Writer<K, V> for Unsupported
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn set(&mut self, _key: K, _value: V) -> Result<Option<Entry<K, V>>> {
+        self.to_error("set")
+    }
+
+    fn set_cas(&mut self, _: K, _: V, _: u64) -> Result<Option<Entry<K, V>>> {
+        self.to_error("set_cas")
+    }
+
+    fn delete<Q>(&mut self, _key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        self.to_error("delete")
+    }
+}
+
+impl<K, V> Reader<K, V> for Unsupported
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn get<Q>(&mut self, _: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.to_error("get")
+    }
+
+    fn iter(&mut self) -> Result<IndexIter<K, V>> {
+        self.to_error("iter")
+    }
+
+    fn range<'a, R, Q>(&'a mut self, _: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.to_error("range")
+    }
+
+    fn reverse<'a, R, Q>(&'a mut self, _: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.to_error("reverse")
+    }
+
+    fn get_with_versions<Q>(&mut self, _: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.to_error("get_with_versions")
+    }
+
+    fn iter_with_versions(&mut self) -> Result<IndexIter<K, V>> {
+        self.to_error("iter_with_versions")
+    }
+
+    fn range_with_versions<'a, R, Q>(&'a mut self, _: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.to_error("range_with_versions")
+    }
+
+    fn reverse_with_versions<'a, R, Q>(&'a mut self, _: R) -> Result<IndexIter<K, V>>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        self.to_error("reverse_with_versions")
+    }
+}
+
+impl<K, V> CommitIterator<K, V> for Unsupported
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn scan<G>(&mut self, _within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        self.to_error("scan")
+    }
+
+    fn scans<G>(&mut self, _n_shards: usize, _within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        self.to_error("scans")
+    }
+
+    fn range_scans<N, G>(&mut self, _ranges: Vec<N>, _within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+        N: Clone + RangeBounds<K>,
+    {
+        self.to_error("range_scans")
+    }
+}
@@ -2,18 +2,46 @@
 //!
 //! [Rdms] can be composed using underlying components and mechanisms defined
 //! in [core] module.
+//!
+//! When compiled with `tracing` feature, writes, gets, commits,
+//! compactions and write-ahead-log fsyncs emit structured events, under
+//! the `rdms` target, carrying seqno and byte counts. For commits and
+//! compactions, bytes is the number of bytes written to disk. Commits
+//! and compactions, whether explicit or from auto threads, are
+//! additionally wrapped in spans, so that latency spikes in application
+//! can be correlated with background maintenance.
 
 use std::{
-    fmt, marker,
-    sync::{self, Arc, MutexGuard},
+    borrow::Borrow,
+    cmp,
+    collections::hash_map::RandomState,
+    convert::TryInto,
+    ffi, fmt, fs,
+    hash::{BuildHasher, Hash},
+    marker,
+    ops::Bound,
+    result,
+    str::FromStr,
+    sync::{
+        self,
+        atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst},
+        mpsc, Arc, MutexGuard,
+    },
+    thread, time,
 };
 
 #[allow(unused_imports)]
 use crate::core;
 use crate::{
-    core::{CommitIter, CommitIterator, Diff, Footprint, Index},
-    core::{Cutoff, Result, Validate},
+    core::{CasPolicy, CommitIter, CommitIterator, Diff, Footprint, Index},
+    core::{Cutoff, DiskIndexFactory, Entry, Replay, Result, Serialize},
+    core::{ToJson, Validate, WalWriter, WriteIndexFactory, Writer},
+    dgm::Dgm,
+    dlog::{self, Dlog},
     error::Error,
+    heatmap::{HeatReader, Heatmap},
+    schema,
+    wal::{self, Wal},
 };
 
 /// Index type, composable index type. Check module documentation for
@@ -27,6 +55,11 @@ where
     name: String,
 
     index: Option<Arc<sync::Mutex<I>>>,
+    wal: Option<WalRef<K, V>>,
+    heatmap: Arc<Heatmap<K>>,
+    pools: Vec<sync::Weak<ReaderPool<K, V, I>>>,
+    logical_bytes: Arc<AtomicUsize>, // bytes ingested via wal writers.
+    wal_bytes: Arc<AtomicUsize>,     // bytes appended to wal journals.
 
     _key: marker::PhantomData<K>,
     _value: marker::PhantomData<V>,
@@ -48,6 +81,11 @@ where
             name: name.as_ref().to_string(),
 
             index: Some(Arc::new(sync::Mutex::new(index))),
+            wal: None,
+            heatmap: Arc::new(Heatmap::default()),
+            pools: vec![],
+            logical_bytes: Default::default(),
+            wal_bytes: Default::default(),
 
             _key: marker::PhantomData,
             _value: marker::PhantomData,
@@ -69,8 +107,12 @@ where
         }
     }
 
-    /// Purge this index along with disk data.
+    /// Purge this index along with disk data, including the journals of
+    /// write-ahead-log, if attached.
     pub fn purge(mut self) -> Result<()> {
+        if let Some(wal) = self.wal.take() {
+            lock_wal(&wal)?.purge()?;
+        }
         self.do_close()?;
         match Arc::try_unwrap(self.index.take().unwrap()) {
             Ok(index) => {
@@ -82,7 +124,437 @@ where
     }
 
     fn do_close(&mut self) -> Result<()> {
-        Ok(()) // TODO cleanup this function
+        if let Some(wal) = self.wal.take() {
+            lock_wal(&wal)?.close()?;
+        }
+        Ok(())
+    }
+}
+
+/// Configuration for write-ahead-log attached to [Rdms] instance, refer
+/// to [Rdms::set_wal].
+#[derive(Clone, Debug)]
+pub struct WalConfig {
+    /// Directory to persist the journal files.
+    pub dir: ffi::OsString,
+    /// Number of shards to log mutations.
+    pub nshards: usize,
+    /// Journal files are rotated once they exceed this limit, in bytes.
+    pub journal_limit: usize,
+    /// Maximum number of mutations to flush in a single batch.
+    pub batch_size: usize,
+    /// Whether to fsync journal files after every flush.
+    pub fsync: bool,
+}
+
+impl WalConfig {
+    /// New configuration, persisting journals under `dir`, with a single
+    /// shard and fsync enabled.
+    pub fn new(dir: &ffi::OsStr) -> WalConfig {
+        WalConfig {
+            dir: dir.to_os_string(),
+            nshards: 1,
+            journal_limit: dlog::JOURNAL_LIMIT,
+            batch_size: 1,
+            fsync: true,
+        }
+    }
+
+    fn to_wal<K, V>(&self, name: &str) -> Result<Wal<K, V, RandomState>>
+    where
+        K: 'static + Send + Clone + Default + Ord + Hash + Serialize,
+        V: 'static + Send + Clone + Default + Serialize,
+    {
+        let dl = Dlog::<wal::State, wal::Op<K, V>>::load(
+            self.dir.clone(),
+            name.to_string(),
+            self.nshards,
+            self.journal_limit,
+            self.batch_size,
+            self.fsync,
+        )?;
+        Ok(Wal::from_dlog(dl, RandomState::new()))
+    }
+}
+
+impl<K, V, I> Rdms<K, V, I>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Clone + Default + Diff + Footprint + Serialize,
+    I: Index<K, V>,
+    <I as Index<K, V>>::W: WalWriter<K, V>,
+{
+    /// Attach a write-ahead-log to this instance, journals are persisted
+    /// under `config.dir` using this instance's name. Mutations that are
+    /// logged, but are missing in the index, are replayed before the log
+    /// is attached. Return the number of mutations replayed.
+    ///
+    /// Once attached, mutations shall go through
+    /// [to_wal_writer][Rdms::to_wal_writer] handles.
+    pub fn set_wal(&mut self, config: WalConfig) -> Result<usize> {
+        if self.wal.is_some() {
+            err_at!(APIMisuse, msg: format!("{:?} wal already set", self.name))?;
+        }
+        err_at!(IoError, fs::create_dir_all(&config.dir))?;
+
+        let (n, seqno) = {
+            let mut index = self.as_index()?;
+            let seqno = index.to_seqno()?;
+            let wl = config.to_wal(&self.name)?;
+            let mut w = ReplayWriter::new(index.to_writer()?, seqno);
+            let n = wl.replay(&mut w, seqno)?;
+            (n, index.to_seqno()?)
+        };
+
+        let mut wl = config.to_wal(&self.name)?;
+        // index might have ingested mutations that are not in the log.
+        wl.fast_forward(seqno);
+        {
+            let wal_bytes = Arc::clone(&self.wal_bytes);
+            wl.set_commit_hook(move |summary| {
+                wal_bytes.fetch_add(summary.bytes, SeqCst);
+            })?;
+        }
+        let writer = Some(wl.to_writer()?);
+        let wal: Box<dyn WalLog<K, V>> = Box::new(WalLogger {
+            wal: Some(wl),
+            writer,
+            logical_bytes: Arc::clone(&self.logical_bytes),
+        });
+        self.wal = Some(Arc::new(sync::Mutex::new(wal)));
+
+        Ok(n)
+    }
+
+    /// Create a new writer handle, that shall log every mutation in the
+    /// write-ahead-log before applying it on the index.
+    pub fn to_wal_writer(&mut self) -> Result<RdmsWriter<K, V, <I as Index<K, V>>::W>> {
+        let wal = match &self.wal {
+            Some(wal) => Arc::clone(wal),
+            None => err_at!(APIMisuse, msg: format!("{:?} wal not set", self.name))?,
+        };
+        let mut index = self.as_index()?;
+        Ok(RdmsWriter {
+            wal,
+            index: index.to_writer()?,
+        })
+    }
+}
+
+impl<K, V, M, D> Rdms<K, V, Box<Dgm<K, V, M, D>>>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Clone + Default + Diff + Footprint + Serialize,
+    <V as Diff>::D: Serialize,
+    M: 'static + Send + WriteIndexFactory<K, V>,
+    D: 'static + Send + DiskIndexFactory<K, V>,
+    <M as WriteIndexFactory<K, V>>::I: 'static + Send,
+    <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+    <<M as WriteIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send + WalWriter<K, V>,
+    <D as DiskIndexFactory<K, V>>::I: 'static + Send,
+    <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::R: 'static + Send,
+    <<D as DiskIndexFactory<K, V>>::I as Index<K, V>>::W: 'static + Send,
+{
+    /// Open a [Dgm] index under `dir` and recover it from its
+    /// write-ahead-log, in a single call:
+    ///
+    /// * Load disk snapshots, memory index is aligned with the maximum
+    ///   persisted seqno.
+    /// * Replay only those logged mutations beyond the persisted seqno
+    ///   into the memory index, in seqno order.
+    /// * Fail with `Error::Fatal` if replayed seqnos are not monotonic.
+    /// * Attach the write-ahead-log, refer to [Rdms::set_wal].
+    ///
+    /// `wal.dir` must not be same as, or under, `dir`, since a [Dgm] index
+    /// without any disk snapshot is created afresh.
+    pub fn open(
+        dir: &ffi::OsStr,
+        name: &str,
+        mem_factory: M,
+        disk_factory: D,
+        wal: WalConfig,
+    ) -> Result<Box<Rdms<K, V, Box<Dgm<K, V, M, D>>>>> {
+        let index = Dgm::open(dir, name, mem_factory, disk_factory)?;
+        let mut rdms = Rdms::new(name, index)?;
+        rdms.set_wal(wal)?;
+        Ok(rdms)
+    }
+}
+
+/// Writer handle for [Rdms] instance with write-ahead-log, refer to
+/// [Rdms::to_wal_writer].
+///
+/// Mutations from all writer handles are serialized, so that they are
+/// applied on the index in the same order as they are logged.
+pub struct RdmsWriter<K, V, W>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    W: WalWriter<K, V>,
+{
+    wal: WalRef<K, V>,
+    index: W,
+}
+
+impl<K, V, W> Writer<K, V> for RdmsWriter<K, V, W>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    W: WalWriter<K, V>,
+{
+    fn set(&mut self, key: K, value: V) -> Result<Option<Entry<K, V>>> {
+        let mut wal = lock_wal(&self.wal)?;
+        let seqno = wal.set(key.clone(), value.clone())?;
+        trace_event!("set", seqno = seqno);
+        self.index.set_index(key, value, seqno)
+    }
+
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<Option<Entry<K, V>>> {
+        let mut wal = lock_wal(&self.wal)?;
+        let seqno = wal.set_cas(key.clone(), value.clone(), cas)?;
+        trace_event!("set_cas", seqno = seqno, cas = cas);
+        self.index.set_cas_index(key, value, cas, seqno)
+    }
+
+    fn delete<Q>(&mut self, key: &Q) -> Result<Option<Entry<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + Ord + ?Sized,
+    {
+        let mut wal = lock_wal(&self.wal)?;
+        let seqno = wal.delete(key.to_owned())?;
+        trace_event!("delete", seqno = seqno);
+        self.index.delete_index(key, seqno)
+    }
+}
+
+type WalRef<K, V> = Arc<sync::Mutex<Box<dyn WalLog<K, V>>>>;
+
+fn lock_wal<K, V>(wal: &WalRef<K, V>) -> Result<MutexGuard<Box<dyn WalLog<K, V>>>> {
+    match wal.lock() {
+        Ok(wal) => Ok(wal),
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+// Write-ahead-log attached to Rdms, type erased so that Rdms need not
+// carry the trait bounds required by Wal.
+trait WalLog<K, V>: Send {
+    fn set(&mut self, key: K, value: V) -> Result<u64>;
+
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<u64>;
+
+    fn delete(&mut self, key: K) -> Result<u64>;
+
+    fn close(&mut self) -> Result<u64>;
+
+    fn purge(&mut self) -> Result<u64>;
+}
+
+struct WalLogger<K, V>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Clone + Default + Footprint + Serialize,
+{
+    wal: Option<Wal<K, V, RandomState>>,
+    writer: Option<wal::Writer<K, V, RandomState>>,
+    logical_bytes: Arc<AtomicUsize>,
+}
+
+impl<K, V> WalLogger<K, V>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Clone + Default + Footprint + Serialize,
+{
+    fn as_writer(&mut self) -> Result<&mut wal::Writer<K, V, RandomState>> {
+        match self.writer.as_mut() {
+            Some(writer) => Ok(writer),
+            None => err_at!(APIMisuse, msg: format!("wal closed")),
+        }
+    }
+
+    // shards can be closed only after all its clients are dropped.
+    fn take_wal(&mut self) -> Result<Wal<K, V, RandomState>> {
+        self.writer.take();
+        match self.wal.take() {
+            Some(wal) => Ok(wal),
+            None => err_at!(APIMisuse, msg: format!("wal closed")),
+        }
+    }
+
+    fn add_logical_bytes(&self, n: isize) -> Result<()> {
+        self.logical_bytes.fetch_add(convert_at!(n)?, SeqCst);
+        Ok(())
+    }
+}
+
+impl<K, V> WalLog<K, V> for WalLogger<K, V>
+where
+    K: 'static + Send + Clone + Default + Ord + Hash + Footprint + Serialize,
+    V: 'static + Send + Clone + Default + Footprint + Serialize,
+{
+    fn set(&mut self, key: K, value: V) -> Result<u64> {
+        let n = key.footprint()? + value.footprint()?;
+        let seqno = self.as_writer()?.set(key, value)?;
+        self.add_logical_bytes(n)?;
+        Ok(seqno)
+    }
+
+    fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<u64> {
+        let n = key.footprint()? + value.footprint()?;
+        let seqno = self.as_writer()?.set_cas(key, value, cas)?;
+        self.add_logical_bytes(n)?;
+        Ok(seqno)
+    }
+
+    fn delete(&mut self, key: K) -> Result<u64> {
+        let n = key.footprint()?;
+        let seqno = self.as_writer()?.delete(&key)?;
+        self.add_logical_bytes(n)?;
+        Ok(seqno)
+    }
+
+    fn close(&mut self) -> Result<u64> {
+        self.take_wal()?.close()
+    }
+
+    fn purge(&mut self) -> Result<u64> {
+        self.take_wal()?.purge()
+    }
+}
+
+// Replay logged mutations on the index, via its write handle. Replayed
+// seqno must increase monotonically, starting after the index seqno.
+struct ReplayWriter<W> {
+    w: W,
+    seqno: u64,
+}
+
+impl<W> ReplayWriter<W> {
+    fn new(w: W, seqno: u64) -> ReplayWriter<W> {
+        ReplayWriter { w, seqno }
+    }
+
+    fn check_seqno(&mut self, seqno: u64) -> Result<()> {
+        if seqno <= self.seqno {
+            let msg = format!("replay seqno {} after {}", seqno, self.seqno);
+            err_at!(Fatal, msg: msg)
+        } else {
+            self.seqno = seqno;
+            Ok(())
+        }
+    }
+}
+
+impl<K, V, W> Replay<K, V> for ReplayWriter<W>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    W: WalWriter<K, V>,
+{
+    fn set_index(&mut self, key: K, value: V, seqno: u64) -> Result<()> {
+        self.check_seqno(seqno)?;
+        self.w.set_index(key, value, seqno)?;
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, seqno: u64) -> Result<()> {
+        self.check_seqno(seqno)?;
+        // mutations are logged before they are applied on the index, a
+        // set-cas that failed on the index shall fail again.
+        match self.w.set_cas_index(key, value, cas, seqno) {
+            Ok(_) | Err(Error::InvalidCAS(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn delete_index(&mut self, key: K, seqno: u64) -> Result<()> {
+        self.check_seqno(seqno)?;
+        self.w.delete_index(&key, seqno)?;
+        Ok(())
+    }
+
+    fn set_cas_policy(&mut self, _policy: CasPolicy<V>) -> Result<()> {
+        // policy is configured on the index by the application, it can't
+        // be changed via a write handle.
+        err_at!(NotSupported, msg: format!("cas policy via write handle"))
+    }
+}
+
+/// Result of [Rdms::close_wait], for each component that was shutdown.
+#[derive(Debug)]
+pub struct CloseResult {
+    /// Result of flushing the memory index, None if flush was not
+    /// requested.
+    pub flush: Option<Result<()>>,
+    /// Result of closing the index, which includes stopping its
+    /// background threads and joining them.
+    pub index: Result<()>,
+}
+
+impl CloseResult {
+    /// Return true if all components were shutdown without error.
+    pub fn is_ok(&self) -> bool {
+        let flush = match &self.flush {
+            Some(res) => res.is_ok(),
+            None => true,
+        };
+        flush && self.index.is_ok()
+    }
+}
+
+impl<K, V, I> Rdms<K, V, I>
+where
+    K: 'static + Send + Clone + Ord,
+    V: 'static + Send + Clone + Diff,
+    I: 'static + Send + Index<K, V>,
+{
+    /// Close this `Rdms` instance, waiting for at most `timeout`. Unlike
+    /// [close][Rdms::close], which leaves it to the index to stop and
+    /// join its threads, this call acts as a durability barrier:
+    ///
+    /// * If `flush` is true, memory index is committed to disk, along
+    ///   with its root/manifest file, before closing.
+    /// * Index is closed, stopping its scheduler and joining all its
+    ///   background threads.
+    ///
+    /// Result of each component is returned in [CloseResult]. If index
+    /// does not close within `timeout`, `Error::TimedOut` is returned,
+    /// and the index shall continue to close in the background.
+    pub fn close_wait(mut self, flush: bool, timeout: time::Duration) -> Result<CloseResult> {
+        self.do_close()?;
+        let mut index = match Arc::try_unwrap(self.index.take().unwrap()) {
+            Ok(index) => match index.into_inner() {
+                Ok(index) => index,
+                Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err))?,
+            },
+            Err(_) => err_at!(APIMisuse, msg: format!("{:?} is shared", self.name))?,
+        };
+
+        let flush = match flush {
+            true => {
+                let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+                let empty: Vec<Result<Entry<K, V>>> = vec![];
+                let iter = CommitIter::new(empty.into_iter(), within);
+                Some(index.commit(iter, |meta| meta))
+            }
+            false => None,
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let name = format!("rdms-close-{}", self.name);
+        let res = thread::Builder::new().name(name).spawn(move || {
+            tx.send(index.close()).ok();
+        });
+        err_at!(SystemFail, res)?;
+
+        let index = match rx.recv_timeout(timeout) {
+            Ok(res) => res,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(Error::TimedOut)?,
+            Err(err) => err_at!(IPCFail, msg: format!("{:?} close {}", self.name, err))?,
+        };
+
+        Ok(CloseResult { flush, index })
     }
 }
 
@@ -130,7 +602,71 @@ where
         index.to_reader()
     }
 
+    /// Create a pool of `n` reader handles into the index. Handles are
+    /// handed out round-robin, refer to [ReaderPool::acquire], and are
+    /// refreshed after every [commit][Rdms::commit],
+    /// [commit_within][Rdms::commit_within] and [compact][Rdms::compact]
+    /// on this instance, and after every level swap in the index, refer
+    /// to [Index::to_generation], so that they don't observe stale
+    /// levels. Pool shall be dropped before closing this instance.
+    pub fn to_reader_pool(&mut self, n: usize) -> Result<Arc<ReaderPool<K, V, I>>> {
+        if n == 0 {
+            err_at!(InvalidInput, msg: format!("reader pool of size 0"))?;
+        }
+        let (generation, readers, generations) = {
+            let mut index = self.as_index()?;
+            let generation = index.to_generation()?;
+            let seen = generation.as_ref().map_or(0, |g| g.load(SeqCst));
+            let (mut readers, mut generations) = (vec![], vec![]);
+            for _ in 0..n {
+                readers.push(sync::Mutex::new(index.to_reader()?));
+                generations.push(AtomicU64::new(seen));
+            }
+            (generation, readers, generations)
+        };
+        let pool = Arc::new(ReaderPool {
+            index: Arc::downgrade(self.index.as_ref().unwrap()),
+            generation,
+            readers,
+            generations,
+            next: AtomicUsize::new(0),
+
+            _key: marker::PhantomData,
+            _value: marker::PhantomData,
+        });
+        self.pools.push(Arc::downgrade(&pool));
+        Ok(pool)
+    }
+
+    /// Create a new reader handle into the index, that shall record
+    /// its lookups into this instance's heatmap, refer to
+    /// [set_heatmap][Rdms::set_heatmap].
+    pub fn to_heat_reader(&mut self) -> Result<HeatReader<K, V, <I as Index<K, V>>::R>> {
+        let reader = self.to_reader()?;
+        Ok(HeatReader::new(reader, Arc::clone(&self.heatmap)))
+    }
+
+    /// Enable or disable hot-key tracking for lookups done via
+    /// [to_heat_reader][Rdms::to_heat_reader] handles. Takes effect on
+    /// handles that are already created.
+    pub fn set_heatmap(&mut self, enabled: bool) -> Result<()> {
+        self.heatmap.set_enabled(enabled);
+        Ok(())
+    }
+
+    /// Return upto `k` most frequently accessed keys, along with their
+    /// approximate access count, hottest first.
+    pub fn to_hot_keys(&self, k: usize) -> Result<Vec<(K, u64)>> {
+        self.heatmap.to_hot_keys(k)
+    }
+
+    /// Create a new writer handle into the index. If a write-ahead-log
+    /// is attached, use [to_wal_writer][Rdms::to_wal_writer] instead.
     pub fn to_writer(&mut self) -> Result<<I as Index<K, V>>::W> {
+        if self.wal.is_some() {
+            let msg = format!("{:?} has wal, use to_wal_writer()", self.name);
+            err_at!(APIMisuse, msg: msg)?;
+        }
         let mut index = self.as_index()?;
         index.to_writer()
     }
@@ -140,13 +676,270 @@ where
         C: CommitIterator<K, V>,
         F: Fn(Vec<u8>) -> Vec<u8>,
     {
-        let mut index = self.as_index()?;
-        index.commit(scanner, metacb)
+        self.as_index()?.commit(scanner, metacb)?;
+        self.refresh_pools()
+    }
+
+    /// Commit entries within a seqno `window`, refer to
+    /// [Index::commit_within].
+    pub fn commit_within<C, F>(
+        &mut self,
+        scanner: CommitIter<K, V, C>,
+        window: (Bound<u64>, Bound<u64>),
+        metacb: F,
+    ) -> Result<(Bound<u64>, Bound<u64>)>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        let res = self.as_index()?.commit_within(scanner, window, metacb)?;
+        self.refresh_pools()?;
+        Ok(res)
     }
 
     pub fn compact(&mut self, cutoff: Cutoff) -> Result<usize> {
-        let mut index = self.as_index()?;
-        index.compact(cutoff)
+        let n = self.as_index()?.compact(cutoff)?;
+        self.refresh_pools()?;
+        Ok(n)
+    }
+
+    // replace reader handles in live pools with fresh handles, pools
+    // that are dropped by the application are forgotten.
+    fn refresh_pools(&mut self) -> Result<()> {
+        self.pools.retain(|pool| pool.strong_count() > 0);
+        for pool in self.pools.iter().filter_map(|pool| pool.upgrade()) {
+            for n in 0..pool.len() {
+                let mut reader = lock_reader(&pool.readers[n])?;
+                pool.refresh(n, &mut reader)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, I> Rdms<K, V, I>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: Index<K, V> + Footprint,
+{
+    /// Return a snapshot of metrics for this instance, refer to
+    /// [METRICS_SCHEMA] for its JSON encoding.
+    pub fn to_metrics(&self) -> Result<Metrics> {
+        let index = self.as_index()?;
+        Ok(Metrics {
+            name: self.name.clone(),
+            index: index.to_name()?,
+            seqno: index.to_seqno()?,
+            footprint: index.footprint()?,
+            reclaimable: index.to_reclaimable(false)?,
+            reclaimable_cutoff: index.to_reclaimable(true)?,
+        })
+    }
+
+    /// Return write and space amplification for this instance. Ingested
+    /// bytes are counted only for mutations through
+    /// [to_wal_writer][Rdms::to_wal_writer] handles, and all byte counts
+    /// start from ZERO when this instance is created.
+    pub fn to_amplification(&self) -> Result<Amplification> {
+        let index = self.as_index()?;
+        let footprint = index.footprint()?;
+        let reclaimable: isize = convert_at!(index.to_reclaimable(true)?)?;
+        Ok(Amplification {
+            logical_bytes: self.logical_bytes.load(SeqCst),
+            wal_bytes: self.wal_bytes.load(SeqCst),
+            disk_bytes: index.to_bytes_written()?,
+            footprint,
+            live_bytes: footprint - reclaimable,
+        })
+    }
+}
+
+/// Pool of reader handles into [Rdms] index, refer to
+/// [Rdms::to_reader_pool]. Pool can be shared across threads, say by
+/// web handlers, using the returned `Arc`.
+pub struct ReaderPool<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Index<K, V>,
+{
+    index: sync::Weak<sync::Mutex<I>>,
+    generation: Option<Arc<AtomicU64>>, // level swaps in index.
+    readers: Vec<sync::Mutex<<I as Index<K, V>>::R>>,
+    generations: Vec<AtomicU64>, // generation seen by each handle.
+    next: AtomicUsize,
+
+    _key: marker::PhantomData<K>,
+    _value: marker::PhantomData<V>,
+}
+
+impl<K, V, I> ReaderPool<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Index<K, V>,
+{
+    /// Return the number of reader handles in this pool.
+    pub fn len(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Return true if pool has no reader handles.
+    pub fn is_empty(&self) -> bool {
+        self.readers.is_empty()
+    }
+
+    /// Acquire the next reader handle, in round-robin fashion, blocks if
+    /// the handle is held by another caller. If index has swapped its
+    /// levels since the handle was created, say by auto-commit, handle
+    /// is replaced with a fresh one. Handle is returned to the pool when
+    /// the guard is dropped.
+    pub fn acquire(&self) -> Result<MutexGuard<<I as Index<K, V>>::R>> {
+        let n = self.next.fetch_add(1, SeqCst) % self.readers.len();
+        let mut reader = lock_reader(&self.readers[n])?;
+        if self.generations[n].load(SeqCst) != self.to_generation() {
+            self.refresh(n, &mut reader)?;
+        }
+        Ok(reader)
+    }
+
+    fn to_generation(&self) -> u64 {
+        self.generation.as_ref().map_or(0, |g| g.load(SeqCst))
+    }
+
+    // replace n-th handle, locked by caller, with a fresh handle.
+    fn refresh(&self, n: usize, reader: &mut <I as Index<K, V>>::R) -> Result<()> {
+        let generation = self.to_generation();
+        *reader = match self.index.upgrade() {
+            Some(index) => match index.lock() {
+                Ok(mut index) => index.to_reader()?,
+                Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err))?,
+            },
+            None => err_at!(APIMisuse, msg: format!("reader pool after close"))?,
+        };
+        self.generations[n].store(generation, SeqCst);
+        Ok(())
+    }
+}
+
+fn lock_reader<R>(reader: &sync::Mutex<R>) -> Result<MutexGuard<R>> {
+    match reader.lock() {
+        Ok(value) => Ok(value),
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+/// Schema for [Metrics], scraped by external tools as JSON.
+pub static METRICS_SCHEMA: schema::Schema = {
+    use schema::{Field, Kind};
+
+    schema::Schema {
+        name: "rdms.metrics",
+        version: 1,
+        fields: &[
+            Field::new("name", Kind::Str, 0),
+            Field::new("index", Kind::Str, 0),
+            Field::new("seqno", Kind::Int, 0),
+            Field::new("footprint", Kind::Int, 0),
+            Field::new("reclaimable", Kind::Int, 0),
+            Field::new("reclaimable_cutoff", Kind::Int, 0),
+        ],
+    }
+};
+
+/// Metrics for a [Rdms] instance, refer to [Rdms::to_metrics].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Name of the [Rdms] instance.
+    pub name: String,
+    /// Name of the underlying index.
+    pub index: String,
+    /// Latest seqno in the index.
+    pub seqno: u64,
+    /// Memory and disk footprint of the index.
+    pub footprint: isize,
+    /// Bytes that can be reclaimed without a cutoff.
+    pub reclaimable: usize,
+    /// Bytes that can be reclaimed with a cutoff.
+    pub reclaimable_cutoff: usize,
+}
+
+impl Metrics {
+    fn to_record(&self) -> schema::Record {
+        let mut r = schema::Record::new(&METRICS_SCHEMA);
+        r.set("name", self.name.as_str())
+            .set("index", self.index.as_str())
+            .set("seqno", self.seqno)
+            .set("footprint", self.footprint)
+            .set("reclaimable", self.reclaimable)
+            .set("reclaimable_cutoff", self.reclaimable_cutoff);
+        r
+    }
+}
+
+impl ToJson for Metrics {
+    fn to_json(&self) -> String {
+        self.to_record().to_json()
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.to_record())
+    }
+}
+
+impl FromStr for Metrics {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Metrics> {
+        let r = schema::Record::from_json(&METRICS_SCHEMA, s)?;
+        Ok(Metrics {
+            name: r.get_string("name")?,
+            index: r.get_string("index")?,
+            seqno: r.get_u64("seqno")?,
+            footprint: r.get_isize("footprint")?,
+            reclaimable: r.get_usize("reclaimable")?,
+            reclaimable_cutoff: r.get_usize("reclaimable_cutoff")?,
+        })
+    }
+}
+
+/// Write and space amplification for a [Rdms] instance, refer to
+/// [Rdms::to_amplification].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Amplification {
+    /// Bytes ingested by the application, as key and value footprint.
+    pub logical_bytes: usize,
+    /// Bytes appended to write-ahead-log journals.
+    pub wal_bytes: usize,
+    /// Bytes written to disk by index commits and compactions.
+    pub disk_bytes: usize,
+    /// Memory and disk footprint of the index.
+    pub footprint: isize,
+    /// Footprint of the index, less the bytes that can be reclaimed with
+    /// a cutoff.
+    pub live_bytes: isize,
+}
+
+impl Amplification {
+    /// Return the ratio of bytes written, to write-ahead-log and to disk,
+    /// against bytes ingested. Return ZERO if nothing is ingested.
+    pub fn to_write_amplification(&self) -> f64 {
+        match self.logical_bytes {
+            0 => 0.0,
+            n => ((self.wal_bytes + self.disk_bytes) as f64) / (n as f64),
+        }
+    }
+
+    /// Return the ratio of index footprint against live data. Return
+    /// ZERO if there is no live data.
+    pub fn to_space_amplification(&self) -> f64 {
+        match self.live_bytes {
+            n if n <= 0 => 0.0,
+            n => (self.footprint as f64) / (n as f64),
+        }
     }
 }
 
@@ -174,3 +967,143 @@ where
         index.validate()
     }
 }
+
+/// Summary of a single component in a [Dgm] index, refer to
+/// [verify_consistency].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Component {
+    /// Either `m0`, `m1`, or the disk level's name.
+    pub name: String,
+    /// Number of entries in this component.
+    pub n_entries: usize,
+    /// Smallest seqno, including older versions, in this component.
+    pub min_seqno: Option<u64>,
+    /// Largest seqno in this component.
+    pub max_seqno: Option<u64>,
+}
+
+impl Component {
+    pub(crate) fn new(name: String) -> Component {
+        Component {
+            name,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn add_entry<K, V>(&mut self, entry: &Entry<K, V>)
+    where
+        K: Clone + Ord,
+        V: Clone + Diff,
+    {
+        let seqno = entry.to_seqno();
+        let min_seqno = entry
+            .as_deltas()
+            .iter()
+            .map(|d| d.to_seqno())
+            .fold(seqno, cmp::min);
+
+        self.n_entries += 1;
+        self.min_seqno = Some(self.min_seqno.map_or(min_seqno, |s| cmp::min(s, min_seqno)));
+        self.max_seqno = Some(self.max_seqno.map_or(seqno, |s| cmp::max(s, seqno)));
+    }
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let none = "none".to_string();
+        let a = self.min_seqno.map_or(none.clone(), |x| x.to_string());
+        let z = self.max_seqno.map_or(none, |x| x.to_string());
+        write!(
+            f,
+            "{} = {{ n_entries={}, min_seqno={}, max_seqno={} }}",
+            self.name, self.n_entries, a, z
+        )
+    }
+}
+
+/// Report generated by [verify_consistency].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Consistency {
+    /// Seqno of the memory index.
+    pub mem_seqno: u64,
+    /// Latest seqno persisted in disk levels.
+    pub disk_seqno: u64,
+    /// Replay window of the write-ahead-log, refer to
+    /// [Wal::to_replay_window].
+    pub wal_window: Option<(u64, u64)>,
+    /// Summary of each component, starting from the latest.
+    pub components: Vec<Component>,
+    /// Number of disk entries that are not reachable via lookup.
+    pub n_missing: usize,
+}
+
+impl fmt::Display for Consistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let w = match self.wal_window {
+            Some((low, high)) => format!("({}, {}]", low, high),
+            None => "none".to_string(),
+        };
+        write!(
+            f,
+            "rdms = {{ mem_seqno={}, disk_seqno={}, wal_window={}, n_missing={} }}\n",
+            self.mem_seqno, self.disk_seqno, w, self.n_missing
+        )?;
+        for component in self.components.iter() {
+            write!(f, "rdms.{}\n", component)?;
+        }
+        Ok(())
+    }
+}
+
+/// Self check for LSM indexes, can be run on startup.
+///
+/// Verify that,
+///
+/// * seqno of memory index is at or beyond the latest seqno persisted
+///   in disk levels.
+/// * if `wal` is supplied, mutations that are only in memory, that is,
+///   after `disk_seqno` and upto `mem_seqno`, can be replayed from `wal`.
+/// * every entry in disk levels is reachable via index lookup, with the
+///   same or a newer seqno. Missing entries are typically left behind by
+///   a bad commit or compaction.
+///
+/// Return the report on success, with a summary of every component.
+/// Return `Error::Fatal` if any of the above checks fail.
+pub fn verify_consistency<K, V, M, D, H>(
+    index: &mut Dgm<K, V, M, D>,
+    wal: Option<&mut Wal<K, V, H>>,
+) -> Result<Consistency>
+where
+    K: 'static + Send + Clone + Ord + Default + Hash + Serialize + Footprint + fmt::Debug,
+    V: 'static + Send + Clone + Default + Diff + Serialize + Footprint,
+    <V as Diff>::D: Serialize,
+    M: WriteIndexFactory<K, V>,
+    D: DiskIndexFactory<K, V>,
+    H: Clone + BuildHasher,
+{
+    let mut report = index.to_consistency()?;
+    report.wal_window = wal.map(|wal| wal.to_replay_window());
+
+    if report.mem_seqno < report.disk_seqno {
+        let (m, d) = (report.mem_seqno, report.disk_seqno);
+        err_at!(Fatal, msg: format!("mem_seqno {} < disk_seqno {}", m, d))?;
+    }
+    let (d, m) = (report.disk_seqno, report.mem_seqno);
+    match report.wal_window {
+        Some((low, high)) if m > d && (low > d || high < m) => {
+            let msg = format!("wal ({}, {}] does not cover ({}, {}]", low, high, d, m);
+            err_at!(Fatal, msg: msg)?;
+        }
+        _ => (),
+    }
+    if report.n_missing > 0 {
+        let msg = format!("{} entries missing in disk levels", report.n_missing);
+        err_at!(Fatal, msg: msg)?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+#[path = "rdms_test.rs"]
+mod rdms_test;
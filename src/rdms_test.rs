@@ -0,0 +1,83 @@
+use std::{convert, mem, thread, time};
+
+use super::*;
+use crate::{
+    core::{Reader, Writer},
+    dgm::{self, Dgm},
+    mvcc,
+    nobitmap::NoBitmap,
+    robt,
+};
+
+#[test]
+fn test_reader_pool_generation() {
+    let mut config: dgm::Config = Default::default();
+    config.set_mem_ratio(1000.0).unwrap();
+    config.set_disk_ratio(0.0).unwrap();
+    config
+        .set_commit_interval(time::Duration::from_secs(1))
+        .unwrap();
+    config
+        .set_compact_interval(time::Duration::from_secs(2))
+        .unwrap();
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-rdms-reader-pool-generation");
+        dir.into_os_string()
+    };
+    let name = "rdms-reader-pool-generation";
+    let index = Dgm::new(
+        &dir,
+        name,
+        mvcc::mvcc_factory(false /*lsm*/),
+        robt::robt_factory::<i64, i64, NoBitmap>(Default::default()),
+        config,
+    )
+    .unwrap();
+
+    let mut rdms = Rdms::new(name, index).unwrap();
+    let pool = rdms.to_reader_pool(2).unwrap();
+
+    // two commits, into two levels, that the auto-compaction thread
+    // shall later merge into one.
+    for i in 1..3 {
+        {
+            let mut w = rdms.to_writer().unwrap();
+            for key in 0..100 {
+                w.set(key, key * i).unwrap();
+            }
+        }
+        rdms.commit(CommitIter::new_empty(), convert::identity)
+            .unwrap();
+    }
+    let seen = pool.to_generation();
+    assert!(seen > 0);
+    for g in pool.generations.iter() {
+        assert_eq!(g.load(SeqCst), seen);
+    }
+
+    let start = time::SystemTime::now();
+    while pool.to_generation() == seen {
+        assert!(start.elapsed().unwrap() < time::Duration::from_secs(30));
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    // level swap by auto-compaction does not go through rdms.
+    for g in pool.generations.iter() {
+        assert_eq!(g.load(SeqCst), seen);
+    }
+
+    for n in 0..pool.len() {
+        let mut r = pool.acquire().unwrap();
+        for key in 0..100 {
+            let entry = r.get(&key).unwrap();
+            assert_eq!(entry.to_native_value(), Some(key * 2));
+        }
+        let g = pool.generations[n].load(SeqCst);
+        assert_eq!(g, pool.to_generation());
+        assert!(g > seen);
+    }
+
+    mem::drop(pool);
+    rdms.purge().unwrap();
+}
@@ -7,7 +7,7 @@
 //!
 //! ```text
 //! *------------------------------------------* SeekFrom::End(0)
-//! |                marker-length             |
+//! |          version | marker-length         |
 //! *------------------------------------------* SeekFrom::End(-8)
 //! |                stats-length              |
 //! *------------------------------------------* SeekFrom::End(-16)
@@ -16,6 +16,10 @@
 //! |                bitmap-length             |
 //! *------------------------------------------* SeekFrom::End(-32)
 //! |                  root-fpos               |
+//! *------------------------------------------* SeekFrom::End(-40)
+//! |          range-tombstones-length         |
+//! *------------------------------------------* SeekFrom::End(-48)
+//! |             dictionary-length            |
 //! *------------------------------------------* SeekFrom::MetaBlock
 //! *                 meta-blocks              *
 //! *                    ...                   *
@@ -33,43 +37,84 @@
 //! * Bitmap length, to optimize missing key lookups.
 //! * File-position for btree's root-block.
 //!
+//! Upper 16 bits of `marker-length` carry the header version. Version 0
+//! is the 40-byte header, later versions extend the header backwards
+//! with following details:
+//! * Version 1: Range tombstones length, refer to [RangeTombstone].
+//! * Version 2: Value-log dictionary length, refer to [Compressor].
+//!
 //! Total length of `metadata-blocks` can be computed based on
-//! `marker-length`, `stats-length`, `app-metadata-length`, `bitmap-length`.
+//! `marker-length`, `stats-length`, `app-metadata-length`, `bitmap-length`,
+//! `range-tombstones-length`, `dictionary-length`. Meta-blocks are padded,
+//! so that they start at the same alignment as btree-blocks, refer to
+//! [Config::set_align].
+//!
+//! Snapshots can be exported into a portable format using
+//! [Snapshot::export], and converted back using [Importer].
+//!
+//! Btree blocks and value-log blocks can be encrypted at rest by
+//! configuring a [Cipher], refer to [Config::set_cipher]. Values and
+//! deltas in value-log can be compressed using a trained dictionary,
+//! refer to [Config::set_compression].
+//!
+//! Very large builds can periodically checkpoint their progress into a
+//! sidecar file, so that a crashed build can be continued from its last
+//! checkpoint, refer to [Builder::set_checkpoint] and [Builder::resume].
+//!
+//! Builder expects entries in sort order, unsorted input can be sorted
+//! within a memory budget using [ExtSorter].
 //!
 //! [Config]: crate::robt::Config
 //!
 
 use fs2::FileExt;
 use lazy_static::lazy_static;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 use std::{
     borrow::Borrow,
     cmp,
+    collections::HashMap,
     convert::{TryFrom, TryInto},
     ffi, fmt, fs,
-    hash::Hash,
+    hash::{BuildHasher, Hash},
     io::{self, Read, Seek, Write},
     marker, mem,
     ops::{Bound, Deref, RangeBounds},
     path, result,
     str::FromStr,
-    sync::{self, mpsc, Arc, MutexGuard},
-    thread, time,
+    sync::{
+        self,
+        atomic::{AtomicU64, AtomicUsize, Ordering::SeqCst},
+        mpsc, Arc, MutexGuard,
+    },
+    thread, time, vec,
 };
 
-#[allow(unused_imports)] // for documentation
-use crate::rdms::Rdms;
 use crate::{
+    core::CompactionFilter,
     core::Cutoff,
     core::{self, Bloom, CommitIterator, Index, Serialize, ToJson, Validate},
     core::{Diff, DiskIndexFactory, Entry, Footprint, IndexIter, Reader, Result},
-    error::Error,
-    panic::Panic,
+    core::{MergePolicy, SharedMergePolicy},
+    core::{PiecewiseScan, ScanEntry, ScanIter, SizeLimit, SizePolicy},
+    error::{Error, Internal},
+    panic::Unsupported,
+    robt_cipher::{self, FileCipher},
+    robt_compress::{self, Sampler, ValueDict},
     robt_entry::MEntry,
     robt_index::{MBlock, ZBlock},
-    scans, thread as rt, util,
+    robt_sort::{Sorter, WalSink},
+    scans, schema, thread as rt, util, vlog,
+    wal::Wal,
 };
+#[allow(unused_imports)] // for documentation
+use crate::{core::OrdSpec, rdms::Rdms, types::Ordered};
+
+pub use crate::robt_cipher::{register_cipher, Cipher};
+pub use crate::robt_compress::{register_compressor, Compressor};
+pub use crate::robt_export::{Exporter, Importer, EXPORT_MAGIC, EXPORT_VERSION};
+pub use crate::robt_sort::{ExtSortIter, ExtSorter};
 
 include!("robt_marker.rs");
 
@@ -198,9 +243,19 @@ impl TryFrom<VlogFileName> for Name {
     fn try_from(fname: VlogFileName) -> Result<Name> {
         let check_file = |fname: VlogFileName| -> Option<String> {
             let fname = path::Path::new(&fname.0);
-            match fname.extension()?.to_str()? {
-                "vlog" => Some(fname.file_stem()?.to_str()?.to_string()),
-                _ => None,
+            let stem = match fname.extension()?.to_str()? {
+                "vlog" => fname.file_stem()?.to_str()?.to_string(),
+                _ => return None,
+            };
+            let name: Result<(String, usize)> = Name(stem.clone()).try_into();
+            match name {
+                Ok(_) => Some(stem),
+                // segment files are suffixed with segment number.
+                Err(_) => {
+                    let (stem, segment) = stem.split_at(stem.rfind('-')?);
+                    segment[1..].parse::<usize>().ok()?;
+                    Some(stem.to_string())
+                }
             }
         };
 
@@ -231,6 +286,8 @@ where
 {
     RobtFactory {
         config,
+        filter: None,
+        merge: None,
 
         _phantom_key: marker::PhantomData,
         _phantom_val: marker::PhantomData,
@@ -248,12 +305,41 @@ where
     <V as Diff>::D: Serialize,
 {
     config: Config,
+    filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+    merge: Option<SharedMergePolicy<K, V>>,
 
     _phantom_key: marker::PhantomData<K>,
     _phantom_val: marker::PhantomData<V>,
     _phantom_bitmap: marker::PhantomData<B>,
 }
 
+impl<K, V, B> RobtFactory<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+    <V as Diff>::D: Serialize,
+{
+    /// Set compaction filter for all [Robt] instances constructed by
+    /// this factory, refer to [Robt::set_compaction_filter].
+    pub fn set_compaction_filter(
+        &mut self,
+        filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+    ) -> Result<&mut Self> {
+        self.filter = filter;
+        Ok(self)
+    }
+
+    /// Set merge policy for all [Robt] instances constructed by this
+    /// factory, refer to [Index::set_merge_policy].
+    pub fn set_merge_policy(
+        &mut self,
+        policy: Option<SharedMergePolicy<K, V>>,
+    ) -> Result<&mut Self> {
+        self.merge = policy;
+        Ok(self)
+    }
+}
+
 impl<K, V, B> DiskIndexFactory<K, V> for RobtFactory<K, V, B>
 where
     K: Default + Clone + Ord + Hash + Footprint + Serialize,
@@ -267,7 +353,10 @@ where
         let mut config = self.config.clone();
         config.name = name.to_string();
 
-        Robt::new(dir, name, config)
+        let mut index = Robt::new(dir, name, config)?;
+        index.set_compaction_filter(self.filter.clone())?;
+        index.set_merge_policy(self.merge.clone())?;
+        Ok(index)
     }
 
     fn open(&self, dir: &ffi::OsStr, name: &str) -> Result<Robt<K, V, B>> {
@@ -276,7 +365,18 @@ where
             "{}, open from {:?} ...", name, dir,
         );
 
-        Robt::open(dir, name)
+        let mut index = Robt::open(dir, name)?;
+        index.set_compaction_filter(self.filter.clone())?;
+        index.set_merge_policy(self.merge.clone())?;
+        let ord_spec = index.to_ord_spec()?;
+        if ord_spec != self.config.ord_spec {
+            let msg = format!(
+                "{:?}/{} ord_spec {:?} != {:?}",
+                dir, name, ord_spec, self.config.ord_spec
+            );
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        Ok(index)
     }
 
     fn to_type(&self) -> String {
@@ -294,6 +394,56 @@ where
 {
     inner: sync::Mutex<InnerRobt<K, V, B>>,
     purger: Option<rt::Thread<ffi::OsString, (), ()>>,
+    pins: Arc<sync::Mutex<Pins>>,
+    throttle: Throttle,
+    filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+    merge: Option<SharedMergePolicy<K, V>>,
+    written: Arc<AtomicUsize>, // bytes written by commits and compactions.
+}
+
+/// Backpressure state of [Robt] index, refer to [Robt::to_pressure].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pressure {
+    /// Index is keeping up with ingestion.
+    Ok,
+    /// Garbage is accumulating faster than it is compacted, callers
+    /// should throttle ingestion.
+    SlowDown,
+    /// Callers should stop ingestion until the index is compacted.
+    Stall,
+}
+
+// Backpressure limits, as ratio of reclaimable bytes to disk footprint.
+#[derive(Clone, Copy)]
+struct Throttle {
+    slow_down: f64,
+    stall: f64,
+    block: bool,
+}
+
+impl Default for Throttle {
+    fn default() -> Throttle {
+        Throttle {
+            slow_down: Throttle::SLOW_DOWN_RATIO,
+            stall: Throttle::STALL_RATIO,
+            block: false,
+        }
+    }
+}
+
+impl Throttle {
+    const SLOW_DOWN_RATIO: f64 = 0.5;
+    const STALL_RATIO: f64 = 0.8;
+
+    fn to_pressure(&self, ratio: f64) -> Pressure {
+        if ratio > self.stall {
+            Pressure::Stall
+        } else if ratio > self.slow_down {
+            Pressure::SlowDown
+        } else {
+            Pressure::Ok
+        }
+    }
 }
 
 enum InnerRobt<K, V, B>
@@ -382,6 +532,11 @@ where
         Robt {
             inner: sync::Mutex::new(inner.clone()),
             purger: Some(purger),
+            pins: Arc::clone(&self.pins),
+            throttle: self.throttle,
+            filter: self.filter.clone(),
+            merge: self.merge.clone(),
+            written: Arc::clone(&self.written),
         }
     }
 }
@@ -394,15 +549,17 @@ where
     B: Bloom,
 {
     fn drop(&mut self) {
-        let (name, dir) = loop {
-            match self.inner.get_mut() {
-                Ok(inner) => match inner {
-                    InnerRobt::Build { name, dir, .. } => break (name.clone(), dir.clone()),
-                    InnerRobt::Snapshot { name, dir, .. } => break (name.clone(), dir.clone()),
-                },
-                Err(err) => error!(target: "robt  ", "drop {}", err),
+        let inner = match self.inner.get_mut() {
+            Ok(inner) => inner,
+            Err(err) => {
+                error!(target: "robt  ", "drop {}", err);
+                err.into_inner()
             }
         };
+        let (name, dir) = match inner {
+            InnerRobt::Build { name, dir, .. } => (name.clone(), dir.clone()),
+            InnerRobt::Snapshot { name, dir, .. } => (name.clone(), dir.clone()),
+        };
 
         // wait till purger routine exit.
         match self.purger.take() {
@@ -453,6 +610,11 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner),
             purger: Some(purger),
+            pins: Default::default(),
+            throttle: Default::default(),
+            filter: None,
+            merge: None,
+            written: Default::default(),
         })
     }
 
@@ -487,6 +649,11 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner),
             purger: Some(purger),
+            pins: Default::default(),
+            throttle: Default::default(),
+            filter: None,
+            merge: None,
+            written: Default::default(),
         })
     }
 
@@ -502,6 +669,11 @@ where
         Ok(Robt {
             inner: sync::Mutex::new(inner.deref().clone()),
             purger: Some(purger),
+            pins: Arc::clone(&self.pins),
+            throttle: self.throttle,
+            filter: self.filter.clone(),
+            merge: self.merge.clone(),
+            written: Arc::clone(&self.written),
         })
     }
 
@@ -596,6 +768,80 @@ where
         Ok(parts.1) // version
     }
 
+    /// Set backpressure limits for this index, `slow_down` and `stall`
+    /// are ratio of reclaimable bytes, like stale value-log, to the
+    /// index's disk footprint. Default limits are 0.5 and 0.8. If `block`
+    /// is true, [commit][Index::commit] on a stalled index shall compact
+    /// the index first, blocking the caller.
+    pub fn set_pressure_limits(
+        &mut self,
+        slow_down: f64,
+        stall: f64,
+        block: bool,
+    ) -> Result<&mut Self> {
+        if slow_down < 0.0 || slow_down > stall || stall > 1.0 {
+            let msg = format!("pressure limits slow_down:{} stall:{}", slow_down, stall);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        self.throttle = Throttle {
+            slow_down,
+            stall,
+            block,
+        };
+        Ok(self)
+    }
+
+    /// Set a filter to drop or transform entries while compacting this
+    /// index, refer to [CompactionFilter]. Filter is applied on entries
+    /// that survive the compaction cutoff, and it is not persisted, hence
+    /// shall be set again after [open][Robt::open]. Compaction shall
+    /// rewrite the index, even with an empty cutoff, if a filter is set.
+    pub fn set_compaction_filter(
+        &mut self,
+        filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+    ) -> Result<&mut Self> {
+        self.filter = filter;
+        Ok(self)
+    }
+
+    /// Return the backpressure state of this index, computed from the
+    /// garbage that compaction is yet to reclaim. Ingestion layer can
+    /// use this to throttle its producers, refer to
+    /// [set_pressure_limits][Robt::set_pressure_limits].
+    pub fn to_pressure(&self) -> Result<Pressure> {
+        let ratio = match self.as_inner()?.deref() {
+            InnerRobt::Snapshot { stats, .. } => stats.to_reclaimable_ratio(false),
+            InnerRobt::Build { .. } => 0.0,
+        };
+        Ok(self.throttle.to_pressure(ratio))
+    }
+
+    /// Pin the current version of index, refer to [LevelsGuard] for
+    /// details. Index files of a pinned version shall not be purged,
+    /// neither by subsequent commit and compaction, nor by
+    /// [purge][Index::purge], until the guard is dropped.
+    pub fn pin(&self) -> Result<LevelsGuard<K, V, B>> {
+        let inner = self.as_inner()?;
+        match inner.deref() {
+            InnerRobt::Snapshot {
+                dir, name, bitmap, ..
+            } => {
+                // open the snapshot under the lock, so that its files are
+                // locked before commit or compact can post them for purge.
+                let mut snapshot = Snapshot::open(dir, &name.0)?;
+                snapshot.set_bitmap(Arc::clone(bitmap));
+                lock_pins(&self.pins)?.pin(&name.0);
+
+                Ok(LevelsGuard {
+                    name: name.clone(),
+                    snapshot: Some(snapshot),
+                    pins: Arc::clone(&self.pins),
+                })
+            }
+            InnerRobt::Build { .. } => err_at!(UnInitialized, msg: format!("Robt.pin()")),
+        }
+    }
+
     pub fn to_next_version(&mut self) -> Result<Vec<ffi::OsString>> {
         let mut inner = self.as_inner()?;
         let (new_inner, purge_files) = match inner.deref() {
@@ -608,9 +854,7 @@ where
 
                 // purge old snapshots file(s).
                 purge_files.push(old.index_fd.to_file());
-                if let Some((file, _)) = &old.valog_fd {
-                    purge_files.push(file.clone());
-                }
+                purge_files.extend(old.to_vlog_files());
 
                 let mut config = config.clone();
                 config.vlog_file = None; // ignore the old file.
@@ -676,6 +920,293 @@ where
             }
         }
     }
+
+    fn to_ord_spec(&self) -> Result<String> {
+        match self.as_inner()?.deref() {
+            InnerRobt::Build { config, .. } => Ok(config.ord_spec.clone()),
+            InnerRobt::Snapshot { config, .. } => Ok(config.ord_spec.clone()),
+        }
+    }
+}
+
+/// Guard pinning a version of [Robt] index, refer to [Robt::pin].
+///
+/// While the guard is alive, index-file and value-log-file of the pinned
+/// version are not purged. Purge requests on a pinned version are
+/// deferred till its last guard is dropped. Long running scans, like
+/// backups, shall read through [to_reader][LevelsGuard::to_reader] so
+/// that a concurrent compaction cannot remove files underneath them.
+pub struct LevelsGuard<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    name: Name,
+    snapshot: Option<Snapshot<K, V, B>>,
+    pins: Arc<sync::Mutex<Pins>>,
+}
+
+impl<K, V, B> Drop for LevelsGuard<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    fn drop(&mut self) {
+        // release the file locks before purging deferred files.
+        mem::drop(self.snapshot.take());
+
+        let files = match lock_pins(&self.pins) {
+            Ok(mut pins) => pins.unpin(&self.name.0),
+            Err(err) => {
+                error!(target: "robt  ", "{}, unpin {:?}", self.name, err);
+                vec![]
+            }
+        };
+        let (mut locked_files, mut err_files) = (vec![], vec![]);
+        for file in files.into_iter() {
+            purge_file(file, &mut locked_files, &mut err_files);
+        }
+        if locked_files.len() > 0 || err_files.len() > 0 {
+            error!(
+                target: "robt  ", "{}, deferred purge failed {:?} {:?}",
+                self.name, locked_files, err_files
+            );
+        }
+    }
+}
+
+impl<K, V, B> LevelsGuard<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+    <V as Diff>::D: Serialize,
+    B: Bloom,
+{
+    /// Return the pinned version of index.
+    pub fn to_version(&self) -> Result<usize> {
+        let parts: (String, usize) = TryFrom::try_from(self.name.clone())?;
+        Ok(parts.1) // version
+    }
+
+    /// Open a new reader on the pinned version of index.
+    pub fn to_reader(&self) -> Result<Snapshot<K, V, B>> {
+        match &self.snapshot {
+            Some(snapshot) => {
+                let mut r = Snapshot::open(&snapshot.dir, &self.name.0)?;
+                r.set_bitmap(Arc::clone(&snapshot.bitmap));
+                Ok(r)
+            }
+            None => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
+    /// Pin the same version of index again, returned guard can be
+    /// dropped independent of this guard.
+    pub fn try_clone(&self) -> Result<LevelsGuard<K, V, B>> {
+        let snapshot = self.to_reader()?;
+        lock_pins(&self.pins)?.pin(&self.name.0);
+
+        Ok(LevelsGuard {
+            name: self.name.clone(),
+            snapshot: Some(snapshot),
+            pins: Arc::clone(&self.pins),
+        })
+    }
+}
+
+// Versions pinned by LevelsGuard, shared by a Robt instance and its
+// clones. Maps version-name to number of guards and deferred files.
+#[derive(Default)]
+struct Pins {
+    versions: HashMap<String, (usize, Vec<ffi::OsString>)>,
+}
+
+impl Pins {
+    fn pin(&mut self, name: &str) {
+        let pinned = self.versions.entry(name.to_string()).or_insert((0, vec![]));
+        pinned.0 += 1;
+    }
+
+    // return deferred files, once the last guard is dropped.
+    fn unpin(&mut self, name: &str) -> Vec<ffi::OsString> {
+        let n = match self.versions.get_mut(name) {
+            Some((n, _)) => {
+                *n -= 1;
+                *n
+            }
+            None => return vec![],
+        };
+        match n {
+            0 => match self.versions.remove(name) {
+                Some((_, files)) => files,
+                None => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    // return true, if version is pinned and files are deferred.
+    fn defer(&mut self, name: &str, files: Vec<ffi::OsString>) -> bool {
+        match self.versions.get_mut(name) {
+            Some((_, deferred)) => {
+                deferred.extend(files);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn lock_pins(pins: &sync::Mutex<Pins>) -> Result<MutexGuard<Pins>> {
+    match pins.lock() {
+        Ok(value) => Ok(value),
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+// Iterator holding a LevelsGuard, for the duration of a scan.
+struct PinnedIter<'a, K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    iter: IndexIter<'a, K, V>,
+    _guard: LevelsGuard<K, V, B>,
+}
+
+impl<'a, K, V, B> Iterator for PinnedIter<'a, K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+/// Read fan-out across several snapshots, ordered from the newest to
+/// the oldest, like levels in an LSM tree.
+///
+/// Snapshots can be opened via [Index::to_reader] or, to prevent them
+/// from being purged by concurrent compaction, via
+/// [LevelsGuard::to_reader]. A level is skipped during lookup if the
+/// key falls outside its key range, or if its bitmap rules out the key.
+/// Range tombstones in a level apply to entries from all older levels.
+pub struct Levels<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    levels: Vec<Level<K, V, B>>,
+}
+
+struct Level<K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    snapshot: Snapshot<K, V, B>,
+    range: Option<(K, K)>, // first and last key, None if level is empty.
+    tombs: Arc<Vec<RangeTombstone<K>>>,
+}
+
+impl<K, V, B> Levels<K, V, B>
+where
+    K: Default + Clone + Ord + Serialize + Footprint,
+    V: Default + Clone + Diff + Serialize + Footprint,
+    <V as Diff>::D: Default + Clone + Serialize,
+    B: Bloom,
+{
+    /// Create a read fan-out, `snapshots[0]` is the newest level.
+    pub fn new(snapshots: Vec<Snapshot<K, V, B>>) -> Result<Levels<K, V, B>> {
+        let mut levels = vec![];
+        for mut snapshot in snapshots.into_iter() {
+            let range = match (snapshot.first(), snapshot.last()) {
+                (Ok(first), Ok(last)) => Some((first.to_key(), last.to_key())),
+                (Err(Error::EmptyIndex), _) | (Err(Error::KeyNotFound), _) => None,
+                (_, Err(Error::EmptyIndex)) | (_, Err(Error::KeyNotFound)) => None,
+                (Err(err), _) | (_, Err(err)) => return Err(err),
+            };
+            let tombs = snapshot.to_range_tombstones()?;
+            levels.push(Level {
+                snapshot,
+                range,
+                tombs,
+            });
+        }
+        Ok(Levels { levels })
+    }
+
+    /// Return the number of levels.
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Get the latest version of `key`, probing levels from the newest
+    /// to the oldest.
+    pub fn get<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.do_get(key, false /*versions*/)
+    }
+
+    /// Get `key` along with its older versions. Versions from each
+    /// level are merged, until a level that is not built with
+    /// `delta_ok`, since such levels don't retain older versions.
+    pub fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.do_get(key, true /*versions*/)
+    }
+
+    fn do_get<Q>(&mut self, key: &Q, versions: bool) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let mut acc: Option<Entry<K, V>> = None;
+        let mut tombs: Vec<Arc<Vec<RangeTombstone<K>>>> = vec![];
+        for level in self.levels.iter_mut() {
+            let within = match &level.range {
+                Some((first, last)) => key >= first.borrow() && key <= last.borrow(),
+                None => false,
+            };
+            if within {
+                let res = match versions {
+                    true => level.snapshot.get_with_versions(key),
+                    false => level.snapshot.get(key),
+                };
+                match res {
+                    Ok(entry) => {
+                        // entry could be deleted by a newer level.
+                        if tombs.iter().any(|ts| ts.iter().any(|t| t.covers(&entry))) {
+                            break;
+                        }
+                        acc = Some(match acc.take() {
+                            Some(newer) => newer.xmerge(entry)?,
+                            None => entry,
+                        });
+                        if !versions || !level.snapshot.config.delta_ok {
+                            break;
+                        }
+                    }
+                    Err(Error::KeyNotFound) => (),
+                    Err(err) => return Err(err),
+                }
+            }
+            if !level.tombs.is_empty() {
+                tombs.push(Arc::clone(&level.tombs));
+            }
+        }
+
+        acc.ok_or(Error::KeyNotFound)
+    }
 }
 
 impl<K, V, B> Validate<Stats> for Robt<K, V, B>
@@ -705,7 +1236,7 @@ where
     B: Bloom,
 {
     type R = Snapshot<K, V, B>;
-    type W = Panic;
+    type W = Unsupported;
 
     fn to_name(&self) -> Result<String> {
         let name = match self.as_inner()?.deref() {
@@ -737,6 +1268,24 @@ where
         }
     }
 
+    fn to_reclaimable(&self, cutoff: bool) -> Result<usize> {
+        match self.as_inner()?.deref() {
+            InnerRobt::Snapshot { stats, .. } => Ok(stats.to_reclaimable(cutoff)),
+            InnerRobt::Build { .. } => Ok(0),
+        }
+    }
+
+    fn to_bytes_written(&self) -> Result<usize> {
+        Ok(self.written.load(SeqCst))
+    }
+
+    /// Policy is applied on entries from the commit iterator that are
+    /// already present in the current snapshot.
+    fn set_merge_policy(&mut self, policy: Option<SharedMergePolicy<K, V>>) -> Result<()> {
+        self.merge = policy;
+        Ok(())
+    }
+
     /// Application can set the start sequence number for this index.
     fn set_seqno(&mut self, _seqno: u64) -> Result<()> {
         Ok(())
@@ -757,7 +1306,35 @@ where
     }
 
     fn to_writer(&mut self) -> Result<Self::W> {
-        Ok(Panic::new("robt"))
+        Ok(Unsupported::new("robt"))
+    }
+
+    fn commit_within<C, F>(
+        &mut self,
+        scanner: core::CommitIter<K, V, C>,
+        window: (Bound<u64>, Bound<u64>),
+        metacb: F,
+    ) -> Result<(Bound<u64>, Bound<u64>)>
+    where
+        C: CommitIterator<K, V>,
+        F: Fn(Vec<u8>) -> Vec<u8>,
+    {
+        // seqnos upto the latest snapshot are already persisted.
+        let window = match self.as_inner()?.deref() {
+            InnerRobt::Snapshot { stats, .. } => {
+                let persisted = (Bound::Excluded(stats.seqno), Bound::Unbounded);
+                util::intersect_seqnos(window, persisted)
+            }
+            InnerRobt::Build { .. } => window,
+        };
+        if util::is_empty_seqnos(&window) {
+            let name = self.to_name()?;
+            debug!(target: "robt  ", "{}, skip commit for {:?}", name, window);
+            return Ok(window);
+        }
+
+        self.commit(scanner.clip_within(window.clone()), metacb)?;
+        Ok(window)
     }
 
     fn commit<C, F>(&mut self, mut scanner: core::CommitIter<K, V, C>, metacb: F) -> Result<()>
@@ -765,6 +1342,11 @@ where
         C: CommitIterator<K, V>,
         F: Fn(Vec<u8>) -> Vec<u8>,
     {
+        // reclaim garbage before accepting more, if configured to block.
+        if self.throttle.block && self.to_pressure()? == Pressure::Stall {
+            self.compact(Cutoff::new_lsm_empty())?;
+        }
+
         let mut inner = self.as_inner()?;
         let new_inner = match inner.deref() {
             InnerRobt::Build {
@@ -788,11 +1370,12 @@ where
                     .as_ref()
                     .map(|(vf, _)| vf.clone())
                     .unwrap_or(ffi::OsString::new());
+                let wrote = stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes;
+                self.written.fetch_add(wrote, SeqCst);
                 debug!(
                     target: "robt  ",
                     "{:?}/{}, flush commit to index_file:{:?}, vlog_file:{:?}  footprint:{} wrote:{}",
-                    dir, name, index_file, vlog_file, footprint,
-                    stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes
+                    dir, name, index_file, vlog_file, footprint, wrote
                 );
 
                 InnerRobt::Snapshot {
@@ -800,7 +1383,7 @@ where
                     name: name.clone(),
                     footprint,
                     meta: snapshot.meta.clone(),
-                    config: snapshot.config.clone(),
+                    config: config.to_next_config(snapshot.config.clone(), &stats),
                     stats,
                     bitmap: Arc::clone(&snapshot.bitmap),
                 }
@@ -811,9 +1394,21 @@ where
                 let mut old = Snapshot::<K, V, B>::open(dir, &name.0)?;
                 let old_seqno = old.to_seqno()?;
                 let old_bitmap = Arc::clone(&old.bitmap);
+                let old_uuid = old.to_lineage()?.build_uuid;
+                // carry over range tombstones, older entries are filtered
+                // by the old snapshot's iterator.
+                let old_tombs = old.to_range_tombstones()?.to_vec();
+                // value-log is shared, hence its dictionary.
+                let old_dict = old.to_dictionary()?;
 
                 let (name, snapshot, meta_block_bytes) = {
-                    let bitmap_iter = scans::BitmappedScan::new(scanner.scan()?);
+                    // new entries are expected to be newer than the
+                    // older snapshot.
+                    let bitmap_iter = {
+                        let iter = SeqnoCheck::new(scanner.scan()?, Some(old_seqno));
+                        let iter = UniqueCheck::new(iter, config.strict_unique);
+                        scans::BitmappedScan::new(iter)
+                    };
                     let commit_iter = {
                         let mut mzs = vec![];
                         match old.to_root() {
@@ -822,18 +1417,21 @@ where
                             Err(err) => Err(err),
                         }?;
                         let old_iter = Iter::new_shallow(&mut old, mzs);
-                        CommitScan::new(bitmap_iter, old_iter)
+                        CommitScan::new(bitmap_iter, old_iter, self.merge.clone())
                     };
                     let mut build_iter = BuildScan::new(commit_iter, old_seqno);
 
                     let (name, mut b) = {
                         let name = name.clone().next();
-                        let b = Builder::<K, V, B>::incremental(
+                        let mut b = Builder::<K, V, B>::incremental(
                             //
                             dir,
                             &name.0,
                             config.clone(),
                         )?;
+                        b.set_lineage(vec![old_uuid])?;
+                        b.set_range_tombstones(old_tombs)?;
+                        b.set_dictionary(old_dict)?;
                         (name, b)
                     };
 
@@ -873,11 +1471,12 @@ where
                     .as_ref()
                     .map(|(vf, _)| vf.clone())
                     .unwrap_or(ffi::OsString::new());
+                let wrote = stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes;
+                self.written.fetch_add(wrote, SeqCst);
                 debug!(
                     target: "robt  ",
                     "{:?}/{}, incremental commit to index_file:{:?}, vlog_file:{:?}  footprint:{} wrote:{}",
-                    dir, name, index_file, vlog_file, footprint,
-                    stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes
+                    dir, name, index_file, vlog_file, footprint, wrote
                 );
 
                 InnerRobt::Snapshot {
@@ -885,7 +1484,7 @@ where
                     name: name.clone(),
                     footprint,
                     meta: snapshot.meta.clone(),
-                    config: snapshot.config.clone(),
+                    config: config.to_next_config(snapshot.config.clone(), &stats),
                     stats,
                     bitmap: Arc::clone(&snapshot.bitmap),
                 }
@@ -926,10 +1525,11 @@ where
             } => {
                 {
                     // skip compaction if cutoff is empty and the previous
-                    // build started from a clean vlog file.
+                    // build started from a clean vlog file, unless there
+                    // is a filter to apply.
                     let old = Snapshot::<K, V, B>::open(dir, &name.0)?;
                     let stats = old.to_stats()?;
-                    if cutoff.is_empty() && stats.n_abytes == 0 {
+                    if cutoff.is_empty() && stats.n_abytes == 0 && self.filter.is_none() {
                         return Ok(0);
                     }
                 }
@@ -938,9 +1538,19 @@ where
                     let mut old = Snapshot::<K, V, B>::open(dir, &name.0)?;
                     let old_seqno: u64 = old.to_seqno()?;
 
+                    let old_uuid = old.to_lineage()?.build_uuid;
+                    // entries covered by range tombstones are dropped by
+                    // iteration, tombstones themselves are carried over
+                    // until they fall within the cutoff.
+                    let tombs: Vec<RangeTombstone<K>> = old
+                        .to_range_tombstones()?
+                        .iter()
+                        .filter_map(|tomb| tomb.clone().purge(cutoff))
+                        .collect();
                     let comp_iter = {
                         let iter = old.iter_with_versions()?;
-                        scans::CompactScan::new(iter, cutoff)
+                        let iter = scans::CompactScan::new(iter, cutoff);
+                        scans::CompactFilterScan::new(iter, self.filter.clone())
                     };
 
                     let name = name.clone().next();
@@ -955,6 +1565,8 @@ where
                             _ => err_at!(Fatal, msg: format!("unreachable"))?,
                         };
                         let mut b = Builder::<K, V, B>::initial(dir, &name.0, conf)?;
+                        b.set_lineage(vec![old_uuid])?;
+                        b.set_range_tombstones(tombs)?;
                         // let mbbytes = b.build(comp_iter, meta)?;
 
                         let (root, bitmap): (u64, B) = {
@@ -974,8 +1586,8 @@ where
 
                     // purge old snapshots file(s).
                     self.purger.as_ref().unwrap().post(old.index_fd.to_file())?;
-                    if let Some((file, _)) = &old.valog_fd {
-                        self.purger.as_ref().unwrap().post(file.clone())?;
+                    for file in old.to_vlog_files().into_iter() {
+                        self.purger.as_ref().unwrap().post(file)?;
                     }
 
                     (name, snapshot, meta_block_bytes)
@@ -990,11 +1602,12 @@ where
                     .as_ref()
                     .map(|(vf, _)| vf.clone())
                     .unwrap_or(ffi::OsString::new());
+                let wrote = stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes;
+                self.written.fetch_add(wrote, SeqCst);
                 debug!(
                     target: "robt  ",
                     "{:?}/{}, compacted to index_file:{:?} vlog_file:{:?} footprint:{} wrote:{}",
-                    dir, name, index_file, vlog_file, footprint,
-                    stats.z_bytes + stats.m_bytes + stats.v_bytes + meta_block_bytes
+                    dir, name, index_file, vlog_file, footprint, wrote
                 );
 
                 (
@@ -1003,7 +1616,7 @@ where
                         name: name.clone(),
                         footprint,
                         meta: snapshot.meta.clone(),
-                        config: snapshot.config.clone(),
+                        config: config.to_next_config(snapshot.config.clone(), &stats),
                         stats: stats.clone(),
                         bitmap: Arc::clone(&snapshot.bitmap),
                     },
@@ -1029,11 +1642,22 @@ where
     }
 
     fn purge(mut self) -> Result<()> {
-        self.do_close()?;
+        // files that the purger failed to remove are reported after
+        // purging this snapshot, so that a failed compaction-purge
+        // does not leave the latest snapshot behind.
+        let purger_res = self.do_close();
         let (res, dir, name) = match self.as_inner()?.deref() {
             InnerRobt::Snapshot { dir, name, .. } => {
                 let snapshot = Snapshot::<K, V, B>::open(&dir, &name.0)?;
-                (snapshot.purge(), dir.clone(), name.clone())
+                let mut files = vec![snapshot.index_fd.to_file()];
+                files.extend(snapshot.to_vlog_files());
+                let res = if lock_pins(&self.pins)?.defer(&name.0, files) {
+                    debug!(target: "robt  ", "{:?}/{}, pinned, purge deferred", dir, name);
+                    Ok(())
+                } else {
+                    snapshot.purge()
+                };
+                (res, dir.clone(), name.clone())
             }
             InnerRobt::Build { dir, name, .. } => {
                 let res = err_at!(UnInitialized, msg: format!("Robt.purge()"));
@@ -1043,7 +1667,7 @@ where
 
         debug!(target: "robt  ", "{:?}/{} purged", dir, name);
 
-        res
+        res.and(purger_res)
     }
 }
 
@@ -1058,53 +1682,47 @@ where
     where
         G: Clone + RangeBounds<u64>,
     {
-        match self.as_inner()?.deref() {
-            InnerRobt::Snapshot { dir, name, .. } => {
-                let snap = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                let iter = {
-                    let iter = snap.into_scan()?;
-                    scans::FilterScans::new(vec![iter], within)
-                };
-                Ok(Box::new(iter))
-            }
-            InnerRobt::Build { .. } => err_at!(UnInitialized, msg: format!("Robt.scan()")),
-        }
+        let guard = self.pin()?;
+        let iter: IndexIter<K, V> = {
+            let iter = guard.to_reader()?.into_scan()?;
+            Box::new(scans::FilterScans::new(vec![iter], within))
+        };
+        Ok(Box::new(PinnedIter {
+            iter,
+            _guard: guard,
+        }))
     }
 
     fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
     where
         G: Clone + RangeBounds<u64>,
     {
-        let inner = self.as_inner()?;
-        match inner.deref() {
-            InnerRobt::Snapshot { dir, name, .. } => {
-                let ranges = {
-                    let mut snap = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                    snap.to_shards(n_shards)?
-                };
+        let guard = self.pin()?;
+        let ranges = guard.to_reader()?.to_shards(n_shards)?;
 
-                let mut iters = vec![];
-                for range in ranges.into_iter() {
-                    let snap = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                    let iter: IndexIter<K, V> = Box::new(scans::FilterScans::new(
-                        vec![snap.into_range_scan(range)?],
-                        within.clone(),
-                    ));
-                    iters.push(iter)
-                }
+        let mut iters = vec![];
+        for range in ranges.into_iter() {
+            let snap = guard.to_reader()?;
+            let iter: IndexIter<K, V> = Box::new(scans::FilterScans::new(
+                vec![snap.into_range_scan(range)?],
+                within.clone(),
+            ));
+            let iter: IndexIter<K, V> = Box::new(PinnedIter {
+                iter,
+                _guard: guard.try_clone()?,
+            });
+            iters.push(iter)
+        }
 
-                // If there are not enough shards push empty iterators.
-                for _ in iters.len()..n_shards {
-                    let ss = vec![];
-                    iters.push(Box::new(ss.into_iter()));
-                }
+        // If there are not enough shards push empty iterators.
+        for _ in iters.len()..n_shards {
+            let ss = vec![];
+            iters.push(Box::new(ss.into_iter()));
+        }
 
-                assert_eq!(iters.len(), n_shards);
+        assert_eq!(iters.len(), n_shards);
 
-                Ok(iters)
-            }
-            InnerRobt::Build { .. } => err_at!(UnInitialized, msg: format!("Robt.scans()")),
-        }
+        Ok(iters)
     }
 
     fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
@@ -1112,24 +1730,23 @@ where
         G: Clone + RangeBounds<u64>,
         N: Clone + RangeBounds<K>,
     {
-        let inner = self.as_inner()?;
-        match inner.deref() {
-            InnerRobt::Snapshot { dir, name, .. } => {
-                let mut iters = vec![];
-                for range in ranges.into_iter() {
-                    let snap = Snapshot::<K, V, B>::open(dir, &name.0)?;
-                    let iter: IndexIter<K, V> = Box::new(scans::FilterScans::new(
-                        vec![snap.into_range_scan(util::to_start_end(range))?],
-                        within.clone(),
-                    ));
-
-                    iters.push(iter)
-                }
+        let guard = self.pin()?;
 
-                Ok(iters)
-            }
-            InnerRobt::Build { .. } => err_at!(UnInitialized, msg: format!("Robt.range_scans()")),
+        let mut iters = vec![];
+        for range in ranges.into_iter() {
+            let snap = guard.to_reader()?;
+            let iter: IndexIter<K, V> = Box::new(scans::FilterScans::new(
+                vec![snap.into_range_scan(util::to_start_end(range))?],
+                within.clone(),
+            ));
+            let iter: IndexIter<K, V> = Box::new(PinnedIter {
+                iter,
+                _guard: guard.try_clone()?,
+            });
+            iters.push(iter)
         }
+
+        Ok(iters)
     }
 }
 
@@ -1178,6 +1795,44 @@ pub struct Config {
     pub(crate) value_in_vlog: bool,
     /// Flush queue size. Default: Config::FLUSH_QUEUE_SIZE
     pub(crate) flush_queue_size: usize,
+    /// Number of outstanding writes while flushing index and value-log
+    /// blocks, writes are serialized if ONE. Default: Config::FLUSH_DEPTH
+    pub(crate) flush_depth: usize,
+    /// Optional cipher to encrypt btree blocks and value-log blocks.
+    /// Default: None
+    pub(crate) cipher: Option<Arc<dyn Cipher>>,
+    /// Name of the [OrdSpec] used to sort keys, empty if keys are sorted
+    /// using `K: Ord`. Default: ""
+    pub(crate) ord_spec: String,
+    /// Split value-log into segment files of this size, ZERO to persist
+    /// value-log as a single file. Default: 0
+    pub(crate) vlog_segment: usize,
+    /// Optional compressor for values and deltas persisted in value-log.
+    /// Default: None
+    pub(crate) compressor: Option<Arc<dyn Compressor>>,
+    /// Number of values sampled to train the compression dictionary.
+    /// Default: 0
+    pub(crate) dict_samples: usize,
+    /// Maximum size of the compression dictionary. Default: 0
+    pub(crate) dict_size: usize,
+    /// Block starts and the meta region, in index file, are aligned to
+    /// this boundary. Default: Config::ALIGN
+    pub(crate) align: usize,
+    /// Cap on index file size, for builds that are split into multiple
+    /// parts, refer to [Builder::build_parts]. ZERO for no cap.
+    /// Default: 0
+    pub(crate) max_index_size: usize,
+    /// Minimum average fanout expected for leaf blocks and intermediate
+    /// blocks, a build that falls short is logged as warning. ZERO to
+    /// skip the check. Default: Config::MIN_FANOUT
+    pub(crate) min_fanout: usize,
+    /// Grow block sizes for subsequent builds, like commit and compact
+    /// on [Robt] index, when fanout falls short of `min_fanout`.
+    /// Default: false
+    pub(crate) auto_blocksize: bool,
+    /// Fail the build if consecutive entries, from input iterator,
+    /// share the same key. Default: false
+    pub(crate) strict_unique: bool,
 }
 
 impl Default for Config {
@@ -1200,6 +1855,18 @@ impl Default for Config {
             vlog_file: Default::default(),
             value_in_vlog: false,
             flush_queue_size: Self::FLUSH_QUEUE_SIZE,
+            flush_depth: Self::FLUSH_DEPTH,
+            cipher: None,
+            ord_spec: Default::default(),
+            vlog_segment: 0,
+            compressor: None,
+            dict_samples: 0,
+            dict_size: 0,
+            align: Self::ALIGN,
+            max_index_size: 0,
+            min_fanout: Self::MIN_FANOUT,
+            auto_blocksize: false,
+            strict_unique: false,
         }
     }
 }
@@ -1215,6 +1882,17 @@ impl Config {
     const MARKER_BLOCK_SIZE: usize = 1024 * 4;
     /// Default Flush queue size, channel queue size, holding index blocks.
     const FLUSH_QUEUE_SIZE: usize = 64;
+    /// Default flush depth, blocks are written one after the other.
+    pub const FLUSH_DEPTH: usize = 1;
+    /// Smallest alignment allowed for z-block and m-block sizes.
+    pub const BLOCK_ALIGN: usize = 512;
+    /// Default alignment for block starts and the meta region, 4 * 1024
+    /// bytes.
+    pub const ALIGN: usize = 4 * 1024;
+    /// Maximum size of a value-log segment file.
+    pub const MAX_VLOG_SEGMENT: usize = 1 << vlog::SEGMENT_SHIFT;
+    /// Default minimum fanout for leaf and intermediate blocks.
+    pub const MIN_FANOUT: usize = 4;
 
     /// Configure differt set of block size for leaf-node, intermediate-node.
     pub fn set_blocksize(&mut self, z: usize, v: usize, m: usize) -> Result<&mut Self> {
@@ -1258,6 +1936,216 @@ impl Config {
         self.flush_queue_size = size;
         Ok(self)
     }
+
+    /// Set number of outstanding writes while flushing. With `depth` > 1
+    /// blocks are written at their reserved file position, from as many
+    /// writer threads, to exploit the queue depth of NVMe devices.
+    pub fn set_flush_depth(&mut self, depth: usize) -> Result<&mut Self> {
+        self.flush_depth = depth;
+        Ok(self)
+    }
+
+    /// Cap index file size to `bytes`, [Builder::build_parts] shall roll
+    /// over to a new part once the cap is hit. ZERO to build a single
+    /// index file.
+    pub fn set_max_index_size(&mut self, bytes: usize) -> Result<&mut Self> {
+        self.max_index_size = bytes;
+        Ok(self)
+    }
+
+    /// Verify that leaf blocks and intermediate blocks, on an average,
+    /// hold at least `fanout` entries, refer to [Stats::to_fanout]. Builds
+    /// that fall short are logged as warning. If `auto_blocksize` is
+    /// true, [Robt] index shall grow its block sizes for subsequent
+    /// commits and compactions. ZERO `fanout` disables the check.
+    pub fn set_min_fanout(&mut self, fanout: usize, auto_blocksize: bool) -> Result<&mut Self> {
+        self.min_fanout = fanout;
+        self.auto_blocksize = auto_blocksize;
+        Ok(self)
+    }
+
+    /// Fail the build with `InvalidInput` error, if consecutive entries
+    /// from the input iterator share the same key. Without this, both
+    /// entries are persisted, useful to catch a broken upstream merge
+    /// early. Default is false.
+    pub fn set_strict_unique(&mut self, ok: bool) -> Result<&mut Self> {
+        self.strict_unique = ok;
+        Ok(self)
+    }
+
+    /// Encrypt index blocks and value-log blocks using `cipher`. Cipher
+    /// shall be [registered][register_cipher] before opening the index.
+    /// Incremental builds append to the older value-log file, hence
+    /// shall use the same cipher as the older build.
+    pub fn set_cipher(&mut self, cipher: Option<Arc<dyn Cipher>>) -> Result<&mut Self> {
+        self.cipher = cipher;
+        Ok(self)
+    }
+
+    /// Split value-log into segment files of `size` bytes, smaller
+    /// files are easier to backup and to garbage collect. Segment files
+    /// are named `{name}-{segment}.vlog`, while the first segment keeps
+    /// the value-log file name. A segment can exceed `size` only if a
+    /// single leaf-node's values exceed `size`. Pass `size` as ZERO to
+    /// persist value-log as a single file.
+    pub fn set_vlog_segment(&mut self, size: usize) -> Result<&mut Self> {
+        self.vlog_segment = size;
+        Ok(self)
+    }
+
+    /// Compress values and deltas persisted in value-log using
+    /// `compressor`. Compression dictionary, of upto `dict_size` bytes, is
+    /// trained from the first `samples` values, values and deltas
+    /// flushed before the dictionary is trained are left uncompressed.
+    /// Compressor shall be [registered][register_compressor] before
+    /// opening the index. Incremental builds reuse the dictionary of the
+    /// older snapshot, as they refer to its value-log.
+    pub fn set_compression(
+        &mut self,
+        compressor: Option<Arc<dyn Compressor>>,
+        samples: usize,
+        dict_size: usize,
+    ) -> Result<&mut Self> {
+        self.compressor = compressor;
+        self.dict_samples = samples;
+        self.dict_size = dict_size;
+        Ok(self)
+    }
+
+    /// Align block starts and the meta region, in index file, to `align`
+    /// bytes, so that readers using direct I/O, or disks with 4K native
+    /// sectors, don't need bounce buffers. `align` shall be a power of
+    /// two, no less than [BLOCK_ALIGN][Config::BLOCK_ALIGN], and z-block
+    /// and m-block sizes shall be a multiple of `align`.
+    pub fn set_align(&mut self, align: usize) -> Result<&mut Self> {
+        self.align = align;
+        Ok(self)
+    }
+
+    /// Record the name of the [OrdSpec], `S::NAME`, when keys are of
+    /// type [Ordered]. Index opened via [RobtFactory] shall fail if the
+    /// ordering does not match with the persisted ordering.
+    pub fn set_ord_spec(&mut self, name: &str) -> Result<&mut Self> {
+        self.ord_spec = name.to_string();
+        Ok(self)
+    }
+
+    /// Validate this configuration, called by [Builder] constructors
+    /// before creating any file. Return `InvalidInput` error describing
+    /// the offending parameter.
+    pub fn validate(&self) -> Result<()> {
+        let align = self.align;
+        if align < Self::BLOCK_ALIGN || !align.is_power_of_two() {
+            let msg = format!(
+                "align {} shall be a power of two, no less than {}",
+                align,
+                Self::BLOCK_ALIGN
+            );
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        let blocks = [
+            ("z_blocksize", self.z_blocksize),
+            ("m_blocksize", self.m_blocksize),
+        ];
+        for (param, size) in blocks.iter() {
+            if *size < align || (*size % align) != 0 {
+                let msg = format!(
+                    "{} {} shall be a non-zero multiple of {}",
+                    param, size, align
+                );
+                err_at!(InvalidInput, msg: msg)?;
+            }
+        }
+        if self.v_blocksize == 0 {
+            err_at!(InvalidInput, msg: format!("v_blocksize is ZERO"))?;
+        }
+        if self.flush_depth == 0 {
+            err_at!(InvalidInput, msg: format!("flush_depth is ZERO"))?;
+        }
+        let cap = self.max_index_size;
+        if cap > 0 && cap < self.z_blocksize {
+            let msg = format!("max_index_size {} less than z_blocksize", cap);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        let vseg = self.vlog_segment;
+        let min_vseg: usize = convert_at!(VLOG_HEADER_SIZE)?;
+        if vseg > 0 && (vseg <= min_vseg || vseg > Self::MAX_VLOG_SEGMENT) {
+            let msg = format!("vlog_segment {} out of range", vseg);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        Ok(())
+    }
+
+    fn to_cipher_key_id(&self) -> String {
+        self.cipher
+            .as_ref()
+            .map_or(Default::default(), |cipher| cipher.to_key_id())
+    }
+
+    fn to_compressor_name(&self) -> String {
+        self.compressor
+            .as_ref()
+            .map_or(Default::default(), |compressor| compressor.to_name())
+    }
+
+    fn to_file_cipher(&self, uuid: &str) -> Result<Option<FileCipher>> {
+        match &self.cipher {
+            Some(cipher) => Ok(Some(FileCipher::new(Arc::clone(cipher), uuid)?)),
+            None => Ok(None),
+        }
+    }
+
+    // verify fanout of a build against min_fanout, return the (z, m)
+    // block sizes that would meet min_fanout, if fanout is too low.
+    fn verify_fanout(&self, stats: &Stats) -> Option<(usize, usize)> {
+        if self.min_fanout == 0 {
+            return None;
+        }
+
+        let min = self.min_fanout as f64;
+        let grow = |blocksize: usize, fanout: f64| -> usize {
+            let want = ((blocksize as f64) * min / fanout).ceil() as usize;
+            let want = cmp::min(want.next_power_of_two(), Advice::MAX_BLOCKSIZE);
+            cmp::max(want, blocksize)
+        };
+        let (zf, mf) = stats.to_fanout();
+        let (z, m) = match (zf > 0.0 && zf < min, mf > 0.0 && mf < min) {
+            (false, false) => return None,
+            (true, false) => (grow(stats.z_blocksize, zf), stats.m_blocksize),
+            (false, true) => (stats.z_blocksize, grow(stats.m_blocksize, mf)),
+            (true, true) => (grow(stats.z_blocksize, zf), grow(stats.m_blocksize, mf)),
+        };
+        warn!(
+            target: "robt  ",
+            "{}, low fanout z:{:.1} m:{:.1}, expected {}, try blocksize z:{} m:{}",
+            stats.name, zf, mf, self.min_fanout, z, m
+        );
+        Some((z, m))
+    }
+
+    // configuration for the next build, persisted configuration is
+    // picked from the latest snapshot's `config`, while fanout and
+    // unique settings are carried over from this configuration.
+    fn to_next_config(&self, mut config: Config, stats: &Stats) -> Config {
+        config.min_fanout = self.min_fanout;
+        config.auto_blocksize = self.auto_blocksize;
+        config.strict_unique = self.strict_unique;
+        if !self.auto_blocksize {
+            return config;
+        }
+        if let Some((z, m)) = self.verify_fanout(stats) {
+            if (z, m) != (config.z_blocksize, config.m_blocksize) {
+                info!(
+                    target: "robt  ",
+                    "{}, blocksize z:{}->{} m:{}->{} for subsequent builds",
+                    stats.name, config.z_blocksize, z, config.m_blocksize, m
+                );
+                config.z_blocksize = z;
+                config.m_blocksize = m;
+            }
+        }
+        config
+    }
 }
 
 impl fmt::Display for Config {
@@ -1270,16 +2158,39 @@ impl fmt::Display for Config {
         let (z, m, v) = (self.z_blocksize, self.m_blocksize, self.v_blocksize);
         let dok = self.delta_ok;
         let fqs = self.flush_queue_size;
+        let key_id = self.to_cipher_key_id();
 
         write!(
             f,
             concat!(
                 "robt.name = {}\n",
-                "robt.config.blocksize = {{ z={}, m={}, v={} }}\n",
+                "robt.config.blocksize = {{ z={}, m={}, v={} align={} }}\n",
                 "robt.config = {{ delta_ok={}, value_in_vlog={} vlog_file={} }}\n",
-                "robt.config = {{ flush_queue_size={} }}",
+                "robt.config = {{ flush_queue_size={}, flush_depth={}, cipher={:?} }}\n",
+                "robt.config = {{ ord_spec={:?} vlog_segment={} max_index_size={} }}\n",
+                "robt.config = {{ compressor={:?} dict_samples={} dict_size={} }}\n",
+                "robt.config = {{ min_fanout={} auto_blocksize={} strict_unique={} }}",
             ),
-            self.name, z, m, v, dok, self.value_in_vlog, vlog_file, fqs,
+            self.name,
+            z,
+            m,
+            v,
+            self.align,
+            dok,
+            self.value_in_vlog,
+            vlog_file,
+            fqs,
+            self.flush_depth,
+            key_id,
+            self.ord_spec,
+            self.vlog_segment,
+            self.max_index_size,
+            self.to_compressor_name(),
+            self.dict_samples,
+            self.dict_size,
+            self.min_fanout,
+            self.auto_blocksize,
+            self.strict_unique,
         )
     }
 }
@@ -1297,6 +2208,16 @@ impl ToJson for Config {
                 self.vlog_file.as_ref().map_or(null, |f| f.clone()),
             ),
             format!(r#""flush_queue_size": {}"#, self.flush_queue_size,),
+            format!(r#""flush_depth": {}"#, self.flush_depth),
+            format!(r#""cipher": "{}""#, self.to_cipher_key_id()),
+            format!(r#""ord_spec": "{}""#, self.ord_spec),
+            format!(r#""vlog_segment": {}"#, self.vlog_segment),
+            format!(r#""compressor": "{}""#, self.to_compressor_name()),
+            format!(r#""align": {}"#, self.align),
+            format!(r#""max_index_size": {}"#, self.max_index_size),
+            format!(r#""min_fanout": {}"#, self.min_fanout),
+            format!(r#""auto_blocksize": {}"#, self.auto_blocksize),
+            format!(r#""strict_unique": {}"#, self.strict_unique),
         ];
         format!(
             r#"{{ "robt": {{ "name": "{}", "config": {{ {} }} }}"#,
@@ -1319,6 +2240,28 @@ impl From<Stats> for Config {
             vlog_file: stats.vlog_file,
             value_in_vlog: stats.value_in_vlog,
             flush_queue_size: stats.flush_queue_size,
+            flush_depth: Self::FLUSH_DEPTH,
+            cipher: match stats.cipher_key_id.as_str() {
+                "" => None,
+                key_id => robt_cipher::get_cipher(key_id).ok(),
+            },
+            ord_spec: stats.ord_spec,
+            vlog_segment: stats.vlog_segment,
+            compressor: match stats.compressor.as_str() {
+                "" => None,
+                name => robt_compress::get_compressor(name).ok(),
+            },
+            dict_samples: 0,
+            dict_size: 0,
+            // older snapshots only aligned their block sizes.
+            align: match stats.align {
+                0 => Self::BLOCK_ALIGN,
+                align => align,
+            },
+            max_index_size: 0,
+            min_fanout: Self::MIN_FANOUT,
+            auto_blocksize: false,
+            strict_unique: false,
         }
     }
 }
@@ -1332,6 +2275,13 @@ impl Config {
         index_path.into_os_string()
     }
 
+    // checkpoint file is kept alongside the index file, refer to
+    // Builder::set_checkpoint.
+    fn stitch_checkpoint_file(index_file: &ffi::OsStr) -> ffi::OsString {
+        let ipath = path::Path::new(index_file).with_extension("ckpt");
+        ipath.into_os_string()
+    }
+
     fn stitch_vlog_file(dir: &ffi::OsStr, name: &str) -> ffi::OsString {
         let vlog_file: VlogFileName = Name(name.to_string()).into();
 
@@ -1340,6 +2290,33 @@ impl Config {
         vlog_path.into_os_string()
     }
 
+    // first segment is the value-log file itself, subsequent segments
+    // are suffixed with their segment number.
+    fn stitch_vlog_segment(vlog_file: &ffi::OsStr, segment: usize) -> ffi::OsString {
+        match segment {
+            0 => vlog_file.to_os_string(),
+            _ => {
+                let vpath = path::Path::new(vlog_file).with_extension("");
+                let mut segment_file = vpath.into_os_string();
+                segment_file.push(format!("-{}.vlog", segment));
+                segment_file
+            }
+        }
+    }
+
+    // list value-log file and its segment files, in segment order.
+    fn to_vlog_segments(vlog_file: &ffi::OsStr) -> Vec<ffi::OsString> {
+        let mut files = vec![vlog_file.to_os_string()];
+        loop {
+            let file = Config::stitch_vlog_segment(vlog_file, files.len());
+            if path::Path::new(&file).exists() {
+                files.push(file)
+            } else {
+                break files;
+            }
+        }
+    }
+
     fn compute_root_block(n: usize) -> usize {
         if (n % Config::MARKER_BLOCK_SIZE) == 0 {
             n
@@ -1347,6 +2324,295 @@ impl Config {
             ((n / Config::MARKER_BLOCK_SIZE) + 1) * Config::MARKER_BLOCK_SIZE
         }
     }
+
+    // meta region is padded to a multiple of `align`, never smaller than
+    // the marker block, alignment of ZERO is treated as marker block.
+    fn compute_meta_block(n: usize, align: usize) -> usize {
+        let align = cmp::max(align, Config::MARKER_BLOCK_SIZE);
+        match n % align {
+            0 => n,
+            _ => ((n / align) + 1) * align,
+        }
+    }
+}
+
+/// Identity of a [Robt] snapshot, refer to [Snapshot::to_lineage].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lineage {
+    /// Unique identifier for the build that created the snapshot.
+    pub build_uuid: String,
+    /// Build-uuid of snapshots that were sourced to create the snapshot.
+    pub sources: Vec<String>,
+}
+
+/// Meta items of a [Robt] snapshot, refer to [Snapshot::open_meta].
+#[derive(Clone, Default)]
+pub struct SnapshotMeta {
+    /// Directory where the snapshot is located.
+    pub dir: ffi::OsString,
+    /// Name of the snapshot.
+    pub name: String,
+    /// Btree statistics.
+    pub stats: Stats,
+    /// Application metadata.
+    pub app_meta: Vec<u8>,
+    /// File-position where the root block starts, None if the snapshot
+    /// is empty.
+    pub root: Option<u64>,
+    /// Size of the persisted bitmap, ZERO if built without bitmap.
+    pub bitmap_len: usize,
+    /// Size of the meta block at the tip of the index file.
+    pub meta_block_bytes: usize,
+}
+
+impl SnapshotMeta {
+    /// Return the index file followed by value-log file and its segment
+    /// files, if any.
+    pub fn to_files(&self) -> Result<Vec<ffi::OsString>> {
+        let mut files = vec![Config::stitch_index_file(&self.dir, &self.name)];
+        if let Some(vfile) = &self.stats.vlog_file {
+            let mut vpath = path::PathBuf::new();
+            vpath.push(path::Path::new(&self.dir));
+            vpath.push(match path::Path::new(vfile).file_name() {
+                Some(vfile) => Ok(vfile),
+                None => err_at!(InvalidFile, msg: format!("{:?}", vfile)),
+            }?);
+            files.extend(Config::to_vlog_segments(vpath.as_os_str()));
+        }
+        Ok(files)
+    }
+}
+
+/// Runtime statistics for [Snapshot], refer to [Snapshot::to_runtime_stats].
+///
+/// Unlike [Stats], that is computed once while building the index,
+/// these counters are accumulated by read operations on a snapshot
+/// handle and can be used to measure read amplification.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuntimeStats {
+    /// Number of get operations.
+    pub n_gets: usize,
+    /// Number of get operations answered by the bitmap, without
+    /// reading the index-file.
+    pub n_bitmap_skips: usize,
+    /// Number of get operations that passed the bitmap, but did not
+    /// find the key.
+    pub n_bitmap_false_positives: usize,
+    /// Number of blocks read by get operations, for each level of the
+    /// btree, starting from root. Last level is z-block.
+    pub get_levels: Vec<usize>,
+    /// Number of blocks read from index-file, by all operations.
+    pub n_block_reads: usize,
+    /// Number of values and deltas read from value-log file.
+    pub n_vlog_reads: usize,
+}
+
+impl RuntimeStats {
+    /// Return the average number of index blocks read for every get
+    /// operation that was not answered by the bitmap.
+    pub fn to_blocks_per_get(&self) -> f64 {
+        let n_blocks: usize = self.get_levels.iter().sum();
+        match self.n_gets - self.n_bitmap_skips {
+            0 => 0.0,
+            n => (n_blocks as f64) / (n as f64),
+        }
+    }
+
+    fn incr_level(&mut self, level: usize) {
+        if self.get_levels.len() <= level {
+            self.get_levels.resize(level + 1, 0);
+        }
+        self.get_levels[level] += 1;
+    }
+}
+
+/// Result of validating a bitmap against the keys in a [Snapshot],
+/// refer to [Snapshot::validate_bitmap].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitmapCheck {
+    /// Number of keys scanned, all of them are present in the bitmap.
+    pub n_keys: u64,
+    /// Number of lookups for elements that are never indexed.
+    pub n_probes: u64,
+    /// Number of probes that the bitmap claimed to be present.
+    pub n_false_positives: u64,
+}
+
+impl BitmapCheck {
+    /// Return the observed false positive rate, between 0.0 and 1.0.
+    pub fn to_false_positive_rate(&self) -> f64 {
+        match self.n_probes {
+            0 => 0.0,
+            n => (self.n_false_positives as f64) / (n as f64),
+        }
+    }
+}
+
+/// Range tombstone, deletes all entries within a key-range, in bulk.
+///
+/// Range tombstones are persisted as a meta-item in the index-file,
+/// refer to [Builder::delete_range]. An entry whose key falls within
+/// `low` and `high`, and whose seqno is less than or equal to the
+/// tombstone's seqno, is treated as deleted by reads, and purged by
+/// compaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeTombstone<K> {
+    /// Lower bound of deleted keys.
+    pub low: Bound<K>,
+    /// Upper bound of deleted keys.
+    pub high: Bound<K>,
+    /// Entries modified at or before this seqno are deleted.
+    pub seqno: u64,
+}
+
+impl<K> RangeTombstone<K>
+where
+    K: Clone + Ord + Serialize,
+{
+    /// Return whether `entry` is deleted by this tombstone.
+    pub fn covers<V>(&self, entry: &Entry<K, V>) -> bool
+    where
+        V: Clone + Diff,
+    {
+        self.contains_key(entry.as_key()) && entry.to_seqno() <= self.seqno
+    }
+
+    /// Return whether `key` falls within this tombstone's key-range.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let low_ok = match &self.low {
+            Bound::Included(low) => key.ge(low.borrow()),
+            Bound::Excluded(low) => key.gt(low.borrow()),
+            Bound::Unbounded => true,
+        };
+        let high_ok = match &self.high {
+            Bound::Included(high) => key.le(high.borrow()),
+            Bound::Excluded(high) => key.lt(high.borrow()),
+            Bound::Unbounded => true,
+        };
+        low_ok && high_ok
+    }
+
+    // same semantics as purging a deleted entry, refer to Entry::purge.
+    fn purge(self, cutoff: Cutoff) -> Option<Self> {
+        let n = self.seqno;
+        match cutoff {
+            Cutoff::Mono => None,
+            Cutoff::Lsm(cutoff) | Cutoff::Tombstone(cutoff) => match cutoff {
+                Bound::Included(cutoff) if n <= cutoff => None,
+                Bound::Excluded(cutoff) if n < cutoff => None,
+                Bound::Unbounded => None,
+                _ => Some(self),
+            },
+        }
+    }
+
+    // +--------------------------------+-------+--------+
+    // |             seqno              | lkind | hkind  |
+    // +--------------------------------+-------+--------+
+    // | low-key-len (u32) | low-key  ... (if bounded)   |
+    // | high-key-len (u32) | high-key ... (if bounded)  |
+    // +-------------------------------------------------+
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let m = buf.len();
+        buf.extend_from_slice(&self.seqno.to_be_bytes());
+        buf.push(Self::to_bound_kind(&self.low));
+        buf.push(Self::to_bound_kind(&self.high));
+        for bound in [&self.low, &self.high].iter() {
+            match bound {
+                Bound::Included(key) | Bound::Excluded(key) => {
+                    let n = buf.len();
+                    buf.extend_from_slice(&[0_u8; 4]);
+                    let klen: u32 = convert_at!(key.encode(buf)?)?;
+                    buf[n..n + 4].copy_from_slice(&klen.to_be_bytes());
+                }
+                Bound::Unbounded => (),
+            }
+        }
+        Ok(buf.len() - m)
+    }
+
+    fn decode(buf: &[u8]) -> Result<(Self, usize)>
+    where
+        K: Default,
+    {
+        check_remaining!(buf, 10, "robt-range-tombstone-hdr")?;
+        let seqno = u64::from_be_bytes(array_at!(buf[..8])?);
+        let mut n = 10;
+        let mut bounds = vec![];
+        for kind in buf[8..10].to_vec().into_iter() {
+            let bound = match kind {
+                0 => Bound::Unbounded,
+                1 | 2 => {
+                    check_remaining!(buf, n + 4, "robt-range-tombstone-klen")?;
+                    let klen: usize = {
+                        let klen = u32::from_be_bytes(array_at!(buf[n..n + 4])?);
+                        convert_at!(klen)?
+                    };
+                    n += 4;
+                    check_remaining!(buf, n + klen, "robt-range-tombstone-key")?;
+                    let mut key: K = Default::default();
+                    key.decode(&buf[n..n + klen])?;
+                    n += klen;
+                    if kind == 1 {
+                        Bound::Included(key)
+                    } else {
+                        Bound::Excluded(key)
+                    }
+                }
+                kind => err_at!(DecodeFail, msg: format!("bound kind {}", kind))?,
+            };
+            bounds.push(bound);
+        }
+        let high = bounds.pop().unwrap_or(Bound::Unbounded);
+        let low = bounds.pop().unwrap_or(Bound::Unbounded);
+
+        Ok((RangeTombstone { low, high, seqno }, n))
+    }
+
+    fn to_bound_kind(bound: &Bound<K>) -> u8 {
+        match bound {
+            Bound::Unbounded => 0,
+            Bound::Included(_) => 1,
+            Bound::Excluded(_) => 2,
+        }
+    }
+}
+
+fn encode_range_tombstones<K>(tombs: &[RangeTombstone<K>]) -> Result<Vec<u8>>
+where
+    K: Clone + Ord + Serialize,
+{
+    let mut buf = vec![];
+    if tombs.len() > 0 {
+        let n: u32 = convert_at!(tombs.len())?;
+        buf.extend_from_slice(&n.to_be_bytes());
+        for tomb in tombs.iter() {
+            tomb.encode(&mut buf)?;
+        }
+    }
+    Ok(buf)
+}
+
+fn decode_range_tombstones<K>(buf: &[u8]) -> Result<Vec<RangeTombstone<K>>>
+where
+    K: Default + Clone + Ord + Serialize,
+{
+    let mut tombs = vec![];
+    if buf.len() > 0 {
+        check_remaining!(buf, 4, "robt-range-tombstones")?;
+        let n = u32::from_be_bytes(array_at!(buf[..4])?);
+        let mut m = 4;
+        for _ in 0..n {
+            let (tomb, k) = RangeTombstone::decode(&buf[m..])?;
+            tombs.push(tomb);
+            m += k;
+        }
+    }
+    Ok(tombs)
 }
 
 /// Enumeration of meta items stored in [Robt] index.
@@ -1369,12 +2635,18 @@ pub enum MetaItem {
     Bitmap(Vec<u8>),
     /// File-position where the root block for the Btree starts.
     Root(u64),
+    /// Encoded list of [RangeTombstone], optional while writing.
+    RangeTombstones(Vec<u8>),
+    /// Dictionary used to compress value-log, empty if value-log is not
+    /// compressed, optional while writing. Refer to [Compressor].
+    Dictionary(Vec<u8>),
 }
 
 // returns bytes appended to file.
 pub(crate) fn write_meta_items(
     file: ffi::OsString,
     items: Vec<MetaItem>, // list of meta items, starting from Marker
+    align: usize,
 ) -> Result<u64> {
     let mut fd = {
         let p = path::Path::new(&file);
@@ -1382,8 +2654,9 @@ pub(crate) fn write_meta_items(
         err_at!(IoError, opts.append(true).open(p))?
     };
 
-    let (mut hdr, mut block) = (vec![], vec![]);
-    hdr.resize(40, 0);
+    let (mut hdr, mut block, mut tombs, mut dict) = (vec![], vec![], vec![], vec![]);
+    hdr.resize(HDR_FIXED, 0);
+    let mut hdr_ext = [0_u64; 2];
 
     // (fpos, bitmap-len, app-meta-len, stats-len)
     let mut debug_args: (u64, u64, u64, u64) = Default::default();
@@ -1413,9 +2686,18 @@ pub(crate) fn write_meta_items(
             }
             (4, MetaItem::Marker(data)) => {
                 let ln: u64 = convert_at!(data.len())?;
-                hdr[32..40].copy_from_slice(&ln.to_be_bytes());
+                let word = (HDR_VERSION << 48) | ln;
+                hdr[32..40].copy_from_slice(&word.to_be_bytes());
                 block.extend_from_slice(&data);
             }
+            (5, MetaItem::RangeTombstones(data)) => {
+                hdr_ext[0] = convert_at!(data.len())?;
+                tombs = data;
+            }
+            (6, MetaItem::Dictionary(data)) => {
+                hdr_ext[1] = convert_at!(data.len())?;
+                dict = data;
+            }
             (i, m) => return err_at!(Fatal, msg: format!("meta-item {},{}", i, m)),
         }
     }
@@ -1424,10 +2706,22 @@ pub(crate) fn write_meta_items(
         "{:?}, writing root:{} bitmap_len:{} meta_len:{}  stats_len:{}",
         file, debug_args.0, debug_args.1, debug_args.2, debug_args.3,
     );
+    // header extensions are placed ahead of the fixed header, in the
+    // order they were added to the header.
+    for ln in hdr_ext.iter() {
+        let mut ext = ln.to_be_bytes().to_vec();
+        ext.extend_from_slice(&hdr);
+        hdr = ext;
+    }
     block.extend_from_slice(&hdr[..]);
+    // range tombstones are placed ahead of the bitmap, and dictionary
+    // ahead of range tombstones.
+    tombs.extend_from_slice(&block);
+    dict.extend_from_slice(&tombs);
+    let mut block = dict;
 
     // flush / append into file.
-    let n = Config::compute_root_block(block.len());
+    let n = Config::compute_meta_block(block.len(), align);
     let (shift, m) = (n - block.len(), block.len());
     block.resize(n, 0);
     block.copy_within(0..m, shift);
@@ -1451,20 +2745,34 @@ pub fn read_meta_items(
 
     let index_file = Config::stitch_index_file(dir, name);
     let m = err_at!(IoError, fs::metadata(&index_file))?.len();
+    let n_fixed: u64 = convert_at!(HDR_FIXED)?;
+    if m < n_fixed {
+        let msg = format!("{:?} file size {} < header", index_file, m);
+        return err_at!(CorruptedMeta, msg: msg);
+    }
     let mut fd = util::open_file_r(index_file.as_ref())?;
 
-    // read header
-    let hdr = read_file!(&mut fd, m - 40, 40, "read root-block header")?;
-    let root = u64::from_be_bytes(array_at!(hdr[..8])?);
-    let n_bmap: usize = convert_at!(u64::from_be_bytes(array_at!(hdr[8..16])?))?;
-    let n_md: usize = convert_at!(u64::from_be_bytes(array_at!(hdr[16..24])?))?;
-    let n_stats: usize = convert_at!(u64::from_be_bytes(array_at!(hdr[24..32])?))?;
-    let n_marker: usize = convert_at!(u64::from_be_bytes(array_at!(hdr[32..40])?))?;
-    // read block
-    let meta_block_bytes: u64 = {
-        let n_total = n_bmap + n_md + n_stats + n_marker + 40;
-        convert_at!(Config::compute_root_block(n_total))?
+    // read header, fixed part of the header tells its version.
+    let hdr = read_file!(&mut fd, m - n_fixed, n_fixed, "read root-block header")?;
+    let n_hdr = meta_header_len(&hdr)?;
+    let hdr = {
+        let n_hdr: u64 = convert_at!(n_hdr)?;
+        if m < n_hdr {
+            let msg = format!("{:?} file size {} < header {}", index_file, m, n_hdr);
+            return err_at!(CorruptedMeta, msg: msg);
+        }
+        read_file!(&mut fd, m - n_hdr, n_hdr, "read root-block header")?
     };
+    let (root, lens) = decode_meta_header(&hdr, m)?;
+    let [n_bmap, n_md, n_stats, n_marker, n_tombs, n_dict] = lens;
+    // read block, meta items are packed towards the tip, hence fit
+    // within the marker block alignment.
+    let n_total = n_dict + n_tombs + n_bmap + n_md + n_stats + n_marker + n_hdr;
+    let meta_block_bytes: u64 = convert_at!(Config::compute_root_block(n_total))?;
+    if meta_block_bytes > m {
+        let msg = format!("meta-block {} > file size {}", meta_block_bytes, m);
+        return err_at!(CorruptedMeta, msg: msg);
+    }
     let block: Vec<u8> = read_file!(
         &mut fd,
         m - meta_block_bytes,
@@ -1477,7 +2785,7 @@ pub fn read_meta_items(
     let mut meta_items: Vec<MetaItem> = vec![];
     let z = {
         let z: usize = convert_at!(meta_block_bytes)?;
-        z - 40
+        z - n_hdr
     };
 
     let (x, y) = (z - n_marker, z);
@@ -1487,7 +2795,7 @@ pub fn read_meta_items(
     }
 
     let (x, y) = (z - n_marker - n_stats, z - n_marker);
-    let stats = err_at!(InvalidInput, from_utf8(&block[x..y]))?.to_string();
+    let stats = err_at!(CorruptedMeta, from_utf8(&block[x..y]))?.to_string();
 
     let (x, y) = (z - n_marker - n_stats - n_md, z - n_marker - n_stats);
     let app_data = block[x..y].to_vec();
@@ -1498,27 +2806,184 @@ pub fn read_meta_items(
     );
     let bitmap = block[x..y].to_vec();
 
+    let (x, y) = (
+        z - n_marker - n_stats - n_md - n_bmap - n_tombs,
+        z - n_marker - n_stats - n_md - n_bmap,
+    );
+    let tombs = block[x..y].to_vec();
+
+    let (x, y) = (
+        z - n_marker - n_stats - n_md - n_bmap - n_tombs - n_dict,
+        z - n_marker - n_stats - n_md - n_bmap - n_tombs,
+    );
+    let dict = block[x..y].to_vec();
+
     meta_items.push(MetaItem::Root(root));
     meta_items.push(MetaItem::Bitmap(bitmap));
     meta_items.push(MetaItem::AppMetadata(app_data));
     meta_items.push(MetaItem::Stats(stats.clone()));
     meta_items.push(MetaItem::Marker(marker.clone()));
+    meta_items.push(MetaItem::RangeTombstones(tombs));
+    meta_items.push(MetaItem::Dictionary(dict));
 
     // validate and return
-    let stats: Stats = stats.parse()?;
+    let stats: Stats = match stats.parse() {
+        Ok(stats) => stats,
+        Err(err) => err_at!(CorruptedMeta, msg: format!("stats {:?}", err))?,
+    };
+    let meta_block_bytes: u64 = convert_at!(Config::compute_meta_block(n_total, stats.align))?;
+    if meta_block_bytes > m {
+        let msg = format!("meta-block {} > file size {}", meta_block_bytes, m);
+        return err_at!(CorruptedMeta, msg: msg);
+    }
     if root == std::u64::MAX {
         Ok((meta_items, convert_at!(meta_block_bytes)?))
     } else {
         let at: u64 = convert_at!(stats.m_blocksize)?;
-        let at = m - meta_block_bytes - at;
-        if at == root {
-            Ok((meta_items, convert_at!(meta_block_bytes)?))
-        } else {
-            err_at!(InvalidFile, msg: format!("root:{}, found:{}", at, root))
+        match (m - meta_block_bytes).checked_sub(at) {
+            Some(at) if at == root => Ok((meta_items, convert_at!(meta_block_bytes)?)),
+            Some(at) => err_at!(InvalidFile, msg: format!("root:{}, found:{}", at, root)),
+            None => {
+                let msg = format!("m_blocksize {} exceeds file size {}", at, m);
+                err_at!(CorruptedMeta, msg: msg)
+            }
         }
     }
 }
 
+// Version of the header written at the tip of the index file, refer
+// to module documentation.
+const HDR_VERSION: u64 = 2;
+// Length of the version 0 header, later versions extend it backwards.
+const HDR_FIXED: usize = 40;
+// Marker length is held in the lower 48 bits of its word.
+const HDR_MARKER_MASK: u64 = 0xFFFF_FFFF_FFFF;
+
+// Return the full length of the header, `hdr` shall end with the fixed
+// part of the header.
+fn meta_header_len(hdr: &[u8]) -> Result<usize> {
+    check_remaining!(hdr, HDR_FIXED, "robt meta header")?;
+
+    let n = hdr.len();
+    let version = u64::from_be_bytes(array_at!(hdr[n - 8..])?) >> 48;
+    match version {
+        0 => Ok(HDR_FIXED),
+        1 => Ok(HDR_FIXED + 8),
+        2 => Ok(HDR_FIXED + 16),
+        _ => err_at!(InvalidFile, msg: format!("robt header version {}", version)),
+    }
+}
+
+// Decode the header at the tip of the index file, validating each
+// length against the file-size `m` before any of them are used for
+// allocation. Returns the root fpos, and the lengths of bitmap,
+// app-metadata, stats, marker, range-tombstones and dictionary, in that
+// order. Lengths that are missing in older header versions are ZERO.
+fn decode_meta_header(hdr: &[u8], m: u64) -> Result<(u64, [usize; 6])> {
+    let n_hdr = meta_header_len(hdr)?;
+    if hdr.len() != n_hdr {
+        let msg = format!("header len {} != {}", hdr.len(), n_hdr);
+        return err_at!(CorruptedMeta, msg: msg);
+    }
+
+    // fixed part of the header, followed by extensions, in the order
+    // they were added to the header.
+    let (ext, fixed) = hdr.split_at(n_hdr - HDR_FIXED);
+    let root = u64::from_be_bytes(array_at!(fixed[..8])?);
+    let mut words = vec![];
+    for i in 1..5 {
+        words.push(u64::from_be_bytes(array_at!(fixed[(i * 8)..(i * 8) + 8])?));
+    }
+    words[3] &= HDR_MARKER_MASK;
+    for i in (0..(ext.len() / 8)).rev() {
+        words.push(u64::from_be_bytes(array_at!(ext[(i * 8)..(i * 8) + 8])?));
+    }
+
+    let mut lens = [0_usize; 6];
+    let mut n_total: u64 = convert_at!(n_hdr)?;
+    for (i, (len, n)) in lens.iter_mut().zip(words.into_iter()).enumerate() {
+        n_total = match n_total.checked_add(n) {
+            Some(n_total) if n_total <= m => n_total,
+            _ => {
+                let msg = format!("meta-item-{} len {} overflows file {}", i + 1, n, m);
+                err_at!(CorruptedMeta, msg: msg)?
+            }
+        };
+        *len = convert_at!(n)?;
+    }
+
+    let n_marker = lens[3];
+    if n_marker != ROOT_MARKER.len() {
+        let msg = format!("marker len {} != {}", n_marker, ROOT_MARKER.len());
+        return err_at!(CorruptedMeta, msg: msg);
+    }
+    if lens[2] == 0 {
+        return err_at!(CorruptedMeta, msg: "missing stats");
+    }
+
+    Ok((root, lens))
+}
+
+// every build shall carry a well formed uuid, and shall never source
+// from itself.
+fn verify_lineage(dir: &ffi::OsStr, name: &str, stats: &Stats) -> Result<()> {
+    let uuid = &stats.build_uuid;
+    let ok = uuid.len() == 32 && uuid.chars().all(|ch| ch.is_ascii_hexdigit());
+    if !ok {
+        let msg = format!("{:?}/{}, build_uuid {:?}", dir, name, uuid);
+        err_at!(InvalidFile, msg: msg)
+    } else if stats.lineage.contains(uuid) {
+        let msg = format!("{:?}/{}, self lineage {}", dir, name, uuid);
+        err_at!(InvalidFile, msg: msg)
+    } else {
+        Ok(())
+    }
+}
+
+/// Size of the header at the start of value-log file. Header is
+/// made up of `b"rdms-robt-vlog"` followed by 32 byte vlog-uuid and
+/// padded with ZEROs.
+pub(crate) const VLOG_HEADER_SIZE: u64 = 64;
+const VLOG_MAGIC: &[u8; 14] = b"rdms-robt-vlog";
+
+fn vlog_header(vlog_uuid: &str) -> Result<Vec<u8>> {
+    let mut hdr = VLOG_MAGIC.to_vec();
+    hdr.extend_from_slice(vlog_uuid.as_bytes());
+    hdr.resize(convert_at!(VLOG_HEADER_SIZE)?, 0);
+    Ok(hdr)
+}
+
+// Place a value-log blob of `n` bytes that is due at `fpos`. Blob is
+// moved to the start of next segment, if it shall cross the segment
+// size. A blob larger than the segment size is placed as the only blob
+// in its segment.
+pub(crate) fn vlog_place(fpos: u64, n: u64, vlog_segment: usize) -> Result<u64> {
+    let (segment, offset) = vlog::to_segment(fpos)?;
+    let size: u64 = convert_at!(vlog_segment)?;
+    if size > 0 && n > 0 && offset > VLOG_HEADER_SIZE && (offset + n) > size {
+        vlog::from_segment(segment + 1, VLOG_HEADER_SIZE)
+    } else {
+        Ok(fpos)
+    }
+}
+
+fn read_vlog_uuid(fd: &mut fs::File, vlog_file: &ffi::OsStr) -> Result<String> {
+    let n = err_at!(IoError, fd.metadata())?.len();
+    if n < VLOG_HEADER_SIZE {
+        let msg = format!("{:?}, vlog header missing, size {}", vlog_file, n);
+        return err_at!(InvalidFile, msg: msg);
+    }
+    let hdr = read_file!(fd, 0, VLOG_HEADER_SIZE, "read vlog header")?;
+    if &hdr[..14] != &VLOG_MAGIC[..] {
+        let msg = format!("{:?}, vlog magic {:?}", vlog_file, &hdr[..14]);
+        return err_at!(InvalidFile, msg: msg);
+    }
+    match std::str::from_utf8(&hdr[14..46]) {
+        Ok(vlog_uuid) => Ok(vlog_uuid.to_string()),
+        Err(_) => err_at!(InvalidFile, msg: format!("{:?}, vlog uuid", vlog_file)),
+    }
+}
+
 impl fmt::Display for MetaItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         match self {
@@ -1527,6 +2992,8 @@ impl fmt::Display for MetaItem {
             MetaItem::AppMetadata(_) => write!(f, "MetaItem::AppMetadata"),
             MetaItem::Bitmap(_) => write!(f, "MetaItem::Bitmap"),
             MetaItem::Root(_) => write!(f, "MetaItem::Root"),
+            MetaItem::RangeTombstones(_) => write!(f, "MetaItem::RangeTombstones"),
+            MetaItem::Dictionary(_) => write!(f, "MetaItem::Dictionary"),
         }
     }
 }
@@ -1565,6 +3032,22 @@ pub struct Stats {
     pub value_in_vlog: bool,
     /// Flush queue size. Default: Config::FLUSH_QUEUE_SIZE
     pub flush_queue_size: usize,
+    /// Part of _build-configuration_, key-id of the [Cipher] used to
+    /// encrypt btree blocks and value-log blocks. Empty if not encrypted.
+    pub cipher_key_id: String,
+    /// Part of _build-configuration_, name of the [OrdSpec] used to sort
+    /// keys. Empty if keys are sorted using `K: Ord`.
+    pub ord_spec: String,
+    /// Part of _build-configuration_, size of value-log segment files,
+    /// ZERO if value-log is a single file.
+    pub vlog_segment: usize,
+    /// Part of _build-configuration_, name of the [Compressor] used to
+    /// compress value-log. Empty if value-log is not compressed.
+    pub compressor: String,
+    /// Part of _build-configuration_, alignment of block starts and the
+    /// meta region in index file. ZERO for snapshots built before it was
+    /// recorded, whose meta region is aligned to 4KB.
+    pub align: usize,
 
     /// Number of entries indexed.
     pub n_count: u64,
@@ -1588,6 +3071,9 @@ pub struct Stats {
     pub padding: usize,
     /// Older size of value-log file, applicable only in compact build.
     pub n_abytes: usize,
+    /// Total disk footprint for tombstones and superseded deltas, that
+    /// can be reclaimed by compacting with a cutoff.
+    pub dead_bytes: usize,
     /// Size of serialized bitmap bytes.
     pub mem_bitmap: usize,
     /// Number of entries in bitmap.
@@ -1597,36 +3083,186 @@ pub struct Stats {
     pub build_time: u64,
     /// Timestamp for this index.
     pub epoch: i128,
+    /// Unique identifier, generated for every build.
+    pub build_uuid: String,
+    /// Build-uuid of snapshots, this index was built from. Empty for
+    /// initial builds.
+    pub lineage: Vec<String>,
+    /// Identity of value-log file, recorded in its header. Same as the
+    /// build-uuid of the build that created the value-log file.
+    pub vlog_uuid: String,
 }
 
 impl Stats {
-    pub fn merge(self, other: Stats) -> Stats {
-        Stats {
-            name: other.name.clone(),
-            z_blocksize: other.z_blocksize,
-            m_blocksize: other.m_blocksize,
-            v_blocksize: other.v_blocksize,
-            delta_ok: other.delta_ok,
-            vlog_file: None,
-            value_in_vlog: other.value_in_vlog,
-            flush_queue_size: other.flush_queue_size,
+    /// Return the fraction of index footprint that can be reclaimed by
+    /// compaction. If `cutoff` is false, only stale value-log counts.
+    pub fn to_reclaimable_ratio(&self, cutoff: bool) -> f64 {
+        let total = self.z_bytes + self.m_bytes + self.v_bytes + self.n_abytes;
+        match total {
+            0 => 0.0,
+            total => (self.to_reclaimable(cutoff) as f64) / (total as f64),
+        }
+    }
 
-            n_count: self.n_count + other.n_count,
-            n_deleted: self.n_deleted + other.n_deleted,
-            seqno: cmp::max(self.seqno, other.seqno),
-            key_mem: self.key_mem + other.key_mem,
-            diff_mem: self.diff_mem + other.diff_mem,
-            val_mem: self.val_mem + other.val_mem,
-            z_bytes: self.z_bytes + other.z_bytes,
-            m_bytes: self.m_bytes + other.m_bytes,
-            v_bytes: self.v_bytes + other.v_bytes,
-            padding: self.padding + other.padding,
-            n_abytes: self.n_abytes + other.n_abytes,
+    /// Return the average fanout of leaf blocks, as entries per z-block,
+    /// and of intermediate blocks, as child blocks per m-block. Fanout is
+    /// ZERO for a level that has less than two blocks.
+    pub fn to_fanout(&self) -> (f64, f64) {
+        let n_zblocks = self.z_bytes / cmp::max(self.z_blocksize, 1);
+        let n_mblocks = self.m_bytes / cmp::max(self.m_blocksize, 1);
+        let z = match n_zblocks {
+            0 | 1 => 0.0,
+            n => (self.n_count as f64) / (n as f64),
+        };
+        // every block, except the root, is a child of an m-block.
+        let m = match n_mblocks {
+            0 | 1 => 0.0,
+            n => ((n_zblocks + n - 1) as f64) / (n as f64),
+        };
+        (z, m)
+    }
+
+    fn to_reclaimable(&self, cutoff: bool) -> usize {
+        if cutoff {
+            self.n_abytes + self.dead_bytes
+        } else {
+            self.n_abytes
+        }
+    }
+
+    /// Analyse key, value and delta footprint and recommend changes to
+    /// build configuration, refer to [Advice] for details.
+    pub fn to_advice(&self) -> Advice {
+        let mut advice: Advice = Default::default();
+        if self.n_count == 0 {
+            return advice;
+        }
+
+        let n_count = self.n_count as usize;
+        let n_live = cmp::max(n_count.saturating_sub(self.n_deleted), 1);
+        let avg_key = self.key_mem / n_count;
+        let avg_value = self.val_mem / n_live;
+        let avg_diff = self.diff_mem / n_count;
+
+        // values that leave room for few entries in a leaf block are
+        // better off in value-log, and tiny values are not worth the
+        // extra disk seek.
+        if !self.value_in_vlog && (avg_value * Advice::LEAF_ENTRIES) > self.z_blocksize {
+            advice.value_in_vlog = Some(true);
+            advice.notes.push(format!(
+                "average value of {} bytes leaves less than {} entries per leaf",
+                avg_value,
+                Advice::LEAF_ENTRIES
+            ));
+        } else if self.value_in_vlog && avg_value < Advice::SMALL_VALUE {
+            advice.value_in_vlog = Some(false);
+            advice.notes.push(format!(
+                "average value of {} bytes is cheaper to keep in leaf",
+                avg_value
+            ));
+        }
+
+        let value_in_vlog = advice.value_in_vlog.unwrap_or(self.value_in_vlog);
+        let (z, v, m) = {
+            let leaf_value = if value_in_vlog {
+                Advice::VALUE_REF
+            } else {
+                avg_value
+            };
+            let z = Advice::to_blocksize(self.z_blocksize, avg_key + leaf_value);
+            let v = match value_in_vlog {
+                true if avg_value > self.v_blocksize => Advice::to_blocksize(avg_value, 1),
+                _ => self.v_blocksize,
+            };
+            let m = Advice::to_blocksize(self.m_blocksize, avg_key);
+            (z, v, m)
+        };
+        if (z, v, m) != (self.z_blocksize, self.v_blocksize, self.m_blocksize) {
+            advice.blocksize = Some((z, v, m));
+            advice.notes.push(format!(
+                "average key of {} bytes and value of {} bytes, block sizes {:?}",
+                avg_key,
+                avg_value,
+                (z, v, m)
+            ));
+        }
+
+        // deltas out-weighing the value, by several times, indicate long
+        // delta chains that cost more to persist than they are worth.
+        if self.delta_ok && avg_diff > (cmp::max(avg_value, 1) * Advice::DELTA_CAP) {
+            advice.delta_ok = Some(false);
+            advice.notes.push(format!(
+                "average delta footprint of {} bytes, exceeds {}x value footprint",
+                avg_diff,
+                Advice::DELTA_CAP
+            ));
+        }
+
+        let ratio = self.to_reclaimable_ratio(true);
+        if ratio > Advice::DEAD_RATIO {
+            advice.compact = true;
+            advice
+                .notes
+                .push(format!("{:.2} of footprint can be reclaimed", ratio));
+        }
+
+        advice
+    }
+
+    /// Merge statistics of two btree indexes, typically built for
+    /// separate partitions or shards of the same data-set, into one.
+    /// Counts and byte totals are summed up, seqno and epoch are the
+    /// latest of the two and build-time is the longer of the two, as
+    /// partitions are built in parallel. Build configuration and identity
+    /// are picked from `other`, while lineage is the union of both.
+    pub fn merge(self, other: Stats) -> Stats {
+        self.merge_ref(&other)
+    }
+
+    /// Same as [merge][Stats::merge], without consuming either of them.
+    pub fn merge_ref(&self, other: &Stats) -> Stats {
+        let mut lineage = self.lineage.clone();
+        for uuid in other.lineage.iter() {
+            if !lineage.contains(uuid) {
+                lineage.push(uuid.clone())
+            }
+        }
+
+        Stats {
+            name: other.name.clone(),
+            z_blocksize: other.z_blocksize,
+            m_blocksize: other.m_blocksize,
+            v_blocksize: other.v_blocksize,
+            delta_ok: other.delta_ok,
+            vlog_file: None,
+            value_in_vlog: other.value_in_vlog,
+            flush_queue_size: other.flush_queue_size,
+            cipher_key_id: other.cipher_key_id.clone(),
+            ord_spec: other.ord_spec.clone(),
+            vlog_segment: other.vlog_segment,
+            compressor: other.compressor.clone(),
+            align: other.align,
+
+            n_count: self.n_count + other.n_count,
+            n_deleted: self.n_deleted + other.n_deleted,
+            seqno: cmp::max(self.seqno, other.seqno),
+            key_mem: self.key_mem + other.key_mem,
+            diff_mem: self.diff_mem + other.diff_mem,
+            val_mem: self.val_mem + other.val_mem,
+            z_bytes: self.z_bytes + other.z_bytes,
+            m_bytes: self.m_bytes + other.m_bytes,
+            v_bytes: self.v_bytes + other.v_bytes,
+            padding: self.padding + other.padding,
+            n_abytes: self.n_abytes + other.n_abytes,
+            dead_bytes: self.dead_bytes + other.dead_bytes,
             mem_bitmap: self.mem_bitmap + other.mem_bitmap,
             n_bitmap: self.n_bitmap + other.n_bitmap,
 
-            build_time: other.build_time,
-            epoch: other.epoch,
+            build_time: cmp::max(self.build_time, other.build_time),
+            epoch: cmp::max(self.epoch, other.epoch),
+            build_uuid: other.build_uuid.clone(),
+            lineage,
+            vlog_uuid: other.vlog_uuid.clone(),
         }
     }
 }
@@ -1654,52 +3290,118 @@ impl fmt::Display for Stats {
             "robt.stats = {{ mem_bitmap={}, n_bitmap={}, }}\n",
             self.mem_bitmap, self.n_bitmap,
         )?;
+        write!(
+            f,
+            "robt.lineage = {{ build_uuid={}, sources={:?}, vlog_uuid={} }}\n",
+            self.build_uuid, self.lineage, self.vlog_uuid,
+        )?;
         let bt = time::Duration::from_nanos(self.build_time);
         write!(
             f,
-            "robt.stats = {{ padding={}, n_abytes={}, took=\"{:?}\" }}",
-            self.padding, self.n_abytes, bt
+            "robt.stats = {{ padding={}, n_abytes={}, dead_bytes={}, took=\"{:?}\" }}",
+            self.padding, self.n_abytes, self.dead_bytes, bt
         )
     }
 }
 
+/// Schema for [Stats], persisted in index file and exported as JSON.
+pub static STATS_SCHEMA: schema::Schema = {
+    use schema::{Field, Kind};
+
+    schema::Schema {
+        name: "robt.stats",
+        version: 3,
+        fields: &[
+            Field::new("name", Kind::Str, 0),
+            Field::new("z_blocksize", Kind::Int, 0),
+            Field::new("m_blocksize", Kind::Int, 0),
+            Field::new("v_blocksize", Kind::Int, 0),
+            Field::new("delta_ok", Kind::Bool, 0),
+            Field::new("vlog_file", Kind::OptStr, 0),
+            Field::new("value_in_vlog", Kind::Bool, 0),
+            Field::new("flush_queue_size", Kind::Int, 0),
+            Field::new("cipher_key_id", Kind::Str, 1),
+            Field::new("ord_spec", Kind::Str, 1),
+            Field::new("vlog_segment", Kind::Int, 1),
+            Field::new("compressor", Kind::Str, 2),
+            Field::new("align", Kind::Int, 3),
+            Field::new("seqno", Kind::Int, 0),
+            Field::new("n_count", Kind::Int, 0),
+            Field::new("n_deleted", Kind::Int, 0),
+            Field::new("key_mem", Kind::Int, 0),
+            Field::new("val_mem", Kind::Int, 0),
+            Field::new("diff_mem", Kind::Int, 0),
+            Field::new("z_bytes", Kind::Int, 0),
+            Field::new("m_bytes", Kind::Int, 0),
+            Field::new("v_bytes", Kind::Int, 0),
+            Field::new("mem_bitmap", Kind::Int, 0),
+            Field::new("n_bitmap", Kind::Int, 0),
+            Field::new("padding", Kind::Int, 0),
+            Field::new("n_abytes", Kind::Int, 0),
+            Field::new("dead_bytes", Kind::Int, 1),
+            Field::new("build_time", Kind::Int, 0),
+            Field::new("epoch", Kind::Int, 0),
+            Field::new("build_uuid", Kind::Str, 1),
+            Field::new("lineage", Kind::List, 1),
+            Field::new("vlog_uuid", Kind::Str, 1),
+        ],
+    }
+};
+
+impl Stats {
+    fn to_record(&self) -> schema::Record {
+        let vlog_file = self
+            .vlog_file
+            .as_ref()
+            .map(|f| f.to_string_lossy().to_string());
+
+        let mut r = schema::Record::new(&STATS_SCHEMA);
+        r.set("name", self.name.as_str())
+            .set("z_blocksize", self.z_blocksize)
+            .set("m_blocksize", self.m_blocksize)
+            .set("v_blocksize", self.v_blocksize)
+            .set("delta_ok", self.delta_ok)
+            .set("vlog_file", vlog_file)
+            .set("value_in_vlog", self.value_in_vlog)
+            .set("flush_queue_size", self.flush_queue_size)
+            .set("cipher_key_id", self.cipher_key_id.as_str())
+            .set("ord_spec", self.ord_spec.as_str())
+            .set("vlog_segment", self.vlog_segment)
+            .set("compressor", self.compressor.as_str())
+            .set("align", self.align)
+            .set("seqno", self.seqno)
+            .set("n_count", self.n_count)
+            .set("n_deleted", self.n_deleted)
+            .set("key_mem", self.key_mem)
+            .set("val_mem", self.val_mem)
+            .set("diff_mem", self.diff_mem)
+            .set("z_bytes", self.z_bytes)
+            .set("m_bytes", self.m_bytes)
+            .set("v_bytes", self.v_bytes)
+            .set("mem_bitmap", self.mem_bitmap)
+            .set("n_bitmap", self.n_bitmap)
+            .set("padding", self.padding)
+            .set("n_abytes", self.n_abytes)
+            .set("dead_bytes", self.dead_bytes)
+            .set("build_time", self.build_time)
+            .set("epoch", self.epoch)
+            .set("build_uuid", self.build_uuid.as_str())
+            .set("lineage", self.lineage.clone())
+            .set("vlog_uuid", self.vlog_uuid.as_str());
+        r
+    }
+}
+
 impl ToJson for Stats {
     fn to_json(&self) -> String {
-        let vlog_file = match &self.vlog_file {
-            Some(vlog_file) => format!("{:?}", vlog_file),
-            None => "null".to_string(),
-        };
-        let props = [
-            format!(r#""name": "{}""#, self.name),
-            format!(r#""z_blocksize": {}"#, self.z_blocksize),
-            format!(r#""m_blocksize": {}"#, self.m_blocksize),
-            format!(r#""v_blocksize": {}"#, self.v_blocksize),
-            format!(r#""delta_ok": {}"#, self.delta_ok),
-            format!(r#""vlog_file": {}"#, vlog_file),
-            format!(r#""value_in_vlog": {}"#, self.value_in_vlog),
-            format!(r#""flush_queue_size": {}"#, self.flush_queue_size),
-            format!(r#""seqno": {}"#, self.seqno),
-            format!(r#""n_count": {}"#, self.n_count),
-            format!(r#""n_deleted": {}"#, self.n_deleted),
-            format!(r#""key_mem": {}"#, self.key_mem),
-            format!(r#""val_mem": {}"#, self.val_mem),
-            format!(r#""diff_mem": {}"#, self.diff_mem),
-            format!(r#""z_bytes": {}"#, self.z_bytes),
-            format!(r#""m_bytes": {}"#, self.m_bytes),
-            format!(r#""v_bytes": {}"#, self.v_bytes),
-            format!(r#""mem_bitmap": {}"#, self.mem_bitmap),
-            format!(r#""n_bitmap": {}"#, self.n_bitmap),
-            format!(r#""padding": {}"#, self.padding),
-            format!(r#""n_abytes": {}"#, self.n_abytes),
-            format!(r#""build_time": {}"#, self.build_time),
-            format!(r#""epoch": {}"#, self.epoch),
-        ];
-        format!(r#"{{ {} }}"#, props.join(", "))
+        self.to_record().to_json()
     }
 }
 
 impl From<Config> for Stats {
     fn from(config: Config) -> Stats {
+        let cipher_key_id = config.to_cipher_key_id();
+        let compressor = config.to_compressor_name();
         Stats {
             name: config.name,
             z_blocksize: config.z_blocksize,
@@ -1709,6 +3411,11 @@ impl From<Config> for Stats {
             vlog_file: config.vlog_file,
             value_in_vlog: config.value_in_vlog,
             flush_queue_size: config.flush_queue_size,
+            cipher_key_id,
+            ord_spec: config.ord_spec,
+            vlog_segment: config.vlog_segment,
+            compressor,
+            align: config.align,
 
             n_count: Default::default(),
             n_deleted: Default::default(),
@@ -1723,9 +3430,13 @@ impl From<Config> for Stats {
             n_bitmap: Default::default(),
             padding: Default::default(),
             n_abytes: Default::default(),
+            dead_bytes: Default::default(),
 
             build_time: Default::default(),
             epoch: Default::default(),
+            build_uuid: Default::default(),
+            lineage: Default::default(),
+            vlog_uuid: Default::default(),
         }
     }
 }
@@ -1734,82 +3445,114 @@ impl FromStr for Stats {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Stats> {
-        use jsondata::Json;
-
-        let js: Json = err_at!(InvalidInput, s.parse())?;
-
-        let to_usize = |key: &str| -> Result<usize> {
-            match err_at!(InvalidInput, js.get(key))?.to_integer() {
-                Some(n) => convert_at!(n),
-                None => err_at!(InvalidInput, msg: format!("key:{}", key)),
-            }
-        };
-        let to_u64 = |key: &str| -> Result<u64> {
-            match err_at!(InvalidInput, js.get(key))?.to_integer() {
-                Some(n) => convert_at!(n),
-                None => err_at!(InvalidInput, msg: format!("key:{}", key)),
-            }
-        };
-        let to_i128 = |key: &str| -> Result<i128> {
-            match err_at!(InvalidInput, js.get(key))?.to_integer() {
-                Some(n) => convert_at!(n),
-                None => err_at!(InvalidInput, msg: format!("key:{}", key)),
-            }
-        };
-        let to_bool = |key: &str| -> Result<bool> {
-            match err_at!(InvalidInput, js.get(key))?.to_bool() {
-                Some(val) => Ok(val),
-                None => err_at!(InvalidInput, msg: format!("key:{}", key)),
-            }
-        };
-        let to_string = |key: &str| -> Result<String> {
-            match err_at!(InvalidInput, js.get(key))?.as_str() {
-                Some(val) => Ok(val.to_string()),
-                None => err_at!(InvalidInput, msg: format!("key:{}", key)),
-            }
-        };
-        let vlog_file = {
-            match err_at!(InvalidInput, js.get("/vlog_file"))?.as_str() {
-                Some(s) if s.len() == 0 => None,
-                None => None,
-                Some(s) => {
-                    let vlog_file: ffi::OsString = s.to_string().into();
-                    Some(vlog_file)
-                }
-            }
-        };
+        let r = schema::Record::from_json(&STATS_SCHEMA, s)?;
+        let vlog_file: Option<ffi::OsString> = r.get_opt_string("vlog_file")?.map(From::from);
 
         Ok(Stats {
-            name: to_string("/name")?,
+            name: r.get_string("name")?,
             // config fields.
-            z_blocksize: to_usize("/z_blocksize")?,
-            m_blocksize: to_usize("/m_blocksize")?,
-            v_blocksize: to_usize("/v_blocksize")?,
-            delta_ok: to_bool("/delta_ok")?,
-            vlog_file: vlog_file,
-            value_in_vlog: to_bool("/value_in_vlog")?,
-            flush_queue_size: to_usize("/flush_queue_size")?,
+            z_blocksize: r.get_usize("z_blocksize")?,
+            m_blocksize: r.get_usize("m_blocksize")?,
+            v_blocksize: r.get_usize("v_blocksize")?,
+            delta_ok: r.get_bool("delta_ok")?,
+            vlog_file,
+            value_in_vlog: r.get_bool("value_in_vlog")?,
+            flush_queue_size: r.get_usize("flush_queue_size")?,
+            cipher_key_id: r.get_string("cipher_key_id")?,
+            ord_spec: r.get_string("ord_spec")?,
+            vlog_segment: r.get_usize("vlog_segment")?,
+            compressor: r.get_string("compressor")?,
+            align: r.get_usize("align")?,
             // statitics fields.
-            n_count: to_u64("/n_count")?,
-            n_deleted: to_usize("/n_deleted")?,
-            seqno: to_u64("/seqno")?,
-            key_mem: to_usize("/key_mem")?,
-            diff_mem: to_usize("/diff_mem")?,
-            val_mem: to_usize("/val_mem")?,
-            z_bytes: to_usize("/z_bytes")?,
-            v_bytes: to_usize("/v_bytes")?,
-            m_bytes: to_usize("/m_bytes")?,
-            mem_bitmap: to_usize("/mem_bitmap")?,
-            n_bitmap: to_usize("/n_bitmap")?,
-            padding: to_usize("/padding")?,
-            n_abytes: to_usize("/n_abytes")?,
-
-            build_time: to_u64("/build_time")?,
-            epoch: to_i128("/epoch")?,
+            n_count: r.get_u64("n_count")?,
+            n_deleted: r.get_usize("n_deleted")?,
+            seqno: r.get_u64("seqno")?,
+            key_mem: r.get_usize("key_mem")?,
+            diff_mem: r.get_usize("diff_mem")?,
+            val_mem: r.get_usize("val_mem")?,
+            z_bytes: r.get_usize("z_bytes")?,
+            v_bytes: r.get_usize("v_bytes")?,
+            m_bytes: r.get_usize("m_bytes")?,
+            mem_bitmap: r.get_usize("mem_bitmap")?,
+            n_bitmap: r.get_usize("n_bitmap")?,
+            padding: r.get_usize("padding")?,
+            n_abytes: r.get_usize("n_abytes")?,
+            dead_bytes: r.get_usize("dead_bytes")?,
+
+            build_time: r.get_u64("build_time")?,
+            epoch: r.get_i128("epoch")?,
+            build_uuid: r.get_string("build_uuid")?,
+            lineage: r.get_list("lineage")?,
+            vlog_uuid: r.get_string("vlog_uuid")?,
         })
     }
 }
 
+/// Recommended changes to build [Config], computed from [Stats] of a
+/// btree snapshot, refer to [Snapshot::advice]. Recommendations are
+/// None if current configuration is fine.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Advice {
+    /// Recommended setting for [Config::set_value_log].
+    pub value_in_vlog: Option<bool>,
+    /// Recommended (z, v, m) block sizes for [Config::set_blocksize].
+    pub blocksize: Option<(usize, usize, usize)>,
+    /// Recommended setting for [Config::set_delta], false if delta chains
+    /// out-weigh the values they belong to.
+    pub delta_ok: Option<bool>,
+    /// True if most of the footprint is dead, compact the snapshot with
+    /// a cutoff.
+    pub compact: bool,
+    /// Reasoning for each recommendation.
+    pub notes: Vec<String>,
+}
+
+impl Advice {
+    /// Minimum number of entries expected to fit in a leaf block.
+    pub const LEAF_ENTRIES: usize = 16;
+    /// Values smaller than this are cheaper to keep in leaf.
+    pub const SMALL_VALUE: usize = 64;
+    /// Maximum ratio of delta footprint to value footprint, per entry.
+    pub const DELTA_CAP: usize = 4;
+    /// Fraction of reclaimable footprint that calls for a compaction.
+    pub const DEAD_RATIO: f64 = 0.5;
+    /// Maximum block size recommended.
+    pub const MAX_BLOCKSIZE: usize = 1024 * 1024;
+    // approximate leaf footprint for a value persisted in value-log.
+    const VALUE_REF: usize = 16;
+
+    /// Return true if there is nothing to recommend.
+    pub fn is_empty(&self) -> bool {
+        self.value_in_vlog.is_none()
+            && self.blocksize.is_none()
+            && self.delta_ok.is_none()
+            && !self.compact
+    }
+
+    /// Apply recommendations to `config`.
+    pub fn apply(&self, config: &mut Config) -> Result<()> {
+        if let Some(ok) = self.value_in_vlog {
+            config.set_value_log(None, ok)?;
+        }
+        if let Some((z, v, m)) = self.blocksize {
+            config.set_blocksize(z, v, m)?;
+        }
+        if let Some(ok) = self.delta_ok {
+            config.set_delta(None, ok)?;
+        }
+        Ok(())
+    }
+
+    // grow blocksize to hold LEAF_ENTRIES of `item` size.
+    fn to_blocksize(blocksize: usize, item: usize) -> usize {
+        let want = (item * Self::LEAF_ENTRIES).next_power_of_two();
+        match want {
+            want if want > blocksize => cmp::min(want, Self::MAX_BLOCKSIZE),
+            _ => blocksize,
+        }
+    }
+}
+
 /// Builder type, for constructing Read-Only-BTree index from an iterator.
 ///
 /// Index can be built in [initial][Builder::initial] mode or
@@ -1824,7 +3567,19 @@ where
     config: Config,
     iflusher: Option<rt::Thread<Vec<u8>, (), (ffi::OsString, u64)>>,
     vflusher: Option<rt::Thread<Vec<u8>, (), (ffi::OsString, u64)>>,
+    vfpos: u64,
     stats: Stats,
+    range_tombstones: Vec<RangeTombstone<K>>,
+    size_limit: SizeLimit<V>,
+    index_file: ffi::OsString,
+    checkpoint: Option<Checkpointer<K, B>>,
+    resume: Option<Resume<K, V>>,
+    sampler: Option<Sampler>,
+    dict: Option<ValueDict>,
+    flags_hook: Option<fn(&Entry<K, V>) -> u8>,
+    sort_limit: usize,
+    part_fpos: Option<Arc<AtomicU64>>,
+    min_seqno: Option<u64>,
 
     _phantom_key: marker::PhantomData<K>,
     _phantom_val: marker::PhantomData<V>,
@@ -1843,15 +3598,39 @@ where
     pub fn initial(
         dir: &ffi::OsStr, // directory path where index file(s) are stored
         name: &str,
+        config: Config,
+    ) -> Result<Builder<K, V, B>> {
+        Self::new_initial(dir, name, config, false /*dry_run*/)
+    }
+
+    // for dry-run, blocks are discarded instead of writing them to
+    // index file and value-log file.
+    fn new_initial(
+        dir: &ffi::OsStr,
+        name: &str,
         mut config: Config, //  TODO: Bit of ugliness here
+        dry_run: bool,
     ) -> Result<Builder<K, V, B>> {
-        let create = true;
+        config.validate()?;
+        let build_uuid = util::new_uuid();
 
-        let iflusher = {
-            let ifile = Config::stitch_index_file(dir, name);
+        let index_file = Config::stitch_index_file(dir, name);
+        let iflusher = if dry_run {
+            let ifile = index_file.clone();
+            rt::Thread::new_sync(
+                format!("robt-index-dry-run-{}", name),
+                move |rx| move || thread_discard(ifile, rx),
+                config.flush_queue_size,
+            )
+        } else {
+            let ifile = index_file.clone();
+            let icipher = config.to_file_cipher(&build_uuid)?;
+            let depth = config.flush_depth;
             rt::Thread::new_sync(
                 format!("robt-index-flusher-{}", name),
-                move |rx| move || thread_flush(ifile, create, rx),
+                move |rx| {
+                    move || thread_flush(ifile, true /*create*/, icipher, None, depth, rx)
+                },
                 config.flush_queue_size,
             )
         };
@@ -1863,23 +3642,78 @@ where
             _ => None,
         };
 
+        // value-log header is written in plain text, before the flusher
+        // starts appending (optionally encrypted) value blocks.
         let vflusher = match &config.vlog_file {
+            Some(vfile) if dry_run => {
+                let vfile = vfile.clone();
+                Some(rt::Thread::new_sync(
+                    format!("robt-vlog-dry-run-{}", name),
+                    move |rx| move || thread_discard(vfile, rx),
+                    config.flush_queue_size,
+                ))
+            }
             Some(vfile) => {
                 let vfile = vfile.clone();
+                // segments left behind by an older build are stale.
+                for file in Config::to_vlog_segments(&vfile).into_iter().skip(1) {
+                    err_at!(IoError, fs::remove_file(&file))?;
+                }
+                let mut fd = util::create_file_a(vfile.clone())?;
+                let hdr = vlog_header(&build_uuid)?;
+                write_file!(fd, &hdr, vfile.clone(), "robt-vlog-header")?;
+
+                let vcipher = config.to_file_cipher(&build_uuid)?;
+                let depth = config.flush_depth;
+                let seg = VlogSegment::new(&vfile, 0, config.vlog_segment, hdr);
                 Some(rt::Thread::new_sync(
                     format!("robt-vlog-flusher-{}", name),
-                    move |rx| move || thread_flush(vfile, create, rx),
+                    move |rx| {
+                        move || thread_flush(vfile, false /*create*/, vcipher, seg, depth, rx)
+                    },
                     config.flush_queue_size,
                 ))
             }
             None => None,
         };
+        // values of a fresh value-log file start after its header.
+        let vfpos = match &vflusher {
+            Some(_) => VLOG_HEADER_SIZE,
+            None => 0,
+        };
+
+        let mut stats: Stats = From::from(config.clone());
+        if vflusher.is_some() {
+            stats.vlog_uuid = build_uuid.clone();
+            stats.v_bytes += convert_at!(VLOG_HEADER_SIZE)?;
+        }
+        stats.build_uuid = build_uuid;
+
+        let sampler = match &config.compressor {
+            Some(compressor) if vflusher.is_some() && config.dict_samples > 0 => {
+                let (n, size) = (config.dict_samples, config.dict_size);
+                Some(Sampler::new(Arc::clone(compressor), n, size))
+            }
+            _ => None,
+        };
 
         Ok(Builder {
             config: config.clone(),
             iflusher: Some(iflusher),
             vflusher,
-            stats: From::from(config),
+            vfpos,
+            stats,
+            range_tombstones: vec![],
+            size_limit: Default::default(),
+            index_file,
+            checkpoint: None,
+            resume: None,
+            sampler,
+            dict: None,
+            flags_hook: None,
+            sort_limit: robt_sort::SORT_LIMIT,
+            part_fpos: None,
+            min_seqno: None,
 
             _phantom_key: marker::PhantomData,
             _phantom_val: marker::PhantomData,
@@ -1889,16 +3723,26 @@ where
 
     /// For compact build, index file is created new, while
     /// value-log-file, if configured, shall be appended to older version.
+    /// If older version's value-log is compressed, its dictionary shall
+    /// be carried over using [set_dictionary][Builder::set_dictionary].
     pub fn incremental(
         dir: &ffi::OsStr, // directory path where index files are stored
         name: &str,
         mut config: Config,
     ) -> Result<Builder<K, V, B>> {
+        config.validate()?;
+        let build_uuid = util::new_uuid();
+
+        let index_file = Config::stitch_index_file(dir, name);
         let iflusher = {
-            let ifile = Config::stitch_index_file(dir, name);
+            let ifile = index_file.clone();
+            let icipher = config.to_file_cipher(&build_uuid)?;
+            let depth = config.flush_depth;
             rt::Thread::new_sync(
                 format!("robt-index-flusher-{}", name),
-                move |rx| move || thread_flush(ifile, true /*create*/, rx),
+                move |rx| {
+                    move || thread_flush(ifile, true /*create*/, icipher, None, depth, rx)
+                },
                 config.flush_queue_size,
             )
         };
@@ -1912,30 +3756,237 @@ where
 
         let create = false;
 
-        let (vflusher, vf_fpos): (_, usize) = match &config.vlog_file {
+        let (vflusher, vfpos, vf_fpos, vlog_uuid): (_, _, usize, _) = match &config.vlog_file {
             Some(vfile) => {
                 let vfile = vfile.clone();
-                let vf_fpos = err_at!(IoError, fs::metadata(&vfile))?.len();
+                let vlog_uuid = {
+                    let mut fd = util::open_file_r(&vfile)?;
+                    read_vlog_uuid(&mut fd, &vfile)?
+                };
+                // append to the last segment of value-log.
+                let segments = Config::to_vlog_segments(&vfile);
+                let mut vf_fpos = 0;
+                for file in segments.iter() {
+                    vf_fpos += err_at!(IoError, fs::metadata(file))?.len();
+                }
+                let segment = segments.len() - 1;
+                let sfile = segments[segment].clone();
+                let vfpos = {
+                    let n = err_at!(IoError, fs::metadata(&sfile))?.len();
+                    vlog::from_segment(segment, n)?
+                };
 
+                let vcipher = config.to_file_cipher(&vlog_uuid)?;
+                let depth = config.flush_depth;
+                let hdr = vlog_header(&vlog_uuid)?;
+                let seg = VlogSegment::new(&vfile, segment, config.vlog_segment, hdr);
                 let t = rt::Thread::new_sync(
                     format!("robt-vlog-flusher-{}", name),
-                    move |rx| move || thread_flush(vfile, create, rx),
+                    move |rx| move || thread_flush(sfile, create, vcipher, seg, depth, rx),
                     config.flush_queue_size,
                 );
 
-                (Some(t), convert_at!(vf_fpos)?)
+                (Some(t), vfpos, convert_at!(vf_fpos)?, vlog_uuid)
             }
-            None => (None, Default::default()),
+            None => (None, 0, Default::default(), Default::default()),
         };
 
         let mut stats: Stats = From::from(config.clone());
         stats.n_abytes += vf_fpos;
+        stats.build_uuid = build_uuid;
+        stats.vlog_uuid = vlog_uuid;
 
+        // value-log is shared with the older snapshot, hence dictionary
+        // is not trained, refer to set_dictionary().
         Ok(Builder {
             config: config.clone(),
             iflusher: Some(iflusher),
             vflusher,
+            vfpos,
+            stats,
+            range_tombstones: vec![],
+            size_limit: Default::default(),
+            index_file,
+            checkpoint: None,
+            resume: None,
+            sampler: None,
+            dict: None,
+            flags_hook: None,
+            sort_limit: robt_sort::SORT_LIMIT,
+            part_fpos: None,
+            min_seqno: None,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+            _phantom_bitmap: marker::PhantomData,
+        })
+    }
+
+    /// Record build progress into a sidecar checkpoint file, once for
+    /// every `every` leaf blocks flushed, so that a crashed build can be
+    /// continued using [resume][Builder::resume] instead of restarting
+    /// from scratch. Checkpoints are recorded only by
+    /// [build][Builder::build], and the checkpoint file is removed once
+    /// the build completes.
+    pub fn set_checkpoint(&mut self, every: usize) -> Result<&mut Self>
+    where
+        K: Hash,
+    {
+        if every == 0 {
+            err_at!(InvalidInput, msg: format!("checkpoint every 0 blocks"))?;
+        }
+        self.checkpoint = Some(Checkpointer {
+            file: Config::stitch_checkpoint_file(&self.index_file),
+            every,
+            n_zblocks: 0,
+            seqno: 0,
+            n_count: 0,
+            n_deleted: 0,
+            bitmap: <B as Bloom>::create(),
+            add_key: |bitmap, key| bitmap.add_key(key),
+        });
+        Ok(self)
+    }
+
+    /// Resume an index build, that crashed after recording one or more
+    /// checkpoints, refer to [set_checkpoint][Builder::set_checkpoint].
+    /// Index file and value-log file are truncated to the last
+    /// checkpointed block, and the build shall continue from
+    /// [resume key][Builder::to_resume_key].
+    ///
+    /// Application shall call [build][Builder::build] with entries
+    /// starting from the resume key, entries before the resume key, if
+    /// any, are skipped. Size limit is not part of the checkpoint, and
+    /// shall be configured again. Compression dictionary, if trained
+    /// before the checkpoint, is restored, otherwise values flushed after
+    /// the checkpoint are left uncompressed.
+    pub fn resume(dir: &ffi::OsStr, name: &str) -> Result<Builder<K, V, B>>
+    where
+        K: Default + Hash,
+    {
+        let index_file = Config::stitch_index_file(dir, name);
+        let ckpt_file = Config::stitch_checkpoint_file(&index_file);
+        let cp: Checkpoint<K> = Checkpoint::load(&ckpt_file)?;
+
+        let stats: Stats = cp.stats.parse()?;
+        let mut config: Config = stats.clone().into();
+        config.vlog_file = match &config.vlog_file {
+            Some(vfile) => {
+                // value-log file is expected in the same directory.
+                let mut vpath = path::PathBuf::from(dir);
+                vpath.push(match path::Path::new(vfile).file_name() {
+                    Some(vfile) => Ok(vfile),
+                    None => err_at!(InvalidFile, msg: format!("{:?}", vfile)),
+                }?);
+                Some(vpath.into_os_string())
+            }
+            None => None,
+        };
+
+        // blocks flushed after the checkpoint are discarded.
+        {
+            let fd = util::open_file_w(&index_file)?;
+            err_at!(IoError, fd.set_len(cp.fpos))?;
+            err_at!(IoError, fd.sync_all())?;
+        }
+        let iflusher = {
+            let ifile = index_file.clone();
+            let icipher = config.to_file_cipher(&stats.build_uuid)?;
+            let depth = config.flush_depth;
+            rt::Thread::new_sync(
+                format!("robt-index-flusher-{}", name),
+                move |rx| {
+                    move || thread_flush(ifile, false /*create*/, icipher, None, depth, rx)
+                },
+                config.flush_queue_size,
+            )
+        };
+
+        let vflusher = match &config.vlog_file {
+            Some(vfile) => {
+                let vfile = vfile.clone();
+                let (segment, offset) = vlog::to_segment(cp.vfpos)?;
+                let segments = Config::to_vlog_segments(&vfile);
+                for file in segments.into_iter().skip(segment + 1) {
+                    err_at!(IoError, fs::remove_file(&file))?;
+                }
+                let sfile = Config::stitch_vlog_segment(&vfile, segment);
+                {
+                    let fd = util::open_file_w(&sfile)?;
+                    err_at!(IoError, fd.set_len(offset))?;
+                    err_at!(IoError, fd.sync_all())?;
+                }
+
+                let vcipher = config.to_file_cipher(&stats.vlog_uuid)?;
+                let depth = config.flush_depth;
+                let hdr = vlog_header(&stats.vlog_uuid)?;
+                let seg = VlogSegment::new(&vfile, segment, config.vlog_segment, hdr);
+                Some(rt::Thread::new_sync(
+                    format!("robt-vlog-flusher-{}", name),
+                    move |rx| {
+                        move || thread_flush(sfile, false /*create*/, vcipher, seg, depth, rx)
+                    },
+                    config.flush_queue_size,
+                ))
+            }
+            None => None,
+        };
+
+        let dict = match &config.compressor {
+            Some(compressor) if cp.dict.len() > 0 => {
+                Some(ValueDict::new(Arc::clone(compressor), cp.dict.clone()))
+            }
+            None if cp.dict.len() > 0 => {
+                let msg = format!("unknown compressor {:?}", stats.compressor);
+                err_at!(InvalidInput, msg: msg)?
+            }
+            _ => None,
+        };
+
+        let ms: Vec<MBlock<K, V>> = cp
+            .ms
+            .into_iter()
+            .map(|(first_key, offsets, block)| {
+                MBlock::from_encode_state(config.clone(), first_key, offsets, block)
+            })
+            .collect();
+
+        info!(
+            target: "robt  ",
+            "{:?}/{}, resume build from fpos:{} vfpos:{} n_count:{}",
+            dir, name, cp.fpos, cp.vfpos, cp.n_count
+        );
+
+        Ok(Builder {
+            config,
+            iflusher: Some(iflusher),
+            vflusher,
+            vfpos: cp.vfpos,
             stats,
+            range_tombstones: decode_range_tombstones(&cp.tombs)?,
+            size_limit: Default::default(),
+            index_file,
+            checkpoint: Some(Checkpointer {
+                file: ckpt_file,
+                every: cp.every,
+                n_zblocks: 0,
+                seqno: cp.seqno,
+                n_count: cp.n_count,
+                n_deleted: cp.n_deleted,
+                bitmap: <B as Bloom>::from_vec(&cp.bitmap)?,
+                add_key: |bitmap, key| bitmap.add_key(key),
+            }),
+            resume: Some(Resume {
+                key: cp.key,
+                fpos: cp.fpos,
+                ms,
+            }),
+            sampler: None,
+            dict,
+            flags_hook: None,
+            sort_limit: robt_sort::SORT_LIMIT,
+            part_fpos: None,
+            min_seqno: None,
 
             _phantom_key: marker::PhantomData,
             _phantom_val: marker::PhantomData,
@@ -1943,6 +3994,140 @@ where
         })
     }
 
+    /// Return the key from which a [resumed][Builder::resume] build
+    /// shall continue, None if this is not a resumed build.
+    pub fn to_resume_key(&self) -> Option<K> {
+        self.resume.as_ref().map(|resume| resume.key.clone())
+    }
+
+    /// Record the build-uuid of snapshots, that are sourced for this
+    /// build, refer to [Snapshot::to_lineage].
+    pub fn set_lineage(&mut self, sources: Vec<String>) -> Result<&mut Self> {
+        self.stats.lineage = sources;
+        Ok(self)
+    }
+
+    /// Compress value-log using `dict`, instead of training a new
+    /// dictionary. Incremental builds shall carry over the dictionary of
+    /// the older snapshot, refer to [Snapshot::to_dictionary]. Empty
+    /// `dict` is ignored.
+    pub fn set_dictionary(&mut self, dict: Vec<u8>) -> Result<&mut Self> {
+        if dict.len() == 0 {
+            return Ok(self);
+        }
+        match &self.config.compressor {
+            Some(compressor) => {
+                self.dict = Some(ValueDict::new(Arc::clone(compressor), dict));
+                self.sampler = None;
+                Ok(self)
+            }
+            None => err_at!(InvalidInput, msg: format!("dictionary without compressor")),
+        }
+    }
+
+    /// Delete all entries, whose key fall within `range` and whose seqno
+    /// is less than or equal to `seqno`, refer to [RangeTombstone]. Shall
+    /// be called before [build][Builder::build] or
+    /// [build_finish][Builder::build_finish].
+    pub fn delete_range<R>(&mut self, range: R, seqno: u64) -> Result<&mut Self>
+    where
+        R: RangeBounds<K>,
+    {
+        let (low, high) = util::to_start_end(range);
+        let tomb = RangeTombstone { low, high, seqno };
+        self.range_tombstones.push(tomb);
+        Ok(self)
+    }
+
+    /// Configure maximum size of keys and values, measured as serialized
+    /// bytes, refer to [SizeLimit]. [SizePolicy::SpillToVlog] expects
+    /// values to be persisted in value-log.
+    pub fn set_size_limit(&mut self, limit: SizeLimit<V>) -> Result<&mut Self> {
+        match limit.policy {
+            SizePolicy::SpillToVlog if !self.config.value_in_vlog => {
+                let msg = format!("SpillToVlog without value_in_vlog");
+                err_at!(InvalidInput, msg: msg)?
+            }
+            _ => (),
+        }
+        self.size_limit = limit;
+        Ok(self)
+    }
+
+    /// Compute application defined flags for every entry, before it is
+    /// encoded into z-block, flags can be read back via
+    /// [Entry::to_flags]. Without a hook, flags carried by the source
+    /// entries are persisted as is. Resumed builds shall set the hook
+    /// again.
+    pub fn set_flags_hook(&mut self, hook: fn(&Entry<K, V>) -> u8) -> Result<&mut Self> {
+        self.flags_hook = Some(hook);
+        Ok(self)
+    }
+
+    /// Configure the memory limit, in bytes, for sorting entries in
+    /// [build_from_wal][Builder::build_from_wal]. Sorted runs beyond this
+    /// limit are spilled into the index directory and merged back while
+    /// building the index. Default is 256MB.
+    pub fn set_sort_limit(&mut self, limit: usize) -> Result<&mut Self> {
+        if limit == 0 {
+            err_at!(InvalidInput, msg: format!("sort limit of 0 bytes"))?;
+        }
+        self.sort_limit = limit;
+        Ok(self)
+    }
+
+    /// Expect every entry supplied to the build to have its seqno above
+    /// `seqno`, typically the seqno of previous build. Build shall fail
+    /// with `InvalidInput` on the first entry that regress, instead of
+    /// producing an index whose seqno, refer [Stats], is inconsistent
+    /// with its entries. Irrespective of this setting, versions within
+    /// an entry are always expected in descending seqno order.
+    pub fn set_min_seqno(&mut self, seqno: u64) -> Result<&mut Self> {
+        self.min_seqno = Some(seqno);
+        Ok(self)
+    }
+
+    // apply size limits on entry before it is encoded into z-block.
+    fn apply_size_limit(&self, entry: Entry<K, V>) -> Result<Entry<K, V>> {
+        if self.size_limit.is_unlimited() {
+            return Ok(entry);
+        }
+
+        let mut buf = vec![];
+        let key_size = entry.as_key().encode(&mut buf)?;
+        self.size_limit.check_key(key_size)?;
+
+        let value = match entry.to_native_value() {
+            Some(value) => value,
+            None => return Ok(entry),
+        };
+        buf.truncate(0);
+        let value_size = value.encode(&mut buf)?;
+        match self.size_limit.policy {
+            SizePolicy::Truncate(_) if value_size > self.size_limit.max_value => {
+                if entry.to_delta_count() > 0 {
+                    let msg = format!("cannot truncate versioned entry of {}", value_size);
+                    err_at!(InvalidInput, msg: msg)?
+                }
+                let (seqno, key) = (entry.to_seqno(), entry.to_key());
+                let value = self.size_limit.apply_value(value_size, value)?;
+                let mut tentry = Entry::new(key, core::Value::new_upsert_value(value, seqno));
+                tentry.set_flags(entry.to_flags());
+                Ok(tentry)
+            }
+            _ => {
+                self.size_limit.apply_value(value_size, value)?;
+                Ok(entry)
+            }
+        }
+    }
+
+    // carry over range tombstones from older snapshot.
+    fn set_range_tombstones(&mut self, tombs: Vec<RangeTombstone<K>>) -> Result<&mut Self> {
+        self.range_tombstones.extend(tombs.into_iter());
+        Ok(self)
+    }
+
     /// Build a new index from the supplied iterator. The iterator shall
     /// return an index entry for each iteration, and the entries are
     /// expected in sort order.
@@ -1951,19 +4136,214 @@ where
         K: Hash,
         I: Iterator<Item = Result<Entry<K, V>>>,
     {
-        let (root, bitmap): (u64, B) = {
-            let mut bscanner = {
-                let seqno: u64 = Default::default();
-                BuildScan::new(scans::BitmappedScan::new(iter), seqno)
-            };
-            let root = self.build_tree(&mut bscanner)?;
-            let (_, bitmap) = bscanner.update_stats(&mut self.stats)?.close()?;
-            (root, bitmap)
+        let iter = SeqnoCheck::new(iter, self.min_seqno);
+        let iter = UniqueCheck::new(iter, self.config.strict_unique);
+        let (root, bitmap): (u64, B) = match &self.checkpoint {
+            Some(cp) => {
+                // skip entries that are already persisted.
+                let key = self.to_resume_key();
+                let iter = iter.skip_while(move |item| match (&key, item) {
+                    (Some(key), Ok(entry)) => entry.as_key().lt(key),
+                    _ => false,
+                });
+                let mut bscanner = BuildScan::new(iter, cp.seqno);
+                bscanner.n_count = cp.n_count;
+                bscanner.n_deleted = cp.n_deleted;
+
+                let root = self.build_tree(&mut bscanner)?;
+                let _iter = bscanner.update_stats(&mut self.stats)?;
+                let bitmap = match self.checkpoint.as_mut() {
+                    Some(cp) => mem::replace(&mut cp.bitmap, <B as Bloom>::create()),
+                    None => err_at!(Fatal, msg: format!("unreachable"))?,
+                };
+                (root, bitmap)
+            }
+            None => {
+                let mut bscanner = {
+                    let seqno: u64 = Default::default();
+                    BuildScan::new(scans::BitmappedScan::new(iter), seqno)
+                };
+                let root = self.build_tree(&mut bscanner)?;
+                let (_, bitmap) = bscanner.update_stats(&mut self.stats)?.close()?;
+                (root, bitmap)
+            }
         };
 
         self.build_finish(app_meta, bitmap, root)
     }
 
+    /// Build a new index from lines of delimited text, like CSV or
+    /// JSON-lines. `parser` is called for each line and shall return a
+    /// key, value pair, or None to skip the line. Lines are expected in
+    /// sort order of keys, and each entry is assigned an incrementing
+    /// seqno starting from 1.
+    pub fn build_from_text<R, F>(self, r: R, mut parser: F, app_meta: Vec<u8>) -> Result<usize>
+    where
+        K: Hash,
+        R: io::BufRead,
+        F: FnMut(&str) -> Result<Option<(K, V)>>,
+    {
+        let (mut seqno, mut last_key): (u64, Option<K>) = (0, None);
+        let iter = r.lines().filter_map(move |line| {
+            let line = match err_at!(IoError, line) {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            let (key, value) = match parser(&line) {
+                Ok(Some((key, value))) => (key, value),
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            match &last_key {
+                Some(last_key) if last_key.ge(&key) => {
+                    let msg = format!("text input not sorted at record {}", seqno + 1);
+                    return Some(err_at!(InvalidInput, msg: msg));
+                }
+                _ => (),
+            }
+            last_key = Some(key.clone());
+            seqno += 1;
+            let value = core::Value::new_upsert_value(value, seqno);
+            Some(Ok(Entry::new(key, value)))
+        });
+
+        self.build(iter, app_meta)
+    }
+
+    /// Build a new index by replaying mutations from `wal`, upto and
+    /// including `upto_seqno`. Mutations on the same key are folded into
+    /// its latest entry, set-cas operations are validated using the
+    /// [CasPolicy][core::CasPolicy] configured on `wal`, default being
+    /// `Strict`, and deletes are persisted as tombstones. Application operations are ignored.
+    ///
+    /// Mutations are sorted in memory upto the configured
+    /// [sort limit][Builder::set_sort_limit], beyond which they are
+    /// spilled to disk.
+    pub fn build_from_wal<H>(
+        self,
+        wal: Wal<K, V, H>,
+        upto_seqno: u64,
+        app_meta: Vec<u8>,
+    ) -> Result<usize>
+    where
+        K: 'static + Send + Default + Hash,
+        V: 'static + Send + Default,
+        H: Clone + BuildHasher,
+    {
+        let ifile = path::Path::new(&self.index_file);
+        let (dir, name) = match (ifile.parent(), ifile.file_name()) {
+            (Some(dir), Some(name)) => (dir.as_os_str(), name.to_string_lossy()),
+            _ => err_at!(InvalidFile, msg: format!("{:?}", self.index_file))?,
+        };
+
+        let mut sink = WalSink::new(Sorter::new(dir, &name, self.sort_limit), upto_seqno);
+        wal.replay(&mut sink, 0)?;
+        info!(
+            target: "robt  ",
+            "{:?}, replayed {} wal operations upto seqno:{}",
+            self.index_file, sink.to_ops(), upto_seqno
+        );
+
+        let iter = sink.into_entries()?;
+        self.build(iter, app_meta)
+    }
+
+    /// Build one or more index files from the supplied iterator, rolling
+    /// over to the next part when the index file hits the cap configured
+    /// via [set_max_index_size][Config::set_max_index_size]. Parts are
+    /// named `{name}-part{n}`, with `n` starting from ZERO, and each part
+    /// is a standalone snapshot with its own bitmap and value-log file.
+    /// Cap is checked as leaf blocks are flushed, hence a part can exceed
+    /// the cap by a leaf block, its intermediate blocks and the metadata.
+    ///
+    /// If cap is ZERO, a single snapshot is built using `name`. Return
+    /// the built snapshots in key order.
+    pub fn build_parts<I>(
+        dir: &ffi::OsStr,
+        name: &str,
+        mut config: Config,
+        iter: I,
+        app_meta: Vec<u8>,
+    ) -> Result<Vec<Snapshot<K, V, B>>>
+    where
+        K: Hash,
+        I: Iterator<Item = Result<Entry<K, V>>>,
+    {
+        let cap: u64 = convert_at!(config.max_index_size)?;
+        if cap == 0 {
+            Builder::<K, V, B>::initial(dir, name, config)?.build(iter, app_meta)?;
+            return Ok(vec![Snapshot::open(dir, name)?]);
+        }
+        // value-log file, if any, is derived from the part name.
+        config.vlog_file = None;
+
+        let mut iter = iter.peekable();
+        let mut snapshots = vec![];
+        loop {
+            let part_name = format!("{}-part{}", name, snapshots.len());
+            let part_fpos = Arc::new(AtomicU64::new(0));
+            let mut b = Builder::<K, V, B>::initial(dir, &part_name, config.clone())?;
+            b.part_fpos = Some(Arc::clone(&part_fpos));
+
+            // stop feeding entries to this part once it hits the cap.
+            let part = std::iter::from_fn(|| match part_fpos.load(SeqCst) {
+                fpos if fpos >= cap => None,
+                _ => iter.next(),
+            });
+            b.build(part, app_meta.clone())?;
+
+            let snapshot = Snapshot::open(dir, &part_name)?;
+            info!(
+                target: "robt  ",
+                "{:?}/{}, built part with {} entries", dir, part_name, snapshot.len()?
+            );
+            snapshots.push(snapshot);
+            if iter.peek().is_none() {
+                break Ok(snapshots);
+            }
+        }
+    }
+
+    /// Run the build pipeline over the supplied iterator, packing leaf
+    /// blocks and intermediate blocks and accumulating statistics, but
+    /// discard the blocks instead of writing them to disk. Return the
+    /// statistics of the index that would have been built, useful for
+    /// capacity planning. No file is created, or removed, under `dir`.
+    pub fn dry_run<I>(dir: &ffi::OsStr, name: &str, config: Config, iter: I) -> Result<Stats>
+    where
+        K: Hash,
+        I: Iterator<Item = Result<Entry<K, V>>>,
+    {
+        let mut b = Self::new_initial(dir, name, config, true /*dry_run*/)?;
+
+        let mut bscanner = {
+            let seqno: u64 = Default::default();
+            let iter = SeqnoCheck::new(iter, None);
+            let iter = UniqueCheck::new(iter, b.config.strict_unique);
+            BuildScan::new(scans::BitmappedScan::new(iter), seqno)
+        };
+        b.build_tree(&mut bscanner)?;
+        let (_, bitmap) = bscanner.update_stats(&mut b.stats)?.close()?;
+        b.stats.n_bitmap = bitmap.len()?;
+        b.stats.mem_bitmap = bitmap.to_vec().len();
+        b.config.verify_fanout(&b.stats);
+
+        let (_, n) = match b.iflusher.take() {
+            Some(iflusher) => iflusher.close_wait()?,
+            None => err_at!(Fatal, msg: format!("unreachable"))?,
+        };
+        if let Some(vflusher) = b.vflusher.take() {
+            vflusher.close_wait()?;
+        }
+
+        info!(
+            target: "robt  ",
+            "{:?}/{}, dry-run discarded {} index bytes, n_count:{}",
+            dir, name, n, b.stats.n_count
+        );
+        Ok(b.stats)
+    }
+
     /// Start building the index, this API should be used along with
     /// [build_finish][Builder::build_finish] to have more fine grained
     /// control, compared to [build][Builder::build], over the index build
@@ -1972,8 +4352,13 @@ where
     where
         I: Iterator<Item = Result<Entry<K, V>>>,
     {
+        if self.checkpoint.is_some() {
+            err_at!(APIMisuse, msg: format!("checkpoint with build_start"))?;
+        }
         let mut build_scanner = {
             let seqno: u64 = Default::default();
+            let iter = SeqnoCheck::new(iter, self.min_seqno);
+            let iter = UniqueCheck::new(iter, self.config.strict_unique);
             BuildScan::new(iter, seqno)
         };
         let root = self.build_tree(&mut build_scanner)?;
@@ -1985,9 +4370,15 @@ where
     /// [build_start][Builder::build_start] for details.
     pub fn build_finish(mut self, app_meta: Vec<u8>, bitmap: B, root: u64) -> Result<usize> {
         let (n_bitmap, bitmap) = (bitmap.len()?, bitmap.to_vec());
+        let tombs = encode_range_tombstones(&self.range_tombstones)?;
+        let dict = self.dict.as_ref().map_or(vec![], |d| d.as_dict().to_vec());
         let stats: String = {
             self.stats.n_bitmap = n_bitmap;
             self.stats.mem_bitmap = bitmap.len();
+            for tomb in self.range_tombstones.iter() {
+                self.stats.seqno = cmp::max(self.stats.seqno, tomb.seqno);
+            }
+            self.config.verify_fanout(&self.stats);
             self.stats.to_json()
         };
 
@@ -1998,6 +4389,8 @@ where
             MetaItem::AppMetadata(app_meta),
             MetaItem::Stats(stats),
             MetaItem::Marker(ROOT_MARKER.clone()), // tip of the index.
+            MetaItem::RangeTombstones(tombs),
+            MetaItem::Dictionary(dict),
         ];
 
         // flush blocks and close
@@ -2012,7 +4405,11 @@ where
             None => (),
         };
         // flush meta items to disk and close
-        let meta_block_bytes = write_meta_items(index_file, meta_items)?;
+        let meta_block_bytes = write_meta_items(index_file, meta_items, self.config.align)?;
+        if let Some(cp) = &self.checkpoint {
+            // build might have finished before the first checkpoint.
+            fs::remove_file(&cp.file).ok();
+        }
         Ok(convert_at!(meta_block_bytes)?)
     }
 
@@ -2030,24 +4427,34 @@ where
             z: ZBlock<K, V>,
             ms: Vec<MBlock<K, V>>,
         };
-        let mut c = {
-            let vfpos: u64 = convert_at!(self.stats.n_abytes)?;
-            Context {
-                fpos: 0,
-                zfpos: 0,
-                vfpos,
-                z: ZBlock::new_encode(vfpos, self.config.clone()),
-                ms: vec![MBlock::new_encode(self.config.clone())],
-            }
+        let (fpos, ms) = match self.resume.take() {
+            Some(Resume { fpos, ms, .. }) => (fpos, ms),
+            None => (0, vec![MBlock::new_encode(self.config.clone())]),
+        };
+        let mut c = Context {
+            fpos,
+            zfpos: fpos,
+            vfpos: self.vfpos,
+            z: ZBlock::new_encode(self.vfpos, self.config.clone()),
+            ms,
         };
 
         for entry in iter {
-            let entry = entry?;
+            let mut entry = self.apply_size_limit(entry?)?;
+            if let Some(hook) = self.flags_hook {
+                entry.set_flags(hook(&entry));
+            }
             // println!("build key: {:?}", entry.to_key());
             // println!("build entry: {}", entry.to_seqno());
-            match c.z.insert(&entry, &mut self.stats) {
+            self.sample_value(&entry)?;
+            match c.z.insert(&entry, self.dict.as_ref(), &mut self.stats) {
                 Ok(_) => (),
-                Err(Error::__ZBlockOverflow(_)) => {
+                Err(err @ Error::Internal(Internal::ZBlockOverflow(_)))
+                    if c.z.has_first_key()? == false =>
+                {
+                    self.to_overflow_err(err)?
+                }
+                Err(Error::Internal(Internal::ZBlockOverflow(_))) => {
                     // zbytes is z_blocksize
                     let (zbytes, vbytes) = c.z.finalize(&mut self.stats)?;
                     c.z.flush(self.iflusher.as_ref(), self.vflusher.as_ref())?;
@@ -2057,7 +4464,7 @@ where
                     let mut m = c.ms.pop().unwrap();
                     match m.insertz(c.z.as_first_key()?, c.zfpos) {
                         Ok(_) => c.ms.push(m),
-                        Err(Error::__MBlockOverflow(_)) => {
+                        Err(Error::Internal(Internal::MBlockOverflow(_))) => {
                             // x is m_blocksize
                             let x = m.finalize(&mut self.stats)?;
                             m.flush(self.iflusher.as_ref())?;
@@ -2067,7 +4474,8 @@ where
                             c.fpos = r.1;
 
                             m.reset()?;
-                            m.insertz(c.z.as_first_key()?, c.zfpos)?;
+                            m.insertz(c.z.as_first_key()?, c.zfpos)
+                                .or_else(|err| self.to_overflow_err(err))?;
                             c.ms.push(m)
                         }
                         Err(err) => return Err(err),
@@ -2075,11 +4483,19 @@ where
 
                     c.zfpos = c.fpos;
                     c.z.reset(c.vfpos)?;
+                    if let Some(part_fpos) = &self.part_fpos {
+                        part_fpos.store(c.fpos, SeqCst);
+                    }
+                    self.do_checkpoint(c.fpos, c.vfpos, &c.ms, entry.as_key())?;
 
-                    c.z.insert(&entry, &mut self.stats)?;
+                    c.z.insert(&entry, self.dict.as_ref(), &mut self.stats)
+                        .or_else(|err| self.to_overflow_err(err))?;
                 }
                 Err(err) => return Err(err),
             };
+            if let Some(cp) = self.checkpoint.as_mut() {
+                cp.add_entry(&entry);
+            }
         }
 
         if c.z.has_first_key()? == false && c.fpos == 0 {
@@ -2100,7 +4516,7 @@ where
             let mut m = c.ms.pop().unwrap();
             match m.insertz(c.z.as_first_key()?, c.zfpos) {
                 Ok(_) => c.ms.push(m),
-                Err(Error::__MBlockOverflow(_)) => {
+                Err(Error::Internal(Internal::MBlockOverflow(_))) => {
                     let x = m.finalize(&mut self.stats)?;
                     m.flush(self.iflusher.as_ref())?;
                     let mkey = m.as_first_key()?;
@@ -2109,7 +4525,8 @@ where
                     c.fpos = res.1;
 
                     m.reset()?;
-                    m.insertz(c.z.as_first_key()?, c.zfpos)?;
+                    m.insertz(c.z.as_first_key()?, c.zfpos)
+                        .or_else(|err| self.to_overflow_err(err))?;
                     c.ms.push(m);
                 }
                 Err(err) => return Err(err),
@@ -2135,47 +4552,484 @@ where
                 c.fpos = res.1
             }
         }
-        let n: u64 = convert_at!(self.config.m_blocksize)?;
-        Ok(c.fpos - n)
+        let n: u64 = convert_at!(self.config.m_blocksize)?;
+        Ok(c.fpos - n)
+    }
+
+    // sample values, until the compression dictionary is trained.
+    fn sample_value(&mut self, entry: &Entry<K, V>) -> Result<()> {
+        let sampler = match self.sampler.as_mut() {
+            Some(sampler) => sampler,
+            None => return Ok(()),
+        };
+        if let Some(value) = entry.to_native_value() {
+            let mut buf = vec![];
+            value.encode(&mut buf)?;
+            if let Some(dict) = sampler.add_sample(buf)? {
+                self.dict = Some(dict);
+                self.sampler = None;
+            }
+        }
+        Ok(())
+    }
+
+    // record a checkpoint at leaf block boundary, `key` is the first key
+    // of the next leaf block.
+    fn do_checkpoint(&mut self, fpos: u64, vfpos: u64, ms: &[MBlock<K, V>], key: &K) -> Result<()> {
+        match self.checkpoint.as_mut() {
+            Some(cp) => {
+                cp.n_zblocks += 1;
+                if (cp.n_zblocks % cp.every) != 0 {
+                    return Ok(());
+                }
+            }
+            None => return Ok(()),
+        }
+
+        // blocks posted to flushers shall be durable before checkpoint.
+        if let Some(iflusher) = &self.iflusher {
+            iflusher.request(vec![])?;
+        }
+        if let Some(vflusher) = &self.vflusher {
+            vflusher.request(vec![])?;
+        }
+
+        let cp = match &self.checkpoint {
+            Some(cp) => cp,
+            None => err_at!(Fatal, msg: format!("unreachable"))?,
+        };
+        let mut ms_state = vec![];
+        for m in ms.iter() {
+            let (first_key, offsets, block) = m.to_encode_state()?;
+            ms_state.push((first_key.cloned(), offsets.to_vec(), block.to_vec()));
+        }
+        let ckpt = Checkpoint {
+            every: cp.every,
+            key: key.clone(),
+            fpos,
+            vfpos,
+            seqno: cp.seqno,
+            n_count: cp.n_count,
+            n_deleted: cp.n_deleted,
+            stats: self.stats.to_json(),
+            tombs: encode_range_tombstones(&self.range_tombstones)?,
+            bitmap: cp.bitmap.to_vec(),
+            dict: self.dict.as_ref().map_or(vec![], |d| d.as_dict().to_vec()),
+            ms: ms_state,
+        };
+        ckpt.persist(&cp.file)?;
+
+        debug!(
+            target: "robt  ",
+            "{:?}, checkpoint fpos:{} vfpos:{} n_count:{}", cp.file, fpos, vfpos, cp.n_count
+        );
+        Ok(())
+    }
+
+    // overflow on an empty block means the entry, or its key, can never
+    // fit in the configured block size.
+    fn to_overflow_err<T>(&self, err: Error) -> Result<T> {
+        match err {
+            Error::Internal(Internal::ZBlockOverflow(n)) => {
+                let z = self.config.z_blocksize;
+                let msg = format!("entry of {} bytes exceeds z_blocksize {}", n, z);
+                err_at!(InvalidInput, msg: msg)
+            }
+            Error::Internal(Internal::MBlockOverflow(n)) => {
+                let m = self.config.m_blocksize;
+                let msg = format!("key of {} bytes exceeds m_blocksize {}", n, m);
+                err_at!(InvalidInput, msg: msg)
+            }
+            err => Err(err),
+        }
+    }
+
+    fn insertms(
+        &mut self,
+        mut ms: Vec<MBlock<K, V>>,
+        mut fpos: u64,
+        key: &K,
+        mfpos: u64,
+    ) -> Result<(Vec<MBlock<K, V>>, u64)> {
+        // println!("insertms key:{:?} {}", key, mfpos);
+        let m0 = ms.pop();
+        let m0 = match m0 {
+            None => {
+                // println!("new mblock for {:?} {}", key, mfpos);
+                let mut m0 = MBlock::new_encode(self.config.clone());
+                m0.insertm(key, mfpos)
+                    .or_else(|err| self.to_overflow_err(err))?;
+                m0
+            }
+            Some(mut m0) => match m0.insertm(key, mfpos) {
+                Ok(_) => m0,
+                Err(Error::Internal(Internal::MBlockOverflow(_))) => {
+                    // println!("overflow for {:?} {}", key, mfpos);
+                    // x is m_blocksize
+                    let x = m0.finalize(&mut self.stats)?;
+                    m0.flush(self.iflusher.as_ref())?;
+                    let mkey = m0.as_first_key()?;
+                    let res = self.insertms(ms, fpos + x, mkey, fpos)?;
+                    ms = res.0;
+                    fpos = res.1;
+
+                    m0.reset()?;
+                    m0.insertm(key, mfpos)?;
+                    m0
+                }
+                Err(err) => return Err(err),
+            },
+        };
+        ms.push(m0);
+        Ok((ms, fpos))
+    }
+}
+
+// Book-keeping for recording checkpoints, refer to Builder::set_checkpoint.
+struct Checkpointer<K, B> {
+    file: ffi::OsString,
+    every: usize,
+    n_zblocks: usize,
+    // seqno, n_count, n_deleted and bitmap, for entries that are added
+    // to leaf blocks.
+    seqno: u64,
+    n_count: u64,
+    n_deleted: usize,
+    bitmap: B,
+    add_key: fn(&mut B, &K),
+}
+
+impl<K, B> Checkpointer<K, B> {
+    fn add_entry<V>(&mut self, entry: &Entry<K, V>)
+    where
+        K: Clone + Ord,
+        V: Clone + Diff,
+    {
+        self.seqno = cmp::max(self.seqno, entry.to_seqno());
+        self.n_count += 1;
+        if entry.is_deleted() {
+            self.n_deleted += 1;
+        }
+        (self.add_key)(&mut self.bitmap, entry.as_key());
+    }
+}
+
+// Build state restored from checkpoint, refer to Builder::resume.
+struct Resume<K, V> {
+    key: K,
+    fpos: u64,
+    ms: Vec<MBlock<K, V>>,
+}
+
+// Checkpoint file format, all integers are encoded in big-endian, and
+// byte-strings are prefixed with their 8-byte length.
+//
+// *-------------------------------------------*
+// |  version  |   every   |   fpos   |  vfpos  |
+// *-------------------------------------------*
+// |   seqno   |  n_count  | n_deleted |        |
+// *-------------------------------------------*
+// |  resume-key  |  stats  |  tombs  |  bitmap |
+// *-------------------------------------------*
+// |                dictionary                 |
+// *-------------------------------------------*
+// |  n-mblocks  | {first-key, offsets, block}..|
+// *-------------------------------------------*
+//
+// Checkpoint file is replaced atomically by renaming a temporary file.
+struct Checkpoint<K> {
+    every: usize,
+    key: K,
+    fpos: u64,
+    vfpos: u64,
+    seqno: u64,
+    n_count: u64,
+    n_deleted: usize,
+    stats: String,
+    tombs: Vec<u8>,
+    bitmap: Vec<u8>,
+    dict: Vec<u8>,
+    ms: Vec<(Option<K>, Vec<u32>, Vec<u8>)>,
+}
+
+impl<K> Checkpoint<K>
+where
+    K: Serialize,
+{
+    const VERSION: u64 = 1;
+
+    fn persist(&self, file: &ffi::OsStr) -> Result<()> {
+        let mut buf = vec![];
+        let every: u64 = convert_at!(self.every)?;
+        let n_deleted: u64 = convert_at!(self.n_deleted)?;
+        let nums = [
+            Self::VERSION,
+            every,
+            self.fpos,
+            self.vfpos,
+            self.seqno,
+            self.n_count,
+            n_deleted,
+        ];
+        for n in nums.iter() {
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+        let mut scratch = vec![];
+        self.key.encode(&mut scratch)?;
+        Self::encode_bytes(&mut buf, &scratch)?;
+        Self::encode_bytes(&mut buf, self.stats.as_bytes())?;
+        Self::encode_bytes(&mut buf, &self.tombs)?;
+        Self::encode_bytes(&mut buf, &self.bitmap)?;
+        Self::encode_bytes(&mut buf, &self.dict)?;
+
+        let n: u64 = convert_at!(self.ms.len())?;
+        buf.extend_from_slice(&n.to_be_bytes());
+        for (first_key, offsets, block) in self.ms.iter() {
+            scratch.truncate(0);
+            if let Some(first_key) = first_key {
+                first_key.encode(&mut scratch)?;
+            }
+            Self::encode_bytes(&mut buf, &scratch)?;
+            scratch.truncate(0);
+            offsets
+                .iter()
+                .for_each(|offset| scratch.extend_from_slice(&offset.to_be_bytes()));
+            Self::encode_bytes(&mut buf, &scratch)?;
+            Self::encode_bytes(&mut buf, block)?;
+        }
+
+        let tmp_file = {
+            let mut tmp_file = file.to_os_string();
+            tmp_file.push(".tmp");
+            tmp_file
+        };
+        let mut fd = util::create_file_a(tmp_file.clone())?;
+        write_file!(fd, &buf, tmp_file.clone(), "robt-checkpoint")?;
+        err_at!(IoError, fd.sync_all())?;
+        err_at!(IoError, fs::rename(&tmp_file, file))?;
+        Ok(())
+    }
+
+    fn load(file: &ffi::OsStr) -> Result<Checkpoint<K>>
+    where
+        K: Default,
+    {
+        let buf = err_at!(IoError, fs::read(file))?;
+        let mut off = 0;
+
+        let version = Self::decode_u64(&buf, &mut off)?;
+        if version != Self::VERSION {
+            let msg = format!("{:?}, checkpoint version {}", file, version);
+            err_at!(InvalidFile, msg: msg)?;
+        }
+        let every = Self::decode_u64(&buf, &mut off)?;
+        let fpos = Self::decode_u64(&buf, &mut off)?;
+        let vfpos = Self::decode_u64(&buf, &mut off)?;
+        let seqno = Self::decode_u64(&buf, &mut off)?;
+        let n_count = Self::decode_u64(&buf, &mut off)?;
+        let n_deleted = Self::decode_u64(&buf, &mut off)?;
+        let key = Self::decode_key(Self::decode_bytes(&buf, &mut off)?)?;
+        let stats = match std::str::from_utf8(Self::decode_bytes(&buf, &mut off)?) {
+            Ok(stats) => stats.to_string(),
+            Err(err) => err_at!(InvalidFile, msg: format!("{:?}, {}", file, err))?,
+        };
+        let tombs = Self::decode_bytes(&buf, &mut off)?.to_vec();
+        let bitmap = Self::decode_bytes(&buf, &mut off)?.to_vec();
+        let dict = Self::decode_bytes(&buf, &mut off)?.to_vec();
+
+        let mut ms = vec![];
+        for _ in 0..Self::decode_u64(&buf, &mut off)? {
+            let first_key = match Self::decode_bytes(&buf, &mut off)? {
+                fk if fk.len() == 0 => None,
+                fk => Some(Self::decode_key(fk)?),
+            };
+            let offsets: Vec<u32> = Self::decode_bytes(&buf, &mut off)?
+                .chunks(4)
+                .map(|x| array_at!(x).map(u32::from_be_bytes))
+                .collect::<Result<Vec<u32>>>()?;
+            let block = Self::decode_bytes(&buf, &mut off)?.to_vec();
+            ms.push((first_key, offsets, block));
+        }
+
+        Ok(Checkpoint {
+            every: convert_at!(every)?,
+            key,
+            fpos,
+            vfpos,
+            seqno,
+            n_count,
+            n_deleted: convert_at!(n_deleted)?,
+            stats,
+            tombs,
+            bitmap,
+            dict,
+            ms,
+        })
+    }
+
+    fn encode_bytes(buf: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+        let n: u64 = convert_at!(bytes.len())?;
+        buf.extend_from_slice(&n.to_be_bytes());
+        buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn decode_u64(buf: &[u8], off: &mut usize) -> Result<u64> {
+        check_remaining!(buf[*off..], 8, "checkpoint")?;
+        let n = u64::from_be_bytes(array_at!(buf[*off..*off + 8])?);
+        *off += 8;
+        Ok(n)
+    }
+
+    fn decode_bytes<'a>(buf: &'a [u8], off: &mut usize) -> Result<&'a [u8]> {
+        let n = Self::decode_u64(buf, off)?;
+        let n: usize = convert_at!(n)?;
+        check_remaining!(buf[*off..], n, "checkpoint")?;
+        let bytes = &buf[*off..*off + n];
+        *off += n;
+        Ok(bytes)
+    }
+
+    fn decode_key(buf: &[u8]) -> Result<K>
+    where
+        K: Default,
+    {
+        let mut key: K = Default::default();
+        key.decode(buf)?;
+        Ok(key)
+    }
+}
+
+// Validate that seqno does not regress across versions of an entry,
+// and optionally, that entries are newer than `min_seqno`.
+struct SeqnoCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    iter: I,
+    min_seqno: Option<u64>,
+    n_count: u64,
+}
+
+impl<K, V, I> SeqnoCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    fn new(iter: I, min_seqno: Option<u64>) -> SeqnoCheck<K, V, I> {
+        SeqnoCheck {
+            iter,
+            min_seqno,
+            n_count: Default::default(),
+        }
+    }
+
+    fn check(&self, entry: &Entry<K, V>) -> Result<()> {
+        let seqno = entry.to_seqno();
+        match self.min_seqno {
+            Some(min_seqno) if seqno <= min_seqno => {
+                let msg = format!(
+                    "entry {} seqno {} regress, expected above {}",
+                    self.n_count, seqno, min_seqno
+                );
+                err_at!(InvalidInput, msg: msg)?
+            }
+            _ => (),
+        }
+        let mut newer = seqno;
+        for delta in entry.as_deltas().iter() {
+            let older = delta.to_seqno();
+            if older >= newer {
+                let msg = format!(
+                    "entry {} version seqno {} regress, expected below {}",
+                    self.n_count, older, newer
+                );
+                err_at!(InvalidInput, msg: msg)?
+            }
+            newer = older;
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, I> Iterator for SeqnoCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next() {
+            Some(Ok(entry)) => {
+                let res = self.check(&entry);
+                self.n_count += 1;
+                Some(res.map(|_| entry))
+            }
+            item => item,
+        }
+    }
+}
+
+// Validate that consecutive entries don't share the same key, checked
+// only if `strict` is true.
+struct UniqueCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    iter: I,
+    strict: bool,
+    last_key: Option<K>,
+    n_count: u64,
+}
+
+impl<K, V, I> UniqueCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    fn new(iter: I, strict: bool) -> UniqueCheck<K, V, I> {
+        UniqueCheck {
+            iter,
+            strict,
+            last_key: None,
+            n_count: Default::default(),
+        }
     }
+}
 
-    fn insertms(
-        &mut self,
-        mut ms: Vec<MBlock<K, V>>,
-        mut fpos: u64,
-        key: &K,
-        mfpos: u64,
-    ) -> Result<(Vec<MBlock<K, V>>, u64)> {
-        // println!("insertms key:{:?} {}", key, mfpos);
-        let m0 = ms.pop();
-        let m0 = match m0 {
-            None => {
-                // println!("new mblock for {:?} {}", key, mfpos);
-                let mut m0 = MBlock::new_encode(self.config.clone());
-                m0.insertm(key, mfpos)?;
-                m0
-            }
-            Some(mut m0) => match m0.insertm(key, mfpos) {
-                Ok(_) => m0,
-                Err(Error::__MBlockOverflow(_)) => {
-                    // println!("overflow for {:?} {}", key, mfpos);
-                    // x is m_blocksize
-                    let x = m0.finalize(&mut self.stats)?;
-                    m0.flush(self.iflusher.as_ref())?;
-                    let mkey = m0.as_first_key()?;
-                    let res = self.insertms(ms, fpos + x, mkey, fpos)?;
-                    ms = res.0;
-                    fpos = res.1;
+impl<K, V, I> Iterator for UniqueCheck<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    type Item = Result<Entry<K, V>>;
 
-                    m0.reset()?;
-                    m0.insertm(key, mfpos)?;
-                    m0
-                }
-                Err(err) => return Err(err),
-            },
-        };
-        ms.push(m0);
-        Ok((ms, fpos))
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next() {
+            Some(Ok(entry)) if self.strict => {
+                let key = entry.to_key();
+                let res = match &self.last_key {
+                    Some(last_key) if last_key == &key => {
+                        let msg = format!("entry {} duplicate key", self.n_count);
+                        err_at!(InvalidInput, msg: msg)
+                    }
+                    _ => Ok(entry),
+                };
+                self.last_key = Some(key);
+                self.n_count += 1;
+                Some(res)
+            }
+            item => item,
+        }
     }
 }
 
@@ -2265,6 +5119,7 @@ where
     y_iter: Box<Iter<'a, K, V, B>>, // old iterator
     x_entry: Option<Result<Entry<K, V>>>,
     y_entry: Option<Result<Entry<K, V>>>,
+    policy: Option<SharedMergePolicy<K, V>>,
 }
 
 impl<'a, K, V, I, B> CommitScan<'a, K, V, I, B>
@@ -2274,7 +5129,11 @@ where
     <V as Diff>::D: Default + Serialize,
     I: Iterator<Item = Result<Entry<K, V>>>,
 {
-    fn new(mut x_iter: I, mut y_iter: Box<Iter<'a, K, V, B>>) -> CommitScan<'a, K, V, I, B> {
+    fn new(
+        mut x_iter: I,
+        mut y_iter: Box<Iter<'a, K, V, B>>,
+        policy: Option<SharedMergePolicy<K, V>>,
+    ) -> CommitScan<'a, K, V, I, B> {
         let x_entry = x_iter.next();
         let y_entry = y_iter.next();
         CommitScan {
@@ -2282,6 +5141,7 @@ where
             y_iter,
             x_entry,
             y_entry,
+            policy,
         }
     }
 
@@ -2320,7 +5180,10 @@ where
                     // println!("commitscan Equal {} {}", xe.to_seqno(), ye.to_seqno(),);
                     // fetch the value from old snapshot, only value.
                     match self.y_iter.snap.fetch(&mut ye, false, false) {
-                        Ok(()) => Some(xe.xmerge(ye)),
+                        Ok(()) => match self.policy.as_mut() {
+                            Some(policy) => Some(policy.merge(xe, ye)),
+                            None => Some(xe.xmerge(ye)),
+                        },
                         Err(err) => Some(Err(err)),
                     }
                 }
@@ -2340,9 +5203,47 @@ where
     }
 }
 
+// Value-log segment that is currently appended by the flusher thread.
+struct VlogSegment {
+    vlog_file: ffi::OsString,
+    segment: usize,
+    size: usize,
+    header: Vec<u8>,
+}
+
+impl VlogSegment {
+    fn new(vlog_file: &ffi::OsStr, segment: usize, size: usize, hdr: Vec<u8>) -> Option<Self> {
+        Some(VlogSegment {
+            vlog_file: vlog_file.to_os_string(),
+            segment,
+            size,
+            header: hdr,
+        })
+    }
+}
+
+// drain blocks posted by a dry-run build, without writing them. Return
+// the number of bytes discarded.
+fn thread_discard(file: ffi::OsString, rx: rt::Rx<Vec<u8>, ()>) -> Result<(ffi::OsString, u64)> {
+    let mut n = 0_u64;
+    for (data, tx) in rx {
+        if let Some(tx) = tx {
+            // sync request, nothing to sync.
+            err_at!(IPCFail, tx.send(()))?;
+            continue;
+        }
+        let m: u64 = convert_at!(data.len())?;
+        n += m;
+    }
+    Ok((file, n))
+}
+
 fn thread_flush(
-    file: ffi::OsString, // for debuging purpose
-    create: bool,        // if true create a new file
+    mut file: ffi::OsString, // for debuging purpose
+    create: bool,            // if true create a new file
+    cipher: Option<FileCipher>,
+    mut vseg: Option<VlogSegment>,
+    depth: usize, // number of outstanding writes
     rx: rt::Rx<Vec<u8>, ()>,
 ) -> Result<(ffi::OsString, u64)> {
     let (mut fd, fpos) = if create {
@@ -2356,15 +5257,72 @@ fn thread_flush(
 
     err_at!(IoError, fd.lock_shared())?; // <---- read lock
 
-    for (data, _) in rx {
+    let mut writers = match depth {
+        0 | 1 => None,
+        depth => Some(FlushWriters::new(depth)),
+    };
+
+    // value-log positions are logical, (segment, offset)
+    let mut wpos = match &vseg {
+        Some(vseg) => vlog::from_segment(vseg.segment, fpos)?,
+        None => fpos,
+    };
+    for (mut data, tx) in rx {
+        if let Some(tx) = tx {
+            // sync request, data posted so far shall be durable.
+            if let Some(writers) = &mut writers {
+                writers.wait()?;
+            }
+            err_at!(IoError, fd.sync_all())?;
+            err_at!(IPCFail, tx.send(()))?;
+            continue;
+        }
         // println!("flusher {:?} {} {}", file, fpos, data.len());
         // fpos += data.len();
-        let n = write_file!(fd, &data, file.clone(), "robt-thread-flush")?;
-        if n != data.len() {
-            err_at!(IoError, fd.unlock())?; // <----- read un-lock
+        let m: u64 = convert_at!(data.len())?;
+        if let Some(vseg) = &mut vseg {
+            let vpos = vlog_place(wpos, m, vseg.size)?;
+            if vpos != wpos {
+                // roll over to the next segment file.
+                if let Some(writers) = &mut writers {
+                    writers.wait()?;
+                }
+                err_at!(IoError, fd.sync_all())?;
+                err_at!(IoError, fd.unlock())?; // <----- read un-lock
+                vseg.segment += 1;
+                file = Config::stitch_vlog_segment(&vseg.vlog_file, vseg.segment);
+                fd = util::create_file_a(file.clone())?;
+                err_at!(IoError, fd.lock_shared())?; // <---- read lock
+                write_file!(fd, &vseg.header, file.clone(), "robt-vlog-header")?;
+                wpos = vpos;
+            }
+        }
+        if let Some(cipher) = &cipher {
+            cipher.encrypt(wpos, &mut data)?;
+        }
+        match &mut writers {
+            Some(writers) => {
+                // block is written at its reserved position in the file.
+                let fpos = match &vseg {
+                    Some(_) => vlog::to_segment(wpos)?.1,
+                    None => wpos,
+                };
+                writers.post(&file, fpos, data)?;
+            }
+            None => {
+                let n = write_file!(fd, &data, file.clone(), "robt-thread-flush")?;
+                if n != data.len() {
+                    err_at!(IoError, fd.unlock())?; // <----- read un-lock
+                }
+                util::put_buffer(data);
+            }
         }
+        wpos += m;
     }
 
+    if let Some(writers) = &mut writers {
+        writers.wait()?;
+    }
     err_at!(IoError, fd.sync_all())?;
 
     // file descriptor and receiver channel shall be dropped.
@@ -2372,38 +5330,144 @@ fn thread_flush(
     Ok((file, fpos))
 }
 
+type FlushWrite = (ffi::OsString, u64, Vec<u8>);
+
+// Pool of writer threads, used by flusher to keep more than one write
+// outstanding. Each block is written at its reserved file position.
+struct FlushWriters {
+    tx: Option<mpsc::SyncSender<FlushWrite>>,
+    done: mpsc::Receiver<Result<()>>,
+    handles: Vec<thread::JoinHandle<()>>,
+    n_pending: usize,
+}
+
+impl FlushWriters {
+    fn new(depth: usize) -> FlushWriters {
+        let (tx, rx) = mpsc::sync_channel(depth);
+        let (done_tx, done) = mpsc::channel();
+        let rx = Arc::new(sync::Mutex::new(rx));
+        let handles = (0..depth)
+            .map(|_| {
+                let (rx, done_tx) = (Arc::clone(&rx), done_tx.clone());
+                thread::spawn(move || thread_pwrite(rx, done_tx))
+            })
+            .collect();
+
+        FlushWriters {
+            tx: Some(tx),
+            done,
+            handles,
+            n_pending: 0,
+        }
+    }
+
+    fn post(&mut self, file: &ffi::OsString, fpos: u64, data: Vec<u8>) -> Result<()> {
+        // collect completed writes, fail early on error.
+        while let Ok(res) = self.done.try_recv() {
+            self.n_pending -= 1;
+            res?;
+        }
+        match &self.tx {
+            Some(tx) => err_at!(IPCFail, tx.send((file.clone(), fpos, data)))?,
+            None => err_at!(Fatal, msg: format!("unreachable"))?,
+        }
+        self.n_pending += 1;
+        Ok(())
+    }
+
+    // wait for all outstanding writes to complete.
+    fn wait(&mut self) -> Result<()> {
+        let mut res = Ok(());
+        while self.n_pending > 0 {
+            let r = err_at!(IPCFail, self.done.recv())?;
+            self.n_pending -= 1;
+            res = res.and(r);
+        }
+        res
+    }
+}
+
+impl Drop for FlushWriters {
+    fn drop(&mut self) {
+        self.tx.take();
+        for handle in self.handles.drain(..) {
+            handle.join().ok();
+        }
+    }
+}
+
+fn thread_pwrite(rx: Arc<sync::Mutex<mpsc::Receiver<FlushWrite>>>, done: mpsc::Sender<Result<()>>) {
+    let mut fd: Option<(ffi::OsString, fs::File)> = None;
+    loop {
+        let (file, fpos, data) = match rx.lock().map(|rx| rx.recv()) {
+            Ok(Ok(item)) => item,
+            _ => break,
+        };
+        let res = do_pwrite(&mut fd, file, fpos, &data);
+        util::put_buffer(data);
+        if done.send(res).is_err() {
+            break;
+        }
+    }
+}
+
+fn do_pwrite(
+    fd: &mut Option<(ffi::OsString, fs::File)>,
+    file: ffi::OsString,
+    fpos: u64,
+    data: &[u8],
+) -> Result<()> {
+    // flusher moves on to the next file, after value-log segment roll over.
+    let mut wfd = match fd.take() {
+        Some((wfile, wfd)) if wfile == file => wfd,
+        _ => util::open_file_pw(&file)?,
+    };
+    err_at!(IoError, wfd.seek(io::SeekFrom::Start(fpos)))?;
+    write_file!(wfd, data, file.clone(), "robt-flush-pwrite")?;
+    *fd = Some((file, wfd));
+    Ok(())
+}
+
 // enumerated index file, that can use file-access or mmap-access based
 // on configured variant.
 enum IndexFile {
     Block {
         fd: fs::File,
         file: ffi::OsString,
+        cipher: Option<FileCipher>,
     },
     Mmap {
         fd: fs::File,
         mmap: memmap::Mmap,
         file: ffi::OsString,
+        cipher: Option<FileCipher>,
     },
 }
 
 impl IndexFile {
     // always created for file access.
-    fn new_block(file: ffi::OsString) -> Result<IndexFile> {
+    fn new_block(file: ffi::OsString, cipher: Option<FileCipher>) -> Result<IndexFile> {
         Ok(IndexFile::Block {
             fd: util::open_file_r(&file)?,
             file,
+            cipher,
         })
     }
 
     // and later on converted to mmap access, if configured,
     unsafe fn set_mmap(&mut self, ok: bool) -> Result<()> {
         match self {
-            IndexFile::Block { file, .. } if ok => {
-                let file = file.clone();
+            IndexFile::Block { file, cipher, .. } if ok => {
+                let (file, cipher) = (file.clone(), cipher.clone());
                 let fd = util::open_file_r(&file)?;
                 match memmap::Mmap::map(&fd) {
                     Ok(mmap) => {
-                        *self = IndexFile::Mmap { fd, file, mmap };
+                        *self = IndexFile::Mmap {
+                            fd,
+                            file,
+                            mmap,
+                            cipher,
+                        };
                         Ok(())
                     }
                     Err(err) => {
@@ -2412,10 +5476,10 @@ impl IndexFile {
                     }
                 }
             }
-            IndexFile::Mmap { file, .. } if !ok => {
-                let file = file.clone();
+            IndexFile::Mmap { file, cipher, .. } if !ok => {
+                let (file, cipher) = (file.clone(), cipher.clone());
                 let fd = util::open_file_r(&file)?;
-                *self = IndexFile::Block { file, fd };
+                *self = IndexFile::Block { file, fd, cipher };
                 Ok(())
             }
             IndexFile::Block { .. } => Ok(()),
@@ -2424,16 +5488,26 @@ impl IndexFile {
     }
 
     fn read_buffer(&mut self, fpos: u64, n: usize, msg: &str) -> Result<Vec<u8>> {
-        Ok(match self {
-            IndexFile::Block { fd, .. } => {
+        let (mut buf, cipher) = match self {
+            IndexFile::Block { fd, cipher, file } => {
                 let n: u64 = convert_at!(n)?;
-                read_file!(fd, fpos, n, msg)?
+                match read_file!(fd, fpos, n, msg) {
+                    Ok(buf) => (buf, cipher),
+                    Err(err) => {
+                        let file = Some(file.clone());
+                        return Err(err.into_file_fail(file, Some(fpos), None));
+                    }
+                }
             }
-            IndexFile::Mmap { mmap, .. } => {
+            IndexFile::Mmap { mmap, cipher, .. } => {
                 let start: usize = convert_at!(fpos)?;
-                mmap[start..(start + n)].to_vec()
+                (mmap[start..(start + n)].to_vec(), cipher)
             }
-        })
+        };
+        if let Some(cipher) = cipher {
+            cipher.decrypt(fpos, &mut buf)?;
+        }
+        Ok(buf)
     }
 
     fn to_file(&self) -> ffi::OsString {
@@ -2461,8 +5535,11 @@ impl IndexFile {
 
 /// Read handle into [Robt] indexes.
 ///
-/// Every open snapshot will hold an open file-descriptors, and two
-/// open file-descritors when configured with value-log file.
+/// Every open snapshot will hold an open file-descriptors, and an
+/// additional file-descriptor for each value-log segment, when
+/// configured with value-log file.
+/// Values and deltas are fetched from value-log using positional reads,
+/// that leave the file cursor untouched.
 pub struct Snapshot<K, V, B>
 where
     K: Clone + Ord + Serialize,
@@ -2476,7 +5553,12 @@ where
 
     // working fields
     index_fd: IndexFile,
-    valog_fd: Option<(ffi::OsString, fs::File)>,
+    valog_fd: Option<(ffi::OsString, Vec<fs::File>)>,
+    valog_cipher: Option<FileCipher>,
+    valog_dict: Option<ValueDict>,
+    runtime: RuntimeStats,
+    // decoded lazily, refer to to_range_tombstones().
+    range_tombstones: Option<Arc<Vec<RangeTombstone<K>>>>,
 
     _phantom_key: marker::PhantomData<K>,
     _phantom_val: marker::PhantomData<V>,
@@ -2494,12 +5576,14 @@ where
                 target: "robtr ", "{}, unlock index file {}", self.name, err
             ),
         }
-        if let Some((_, fd)) = &self.valog_fd {
-            match fd.unlock() {
-                Ok(_) => (),
-                Err(err) => error!(
-                    target: "robtr ", "{}, unlock vlog file {}", self.name, err
-                ),
+        if let Some((_, fds)) = &self.valog_fd {
+            for fd in fds.iter() {
+                match fd.unlock() {
+                    Ok(_) => (),
+                    Err(err) => error!(
+                        target: "robtr ", "{}, unlock vlog file {}", self.name, err
+                    ),
+                }
             }
         }
 
@@ -2536,10 +5620,38 @@ where
             err_at!(InvalidFile, msg: format!("{:?}/{}", dir, name))
         }?;
 
+        verify_lineage(dir, name, &stats)?;
+        let vlog_uuid_want = stats.vlog_uuid.clone();
+
+        // ciphers are located using the key-id persisted in stats.
+        let (icipher, valog_cipher) = match stats.cipher_key_id.as_str() {
+            "" => (None, None),
+            key_id => {
+                let cipher = robt_cipher::get_cipher(key_id)?;
+                let icipher = FileCipher::new(Arc::clone(&cipher), &stats.build_uuid)?;
+                let vcipher = match stats.vlog_uuid.as_str() {
+                    "" => None,
+                    vlog_uuid => Some(FileCipher::new(cipher, vlog_uuid)?),
+                };
+                (Some(icipher), vcipher)
+            }
+        };
+        // so is the compressor, dictionary is persisted as meta item.
+        let valog_dict = match &meta_items[6] {
+            MetaItem::Dictionary(dict) if dict.len() > 0 => {
+                let compressor = robt_compress::get_compressor(&stats.compressor)?;
+                Some(ValueDict::new(compressor, dict.clone()))
+            }
+            _ => None,
+        };
+
         let config: Config = stats.into();
 
         // open index file.
-        let index_fd = IndexFile::new_block(Config::stitch_index_file(dir, name))?;
+        let index_fd = {
+            let index_file = Config::stitch_index_file(dir, name);
+            IndexFile::new_block(index_file, icipher)?
+        };
         err_at!(IoError, index_fd.as_fd().lock_shared())?;
         // open optional value log file.
         let valog_fd = match config.vlog_file {
@@ -2552,9 +5664,22 @@ where
                     None => err_at!(InvalidFile, msg: format!("{:?}", vfile)),
                 }?);
                 let vlog_file = vpath.as_os_str().to_os_string();
-                let fd = util::open_file_r(&vlog_file)?;
-                err_at!(IoError, fd.lock_shared())?;
-                Some((vlog_file, fd))
+                let mut fds = vec![];
+                for file in Config::to_vlog_segments(&vlog_file) {
+                    let mut fd = util::open_file_r(&file)?;
+                    err_at!(IoError, fd.lock_shared())?;
+
+                    let vlog_uuid = read_vlog_uuid(&mut fd, &file)?;
+                    if vlog_uuid != vlog_uuid_want {
+                        let msg = format!(
+                            "{:?}/{} expects vlog {}, {:?} is vlog {}",
+                            dir, name, vlog_uuid_want, file, vlog_uuid
+                        );
+                        err_at!(InvalidFile, msg: msg)?;
+                    }
+                    fds.push(fd);
+                }
+                Some((vlog_file, fds))
             }
             None => None,
         };
@@ -2568,6 +5693,10 @@ where
 
             index_fd,
             valog_fd,
+            valog_cipher,
+            valog_dict,
+            runtime: Default::default(),
+            range_tombstones: None,
 
             _phantom_key: marker::PhantomData,
             _phantom_val: marker::PhantomData,
@@ -2579,6 +5708,34 @@ where
         Ok(snap) // Okey dockey
     }
 
+    /// Read only the meta block of BTree snapshot, without opening the
+    /// value-log or setting up reader state. Useful for inventory and
+    /// garbage-collection tools that list many snapshots.
+    pub fn open_meta(dir: &ffi::OsStr, name: &str) -> Result<SnapshotMeta> {
+        let (meta_items, meta_block_bytes) = read_meta_items(dir, name)?;
+
+        let mut meta = SnapshotMeta {
+            dir: dir.to_os_string(),
+            name: name.to_string(),
+            meta_block_bytes,
+            ..Default::default()
+        };
+        for item in meta_items.into_iter() {
+            match item {
+                MetaItem::Root(root) if root == std::u64::MAX => meta.root = None,
+                MetaItem::Root(root) => meta.root = Some(root),
+                MetaItem::Bitmap(data) => meta.bitmap_len = data.len(),
+                MetaItem::AppMetadata(data) => meta.app_meta = data,
+                MetaItem::Stats(stats) => meta.stats = stats.parse()?,
+                _ => (),
+            }
+        }
+
+        debug!(target: "robtr ", "{:?}/{}, open snapshot meta", dir, name);
+
+        Ok(meta)
+    }
+
     pub fn set_mmap(&mut self, ok: bool) -> Result<()> {
         unsafe { self.index_fd.set_mmap(ok) }
     }
@@ -2598,9 +5755,20 @@ where
         name.is_ok()
     }
 
+    /// Return value-log file and its segment files, if any. Empty if
+    /// snapshot is not configured with value-log.
+    pub fn to_vlog_files(&self) -> Vec<ffi::OsString> {
+        match &self.valog_fd {
+            Some((vlog_file, fds)) => (0..fds.len())
+                .map(|segment| Config::stitch_vlog_segment(vlog_file, segment))
+                .collect(),
+            None => vec![],
+        }
+    }
+
     pub fn purge(self) -> Result<()> {
         let index_file = self.index_fd.to_file();
-        let vlog_file = self.valog_fd.as_ref().map(|x| x.0.clone());
+        let vlog_files = self.to_vlog_files();
         let (dir, name) = (self.dir.clone(), self.name.clone());
 
         mem::drop(self); // IMPORTANT: Close this snapshot first.
@@ -2612,16 +5780,16 @@ where
             _ => err_at!(Fatal, msg: format!("unreachable")),
         }?;
 
-        let res = if let Some(vlog_file) = vlog_file {
-            match purge_file(vlog_file.clone(), &mut vec![], &mut vec![]) {
+        let mut res = Ok(());
+        for vlog_file in vlog_files.into_iter() {
+            let r = match purge_file(vlog_file.clone(), &mut vec![], &mut vec![]) {
                 "ok" => Ok(()),
                 "locked" => err_at!(InvalidFile, msg: format!("{:?} locked", vlog_file)),
                 "error" => err_at!(Fatal, msg: format!("error unlocking {:?}", vlog_file)),
                 _ => err_at!(Fatal, msg: format!("unreachable")),
-            }
-        } else {
-            Ok(())
-        };
+            };
+            res = res.and(r);
+        }
 
         debug!(target: "robtr ", "{:?}/{}, snapshot purged", dir, name);
 
@@ -2655,6 +5823,14 @@ where
                     target: "robt  ", "{}, meta-item marker {} bytes",
                     self.name, data.len()
                 ),
+                (5, MetaItem::RangeTombstones(data)) => info!(
+                    target: "robt  ", "{}, meta-item range-tombstones {} bytes",
+                    self.name, data.len()
+                ),
+                (6, MetaItem::Dictionary(data)) => info!(
+                    target: "robt  ", "{}, meta-item dictionary {} bytes",
+                    self.name, data.len()
+                ),
                 _ => err_at!(Fatal, msg: format!("unreachable"))?,
             }
         }
@@ -2691,6 +5867,24 @@ where
         }
     }
 
+    /// Export latest version of all entries, including tombstones, into
+    /// `w` using the portable format. Refer to [Exporter] for details.
+    /// Return the number of entries exported.
+    pub fn export<W>(&mut self, w: W) -> Result<u64>
+    where
+        K: Default,
+        V: Default,
+        <V as Diff>::D: Default + Clone + Serialize,
+        B: Bloom,
+        W: io::Write,
+    {
+        let mut exporter = Exporter::new(w, &self.to_app_meta()?)?;
+        for entry in self.iter()? {
+            exporter.push(&entry?)?;
+        }
+        Ok(exporter.finish()?.1)
+    }
+
     /// Return the application metadata.
     pub fn to_app_meta(&self) -> Result<Vec<u8>> {
         if let MetaItem::AppMetadata(data) = &self.meta[2] {
@@ -2700,6 +5894,33 @@ where
         }
     }
 
+    /// Return the dictionary used to compress value-log, empty if
+    /// value-log is not compressed.
+    pub fn to_dictionary(&self) -> Result<Vec<u8>> {
+        if let MetaItem::Dictionary(data) = &self.meta[6] {
+            Ok(data.clone())
+        } else {
+            err_at!(Fatal, msg: format!("{}", self.meta[6]))
+        }
+    }
+
+    /// Return the build-uuid for this snapshot along with build-uuid
+    /// of the snapshots it was built from. Can be used to audit the
+    /// compactions that produced this snapshot.
+    pub fn to_lineage(&self) -> Result<Lineage> {
+        let stats = self.to_stats()?;
+        Ok(Lineage {
+            build_uuid: stats.build_uuid,
+            sources: stats.lineage,
+        })
+    }
+
+    /// Return runtime statistics accumulated by read operations on
+    /// this snapshot handle.
+    pub fn to_runtime_stats(&self) -> RuntimeStats {
+        self.runtime.clone()
+    }
+
     /// Return Btree statistics.
     pub fn to_stats(&self) -> Result<Stats> {
         if let MetaItem::Stats(stats) = &self.meta[3] {
@@ -2709,6 +5930,17 @@ where
         }
     }
 
+    /// Return recommended changes to build configuration, for the next
+    /// build, based on the statistics of this snapshot.
+    pub fn advice(&self) -> Result<Advice> {
+        Ok(self.to_stats()?.to_advice())
+    }
+
+    fn read_block(&mut self, fpos: u64, n: usize, msg: &str) -> Result<Vec<u8>> {
+        self.runtime.n_block_reads += 1;
+        self.index_fd.read_buffer(fpos, n, msg)
+    }
+
     pub fn to_vlog_path_file(&self) -> Result<Option<String>> {
         let stats: Stats = match &self.meta[3] {
             MetaItem::Stats(stats) => stats.parse()?,
@@ -2733,6 +5965,48 @@ where
     V: Default + Clone + Diff + Serialize,
     <V as Diff>::D: Default + Clone + Serialize,
 {
+    /// Return an iterator over blocks in the index file, in physical
+    /// order, bypassing tree navigation. Intermediate blocks are decoded
+    /// upfront to learn the kind of every block, leaf blocks are read
+    /// lazily in file order. Faster for full-file verification,
+    /// conversion and compaction, where logical order is guaranteed by
+    /// construction. Entries covered by range tombstones are not
+    /// skipped.
+    pub fn iter_blocks(&mut self) -> Result<IterBlocks<K, V, B>> {
+        let m_blocksize = self.config.m_blocksize;
+
+        let mut blocks = vec![];
+        let mut fposs = match self.to_root() {
+            Ok(root) => vec![root],
+            Err(Error::EmptyIndex) => vec![],
+            Err(err) => return Err(err),
+        };
+        while let Some(fpos) = fposs.pop() {
+            let mblock = MBlock::<K, V>::new_decode(self.read_block(
+                fpos,
+                m_blocksize,
+                "iter_blocks(), reading mblock",
+            )?)?;
+            let mut children = vec![];
+            for index in 0..mblock.len() {
+                let mentry = mblock.to_entry(index)?;
+                if mentry.is_zblock() {
+                    blocks.push((mentry.to_fpos(), None));
+                } else {
+                    fposs.push(mentry.to_fpos());
+                }
+                children.push((mblock.to_key(index)?, mentry.to_fpos()));
+            }
+            blocks.push((fpos, Some(children)));
+        }
+        blocks.sort_by_key(|(fpos, _)| *fpos);
+
+        Ok(IterBlocks {
+            snap: self,
+            blocks: blocks.into_iter(),
+        })
+    }
+
     pub(crate) fn into_scan(mut self) -> Result<Scan<K, V, B>> {
         let mut mzs = vec![];
         match self.to_root() {
@@ -2775,11 +6049,10 @@ where
             }?,
         };
 
-        let mut r = ScanRange::new(self, mzs, range);
         if skip_one {
-            r.next();
+            skip_cursor(&mut mzs, false /*rev*/);
         }
-        Ok(r)
+        Ok(ScanRange::new(self, mzs, range))
     }
 }
 
@@ -2803,7 +6076,7 @@ where
             Err(Error::EmptyIndex) => return Ok(vec![]),
             Err(err) => Err(err),
         }?;
-        let mblock1 = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock1 = MBlock::<K, V>::new_decode(self.read_block(
             fpos,
             m_blocksize,
             "partitions, reading root",
@@ -2822,7 +6095,7 @@ where
                 partitions.push(range);
                 lk = Bound::Included(hk);
             } else {
-                let mblock2 = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock2 = MBlock::<K, V>::new_decode(self.read_block(
                     mentry.to_fpos(),
                     m_blocksize,
                     "partitions, reading mblock1",
@@ -2879,13 +6152,11 @@ where
 {
     fn footprint(&self) -> Result<isize> {
         let i_footprint: isize = self.index_fd.footprint()?;
-        let v_footprint: isize = match &self.valog_fd {
-            Some((vlog_file, _)) => {
-                let md = err_at!(IoError, fs::metadata(vlog_file))?;
-                convert_at!(md.len())?
-            }
-            None => 0,
-        };
+        let mut v_footprint: isize = 0;
+        for vlog_file in self.to_vlog_files().iter() {
+            let md = err_at!(IoError, fs::metadata(vlog_file))?;
+            v_footprint += convert_at!(md.len())?;
+        }
         Ok(i_footprint + v_footprint)
     }
 }
@@ -2938,6 +6209,9 @@ where
         };
         assert_eq!(footprint, self.footprint()?);
 
+        // entries deleted by range tombstones are skipped by iteration.
+        let exact = self.to_range_tombstones()?.is_empty();
+
         let iter = self.iter()?;
         let mut prev_key: Option<K> = None;
         let (mut n_count, mut n_deleted, mut seqno) = (0, 0, 0);
@@ -2967,10 +6241,10 @@ where
             }
         }
 
-        if n_count != s.n_count {
+        if (exact && n_count != s.n_count) || n_count > s.n_count {
             let msg = format!("validate, n_count {} > {}", n_count, s.n_count);
             err_at!(Fatal, msg: msg)
-        } else if n_deleted != s.n_deleted {
+        } else if (exact && n_deleted != s.n_deleted) || n_deleted > s.n_deleted {
             let msg = format!("validate, n_deleted {} > {}", n_deleted, s.n_deleted);
             err_at!(Fatal, msg: msg)
         } else if seqno > 0 && seqno > s.seqno {
@@ -2982,6 +6256,55 @@ where
     }
 }
 
+impl<K, V, B> Snapshot<K, V, B>
+where
+    K: Default + Clone + Ord + Serialize + Hash + fmt::Debug,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+    B: Bloom,
+{
+    /// Scan keys in this snapshot and confirm that every one of them is
+    /// present in `bitmap`, typically a bitmap loaded from a sidecar
+    /// copy, to detect a mismatch between the bitmap and the index.
+    /// False positive rate is measured by probing `bitmap`, once for
+    /// every key, with an element that is never indexed. Values are not
+    /// fetched from value-log.
+    pub fn validate_bitmap<X>(&mut self, bitmap: &X) -> Result<BitmapCheck>
+    where
+        X: Bloom,
+    {
+        let mut mzs = vec![];
+        match self.to_root() {
+            Ok(root) => Ok(self.build_fwd(root, &mut mzs)?),
+            Err(Error::EmptyIndex) => Ok(()),
+            Err(err) => Err(err),
+        }?;
+
+        let mut check: BitmapCheck = Default::default();
+        for entry in Iter::new_shallow(self, mzs) {
+            let entry = entry?;
+            let key = entry.as_key();
+            if !bitmap.contains(key) {
+                let msg = format!("validate_bitmap, missing key {:?}", key);
+                return err_at!(Fatal, msg: msg);
+            }
+            check.n_keys += 1;
+            // a key salted with its position is never indexed.
+            check.n_probes += 1;
+            if bitmap.contains(&(key, check.n_keys)) {
+                check.n_false_positives += 1;
+            }
+        }
+
+        debug!(
+            target: "robtr ",
+            "{:?}/{}, validate_bitmap n_keys:{} fpr:{}",
+            self.dir, self.name, check.n_keys, check.to_false_positive_rate()
+        );
+        Ok(check)
+    }
+}
+
 // Read methods
 impl<K, V, B> Reader<K, V> for Snapshot<K, V, B>
 where
@@ -2995,24 +6318,72 @@ where
         K: Borrow<Q>,
         Q: Ord + ?Sized + Hash,
     {
-        // check in the bitmap if key is present, there can be false
-        // positive, but can't be a false negative.
+        // check in the bitmap if key is present, there can be false
+        // positive, but can't be a false negative.
+        self.runtime.n_gets += 1;
+        if self.bitmap.contains(key) == false {
+            self.runtime.n_bitmap_skips += 1;
+            return Err(Error::KeyNotFound);
+        }
+        // println!("robt get ..");
+        let versions = false;
+        self.do_get(key, versions, None)
+    }
+
+    /// Check whether key is present, lookup stops at the z-block and
+    /// values are not fetched from value-log.
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        self.runtime.n_gets += 1;
+        if self.bitmap.contains(key) == false {
+            self.runtime.n_bitmap_skips += 1;
+            return Ok(false);
+        }
+        // key is present, if deleted by a range tombstone get() shall
+        // still return a deleted entry.
+        match self.find_zentry(key, None) {
+            Ok(_) => Ok(true),
+            Err(Error::KeyNotFound) => {
+                self.runtime.n_bitmap_false_positives += 1;
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn to_range_deleted<Q>(&mut self, key: &Q) -> Result<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let tombs = self.to_range_tombstones()?;
+        Ok(tombs
+            .iter()
+            .filter(|tomb| tomb.contains_key(key))
+            .map(|tomb| tomb.seqno)
+            .max())
+    }
+
+    fn get_deadline<Q>(&mut self, key: &Q, timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let deadline = Some(time::Instant::now() + timeout);
+        self.runtime.n_gets += 1;
         if self.bitmap.contains(key) == false {
+            self.runtime.n_bitmap_skips += 1;
             return Err(Error::KeyNotFound);
         }
-        // println!("robt get ..");
         let versions = false;
-        self.do_get(key, versions)
+        self.do_get(key, versions, deadline)
     }
 
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
-        let mut mzs = vec![];
-        match self.to_root() {
-            Ok(root) => Ok(self.build_fwd(root, &mut mzs)?),
-            Err(Error::EmptyIndex) => Ok(()),
-            Err(err) => Err(err),
-        }?;
-        Ok(Iter::new(self, mzs))
+        Ok(self.to_iter()?)
     }
 
     fn range<'a, R, Q>(&'a mut self, range: R) -> Result<IndexIter<K, V>>
@@ -3035,6 +6406,15 @@ where
         self.do_reverse(range, versions)
     }
 
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
@@ -3042,12 +6422,14 @@ where
     {
         // check in the bitmap if key is present, there can be false
         // positive, but can't be a false negative.
+        self.runtime.n_gets += 1;
         if self.bitmap.contains(key) == false {
+            self.runtime.n_bitmap_skips += 1;
             return Err(Error::KeyNotFound);
         }
 
         let versions = true;
-        self.do_get(key, versions)
+        self.do_get(key, versions, None)
     }
 
     /// Iterate over all entries in this index. Returned entry shall
@@ -3093,6 +6475,58 @@ where
     }
 }
 
+impl<K, V, B> PiecewiseScan<K, V> for Snapshot<K, V, B>
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+    B: Bloom,
+{
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries greater than from bound,
+    /// * Only entries whose modified seqno is within seqno-range.
+    ///
+    /// Snapshot is immutable, hence the iterator never returns
+    /// ScanEntry::Retry. Older versions are fetched from disk, so that
+    /// an entry modified after the seqno-range is rolled back to its
+    /// version within the range. Without deltas, such entries are
+    /// skipped.
+    fn pw_scan<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let (start, end) = util::to_start_end(within);
+        let iter = self.range_with_versions((from, Bound::<K>::Unbounded))?;
+        Ok(Box::new(iter.filter_map(move |entry| match entry {
+            Ok(entry) => match entry.filter_within(start.clone(), end.clone()) {
+                Some(entry) => Some(Ok(ScanEntry::Found(entry))),
+                None => None,
+            },
+            Err(err) => Some(Err(err)),
+        })))
+    }
+
+    /// Return an iterator over entries that meet following properties
+    /// * Only entries less than from bound, in descending order,
+    /// * Only entries whose modified seqno is within seqno-range.
+    ///
+    /// Like `pw_scan`, never returns ScanEntry::Retry.
+    fn pw_scan_rev<G>(&mut self, from: Bound<K>, within: G) -> Result<ScanIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let (start, end) = util::to_start_end(within);
+        let iter = self.reverse_with_versions((Bound::<K>::Unbounded, from))?;
+        Ok(Box::new(iter.filter_map(move |entry| match entry {
+            Ok(entry) => match entry.filter_within(start.clone(), end.clone()) {
+                Some(entry) => Some(Ok(ScanEntry::Found(entry))),
+                None => None,
+            },
+            Err(err) => Some(Err(err)),
+        })))
+    }
+}
+
 impl<K, V, B> CommitIterator<K, V> for Snapshot<K, V, B>
 where
     K: Default + Clone + Ord + Serialize,
@@ -3169,10 +6603,14 @@ where
 {
     /// Return the first entry in index, with only latest value.
     pub fn first(&mut self) -> Result<Entry<K, V>> {
+        if !self.to_range_tombstones()?.is_empty() {
+            return self.edge_entry(false /*rev*/, false /*versions*/);
+        }
+
         let zfpos = self.first_zpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.read_block(
             zfpos,
             z_blocksize,
             "first(), reading zblock",
@@ -3185,10 +6623,14 @@ where
 
     /// Return the first entry in index, with all versions.
     pub fn first_with_versions(&mut self) -> Result<Entry<K, V>> {
+        if !self.to_range_tombstones()?.is_empty() {
+            return self.edge_entry(false /*rev*/, true /*versions*/);
+        }
+
         let zfpos = self.first_zpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.read_block(
             zfpos,
             z_blocksize,
             "first(), reading zblock",
@@ -3201,10 +6643,14 @@ where
 
     /// Return the last entry in index, with only latest value.
     pub fn last(&mut self) -> Result<Entry<K, V>> {
+        if !self.to_range_tombstones()?.is_empty() {
+            return self.edge_entry(true /*rev*/, false /*versions*/);
+        }
+
         let zfpos = self.last_zfpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.read_block(
             zfpos,
             z_blocksize,
             "last(), reading zblock",
@@ -3217,10 +6663,14 @@ where
 
     /// Return the last entry in index, with all versions.
     pub fn last_with_versions(&mut self) -> Result<Entry<K, V>> {
+        if !self.to_range_tombstones()?.is_empty() {
+            return self.edge_entry(true /*rev*/, true /*versions*/);
+        }
+
         let zfpos = self.last_zfpos(self.to_root()?)?;
 
         let z_blocksize = self.config.z_blocksize;
-        let zblock = ZBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::<K, V>::new_decode(self.read_block(
             zfpos,
             z_blocksize,
             "last(), reading zblock",
@@ -3231,9 +6681,50 @@ where
         Ok(entry)
     }
 
+    /// Full table scan, same as [iter][Reader::iter], but return the
+    /// iterator type to allow further configuration, like
+    /// [Iter::with_skip_corrupt].
+    pub fn to_iter(&mut self) -> Result<Box<Iter<K, V, B>>> {
+        let mut mzs = vec![];
+        match self.to_root() {
+            Ok(root) => Ok(self.build_fwd(root, &mut mzs)?),
+            Err(Error::EmptyIndex) => Ok(()),
+            Err(err) => Err(err),
+        }?;
+        Ok(Iter::new(self, mzs))
+    }
+
+    // entries at the edge can be deleted by range tombstones, fall back
+    // to iteration.
+    fn edge_entry(&mut self, rev: bool, versions: bool) -> Result<Entry<K, V>> {
+        let mut iter = if rev {
+            self.do_reverse::<_, K>(.., versions)?
+        } else {
+            self.do_range::<_, K>(.., versions)?
+        };
+        match iter.next() {
+            Some(res) => res,
+            None => Err(Error::EmptyIndex),
+        }
+    }
+
+    // range tombstones are decoded on first use, and shared with
+    // iterators.
+    fn to_range_tombstones(&mut self) -> Result<Arc<Vec<RangeTombstone<K>>>> {
+        if let Some(tombs) = &self.range_tombstones {
+            return Ok(Arc::clone(tombs));
+        }
+        let tombs = match &self.meta[5] {
+            MetaItem::RangeTombstones(data) => Arc::new(decode_range_tombstones(data)?),
+            _ => err_at!(Fatal, msg: format!("{}", self.meta[5]))?,
+        };
+        self.range_tombstones = Some(Arc::clone(&tombs));
+        Ok(tombs)
+    }
+
     fn first_zpos(&mut self, fpos: u64) -> Result<u64> {
         let m_blocksize = self.config.m_blocksize;
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock = MBlock::<K, V>::new_decode(self.read_block(
             fpos,
             m_blocksize,
             "first_zpos, reading mblock",
@@ -3249,7 +6740,7 @@ where
 
     fn last_zfpos(&mut self, fpos: u64) -> Result<u64> {
         let m_blocksize = self.config.m_blocksize;
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        let mblock = MBlock::<K, V>::new_decode(self.read_block(
             fpos,
             m_blocksize,
             "last_zpos, reading mblock",
@@ -3263,48 +6754,108 @@ where
         }
     }
 
-    fn get_zpos<Q>(&mut self, key: &Q, fpos: u64) -> Result<u64>
+    // return the z-block's file-position and its level in the btree.
+    fn get_zpos<Q>(
+        &mut self,
+        key: &Q,
+        fpos: u64,
+        level: usize,
+        deadline: Option<time::Instant>, // give up after deadline
+    ) -> Result<(u64, usize)>
     where
         K: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+        util::check_deadline(deadline)?;
+        let mblock = MBlock::<K, V>::new_decode(self.read_block(
             fpos,
             self.config.m_blocksize,
             "get_zpos(), reading mblock",
         )?)?;
+        self.runtime.incr_level(level);
         match mblock.get(key, Bound::Unbounded, Bound::Unbounded) {
-            Err(Error::__LessThan) => Err(Error::KeyNotFound),
-            Ok(mentry) if mentry.is_zblock() => Ok(mentry.to_fpos()),
-            Ok(mentry) => self.get_zpos(key, mentry.to_fpos()),
+            Err(Error::Internal(Internal::LessThan)) => Err(Error::KeyNotFound),
+            Ok(mentry) if mentry.is_zblock() => Ok((mentry.to_fpos(), level + 1)),
+            Ok(mentry) => self.get_zpos(key, mentry.to_fpos(), level + 1, deadline),
             Err(err) => Err(err),
         }
     }
 
-    fn do_get<Q>(&mut self, key: &Q, versions: bool) -> Result<Entry<K, V>>
+    fn do_get<Q>(
+        &mut self,
+        key: &Q,
+        versions: bool,
+        deadline: Option<time::Instant>, // give up after deadline
+    ) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        // bitmap has passed the key, count the misses.
+        let entry = match self.find_entry(key, versions, deadline) {
+            Err(Error::KeyNotFound) => {
+                self.runtime.n_bitmap_false_positives += 1;
+                Err(Error::KeyNotFound)
+            }
+            res => res,
+        }?;
+        // key is present, but could be deleted by a range tombstone, in
+        // which case return a deleted entry so that lookups across
+        // levels don't fall through to older versions of the key.
+        let tombs = self.to_range_tombstones()?;
+        let seqno = tombs
+            .iter()
+            .filter(|tomb| tomb.covers(&entry))
+            .map(|tomb| tomb.seqno)
+            .max();
+        match seqno {
+            Some(seqno) => Ok(Entry::new(entry.to_key(), core::Value::new_delete(seqno))),
+            None => Ok(entry),
+        }
+    }
+
+    fn find_entry<Q>(
+        &mut self,
+        key: &Q,
+        versions: bool,
+        deadline: Option<time::Instant>, // give up after deadline
+    ) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut entry = self.find_zentry(key, deadline)?;
+        util::check_deadline(deadline)?;
+        self.fetch(&mut entry, false /*shallow*/, versions)?;
+        Ok(entry)
+    }
+
+    // find the entry for key in its z-block, values and older versions
+    // are not fetched from value-log.
+    fn find_zentry<Q>(
+        &mut self,
+        key: &Q,
+        deadline: Option<time::Instant>, // give up after deadline
+    ) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
         Q: Ord + ?Sized,
     {
-        let zfpos = self.get_zpos(key, self.to_root()?)?;
+        let (zfpos, zlevel) = self.get_zpos(key, self.to_root()?, 0, deadline)?;
 
         // println!("do_get {}", zfpos);
-        let zblock: ZBlock<K, V> = ZBlock::new_decode(self.index_fd.read_buffer(
+        util::check_deadline(deadline)?;
+        let zblock: ZBlock<K, V> = ZBlock::new_decode(self.read_block(
             zfpos,
             self.config.z_blocksize,
             "do_get(), reading zblock",
         )?)?;
+        self.runtime.incr_level(zlevel);
         match zblock.find(key, Bound::Unbounded, Bound::Unbounded) {
-            Ok((_, mut entry)) => {
-                if entry.as_key().borrow().eq(key) {
-                    self.fetch(&mut entry, false /*shallow*/, versions)?;
-                    Ok(entry)
-                } else {
-                    Err(Error::KeyNotFound)
-                }
-            }
-            Err(Error::__LessThan) => Err(Error::KeyNotFound),
-            Err(Error::__ZBlockExhausted(_)) => Err(Error::KeyNotFound),
+            Ok((_, entry)) if entry.as_key().borrow().eq(key) => Ok(entry),
+            Ok(_) => Err(Error::KeyNotFound),
+            Err(Error::Internal(Internal::LessThan)) => Err(Error::KeyNotFound),
+            Err(Error::Internal(Internal::ZBlockExhausted(_))) => Err(Error::KeyNotFound),
             Err(err) => Err(err),
         }
     }
@@ -3346,11 +6897,10 @@ where
                 Err(err) => Err(err),
             }?,
         };
-        let mut r = Range::new(self, mzs, range, versions);
         if skip_one {
-            r.next();
+            skip_cursor(&mut mzs, false /*rev*/);
         }
-        Ok(r)
+        Ok(Range::new(self, mzs, range, versions))
     }
 
     fn do_reverse<'a, R, Q>(
@@ -3390,11 +6940,10 @@ where
                 Err(err) => Err(err),
             }?,
         };
-        let mut rr = Reverse::new(self, mzs, range, versions);
         if skip_one {
-            rr.next();
+            skip_cursor(&mut mzs, true /*rev*/);
         }
-        Ok(rr)
+        Ok(Reverse::new(self, mzs, range, versions))
     }
 
     fn build_fwd(
@@ -3402,13 +6951,13 @@ where
         mut fpos: u64,           // from node
         mzs: &mut Vec<MZ<K, V>>, // output
     ) -> Result<()> {
-        let config = &self.config;
+        let (m_blocksize, z_blocksize) = (self.config.m_blocksize, self.config.z_blocksize);
 
         // println!("build_fwd {} {}", mzs.len(), fpos);
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.read_block(
                 fpos,
-                config.m_blocksize,
+                m_blocksize,
                 "build_fwd(), reading mblock",
             )?)?;
             mzs.push(MZ::M { fpos, index: 0 });
@@ -3421,24 +6970,29 @@ where
         };
         // println!("build_fwd {}", mzs.len());
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::new_decode(self.read_block(
             zfpos,
-            config.z_blocksize,
+            z_blocksize,
             "build_fwd(), reading zblock",
         )?)?;
-        mzs.push(MZ::Z { zblock, index: 0 });
+        let tombs = self.to_range_tombstones()?;
+        mzs.push(MZ::Z {
+            zblock,
+            index: 0,
+            tombs,
+        });
         Ok(())
     }
 
     fn rebuild_fwd(&mut self, mzs: &mut Vec<MZ<K, V>>) -> Result<()> {
-        let config = &self.config;
+        let (m_blocksize, z_blocksize) = (self.config.m_blocksize, self.config.z_blocksize);
 
         match mzs.pop() {
             None => Ok(()),
             Some(MZ::M { fpos, mut index }) => {
-                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock = MBlock::<K, V>::new_decode(self.read_block(
                     fpos,
-                    config.m_blocksize,
+                    m_blocksize,
                     "rebuild_fwd(), reading mblock",
                 )?)?;
                 index += 1;
@@ -3446,12 +7000,17 @@ where
                     Ok(MEntry::DecZ { fpos: zfpos, .. }) => {
                         mzs.push(MZ::M { fpos, index });
 
-                        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+                        let zblock = ZBlock::new_decode(self.read_block(
                             zfpos,
-                            config.z_blocksize,
+                            z_blocksize,
                             "rebuild_fwd(), reading zblock",
                         )?)?;
-                        mzs.push(MZ::Z { zblock, index: 0 });
+                        let tombs = self.to_range_tombstones()?;
+                        mzs.push(MZ::Z {
+                            zblock,
+                            index: 0,
+                            tombs,
+                        });
                         Ok(())
                     }
                     Ok(MEntry::DecM { fpos: mfpos, .. }) => {
@@ -3459,7 +7018,7 @@ where
                         self.build_fwd(mfpos, mzs)?;
                         Ok(())
                     }
-                    Err(Error::__MBlockExhausted(_)) => self.rebuild_fwd(mzs),
+                    Err(Error::Internal(Internal::MBlockExhausted(_))) => self.rebuild_fwd(mzs),
                     _ => err_at!(Fatal, msg: format!("unreachable")),
                 }
             }
@@ -3467,17 +7026,34 @@ where
         }
     }
 
+    // return fpos of the child block under the cursor of inner-most
+    // m-block, used to locate a block that failed to read or decode.
+    fn to_child_fpos(&mut self, mzs: &[MZ<K, V>]) -> Result<u64> {
+        match mzs.last() {
+            Some(MZ::M { fpos, index }) => {
+                let (fpos, index) = (*fpos, *index);
+                let mblock = MBlock::<K, V>::new_decode(self.read_block(
+                    fpos,
+                    self.config.m_blocksize,
+                    "to_child_fpos(), reading mblock",
+                )?)?;
+                Ok(mblock.to_entry(index)?.to_fpos())
+            }
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     fn build_rev(
         &mut self,
         mut fpos: u64,           // from node
         mzs: &mut Vec<MZ<K, V>>, // output
     ) -> Result<()> {
-        let config = &self.config;
+        let (m_blocksize, z_blocksize) = (self.config.m_blocksize, self.config.z_blocksize);
 
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.read_block(
                 fpos,
-                config.m_blocksize,
+                m_blocksize,
                 "build_rev(), reading mblock",
             )?)?;
             let index = mblock.len() - 1;
@@ -3490,26 +7066,31 @@ where
             fpos = mentry.to_fpos();
         };
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+        let zblock = ZBlock::new_decode(self.read_block(
             zfpos,
-            config.z_blocksize,
+            z_blocksize,
             "build_rev(), reading zblock",
         )?)?;
         let index: isize = convert_at!((zblock.len()? - 1))?;
-        mzs.push(MZ::Z { zblock, index });
+        let tombs = self.to_range_tombstones()?;
+        mzs.push(MZ::Z {
+            zblock,
+            index,
+            tombs,
+        });
         Ok(())
     }
 
     fn rebuild_rev(&mut self, mzs: &mut Vec<MZ<K, V>>) -> Result<()> {
-        let config = &self.config;
+        let (m_blocksize, z_blocksize) = (self.config.m_blocksize, self.config.z_blocksize);
 
         match mzs.pop() {
             None => Ok(()),
             Some(MZ::M { index: 0, .. }) => self.rebuild_rev(mzs),
             Some(MZ::M { fpos, mut index }) => {
-                let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+                let mblock = MBlock::<K, V>::new_decode(self.read_block(
                     fpos,
-                    config.m_blocksize,
+                    m_blocksize,
                     "rebuild_rev(), reading mblock",
                 )?)?;
                 index -= 1;
@@ -3517,13 +7098,18 @@ where
                     Ok(MEntry::DecZ { fpos: zfpos, .. }) => {
                         mzs.push(MZ::M { fpos, index });
 
-                        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
+                        let zblock = ZBlock::new_decode(self.read_block(
                             zfpos,
-                            config.z_blocksize,
+                            z_blocksize,
                             "rebuild_rev(), reading zblock",
                         )?)?;
                         let idx: isize = convert_at!((zblock.len()? - 1))?;
-                        mzs.push(MZ::Z { zblock, index: idx });
+                        let tombs = self.to_range_tombstones()?;
+                        mzs.push(MZ::Z {
+                            zblock,
+                            index: idx,
+                            tombs,
+                        });
                         Ok(())
                     }
                     Ok(MEntry::DecM { fpos: mfpos, .. }) => {
@@ -3548,18 +7134,18 @@ where
         Q: Ord + ?Sized,
     {
         let mut fpos = self.to_root()?;
-        let config = &self.config;
+        let (m_blocksize, z_blocksize) = (self.config.m_blocksize, self.config.z_blocksize);
         let (from_min, to_max) = (Bound::Unbounded, Bound::Unbounded);
 
         let zfpos = loop {
-            let mblock = MBlock::<K, V>::new_decode(self.index_fd.read_buffer(
+            let mblock = MBlock::<K, V>::new_decode(self.read_block(
                 fpos,
-                config.m_blocksize,
+                m_blocksize,
                 "build(), reading mblock",
             )?)?;
             let mentry = match mblock.find(key, from_min, to_max) {
                 Ok(mentry) => Ok(mentry),
-                Err(Error::__LessThan) => mblock.to_entry(0),
+                Err(Error::Internal(Internal::LessThan)) => mblock.to_entry(0),
                 Err(err) => Err(err),
             }?;
             let index = mentry.to_index()?;
@@ -3570,23 +7156,22 @@ where
             fpos = mentry.to_fpos();
         };
 
-        let zblock = ZBlock::new_decode(self.index_fd.read_buffer(
-            zfpos,
-            config.z_blocksize,
-            "build(), reading zblock",
-        )?)?;
+        let zblock =
+            ZBlock::new_decode(self.read_block(zfpos, z_blocksize, "build(), reading zblock")?)?;
         let (index, entry) = match zblock.find(key, from_min, to_max) {
             Ok((index, entry)) => Ok((index, entry)),
-            Err(Error::__LessThan) => zblock.to_entry(0),
-            Err(Error::__ZBlockExhausted(index)) => {
+            Err(Error::Internal(Internal::LessThan)) => zblock.to_entry(0),
+            Err(Error::Internal(Internal::ZBlockExhausted(index))) => {
                 let (_, entry) = zblock.to_entry(index)?;
                 Ok((index, entry))
             }
             Err(err) => Err(err),
         }?;
+        let tombs = self.to_range_tombstones()?;
         mzs.push(MZ::Z {
             zblock,
             index: convert_at!(index)?,
+            tombs,
         });
         Ok(entry)
     }
@@ -3604,15 +7189,22 @@ where
         shallow: bool,  // fetch neither value nor deltas.
         versions: bool, // fetch deltas as well
     ) -> Result<()> {
+        let (cipher, dict) = (self.valog_cipher.as_ref(), self.valog_dict.as_ref());
         if !shallow {
-            match &mut self.valog_fd {
-                Some((_, fd)) => entry.fetch_value(fd)?,
+            match &self.valog_fd {
+                Some((file, fds)) => {
+                    let n = entry.fetch_value(file, fds, cipher, dict)?;
+                    self.runtime.n_vlog_reads += n;
+                }
                 _ => (),
             }
         }
         if versions {
-            match &mut self.valog_fd {
-                Some((_, fd)) => entry.fetch_deltas(fd)?,
+            match &self.valog_fd {
+                Some((file, fds)) => {
+                    let n = entry.fetch_deltas(file, fds, cipher, dict)?;
+                    self.runtime.n_vlog_reads += n;
+                }
                 _ => (),
             }
         }
@@ -3669,6 +7261,7 @@ where
                 }
                 None => {
                     // println!("three none");
+                    self.n_zblocks += 1;
                     match self.snap.rebuild_fwd(&mut self.mzs) {
                         Err(err) => Some(Err(err)),
                         Ok(_) => self.next(),
@@ -3750,6 +7343,81 @@ where
     }
 }
 
+/// Block decoded from index file, refer to [Snapshot::iter_blocks].
+pub enum Block<K, V> {
+    /// Intermediate block, with first key and file-position of each of
+    /// its child blocks.
+    M { fpos: u64, children: Vec<(K, u64)> },
+    /// Leaf block, with its entries. Values and deltas are fetched from
+    /// value-log, if any.
+    Z {
+        fpos: u64,
+        entries: Vec<Entry<K, V>>,
+    },
+}
+
+impl<K, V> Block<K, V> {
+    /// Return the file-position of this block in the index file.
+    pub fn to_fpos(&self) -> u64 {
+        match self {
+            Block::M { fpos, .. } => *fpos,
+            Block::Z { fpos, .. } => *fpos,
+        }
+    }
+}
+
+/// Iterate type, to scan blocks of [Robt] index in physical order, refer
+/// to [Snapshot::iter_blocks].
+pub struct IterBlocks<'a, K, V, B>
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+{
+    snap: &'a mut Snapshot<K, V, B>,
+    blocks: vec::IntoIter<(u64, Option<Vec<(K, u64)>>)>,
+}
+
+impl<'a, K, V, B> IterBlocks<'a, K, V, B>
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+{
+    fn read_zblock(&mut self, fpos: u64) -> Result<Block<K, V>> {
+        let z_blocksize = self.snap.config.z_blocksize;
+        let zblock = ZBlock::<K, V>::new_decode(self.snap.read_block(
+            fpos,
+            z_blocksize,
+            "iter_blocks(), reading zblock",
+        )?)?;
+
+        let mut entries = vec![];
+        for index in 0..zblock.len()? {
+            let (_, mut entry) = zblock.to_entry(index)?;
+            let (shallow, versions) = (false, true);
+            self.snap.fetch(&mut entry, shallow, versions)?;
+            entries.push(entry);
+        }
+        Ok(Block::Z { fpos, entries })
+    }
+}
+
+impl<'a, K, V, B> Iterator for IterBlocks<'a, K, V, B>
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+{
+    type Item = Result<Block<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.blocks.next()? {
+            (fpos, Some(children)) => Some(Ok(Block::M { fpos, children })),
+            (fpos, None) => Some(self.read_zblock(fpos)),
+        }
+    }
+}
+
 /// Iterate type, to do full-table scan over [Robt] index.
 pub struct Iter<'a, K, V, B>
 where
@@ -3761,6 +7429,9 @@ where
     mzs: Vec<MZ<K, V>>,
     shallow: bool,
     versions: bool,
+    skip_corrupt: bool,
+    resume: bool,
+    n_zblocks: usize, // z-blocks exhausted so far.
 }
 
 impl<'a, K, V, B> Iter<'a, K, V, B>
@@ -3775,6 +7446,9 @@ where
             mzs,
             shallow: false,
             versions: false,
+            skip_corrupt: false,
+            resume: false,
+            n_zblocks: 0,
         })
     }
 
@@ -3784,6 +7458,9 @@ where
             mzs,
             shallow: false,
             versions: true,
+            skip_corrupt: false,
+            resume: false,
+            n_zblocks: 0,
         })
     }
 
@@ -3793,10 +7470,185 @@ where
             mzs,
             shallow: true,
             versions: false,
+            skip_corrupt: false,
+            resume: false,
+            n_zblocks: 0,
+        })
+    }
+
+    /// Enable recovery mode, when a z-block, or the m-block leading
+    /// to it, fails to read or decode, iterator shall yield
+    /// [Error::CorruptBlock] once and continue with the next block.
+    /// Useful for salvaging entries from a damaged index. By default
+    /// iteration ends with the first error.
+    pub fn with_skip_corrupt(mut self: Box<Self>, skip: bool) -> Box<Self> {
+        self.skip_corrupt = skip;
+        self
+    }
+}
+
+impl<'a, K, V, B> Iter<'a, K, V, B>
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+{
+    // report the corrupted block under the cursor and arrange to resume
+    // from the next block. Fail with `err` if the block can't be located.
+    fn skip_block(&mut self, err: Error) -> Result<Entry<K, V>> {
+        match self.snap.to_child_fpos(&self.mzs) {
+            Ok(fpos) => {
+                warn!(target: "robt  ", "skip corrupt block at {}, {:?}", fpos, err);
+                self.resume = true;
+                Err(Error::CorruptBlock { fpos })
+            }
+            Err(_) => {
+                self.mzs.truncate(0);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<'a, K, V, B> Iter<'a, K, V, B>
+where
+    K: 'static + Send + Sync + Default + Clone + Ord + Serialize,
+    V: 'static + Send + Sync + Default + Clone + Diff + Serialize,
+    <V as Diff>::D: 'static + Send + Default + Clone + Serialize,
+    B: 'static + Send + Sync + Bloom,
+{
+    /// Continue iteration on a helper thread, that decodes upto `depth`
+    /// z-blocks ahead of the consumer, overlapping block reads and
+    /// decoding with the consumer's work, like merging entries during
+    /// compaction. Helper thread iterates on its own handle to the
+    /// snapshot, and exits when returned iterator is dropped.
+    pub fn spawn_prefetch(mut self: Box<Self>, depth: usize) -> Result<Prefetch<K, V>> {
+        if depth == 0 {
+            err_at!(InvalidInput, msg: format!("prefetch depth 0"))?;
+        }
+
+        let mut snap = Snapshot::<K, V, B>::open(&self.snap.dir, &self.snap.name)?;
+        if let IndexFile::Mmap { .. } = &self.snap.index_fd {
+            snap.set_mmap(true)?;
+        }
+
+        let mzs = mem::replace(&mut self.mzs, vec![]);
+        let (shallow, versions) = (self.shallow, self.versions);
+        let (skip_corrupt, resume) = (self.skip_corrupt, self.resume);
+
+        let (tx, rx) = mpsc::sync_channel(depth);
+        let handle = thread::spawn(move || {
+            let iter = Iter {
+                snap: &mut snap,
+                mzs,
+                shallow,
+                versions,
+                skip_corrupt,
+                resume,
+                n_zblocks: 0,
+            };
+            thread_prefetch(iter, tx)
+        });
+
+        Ok(Prefetch {
+            rx: Some(rx),
+            batch: vec![].into_iter(),
+            handle: Some(handle),
         })
     }
 }
 
+// post entries, batched by z-block, until iteration ends or consumer
+// hangs up.
+fn thread_prefetch<K, V, B>(mut iter: Iter<K, V, B>, tx: mpsc::SyncSender<Result<Vec<Entry<K, V>>>>)
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    <V as Diff>::D: Default + Clone + Serialize,
+{
+    let (mut batch, mut n_zblocks) = (vec![], iter.n_zblocks);
+    loop {
+        let res = match iter.next() {
+            Some(Ok(entry)) if iter.n_zblocks == n_zblocks => {
+                batch.push(entry);
+                continue;
+            }
+            Some(Ok(entry)) => {
+                n_zblocks = iter.n_zblocks;
+                tx.send(Ok(mem::replace(&mut batch, vec![entry])))
+            }
+            Some(Err(err)) => {
+                n_zblocks = iter.n_zblocks;
+                match tx.send(Ok(mem::replace(&mut batch, vec![]))) {
+                    Ok(()) => tx.send(Err(err)),
+                    Err(err) => Err(err),
+                }
+            }
+            None => {
+                tx.send(Ok(batch)).ok();
+                break;
+            }
+        };
+        if res.is_err() {
+            break; // consumer is gone.
+        }
+    }
+}
+
+/// Iterator returned by [Iter::spawn_prefetch].
+pub struct Prefetch<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    rx: Option<mpsc::Receiver<Result<Vec<Entry<K, V>>>>>,
+    batch: std::vec::IntoIter<Entry<K, V>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl<K, V> Drop for Prefetch<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn drop(&mut self) {
+        mem::drop(self.rx.take()); // unblock the helper thread.
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!(target: "robt  ", "prefetch thread panicked");
+            }
+        }
+    }
+}
+
+impl<K, V> Iterator for Prefetch<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Result<Entry<K, V>>> {
+        loop {
+            if let Some(entry) = self.batch.next() {
+                break Some(Ok(entry));
+            }
+            match self.rx.as_ref()?.recv() {
+                Ok(Ok(batch)) => self.batch = batch.into_iter(),
+                Ok(Err(err)) => break Some(Err(err)),
+                Err(_) => {
+                    self.rx.take();
+                    let handle = self.handle.take()?;
+                    break match handle.join() {
+                        Ok(()) => None,
+                        Err(_) => Some(err_at!(Fatal, msg: format!("prefetch panicked"))),
+                    };
+                }
+            }
+        }
+    }
+}
+
 impl<'a, K, V, B> Iterator for Iter<'a, K, V, B>
 where
     K: Default + Clone + Ord + Serialize,
@@ -3806,6 +7658,13 @@ where
     type Item = Result<Entry<K, V>>;
 
     fn next(&mut self) -> Option<Result<Entry<K, V>>> {
+        if self.resume {
+            self.resume = false;
+            if let Err(err) = self.snap.rebuild_fwd(&mut self.mzs) {
+                return Some(self.skip_block(err));
+            }
+        }
+
         match self.mzs.pop() {
             None => None,
             Some(mut z) => match z.next() {
@@ -3817,6 +7676,7 @@ where
                         Err(err) => Some(Err(err)),
                     }
                 }
+                Some(Err(err)) if self.skip_corrupt => Some(self.skip_block(err)),
                 Some(Err(err)) => {
                     // println!("two {}", err);
                     self.mzs.truncate(0);
@@ -3825,6 +7685,7 @@ where
                 None => {
                     // println!("three none");
                     match self.snap.rebuild_fwd(&mut self.mzs) {
+                        Err(err) if self.skip_corrupt => Some(self.skip_block(err)),
                         Err(err) => Some(Err(err)),
                         Ok(_) => self.next(),
                     }
@@ -4016,8 +7877,15 @@ where
     V: Clone + Diff + Serialize,
     <V as Diff>::D: Clone + Serialize,
 {
-    M { fpos: u64, index: usize },
-    Z { zblock: ZBlock<K, V>, index: isize },
+    M {
+        fpos: u64,
+        index: usize,
+    },
+    Z {
+        zblock: ZBlock<K, V>,
+        index: isize,
+        tombs: Arc<Vec<RangeTombstone<K>>>,
+    },
 }
 
 impl<K, V> Iterator for MZ<K, V>
@@ -4030,17 +7898,24 @@ where
 
     fn next(&mut self) -> Option<Result<Entry<K, V>>> {
         match self {
-            MZ::Z { zblock, index } => {
+            // skip entries deleted by range tombstones.
+            MZ::Z {
+                zblock,
+                index,
+                tombs,
+            } => loop {
                 let undex: usize = (*index).try_into().unwrap();
                 match zblock.to_entry(undex) {
                     Ok((_, entry)) => {
                         *index += 1;
-                        Some(Ok(entry))
+                        if !tombs.iter().any(|tomb| tomb.covers(&entry)) {
+                            break Some(Ok(entry));
+                        }
                     }
-                    Err(Error::__ZBlockExhausted(_)) => None,
-                    Err(err) => Some(Err(err)),
+                    Err(Error::Internal(Internal::ZBlockExhausted(_))) => break None,
+                    Err(err) => break Some(Err(err)),
                 }
-            }
+            },
             MZ::M { .. } => Some(err_at!(Fatal, msg: format!("unreachable"))),
         }
     }
@@ -4054,23 +7929,44 @@ where
 {
     fn next_back(&mut self) -> Option<Result<Entry<K, V>>> {
         match self {
-            MZ::Z { zblock, index } if *index >= 0 => {
+            MZ::Z {
+                zblock,
+                index,
+                tombs,
+            } => loop {
+                if *index < 0 {
+                    break None;
+                }
                 let undex: usize = (*index).try_into().unwrap();
                 match zblock.to_entry(undex) {
                     Ok((_, entry)) => {
                         *index -= 1;
-                        Some(Ok(entry))
+                        if !tombs.iter().any(|tomb| tomb.covers(&entry)) {
+                            break Some(Ok(entry));
+                        }
                     }
-                    Err(Error::__ZBlockExhausted(_)) => None,
-                    Err(err) => Some(Err(err)),
+                    Err(Error::Internal(Internal::ZBlockExhausted(_))) => break None,
+                    Err(err) => break Some(Err(err)),
                 }
-            }
-            MZ::Z { .. } => None,
+            },
             MZ::M { .. } => Some(err_at!(Fatal, msg: format!("unreachable"))),
         }
     }
 }
 
+// skip the entry under cursor, entry shall be skipped even if it is
+// deleted by a range tombstone.
+fn skip_cursor<K, V>(mzs: &mut Vec<MZ<K, V>>, rev: bool)
+where
+    K: Clone + Ord + Serialize,
+    V: Clone + Diff + Serialize,
+    <V as Diff>::D: Clone + Serialize,
+{
+    if let Some(MZ::Z { index, .. }) = mzs.last_mut() {
+        *index = if rev { *index - 1 } else { *index + 1 };
+    }
+}
+
 fn purge_file(
     file: ffi::OsString,
     locked_files: &mut Vec<ffi::OsString>,
@@ -0,0 +1,91 @@
+//! Pluggable encryption for robt index-file and value-log-file, refer
+//! to [Cipher].
+
+use lazy_static::lazy_static;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{core::Result, error::Error};
+
+/// Cipher for encrypting [Robt] blocks at rest.
+///
+/// Leaf blocks, intermediate blocks and value-log blocks are encrypted
+/// before they are flushed to disk, and decrypted after they are read
+/// back. Meta-items at the tip of the index-file and the header of
+/// value-log-file are left in plain text, so that a snapshot can be
+/// identified without the key.
+///
+/// Implementations shall be length preserving, and shall be seekable,
+/// that is, any sub-slice of a flushed block shall be decryptable given
+/// its file-position. A counter mode stream cipher, with the counter
+/// derived from `nonce` and `fpos`, meets both requirements. `nonce` is
+/// unique for every file, hence the pair `(nonce, fpos)` is unique for
+/// every encrypted byte.
+///
+/// [Robt]: crate::robt::Robt
+pub trait Cipher: Send + Sync {
+    /// Identify the key used by this cipher. Key-id is persisted in
+    /// index statistics, key material is never persisted.
+    fn to_key_id(&self) -> String;
+
+    /// Encrypt `data` in-place, `data[0]` is at file-position `fpos`.
+    fn encrypt(&self, nonce: &[u8; 16], fpos: u64, data: &mut [u8]) -> Result<()>;
+
+    /// Decrypt `data` in-place, `data[0]` is at file-position `fpos`.
+    fn decrypt(&self, nonce: &[u8; 16], fpos: u64, data: &mut [u8]) -> Result<()>;
+}
+
+lazy_static! {
+    static ref CIPHERS: RwLock<HashMap<String, Arc<dyn Cipher>>> = RwLock::new(HashMap::new());
+}
+
+/// Register a cipher, snapshots encrypted with cipher's key-id can be
+/// opened only after the cipher is registered. Registering a cipher
+/// with an existing key-id shall replace the older one.
+pub fn register_cipher(cipher: Arc<dyn Cipher>) -> Result<()> {
+    match CIPHERS.write() {
+        Ok(mut ciphers) => {
+            ciphers.insert(cipher.to_key_id(), cipher);
+            Ok(())
+        }
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+pub(crate) fn get_cipher(key_id: &str) -> Result<Arc<dyn Cipher>> {
+    match CIPHERS.read() {
+        Ok(ciphers) => match ciphers.get(key_id) {
+            Some(cipher) => Ok(Arc::clone(cipher)),
+            None => err_at!(InvalidInput, msg: format!("unknown cipher {:?}", key_id)),
+        },
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+// Cipher bound to a single file, nonce is derived from file's uuid.
+#[derive(Clone)]
+pub(crate) struct FileCipher {
+    cipher: Arc<dyn Cipher>,
+    nonce: [u8; 16],
+}
+
+impl FileCipher {
+    pub(crate) fn new(cipher: Arc<dyn Cipher>, uuid: &str) -> Result<FileCipher> {
+        let nonce = match u128::from_str_radix(uuid, 16) {
+            Ok(nonce) => nonce.to_be_bytes(),
+            Err(err) => err_at!(InvalidFile, msg: format!("uuid {:?} {}", uuid, err))?,
+        };
+        Ok(FileCipher { cipher, nonce })
+    }
+
+    pub(crate) fn encrypt(&self, fpos: u64, data: &mut [u8]) -> Result<()> {
+        self.cipher.encrypt(&self.nonce, fpos, data)
+    }
+
+    pub(crate) fn decrypt(&self, fpos: u64, data: &mut [u8]) -> Result<()> {
+        self.cipher.decrypt(&self.nonce, fpos, data)
+    }
+}
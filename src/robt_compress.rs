@@ -0,0 +1,125 @@
+//! Pluggable dictionary compression for values and deltas persisted in
+//! robt value-log, refer to [Compressor].
+
+use lazy_static::lazy_static;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use crate::{core::Result, error::Error};
+
+/// Compressor for values and deltas persisted in [Robt] value-log.
+///
+/// Small values, like JSON documents, compress poorly on their own.
+/// Instead, a dictionary is trained from a sample of values during the
+/// build, and each value and delta is compressed independently using
+/// the shared dictionary, so that random reads can decompress a single
+/// value. Dictionary is persisted along with the index, as a
+/// [MetaItem][crate::robt::MetaItem]. A zstd implementation, with
+/// trained dictionaries, meets these requirements.
+///
+/// [Robt]: crate::robt::Robt
+pub trait Compressor: Send + Sync {
+    /// Identify the compression algorithm. Name is persisted in index
+    /// statistics.
+    fn to_name(&self) -> String;
+
+    /// Train a dictionary, no larger than `dict_size` bytes, from
+    /// `samples`.
+    fn train(&self, samples: &[Vec<u8>], dict_size: usize) -> Result<Vec<u8>>;
+
+    /// Compress `data` using `dict`.
+    fn compress(&self, dict: &[u8], data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decompress `data` using `dict`, that was used to compress it.
+    fn decompress(&self, dict: &[u8], data: &[u8]) -> Result<Vec<u8>>;
+}
+
+lazy_static! {
+    static ref COMPRESSORS: RwLock<HashMap<String, Arc<dyn Compressor>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Register a compressor, snapshots compressed with compressor's name
+/// can be opened only after the compressor is registered. Registering
+/// a compressor with an existing name shall replace the older one.
+pub fn register_compressor(compressor: Arc<dyn Compressor>) -> Result<()> {
+    match COMPRESSORS.write() {
+        Ok(mut compressors) => {
+            compressors.insert(compressor.to_name(), compressor);
+            Ok(())
+        }
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+pub(crate) fn get_compressor(name: &str) -> Result<Arc<dyn Compressor>> {
+    match COMPRESSORS.read() {
+        Ok(compressors) => match compressors.get(name) {
+            Some(compressor) => Ok(Arc::clone(compressor)),
+            None => err_at!(InvalidInput, msg: format!("unknown compressor {:?}", name)),
+        },
+        Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+    }
+}
+
+// Compressor bound to a trained dictionary.
+#[derive(Clone)]
+pub(crate) struct ValueDict {
+    compressor: Arc<dyn Compressor>,
+    dict: Arc<Vec<u8>>,
+}
+
+impl ValueDict {
+    pub(crate) fn new(compressor: Arc<dyn Compressor>, dict: Vec<u8>) -> ValueDict {
+        ValueDict {
+            compressor,
+            dict: Arc::new(dict),
+        }
+    }
+
+    pub(crate) fn as_dict(&self) -> &[u8] {
+        &self.dict
+    }
+
+    pub(crate) fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.compressor.compress(&self.dict, data)
+    }
+
+    pub(crate) fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.compressor.decompress(&self.dict, data)
+    }
+}
+
+// Collect a sample of serialized values, and train a dictionary once
+// enough samples are collected.
+pub(crate) struct Sampler {
+    compressor: Arc<dyn Compressor>,
+    samples: Vec<Vec<u8>>,
+    n_samples: usize,
+    dict_size: usize,
+}
+
+impl Sampler {
+    pub(crate) fn new(compressor: Arc<dyn Compressor>, n: usize, dict_size: usize) -> Sampler {
+        Sampler {
+            compressor,
+            samples: Vec::with_capacity(n),
+            n_samples: n,
+            dict_size,
+        }
+    }
+
+    // return the trained dictionary, once enough samples are collected.
+    pub(crate) fn add_sample(&mut self, sample: Vec<u8>) -> Result<Option<ValueDict>> {
+        self.samples.push(sample);
+        if self.samples.len() < self.n_samples {
+            return Ok(None);
+        }
+        let dict = self.compressor.train(&self.samples, self.dict_size)?;
+        self.samples.truncate(0);
+        Ok(Some(ValueDict::new(Arc::clone(&self.compressor), dict)))
+    }
+}
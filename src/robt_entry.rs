@@ -3,6 +3,7 @@ use std::{convert::TryInto, marker};
 use crate::{
     core::{self, Diff, Result, Serialize},
     error::Error,
+    robt_compress::ValueDict,
     vlog,
 };
 
@@ -81,6 +82,7 @@ where
     K: 'a + Serialize,
 {
     pub(crate) fn decode_entry(entry: &[u8], index: usize) -> Result<MEntry<K>> {
+        check_remaining!(entry, 16, "mentry-hdr")?;
         let hdr1 = u64::from_be_bytes(array_at!(entry[0..8])?);
         let fpos = u64::from_be_bytes(array_at!(entry[8..16])?);
         match (hdr1 & Self::ZBLOCK_FLAG) == Self::ZBLOCK_FLAG {
@@ -161,7 +163,12 @@ where
     const DLEN_MASK: u64 = 0x0FFFFFFFFFFFFFFF;
     const REFERENCE_FLAG: u64 = 0x8000000000000000;
 
-    fn encode(delta: &core::Delta<V>, leaf: &mut Vec<u8>, blob: &mut Vec<u8>) -> Result<usize> {
+    fn encode(
+        delta: &core::Delta<V>,
+        leaf: &mut Vec<u8>,
+        blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
+    ) -> Result<usize> {
         match delta.as_ref() {
             core::InnerDelta::U {
                 delta:
@@ -189,7 +196,14 @@ where
                 let mpos: u64 = convert_at!(blob.len())?;
 
                 let (hdr1, n) = {
-                    let n = delta.encode(blob)?;
+                    let n = match dict {
+                        Some(dict) => {
+                            let m = blob.len();
+                            delta.encode(blob)?;
+                            vlog::compress_payload(blob, m, dict)?
+                        }
+                        None => delta.encode(blob)?,
+                    };
                     let hdr1: u64 = convert_at!(n)?;
                     let hdr1 = hdr1 | Self::UPSERT_FLAG;
                     (hdr1, n)
@@ -257,8 +271,8 @@ where
 // *-------------------*----------------------*
 // |  32-bit key len   |   number of deltas   |
 // *-------------------*----------------------*
-// |flags|      60-bit value-len              |
-// *-----*------------------------------------*
+// |flags|user |      52-bit value-len        |
+// *-----*-----*------------------------------*
 // |              64-bit seqno                |
 // *-------------------*----------------------*
 // |                  key                     |
@@ -276,6 +290,9 @@ where
 // * bit 62: reserved
 // * bit 63: reserved
 //
+// User:
+// * bit 52-59: application defined flags, refer to Entry::to_flags().
+//
 pub(crate) enum ZEntry<K, V>
 where
     K: Serialize,
@@ -324,7 +341,9 @@ where
 {
     const UPSERT_FLAG: u64 = 0x1000000000000000;
     const VLOG_FLAG: u64 = 0x2000000000000000;
-    const VLEN_MASK: u64 = 0x0FFFFFFFFFFFFFFF;
+    const USER_FLAGS_MASK: u64 = 0x0FF0000000000000;
+    const USER_FLAGS_SHIFT: u64 = 52;
+    const VLEN_MASK: u64 = 0x000FFFFFFFFFFFFF;
     const NDELTA_MASK: u64 = 0xFFFFFFFF;
     const KLEN_SHIFT: u64 = 32;
     const REFERENCE_FLAG: u64 = 0x8000000000000000;
@@ -339,12 +358,13 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<ZEntry<K, V>> {
         let m = leaf.len();
         let (n_deltas, is_vlog) = (entry.to_delta_count(), false);
         let (k, v) = Self::encode_leaf1(entry, n_deltas, is_vlog, leaf)?;
         let doff = leaf.len() - m;
-        let d = ZEntry::encode_deltas(entry, leaf, blob)?;
+        let d = ZEntry::encode_deltas(entry, leaf, blob, dict)?;
         Ok(ZEntry::EncLD {
             doff,
             n_deltas,
@@ -358,9 +378,10 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<ZEntry<K, V>> {
         let (n_deltas, is_vlog) = (0_usize, true);
-        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob)?;
+        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob, dict)?;
         Ok(ZEntry::EncLV { voff: x, k, v })
     }
 
@@ -368,13 +389,14 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<ZEntry<K, V>> {
         let m = leaf.len();
         let (n_deltas, is_vlog) = (entry.to_delta_count(), true);
-        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob)?;
+        let (x, k, v) = Self::encode_leaf2(entry, n_deltas, is_vlog, leaf, blob, dict)?;
         // encode deltas
         let doff = leaf.len() - m;
-        let d = ZEntry::encode_deltas(entry, leaf, blob)?;
+        let d = ZEntry::encode_deltas(entry, leaf, blob, dict)?;
         Ok(ZEntry::EncLVD {
             voff: x,
             doff,
@@ -401,6 +423,7 @@ where
         // encode header.
         let hdr = &mut leaf[m..m + 24];
         Self::encode_header(klen, n_deltas, vlen, is_del, is_vlog, seqno, hdr)?;
+        Self::encode_user_flags(entry.to_flags(), hdr)?;
         Ok((klen, vlen))
     }
 
@@ -410,6 +433,7 @@ where
         is_vlog: bool,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<(usize, usize, usize)> {
         // adjust space for header.
         let m = leaf.len();
@@ -418,7 +442,7 @@ where
         let klen = Self::encode_key(entry.as_key(), leaf)?;
         // encode value
         let pos = blob.len();
-        let (fpos, vlen, is_del, seqno) = ZEntry::encode_value_vlog(entry, blob)?;
+        let (fpos, vlen, is_del, seqno) = ZEntry::encode_value_vlog(entry, blob, dict)?;
         let voff = leaf.len() - m;
         if !is_del {
             let fpos: u64 = match fpos {
@@ -430,6 +454,7 @@ where
         // encode header.
         let hdr = &mut leaf[m..m + 24];
         Self::encode_header(klen, n_deltas, vlen, is_del, is_vlog, seqno, hdr)?;
+        Self::encode_user_flags(entry.to_flags(), hdr)?;
 
         Ok((voff, klen, vlen))
     }
@@ -467,6 +492,13 @@ where
         Ok(())
     }
 
+    fn encode_user_flags(flags: u8, hdr: &mut [u8]) -> Result<()> {
+        let hdr2 = u64::from_be_bytes(array_at!(hdr[8..16])?);
+        let hdr2 = hdr2 | (u64::from(flags) << Self::USER_FLAGS_SHIFT);
+        hdr[8..16].copy_from_slice(&hdr2.to_be_bytes());
+        Ok(())
+    }
+
     fn encode_key(key: &K, buf: &mut Vec<u8>) -> Result<usize> {
         let n = key.encode(buf)?;
         if n > core::Entry::<i32, i32>::KEY_SIZE_LIMIT {
@@ -485,6 +517,10 @@ where
                 let vlen = value.encode_leaf(buf)?;
                 Ok((vlen, false, *seqno))
             }
+            core::Value::N { value, seqno } => {
+                let vlen = vlog::Value::new_native(value.clone()).encode_leaf(buf)?;
+                Ok((vlen, false, *seqno))
+            }
             core::Value::D { seqno } => Ok((0, true, *seqno)),
         }
     }
@@ -492,10 +528,23 @@ where
     fn encode_value_vlog(
         entry: &core::Entry<K, V>,
         buf: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<(Option<u64>, usize, bool, u64)> {
         match entry.as_value() {
             core::Value::U { value, seqno, .. } => {
-                let (fpos, vlen) = value.encode(buf)?;
+                let m = buf.len();
+                let (fpos, vlen) = match (value.encode(buf)?, dict) {
+                    ((None, _), Some(dict)) => (None, vlog::compress_payload(buf, m, dict)?),
+                    (res, _) => res,
+                };
+                Ok((fpos, vlen, false, *seqno))
+            }
+            core::Value::N { value, seqno } => {
+                let (m, value) = (buf.len(), vlog::Value::new_native(value.clone()));
+                let (fpos, vlen) = match (value.encode(buf)?, dict) {
+                    ((None, _), Some(dict)) => (None, vlog::compress_payload(buf, m, dict)?),
+                    (res, _) => res,
+                };
                 Ok((fpos, vlen, false, *seqno))
             }
             core::Value::D { seqno } => Ok((None, 0, true, *seqno)),
@@ -506,10 +555,11 @@ where
         entry: &core::Entry<K, V>,
         leaf: &mut Vec<u8>,
         blob: &mut Vec<u8>,
+        dict: Option<&ValueDict>,
     ) -> Result<usize> {
         let mut n = 0_usize;
         for delta in entry.as_deltas() {
-            n += DiskDelta::encode(delta, leaf, blob)?;
+            n += DiskDelta::encode(delta, leaf, blob, dict)?;
         }
         Ok(n)
     }
@@ -581,29 +631,35 @@ where
         K: Default,
         V: Default,
     {
+        check_remaining!(e, 24, "zentry-hdr")?;
         let (klen, n_deltas) = {
             let hdr1 = u64::from_be_bytes(array_at!(e[0..8])?);
             let n_deltas: usize = convert_at!((hdr1 & Self::NDELTA_MASK))?;
             let klen: usize = convert_at!((hdr1 >> Self::KLEN_SHIFT))?;
             (klen, n_deltas)
         };
-        let (is_deleted, is_vlog, vlen) = {
+        let (is_deleted, is_vlog, flags, vlen) = {
             let hdr2 = u64::from_be_bytes(array_at!(e[8..16])?);
+            let flags = (hdr2 & Self::USER_FLAGS_MASK) >> Self::USER_FLAGS_SHIFT;
+            let flags: u8 = convert_at!(flags)?;
             (
                 (hdr2 & Self::UPSERT_FLAG) == 0,
                 (hdr2 & Self::VLOG_FLAG) != 0,
+                flags,
                 hdr2 & Self::VLEN_MASK,
             )
         };
         let seqno = u64::from_be_bytes(array_at!(e[16..24])?);
 
         let mut key: K = Default::default();
+        check_remaining!(e, 24 + klen, "zentry-key")?;
         key.decode(&e[24..24 + klen])?;
 
         let n = 24 + klen;
         let (mut n, value) = match (is_deleted, is_vlog) {
             (true, _) => (n, core::Value::new_delete(seqno)),
             (false, true) => {
+                check_remaining!(e, n + 8, "zentry-vlog-fpos")?;
                 let fpos = u64::from_be_bytes(array_at!(e[n..n + 8])?);
                 let v = Box::new(vlog::Value::new_reference(fpos, vlen, seqno));
                 (n + 8, core::Value::new_upsert(v, seqno))
@@ -611,6 +667,7 @@ where
             (false, false) => {
                 let mut value: V = Default::default();
                 let vlen: usize = convert_at!(vlen)?;
+                check_remaining!(e, n + vlen, "zentry-value")?;
                 value.decode(&e[n..n + vlen])?;
                 let value = Box::new(vlog::Value::Native { value });
                 (n + vlen, core::Value::new_upsert(value, seqno))
@@ -618,9 +675,11 @@ where
         };
 
         let mut entry = core::Entry::new(key, value);
+        entry.set_flags(flags);
 
         let mut deltas: Vec<core::Delta<V>> = vec![];
         for _i in 0..n_deltas {
+            check_remaining!(e, n + 24, "zentry-delta")?;
             deltas.push(DiskDelta::decode_delta(&e[n..])?);
             n += 24;
         }
@@ -174,6 +174,26 @@ fn test_zentry_header() {
     }
 }
 
+#[test]
+fn test_zentry_user_flags() {
+    for flags in [0_u8, 1, 0x5a, 0xff].iter() {
+        let value = core::Value::new_upsert_value(10000, 10);
+        let mut entry = core::Entry::new(100, value);
+        entry.set_flags(*flags);
+
+        let mut leaf = vec![];
+        ZEntry::<i32, i32>::encode_l(&entry, &mut leaf).unwrap();
+        assert_eq!(leaf[8], 0x10 | (flags >> 4));
+        assert_eq!(leaf[9] >> 4, flags & 0xf);
+
+        let entry = ZEntry::<i32, i32>::decode_entry(&leaf).unwrap();
+        assert_eq!(entry.to_flags(), *flags);
+        assert_eq!(entry.to_key(), 100);
+        assert_eq!(entry.to_native_value(), Some(10000));
+        assert_eq!(entry.to_seqno(), 10);
+    }
+}
+
 #[test]
 fn test_zentry_key() {
     let key = 100_i32;
@@ -309,7 +329,7 @@ fn test_zentry_ld() {
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
     let ze = ZEntry::<i32, i32>::encode_ld(
-        &entry, &mut leaf, &mut blob, // arguments
+        &entry, &mut leaf, &mut blob, None, // arguments
     )
     .unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
@@ -429,7 +449,7 @@ fn test_zentry_lv() {
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
     let ze = ZEntry::<i32, i32>::encode_lv(
-        &entry, &mut leaf, &mut blob, // arguments
+        &entry, &mut leaf, &mut blob, None, // arguments
     )
     .unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
@@ -505,7 +525,7 @@ fn test_zentry_lvd() {
 
     let (mut leaf, mut blob): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
     let ze = ZEntry::<i32, i32>::encode_lvd(
-        &entry, &mut leaf, &mut blob, // arguments
+        &entry, &mut leaf, &mut blob, None, // arguments
     )
     .unwrap();
     let (k, v, d) = ze.to_kvd_stats().unwrap();
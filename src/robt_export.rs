@@ -0,0 +1,264 @@
+//! Portable export format for robt snapshots, refer to [Exporter].
+
+use std::{convert::TryInto, io, marker};
+
+use crate::{
+    core::{Diff, Entry, Result, Serialize, Value},
+    error::Error,
+};
+
+/// Magic bytes at the start of an exported file.
+pub const EXPORT_MAGIC: &[u8; 13] = b"rdms-robt-exp";
+
+/// Version of the export format.
+pub const EXPORT_VERSION: u16 = 1;
+
+const FLAG_DELETED: u8 = 0x01;
+const FLAG_END: u8 = 0xFF;
+
+/// Write entries in portable export format.
+///
+/// Downstream systems can bulk-ingest data produced by rdms by reading
+/// this format, without linking with robt's btree layout. Only the
+/// latest version of each entry is exported, tombstones are preserved.
+/// All integers are encoded in big-endian.
+///
+/// ```text
+/// *------------------------------------------* 0
+/// |      magic "rdms-robt-exp" (13 bytes)    |
+/// |           format version (u16)           |
+/// |        app-metadata-length (u64)         |
+/// |              app-metadata                |
+/// *------------------------------------------*
+/// |                 record                   |
+/// |                  ...                     |
+/// *------------------------------------------*
+/// |        end-of-records, flags 0xFF        |
+/// |         number of records (u64)          |
+/// *------------------------------------------*
+/// ```
+///
+/// Each record is encoded as:
+///
+/// ```text
+/// | flags (u8) | seqno (u64) | key-len (u32) | key | value-len (u32) | value |
+/// ```
+///
+/// Bit-0 in flags is set for deleted entries, in which case value-len
+/// is ZERO. Keys and values are encoded using the [Serialize] trait.
+pub struct Exporter<W>
+where
+    W: io::Write,
+{
+    w: W,
+    n_records: u64,
+    buf: Vec<u8>,
+}
+
+impl<W> Exporter<W>
+where
+    W: io::Write,
+{
+    /// Start an export, writing the header along with application
+    /// metadata into `w`.
+    pub fn new(mut w: W, app_meta: &[u8]) -> Result<Exporter<W>> {
+        let mut hdr = EXPORT_MAGIC.to_vec();
+        hdr.extend_from_slice(&EXPORT_VERSION.to_be_bytes());
+        let n: u64 = convert_at!(app_meta.len())?;
+        hdr.extend_from_slice(&n.to_be_bytes());
+        hdr.extend_from_slice(app_meta);
+        err_at!(IoError, w.write_all(&hdr))?;
+
+        Ok(Exporter {
+            w,
+            n_records: Default::default(),
+            buf: Vec::default(),
+        })
+    }
+
+    /// Append entry to export. Entries are expected in sort order.
+    pub fn push<K, V>(&mut self, entry: &Entry<K, V>) -> Result<()>
+    where
+        K: Clone + Ord + Serialize,
+        V: Clone + Diff + Serialize,
+    {
+        self.buf.truncate(0);
+
+        let flags = if entry.is_deleted() { FLAG_DELETED } else { 0 };
+        self.buf.push(flags);
+        self.buf.extend_from_slice(&entry.to_seqno().to_be_bytes());
+
+        self.buf.extend_from_slice(&[0_u8; 4]);
+        let n: u32 = convert_at!(entry.as_key().encode(&mut self.buf)?)?;
+        self.buf[9..13].copy_from_slice(&n.to_be_bytes());
+
+        let m = self.buf.len();
+        self.buf.extend_from_slice(&[0_u8; 4]);
+        if let Some(value) = entry.to_native_value() {
+            let n: u32 = convert_at!(value.encode(&mut self.buf)?)?;
+            self.buf[m..m + 4].copy_from_slice(&n.to_be_bytes());
+        }
+
+        err_at!(IoError, self.w.write_all(&self.buf))?;
+        self.n_records += 1;
+        Ok(())
+    }
+
+    /// Finish the export, return the underlying writer and number
+    /// of records exported.
+    pub fn finish(mut self) -> Result<(W, u64)> {
+        let mut tail = vec![FLAG_END];
+        tail.extend_from_slice(&self.n_records.to_be_bytes());
+        err_at!(IoError, self.w.write_all(&tail))?;
+        err_at!(IoError, self.w.flush())?;
+
+        Ok((self.w, self.n_records))
+    }
+}
+
+/// Read entries from portable export format. Can be passed directly
+/// to [robt::Builder::build][crate::robt::Builder::build] to convert an
+/// export back into a robt index.
+pub struct Importer<K, V, R>
+where
+    R: io::Read,
+{
+    r: R,
+    app_meta: Vec<u8>,
+    n_records: u64,
+    done: bool,
+
+    _phantom_key: marker::PhantomData<K>,
+    _phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V, R> Importer<K, V, R>
+where
+    R: io::Read,
+{
+    /// Validate the header from `r` and start the import.
+    pub fn new(mut r: R) -> Result<Importer<K, V, R>> {
+        let mut hdr = [0_u8; 23];
+        err_at!(IoError, r.read_exact(&mut hdr))?;
+        if &hdr[..13] != &EXPORT_MAGIC[..] {
+            err_at!(InvalidFile, msg: format!("export magic {:?}", &hdr[..13]))?;
+        }
+        let version = u16::from_be_bytes(array_at!(hdr[13..15])?);
+        if version != EXPORT_VERSION {
+            err_at!(InvalidFile, msg: format!("export version {}", version))?;
+        }
+
+        let n = u64::from_be_bytes(array_at!(hdr[15..23])?);
+        let app_meta = read_sized(&mut r, n)?;
+
+        Ok(Importer {
+            r,
+            app_meta,
+            n_records: Default::default(),
+            done: false,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+        })
+    }
+
+    /// Return the application metadata saved in export.
+    pub fn to_app_meta(&self) -> Vec<u8> {
+        self.app_meta.clone()
+    }
+
+    fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; n];
+        err_at!(IoError, self.r.read_exact(&mut buf))?;
+        Ok(buf)
+    }
+
+    fn read_u32(&mut self) -> Result<usize> {
+        let buf = self.read_bytes(4)?;
+        Ok(convert_at!(u32::from_be_bytes(array_at!(buf[..4])?))?)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        let buf = self.read_bytes(8)?;
+        Ok(u64::from_be_bytes(array_at!(buf[..8])?))
+    }
+
+    fn read_entry(&mut self) -> Result<Option<Entry<K, V>>>
+    where
+        K: Default + Clone + Ord + Serialize,
+        V: Default + Clone + Diff + Serialize,
+    {
+        let flags = self.read_bytes(1)?[0];
+        if flags == FLAG_END {
+            let n_records = self.read_u64()?;
+            return if n_records == self.n_records {
+                Ok(None)
+            } else {
+                let msg = format!("export records {}/{}", self.n_records, n_records);
+                err_at!(InvalidFile, msg: msg)
+            };
+        }
+
+        let seqno = self.read_u64()?;
+
+        let n = self.read_u32()?;
+        let mut key: K = Default::default();
+        key.decode(&read_sized(&mut self.r, convert_at!(n)?)?)?;
+
+        let n = self.read_u32()?;
+        let value = if (flags & FLAG_DELETED) == FLAG_DELETED {
+            Value::new_delete(seqno)
+        } else {
+            let mut value: V = Default::default();
+            value.decode(&read_sized(&mut self.r, convert_at!(n)?)?)?;
+            Value::new_upsert_value(value, seqno)
+        };
+
+        self.n_records += 1;
+        Ok(Some(Entry::new(key, value)))
+    }
+}
+
+// read `n` bytes, where `n` is decoded from the file and cannot be
+// trusted, hence buffer grows with the bytes actually read instead of
+// allocating `n` bytes upfront.
+fn read_sized<R: io::Read>(r: &mut R, n: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    let m = err_at!(IoError, r.take(n).read_to_end(&mut buf))?;
+    let m: u64 = convert_at!(m)?;
+    if m == n {
+        Ok(buf)
+    } else {
+        err_at!(InvalidFile, msg: format!("export length {}/{}", m, n))
+    }
+}
+
+impl<K, V, R> Iterator for Importer<K, V, R>
+where
+    K: Default + Clone + Ord + Serialize,
+    V: Default + Clone + Diff + Serialize,
+    R: io::Read,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "robt_export_test.rs"]
+mod robt_export_test;
@@ -0,0 +1,135 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use std::ops::Bound;
+
+use super::*;
+use crate::{
+    core::{self, Index, Reader, Writer},
+    llrb::Llrb,
+    nobitmap::NoBitmap,
+    robt::{self, Builder, Robt, Snapshot},
+    scans,
+};
+
+#[test]
+fn test_export_import() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for _ in 0..10_000 {
+        let key: i64 = rng.gen::<i64>().abs() % 2_000;
+        match rng.gen::<u8>() % 3 {
+            0 => {
+                llrb.delete(&key).unwrap();
+            }
+            _ => {
+                llrb.set(key, rng.gen()).unwrap();
+            }
+        }
+    }
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-export");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = rng.gen();
+
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, "test-export", config.clone()).unwrap();
+    let iter = {
+        let iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+        core::CommitIter::new(
+            scans::CommitWrapper::new(vec![Box::new(iter)]),
+            (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+        )
+    };
+    let app_meta = "export-meta".as_bytes().to_vec();
+    index.commit(iter, |_| app_meta.clone()).unwrap();
+
+    let mut buf = vec![];
+    let n = index.to_reader().unwrap().export(&mut buf).unwrap();
+    assert_eq!(n, llrb.len() as u64);
+    assert_eq!(&buf[..13], &EXPORT_MAGIC[..]);
+
+    // read back the export.
+    let importer: Importer<i64, i64, &[u8]> = Importer::new(buf.as_slice()).unwrap();
+    assert_eq!(importer.to_app_meta(), app_meta);
+    let entries: Vec<Entry<i64, i64>> = importer.map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), llrb.len());
+    for (e, re) in entries.iter().zip(llrb.iter().unwrap()) {
+        let re = re.unwrap();
+        assert_eq!(e.to_key(), re.to_key());
+        assert_eq!(e.to_seqno(), re.to_seqno());
+        assert_eq!(e.is_deleted(), re.is_deleted());
+        assert_eq!(e.to_native_value(), re.to_native_value());
+    }
+
+    // convert the export back into a robt index.
+    let name = "test-export-import-robt-000";
+    let importer: Importer<i64, i64, &[u8]> = Importer::new(buf.as_slice()).unwrap();
+    let app_meta = importer.to_app_meta();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(importer, app_meta.clone()).unwrap();
+
+    let mut snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.to_app_meta().unwrap(), app_meta);
+    assert_eq!(snapshot.len().unwrap(), llrb.len());
+    for (e, re) in snapshot.iter().unwrap().zip(llrb.iter().unwrap()) {
+        let (e, re) = (e.unwrap(), re.unwrap());
+        assert_eq!(e.to_key(), re.to_key());
+        assert_eq!(e.to_seqno(), re.to_seqno());
+        assert_eq!(e.is_deleted(), re.is_deleted());
+        assert_eq!(e.to_native_value(), re.to_native_value());
+    }
+}
+
+#[test]
+fn test_import_bad_magic() {
+    let mut buf = EXPORT_MAGIC.to_vec();
+    buf[0] = b'x';
+    buf.extend_from_slice(&EXPORT_VERSION.to_be_bytes());
+    buf.extend_from_slice(&0_u64.to_be_bytes());
+
+    match Importer::<i64, i64, &[u8]>::new(buf.as_slice()) {
+        Err(Error::InvalidFile(_)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid file"),
+    }
+}
+
+#[test]
+fn test_import_bad_length() {
+    let mut buf = EXPORT_MAGIC.to_vec();
+    buf.extend_from_slice(&EXPORT_VERSION.to_be_bytes());
+    buf.extend_from_slice(&u64::MAX.to_be_bytes());
+    buf.extend_from_slice(&[1, 2, 3, 4]);
+
+    match Importer::<i64, i64, &[u8]>::new(buf.as_slice()) {
+        Err(Error::InvalidFile(_)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid file"),
+    }
+}
+
+#[test]
+fn test_import_truncated() {
+    let mut buf = vec![];
+    let mut exporter = Exporter::new(&mut buf, &[]).unwrap();
+    let entry = Entry::new(10_i64, Value::new_upsert_value(100_i64, 1));
+    exporter.push(&entry).unwrap();
+    exporter.finish().unwrap();
+    buf.truncate(buf.len() - 4);
+
+    let mut importer = Importer::<i64, i64, &[u8]>::new(buf.as_slice()).unwrap();
+    assert_eq!(importer.next().unwrap().unwrap().to_key(), 10);
+    match importer.next() {
+        Some(Err(Error::IoError(_))) => (),
+        Some(res) => panic!("unexpected {:?}", res.map(|e| e.to_key())),
+        None => panic!("expected io error"),
+    }
+    assert!(importer.next().is_none());
+}
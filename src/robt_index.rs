@@ -4,9 +4,11 @@ use std::{borrow::Borrow, cmp::Ordering, convert::TryInto, marker, ops::Bound};
 
 use crate::{
     core::{self, Diff, Result, Serialize},
-    error::Error,
-    robt::{Config, Flusher, Stats},
+    error::{Error, Internal},
+    robt::{self, Config, Flusher, Stats},
+    robt_compress::ValueDict,
     robt_entry::{MEntry, ZEntry},
+    util,
 };
 
 // Binary format (InterMediate-Block prefix):
@@ -56,6 +58,36 @@ where
         }
     }
 
+    // restore a partially built block, refer to to_encode_state().
+    pub(crate) fn from_encode_state(
+        config: Config,
+        first_key: Option<K>,
+        offsets: Vec<u32>,
+        mut block: Vec<u8>,
+    ) -> MBlock<K, V> {
+        block.reserve(config.m_blocksize.saturating_sub(block.len()));
+        MBlock::Encode {
+            mblock: block,
+            offsets,
+            first_key,
+            m_blocksize: config.m_blocksize,
+        }
+    }
+
+    // return the state of a partially built block, that is, its first
+    // key, entry offsets and encoded entries.
+    pub(crate) fn to_encode_state(&self) -> Result<(Option<&K>, &[u32], &[u8])> {
+        match self {
+            MBlock::Encode {
+                mblock,
+                offsets,
+                first_key,
+                ..
+            } => Ok((first_key.as_ref(), offsets, mblock)),
+            MBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     pub(crate) fn reset(&mut self) -> Result<()> {
         match self {
             MBlock::Encode {
@@ -111,7 +143,7 @@ where
                     Ok(convert_at!(offsets.len())?)
                 } else {
                     mblock.truncate(offset);
-                    Err(Error::__MBlockOverflow(n))
+                    Err(Error::Internal(Internal::MBlockOverflow(n)))
                 }
             }
             MBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
@@ -136,7 +168,7 @@ where
                     Ok(convert_at!(offsets.len())?)
                 } else {
                     mblock.truncate(offset);
-                    Err(Error::__MBlockOverflow(n))
+                    Err(Error::Internal(Internal::MBlockOverflow(n)))
                 }
             }
             MBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
@@ -185,7 +217,7 @@ where
     {
         match self {
             MBlock::Encode { mblock, .. } => match iflusher {
-                Some(iflusher) => iflusher.post(mblock.clone())?,
+                Some(iflusher) => iflusher.post(util::copy_buffer(mblock))?,
                 None => err_at!(Fatal, msg: format!("unreachable"))?,
             },
             MBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable"))?,
@@ -209,13 +241,15 @@ where
     K: Ord + Serialize,
 {
     pub(crate) fn new_decode(block: Vec<u8>) -> Result<MBlock<K, V>> {
-        let count = u32::from_be_bytes(array_at!(block[..4])?);
-        let adjust: usize = convert_at!((4 + (count * 4)))?;
+        check_remaining!(block, 4, "mblock-count")?;
+        let count: usize = convert_at!(u32::from_be_bytes(array_at!(block[..4])?))?;
+        let adjust = 4 + (count * 4);
+        check_remaining!(block, adjust, "mblock-offsets")?;
         let offsets = &block[4..adjust] as *const [u8];
 
         Ok(MBlock::Decode {
             block,
-            count: convert_at!(count)?,
+            count,
             offsets: unsafe { offsets.as_ref().unwrap() },
             phantom_val: marker::PhantomData,
         })
@@ -251,7 +285,7 @@ where
         //    self.to_key(pivot)?
         //);
         match key.cmp(self.to_key(pivot)?.borrow()) {
-            Ordering::Less if pivot == 0 => Err(Error::__LessThan),
+            Ordering::Less if pivot == 0 => Err(Error::Internal(Internal::LessThan)),
             Ordering::Less if pivot == f => err_at!(Fatal, msg: format!("unreachable")),
             Ordering::Less => self.get(key, from, Bound::Excluded(pivot)),
             Ordering::Equal => self.to_entry(pivot),
@@ -280,7 +314,7 @@ where
         //    self.to_key(pivot)?
         //);
         match key.cmp(self.to_key(pivot)?.borrow()) {
-            Ordering::Less if pivot == 0 => Err(Error::__LessThan),
+            Ordering::Less if pivot == 0 => Err(Error::Internal(Internal::LessThan)),
             Ordering::Less if pivot == f => err_at!(Fatal, msg: format!("unreachable")),
             Ordering::Less => self.find(key, from, Bound::Excluded(pivot)),
             Ordering::Equal => self.to_entry(pivot),
@@ -320,9 +354,10 @@ where
         if index < count {
             let idx = index * 4;
             let offset: usize = convert_at!(u32::from_be_bytes(array_at!(offsets[idx..idx + 4])?))?;
+            check_remaining!(block, offset, "mblock-entry")?;
             Ok(MEntry::decode_entry(&block[offset..], index)?)
         } else {
-            Err(Error::__MBlockExhausted(index))
+            Err(Error::Internal(Internal::MBlockExhausted(index)))
         }
     }
 
@@ -340,9 +375,10 @@ where
             let index = count - 1;
             let idx = index * 4;
             let offset: usize = convert_at!(u32::from_be_bytes(array_at!(offsets[idx..idx + 4])?))?;
+            check_remaining!(block, offset, "mblock-entry")?;
             Ok(MEntry::decode_entry(&block[offset..], index)?)
         } else {
-            Err(Error::__MBlockExhausted(count))
+            Err(Error::Internal(Internal::MBlockExhausted(count)))
         }
     }
 
@@ -364,7 +400,7 @@ where
             let offset: usize = convert_at!(u32::from_be_bytes(array_at!(offsets[idx..idx + 4])?))?;
             MEntry::decode_key(&block[offset..])
         } else {
-            Err(Error::__MBlockExhausted(index))
+            Err(Error::Internal(Internal::MBlockExhausted(index)))
         }
     }
 }
@@ -404,6 +440,7 @@ where
         z_blocksize: usize,
         value_in_vlog: bool,
         delta_ok: bool,
+        vlog_segment: usize,
     },
     Decode {
         block: Vec<u8>,
@@ -423,6 +460,7 @@ where
         let z_blocksize = config.z_blocksize;
         let value_in_vlog = config.value_in_vlog;
         let delta_ok = config.delta_ok;
+        let vlog_segment = config.vlog_segment;
 
         ZBlock::Encode {
             leaf: Vec::with_capacity(z_blocksize),
@@ -435,6 +473,7 @@ where
             z_blocksize,
             value_in_vlog,
             delta_ok,
+            vlog_segment,
         }
     }
 
@@ -482,7 +521,14 @@ where
         }
     }
 
-    pub(crate) fn insert(&mut self, entry: &core::Entry<K, V>, stats: &mut Stats) -> Result<u64> {
+    // values and deltas persisted in value-log are compressed, if `dict`
+    // is supplied.
+    pub(crate) fn insert(
+        &mut self,
+        entry: &core::Entry<K, V>,
+        dict: Option<&ValueDict>,
+        stats: &mut Stats,
+    ) -> Result<u64> {
         use crate::robt_entry::ZEntry as DZ;
 
         match self {
@@ -501,9 +547,9 @@ where
                 let (leaf_i, blob_i) = (leaf.len(), blob.len());
                 let de = match (*value_in_vlog, *delta_ok) {
                     (false, false) => DZ::encode_l(entry, leaf)?,
-                    (false, true) => DZ::encode_ld(entry, leaf, blob)?,
-                    (true, false) => DZ::encode_lv(entry, leaf, blob)?,
-                    (true, true) => DZ::encode_lvd(entry, leaf, blob)?,
+                    (false, true) => DZ::encode_ld(entry, leaf, blob, dict)?,
+                    (true, false) => DZ::encode_lv(entry, leaf, blob, dict)?,
+                    (true, true) => DZ::encode_lvd(entry, leaf, blob, dict)?,
                 };
                 let (k, v, d) = de.to_kvd_stats()?;
                 zentries.push(de);
@@ -513,13 +559,15 @@ where
                     stats.key_mem += k;
                     stats.val_mem += v;
                     stats.diff_mem += d;
+                    // older versions are superseded, tombstones are dead.
+                    stats.dead_bytes += if entry.is_deleted() { k + d } else { d };
                     offsets.push(convert_at!(leaf_i)?);
                     first_key.get_or_insert_with(|| entry.as_key().clone());
                     Ok(convert_at!(offsets.len())?)
                 } else {
                     leaf.truncate(leaf_i);
                     blob.truncate(blob_i);
-                    Err(Error::__ZBlockOverflow(n))
+                    Err(Error::Internal(Internal::ZBlockOverflow(n)))
                 }
             }
             ZBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
@@ -536,8 +584,21 @@ where
                 vpos,
                 // configuration
                 z_blocksize,
+                vlog_segment,
                 ..
             } => {
+                // blob may be moved to the next value-log segment.
+                let skip = {
+                    let n: u64 = convert_at!(blob.len())?;
+                    let at = robt::vlog_place(*vpos, n, *vlog_segment)?;
+                    let skip = at - *vpos;
+                    if skip > 0 {
+                        // header of the new segment file.
+                        stats.v_bytes += convert_at!(robt::VLOG_HEADER_SIZE)?;
+                    }
+                    *vpos = at;
+                    skip
+                };
                 let adjust: u32 = {
                     let m = leaf.len();
                     let adjust = 4 + (offsets.len() * 4);
@@ -564,9 +625,10 @@ where
                 // align blocks
                 leaf.resize(*z_blocksize, 0);
 
+                let vbytes: u64 = convert_at!(blob.len())?;
                 Ok((
                     convert_at!(*z_blocksize)?, // full block
-                    convert_at!(blob.len())?,
+                    skip + vbytes,
                 ))
             }
             ZBlock::Decode { .. } => err_at!(Fatal, msg: format!("unreachable")),
@@ -581,11 +643,11 @@ where
         match self {
             ZBlock::Encode { leaf, blob, .. } => {
                 match iflusher {
-                    Some(iflusher) => iflusher.post(leaf.clone())?,
+                    Some(iflusher) => iflusher.post(util::copy_buffer(leaf))?,
                     None => err_at!(Fatal, msg: format!("unreachable"))?,
                 }
                 match vflusher {
-                    Some(vflusher) => vflusher.post(blob.clone())?,
+                    Some(vflusher) => vflusher.post(util::copy_buffer(blob))?,
                     None => (),
                 }
             }
@@ -610,13 +672,15 @@ where
     <V as Diff>::D: Serialize,
 {
     pub(crate) fn new_decode(block: Vec<u8>) -> Result<ZBlock<K, V>> {
-        let count = u32::from_be_bytes(array_at!(block[..4])?);
-        let adjust: usize = convert_at!((4 + (count * 4)))?;
+        check_remaining!(block, 4, "zblock-count")?;
+        let count: usize = convert_at!(u32::from_be_bytes(array_at!(block[..4])?))?;
+        let adjust = 4 + (count * 4);
+        check_remaining!(block, adjust, "zblock-offsets")?;
         let offsets = &block[4..adjust] as *const [u8];
 
         Ok(ZBlock::Decode {
             block,
-            count: convert_at!(count)?,
+            count,
             offsets: unsafe { offsets.as_ref().unwrap() },
             phantom_val: marker::PhantomData,
         })
@@ -657,11 +721,11 @@ where
         //    self.to_key(pivot)?
         //);
         match key.cmp(self.to_key(pivot)?.borrow()) {
-            Ordering::Less if pivot == 0 => Err(Error::__LessThan),
+            Ordering::Less if pivot == 0 => Err(Error::Internal(Internal::LessThan)),
             Ordering::Less if pivot == f => err_at!(Fatal, msg: format!("unreachable")),
             Ordering::Less => self.find(key, from, Bound::Excluded(pivot)),
             Ordering::Equal => self.to_entry(pivot),
-            Ordering::Greater if pivot == f => Err(Error::__ZBlockExhausted(f)),
+            Ordering::Greater if pivot == f => Err(Error::Internal(Internal::ZBlockExhausted(f))),
             Ordering::Greater => self.find(key, Bound::Included(pivot), to),
         }
     }
@@ -701,10 +765,11 @@ where
         if index < count {
             let idx = index * 4;
             let offset: usize = convert_at!(u32::from_be_bytes(array_at!(offsets[idx..idx + 4])?))?;
+            check_remaining!(block, offset, "zblock-entry")?;
             let entry = &block[offset..];
             Ok((index, ZEntry::decode_entry(entry)?))
         } else {
-            Err(Error::__ZBlockExhausted(index))
+            Err(Error::Internal(Internal::ZBlockExhausted(index)))
         }
     }
 
@@ -726,10 +791,11 @@ where
             let index = count - 1;
             let idx = index * 4;
             let offset: usize = convert_at!(u32::from_be_bytes(array_at!(offsets[idx..idx + 4])?))?;
+            check_remaining!(block, offset, "zblock-entry")?;
             let entry = &block[offset..];
             Ok((index, ZEntry::decode_entry(entry)?))
         } else {
-            Err(Error::__ZBlockExhausted(count))
+            Err(Error::Internal(Internal::ZBlockExhausted(count)))
         }
     }
 
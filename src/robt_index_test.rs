@@ -22,7 +22,7 @@ fn test_mblock_m() {
                 keys.push((key, fpos));
                 assert_eq!(n, (i as u64) + 1);
             }
-            Err(Error::__MBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::MBlockOverflow(_n))) => {
                 break;
             }
             _ => unreachable!(),
@@ -95,8 +95,8 @@ fn test_mblock_m() {
                 assert_eq!(index, i - 1);
             }
             Ok(MEntry::DecM { index, .. }) => panic!("why ok {}", index),
-            Err(Error::__LessThan) if i == 0 => (),
-            Err(Error::__MBlockExhausted(_n)) if i == keys.len() => (),
+            Err(Error::Internal(Internal::LessThan)) if i == 0 => (),
+            Err(Error::Internal(Internal::MBlockExhausted(_n))) if i == keys.len() => (),
             Err(err) => panic!("unexpected err {:?}", err),
             _ => unreachable!(),
         }
@@ -111,8 +111,8 @@ fn test_mblock_m() {
                 assert_eq!(fpos, ((i - 1) * 4096) as u64);
                 assert_eq!(index, i - 1);
             }
-            Err(Error::__LessThan) if key == (entry.0 - 1) => (),
-            Err(Error::__MBlockExhausted(_n)) if key == (entry.0 + 1) => (),
+            Err(Error::Internal(Internal::LessThan)) if key == (entry.0 - 1) => (),
+            Err(Error::Internal(Internal::MBlockExhausted(_n))) if key == (entry.0 + 1) => (),
             _ => unreachable!(),
         }
     }
@@ -130,11 +130,11 @@ fn test_mblock_m() {
 
     let index = keys.len();
     match mb.to_entry(index) {
-        Err(Error::__MBlockExhausted(n)) => assert_eq!(index, n),
+        Err(Error::Internal(Internal::MBlockExhausted(n))) => assert_eq!(index, n),
         _ => unreachable!(),
     }
     match mb.to_key(index) {
-        Err(Error::__MBlockExhausted(n)) => assert_eq!(index, n),
+        Err(Error::Internal(Internal::MBlockExhausted(n))) => assert_eq!(index, n),
         _ => unreachable!(),
     }
 }
@@ -154,7 +154,7 @@ fn test_mblock_z() {
                 keys.push((key, fpos));
                 assert_eq!(n, (i as u64) + 1);
             }
-            Err(Error::__MBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::MBlockOverflow(_n))) => {
                 break;
             }
             _ => unreachable!(),
@@ -226,7 +226,7 @@ fn test_mblock_z() {
                 assert_eq!(index, i - 1);
             }
             Ok(MEntry::DecZ { index, .. }) => panic!("why ok {}", index),
-            Err(Error::__LessThan) if i == 0 => (),
+            Err(Error::Internal(Internal::LessThan)) if i == 0 => (),
             Err(err) => panic!("unexpected err {:?}", err),
             _ => unreachable!(),
         }
@@ -242,18 +242,18 @@ fn test_mblock_z() {
                 assert_eq!(index, i - 1);
             }
             Ok(MEntry::DecZ { index, .. }) => panic!("why ok {}", index),
-            Err(Error::__LessThan) if i == 0 => (),
+            Err(Error::Internal(Internal::LessThan)) if i == 0 => (),
             _ => unreachable!(),
         }
     }
 
     let index = keys.len();
     match mb.to_entry(index) {
-        Err(Error::__MBlockExhausted(n)) => assert_eq!(index, n),
+        Err(Error::Internal(Internal::MBlockExhausted(n))) => assert_eq!(index, n),
         _ => unreachable!(),
     }
     match mb.to_key(index) {
-        Err(Error::__MBlockExhausted(n)) => assert_eq!(index, n),
+        Err(Error::Internal(Internal::MBlockExhausted(n))) => assert_eq!(index, n),
         _ => unreachable!(),
     }
 }
@@ -272,9 +272,9 @@ fn test_zblock1() {
     let mut stats: Stats = Default::default();
     let mut val_mem = 0;
     for (i, entry) in entries.iter_mut().enumerate() {
-        match zb.insert(entry, &mut stats) {
+        match zb.insert(entry, None, &mut stats) {
             Ok(n) => assert_eq!(n, (i as u64) + 1),
-            Err(Error::__ZBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::ZBlockOverflow(_n))) => {
                 entries.truncate(i);
                 break;
             }
@@ -350,12 +350,12 @@ fn test_zblock1() {
 
     let key = entries[0].to_key() - 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__LessThan) => (),
+        Err(Error::Internal(Internal::LessThan)) => (),
         _ => unreachable!(),
     }
     let key = entries[entries.len() - 1].to_key() + 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__ZBlockExhausted(k)) => assert_eq!(k as i32, key - 2),
+        Err(Error::Internal(Internal::ZBlockExhausted(k))) => assert_eq!(k as i32, key - 2),
         _ => unreachable!(),
     }
 }
@@ -374,9 +374,9 @@ fn test_zblock2() {
     let mut stats: Stats = Default::default();
     let (mut val_mem, mut diff_mem) = (0, 0);
     for (i, entry) in entries.iter_mut().enumerate() {
-        match zb.insert(entry, &mut stats) {
+        match zb.insert(entry, None, &mut stats) {
             Ok(n) => assert_eq!(n, (i as u64) + 1),
-            Err(Error::__ZBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::ZBlockOverflow(_n))) => {
                 entries.truncate(i);
                 break;
             }
@@ -478,12 +478,12 @@ fn test_zblock2() {
     }
     let key = entries[0].to_key() - 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__LessThan) => (),
+        Err(Error::Internal(Internal::LessThan)) => (),
         _ => unreachable!(),
     }
     let key = entries[entries.len() - 1].to_key() + 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__ZBlockExhausted(k)) => assert_eq!(k as i32, key - 2),
+        Err(Error::Internal(Internal::ZBlockExhausted(k))) => assert_eq!(k as i32, key - 2),
         _ => unreachable!(),
     }
 }
@@ -502,9 +502,9 @@ fn test_zblock3() {
     let mut stats: Stats = Default::default();
     let mut val_mem = 0;
     for (i, entry) in entries.iter_mut().enumerate() {
-        match zb.insert(entry, &mut stats) {
+        match zb.insert(entry, None, &mut stats) {
             Ok(n) => assert_eq!(n, (i as u64) + 1),
-            Err(Error::__ZBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::ZBlockOverflow(_n))) => {
                 entries.truncate(i);
                 break;
             }
@@ -588,12 +588,12 @@ fn test_zblock3() {
     }
     let key = entries[0].to_key() - 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__LessThan) => (),
+        Err(Error::Internal(Internal::LessThan)) => (),
         _ => unreachable!(),
     }
     let key = entries[entries.len() - 1].to_key() + 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__ZBlockExhausted(k)) => assert_eq!(k as i32, key - 2),
+        Err(Error::Internal(Internal::ZBlockExhausted(k))) => assert_eq!(k as i32, key - 2),
         _ => unreachable!(),
     }
 }
@@ -612,9 +612,9 @@ fn test_zblock4() {
     let mut stats: Stats = Default::default();
     let (mut val_mem, mut diff_mem) = (0, 0);
     for (i, entry) in entries.iter_mut().enumerate() {
-        match zb.insert(entry, &mut stats) {
+        match zb.insert(entry, None, &mut stats) {
             Ok(n) => assert_eq!(n, (i as u64) + 1),
-            Err(Error::__ZBlockOverflow(_n)) => {
+            Err(Error::Internal(Internal::ZBlockOverflow(_n))) => {
                 entries.truncate(i);
                 break;
             }
@@ -733,12 +733,12 @@ fn test_zblock4() {
     }
     let key = entries[0].to_key() - 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__LessThan) => (),
+        Err(Error::Internal(Internal::LessThan)) => (),
         _ => unreachable!(),
     }
     let key = entries[entries.len() - 1].to_key() + 1;
     match zb.find(&key, Bound::Unbounded, Bound::Unbounded) {
-        Err(Error::__ZBlockExhausted(k)) => assert_eq!(k as i32, key - 2),
+        Err(Error::Internal(Internal::ZBlockExhausted(k))) => assert_eq!(k as i32, key - 2),
         _ => unreachable!(),
     }
 }
@@ -0,0 +1,703 @@
+//! External merge sort, used by robt builder to sort entries that do
+//! not fit in memory, refer to [ExtSorter].
+//!
+//! Items are buffered in memory until their serialized footprint
+//! exceeds the configured limit, after which buffered items are sorted
+//! and spilled into a run-file. Once all items are pushed, run-files are
+//! k-way merged to return items in sort order.
+
+use log::debug;
+
+use std::{
+    convert::TryInto,
+    ffi, fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    mem, path, vec,
+};
+
+use crate::{
+    core::{self, CasPolicy, Diff, Entry, Replay, Result, Serialize},
+    error::Error,
+    util,
+    wal::Op,
+};
+
+/// Default memory limit for sorting entries, before spilling them
+/// to disk.
+pub(crate) const SORT_LIMIT: usize = 256 * 1024 * 1024; // 256MB
+
+pub(crate) struct Sorter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    dir: ffi::OsString,
+    name: String,
+    mem_limit: usize,
+
+    items: Vec<T>,
+    n_bytes: usize,
+    runs: Vec<ffi::OsString>,
+    buf: Vec<u8>,
+}
+
+impl<T> Drop for Sorter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    fn drop(&mut self) {
+        remove_runs(&self.runs)
+    }
+}
+
+impl<T> Sorter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    /// Create a new sorter, run-files, if any, are created under `dir`
+    /// with `name` as prefix.
+    pub(crate) fn new(dir: &ffi::OsStr, name: &str, mem_limit: usize) -> Sorter<T> {
+        Sorter {
+            dir: dir.to_os_string(),
+            name: name.to_string(),
+            mem_limit,
+
+            items: vec![],
+            n_bytes: 0,
+            runs: vec![],
+            buf: vec![],
+        }
+    }
+
+    pub(crate) fn push(&mut self, item: T) -> Result<()> {
+        self.buf.truncate(0);
+        self.n_bytes += item.encode(&mut self.buf)?;
+        self.items.push(item);
+        if self.n_bytes >= self.mem_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Return the number of run-files spilled so far.
+    pub(crate) fn to_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Return an iterator over all items pushed into the sorter, in
+    /// sort order. Items comparing equal are returned in the order they
+    /// were pushed.
+    pub(crate) fn into_iter(mut self) -> Result<SortIter<T>> {
+        if self.runs.len() == 0 {
+            let mut items = mem::replace(&mut self.items, vec![]);
+            items.sort();
+            return Ok(SortIter::Mem(items.into_iter()));
+        }
+
+        if self.items.len() > 0 {
+            self.spill()?;
+        }
+
+        let runs = mem::replace(&mut self.runs, vec![]);
+        let mut readers = vec![];
+        for file in runs.iter() {
+            readers.push(RunReader::new(file)?);
+        }
+        let mut heads = vec![];
+        for reader in readers.iter_mut() {
+            heads.push(reader.next_item()?);
+        }
+        Ok(SortIter::Merge {
+            runs,
+            readers,
+            heads,
+        })
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        let mut items = mem::replace(&mut self.items, vec![]);
+        items.sort();
+
+        let file: ffi::OsString = {
+            let mut file = path::PathBuf::from(&self.dir);
+            file.push(format!("{}-sort-run-{}", self.name, self.runs.len()));
+            file.into_os_string()
+        };
+        // track the run-file before writing, so that it gets cleaned up
+        // on failure.
+        self.runs.push(file.clone());
+
+        let mut w = BufWriter::new(util::create_file_a(file.clone())?);
+        for item in items.into_iter() {
+            self.buf.truncate(0);
+            let n = item.encode(&mut self.buf)?;
+            let n: u64 = convert_at!(n)?;
+            err_at!(IoError, w.write_all(&n.to_be_bytes()))?;
+            err_at!(IoError, w.write_all(&self.buf))?;
+        }
+        err_at!(IoError, w.flush())?;
+
+        debug!(
+            target: "robt  ",
+            "{:?}, spilled {} bytes of sort items", file, self.n_bytes
+        );
+        self.n_bytes = 0;
+
+        Ok(())
+    }
+}
+
+/// Iterate over sorted items, refer to [Sorter::into_iter].
+pub(crate) enum SortIter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    Mem(vec::IntoIter<T>),
+    Merge {
+        runs: Vec<ffi::OsString>,
+        readers: Vec<RunReader>,
+        heads: Vec<Option<T>>,
+    },
+}
+
+impl<T> Drop for SortIter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    fn drop(&mut self) {
+        match self {
+            SortIter::Mem(_) => (),
+            SortIter::Merge { runs, .. } => remove_runs(runs),
+        }
+    }
+}
+
+impl<T> Iterator for SortIter<T>
+where
+    T: Default + Ord + Serialize,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortIter::Mem(iter) => iter.next().map(Ok),
+            SortIter::Merge { readers, heads, .. } => {
+                // on equal items, older run-file wins.
+                let mut next: Option<(usize, &T)> = None;
+                for (i, head) in heads.iter().enumerate() {
+                    next = match (next, head) {
+                        (None, Some(item)) => Some((i, item)),
+                        (Some((_, min)), Some(item)) if item.lt(min) => Some((i, item)),
+                        (next, _) => next,
+                    };
+                }
+                let i = next?.0;
+                match readers[i].next_item() {
+                    Ok(item) => mem::replace(&mut heads[i], item).map(Ok),
+                    Err(err) => Some(Err(err)),
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct RunReader {
+    file: ffi::OsString,
+    r: BufReader<fs::File>,
+    buf: Vec<u8>,
+}
+
+impl RunReader {
+    fn new(file: &ffi::OsStr) -> Result<RunReader> {
+        let fd = util::open_file_r(file)?;
+        Ok(RunReader {
+            file: file.to_os_string(),
+            r: BufReader::new(fd),
+            buf: vec![],
+        })
+    }
+
+    fn next_item<T>(&mut self) -> Result<Option<T>>
+    where
+        T: Default + Serialize,
+    {
+        let mut scratch = [0_u8; 8];
+        match self.r.read_exact(&mut scratch) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => err_at!(IoError, msg: format!("{:?} {}", self.file, err))?,
+        }
+        let n = u64::from_be_bytes(scratch);
+        let n: usize = convert_at!(n)?;
+        self.buf.resize(n, 0);
+        err_at!(IoError, self.r.read_exact(&mut self.buf))?;
+
+        let mut item: T = Default::default();
+        item.decode(&self.buf)?;
+        Ok(Some(item))
+    }
+}
+
+fn remove_runs(runs: &[ffi::OsString]) {
+    for file in runs.iter() {
+        // NOTE: ignore remove errors, run-files are scratch data.
+        fs::remove_file(file).ok();
+    }
+}
+
+/// Sort entries that are not in key order, using a memory budget.
+///
+/// [Builder::build][crate::robt::Builder::build] expects entries in
+/// sort order. Applications having unsorted input can push them into
+/// ExtSorter and feed the sorted iterator, refer to
+/// [into_iter][ExtSorter::into_iter], to the builder, instead of
+/// loading them into a memory index. Entries are buffered in memory
+/// until their serialized size exceeds `mem_limit`, then buffered
+/// entries are sorted and spilled into a temporary run-file, which are
+/// merged back while iterating. Run-files are removed once the sorter
+/// or its iterator is dropped.
+///
+/// Only the latest version of each entry is sorted, older versions are
+/// ignored. When the same key is pushed more than once, entry with the
+/// highest seqno is returned, and between entries with same seqno, the
+/// one pushed last is returned.
+pub struct ExtSorter<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    sorter: Sorter<SortEntry<K, V>>,
+    n_pushed: usize,
+}
+
+impl<K, V> ExtSorter<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    /// Create a new sorter, run-files, if any, are created under `dir`
+    /// with `name` as prefix.
+    pub fn new(dir: &ffi::OsStr, name: &str, mem_limit: usize) -> Result<ExtSorter<K, V>> {
+        if mem_limit == 0 {
+            err_at!(InvalidInput, msg: format!("sort limit of 0 bytes"))?;
+        }
+        Ok(ExtSorter {
+            sorter: Sorter::new(dir, name, mem_limit),
+            n_pushed: 0,
+        })
+    }
+
+    /// Push an entry into the sorter, entries can be pushed in any
+    /// order.
+    pub fn push(&mut self, entry: Entry<K, V>) -> Result<()> {
+        let value = match entry.to_native_value() {
+            Some(value) => Some(value),
+            None if entry.is_deleted() => None,
+            None => err_at!(InvalidInput, msg: format!("value not in memory"))?,
+        };
+        let item = SortEntry {
+            key: entry.to_key(),
+            seqno: entry.to_seqno(),
+            flags: entry.to_flags(),
+            value,
+            n: self.n_pushed,
+        };
+        self.sorter.push(item)?;
+        self.n_pushed += 1;
+        Ok(())
+    }
+
+    /// Return the number of run-files spilled so far.
+    pub fn to_runs(&self) -> usize {
+        self.sorter.to_runs()
+    }
+
+    /// Return an iterator over entries in key order, one entry for each
+    /// key.
+    pub fn into_iter(self) -> Result<ExtSortIter<K, V>> {
+        Ok(ExtSortIter {
+            iter: self.sorter.into_iter()?,
+            head: None,
+        })
+    }
+}
+
+/// Iterator over sorted entries, refer to [ExtSorter::into_iter].
+pub struct ExtSortIter<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    iter: SortIter<SortEntry<K, V>>,
+    head: Option<SortEntry<K, V>>,
+}
+
+impl<K, V> Iterator for ExtSortIter<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut latest = match self.head.take() {
+            Some(item) => item,
+            None => match self.iter.next()? {
+                Ok(item) => item,
+                Err(err) => return Some(Err(err)),
+            },
+        };
+        // items are sorted on (key, seqno, n), last item for a key wins.
+        loop {
+            match self.iter.next() {
+                Some(Ok(item)) if item.key == latest.key => latest = item,
+                Some(Ok(item)) => {
+                    self.head = Some(item);
+                    break;
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None => break,
+            }
+        }
+        Some(Ok(latest.into_entry()))
+    }
+}
+
+// Entry as sort item, ordered on (key, seqno, n), where `n` is the
+// order in which it was pushed.
+#[derive(Default)]
+struct SortEntry<K, V> {
+    key: K,
+    seqno: u64,
+    flags: u8,
+    value: Option<V>,
+    n: usize,
+}
+
+impl<K, V> SortEntry<K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn into_entry(self) -> Entry<K, V> {
+        let value = match self.value {
+            Some(value) => core::Value::new_upsert_value(value, self.seqno),
+            None => core::Value::new_delete(self.seqno),
+        };
+        let mut entry = Entry::new(self.key, value);
+        entry.set_flags(self.flags);
+        entry
+    }
+}
+
+impl<K, V> PartialEq for SortEntry<K, V>
+where
+    K: Ord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl<K, V> Eq for SortEntry<K, V> where K: Ord {}
+
+impl<K, V> PartialOrd for SortEntry<K, V>
+where
+    K: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V> Ord for SortEntry<K, V>
+where
+    K: Ord,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.key, self.seqno, self.n).cmp(&(&other.key, other.seqno, other.n))
+    }
+}
+
+// | seqno (u64) | n (u64) | flags (u8) | deleted (u8) |
+// | key-len (u32) | key | value |
+impl<K, V> Serialize for SortEntry<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let m = buf.len();
+        buf.extend_from_slice(&self.seqno.to_be_bytes());
+        let n: u64 = convert_at!(self.n)?;
+        buf.extend_from_slice(&n.to_be_bytes());
+        buf.push(self.flags);
+        buf.push(if self.value.is_none() { 1 } else { 0 });
+
+        let k = buf.len();
+        buf.extend_from_slice(&[0_u8; 4]);
+        let n = self.key.encode(buf)?;
+        let n: u32 = convert_at!(n)?;
+        buf[k..k + 4].copy_from_slice(&n.to_be_bytes());
+
+        if let Some(value) = &self.value {
+            value.encode(buf)?;
+        }
+        Ok(buf.len() - m)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        check_remaining!(buf, 22, "sort-entry-header")?;
+        self.seqno = u64::from_be_bytes(array_at!(buf[..8])?);
+        let n = u64::from_be_bytes(array_at!(buf[8..16])?);
+        self.n = convert_at!(n)?;
+        self.flags = buf[16];
+        let deleted = buf[17] == 1;
+
+        let n = u32::from_be_bytes(array_at!(buf[18..22])?);
+        let n: usize = convert_at!(n)?;
+        check_remaining!(buf, 22 + n, "sort-entry-key")?;
+        self.key.decode(&buf[22..22 + n])?;
+
+        let mut m = 22 + n;
+        self.value = match deleted {
+            true => None,
+            false => {
+                let mut value: V = Default::default();
+                m += value.decode(&buf[m..])?;
+                Some(value)
+            }
+        };
+        Ok(m)
+    }
+}
+
+// Write-ahead-log operation tagged with its seqno, sorted on
+// (key, seqno).
+#[derive(Default)]
+pub(crate) struct WalItem<K, V> {
+    seqno: u64,
+    op: Op<K, V>,
+}
+
+impl<K, V> WalItem<K, V> {
+    fn as_key(&self) -> Option<&K> {
+        match &self.op {
+            Op::Set { key, .. } => Some(key),
+            Op::SetCAS { key, .. } => Some(key),
+            Op::Delete { key } => Some(key),
+            Op::App { .. } => None,
+        }
+    }
+}
+
+impl<K, V> PartialEq for WalItem<K, V>
+where
+    K: Ord,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.seqno == other.seqno && self.as_key() == other.as_key()
+    }
+}
+
+impl<K, V> Eq for WalItem<K, V> where K: Ord {}
+
+impl<K, V> PartialOrd for WalItem<K, V>
+where
+    K: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V> Ord for WalItem<K, V>
+where
+    K: Ord,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.as_key(), self.seqno).cmp(&(other.as_key(), other.seqno))
+    }
+}
+
+impl<K, V> Serialize for WalItem<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        buf.extend_from_slice(&self.seqno.to_be_bytes());
+        Ok(8 + self.op.encode(buf)?)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        check_remaining!(buf, 8, "wal-item-seqno")?;
+        self.seqno = u64::from_be_bytes(array_at!(buf[..8])?);
+        Ok(8 + self.op.decode(&buf[8..])?)
+    }
+}
+
+// Collect write-ahead-log operations, upto a seqno, into a sorter,
+// refer to Builder::build_from_wal.
+pub(crate) struct WalSink<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    sorter: Sorter<WalItem<K, V>>,
+    upto_seqno: u64,
+    policy: CasPolicy<V>,
+    n_ops: usize,
+}
+
+impl<K, V> WalSink<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    pub(crate) fn new(sorter: Sorter<WalItem<K, V>>, upto_seqno: u64) -> WalSink<K, V> {
+        WalSink {
+            sorter,
+            upto_seqno,
+            policy: Default::default(),
+            n_ops: 0,
+        }
+    }
+
+    fn push(&mut self, seqno: u64, op: Op<K, V>) -> Result<()> {
+        if seqno <= self.upto_seqno {
+            self.sorter.push(WalItem { seqno, op })?;
+            self.n_ops += 1;
+        }
+        Ok(())
+    }
+
+    /// Return the number of operations collected so far.
+    pub(crate) fn to_ops(&self) -> usize {
+        self.n_ops
+    }
+
+    /// Return an iterator of entries, one for each key, after folding
+    /// all collected operations in seqno order.
+    pub(crate) fn into_entries(self) -> Result<WalFold<K, V>> {
+        Ok(WalFold {
+            iter: self.sorter.into_iter()?,
+            policy: self.policy,
+            head: None,
+        })
+    }
+}
+
+impl<K, V> Replay<K, V> for WalSink<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    fn set_index(&mut self, key: K, value: V, index: u64) -> Result<()> {
+        self.push(index, Op::new_set(key, value))
+    }
+
+    fn set_cas_index(&mut self, key: K, value: V, cas: u64, index: u64) -> Result<()> {
+        self.push(index, Op::new_set_cas(key, value, cas))
+    }
+
+    fn delete_index(&mut self, key: K, index: u64) -> Result<()> {
+        self.push(index, Op::new_delete(key))
+    }
+
+    fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<()> {
+        self.policy = policy;
+        Ok(())
+    }
+}
+
+pub(crate) struct WalFold<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    iter: SortIter<WalItem<K, V>>,
+    policy: CasPolicy<V>,
+    head: Option<WalItem<K, V>>,
+}
+
+impl<K, V> WalFold<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    fn apply(
+        &self,
+        entry: Option<Entry<K, V>>,
+        item: WalItem<K, V>,
+    ) -> Result<Option<Entry<K, V>>> {
+        let seqno = item.seqno;
+        match item.op {
+            Op::Set { key, value } => {
+                let value = core::Value::new_upsert_value(value, seqno);
+                Ok(Some(Entry::new(key, value)))
+            }
+            Op::SetCAS { key, value, cas } => match self.policy.check(entry.as_ref(), cas) {
+                Ok(()) => {
+                    let value = core::Value::new_upsert_value(value, seqno);
+                    Ok(Some(Entry::new(key, value)))
+                }
+                // same as replaying on an index, failed cas is a no-op.
+                Err(Error::InvalidCAS(_)) => Ok(entry),
+                Err(err) => Err(err),
+            },
+            Op::Delete { key } => {
+                let value = core::Value::new_delete(seqno);
+                Ok(Some(Entry::new(key, value)))
+            }
+            Op::App { .. } => Ok(entry),
+        }
+    }
+
+    fn fold_key(&mut self, item: WalItem<K, V>) -> Result<Option<Entry<K, V>>> {
+        let key = item.as_key().cloned();
+        let mut entry = self.apply(None, item)?;
+        loop {
+            match self.iter.next() {
+                Some(Ok(item)) if item.as_key() == key.as_ref() => {
+                    entry = self.apply(entry, item)?;
+                }
+                Some(Ok(item)) => {
+                    self.head = Some(item);
+                    break Ok(entry);
+                }
+                Some(Err(err)) => break Err(err),
+                None => break Ok(entry),
+            }
+        }
+    }
+}
+
+impl<K, V> Iterator for WalFold<K, V>
+where
+    K: Clone + Default + Ord + Serialize,
+    V: Clone + Default + Diff + Serialize,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = match self.head.take() {
+                Some(item) => item,
+                None => match self.iter.next()? {
+                    Ok(item) => item,
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+            // skip keys, whose operations are all rejected.
+            match self.fold_key(item) {
+                Ok(Some(entry)) => break Some(Ok(entry)),
+                Ok(None) => (),
+                Err(err) => break Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "robt_sort_test.rs"]
+mod robt_sort_test;
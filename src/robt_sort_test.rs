@@ -0,0 +1,142 @@
+use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
+
+use super::*;
+
+#[test]
+fn test_sorter() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-sorter");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    for (n, mem_limit, runs) in vec![(0, 1024, 0), (100, 1024, 0), (10_000, 1024, 78)] {
+        let mut sorter: Sorter<i64> = Sorter::new(&dir, "sorter", mem_limit);
+        let mut items: Vec<i64> = vec![];
+        for _ in 0..n {
+            let item: i64 = rng.gen::<i64>() % 1000;
+            sorter.push(item).unwrap();
+            items.push(item);
+        }
+        assert_eq!(sorter.to_runs(), runs);
+        items.sort();
+
+        let iter = sorter.into_iter().unwrap();
+        let out: Vec<i64> = iter.map(|item| item.unwrap()).collect();
+        assert_eq!(out, items);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    }
+}
+
+#[test]
+fn test_wal_fold() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-wal-fold");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let sorter = Sorter::new(&dir, "fold", 64);
+    let mut sink: WalSink<i64, i64> = WalSink::new(sorter, 8);
+    sink.set_cas_policy(CasPolicy::Strict).unwrap();
+    sink.set_index(20, 200, 1).unwrap();
+    sink.set_index(10, 100, 2).unwrap();
+    sink.set_cas_index(20, 201, 1, 3).unwrap();
+    sink.set_cas_index(10, 101, 1, 4).unwrap(); // invalid cas
+    sink.delete_index(30, 5).unwrap();
+    sink.set_cas_index(40, 400, 7, 6).unwrap(); // invalid cas
+    sink.set_cas_index(30, 300, 5, 7).unwrap();
+    sink.delete_index(10, 8).unwrap();
+    sink.set_index(10, 102, 9).unwrap(); // beyond upto_seqno
+    assert_eq!(sink.to_ops(), 8);
+
+    let entries: Vec<Entry<i64, i64>> = sink
+        .into_entries()
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .collect();
+    let entries: Vec<(i64, Option<i64>, u64)> = entries
+        .into_iter()
+        .map(|e| (e.to_key(), e.to_native_value(), e.to_seqno()))
+        .collect();
+    let refs = vec![(10, None, 8), (20, Some(201), 3), (30, Some(300), 7)];
+    assert_eq!(entries, refs);
+}
+
+#[test]
+fn test_ext_sorter_build() {
+    use crate::{
+        nobitmap::NoBitmap,
+        robt::{self, Builder, Snapshot},
+    };
+    use std::collections::BTreeMap;
+
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-ext-sorter-build");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut sorter: ExtSorter<i64, i64> = ExtSorter::new(&dir, "entries", 4096).unwrap();
+    let mut refs: BTreeMap<i64, (Option<i64>, u64, u8)> = BTreeMap::new();
+    for _ in 0..10_000 {
+        let key: i64 = rng.gen::<i64>().abs() % 2_000;
+        let seqno: u64 = rng.gen::<u64>() % 100_000;
+        let flags: u8 = rng.gen();
+        let (mut entry, value) = match rng.gen::<u8>() % 4 {
+            0 => (Entry::new(key, core::Value::new_delete(seqno)), None),
+            _ => {
+                let value: i64 = rng.gen();
+                let entry = Entry::new(key, core::Value::new_upsert_value(value, seqno));
+                (entry, Some(value))
+            }
+        };
+        entry.set_flags(flags);
+        sorter.push(entry).unwrap();
+        match refs.get(&key) {
+            Some((_, s, _)) if *s > seqno => (),
+            _ => {
+                refs.insert(key, (value, seqno, flags));
+            }
+        }
+    }
+    assert!(sorter.to_runs() > 0);
+
+    let name = "test-robt-ext-sorter-build-robt-000";
+    let config: robt::Config = Default::default();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(sorter.into_iter().unwrap(), vec![]).unwrap();
+
+    let runs = fs::read_dir(&dir)
+        .unwrap()
+        .filter(|item| {
+            let file = item.as_ref().unwrap().file_name();
+            file.to_string_lossy().contains("sort-run")
+        })
+        .count();
+    assert_eq!(runs, 0);
+
+    let mut snap = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    let entries: Vec<Entry<i64, i64>> = snap.iter().unwrap().map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), refs.len());
+    for (entry, (key, (value, seqno, flags))) in entries.into_iter().zip(refs.into_iter()) {
+        assert_eq!(entry.to_key(), key);
+        assert_eq!(entry.to_native_value(), value);
+        assert_eq!(entry.to_seqno(), seqno);
+        assert_eq!(entry.to_flags(), flags);
+    }
+}
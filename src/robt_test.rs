@@ -3,11 +3,12 @@ use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
 use super::*;
 use crate::{
-    core::{self, Delta, Index, Reader, Writer},
+    core::{self, Delta, Index, OrdSpec, Reader, Value, Writer},
     croaring::CRoaring,
     llrb::Llrb,
     nobitmap::NoBitmap,
     robt, scans,
+    types::{AsciiCaseless, Ordered},
 };
 
 #[test]
@@ -57,6 +58,47 @@ fn test_to_next_version() {
     assert_eq!(index.to_version().unwrap(), 1);
 }
 
+#[test]
+fn test_commit_within() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let name = "test-robt-commit-within".to_string();
+    let mut config: Config = Default::default();
+    config.name = name.clone();
+    let dir = std::env::temp_dir().into_os_string();
+
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, &name, config).unwrap();
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm(name.clone());
+    for key in 0..100 {
+        mindex.set(key, key * 10).unwrap();
+    }
+
+    let testcases = vec![
+        ((Unbounded, Included(50)), (Unbounded, Included(50)), 50),
+        // retry shall skip seqnos that are already persisted.
+        ((Unbounded, Included(50)), (Excluded(50), Included(50)), 50),
+        (
+            (Excluded(40), Included(80)),
+            (Excluded(50), Included(80)),
+            80,
+        ),
+        ((Unbounded, Unbounded), (Excluded(80), Unbounded), 100),
+    ];
+    for (window, covered, seqno) in testcases.into_iter() {
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        let res = index.commit_within(scanner, window, |meta| meta);
+        assert_eq!(res.unwrap(), covered);
+        assert_eq!(index.to_seqno().unwrap(), seqno);
+
+        let mut r = index.to_reader().unwrap();
+        let n = r.iter().unwrap().count();
+        assert_eq!(n, seqno as usize);
+    }
+
+    index.purge().unwrap();
+}
+
 #[test]
 fn test_purge() {
     let name = Name("somename-0-robt-000".to_string());
@@ -129,6 +171,11 @@ fn test_stats() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        cipher_key_id: "users-key-1".to_string(),
+        ord_spec: "ascii-caseless".to_string(),
+        vlog_segment: 1048576,
+        compressor: "zdict".to_string(),
+        align: 8192,
 
         n_count: 1000000,
         n_deleted: 100,
@@ -143,14 +190,67 @@ fn test_stats() {
         n_bitmap: 1000000,
         padding: 100000000,
         n_abytes: 0,
+        dead_bytes: 1000000,
 
         build_time: 10000000000000,
         epoch: 121345678998765,
+        build_uuid: "0123456789abcdef0123456789abcdef".to_string(),
+        lineage: vec![
+            "00000000000000000000000000000001".to_string(),
+            "00000000000000000000000000000002".to_string(),
+        ],
+        vlog_uuid: "00000000000000000000000000000002".to_string(),
     };
     let s = stats1.to_json();
     let stats2: Stats = s.parse().unwrap();
     assert!(stats1 == stats2);
 
+    // stats persisted before versioning, vlog_segment, compressor and
+    // align were added later.
+    let s = s
+        .replace(r#""schema_version": 3, "#, "")
+        .replace(r#""vlog_segment": 1048576, "#, "")
+        .replace(r#""compressor": "zdict", "#, "")
+        .replace(r#""align": 8192, "#, "");
+    let stats2: Stats = s.parse().unwrap();
+    assert_eq!(stats2.vlog_segment, 0);
+    assert_eq!(stats2.compressor, "");
+    assert_eq!(stats2.align, 0);
+    assert_eq!(Config::from(stats2.clone()).align, Config::BLOCK_ALIGN);
+    assert_eq!(stats2.lineage, stats1.lineage);
+    // but fields of version-0 are required.
+    match s.replace(r#""n_deleted": 100, "#, "").parse::<Stats>() {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("n_deleted"), "{}", msg),
+        res => panic!("{:?}", res.map(|_| ())),
+    }
+
+    // stats as persisted by baseline releases, before cipher, ordering,
+    // dead-bytes and lineage were tracked.
+    let s = r#"{ "name": "test_stats", "z_blocksize": 16384, "m_blocksize": 4096,
+        "v_blocksize": 65536, "delta_ok": true, "vlog_file": "robt-users-level-1.vlog",
+        "value_in_vlog": true, "flush_queue_size": 64, "seqno": 2000000,
+        "n_count": 1000000, "n_deleted": 100, "key_mem": 128000000, "val_mem": 1024000000,
+        "diff_mem": 512000000, "z_bytes": 256000000, "m_bytes": 256000000,
+        "v_bytes": 2048000000, "mem_bitmap": 12310000, "n_bitmap": 1000000,
+        "padding": 100000000, "n_abytes": 0, "build_time": 10000000000000,
+        "epoch": 121345678998765 }"#;
+    let stats2: Stats = s.parse().unwrap();
+    assert_eq!(stats2.name, stats1.name);
+    assert_eq!(stats2.vlog_file, stats1.vlog_file);
+    assert_eq!(stats2.flush_queue_size, 64);
+    assert_eq!(stats2.seqno, stats1.seqno);
+    assert_eq!(stats2.n_deleted, stats1.n_deleted);
+    assert_eq!(stats2.epoch, stats1.epoch);
+    assert_eq!(stats2.cipher_key_id, "");
+    assert_eq!(stats2.ord_spec, "");
+    assert_eq!(stats2.dead_bytes, 0);
+    assert_eq!(stats2.build_uuid, "");
+    assert_eq!(stats2.lineage.len(), 0);
+    assert_eq!(stats2.vlog_uuid, "");
+    let config = Config::from(stats2);
+    assert!(config.cipher.is_none());
+    assert_eq!(config.ord_spec, Config::default().ord_spec);
+
     let vlog_file: &ffi::OsStr = "robt-users-level-1.vlog".as_ref();
     let dir: &ffi::OsStr = "/path/to/dummy/dir".as_ref();
     let cnf = Config {
@@ -163,6 +263,18 @@ fn test_stats() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        flush_depth: Config::FLUSH_DEPTH,
+        cipher: None,
+        ord_spec: "ascii-caseless".to_string(),
+        vlog_segment: 0,
+        compressor: None,
+        dict_samples: 0,
+        dict_size: 0,
+        align: Config::ALIGN,
+        max_index_size: 0,
+        min_fanout: Config::MIN_FANOUT,
+        auto_blocksize: false,
+        strict_unique: false,
     };
     let stats1: Stats = cnf.into();
     let s = stats1.to_json();
@@ -181,6 +293,11 @@ fn test_stats_merge() {
         vlog_file: None,
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        cipher_key_id: "".to_string(),
+        ord_spec: "".to_string(),
+        vlog_segment: 0,
+        compressor: "".to_string(),
+        align: 4096,
 
         n_count: 1,
         n_deleted: 1,
@@ -195,9 +312,13 @@ fn test_stats_merge() {
         n_bitmap: 1,
         padding: 1,
         n_abytes: 2,
+        dead_bytes: 1,
 
         build_time: 1,
         epoch: 1,
+        build_uuid: "1".to_string(),
+        lineage: vec![],
+        vlog_uuid: "1".to_string(),
     };
     let stats2 = Stats {
         name: "test_stats".to_string(),
@@ -208,6 +329,11 @@ fn test_stats_merge() {
         vlog_file: None,
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        cipher_key_id: "".to_string(),
+        ord_spec: "".to_string(),
+        vlog_segment: 0,
+        compressor: "".to_string(),
+        align: 4096,
 
         n_count: 2,
         n_deleted: 2,
@@ -222,12 +348,16 @@ fn test_stats_merge() {
         n_bitmap: 2,
         padding: 2,
         n_abytes: 2,
+        dead_bytes: 2,
 
         build_time: 2,
         epoch: 2,
+        build_uuid: "2".to_string(),
+        lineage: vec!["1".to_string()],
+        vlog_uuid: "1".to_string(),
     };
 
-    let stats = stats1.merge(stats2);
+    let stats = stats1.clone().merge(stats2.clone());
     assert_eq!(stats.z_blocksize, 16384);
     assert_eq!(stats.m_blocksize, 4096);
     assert_eq!(stats.v_blocksize, 65536);
@@ -249,8 +379,79 @@ fn test_stats_merge() {
     assert_eq!(stats.n_bitmap, 3);
     assert_eq!(stats.padding, 3);
     assert_eq!(stats.n_abytes, 4);
+    assert_eq!(stats.dead_bytes, 3);
     assert_eq!(stats.build_time, 2);
     assert_eq!(stats.epoch, 2);
+    assert_eq!(stats.build_uuid, "2".to_string());
+    assert_eq!(stats.lineage, vec!["1".to_string()]);
+    assert_eq!(stats.vlog_uuid, "1".to_string());
+
+    // latest seqno, epoch and longest build-time, irrespective of order.
+    let mut stats3 = stats2.clone();
+    stats3.build_time = 3;
+    stats3.epoch = 1;
+    stats3.lineage = vec!["1".to_string(), "3".to_string()];
+    let stats = stats3.merge_ref(&stats1);
+    assert_eq!(stats.n_count, 3);
+    assert_eq!(stats.seqno, 2);
+    assert_eq!(stats.build_time, 3);
+    assert_eq!(stats.epoch, 1);
+    assert_eq!(stats.build_uuid, "1".to_string());
+    assert_eq!(stats.lineage, vec!["1".to_string(), "3".to_string()]);
+}
+
+#[test]
+fn test_advice() {
+    let config: Config = Default::default();
+
+    let stats: Stats = config.clone().into();
+    assert!(stats.to_advice().is_empty());
+
+    // small keys and values, nothing to recommend.
+    let mut stats: Stats = config.clone().into();
+    stats.n_count = 1000;
+    stats.key_mem = 16 * 1000;
+    stats.val_mem = 100 * 1000;
+    stats.z_bytes = 120 * 1000;
+    let advice = stats.to_advice();
+    assert!(advice.is_empty(), "{:?}", advice);
+
+    // large values, move them to value-log and fit them in v-blocks.
+    stats.val_mem = 8192 * 1000;
+    let advice = stats.to_advice();
+    assert_eq!(advice.value_in_vlog, Some(true));
+    assert_eq!(advice.blocksize, Some((4096, 8192, 4096)));
+    assert_eq!(advice.delta_ok, None);
+    assert_eq!(advice.compact, false);
+    assert_eq!(advice.notes.len(), 2);
+
+    let mut config = config.clone();
+    advice.apply(&mut config).unwrap();
+    assert_eq!(config.value_in_vlog, true);
+    assert_eq!(config.v_blocksize, 8192);
+
+    // tiny values in value-log, large keys, long delta chains.
+    let mut stats: Stats = config.clone().into();
+    stats.n_count = 1000;
+    stats.key_mem = 512 * 1000;
+    stats.val_mem = 8 * 1000;
+    stats.diff_mem = 64 * 1000;
+    let advice = stats.to_advice();
+    assert_eq!(advice.value_in_vlog, Some(false));
+    assert_eq!(advice.blocksize, Some((16384, 8192, 8192)));
+    assert_eq!(advice.delta_ok, Some(false));
+
+    // mostly dead.
+    let mut stats: Stats = Config::default().into();
+    stats.n_count = 1000;
+    stats.n_deleted = 900;
+    stats.key_mem = 16 * 1000;
+    stats.val_mem = 16 * 100;
+    stats.z_bytes = 40 * 1000;
+    stats.dead_bytes = 30 * 1000;
+    let advice = stats.to_advice();
+    assert!(advice.compact);
+    assert_eq!(advice.value_in_vlog, None);
 }
 
 #[test]
@@ -283,8 +484,8 @@ fn test_meta_items() {
         MetaItem::Stats(stats.clone()),
         MetaItem::Marker(ROOT_MARKER.clone()),
     ];
-    let n = write_meta_items(file, meta_items).unwrap();
-    let ref_n = Config::compute_root_block(32 + len1 + len2 + len3);
+    let n = write_meta_items(file, meta_items, Config::ALIGN).unwrap();
+    let ref_n = Config::compute_root_block(56 + len1 + len2 + len3);
     assert_eq!(n, ref_n as u64);
 
     let iter = read_meta_items(&dir, &name).unwrap().0.into_iter();
@@ -295,11 +496,152 @@ fn test_meta_items() {
             (2, MetaItem::AppMetadata(value)) => assert_eq!(value, app_meta),
             (3, MetaItem::Stats(value)) => assert_eq!(value, stats),
             (4, MetaItem::Marker(v)) => assert_eq!(v, ROOT_MARKER.clone()),
+            (5, MetaItem::RangeTombstones(v)) => assert_eq!(v, vec![]),
+            (6, MetaItem::Dictionary(v)) => assert_eq!(v, vec![]),
             (i, _) => panic!("at {}, failure", i),
         }
     }
 }
 
+#[test]
+fn test_corrupted_meta() {
+    let seed: u128 = random();
+    println!("test_corrupted_meta seed {}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = std::env::temp_dir().into_os_string();
+    let name = "test-corrupted-meta-robt-0".to_string();
+    let file = Config::stitch_index_file(&dir, &name);
+
+    fs::write(&file, [1, 2, 3]).unwrap();
+    match read_meta_items(&dir, &name) {
+        Err(Error::CorruptedMeta(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|x| x.1)),
+    }
+
+    fs::write(&file, [1, 2, 3, 4, 5]).unwrap();
+    let meta_items = vec![
+        MetaItem::Root(5),
+        MetaItem::Bitmap(vec![]),
+        MetaItem::AppMetadata(vec![10, 20, 30]),
+        MetaItem::Stats(<Stats as Default>::default().to_json()),
+        MetaItem::Marker(ROOT_MARKER.clone()),
+        MetaItem::RangeTombstones(vec![]),
+        MetaItem::Dictionary(vec![1, 2, 3, 4]),
+    ];
+    write_meta_items(file.clone(), meta_items, Config::ALIGN).unwrap();
+    let good = fs::read(&file).unwrap();
+    match &read_meta_items(&dir, &name).unwrap().0[6] {
+        MetaItem::Dictionary(dict) => assert_eq!(dict, &vec![1, 2, 3, 4]),
+        item => panic!("unexpected {}", item),
+    }
+
+    // absurd lengths shall be rejected before any allocation, offsets
+    // of dictionary, range-tombstones, bitmap, app-metadata and stats.
+    for i in [56, 48, 32, 24, 16].iter() {
+        for len in [std::u64::MAX, std::u64::MAX - 40, good.len() as u64].iter() {
+            let mut data = good.clone();
+            let off = data.len() - i;
+            data[off..off + 8].copy_from_slice(&len.to_be_bytes());
+            fs::write(&file, &data).unwrap();
+            match read_meta_items(&dir, &name) {
+                Err(Error::CorruptedMeta(_)) => (),
+                res => panic!("unexpected {} {:?}", i, res.map(|x| x.1)),
+            }
+        }
+    }
+
+    // random corruption of the header shall never panic.
+    for _ in 0..1000 {
+        let mut data = good.clone();
+        let n = data.len();
+        for _ in 0..rng.gen_range(1, 8) {
+            let off = rng.gen_range(n - 56, n);
+            data[off] = rng.gen();
+        }
+        fs::write(&file, &data).unwrap();
+        read_meta_items(&dir, &name).ok();
+
+        let mut hdr = [0_u8; 56];
+        rng.fill(&mut hdr[..]);
+        decode_meta_header(&hdr, rng.gen()).ok();
+    }
+
+    // unknown header version.
+    let mut data = good.clone();
+    let off = data.len() - 8;
+    data[off] = 0xFF;
+    fs::write(&file, &data).unwrap();
+    match read_meta_items(&dir, &name) {
+        Err(Error::InvalidFile(_)) => (),
+        res => panic!("unexpected {:?}", res.map(|x| x.1)),
+    }
+
+    fs::remove_file(&file).ok();
+}
+
+#[test]
+fn test_legacy_header() {
+    let name = "test-robt-legacy-header";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let config: robt::Config = Default::default();
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..100 {
+        mindex.set(key, key * 10).unwrap();
+    }
+    let file_name: Name = (name.to_string(), 0).into();
+    let file = Config::stitch_index_file(&dir, &file_name.0);
+
+    // rewrite the header as version 1, without dictionary length, and
+    // as version 0, without range-tombstones length, keeping the
+    // meta-block size unchanged.
+    for (version, n_strip) in [(1_u8, 8), (0_u8, 16)].iter() {
+        {
+            let b = Builder::<i64, i64, NoBitmap>::initial(&dir, &file_name.0, config.clone());
+            b.unwrap().build(mindex.iter().unwrap(), vec![]).unwrap();
+        }
+
+        let data = fs::read(&file).unwrap();
+        let (n, n_meta) = (data.len(), read_meta_items(&dir, &file_name.0).unwrap().1);
+        assert_eq!(data[n - 56..n - 40].to_vec(), vec![0; 16]);
+        let mut legacy = data[..n - n_meta].to_vec();
+        legacy.extend_from_slice(&vec![0; *n_strip]);
+        legacy.extend_from_slice(&data[n - n_meta..n - 56]);
+        legacy.extend_from_slice(&data[n - 56 + n_strip..]);
+        legacy[n - 8] = 0;
+        legacy[n - 7] = *version;
+        fs::write(&file, &legacy).unwrap();
+
+        let (items, n_legacy) = read_meta_items(&dir, &file_name.0).unwrap();
+        assert_eq!(n_legacy, n_meta);
+        match (&items[5], &items[6]) {
+            (MetaItem::RangeTombstones(tombs), MetaItem::Dictionary(dict)) => {
+                assert_eq!(tombs.len(), 0);
+                assert_eq!(dict.len(), 0);
+            }
+            (item, _) => panic!("unexpected {}", item),
+        }
+
+        let mut index = robt_factory::<i64, i64, NoBitmap>(config.clone())
+            .open(&dir, name)
+            .unwrap();
+        let mut r = index.to_reader().unwrap();
+        for key in 0..100 {
+            assert_eq!(r.get(&key).unwrap().to_native_value(), Some(key * 10));
+        }
+        assert_eq!(r.iter().unwrap().count(), 100);
+        assert_eq!(r.to_range_tombstones().unwrap().len(), 0);
+        r.validate().unwrap();
+    }
+}
+
 #[test]
 fn test_config() {
     let vlog_file: &ffi::OsStr = "same-file.log".as_ref();
@@ -314,6 +656,18 @@ fn test_config() {
         vlog_file: Some(vlog_file.to_os_string()),
         value_in_vlog: true,
         flush_queue_size: Config::FLUSH_QUEUE_SIZE,
+        flush_depth: Config::FLUSH_DEPTH,
+        cipher: None,
+        ord_spec: "ascii-caseless".to_string(),
+        vlog_segment: 0,
+        compressor: None,
+        dict_samples: 0,
+        dict_size: 0,
+        align: Config::ALIGN,
+        max_index_size: 0,
+        min_fanout: Config::MIN_FANOUT,
+        auto_blocksize: false,
+        strict_unique: false,
     };
 
     let stats: Stats = config1.clone().into();
@@ -325,6 +679,7 @@ fn test_config() {
     assert_eq!(config2.vlog_file, config1.vlog_file);
     assert_eq!(config2.value_in_vlog, config1.value_in_vlog);
     assert_eq!(config2.flush_queue_size, Config::FLUSH_QUEUE_SIZE);
+    assert_eq!(config2.ord_spec, config1.ord_spec);
 
     config1
         .set_blocksize(1024 * 8, 1024 * 32, 1024 * 64)
@@ -332,16 +687,46 @@ fn test_config() {
     config1.set_delta(None, false).unwrap();
     config1.set_value_log(None, false).unwrap();
     config1.set_flush_queue_size(1023).unwrap();
+    config1.set_flush_depth(4).unwrap();
+    config1.set_max_index_size(1024 * 1024).unwrap();
     assert_eq!(config1.z_blocksize, 1024 * 8);
     assert_eq!(config1.v_blocksize, 1024 * 32);
     assert_eq!(config1.m_blocksize, 1024 * 64);
     assert_eq!(config1.delta_ok, false);
     assert_eq!(config1.value_in_vlog, false);
     assert_eq!(config1.flush_queue_size, 1023);
+    assert_eq!(config1.flush_depth, 4);
+    assert_eq!(config1.max_index_size, 1024 * 1024);
+
+    assert!(config1.validate().is_ok());
+    for (z, v, m) in [(1000, 1024, 4096), (4096, 0, 4096), (4096, 1024, 0)].iter() {
+        config1.set_blocksize(*z, *v, *m).unwrap();
+        match config1.validate() {
+            Err(Error::InvalidInput(_)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    config1
+        .set_blocksize(1024 * 8, 1024 * 32, 1024 * 64)
+        .unwrap();
+    for align in [256, 1000, 1024 * 16].iter() {
+        config1.set_align(*align).unwrap();
+        match config1.validate() {
+            Err(Error::InvalidInput(_)) => (),
+            res => panic!("{} {:?}", align, res),
+        }
+    }
+    config1.set_align(1024 * 8).unwrap();
+    assert!(config1.validate().is_ok());
 
     assert_eq!(Config::compute_root_block(4095), 4096);
     assert_eq!(Config::compute_root_block(4096), 4096);
     assert_eq!(Config::compute_root_block(4097), 8192);
+    assert_eq!(Config::compute_meta_block(4097, 0), 8192);
+    assert_eq!(Config::compute_meta_block(4097, 512), 8192);
+    assert_eq!(Config::compute_meta_block(4095, 16384), 16384);
+    assert_eq!(Config::compute_meta_block(16385, 16384), 32768);
 
     let dir_path = std::env::temp_dir();
     let dir = dir_path.clone().into_os_string();
@@ -848,6 +1233,113 @@ fn test_compact_lsm_cutoff() {
     }
 }
 
+#[test]
+fn test_compact_filter() {
+    struct TestFilter;
+
+    impl core::CompactionFilter<i64, i64> for TestFilter {
+        fn filter(&self, entry: &Entry<i64, i64>) -> Result<core::Decision<i64>> {
+            match entry.to_key() {
+                key if key % 2 == 0 => Ok(core::Decision::Drop),
+                key if key % 5 == 0 => Ok(core::Decision::Modify(-key)),
+                _ => Ok(core::Decision::Keep),
+            }
+        }
+    }
+
+    let name = "test-compact-filter";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let config: robt::Config = Default::default();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..1000 {
+        mindex.set(key, key).unwrap();
+    }
+    for key in 0..1000 {
+        mindex.set(key, key * 10).unwrap();
+    }
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+    {
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    }
+
+    // with empty cutoff, compaction shall still apply the filter.
+    let filter: Arc<dyn core::CompactionFilter<i64, i64>> = Arc::new(TestFilter);
+    index.set_compaction_filter(Some(filter)).unwrap();
+    assert_eq!(index.compact(Cutoff::new_lsm_empty()).unwrap(), 500);
+
+    let entries: Vec<Entry<i64, i64>> = {
+        let mut r = index.to_reader().unwrap();
+        let iter = r.iter_with_versions().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+    assert_eq!(entries.len(), 500);
+    for entry in entries.into_iter() {
+        let key = entry.to_key();
+        assert_eq!(key % 2, 1);
+        if key % 5 == 0 {
+            assert_eq!(entry.to_native_value(), Some(-key));
+            assert_eq!(entry.as_deltas().len(), 0);
+        } else {
+            assert_eq!(entry.to_native_value(), Some(key * 10));
+            assert_eq!(entry.as_deltas().len(), 1);
+        }
+        assert_eq!(entry.to_seqno(), (key as u64) + 1001);
+    }
+}
+
+#[test]
+fn test_bytes_written() {
+    struct TestFilter;
+
+    impl core::CompactionFilter<i64, i64> for TestFilter {
+        fn filter(&self, _entry: &Entry<i64, i64>) -> Result<core::Decision<i64>> {
+            Ok(core::Decision::Keep)
+        }
+    }
+
+    let name = "test-bytes-written";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let config: robt::Config = Default::default();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..1000 {
+        mindex.set(key, key).unwrap();
+    }
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+    assert_eq!(index.to_bytes_written().unwrap(), 0);
+    {
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    }
+    let n = index.to_bytes_written().unwrap();
+    assert!(n > 0, "{}", n);
+    // clones share the counter, re-opened instance start afresh.
+    assert_eq!(index.clone().to_bytes_written().unwrap(), n);
+    {
+        let index = Robt::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+        assert_eq!(index.to_bytes_written().unwrap(), 0);
+    }
+
+    // with a filter, compaction always rewrites the index.
+    let filter: Arc<dyn core::CompactionFilter<i64, i64>> = Arc::new(TestFilter);
+    index.set_compaction_filter(Some(filter)).unwrap();
+    assert_eq!(index.compact(Cutoff::new_lsm_empty()).unwrap(), 1000);
+    let m = index.to_bytes_written().unwrap();
+    assert!(m > n, "{} {}", m, n);
+}
+
 #[test]
 fn test_commit_scan() {
     let seed: u128 = random();
@@ -1294,364 +1786,2263 @@ fn test_commit_iterator_range_scans() {
     }
 }
 
-fn run_robt_llrb(name: &str, n_ops: u64, key_max: i64, repeat: usize, seed: u128) {
-    for i in 0..repeat {
-        let mut n_ops = n_ops;
-        let seed = seed + (i as u128);
-        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
-        // populate llrb
-        let lsm: bool = rng.gen();
-        let sticky: bool = rng.gen();
-        let mut llrb: Box<Llrb<i64, i64>> = if lsm {
+#[test]
+fn test_get_deadline() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+
+    let (n_ops, key_max) = (6_000_i64, 2_000);
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(n_ops, key_max, seed, &mut llrb);
+
+    let iter = {
+        let iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+        core::CommitIter::new(
+            scans::CommitWrapper::new(vec![Box::new(iter)]),
+            (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+        )
+    };
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-get-deadline");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    let mut snapshot = {
+        let name = "test-robt-get-deadline";
+        let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+        index.commit(iter, |_| vec![]).unwrap();
+        index.to_reader().unwrap()
+    };
+
+    let timeout = time::Duration::from_secs(100);
+    for e in llrb.iter().unwrap() {
+        let e = e.unwrap();
+        let re = snapshot.get_deadline(e.as_key(), timeout).unwrap();
+        check_entry1(&e, &re);
+
+        match snapshot.get_deadline(e.as_key(), time::Duration::from_secs(0)) {
+            Err(Error::TimedOut) => (),
+            res => panic!("expected timeout {:?}", res.map(|e| e.to_seqno())),
+        }
+    }
+    match snapshot.get_deadline(&(key_max + 1), timeout) {
+        Err(Error::KeyNotFound) => (),
+        res => panic!("expected key-not-found {:?}", res.map(|e| e.to_seqno())),
+    }
+}
+
+#[test]
+fn test_reclaimable() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+
+    for (i, lsm) in [false, true].iter().enumerate() {
+        let (n_ops, key_max) = (6_000_i64, 2_000);
+        let mut llrb: Box<Llrb<i64, i64>> = if *lsm {
             Llrb::new_lsm("test-llrb")
         } else {
             Llrb::new("test-llrb")
         };
-        llrb.set_sticky(sticky).unwrap();
-
-        random_llrb(n_ops as i64, key_max, seed, &mut llrb);
-
-        // to avoid screwing up the seqno in non-lsm mode, say, what if
-        // the last operation was a delete.
-        llrb.set(123, 123456789).unwrap();
-        n_ops += 1;
+        random_llrb(n_ops, key_max, seed, &mut llrb);
+        let n_deleted = llrb
+            .iter()
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().is_deleted())
+            .count();
 
-        // build ROBT
-        let mut config: robt::Config = Default::default();
-        config.delta_ok = lsm;
-        config.value_in_vlog = rng.gen();
-        let within = match rng.gen::<u64>() % 100 {
-            0..=60 => (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
-            61..=70 => (Bound::<u64>::Excluded(1), Bound::<u64>::Unbounded),
-            71..=80 => (Bound::<u64>::Included(1), Bound::<u64>::Unbounded),
-            81..=90 => {
-                let x = rng.gen::<u64>() % n_ops;
-                (Bound::<u64>::Excluded(x), Bound::<u64>::Unbounded)
-            }
-            91..=100 => (Bound::<u64>::Excluded(n_ops), Bound::<u64>::Unbounded),
-            _ => unreachable!(),
+        let iter = {
+            let iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+            core::CommitIter::new(
+                scans::CommitWrapper::new(vec![Box::new(iter)]),
+                (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+            )
         };
-        let mmap = rng.gen::<bool>();
-        println!(
-            "seed:{} n_ops:{} lsm:{} sticky:{} delta:{} vlog:{}
-            within:{:?} mmap:{}",
-            seed, n_ops, lsm, sticky, config.delta_ok, config.value_in_vlog, within, mmap,
-        );
-        let (mut llrb, refs) = llrb_to_refs1(llrb, within.clone(), &config);
-        let n_deleted: usize = refs
-            .iter()
-            .map(|e| if e.is_deleted() { 1 } else { 0 })
-            .sum();
-        // println!("refs len: {}", refs.len());
-        let mut iter = scans::SkipScan::new(llrb.to_reader().unwrap());
-        iter.set_seqno_range(within).unwrap();
         let dir = {
             let mut dir = std::env::temp_dir();
-            dir.push("test-robt-build");
+            dir.push("test-robt-reclaimable");
             dir.into_os_string()
         };
-        let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config.clone()).unwrap();
-        let app_meta = "heloo world".to_string();
-        b.build(iter, app_meta.as_bytes().to_vec()).unwrap();
-
-        let mut snap = robt::Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
-        assert_eq!(snap.len().unwrap(), refs.len());
-        match snap.validate() {
-            Err(Error::EmptyIndex) if refs.len() == 0 => continue,
-            Err(err) => panic!("{:?}", err),
-            Ok(_) => (),
-        }
-        snap.set_mmap(mmap).unwrap();
-        assert_eq!(snap.to_app_meta().unwrap(), app_meta.as_bytes().to_vec());
-        let stats = snap.to_stats().unwrap();
-        assert_eq!(stats.z_blocksize, config.z_blocksize);
-        assert_eq!(stats.m_blocksize, config.m_blocksize);
-        assert_eq!(stats.v_blocksize, config.v_blocksize);
-        assert_eq!(stats.delta_ok, config.delta_ok);
-        assert_eq!(stats.value_in_vlog, config.value_in_vlog);
-        if lsm || sticky {
-            assert_eq!(stats.n_deleted, n_deleted);
+        let mut config: robt::Config = Default::default();
+        config.delta_ok = *lsm;
+        let name = format!("test-robt-reclaimable-{}", i);
+        let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, &name, config).unwrap();
+        index.commit(iter, |_| vec![]).unwrap();
+
+        let stats = index.to_reader().unwrap().to_stats().unwrap();
+        println!("lsm:{} n_deleted:{} {}", lsm, n_deleted, stats);
+        let advice = index.to_reader().unwrap().advice().unwrap();
+        assert_eq!(advice, stats.to_advice());
+        assert_eq!(stats.n_abytes, 0);
+        if n_deleted == 0 && !*lsm {
+            assert_eq!(stats.dead_bytes, 0);
+        } else {
+            assert!(stats.dead_bytes > 0);
         }
+        assert_eq!(index.to_reclaimable(false).unwrap(), 0);
+        assert_eq!(index.to_reclaimable(true).unwrap(), stats.dead_bytes);
+        assert!(stats.to_reclaimable_ratio(true) < 1.0);
+        assert_eq!(stats.to_reclaimable_ratio(false), 0.0);
+    }
+}
 
-        // test get
-        let mut seqno = 0;
-        for entry in refs.iter() {
-            let e = snap.get(entry.as_key()).unwrap();
-            check_entry1(&entry, &e);
+#[test]
+fn test_pressure() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-pressure");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, "test-pressure", config).unwrap();
+    assert_eq!(index.to_pressure().unwrap(), Pressure::Ok);
+    assert!(index.set_pressure_limits(0.9, 0.5, false).is_err());
+
+    let mut seqno = 0;
+    for i in 0..3 {
+        let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+        llrb.set_seqno(seqno).unwrap();
+        random_llrb(1_000, 500, seed + i, &mut llrb);
+        seqno = llrb.to_seqno().unwrap();
+        let iter = {
+            let iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+            core::CommitIter::new(
+                scans::CommitWrapper::new(vec![Box::new(iter)]),
+                (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+            )
+        };
+        let version = index.to_version().unwrap();
+        index.commit(iter, |meta| meta).unwrap();
+
+        let stats = index.to_reader().unwrap().to_stats().unwrap();
+        let ratio = stats.to_reclaimable_ratio(false);
+        println!("i:{} ratio:{} {:?}", i, ratio, index.to_pressure());
+        match i {
+            0 => {
+                assert_eq!(ratio, 0.0);
+                assert_eq!(index.to_pressure().unwrap(), Pressure::Ok);
+                assert_eq!(index.to_version().unwrap(), version + 1);
+            }
+            1 => {
+                assert!(ratio > 0.0 && ratio < 1.0, "{}", ratio);
+                index.set_pressure_limits(ratio / 2.0, 1.0, false).unwrap();
+                assert_eq!(index.to_pressure().unwrap(), Pressure::SlowDown);
+                index.set_pressure_limits(0.0, ratio / 2.0, false).unwrap();
+                assert_eq!(index.to_pressure().unwrap(), Pressure::Stall);
+                assert_eq!(index.to_version().unwrap(), version + 1);
+                // next commit shall compact the index first.
+                index.set_pressure_limits(0.0, ratio / 2.0, true).unwrap();
+            }
+            _ => assert_eq!(index.to_version().unwrap(), version + 2),
+        }
+    }
+}
+
+#[test]
+fn test_lineage() {
+    let seed: u128 = random();
+    println!("seed: {}", seed);
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-lineage");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, "test-lineage", config).unwrap();
+
+    let (mut uuids, mut seqno): (Vec<String>, u64) = (vec![], 0);
+    for i in 0..3 {
+        let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+        llrb.set_seqno(seqno).unwrap();
+        random_llrb(1_000, 500, seed + i, &mut llrb);
+        seqno = llrb.to_seqno().unwrap();
+        let iter = {
+            let iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+            core::CommitIter::new(
+                scans::CommitWrapper::new(vec![Box::new(iter)]),
+                (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+            )
+        };
+        index.commit(iter, |meta| meta).unwrap();
+
+        let lineage = index.to_reader().unwrap().to_lineage().unwrap();
+        assert_eq!(lineage.build_uuid.len(), 32);
+        assert!(!uuids.contains(&lineage.build_uuid));
+        match uuids.last() {
+            Some(uuid) => assert_eq!(lineage.sources, vec![uuid.clone()]),
+            None => assert_eq!(lineage.sources.len(), 0),
+        }
+        uuids.push(lineage.build_uuid);
+    }
+
+    index.compact(Cutoff::new_lsm(Bound::Included(1))).unwrap();
+    let lineage = index.to_reader().unwrap().to_lineage().unwrap();
+    assert!(!uuids.contains(&lineage.build_uuid));
+    assert_eq!(lineage.sources, vec![uuids.last().unwrap().clone()]);
+}
+
+#[test]
+fn test_iter_blocks() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-iter-blocks");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.set_blocksize(512, 1024, 512).unwrap();
+    config.value_in_vlog = true;
+
+    let name = "test-iter-blocks-robt-000";
+    let entries: Vec<Result<Entry<i64, i64>>> = (0..1000_i64)
+        .map(|key| Ok(Entry::new(key, Value::new_upsert_value(key * 10, 1))))
+        .collect();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(entries.into_iter(), vec![]).unwrap();
+
+    let mut snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    let ref_entries: Vec<Entry<i64, i64>> = {
+        let iter = snapshot.iter().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+
+    let (mut fpos, mut n_mblocks, mut entries) = (None, 0, vec![]);
+    for block in snapshot.iter_blocks().unwrap() {
+        let block = block.unwrap();
+        // blocks are returned in physical order.
+        let next = Some(block.to_fpos());
+        assert!(fpos < next, "{:?} {:?}", fpos, next);
+        fpos = next;
+        match block {
+            Block::M { children, .. } => {
+                assert!(children.len() > 0);
+                n_mblocks += 1;
+            }
+            Block::Z { entries: es, .. } => entries.extend(es),
+        }
+    }
+    assert!(n_mblocks > 1, "{}", n_mblocks);
+
+    assert_eq!(entries.len(), ref_entries.len());
+    for (entry, ref_entry) in entries.into_iter().zip(ref_entries.into_iter()) {
+        assert_eq!(entry.to_key(), ref_entry.to_key());
+        assert_eq!(entry.to_native_value(), ref_entry.to_native_value());
+        assert_eq!(entry.to_seqno(), ref_entry.to_seqno());
+    }
+}
+
+#[test]
+fn test_vlog_pairing() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-vlog-pairing");
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.value_in_vlog = true;
+
+    let names = ["test-pairing-a-robt-000", "test-pairing-b-robt-000"];
+    for name in names.iter() {
+        let entries: Vec<Result<Entry<i64, i64>>> = (0..1000_i64)
+            .map(|key| Ok(Entry::new(key, Value::new_upsert_value(key * 10, 1))))
+            .collect();
+        let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+        b.build(entries.into_iter(), vec![]).unwrap();
+
+        let mut snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+        let stats = snapshot.to_stats().unwrap();
+        assert_eq!(stats.vlog_uuid, stats.build_uuid);
+        assert_eq!(snapshot.get(&10).unwrap().to_native_value(), Some(100));
+    }
+
+    // pair index-a with vlog-b.
+    let vlog_a = Config::stitch_vlog_file(&dir, names[0]);
+    let vlog_b = Config::stitch_vlog_file(&dir, names[1]);
+    fs::copy(&vlog_b, &vlog_a).unwrap();
+    match Snapshot::<i64, i64, NoBitmap>::open(&dir, names[0]) {
+        Err(Error::InvalidFile(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid file"),
+    }
+
+    // truncated vlog, without header.
+    fs::write(&vlog_a, &[0_u8; 10]).unwrap();
+    match Snapshot::<i64, i64, NoBitmap>::open(&dir, names[0]) {
+        Err(Error::InvalidFile(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid file"),
+    }
+}
+
+struct XorCipher(u8);
+
+impl Cipher for XorCipher {
+    fn to_key_id(&self) -> String {
+        format!("test-xor-{}", self.0)
+    }
+
+    fn encrypt(&self, nonce: &[u8; 16], fpos: u64, data: &mut [u8]) -> Result<()> {
+        for (i, byte) in data.iter_mut().enumerate() {
+            let pos = fpos + (i as u64);
+            *byte ^= self.0 ^ nonce[(pos % 16) as usize] ^ (pos as u8);
+        }
+        Ok(())
+    }
+
+    fn decrypt(&self, nonce: &[u8; 16], fpos: u64, data: &mut [u8]) -> Result<()> {
+        self.encrypt(nonce, fpos, data)
+    }
+}
+
+#[test]
+fn test_cipher() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let name = "test-robt-cipher";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let cipher: Arc<dyn Cipher> = Arc::new(XorCipher(0x5a));
+    register_cipher(Arc::clone(&cipher)).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    config.set_cipher(Some(cipher)).unwrap();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config.clone()).unwrap();
+    {
+        random_llrb(10_000, 5_000, seed, &mut mindex);
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    };
+
+    let ref_entries: Vec<Entry<i64, i64>> = {
+        let iter = mindex.iter().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+    for round in 0..2 {
+        let mut r = index.to_reader().unwrap();
+        assert_eq!(r.to_stats().unwrap().cipher_key_id, "test-xor-90");
+        let entries: Vec<Entry<i64, i64>> = {
+            let iter = r.iter_with_versions().unwrap();
+            iter.map(|e| e.unwrap()).collect()
+        };
+        assert_eq!(ref_entries.len(), entries.len(), "round:{}", round);
+        for (e, re) in entries.iter().zip(ref_entries.iter()) {
+            check_entry1(e, re);
+        }
+        // compacted index shall be encrypted with the same cipher, none
+        // of the entries are older than the cutoff.
+        index.compact(Cutoff::new_lsm(Bound::Excluded(1))).unwrap();
+    }
+
+    // key-id recorded in the index is not registered.
+    let name = "test-robt-cipher-unregistered-robt-000";
+    config.set_cipher(Some(Arc::new(XorCipher(0x11)))).unwrap();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(ref_entries.into_iter().map(Ok), vec![]).unwrap();
+    match Snapshot::<i64, i64, NoBitmap>::open(&dir, name) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid input"),
+    }
+}
+
+// shares the longest common prefix with dictionary.
+struct PrefixCompressor(String);
+
+impl Compressor for PrefixCompressor {
+    fn to_name(&self) -> String {
+        self.0.clone()
+    }
+
+    fn train(&self, samples: &[Vec<u8>], dict_size: usize) -> Result<Vec<u8>> {
+        let mut dict = samples[0].clone();
+        dict.truncate(dict_size);
+        Ok(dict)
+    }
+
+    fn compress(&self, dict: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let iter = dict.iter().zip(data.iter());
+        let n = iter.take_while(|(x, y)| x == y).count();
+        let mut out = vec![n as u8];
+        out.extend_from_slice(&data[n..]);
+        Ok(out)
+    }
+
+    fn decompress(&self, dict: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = dict[..(data[0] as usize)].to_vec();
+        out.extend_from_slice(&data[1..]);
+        Ok(out)
+    }
+}
+
+#[test]
+fn test_compression() {
+    let name = "test-robt-compression";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    let compressor: Arc<dyn Compressor> = Arc::new(PrefixCompressor("test-prefix".to_string()));
+    register_compressor(Arc::clone(&compressor)).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config.clone()).unwrap();
+    config.set_compression(Some(compressor), 10, 64).unwrap();
+    let mut cindex = {
+        let name = "test-robt-compression-dict";
+        Robt::<i64, i64, NoBitmap>::new(&dir, name, config.clone()).unwrap()
+    };
+    for round in 0..2_i64 {
+        // small values compress to their last byte.
+        for key in 0..2000_i64 {
+            mindex.set(key, (key + round) % 100).unwrap();
+            if key % 7 == 0 {
+                mindex.delete(&key).unwrap();
+            }
+        }
+        for index in [&mut index, &mut cindex].iter_mut() {
+            let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+            let scanner = core::CommitIter::new(mindex.as_mut(), within);
+            index.commit(scanner, std::convert::identity).unwrap();
+        }
+
+        let ref_entries: Vec<Entry<i64, i64>> = {
+            let iter = mindex.iter().unwrap();
+            iter.map(|e| e.unwrap()).collect()
+        };
+        let mut r = cindex.to_reader().unwrap();
+        assert_eq!(r.to_stats().unwrap().compressor, "test-prefix");
+        // first value sampled is that of key 1.
+        assert_eq!(r.to_dictionary().unwrap(), 1_i64.to_be_bytes().to_vec());
+        let entries: Vec<Entry<i64, i64>> = {
+            let iter = r.iter_with_versions().unwrap();
+            iter.map(|e| e.unwrap()).collect()
+        };
+        assert_eq!(ref_entries.len(), entries.len(), "round:{}", round);
+        for (e, re) in entries.iter().zip(ref_entries.iter()) {
+            check_entry1(e, re);
+        }
+        let value = r.get(&1).unwrap().to_native_value();
+        assert_eq!(value, Some((1 + round) % 100));
+
+        let v_bytes = index.to_reader().unwrap().to_stats().unwrap().v_bytes;
+        let c_bytes = r.to_stats().unwrap().v_bytes;
+        assert!(c_bytes < v_bytes, "{} {}", c_bytes, v_bytes);
+    }
+
+    // compressor recorded in the index is not registered.
+    let name = "test-robt-compression-unregistered-robt-000";
+    let compressor = Arc::new(PrefixCompressor("test-unregistered".to_string()));
+    config.set_compression(Some(compressor), 10, 64).unwrap();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    let entries = (0..100_i64).map(|key| Ok(Entry::new(key, Value::new_upsert_value(0, 1))));
+    b.build(entries, vec![]).unwrap();
+    match Snapshot::<i64, i64, NoBitmap>::open(&dir, name) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid input"),
+    }
+}
+
+#[test]
+fn test_align() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-align");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    for align in [Config::BLOCK_ALIGN, Config::ALIGN, 1024 * 16].iter() {
+        let name = format!("test-robt-align-{}-robt-000", align);
+        let mut config: robt::Config = Default::default();
+        config
+            .set_blocksize(1024 * 16, 1024 * 4, 1024 * 16)
+            .unwrap();
+        config.set_align(*align).unwrap();
+
+        let b = Builder::<i64, i64, NoBitmap>::initial(&dir, &name, config).unwrap();
+        let entries = (0..10000_i64).map(|key| {
+            let value = Value::new_upsert_value(key, 1);
+            Ok(Entry::new(key, value))
+        });
+        b.build(entries, vec![]).unwrap();
+
+        // all block starts and the meta region are aligned.
+        let index_file = Config::stitch_index_file(&dir, &name);
+        let (_, meta_block_bytes) = read_meta_items(&dir, &name).unwrap();
+        let n = fs::metadata(&index_file).unwrap().len() as usize;
+        assert_eq!(n % align, 0, "align:{}", align);
+        assert_eq!((n - meta_block_bytes) % align, 0, "align:{}", align);
+
+        let mut snap = Snapshot::<i64, i64, NoBitmap>::open(&dir, &name).unwrap();
+        assert_eq!(snap.to_stats().unwrap().align, *align);
+        assert_eq!(snap.len().unwrap(), 10000);
+        assert_eq!(snap.get(&9999).unwrap().to_native_value(), Some(9999));
+    }
+}
+
+#[test]
+fn test_flags_hook() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-flags-hook");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    for vlog in [false, true].iter() {
+        let name = format!("test-robt-flags-hook-{}-robt-000", vlog);
+        let mut config: robt::Config = Default::default();
+        config.value_in_vlog = *vlog;
+
+        let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, &name, config).unwrap();
+        b.set_flags_hook(|entry| (*entry.as_key() % 256) as u8)
+            .unwrap();
+        let entries = (0..10000_i64).map(|key| {
+            let value = Value::new_upsert_value(key, 1);
+            Ok(Entry::new(key, value))
+        });
+        b.build(entries, vec![]).unwrap();
+
+        let mut snap = Snapshot::<i64, i64, NoBitmap>::open(&dir, &name).unwrap();
+        let mut n = 0;
+        for entry in snap.iter().unwrap() {
+            let entry = entry.unwrap();
+            assert_eq!(entry.to_flags(), (*entry.as_key() % 256) as u8);
+            assert_eq!(entry.to_native_value(), Some(entry.to_key()));
+            n += 1;
+        }
+        assert_eq!(n, 10000);
+        assert_eq!(snap.get(&9999).unwrap().to_flags(), 15);
+    }
+}
+
+#[test]
+fn test_build_from_wal() {
+    use crate::{
+        dlog::Dlog,
+        wal::{Op, State, Wal},
+    };
+    use std::collections::{hash_map::RandomState, BTreeMap};
+
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-build-from-wal");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("wal".to_string(), 1, 10_000, 10);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    // reference entries, as (value, seqno), upto `upto_seqno`.
+    let mut refs: BTreeMap<i64, (Option<i64>, u64)> = BTreeMap::new();
+    let mut upto_seqno = 0;
+    {
+        let mut w = wl.to_writer().unwrap();
+        for i in 0..10_000 {
+            let key: i64 = rng.gen::<i64>().abs() % 500;
+            let value: i64 = rng.gen();
+            let cas = refs.get(&key).map(|(_, seqno)| *seqno).unwrap_or(0);
+            let (seqno, val) = match rng.gen::<u8>() % 4 {
+                0 => (w.delete(&key).unwrap(), None),
+                1 => (w.set_cas(key, value, cas).unwrap(), Some(value)),
+                2 => {
+                    // invalid cas, shall be ignored.
+                    w.set_cas(key, value, cas + 1_000_000).unwrap();
+                    continue;
+                }
+                _ => (w.set(key, value).unwrap(), Some(value)),
+            };
+            if i < 7_000 {
+                refs.insert(key, (val, seqno));
+                upto_seqno = seqno;
+            }
+        }
+    }
+    wl.close().unwrap();
+
+    let wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    let name = "test-robt-build-from-wal-robt-000";
+    let config: robt::Config = Default::default();
+    let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.set_sort_limit(4096).unwrap();
+    b.build_from_wal(wl, upto_seqno, vec![]).unwrap();
+
+    let runs: Vec<String> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|item| item.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|file| file.contains("sort-run"))
+        .collect();
+    assert!(runs.is_empty(), "{:?}", runs);
+
+    let mut snap = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snap.to_seqno().unwrap(), upto_seqno);
+    let entries: Vec<Entry<i64, i64>> = snap.iter().unwrap().map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), refs.len());
+    for (entry, (key, (value, seqno))) in entries.into_iter().zip(refs.into_iter()) {
+        assert_eq!(entry.to_key(), key);
+        assert_eq!(entry.to_native_value(), value);
+        assert_eq!(entry.to_seqno(), seqno);
+        assert_eq!(entry.is_deleted(), value.is_none());
+    }
+}
+
+#[test]
+fn test_iter_prefetch() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-iter-prefetch");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    for vlog in [false, true].iter() {
+        let name = format!("test-robt-iter-prefetch-{}-robt-000", vlog);
+        let mut config: robt::Config = Default::default();
+        config.value_in_vlog = *vlog;
+
+        let b = Builder::<i64, i64, NoBitmap>::initial(&dir, &name, config).unwrap();
+        let entries = (0..10000_i64).map(|key| {
+            let value = Value::new_upsert_value(key * 10, key as u64 + 1);
+            Ok(Entry::new(key, value))
+        });
+        b.build(entries, vec![]).unwrap();
+
+        let mut snap = Snapshot::<i64, i64, NoBitmap>::open(&dir, &name).unwrap();
+        let refs: Vec<Entry<i64, i64>> = snap.iter().unwrap().map(|e| e.unwrap()).collect();
+        assert_eq!(refs.len(), 10000);
+
+        for depth in [1, 4, 64].iter() {
+            let iter = snap.to_iter().unwrap().spawn_prefetch(*depth).unwrap();
+            let entries: Vec<Entry<i64, i64>> = iter.map(|e| e.unwrap()).collect();
+            assert_eq!(entries.len(), refs.len());
+            for (entry, re) in entries.into_iter().zip(refs.iter()) {
+                assert_eq!(entry.to_key(), re.to_key());
+                assert_eq!(entry.to_native_value(), re.to_native_value());
+                assert_eq!(entry.to_seqno(), re.to_seqno());
+            }
+        }
+
+        // consumer can hang up before the helper thread is done.
+        let mut iter = snap.to_iter().unwrap().spawn_prefetch(2).unwrap();
+        for key in 0..10 {
+            assert_eq!(iter.next().unwrap().unwrap().to_key(), key);
+        }
+        mem::drop(iter);
+
+        assert!(snap.to_iter().unwrap().spawn_prefetch(0).is_err());
+    }
+}
+
+#[test]
+fn test_pin() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let name = "test-robt-pin";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+    {
+        random_llrb(10_000, 5_000, seed, &mut mindex);
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    };
+    let to_files = |guard: &LevelsGuard<i64, i64, NoBitmap>| -> Vec<ffi::OsString> {
+        let r = guard.to_reader().unwrap();
+        vec![r.index_fd.to_file(), r.valog_fd.as_ref().unwrap().0.clone()]
+    };
+    let exists = |files: &[ffi::OsString]| -> bool {
+        files.iter().all(|file| path::Path::new(file).exists())
+    };
+
+    // files of pinned version shall survive compaction.
+    let guard = index.pin().unwrap();
+    let files = to_files(&guard);
+    index.compact(Cutoff::new_mono()).unwrap();
+    assert!(index.to_version().unwrap() > guard.to_version().unwrap());
+    thread::sleep(time::Duration::from_secs(3));
+    assert!(exists(&files), "{:?}", files);
+    let n = guard.to_reader().unwrap().iter().unwrap().count();
+    assert_eq!(n, mindex.len());
+
+    mem::drop(guard);
+    thread::sleep(time::Duration::from_secs(3));
+    assert!(files.iter().all(|file| !path::Path::new(file).exists()));
+
+    // purge on a pinned version is deferred till the guard is dropped.
+    let guard = index.pin().unwrap();
+    let files = to_files(&guard);
+    index.purge().unwrap();
+    assert!(exists(&files), "{:?}", files);
+    mem::drop(guard);
+    assert!(files.iter().all(|file| !path::Path::new(file).exists()));
+}
+
+#[test]
+fn test_runtime_stats() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let name = "test-robt-runtime-stats";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(10_000, 5_000, seed, &mut mindex);
+    let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+
+    let mut snap = Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
+    assert_eq!(snap.to_runtime_stats(), Default::default());
+
+    let (mut n_found, mut n_values) = (0, 0);
+    for _ in 0..10_000 {
+        let key = (rng.gen::<i64>() % 10_000).abs();
+        match snap.get(&key) {
+            Ok(entry) => {
+                n_found += 1;
+                if !entry.is_deleted() {
+                    n_values += 1;
+                }
+            }
+            Err(Error::KeyNotFound) => (),
+            Err(err) => panic!("unexpected {:?}", err),
+        }
+    }
+
+    let rs = snap.to_runtime_stats();
+    println!("{:?} blocks-per-get:{}", rs, rs.to_blocks_per_get());
+    assert_eq!(rs.n_gets, 10_000);
+    assert_eq!(
+        rs.n_gets,
+        n_found + rs.n_bitmap_skips + rs.n_bitmap_false_positives
+    );
+    assert!(rs.n_bitmap_skips > 0);
+    assert_eq!(rs.get_levels[0], rs.n_gets - rs.n_bitmap_skips);
+    assert!(rs.get_levels.iter().all(|n| *n > 0), "{:?}", rs.get_levels);
+    assert_eq!(rs.n_block_reads, rs.get_levels.iter().sum::<usize>());
+    assert_eq!(rs.n_vlog_reads, n_values);
+    assert!(rs.to_blocks_per_get() >= 2.0);
+
+    // iteration only accounts for block-reads and vlog-reads.
+    let n_entries = snap.iter().unwrap().count();
+    let rs2 = snap.to_runtime_stats();
+    assert_eq!(rs2.n_gets, rs.n_gets);
+    assert_eq!(rs2.get_levels, rs.get_levels);
+    assert!(rs2.n_block_reads > rs.n_block_reads);
+    assert!(rs2.n_vlog_reads > rs.n_vlog_reads);
+    assert!(rs2.n_vlog_reads <= rs.n_vlog_reads + n_entries);
+}
+
+#[test]
+fn test_validate_bitmap() {
+    let name = "test-robt-validate-bitmap";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let config: robt::Config = Default::default();
+    let entries = (0..10_000_i64).map(|key| Ok(Entry::new(key, Value::new_upsert_value(key, 1))));
+    let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config).unwrap();
+    b.build(entries, vec![]).unwrap();
+
+    let mut snap = Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
+    let bitmap = Arc::clone(&snap.bitmap);
+    let check = snap.validate_bitmap(bitmap.as_ref()).unwrap();
+    println!("{:?} fpr:{}", check, check.to_false_positive_rate());
+    assert_eq!(check.n_keys, 10_000);
+    assert_eq!(check.n_probes, 10_000);
+    assert!(check.to_false_positive_rate() < 0.01);
+
+    // every probe is a false positive.
+    let check = snap.validate_bitmap(&NoBitmap).unwrap();
+    assert_eq!(check.n_false_positives, 10_000);
+    assert_eq!(check.to_false_positive_rate(), 1.0);
+
+    // bitmap of some other index.
+    let mut other = CRoaring::create();
+    (5_000..15_000_i64).for_each(|key| other.add_key(&key));
+    match snap.validate_bitmap(&other) {
+        Err(Error::Fatal(msg)) => assert!(msg.contains("missing key 0"), "{}", msg),
+        res => panic!("unexpected {:?}", res),
+    }
+}
+
+#[test]
+fn test_ord_spec() {
+    type Key = Ordered<Vec<u8>, AsciiCaseless>;
+
+    let name = "test-robt-ord-spec";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.set_ord_spec(AsciiCaseless::NAME).unwrap();
+    let robtf = robt_factory::<Key, i64, CRoaring>(config);
+
+    let mut mindex: Box<Llrb<Key, i64>> = Llrb::new_lsm("test-llrb");
+    let keys = ["banana", "Apple", "cherry", "APPLE", "Date"];
+    for (i, key) in keys.iter().enumerate() {
+        let key = Key::new(key.as_bytes().to_vec());
+        mindex.set(key, i as i64).unwrap();
+    }
+    {
+        let mut index = robtf.new(&dir, name).unwrap();
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    }
+
+    let mut index = robtf.open(&dir, name).unwrap();
+    let mut r = index.to_reader().unwrap();
+    assert_eq!(r.to_stats().unwrap().ord_spec, "ascii-caseless");
+    let keys: Vec<Vec<u8>> = r
+        .iter()
+        .unwrap()
+        .map(|e| e.unwrap().to_key().into_key().to_ascii_lowercase())
+        .collect();
+    let ref_keys: Vec<Vec<u8>> = vec![
+        b"apple".to_vec(),
+        b"banana".to_vec(),
+        b"cherry".to_vec(),
+        b"date".to_vec(),
+    ];
+    assert_eq!(keys, ref_keys);
+
+    let entry = r.get(&Key::new(b"DATE".to_vec())).unwrap();
+    assert_eq!(entry.to_native_value(), Some(4));
+    let entry = r.get(&Key::new(b"apple".to_vec())).unwrap();
+    assert_eq!(entry.to_native_value(), Some(3));
+
+    // opening with natural ordering shall fail.
+    let robtf = robt_factory::<Key, i64, CRoaring>(Default::default());
+    match robtf.open(&dir, name) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected invalid input"),
+    }
+}
+
+#[test]
+fn test_skip_scan() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let name = "test-robt-skip-scan";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = rng.gen();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+    {
+        random_llrb(10_000, 5_000, seed, &mut mindex);
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+    };
+
+    let seqno = mindex.to_seqno().unwrap();
+    for _ in 0..20 {
+        let (a, b) = (rng.gen::<u64>() % seqno, rng.gen::<u64>() % seqno);
+        let (lo, hi) = (cmp::min(a, b), cmp::max(a, b));
+        let within = match rng.gen::<u8>() % 3 {
+            0 => (Bound::Unbounded, Bound::Unbounded),
+            1 => (Bound::Excluded(lo), Bound::Unbounded),
+            _ => (Bound::Excluded(lo), Bound::Included(hi)),
+        };
+        let batch_size = (rng.gen::<usize>() % 1000) + 1;
+        println!("within:{:?} batch_size:{}", within, batch_size);
+
+        let mut ref_iter = scans::SkipScan::new(mindex.to_reader().unwrap());
+        ref_iter.set_seqno_range(within.clone()).unwrap();
+        ref_iter.set_batch_size(batch_size).unwrap();
+        let mut iter = scans::SkipScan::new(index.to_reader().unwrap());
+        iter.set_seqno_range(within).unwrap();
+        iter.set_batch_size(batch_size).unwrap();
+
+        let mut n = 0;
+        for (e, re) in iter.zip(ref_iter.by_ref()) {
+            check_entry1(&e.unwrap(), &re.unwrap());
+            n += 1;
+        }
+        assert!(ref_iter.next().is_none(), "after {} entries", n);
+    }
+}
+
+#[test]
+fn test_build_from_text() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-build-from-text");
+        dir.into_os_string()
+    };
+    fs::create_dir_all(&dir).unwrap();
+
+    let parser = |line: &str| -> Result<Option<(i64, i64)>> {
+        if line.starts_with("#") {
+            return Ok(None);
+        }
+        let parts: Vec<&str> = line.split(',').collect();
+        match parts.as_slice() {
+            [key, value] => Ok(Some((parse_at!(key, i64)?, parse_at!(value, i64)?))),
+            _ => err_at!(InvalidInput, msg: format!("bad line {}", line)),
+        }
+    };
+
+    let mut text = "# key,value\n".to_string();
+    for key in 0..1000 {
+        text.push_str(&format!("{},{}\n", key, key * 10));
+    }
+
+    let name = "test-robt-build-from-text-robt-000";
+    let config: robt::Config = Default::default();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+    b.build_from_text(text.as_bytes(), parser, vec![]).unwrap();
+
+    let mut snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.len().unwrap(), 1000);
+    assert_eq!(snapshot.to_seqno().unwrap(), 1000);
+    for (i, entry) in snapshot.iter().unwrap().enumerate() {
+        let entry = entry.unwrap();
+        let key = i as i64;
+        assert_eq!(entry.to_key(), key);
+        assert_eq!(entry.to_native_value(), Some(key * 10));
+        assert_eq!(entry.to_seqno(), (i + 1) as u64);
+    }
+
+    // unsorted input is rejected.
+    let name = "test-robt-build-from-text-robt-001";
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    match b.build_from_text("2,20\n1,10\n".as_bytes(), parser, vec![]) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("expected invalid input {:?}", res),
+    }
+}
+
+#[test]
+fn test_build_resume() {
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push("test-robt-build-resume");
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new("test-robt-build-resume");
+    for key in 0..10_000 {
+        llrb.set(key, key * 10).unwrap();
+    }
+    let entries: Vec<Entry<i64, i64>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+
+    let name = "test-robt-build-resume-robt-000";
+    let mut config: robt::Config = Default::default();
+    config.set_blocksize(4096, 4096, 4096).unwrap();
+    config.set_value_log(None, true).unwrap();
+
+    // crash the build midway.
+    let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.set_checkpoint(2).unwrap();
+    let iter = entries
+        .clone()
+        .into_iter()
+        .take(6000)
+        .map(|e| Ok(e))
+        .chain(vec![err_at!(IoError, msg: format!("crash"))].into_iter());
+    match b.build(iter, vec![]) {
+        Err(Error::IoError(_)) => (),
+        res => panic!("expected io error {:?}", res),
+    }
+
+    let b = Builder::<i64, i64, NoBitmap>::resume(&dir, name).unwrap();
+    let key = b.to_resume_key().unwrap();
+    assert!(key > 0 && key <= 6000, "{}", key);
+    b.build(entries.clone().into_iter().map(|e| Ok(e)), vec![])
+        .unwrap();
+
+    let mut snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.len().unwrap(), 10_000);
+    assert_eq!(snapshot.to_seqno().unwrap(), 10_000);
+    for (i, entry) in snapshot.iter().unwrap().enumerate() {
+        let entry = entry.unwrap();
+        let key = i as i64;
+        assert_eq!(entry.to_key(), key);
+        assert_eq!(entry.to_native_value(), Some(key * 10));
+        assert_eq!(entry.to_seqno(), (i + 1) as u64);
+    }
+    snapshot.validate().unwrap();
+
+    // checkpoint is removed after a successful build.
+    assert!(Builder::<i64, i64, NoBitmap>::resume(&dir, name).is_err());
+}
+
+fn run_robt_llrb(name: &str, n_ops: u64, key_max: i64, repeat: usize, seed: u128) {
+    for i in 0..repeat {
+        let mut n_ops = n_ops;
+        let seed = seed + (i as u128);
+        let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+        // populate llrb
+        let lsm: bool = rng.gen();
+        let sticky: bool = rng.gen();
+        let mut llrb: Box<Llrb<i64, i64>> = if lsm {
+            Llrb::new_lsm("test-llrb")
+        } else {
+            Llrb::new("test-llrb")
+        };
+        llrb.set_sticky(sticky).unwrap();
+
+        random_llrb(n_ops as i64, key_max, seed, &mut llrb);
+
+        // to avoid screwing up the seqno in non-lsm mode, say, what if
+        // the last operation was a delete.
+        llrb.set(123, 123456789).unwrap();
+        n_ops += 1;
+
+        // build ROBT
+        let mut config: robt::Config = Default::default();
+        config.delta_ok = lsm;
+        config.value_in_vlog = rng.gen();
+        let within = match rng.gen::<u64>() % 100 {
+            0..=60 => (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded),
+            61..=70 => (Bound::<u64>::Excluded(1), Bound::<u64>::Unbounded),
+            71..=80 => (Bound::<u64>::Included(1), Bound::<u64>::Unbounded),
+            81..=90 => {
+                let x = rng.gen::<u64>() % n_ops;
+                (Bound::<u64>::Excluded(x), Bound::<u64>::Unbounded)
+            }
+            91..=100 => (Bound::<u64>::Excluded(n_ops), Bound::<u64>::Unbounded),
+            _ => unreachable!(),
+        };
+        let mmap = rng.gen::<bool>();
+        println!(
+            "seed:{} n_ops:{} lsm:{} sticky:{} delta:{} vlog:{}
+            within:{:?} mmap:{}",
+            seed, n_ops, lsm, sticky, config.delta_ok, config.value_in_vlog, within, mmap,
+        );
+        let (mut llrb, refs) = llrb_to_refs1(llrb, within.clone(), &config);
+        let n_deleted: usize = refs
+            .iter()
+            .map(|e| if e.is_deleted() { 1 } else { 0 })
+            .sum();
+        // println!("refs len: {}", refs.len());
+        let mut iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+        iter.set_seqno_range(within).unwrap();
+        let dir = {
+            let mut dir = std::env::temp_dir();
+            dir.push("test-robt-build");
+            dir.into_os_string()
+        };
+        let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config.clone()).unwrap();
+        let app_meta = "heloo world".to_string();
+        b.build(iter, app_meta.as_bytes().to_vec()).unwrap();
+
+        let mut snap = robt::Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
+        assert_eq!(snap.len().unwrap(), refs.len());
+        match snap.validate() {
+            Err(Error::EmptyIndex) if refs.len() == 0 => continue,
+            Err(err) => panic!("{:?}", err),
+            Ok(_) => (),
+        }
+        snap.set_mmap(mmap).unwrap();
+        assert_eq!(snap.to_app_meta().unwrap(), app_meta.as_bytes().to_vec());
+        let stats = snap.to_stats().unwrap();
+        assert_eq!(stats.z_blocksize, config.z_blocksize);
+        assert_eq!(stats.m_blocksize, config.m_blocksize);
+        assert_eq!(stats.v_blocksize, config.v_blocksize);
+        assert_eq!(stats.delta_ok, config.delta_ok);
+        assert_eq!(stats.value_in_vlog, config.value_in_vlog);
+        if lsm || sticky {
+            assert_eq!(stats.n_deleted, n_deleted);
+        }
+
+        // test get
+        let mut seqno = 0;
+        for entry in refs.iter() {
+            let e = snap.get(entry.as_key()).unwrap();
+            check_entry1(&entry, &e);
             seqno = std::cmp::max(seqno, e.to_seqno());
         }
-        // assert_eq!(seqno, llrb.to_seqno().unwrap());
-        assert_eq!(snap.to_seqno().unwrap(), seqno);
+        // assert_eq!(seqno, llrb.to_seqno().unwrap());
+        assert_eq!(snap.to_seqno().unwrap(), seqno);
+
+        if seqno == 0 {
+            continue;
+        }
+
+        // test first entry
+        let ref_entry = refs.first().unwrap();
+        let ref_entry = ref_entry
+            .clone()
+            .purge(Cutoff::new_lsm(Bound::Excluded(ref_entry.to_seqno())))
+            .unwrap();
+        let entry = snap.first().unwrap();
+        let entry = entry
+            .clone()
+            .purge(Cutoff::new_lsm(Bound::Excluded(entry.to_seqno())))
+            .unwrap();
+        check_entry1(&entry, &ref_entry);
+        check_entry1(&snap.first_with_versions().unwrap(), &refs.first().unwrap());
+        // test last entry
+        let ref_entry = refs.last().unwrap();
+        let ref_entry = ref_entry
+            .clone()
+            .purge(Cutoff::new_lsm(Bound::Excluded(ref_entry.to_seqno())))
+            .unwrap();
+        let entry = snap.last().unwrap();
+        let entry = entry
+            .clone()
+            .purge(Cutoff::new_lsm(Bound::Excluded(entry.to_seqno())))
+            .unwrap();
+        check_entry1(&entry, &ref_entry);
+        check_entry1(&snap.last_with_versions().unwrap(), &refs.last().unwrap());
+
+        // test get_with_versions
+        for entry in refs.iter() {
+            let e = snap.get_with_versions(entry.as_key()).unwrap();
+            check_entry1(&entry, &e);
+            check_entry2(&entry, &e)
+        }
+        // test iter
+        let xs = snap.iter().unwrap();
+        let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+        for (x, y) in xs.iter().zip(refs.iter()) {
+            check_entry1(&x, &y)
+        }
+        assert_eq!(xs.len(), refs.len());
+        // test iter_with_versions
+        let xs = snap.iter_with_versions().unwrap();
+        let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+        assert_eq!(xs.len(), refs.len());
+        for (x, y) in xs.iter().zip(refs.iter()) {
+            check_entry1(&x, &y);
+            check_entry2(&x, &y)
+        }
+        for j in 0..100 {
+            // test range
+            let range = random_low_high(key_max, seed + j);
+            let refs = llrb_to_refs2(&mut llrb, range, within.clone(), &config);
+            // println!("range bounds {:?} {}", range, refs.len());
+            let xs = snap.range(range).unwrap();
+            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+            assert_eq!(xs.len(), refs.len());
+            for (x, y) in xs.iter().zip(refs.iter()) {
+                check_entry1(&x, &y);
+            }
+            // test range_with_versions
+            let range = random_low_high(key_max, seed + j);
+            let refs = llrb_to_refs2(&mut llrb, range, within.clone(), &config);
+            // println!("range..versions bounds {:?} {}", range, refs.len());
+            let xs = snap.range_with_versions(range).unwrap();
+            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+            assert_eq!(xs.len(), refs.len());
+            for (x, y) in xs.iter().zip(refs.iter()) {
+                check_entry1(&x, &y);
+                check_entry2(&x, &y);
+            }
+            // test reverse
+            let range = random_low_high(key_max, seed + j);
+            let refs = llrb_to_refs3(&mut llrb, range, within.clone(), &config);
+            // println!("reverse bounds {:?} {}", range, refs.len());
+            let xs = snap.reverse(range).unwrap();
+            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+            assert_eq!(xs.len(), refs.len());
+            for (x, y) in xs.iter().zip(refs.iter()) {
+                check_entry1(&x, &y);
+            }
+            // test reverse_with_versions
+            let range = random_low_high(key_max, seed + j);
+            let refs = llrb_to_refs3(&mut llrb, range, within.clone(), &config);
+            // println!("reverse..versions bounds {:?} {}", range, refs.len());
+            let xs = snap.reverse_with_versions(range).unwrap();
+            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
+            assert_eq!(xs.len(), refs.len());
+            for (x, y) in xs.iter().zip(refs.iter()) {
+                check_entry1(&x, &y);
+                check_entry2(&x, &y);
+            }
+        }
+    }
+}
+
+fn llrb_to_refs1(
+    mut llrb: Box<Llrb<i64, i64>>, // reference
+    within: (Bound<u64>, Bound<u64>),
+    config: &Config,
+) -> (Box<Llrb<i64, i64>>, Vec<Entry<i64, i64>>) {
+    let mut iter = scans::SkipScan::new(llrb.to_reader().unwrap());
+    iter.set_seqno_range(within).unwrap();
+    let refs = iter
+        .filter_map(|e| {
+            let mut e = e.unwrap();
+            // println!("llrb_to_refs1 {}", e.to_key());
+            if !config.delta_ok {
+                e.set_deltas(vec![]);
+            }
+            Some(e)
+        })
+        .collect();
+    (llrb, refs)
+}
+
+fn llrb_to_refs2<R>(
+    llrb: &mut Llrb<i64, i64>, // reference
+    range: R,
+    within: (Bound<u64>, Bound<u64>),
+    config: &Config,
+) -> Vec<Entry<i64, i64>>
+where
+    R: Clone + RangeBounds<i64>,
+{
+    let iter = {
+        let iters = vec![llrb.range(range).unwrap()];
+        scans::FilterScans::new(iters, within)
+    };
+    iter.filter_map(|e| {
+        let mut e = e.unwrap();
+        if !config.delta_ok {
+            e.set_deltas(vec![]);
+        }
+        Some(e)
+    })
+    .collect()
+}
+
+fn llrb_to_refs3<R>(
+    llrb: &mut Llrb<i64, i64>, // reference
+    range: R,
+    within: (Bound<u64>, Bound<u64>),
+    config: &Config,
+) -> Vec<Entry<i64, i64>>
+where
+    R: Clone + RangeBounds<i64>,
+{
+    let iter = {
+        let iters = vec![llrb.reverse(range).unwrap()];
+        scans::FilterScans::new(iters, within)
+    };
+    iter.filter_map(|e| {
+        let mut e = e.unwrap();
+        if !config.delta_ok {
+            e.set_deltas(vec![]);
+        }
+        Some(e)
+    })
+    .collect()
+}
+
+fn random_llrb(n_ops: i64, key_max: i64, seed: u128, llrb: &mut Llrb<i64, i64>) {
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    for _i in 0..n_ops {
+        let key = (rng.gen::<i64>() % key_max).abs();
+        let op = rng.gen::<usize>() % 3;
+        match op {
+            0 => {
+                let value: i64 = rng.gen();
+                // println!("key {} {} {} {}", key, llrb.to_seqno(), op, value);
+                llrb.set(key, value).unwrap();
+            }
+            1 => {
+                let value: i64 = rng.gen();
+                // println!("key {} {} {} {}", key, llrb.to_seqno(), op, value);
+                {
+                    let cas = match llrb.get(&key) {
+                        Err(Error::KeyNotFound) => 0,
+                        Err(_err) => unreachable!(),
+                        Ok(e) => e.to_seqno(),
+                    };
+                    llrb.set_cas(key, value, cas).unwrap();
+                }
+            }
+            2 => {
+                // println!("key {} {} {}", key, llrb.to_seqno(), op);
+                llrb.delete(&key).unwrap();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn random_low_high(key_max: i64, seed: u128) -> (Bound<i64>, Bound<i64>) {
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let (low, high): (i64, i64) = (rng.gen(), rng.gen());
+    let low = match rng.gen::<u8>() % 3 {
+        0 => Bound::Included(low % key_max),
+        1 => Bound::Excluded(low % key_max),
+        2 => Bound::Unbounded,
+        _ => unreachable!(),
+    };
+    let high = match rng.gen::<u8>() % 3 {
+        0 => Bound::Included(high % key_max),
+        1 => Bound::Excluded(high % key_max),
+        2 => Bound::Unbounded,
+        _ => unreachable!(),
+    };
+    //println!("low_high {:?} {:?}", low, high);
+    (low, high)
+}
+
+fn check_entry1(e1: &Entry<i64, i64>, e2: &Entry<i64, i64>) {
+    assert_eq!(e1.to_key(), e2.to_key());
+    let key = e1.to_key();
+    assert_eq!(e1.to_seqno(), e2.to_seqno(), "key:{}", key);
+    assert_eq!(e1.to_native_value(), e2.to_native_value(), "key:{}", key);
+    assert_eq!(e1.is_deleted(), e2.is_deleted(), "key:{}", key);
+}
+
+fn check_entry2(e1: &Entry<i64, i64>, e2: &Entry<i64, i64>) {
+    let key = e1.to_key();
+    let xs: Vec<Delta<i64>> = e1.to_deltas();
+    let ys: Vec<Delta<i64>> = e2.to_deltas();
+
+    assert_eq!(e1.as_deltas().len(), e2.as_deltas().len(), "key:{}", key);
+    assert_eq!(xs.len(), ys.len(), "for key {}", key);
+    for (m, n) in xs.iter().zip(ys.iter()) {
+        assert_eq!(m.to_seqno(), n.to_seqno(), "for key {}", key);
+        assert_eq!(m.is_deleted(), n.is_deleted(), "for key {}", key);
+        // println!("d {} {}", m.is_deleted(), n.is_deleted());
+        assert_eq!(m.to_diff(), n.to_diff(), "for key {}", key);
+        // println!("key:{} diff {:?} {:?}", key, m.to_diff(), n.to_diff());
+    }
+}
+
+fn random_ops_keys(seed: u128, ops_limit: i64, key_limit: i64) -> (i64, i64) {
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+
+    let n_ops_set: Vec<i64> = vec![
+        0,
+        ops_limit / 10,
+        ops_limit / 100,
+        ops_limit / 1000,
+        ops_limit / 10000,
+    ];
+    let i = rng.gen::<usize>() % (n_ops_set.len() + 1);
+    let n_ops = if i == n_ops_set.len() {
+        10000 + (rng.gen::<u64>() % (ops_limit as u64))
+    } else {
+        n_ops_set[i] as u64
+    };
+    let n_ops = n_ops as i64;
+
+    let max_key_set: Vec<i64> = vec![
+        (key_limit / 10) + 1,
+        (key_limit / 100) + 1,
+        (key_limit / 1000) + 1,
+        (key_limit / 10000) + 1,
+    ];
+    let i: usize = rng.gen::<usize>() % (max_key_set.len() + 1);
+    let max_key = if i == max_key_set.len() {
+        10000 + (rng.gen::<i64>() % key_limit)
+    } else {
+        max_key_set[i]
+    };
+    (n_ops, i64::max(i64::abs(max_key), n_ops / 10) + 1)
+}
+
+#[test]
+fn test_range_tombstones() {
+    let name = "test-robt-range-tombstones";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..100 {
+        mindex.set(key, key * 10).unwrap();
+    }
+    let seqno = mindex.to_seqno().unwrap();
+
+    let tombs = vec![
+        RangeTombstone {
+            low: Bound::Included(20),
+            high: Bound::Excluded(40),
+            seqno,
+        },
+        RangeTombstone {
+            low: Bound::Excluded(60),
+            high: Bound::Included(80),
+            seqno: 70,
+        },
+        RangeTombstone {
+            low: Bound::Included(90),
+            high: Bound::Unbounded,
+            seqno,
+        },
+    ];
+    {
+        let file_name: Name = (name.to_string(), 0).into();
+        let mut b =
+            Builder::<i64, i64, CRoaring>::initial(&dir, &file_name.0, config.clone()).unwrap();
+        for tomb in tombs.iter() {
+            b.delete_range((tomb.low.clone(), tomb.high.clone()), tomb.seqno)
+                .unwrap();
+        }
+        b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    }
 
-        if seqno == 0 {
-            continue;
+    let ref_entries: Vec<Entry<i64, i64>> = mindex
+        .iter()
+        .unwrap()
+        .map(|e| e.unwrap())
+        .filter(|e| !tombs.iter().any(|tomb| tomb.covers(e)))
+        .collect();
+    let ref_keys: Vec<i64> = ref_entries.iter().map(|e| e.to_key()).collect();
+    assert!(ref_keys.len() < 100 && ref_keys.contains(&75));
+    assert!(!ref_keys.contains(&65));
+
+    let mut index = robt_factory::<i64, i64, CRoaring>(config.clone())
+        .open(&dir, name)
+        .unwrap();
+
+    let check = |r: &mut Snapshot<i64, i64, CRoaring>| {
+        for key in 0..100 {
+            match r.get(&key) {
+                Ok(e) if e.is_deleted() => assert!(!ref_keys.contains(&key), "{}", key),
+                Ok(_) => assert!(ref_keys.contains(&key), "{}", key),
+                Err(Error::KeyNotFound) => assert!(!ref_keys.contains(&key)),
+                Err(err) => panic!("unexpected {:?}", err),
+            }
         }
+        let keys: Vec<i64> = r.iter().unwrap().map(|e| e.unwrap().to_key()).collect();
+        assert_eq!(keys, ref_keys);
 
-        // test first entry
-        let ref_entry = refs.first().unwrap();
-        let ref_entry = ref_entry
-            .clone()
-            .purge(Cutoff::new_lsm(Bound::Excluded(ref_entry.to_seqno())))
-            .unwrap();
-        let entry = snap.first().unwrap();
-        let entry = entry
-            .clone()
-            .purge(Cutoff::new_lsm(Bound::Excluded(entry.to_seqno())))
-            .unwrap();
-        check_entry1(&entry, &ref_entry);
-        check_entry1(&snap.first_with_versions().unwrap(), &refs.first().unwrap());
-        // test last entry
-        let ref_entry = refs.last().unwrap();
-        let ref_entry = ref_entry
-            .clone()
-            .purge(Cutoff::new_lsm(Bound::Excluded(ref_entry.to_seqno())))
-            .unwrap();
-        let entry = snap.last().unwrap();
-        let entry = entry
-            .clone()
-            .purge(Cutoff::new_lsm(Bound::Excluded(entry.to_seqno())))
-            .unwrap();
-        check_entry1(&entry, &ref_entry);
-        check_entry1(&snap.last_with_versions().unwrap(), &refs.last().unwrap());
+        let keys: Vec<i64> = r
+            .reverse((Bound::<i64>::Unbounded, Bound::<i64>::Unbounded))
+            .unwrap()
+            .map(|e| e.unwrap().to_key())
+            .collect();
+        let mut rev_keys = ref_keys.clone();
+        rev_keys.reverse();
+        assert_eq!(keys, rev_keys);
 
-        // test get_with_versions
-        for entry in refs.iter() {
-            let e = snap.get_with_versions(entry.as_key()).unwrap();
-            check_entry1(&entry, &e);
-            check_entry2(&entry, &e)
+        let keys: Vec<i64> = r
+            .range((Bound::<i64>::Included(25), Bound::Excluded(65)))
+            .unwrap()
+            .map(|e| e.unwrap().to_key())
+            .collect();
+        let ref_range: Vec<i64> = ref_keys
+            .iter()
+            .filter(|k| (25..65).contains(*k))
+            .cloned()
+            .collect();
+        assert_eq!(keys, ref_range);
+
+        let keys: Vec<i64> = r
+            .range((Bound::<i64>::Excluded(19), Bound::Unbounded))
+            .unwrap()
+            .map(|e| e.unwrap().to_key())
+            .collect();
+        let ref_range: Vec<i64> = ref_keys.iter().filter(|k| **k > 19).cloned().collect();
+        assert_eq!(keys, ref_range);
+
+        let keys: Vec<i64> = r
+            .reverse((Bound::<i64>::Unbounded, Bound::Excluded(40)))
+            .unwrap()
+            .map(|e| e.unwrap().to_key())
+            .collect();
+        let mut ref_range: Vec<i64> = ref_keys.iter().filter(|k| **k < 40).cloned().collect();
+        ref_range.reverse();
+        assert_eq!(keys, ref_range);
+
+        assert_eq!(r.first().unwrap().to_key(), ref_keys[0]);
+        assert_eq!(r.last().unwrap().to_key(), 89);
+        r.validate().unwrap();
+    };
+
+    let mut r = index.to_reader().unwrap();
+    check(&mut r);
+    let n_tombs = r.to_range_tombstones().unwrap().len();
+    assert_eq!(n_tombs, 3);
+    // covered entries are returned as deleted, not as missing.
+    let entry = r.get(&25).unwrap();
+    assert!(entry.is_deleted());
+    assert_eq!(entry.to_seqno(), seqno);
+    assert_eq!(r.to_range_deleted(&25).unwrap(), Some(seqno));
+    assert_eq!(r.to_range_deleted(&50).unwrap(), None);
+
+    // covered entries are dropped, tombstones are retained beyond cutoff.
+    let cutoff = Cutoff::new_tombstone(Bound::Excluded(50));
+    let n = index.compact(cutoff).unwrap();
+    assert_eq!(n, ref_keys.len());
+    let mut r = index.to_reader().unwrap();
+    check(&mut r);
+    let n_tombs = r.to_range_tombstones().unwrap().len();
+    assert_eq!(n_tombs, 3);
+
+    // tombstones are purged by mono compaction.
+    let n = index.compact(Cutoff::new_mono()).unwrap();
+    assert_eq!(n, ref_keys.len());
+    let mut r = index.to_reader().unwrap();
+    check(&mut r);
+    let n_tombs = r.to_range_tombstones().unwrap().len();
+    assert_eq!(n_tombs, 0);
+}
+
+#[test]
+fn test_skip_corrupt() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let name = "test-robt-skip-corrupt";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let config: robt::Config = Default::default();
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..2000 {
+        mindex.set(key, key * 10).unwrap();
+    }
+    let file_name: Name = (name.to_string(), 0).into();
+    {
+        let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, &file_name.0, config).unwrap();
+        b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    }
+
+    // corrupt the second z-block.
+    let fpos = Config::ZBLOCKSIZE as u64;
+    {
+        let file = Config::stitch_index_file(&dir, &file_name.0);
+        let mut fd = fs::OpenOptions::new().write(true).open(file).unwrap();
+        fd.seek(SeekFrom::Start(fpos)).unwrap();
+        fd.write_all(&[0xFF; 16]).unwrap();
+    }
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, &file_name.0).unwrap();
+
+    let mut iter = snap.to_iter().unwrap();
+    let mut n_ok = 0;
+    loop {
+        match iter.next() {
+            Some(Ok(_)) => n_ok += 1,
+            Some(Err(Error::DecodeFail(_))) => break,
+            Some(Err(err)) => panic!("unexpected {:?}", err),
+            None => panic!("expected decode failure"),
         }
-        // test iter
-        let xs = snap.iter().unwrap();
-        let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-        for (x, y) in xs.iter().zip(refs.iter()) {
-            check_entry1(&x, &y)
+    }
+    assert!(n_ok > 0 && n_ok < 2000);
+    let n_first = n_ok;
+
+    let iter = snap.to_iter().unwrap().with_skip_corrupt(true);
+    let (mut keys, mut corrupts) = (vec![], vec![]);
+    for item in iter {
+        match item {
+            Ok(entry) => keys.push(entry.to_key()),
+            Err(Error::CorruptBlock { fpos }) => corrupts.push(fpos),
+            Err(err) => panic!("unexpected {:?}", err),
         }
-        assert_eq!(xs.len(), refs.len());
-        // test iter_with_versions
-        let xs = snap.iter_with_versions().unwrap();
-        let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-        assert_eq!(xs.len(), refs.len());
-        for (x, y) in xs.iter().zip(refs.iter()) {
-            check_entry1(&x, &y);
-            check_entry2(&x, &y)
+    }
+    assert_eq!(corrupts, vec![fpos]);
+    let ref_keys: Vec<i64> = (0..(n_first as i64)).collect();
+    assert_eq!(keys[..n_first].to_vec(), ref_keys);
+    assert!(keys.len() > 1000 && keys.len() < 2000, "{}", keys.len());
+    assert_eq!(keys.last().cloned(), Some(1999));
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_contains() {
+    let name = "test-robt-contains";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.value_in_vlog = true;
+    config.delta_ok = true;
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in (0..2000).step_by(2) {
+        mindex.set(key, key * 10).unwrap();
+    }
+    for key in (0..2000).step_by(10) {
+        mindex.delete(&key).unwrap();
+    }
+    let file_name: Name = (name.to_string(), 0).into();
+    {
+        let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, &file_name.0, config).unwrap();
+        b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    }
+
+    let mut snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, &file_name.0).unwrap();
+    for key in -10..2010 {
+        let ok = snap.get(&key).is_ok();
+        assert_eq!(snap.contains(&key).unwrap(), ok, "key {}", key);
+        if key < 0 || key >= 2000 || key % 2 == 1 {
+            assert!(!ok, "key {}", key);
+        } else if key % 10 != 0 {
+            assert!(ok, "key {}", key);
         }
-        for j in 0..100 {
-            // test range
-            let range = random_low_high(key_max, seed + j);
-            let refs = llrb_to_refs2(&mut llrb, range, within.clone(), &config);
-            // println!("range bounds {:?} {}", range, refs.len());
-            let xs = snap.range(range).unwrap();
-            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-            assert_eq!(xs.len(), refs.len());
-            for (x, y) in xs.iter().zip(refs.iter()) {
-                check_entry1(&x, &y);
-            }
-            // test range_with_versions
-            let range = random_low_high(key_max, seed + j);
-            let refs = llrb_to_refs2(&mut llrb, range, within.clone(), &config);
-            // println!("range..versions bounds {:?} {}", range, refs.len());
-            let xs = snap.range_with_versions(range).unwrap();
-            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-            assert_eq!(xs.len(), refs.len());
-            for (x, y) in xs.iter().zip(refs.iter()) {
-                check_entry1(&x, &y);
-                check_entry2(&x, &y);
-            }
-            // test reverse
-            let range = random_low_high(key_max, seed + j);
-            let refs = llrb_to_refs3(&mut llrb, range, within.clone(), &config);
-            // println!("reverse bounds {:?} {}", range, refs.len());
-            let xs = snap.reverse(range).unwrap();
-            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-            assert_eq!(xs.len(), refs.len());
-            for (x, y) in xs.iter().zip(refs.iter()) {
-                check_entry1(&x, &y);
+    }
+}
+
+#[test]
+fn test_levels_get() {
+    let name = "test-robt-levels-get";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+
+    // oldest level first, each level is built with increasing seqnos.
+    let ranges = vec![(0, 100, 1), (50, 150, 2), (100, 200, 3)];
+    let mut snapshots = vec![];
+    for (i, (from, till, mul)) in ranges.into_iter().enumerate() {
+        let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+        mindex.set_seqno((i as u64) * 100).unwrap();
+        for key in from..till {
+            mindex.set(key, key * mul).unwrap();
+        }
+        let file_name: Name = (format!("{}-{}", name, i), 0).into();
+        {
+            let (dir, config) = (&dir, config.clone());
+            let mut b = Builder::<i64, i64, NoBitmap>::initial(dir, &file_name.0, config).unwrap();
+            b.build(mindex.iter().unwrap(), vec![]).unwrap();
+        }
+
+        let snap = robt::Snapshot::<i64, i64, NoBitmap>::open(&dir, &file_name.0).unwrap();
+        snapshots.insert(0, snap);
+    }
+
+    let mut levels = Levels::new(snapshots).unwrap();
+    assert_eq!(levels.len(), 3);
+
+    for key in -10..210 {
+        let (value, n_versions) = match key {
+            key if key < 0 || key >= 200 => {
+                match levels.get(&key) {
+                    Err(Error::KeyNotFound) => (),
+                    res => panic!("key {} {:?}", key, res.map(|e| e.to_seqno())),
+                }
+                continue;
             }
-            // test reverse_with_versions
-            let range = random_low_high(key_max, seed + j);
-            let refs = llrb_to_refs3(&mut llrb, range, within.clone(), &config);
-            // println!("reverse..versions bounds {:?} {}", range, refs.len());
-            let xs = snap.reverse_with_versions(range).unwrap();
-            let xs: Vec<Entry<i64, i64>> = xs.map(|e| e.unwrap()).collect();
-            assert_eq!(xs.len(), refs.len());
-            for (x, y) in xs.iter().zip(refs.iter()) {
-                check_entry1(&x, &y);
-                check_entry2(&x, &y);
+            key if key < 50 => (key, 1),
+            key if key < 100 => (key * 2, 2),
+            key if key < 150 => (key * 3, 2),
+            key => (key * 3, 1),
+        };
+        let entry = levels.get(&key).unwrap();
+        assert_eq!(entry.to_native_value(), Some(value), "key {}", key);
+        let entry = levels.get_with_versions(&key).unwrap();
+        assert_eq!(entry.to_native_value(), Some(value), "key {}", key);
+        assert_eq!(entry.versions().count(), n_versions, "key {}", key);
+    }
+}
+
+#[test]
+fn test_build_overflow() {
+    let name = "test-robt-build-overflow";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = false;
+    config.value_in_vlog = false;
+
+    let mut mindex: Box<Llrb<i64, Vec<u8>>> = Llrb::new("test-llrb");
+    mindex.set(1, vec![0; 100]).unwrap();
+    mindex.set(2, vec![0; Config::ZBLOCKSIZE * 2]).unwrap();
+
+    let file_name: Name = (name.to_string(), 0).into();
+    let b = Builder::<i64, Vec<u8>, NoBitmap>::initial(&dir, &file_name.0, config).unwrap();
+    match b.build(mindex.iter().unwrap(), vec![]) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("z_blocksize"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_vlog_segments() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+    let name = "test-robt-vlog-segments";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+
+    let mut config: robt::Config = Default::default();
+    config.delta_ok = true;
+    config.value_in_vlog = true;
+    config.set_vlog_segment(Config::ZBLOCKSIZE * 4).unwrap();
+    assert!(config.validate().is_ok());
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config.clone()).unwrap();
+    for round in 0..2 {
+        random_llrb(10_000, 5_000, seed + round, &mut mindex);
+        let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+        let scanner = core::CommitIter::new(mindex.as_mut(), within);
+        index.commit(scanner, std::convert::identity).unwrap();
+
+        let ref_entries: Vec<Entry<i64, i64>> = {
+            let iter = mindex.iter().unwrap();
+            iter.map(|e| e.unwrap()).collect()
+        };
+        let mut r = index.to_reader().unwrap();
+        assert_eq!(r.to_stats().unwrap().vlog_segment, Config::ZBLOCKSIZE * 4);
+        let vlog_files = r.to_vlog_files();
+        assert!(vlog_files.len() > 1, "{:?}", vlog_files);
+        for (segment, file) in vlog_files.iter().enumerate() {
+            let n = fs::metadata(file).unwrap().len() as usize;
+            assert!(segment == 0 || n > 0, "{:?}", file);
+            let fname = path::Path::new(file).file_name().unwrap();
+            let nm: Name = VlogFileName(fname.to_os_string()).try_into().unwrap();
+            let (nm, _): (String, usize) = nm.try_into().unwrap();
+            assert_eq!(nm, name);
+        }
+
+        let entries: Vec<Entry<i64, i64>> = {
+            let iter = r.iter_with_versions().unwrap();
+            iter.map(|e| e.unwrap()).collect()
+        };
+        assert_eq!(ref_entries.len(), entries.len(), "round:{}", round);
+        for (e, re) in entries.iter().zip(ref_entries.iter()) {
+            check_entry1(e, re);
+        }
+        r.validate().unwrap();
+    }
+
+    let mut config: robt::Config = Default::default();
+    for size in [1, VLOG_HEADER_SIZE as usize, Config::MAX_VLOG_SEGMENT + 1].iter() {
+        config.set_vlog_segment(*size).unwrap();
+        match config.validate() {
+            Err(Error::InvalidInput(_)) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn test_flush_depth() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    for (i, vlog_segment) in [0, Config::ZBLOCKSIZE * 4].iter().enumerate() {
+        let name = format!("test-robt-flush-depth-{}", i);
+        let dir = {
+            let mut dir = std::env::temp_dir();
+            dir.push(&name);
+            dir.into_os_string()
+        };
+        fs::remove_dir_all(&dir).ok();
+
+        let mut config: robt::Config = Default::default();
+        config.delta_ok = true;
+        config.value_in_vlog = true;
+        config.set_vlog_segment(*vlog_segment).unwrap();
+        config.set_flush_depth(4).unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+        let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, &name, config).unwrap();
+        for round in 0..2 {
+            random_llrb(10_000, 5_000, seed + round, &mut mindex);
+            let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+            let scanner = core::CommitIter::new(mindex.as_mut(), within);
+            index.commit(scanner, std::convert::identity).unwrap();
+
+            let ref_entries: Vec<Entry<i64, i64>> = {
+                let iter = mindex.iter().unwrap();
+                iter.map(|e| e.unwrap()).collect()
+            };
+            let mut r = index.to_reader().unwrap();
+            let entries: Vec<Entry<i64, i64>> = {
+                let iter = r.iter_with_versions().unwrap();
+                iter.map(|e| e.unwrap()).collect()
+            };
+            assert_eq!(ref_entries.len(), entries.len(), "round:{}", round);
+            for (e, re) in entries.iter().zip(ref_entries.iter()) {
+                check_entry1(e, re);
             }
+            r.validate().unwrap();
         }
     }
-}
 
-fn llrb_to_refs1(
-    mut llrb: Box<Llrb<i64, i64>>, // reference
-    within: (Bound<u64>, Bound<u64>),
-    config: &Config,
-) -> (Box<Llrb<i64, i64>>, Vec<Entry<i64, i64>>) {
-    let mut iter = scans::SkipScan::new(llrb.to_reader().unwrap());
-    iter.set_seqno_range(within).unwrap();
-    let refs = iter
-        .filter_map(|e| {
-            let mut e = e.unwrap();
-            // println!("llrb_to_refs1 {}", e.to_key());
-            if !config.delta_ok {
-                e.set_deltas(vec![]);
-            }
-            Some(e)
-        })
-        .collect();
-    (llrb, refs)
+    let mut config: robt::Config = Default::default();
+    config.set_flush_depth(0).unwrap();
+    match config.validate() {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res),
+    }
 }
 
-fn llrb_to_refs2<R>(
-    llrb: &mut Llrb<i64, i64>, // reference
-    range: R,
-    within: (Bound<u64>, Bound<u64>),
-    config: &Config,
-) -> Vec<Entry<i64, i64>>
-where
-    R: Clone + RangeBounds<i64>,
-{
-    let iter = {
-        let iters = vec![llrb.range(range).unwrap()];
-        scans::FilterScans::new(iters, within)
+#[test]
+fn test_build_parts() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let name = "test-robt-build-parts";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
     };
-    iter.filter_map(|e| {
-        let mut e = e.unwrap();
-        if !config.delta_ok {
-            e.set_deltas(vec![]);
-        }
-        Some(e)
-    })
-    .collect()
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(20_000, 10_000, seed, &mut mindex);
+    let ref_entries: Vec<Entry<i64, i64>> = {
+        let iter = mindex.iter().unwrap();
+        iter.map(|e| e.unwrap()).collect()
+    };
+
+    // without cap, single snapshot.
+    let config: robt::Config = Default::default();
+    let iter = mindex.iter().unwrap();
+    let snaps =
+        Builder::<i64, i64, NoBitmap>::build_parts(&dir, name, config, iter, vec![]).unwrap();
+    assert_eq!(snaps.len(), 1);
+    assert_eq!(snaps[0].len().unwrap(), ref_entries.len());
+    mem::drop(snaps);
+
+    let cap = Config::ZBLOCKSIZE * 16;
+    let mut config: robt::Config = Default::default();
+    config.set_max_index_size(cap).unwrap();
+    assert!(config.validate().is_ok());
+    let iter = mindex.iter().unwrap();
+    let mut snaps =
+        Builder::<i64, i64, NoBitmap>::build_parts(&dir, name, config, iter, vec![]).unwrap();
+    assert!(snaps.len() > 1, "{}", snaps.len());
+
+    let mut entries: Vec<Entry<i64, i64>> = vec![];
+    for (i, snap) in snaps.iter_mut().enumerate() {
+        let part_name = format!("{}-part{}", name, i);
+        let file = Config::stitch_index_file(&dir, &part_name);
+        let size = fs::metadata(&file).unwrap().len();
+        // cap can overshoot by a z-block, its m-blocks and the meta region.
+        assert!(size < (cap as u64) * 2, "part:{} size:{}", i, size);
+
+        snap.validate().unwrap();
+        let iter = snap.iter_with_versions().unwrap();
+        entries.extend(iter.map(|e| e.unwrap()));
+    }
+    assert_eq!(entries.len(), ref_entries.len());
+    for (e, re) in entries.iter().zip(ref_entries.iter()) {
+        check_entry1(e, re);
+    }
+
+    let mut config: robt::Config = Default::default();
+    config.set_max_index_size(Config::ZBLOCKSIZE - 1).unwrap();
+    match config.validate() {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res),
+    }
 }
 
-fn llrb_to_refs3<R>(
-    llrb: &mut Llrb<i64, i64>, // reference
-    range: R,
-    within: (Bound<u64>, Bound<u64>),
-    config: &Config,
-) -> Vec<Entry<i64, i64>>
-where
-    R: Clone + RangeBounds<i64>,
-{
-    let iter = {
-        let iters = vec![llrb.reverse(range).unwrap()];
-        scans::FilterScans::new(iters, within)
+#[test]
+fn test_build_dry_run() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
+
+    let name = "test-robt-build-dry-run";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
     };
-    iter.filter_map(|e| {
-        let mut e = e.unwrap();
-        if !config.delta_ok {
-            e.set_deltas(vec![]);
-        }
-        Some(e)
-    })
-    .collect()
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(20_000, 10_000, seed, &mut mindex);
+
+    let mut config: robt::Config = Default::default();
+    config.set_delta(None, true).unwrap();
+
+    let iter = mindex.iter().unwrap();
+    let stats = Builder::<i64, i64, CRoaring>::dry_run(&dir, name, config.clone(), iter).unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    let snap = Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
+    let ref_stats = snap.to_stats().unwrap();
+
+    assert_eq!(stats.n_count, ref_stats.n_count);
+    assert_eq!(stats.n_deleted, ref_stats.n_deleted);
+    assert_eq!(stats.seqno, ref_stats.seqno);
+    assert_eq!(stats.key_mem, ref_stats.key_mem);
+    assert_eq!(stats.diff_mem, ref_stats.diff_mem);
+    assert_eq!(stats.val_mem, ref_stats.val_mem);
+    assert_eq!(stats.z_bytes, ref_stats.z_bytes);
+    assert_eq!(stats.m_bytes, ref_stats.m_bytes);
+    assert_eq!(stats.v_bytes, ref_stats.v_bytes);
+    assert_eq!(stats.n_bitmap, ref_stats.n_bitmap);
+    assert_eq!(stats.mem_bitmap, ref_stats.mem_bitmap);
 }
 
-fn random_llrb(n_ops: i64, key_max: i64, seed: u128, llrb: &mut Llrb<i64, i64>) {
-    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
-    for _i in 0..n_ops {
-        let key = (rng.gen::<i64>() % key_max).abs();
-        let op = rng.gen::<usize>() % 3;
-        match op {
-            0 => {
-                let value: i64 = rng.gen();
-                // println!("key {} {} {} {}", key, llrb.to_seqno(), op, value);
-                llrb.set(key, value).unwrap();
-            }
-            1 => {
-                let value: i64 = rng.gen();
-                // println!("key {} {} {} {}", key, llrb.to_seqno(), op, value);
-                {
-                    let cas = match llrb.get(&key) {
-                        Err(Error::KeyNotFound) => 0,
-                        Err(_err) => unreachable!(),
-                        Ok(e) => e.to_seqno(),
-                    };
-                    llrb.set_cas(key, value, cas).unwrap();
-                }
-            }
-            2 => {
-                // println!("key {} {} {}", key, llrb.to_seqno(), op);
-                llrb.delete(&key).unwrap();
-            }
-            _ => unreachable!(),
-        }
+#[test]
+fn test_build_fanout() {
+    let name = "test-robt-build-fanout";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    // huge keys leave room for few entries per block.
+    let mut mindex: Box<Llrb<Vec<u8>, i64>> = Llrb::new("test-llrb");
+    for i in 0..1000_i64 {
+        let mut key = format!("{:010}", i).into_bytes();
+        key.resize(1000, b'x');
+        mindex.set(key, i).unwrap();
     }
-}
 
-fn random_low_high(key_max: i64, seed: u128) -> (Bound<i64>, Bound<i64>) {
-    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
-    let (low, high): (i64, i64) = (rng.gen(), rng.gen());
-    let low = match rng.gen::<u8>() % 3 {
-        0 => Bound::Included(low % key_max),
-        1 => Bound::Excluded(low % key_max),
-        2 => Bound::Unbounded,
-        _ => unreachable!(),
+    let mut config: robt::Config = Default::default();
+    config.set_blocksize(4096, 4096, 4096).unwrap();
+    config.set_min_fanout(8, true).unwrap();
+    let stats = {
+        let iter = mindex.iter().unwrap();
+        type B = Builder<Vec<u8>, i64, NoBitmap>;
+        B::dry_run(&dir, name, config.clone(), iter).unwrap()
     };
-    let high = match rng.gen::<u8>() % 3 {
-        0 => Bound::Included(high % key_max),
-        1 => Bound::Excluded(high % key_max),
-        2 => Bound::Unbounded,
-        _ => unreachable!(),
+    let (zf, mf) = stats.to_fanout();
+    assert!(zf > 1.0 && zf < 8.0, "{}", zf);
+    assert!(mf > 1.0 && mf < 8.0, "{}", mf);
+
+    let (z, m) = config.verify_fanout(&stats).unwrap();
+    assert!(z > 4096 && z.is_power_of_two(), "{}", z);
+    assert!(m > 4096 && m.is_power_of_two(), "{}", m);
+
+    let next = config.to_next_config(stats.clone().into(), &stats);
+    assert_eq!((next.z_blocksize, next.m_blocksize), (z, m));
+    assert_eq!((next.min_fanout, next.auto_blocksize), (8, true));
+
+    config.set_min_fanout(8, false).unwrap();
+    let next = config.to_next_config(stats.clone().into(), &stats);
+    assert_eq!((next.z_blocksize, next.m_blocksize), (4096, 4096));
+
+    config.set_min_fanout(0, true).unwrap();
+    assert_eq!(config.verify_fanout(&stats), None);
+
+    config.set_blocksize(z, 4096, m).unwrap();
+    config.set_min_fanout(8, true).unwrap();
+    let stats = {
+        let iter = mindex.iter().unwrap();
+        type B = Builder<Vec<u8>, i64, NoBitmap>;
+        B::dry_run(&dir, name, config.clone(), iter).unwrap()
     };
-    //println!("low_high {:?} {:?}", low, high);
-    (low, high)
+    let (zf, mf) = stats.to_fanout();
+    assert!(zf >= 8.0, "{}", zf);
+    assert!(mf >= 8.0, "{}", mf);
 }
 
-fn check_entry1(e1: &Entry<i64, i64>, e2: &Entry<i64, i64>) {
-    assert_eq!(e1.to_key(), e2.to_key());
-    let key = e1.to_key();
-    assert_eq!(e1.to_seqno(), e2.to_seqno(), "key:{}", key);
-    assert_eq!(e1.to_native_value(), e2.to_native_value(), "key:{}", key);
-    assert_eq!(e1.is_deleted(), e2.is_deleted(), "key:{}", key);
+#[test]
+fn test_build_seqno_regress() {
+    let name = "test-robt-build-seqno-regress";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..1000_i64 {
+        mindex.set(key, key).unwrap();
+    }
+    let config: robt::Config = Default::default();
+
+    // builds expecting entries above a seqno.
+    let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+    b.set_min_seqno(500).unwrap();
+    match b.build(mindex.iter().unwrap(), vec![]) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected seqno regression"),
+    }
+    let mut b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+    b.set_min_seqno(0).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    let snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.to_seqno().unwrap(), 1000);
+
+    // incremental commit with entries older than the snapshot.
+    let name = "test-robt-commit-seqno-regress";
+    let mut index = Robt::<i64, i64, NoBitmap>::new(&dir, name, config).unwrap();
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+    let scanner = core::CommitIter::new(mindex.as_mut(), within.clone());
+    index.commit(scanner, std::convert::identity).unwrap();
+
+    let mut nindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 1000..1100_i64 {
+        nindex.set(key, key).unwrap();
+    }
+    let scanner = core::CommitIter::new(nindex.as_mut(), within);
+    match index.commit(scanner, std::convert::identity) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected seqno regression"),
+    }
 }
 
-fn check_entry2(e1: &Entry<i64, i64>, e2: &Entry<i64, i64>) {
-    let key = e1.to_key();
-    let xs: Vec<Delta<i64>> = e1.to_deltas();
-    let ys: Vec<Delta<i64>> = e2.to_deltas();
+#[test]
+fn test_build_strict_unique() {
+    let name = "test-robt-build-strict-unique";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
 
-    assert_eq!(e1.as_deltas().len(), e2.as_deltas().len(), "key:{}", key);
-    assert_eq!(xs.len(), ys.len(), "for key {}", key);
-    for (m, n) in xs.iter().zip(ys.iter()) {
-        assert_eq!(m.to_seqno(), n.to_seqno(), "for key {}", key);
-        assert_eq!(m.is_deleted(), n.is_deleted(), "for key {}", key);
-        // println!("d {} {}", m.is_deleted(), n.is_deleted());
-        assert_eq!(m.to_diff(), n.to_diff(), "for key {}", key);
-        // println!("key:{} diff {:?} {:?}", key, m.to_diff(), n.to_diff());
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    for key in 0..1000_i64 {
+        mindex.set(key, key).unwrap();
     }
+    let mut entries: Vec<Entry<i64, i64>> = mindex.iter().unwrap().map(|e| e.unwrap()).collect();
+    let entry = entries[500].clone();
+    entries.insert(500, entry);
+
+    // without the check, both entries are persisted.
+    let config: robt::Config = Default::default();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+    let iter = entries.clone().into_iter().map(Ok);
+    b.build(iter, vec![]).unwrap();
+    let snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.len().unwrap(), 1001);
+
+    let mut config = config;
+    config.set_strict_unique(true).unwrap();
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config.clone()).unwrap();
+    let iter = entries.into_iter().map(Ok);
+    match b.build(iter, vec![]) {
+        Err(Error::InvalidInput(msg)) => println!("{}", msg),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected duplicate key"),
+    }
+    let b = Builder::<i64, i64, NoBitmap>::initial(&dir, name, config).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    let snapshot = Snapshot::<i64, i64, NoBitmap>::open(&dir, name).unwrap();
+    assert_eq!(snapshot.len().unwrap(), 1000);
 }
 
-fn random_ops_keys(seed: u128, ops_limit: i64, key_limit: i64) -> (i64, i64) {
-    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+#[test]
+fn test_open_meta() {
+    let seed: u128 = random();
+    println!("seed:{}", seed);
 
-    let n_ops_set: Vec<i64> = vec![
-        0,
-        ops_limit / 10,
-        ops_limit / 100,
-        ops_limit / 1000,
-        ops_limit / 10000,
-    ];
-    let i = rng.gen::<usize>() % (n_ops_set.len() + 1);
-    let n_ops = if i == n_ops_set.len() {
-        10000 + (rng.gen::<u64>() % (ops_limit as u64))
-    } else {
-        n_ops_set[i] as u64
+    let name = "test-robt-open-meta";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
     };
-    let n_ops = n_ops as i64;
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
 
-    let max_key_set: Vec<i64> = vec![
-        (key_limit / 10) + 1,
-        (key_limit / 100) + 1,
-        (key_limit / 1000) + 1,
-        (key_limit / 10000) + 1,
-    ];
-    let i: usize = rng.gen::<usize>() % (max_key_set.len() + 1);
-    let max_key = if i == max_key_set.len() {
-        10000 + (rng.gen::<i64>() % key_limit)
-    } else {
-        max_key_set[i]
+    let mut mindex: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(10_000, 5_000, seed, &mut mindex);
+
+    let mut config: robt::Config = Default::default();
+    config.set_value_log(None, true).unwrap();
+    let app_meta = "test-open-meta".as_bytes().to_vec();
+    let b = Builder::<i64, i64, CRoaring>::initial(&dir, name, config).unwrap();
+    b.build(mindex.iter().unwrap(), app_meta.clone()).unwrap();
+
+    let meta = Snapshot::<i64, i64, CRoaring>::open_meta(&dir, name).unwrap();
+    let snap = Snapshot::<i64, i64, CRoaring>::open(&dir, name).unwrap();
+    assert!(meta.stats == snap.to_stats().unwrap());
+    assert_eq!(meta.app_meta, app_meta);
+    assert_eq!(meta.root, Some(snap.to_root().unwrap()));
+    assert!(meta.bitmap_len > 0);
+    assert!(meta.meta_block_bytes > 0);
+
+    let mut files = meta.to_files().unwrap();
+    files.sort();
+    let mut ref_files: Vec<ffi::OsString> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|item| {
+            let mut file = path::PathBuf::from(&dir);
+            file.push(item.unwrap().file_name());
+            file.into_os_string()
+        })
+        .collect();
+    ref_files.sort();
+    assert_eq!(files, ref_files);
+}
+
+#[test]
+fn test_build_size_limit() {
+    use crate::core::{SizeLimit, SizePolicy};
+
+    let name = "test-robt-build-size-limit";
+    let dir = {
+        let mut dir = std::env::temp_dir();
+        dir.push(name);
+        dir.into_os_string()
     };
-    (n_ops, i64::max(i64::abs(max_key), n_ops / 10) + 1)
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    fn truncate(mut value: Vec<u8>, n: usize) -> Vec<u8> {
+        value.truncate(n / 2);
+        value
+    }
+
+    let mut mindex: Box<Llrb<i64, Vec<u8>>> = Llrb::new("test-llrb");
+    mindex.set(1, vec![0; 100]).unwrap();
+    mindex.set(2, vec![0; Config::ZBLOCKSIZE * 2]).unwrap();
+
+    let file_name: Name = (name.to_string(), 0).into();
+    let mut config: robt::Config = Default::default();
+
+    // reject
+    let limit = SizeLimit::new(16, 1000, SizePolicy::Reject);
+    let mut b =
+        Builder::<i64, Vec<u8>, NoBitmap>::initial(&dir, &file_name.0, config.clone()).unwrap();
+    b.set_size_limit(limit).unwrap();
+    match b.build(mindex.iter().unwrap(), vec![]) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("value"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+
+    // spill to vlog requires value-log.
+    let limit = SizeLimit::new(16, 1000, SizePolicy::SpillToVlog);
+    let mut b =
+        Builder::<i64, Vec<u8>, NoBitmap>::initial(&dir, &file_name.0, config.clone()).unwrap();
+    match b.set_size_limit(limit) {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res.is_ok()),
+    }
+
+    // truncate
+    let limit = SizeLimit::new(16, 1000, SizePolicy::Truncate(truncate));
+    let mut b =
+        Builder::<i64, Vec<u8>, NoBitmap>::initial(&dir, &file_name.0, config.clone()).unwrap();
+    b.set_size_limit(limit).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    let mut snap = Snapshot::<i64, Vec<u8>, NoBitmap>::open(&dir, &file_name.0).unwrap();
+    assert_eq!(snap.get(&1).unwrap().to_native_value().unwrap().len(), 100);
+    assert_eq!(snap.get(&2).unwrap().to_native_value().unwrap().len(), 500);
+    mem::drop(snap);
+
+    // spill to vlog
+    config.value_in_vlog = true;
+    let limit = SizeLimit::new(16, 1000, SizePolicy::SpillToVlog);
+    let mut b = Builder::<i64, Vec<u8>, NoBitmap>::initial(&dir, &file_name.0, config).unwrap();
+    b.set_size_limit(limit).unwrap();
+    b.build(mindex.iter().unwrap(), vec![]).unwrap();
+    let mut snap = Snapshot::<i64, Vec<u8>, NoBitmap>::open(&dir, &file_name.0).unwrap();
+    let value = snap.get(&2).unwrap().to_native_value().unwrap();
+    assert_eq!(value.len(), Config::ZBLOCKSIZE * 2);
 }
@@ -40,7 +40,9 @@
 //! **From [lsm]**
 //!
 //! * [YIter][lsm::YIter], returned by [y_iter][lsm::y_iter] for lsm iteration
-//!   used in multi-level indexes like [Dgm].
+//!   used in multi-level indexes like [Dgm]. Also returned by
+//!   [y_iter_merge][lsm::y_iter_merge] to resolve entries using a
+//!   [MergePolicy][core::MergePolicy].
 //! * [YIterVersions][lsm::YIterVersions], returned by
 //!   [y_iter_versions][lsm::y_iter_versions] for lsm iteration used in
 //!   multi-level indexes like [Dgm].
@@ -58,25 +60,39 @@
 //!
 //! **From [scans][self]**
 //!
-//! * [SkipScan], useful in full-table scan using `pw_scan()` interface.
-//!   Additionally, can be configured to filter entries within a key-range and/or
-//!   `seqno` range. Used to implement [CommitIterator] for [Llrb] and [Mvcc].
+//! * [SkipScan], useful in full-table scan using `pw_scan()` interface, or
+//!   `pw_scan_rev()` interface for descending scans. Additionally, can be
+//!   configured to filter entries within a key-range and/or `seqno` range.
+//!   Used to implement [CommitIterator] for [Llrb] and [Mvcc].
 //! * [FilterScans], useful in full-table scan using one or more iterators.
 //!   If more than one iterators are supplied Iterators are chained in stack order.
 //!   Additionally, can be configured to filter entries within a `seqno` range.
 //! * [BitmappedScan], useful to build a bitmap index for all iterated keys.
 //! * [CompactScan], useful to filter entries that can be compacted in.
+//! * [WindowScan], useful to ship mutations in fixed size seqno windows.
+//! * [MapScan], useful to transform values, like schema migration, while
+//!   flushing or committing entries.
+//! * [CompactFilterScan], useful to drop or transform entries using a
+//!   [CompactionFilter] while compacting entries.
+//! * [ProgressScan], useful to report progress and ETA of long running
+//!   flush, backup and verification.
+//! * [CommitWrapper], useful to plug any iterator into commit machinery,
+//!   optionally sharded using caller supplied split-keys.
 //!
 
 use std::{
+    collections::VecDeque,
     hash::Hash,
+    marker,
     ops::{Bound, RangeBounds},
-    vec,
+    sync::{Arc, Mutex},
+    time, vec,
 };
 
 use crate::{
-    core::{Bloom, CommitIterator, Diff, Entry, IndexIter, PiecewiseScan, Result},
-    core::{Cutoff, ScanEntry},
+    core::{Bloom, CommitIterator, Diff, Entry, Footprint, IndexIter, PiecewiseScan, Result},
+    core::{CompactionFilter, Cutoff, Decision, ScanEntry, Value},
+    error::Error,
     util,
 };
 
@@ -104,7 +120,9 @@ const SKIP_SCAN_BATCH_SIZE: usize = 1000;
 /// Mitigates following issues.
 ///
 /// * Read references to data-structure is held only for
-///   very small period, like few tens of micro-seconds.
+///   very small period, like few tens of micro-seconds. Each batch is
+///   bounded by entry-count, and optionally by a byte-budget, refer to
+///   [SkipScan::set_batch_bytes].
 /// * Automatically filters mutations that are older than
 ///   specified sequence-no range, there by saving time for
 ///   top-level DB components.
@@ -118,6 +136,10 @@ const SKIP_SCAN_BATCH_SIZE: usize = 1000;
 /// * Data-structure must not suffer any delete/purge
 ///   operation until full-scan is completed.
 /// * Data-structure must implement PiecewiseScan trait.
+///
+/// Use [SkipScan::new_rev] to stitch descending scans, using
+/// `pw_scan_rev()`, from upper-bound to lower-bound of the key range.
+/// Useful for picking the latest N keys under a prefix.
 pub struct SkipScan<K, V, R>
 where
     K: Clone + Ord,
@@ -132,7 +154,10 @@ where
 
     iter: vec::IntoIter<Result<Entry<K, V>>>,
     batch_size: usize,
+    batch_bytes: usize,
+    footprint: Option<fn(&Entry<K, V>) -> Result<isize>>,
     last_batch: bool,
+    reverse: bool,
 }
 
 enum Refill<K, V>
@@ -162,16 +187,42 @@ where
             key_end: Bound::Unbounded,
             iter: vec![].into_iter(),
             batch_size: SKIP_SCAN_BATCH_SIZE,
+            batch_bytes: usize::MAX,
+            footprint: None,
             last_batch: false,
+            reverse: false,
         }
     }
 
+    /// Same as [SkipScan::new], but iterate in descending order, from
+    /// the upper-bound of key range.
+    pub fn new_rev(reader: R) -> SkipScan<K, V, R> {
+        let mut ss = SkipScan::new(reader);
+        ss.reverse = true;
+        ss
+    }
+
     /// Set the batch size for each iteration using the reader handle.
     pub fn set_batch_size(&mut self, batch_size: usize) -> Result<&mut Self> {
         self.batch_size = batch_size;
         Ok(self)
     }
 
+    /// Set the byte-budget for each iteration using the reader handle.
+    /// A batch is closed when either the batch size or the cumulative
+    /// footprint of its entries reach their limit, whichever is earlier.
+    /// Useful when a few large entries can hold the read-lock for too
+    /// long. A batch shall contain at least one entry.
+    pub fn set_batch_bytes(&mut self, batch_bytes: usize) -> Result<&mut Self>
+    where
+        K: Footprint,
+        V: Footprint,
+    {
+        self.batch_bytes = batch_bytes;
+        self.footprint = Some(|entry| entry.footprint());
+        Ok(self)
+    }
+
     /// Set seqno range to filter out all mutations outside the range.
     pub fn set_seqno_range<G>(&mut self, within: G) -> Result<&mut Self>
     where
@@ -212,19 +263,33 @@ where
 
     fn refill(&mut self) -> Refill<K, V> {
         let mut entries: Vec<Result<Entry<K, V>>> = vec![];
+        let mut n_bytes: usize = 0;
         let within = (self.seqno_start.clone(), self.seqno_end.clone());
-        match self.reader.pw_scan(self.key_start.clone(), within) {
+        let niter = if self.reverse {
+            self.reader.pw_scan_rev(self.key_end.clone(), within)
+        } else {
+            self.reader.pw_scan(self.key_start.clone(), within)
+        };
+        match niter {
             Ok(niter) => {
                 let mut niter = niter.enumerate();
                 loop {
                     match niter.next() {
-                        Some((i, Ok(ScanEntry::Found(entry)))) if i <= self.batch_size => {
-                            entries.push(Ok(entry))
-                        }
-                        Some((_, Ok(ScanEntry::Found(entry)))) => {
-                            let key_start = Some(entry.to_key());
-                            entries.push(Ok(entry));
-                            break Refill::Ok(entries, key_start);
+                        Some((i, Ok(ScanEntry::Found(entry)))) => {
+                            match self.to_entry_bytes(&entry) {
+                                Ok(n) => n_bytes = n_bytes.saturating_add(n),
+                                Err(err) => {
+                                    entries.push(Err(err));
+                                    break Refill::Ok(entries, None);
+                                }
+                            }
+                            if i <= self.batch_size && n_bytes < self.batch_bytes {
+                                entries.push(Ok(entry))
+                            } else {
+                                let key = Some(entry.to_key());
+                                entries.push(Ok(entry));
+                                break Refill::Ok(entries, key);
+                            }
                         }
                         Some((_, Ok(ScanEntry::Retry(key)))) => break Refill::Retry(key, entries),
                         Some((_, Err(err))) => {
@@ -242,12 +307,41 @@ where
         }
     }
 
+    fn to_entry_bytes(&self, entry: &Entry<K, V>) -> Result<usize> {
+        match self.footprint {
+            Some(footprint) => {
+                let n = footprint(entry)?;
+                Ok(convert_at!(n)?)
+            }
+            None => Ok(0),
+        }
+    }
+
     fn is_last_batch(&self, entries: &Vec<Result<Entry<K, V>>>) -> bool {
-        match (&self.key_end, entries.last()) {
-            (Bound::Unbounded, Some(Ok(_))) => false,
-            (Bound::Included(key), Some(Ok(last))) => last.as_key().gt(key),
-            (Bound::Excluded(key), Some(Ok(last))) => last.as_key().ge(key),
-            (_, _) => true,
+        match entries.last() {
+            Some(Ok(last)) => !self.is_within(last.as_key()),
+            _ => true,
+        }
+    }
+
+    // check key against the far end of the scan, which is the upper-bound
+    // for forward scan and lower-bound for reverse scan.
+    fn is_within(&self, qey: &K) -> bool {
+        match (self.reverse, &self.key_start, &self.key_end) {
+            (false, _, Bound::Included(key)) => qey.le(key),
+            (false, _, Bound::Excluded(key)) => qey.lt(key),
+            (true, Bound::Included(key), _) => qey.ge(key),
+            (true, Bound::Excluded(key), _) => qey.gt(key),
+            (_, _, _) => true,
+        }
+    }
+
+    // resume the next piece-wise scan after `key`.
+    fn resume_after(&mut self, key: K) {
+        if self.reverse {
+            self.key_end = Bound::Excluded(key);
+        } else {
+            self.key_start = Bound::Excluded(key);
         }
     }
 }
@@ -264,20 +358,12 @@ where
         loop {
             match self.iter.next() {
                 Some(Ok(entry)) if !self.last_batch => break Some(Ok(entry)),
-                Some(Ok(entry)) => match (entry, &self.key_end) {
-                    (entry, Bound::Included(key)) if entry.as_key().le(key) => {
-                        break Some(Ok(entry))
-                    }
-                    (entry, Bound::Excluded(key)) if entry.as_key().lt(key) => {
-                        break Some(Ok(entry))
-                    }
-                    (entry, Bound::Unbounded) => break Some(Ok(entry)),
-                    _ => {
-                        self.batch_size = 0;
-                        self.iter = vec![].into_iter();
-                        break None;
-                    }
-                },
+                Some(Ok(entry)) if self.is_within(entry.as_key()) => break Some(Ok(entry)),
+                Some(Ok(_)) => {
+                    self.batch_size = 0;
+                    self.iter = vec![].into_iter();
+                    break None;
+                }
                 Some(Err(err)) => {
                     self.batch_size = 0;
                     break Some(Err(err));
@@ -285,13 +371,13 @@ where
                 None if self.batch_size == 0 => break None,
                 None => {
                     let entries = match self.refill() {
-                        Refill::Ok(entries, Some(key_start)) => {
-                            self.key_start = Bound::Excluded(key_start);
+                        Refill::Ok(entries, Some(key)) => {
+                            self.resume_after(key);
                             entries
                         }
                         Refill::Ok(entries, None) => entries,
                         Refill::Retry(key, entries) => {
-                            self.key_start = Bound::Excluded(key);
+                            self.resume_after(key);
                             if entries.len() > 0 {
                                 entries
                             } else {
@@ -499,17 +585,494 @@ where
     }
 }
 
+/// Iterator type, to drop or transform entries using a [CompactionFilter].
+/// If filter is None, entries are passed through as is.
+pub struct CompactFilterScan<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    iter: I,
+    filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+}
+
+impl<K, V, I> CompactFilterScan<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    pub fn new(
+        iter: I,
+        filter: Option<Arc<dyn CompactionFilter<K, V>>>,
+    ) -> CompactFilterScan<K, V, I> {
+        CompactFilterScan { iter, filter }
+    }
+
+    pub fn close(self) -> Result<I> {
+        Ok(self.iter)
+    }
+}
+
+impl<K, V, I> Iterator for CompactFilterScan<K, V, I>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.iter.next()? {
+                Ok(entry) => entry,
+                Err(err) => break Some(Err(err)),
+            };
+            let filter = match &self.filter {
+                Some(filter) => filter,
+                None => break Some(Ok(entry)),
+            };
+            match filter.filter(&entry) {
+                Ok(Decision::Keep) => break Some(Ok(entry)),
+                Ok(Decision::Drop) => (),
+                Ok(Decision::Modify(value)) => {
+                    let value = Value::new_upsert_value(value, entry.to_seqno());
+                    let mut new_entry = Entry::new(entry.to_key(), value);
+                    new_entry.set_flags(entry.to_flags());
+                    break Some(Ok(new_entry));
+                }
+                Err(err) => break Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Iterator type, to transform values using a fallible function, like
+/// re-encoding values to a newer schema or stripping fields, while
+/// flushing or committing entries into another index.
+///
+/// Transform is applied to every version of the entry, from oldest to
+/// newest, and the entry is rebuilt with the same key, seqnos, delete
+/// markers and flags. Error from the transform is returned as is. Values
+/// are expected to be native, that is, not a reference into value-log.
+///
+/// When the source is a [CommitIterator], MapScan is a CommitIterator
+/// as well, in which case the transform is cloned for each scan.
+pub struct MapScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    F: FnMut(&K, V) -> Result<V>,
+{
+    iter: I,
+    map: F,
+
+    _phantom_key: marker::PhantomData<K>,
+    _phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V, I, F> MapScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    F: FnMut(&K, V) -> Result<V>,
+{
+    pub fn new(iter: I, map: F) -> MapScan<K, V, I, F> {
+        MapScan {
+            iter,
+            map,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+        }
+    }
+
+    pub fn close(self) -> Result<I> {
+        Ok(self.iter)
+    }
+
+    fn map_entry(&mut self, entry: Entry<K, V>) -> Result<Entry<K, V>> {
+        let key = entry.to_key();
+        let flags = entry.to_flags();
+        // versions are iterated from newest to oldest.
+        let mut versions: Vec<(u64, Option<V>)> = entry.versions_iter().collect();
+
+        let mut new_entry: Option<Entry<K, V>> = None;
+        while let Some((seqno, value)) = versions.pop() {
+            let value = match value {
+                Some(value) => Some((self.map)(&key, value)?),
+                None => None,
+            };
+            new_entry = match (new_entry, value) {
+                (None, Some(value)) => {
+                    let value = Value::new_upsert_value(value, seqno);
+                    Some(Entry::new(key.clone(), value))
+                }
+                (None, None) => {
+                    let value = Value::new_delete(seqno);
+                    Some(Entry::new(key.clone(), value))
+                }
+                (Some(mut e), Some(value)) => {
+                    let value = Value::new_upsert_value(value, seqno);
+                    e.prepend_version(Entry::new(key.clone(), value), true)?;
+                    Some(e)
+                }
+                (Some(mut e), None) => {
+                    e.delete(seqno)?;
+                    Some(e)
+                }
+            };
+        }
+
+        match new_entry {
+            Some(mut e) => {
+                e.set_flags(flags);
+                Ok(e)
+            }
+            None => err_at!(Fatal, msg: format!("map scan, entry without versions")),
+        }
+    }
+}
+
+impl<K, V, I, F> Iterator for MapScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&K, V) -> Result<V>,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next()? {
+            Ok(entry) => Some(self.map_entry(entry)),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<K, V, I, F> CommitIterator<K, V> for MapScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: CommitIterator<K, V>,
+    F: Clone + FnMut(&K, V) -> Result<V>,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let iter = self.iter.scan(within)?;
+        Ok(Box::new(MapScan::new(iter, self.map.clone())))
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let iters = self.iter.scans(n_shards, within)?;
+        let map = &self.map;
+        Ok(iters
+            .into_iter()
+            .map(|iter| {
+                let iter: IndexIter<K, V> = Box::new(MapScan::new(iter, map.clone()));
+                iter
+            })
+            .collect())
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+        N: Clone + RangeBounds<K>,
+    {
+        let iters = self.iter.range_scans(ranges, within)?;
+        let map = &self.map;
+        Ok(iters
+            .into_iter()
+            .map(|iter| {
+                let iter: IndexIter<K, V> = Box::new(MapScan::new(iter, map.clone()));
+                iter
+            })
+            .collect())
+    }
+}
+
+/// Progress of a long running scan, reported by [ProgressScan].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    /// Number of entries processed so far.
+    pub processed: u64,
+    /// Expected number of entries, if known.
+    pub total: Option<u64>,
+    /// Footprint of entries processed so far.
+    pub bytes: u64,
+    /// Expected footprint of all entries, if known.
+    pub total_bytes: Option<u64>,
+    /// Time elapsed since the first entry was pulled.
+    pub elapsed: time::Duration,
+    /// Number of entries processed per second.
+    pub rate: f64,
+    /// Estimated time to complete the scan, computed from `total` if
+    /// known, else from `total_bytes`.
+    pub eta: Option<time::Duration>,
+}
+
+/// Iterator type, to report progress of long running scans like flush,
+/// backup and verification.
+///
+/// Callback is invoked with [Progress] for every `every` entries, and
+/// once more when the underlying iterator is exhausted. Expected entry
+/// count, say `n_count` from [robt::Stats], or expected byte size, can
+/// be supplied to compute the ETA. Bytes are counted using entry's
+/// [Footprint], hence they are approximate.
+pub struct ProgressScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Progress),
+{
+    iter: I,
+    every: u64,
+    callback: F,
+    progress: Progress,
+    start: Option<time::Instant>,
+    done: bool,
+
+    _phantom_key: marker::PhantomData<K>,
+    _phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V, I, F> ProgressScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Progress),
+{
+    pub fn new(iter: I, every: usize, callback: F) -> Result<ProgressScan<K, V, I, F>> {
+        if every == 0 {
+            err_at!(InvalidInput, msg: format!("progress scan, every is ZERO"))?;
+        }
+        Ok(ProgressScan {
+            iter,
+            every: convert_at!(every)?,
+            callback,
+            progress: Default::default(),
+            start: None,
+            done: false,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+        })
+    }
+
+    /// Set the expected number of entries.
+    pub fn set_total(&mut self, total: u64) -> Result<&mut Self> {
+        self.progress.total = Some(total);
+        Ok(self)
+    }
+
+    /// Set the expected footprint of all entries.
+    pub fn set_total_bytes(&mut self, total_bytes: u64) -> Result<&mut Self> {
+        self.progress.total_bytes = Some(total_bytes);
+        Ok(self)
+    }
+
+    /// Return the latest progress.
+    pub fn to_progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    pub fn close(self) -> Result<I> {
+        Ok(self.iter)
+    }
+
+    fn report(&mut self) {
+        let elapsed = match self.start {
+            Some(start) => start.elapsed(),
+            None => Default::default(),
+        };
+        let secs = elapsed.as_secs_f64();
+        let p = &mut self.progress;
+        p.elapsed = elapsed;
+        p.rate = if secs > 0.0 {
+            (p.processed as f64) / secs
+        } else {
+            0.0
+        };
+        p.eta = match (p.total, p.total_bytes) {
+            _ if self.done => Some(Default::default()),
+            (Some(total), _) if p.processed > 0 => {
+                let rem = total.saturating_sub(p.processed) as f64;
+                let eta = rem * secs / (p.processed as f64);
+                Some(time::Duration::from_secs_f64(eta))
+            }
+            (None, Some(total)) if p.bytes > 0 => {
+                let rem = total.saturating_sub(p.bytes) as f64;
+                let eta = rem * secs / (p.bytes as f64);
+                Some(time::Duration::from_secs_f64(eta))
+            }
+            _ => None,
+        };
+        (self.callback)(&self.progress);
+    }
+}
+
+impl<K, V, I, F> Iterator for ProgressScan<K, V, I, F>
+where
+    K: Clone + Ord + Footprint,
+    V: Clone + Diff + Footprint,
+    I: Iterator<Item = Result<Entry<K, V>>>,
+    F: FnMut(&Progress),
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start.is_none() {
+            self.start = Some(time::Instant::now());
+        }
+        match self.iter.next() {
+            Some(Ok(entry)) => {
+                self.progress.processed += 1;
+                let n = entry.footprint().unwrap_or(0).max(0);
+                self.progress.bytes += n as u64;
+                if (self.progress.processed % self.every) == 0 {
+                    self.report();
+                }
+                Some(Ok(entry))
+            }
+            Some(Err(err)) => Some(Err(err)),
+            None if self.done => None,
+            None => {
+                self.done = true;
+                self.report();
+                None
+            }
+        }
+    }
+}
+
+/// Iterator type, for full table iteration bucketed into seqno windows.
+///
+/// Window `n` covers mutations whose seqno is within
+/// `(n * size, (n+1) * size]`. Each iteration yields the next non-empty
+/// window as `(n, entries)`, where entries are in key order and carry
+/// only those versions that fall within the window. Since windows are
+/// aligned to `size`, a replication job can ship them in bounded chunks,
+/// and resume from the last shipped window, refer to
+/// [set_start_window][WindowScan::set_start_window].
+///
+/// Every window is a separate [scan][CommitIterator::scan] on the
+/// source, hence the source shall support repeated scans. Only windows
+/// that are complete as of `seqno`, supplied while creating the scan,
+/// are yielded, that is, a window once yielded shall not gain newer
+/// mutations.
+pub struct WindowScan<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    C: CommitIterator<K, V>,
+{
+    source: C,
+    size: u64,
+    window: u64,    // next window to scan.
+    n_windows: u64, // number of complete windows.
+
+    _phantom_key: marker::PhantomData<K>,
+    _phantom_val: marker::PhantomData<V>,
+}
+
+impl<K, V, C> WindowScan<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    C: CommitIterator<K, V>,
+{
+    /// Create a window scan over `source`, whose latest mutation is
+    /// at `seqno`, with each window covering `size` seqnos.
+    pub fn new(source: C, size: u64, seqno: u64) -> Result<WindowScan<K, V, C>> {
+        if size == 0 {
+            err_at!(InvalidInput, msg: format!("window size is ZERO"))?;
+        }
+        Ok(WindowScan {
+            source,
+            size,
+            window: 0,
+            n_windows: seqno / size,
+
+            _phantom_key: marker::PhantomData,
+            _phantom_val: marker::PhantomData,
+        })
+    }
+
+    /// Skip windows before `window`, typically to resume after the
+    /// last shipped window.
+    pub fn set_start_window(&mut self, window: u64) -> Result<&mut Self> {
+        self.window = window;
+        Ok(self)
+    }
+
+    /// Return the seqno range covered by `window`.
+    pub fn to_window_range(&self, window: u64) -> (Bound<u64>, Bound<u64>) {
+        let start = Bound::Excluded(window * self.size);
+        let end = Bound::Included((window + 1) * self.size);
+        (start, end)
+    }
+
+    pub fn close(self) -> Result<C> {
+        Ok(self.source)
+    }
+}
+
+impl<K, V, C> Iterator for WindowScan<K, V, C>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    C: CommitIterator<K, V>,
+{
+    type Item = Result<(u64, Vec<Entry<K, V>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.window < self.n_windows {
+            let window = self.window;
+            self.window += 1;
+
+            let within = self.to_window_range(window);
+            let iter = match self.source.scan(within) {
+                Ok(iter) => iter,
+                Err(err) => return Some(Err(err)),
+            };
+            let entries: Result<Vec<Entry<K, V>>> = iter.collect();
+            match entries {
+                Ok(entries) if entries.is_empty() => (),
+                Ok(entries) => return Some(Ok((window, entries))),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
 /// Iterator type, to convert any iterator, or chain of iterators, into
 /// CommitIterator trait. It can be used within [CommitIter][core::CommitIter].
 ///
 /// This type assumes that source iterator already _knows_ the _within_
 /// sequence-no range to filter out entries.
+///
+/// Sources that cannot natively shard themselves, like a network stream,
+/// can be wrapped using [with_split_keys][CommitWrapper::with_split_keys],
+/// in which case `scans()` and `range_scans()` shall de-multiplex the
+/// single sorted source into one iterator per shard.
 pub struct CommitWrapper<'a, K, V>
 where
     K: Clone + Ord,
     V: Clone + Diff,
 {
     iters: Vec<IndexIter<'a, K, V>>,
+    split_keys: Option<Vec<K>>,
 }
 
 impl<'a, K, V> CommitWrapper<'a, K, V>
@@ -518,7 +1081,218 @@ where
     V: Clone + Diff,
 {
     pub fn new(iters: Vec<IndexIter<'a, K, V>>) -> CommitWrapper<'a, K, V> {
-        CommitWrapper { iters: iters }
+        CommitWrapper {
+            iters: iters,
+            split_keys: None,
+        }
+    }
+
+    /// Wrap a single source `iter`, that shall iterate entries in sort
+    /// order, and use `split_keys` as shard boundaries. With split-keys
+    /// `[k1, k2, .. kn]`, shards are `(..k1)`, `[k1..k2)`, .. `[kn..)`.
+    ///
+    /// Shard iterators returned by `scans()` and `range_scans()` can be
+    /// consumed in any order, even concurrently, but entries read ahead
+    /// of their shard are buffered in memory until consumed.
+    pub fn with_split_keys(
+        iter: IndexIter<'a, K, V>,
+        mut split_keys: Vec<K>,
+    ) -> CommitWrapper<'a, K, V> {
+        split_keys.sort();
+        split_keys.dedup();
+        CommitWrapper {
+            iters: vec![iter],
+            split_keys: Some(split_keys),
+        }
+    }
+
+    fn to_split_ranges(keys: &[K]) -> Vec<(Bound<K>, Bound<K>)> {
+        let mut low = Bound::Unbounded;
+        let mut ranges = vec![];
+        for key in keys.iter() {
+            ranges.push((low, Bound::Excluded(key.clone())));
+            low = Bound::Included(key.clone());
+        }
+        ranges.push((low, Bound::Unbounded));
+        ranges
+    }
+
+    fn demux_scans<G>(
+        &mut self,
+        ranges: Vec<(Bound<K>, Bound<K>)>,
+        within: G,
+    ) -> Vec<IndexIter<'a, K, V>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut iters: Vec<IndexIter<'a, K, V>> = self.iters.drain(..).collect();
+        iters.reverse();
+        let iter = Box::new(FilterScans::new(iters, within));
+
+        let n = ranges.len();
+        let demux = Arc::new(Mutex::new(Demux {
+            iter,
+            ranges,
+            queues: (0..n).map(|_| VecDeque::new()).collect(),
+            beyond: vec![false; n],
+        }));
+        (0..n)
+            .map(|shard| {
+                let demux = Arc::clone(&demux);
+                let iter: IndexIter<'a, K, V> = Box::new(DemuxScan { demux, shard });
+                iter
+            })
+            .collect()
+    }
+}
+
+impl<'a, K, V> CommitIterator<K, V> for CommitWrapper<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn scan<G>(&mut self, within: G) -> Result<IndexIter<K, V>>
+    where
+        G: RangeBounds<u64>,
+    {
+        let mut iters: Vec<IndexIter<'a, K, V>> = self.iters.drain(..).collect();
+        iters.reverse();
+
+        Ok(Box::new(FilterScans::new(iters, within)))
+    }
+
+    fn scans<G>(&mut self, n_shards: usize, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+    {
+        let mut iters: Vec<IndexIter<K, V>> = match self.split_keys.take() {
+            Some(_) if n_shards == 0 => err_at!(InvalidInput, msg: format!("n_shards is ZERO"))?,
+            Some(keys) if keys.len() < n_shards => {
+                let ranges = Self::to_split_ranges(&keys);
+                self.demux_scans(ranges, within)
+            }
+            Some(keys) => {
+                // too many split-keys, pick them evenly spaced.
+                let keys: Vec<K> = (1..n_shards)
+                    .map(|i| keys[(i * keys.len()) / n_shards].clone())
+                    .collect();
+                let ranges = Self::to_split_ranges(&keys);
+                self.demux_scans(ranges, within)
+            }
+            None => self.iters.drain(..).collect(),
+        };
+
+        // If there are not enough shards push empty iterators.
+        for _ in iters.len()..n_shards {
+            let ss = vec![];
+            iters.push(Box::new(ss.into_iter()));
+        }
+
+        assert_eq!(iters.len(), n_shards);
+
+        Ok(iters)
+    }
+
+    fn range_scans<N, G>(&mut self, ranges: Vec<N>, within: G) -> Result<Vec<IndexIter<K, V>>>
+    where
+        G: Clone + RangeBounds<u64>,
+        N: Clone + RangeBounds<K>,
+    {
+        match self.split_keys.take() {
+            Some(_) => {
+                let ranges: Vec<(Bound<K>, Bound<K>)> = ranges
+                    .iter()
+                    .map(|r| {
+                        let (low, high) = (r.start_bound(), r.end_bound());
+                        (to_owned_bound(low), to_owned_bound(high))
+                    })
+                    .collect();
+                Ok(self.demux_scans(ranges, within))
+            }
+            None => Ok(self.iters.drain(..).collect()),
+        }
+    }
+}
+
+fn to_owned_bound<K: Clone>(bound: Bound<&K>) -> Bound<K> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.clone()),
+        Bound::Excluded(key) => Bound::Excluded(key.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// De-multiplex a single sorted source into several key-ranges, shared by
+// all DemuxScan iterators created from the same CommitWrapper.
+struct Demux<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    iter: IndexIter<'a, K, V>,
+    ranges: Vec<(Bound<K>, Bound<K>)>,
+    queues: Vec<VecDeque<Entry<K, V>>>,
+    // ranges for which the source has moved past the end-bound.
+    beyond: Vec<bool>,
+}
+
+impl<'a, K, V> Demux<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    fn next_for(&mut self, shard: usize) -> Option<Result<Entry<K, V>>> {
+        loop {
+            if let Some(entry) = self.queues[shard].pop_front() {
+                break Some(Ok(entry));
+            } else if self.beyond[shard] {
+                break None;
+            }
+
+            let entry = match self.iter.next()? {
+                Ok(entry) => entry,
+                Err(err) => break Some(Err(err)),
+            };
+
+            let key = entry.as_key();
+            for (i, (_, end)) in self.ranges.iter().enumerate() {
+                self.beyond[i] = self.beyond[i]
+                    || match end {
+                        Bound::Included(end) => key > end,
+                        Bound::Excluded(end) => key >= end,
+                        Bound::Unbounded => false,
+                    };
+            }
+            match self.ranges.iter().position(|r| r.contains(key)) {
+                Some(i) if i == shard => break Some(Ok(entry)),
+                Some(i) => self.queues[i].push_back(entry),
+                None => (), // entry outside all ranges.
+            }
+        }
+    }
+}
+
+struct DemuxScan<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    demux: Arc<Mutex<Demux<'a, K, V>>>,
+    shard: usize,
+}
+
+impl<'a, K, V> Iterator for DemuxScan<'a, K, V>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    type Item = Result<Entry<K, V>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.demux.lock() {
+            Ok(mut demux) => demux.next_for(self.shard),
+            Err(err) => Some(err_at!(Fatal, msg: format!("poisened lock {}", err))),
+        }
     }
 }
 
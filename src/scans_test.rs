@@ -2,7 +2,7 @@ use rand::{prelude::random, rngs::SmallRng, Rng, SeedableRng};
 
 use super::*;
 use crate::{
-    core::{Index, Reader, Writer},
+    core::{Index, Reader, ScanIter, Writer},
     croaring::CRoaring,
     error::Error,
     llrb::Llrb,
@@ -134,6 +134,78 @@ fn test_into_iter_range_scans() {
     }
 }
 
+#[test]
+fn test_commit_wrapper_split_keys() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let seed: u128 = random();
+    println!("seed {}", seed);
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(6_000, 2_000, seed, &mut llrb);
+    let ref_entries: Vec<Entry<i64, i64>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+    let split_keys = vec![1500, 500, 1000];
+    let within = (Bound::<u64>::Unbounded, Bound::<u64>::Unbounded);
+
+    for shards in 1..8 {
+        let mut cw = CommitWrapper::with_split_keys(llrb.iter().unwrap(), split_keys.clone());
+        let iters = cw.scans(shards, within.clone()).unwrap();
+        assert_eq!(iters.len(), shards);
+        // consume shards in reverse order, forcing the read-ahead.
+        let mut batches: Vec<Vec<Entry<i64, i64>>> = iters
+            .into_iter()
+            .rev()
+            .map(|iter| iter.map(|e| e.unwrap()).collect())
+            .collect();
+        batches.reverse();
+        if shards > split_keys.len() {
+            assert!(batches[0].iter().all(|e| *e.as_key() < 500));
+            assert!(batches[1].iter().all(|e| (500..1000).contains(e.as_key())));
+            assert!(batches[2].iter().all(|e| (1000..1500).contains(e.as_key())));
+            assert!(batches[3].iter().all(|e| *e.as_key() >= 1500));
+            batches[4..].iter().for_each(|b| assert_eq!(b.len(), 0));
+        }
+        let entries: Vec<Entry<i64, i64>> = batches.into_iter().flatten().collect();
+        assert_eq!(ref_entries.len(), entries.len());
+        entries
+            .iter()
+            .zip(ref_entries.iter())
+            .for_each(|(e, re)| check_node(e, re));
+    }
+
+    let ranges = vec![
+        (Unbounded, Excluded(100)),
+        (Included(100), Included(200)),
+        (Included(1900), Unbounded),
+    ];
+    let mut cw = CommitWrapper::with_split_keys(llrb.iter().unwrap(), vec![]);
+    let mut iters = cw.range_scans(ranges.clone(), within.clone()).unwrap();
+    assert_eq!(iters.len(), ranges.len());
+    // consume shards in round-robin.
+    let mut batches: Vec<Vec<Entry<i64, i64>>> = vec![vec![]; ranges.len()];
+    let mut n_done = 0;
+    while n_done < iters.len() {
+        n_done = 0;
+        for (i, iter) in iters.iter_mut().enumerate() {
+            match iter.next() {
+                Some(entry) => batches[i].push(entry.unwrap()),
+                None => n_done += 1,
+            }
+        }
+    }
+    for (range, batch) in ranges.iter().zip(batches.into_iter()) {
+        let refs: Vec<&Entry<i64, i64>> = ref_entries
+            .iter()
+            .filter(|e| range.contains(e.as_key()))
+            .collect();
+        assert_eq!(refs.len(), batch.len());
+        batch
+            .iter()
+            .zip(refs.into_iter())
+            .for_each(|(e, re)| check_node(e, re));
+    }
+}
+
 #[test]
 fn test_skip_scan() {
     use std::ops::Bound;
@@ -285,6 +357,59 @@ fn test_skip_scan() {
     assert_eq!(es.len(), 0);
 }
 
+#[test]
+fn test_skip_scan_bytes() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::SeqCst},
+        Arc,
+    };
+
+    // count the number of pieces scanned.
+    struct Pieces<R> {
+        reader: R,
+        n: Arc<AtomicUsize>,
+    }
+
+    impl<R> PiecewiseScan<i64, Vec<u8>> for Pieces<R>
+    where
+        R: PiecewiseScan<i64, Vec<u8>>,
+    {
+        fn pw_scan<G>(&mut self, from: Bound<i64>, within: G) -> Result<ScanIter<i64, Vec<u8>>>
+        where
+            G: Clone + RangeBounds<u64>,
+        {
+            self.n.fetch_add(1, SeqCst);
+            self.reader.pw_scan(from, within)
+        }
+    }
+
+    let mut llrb: Box<Llrb<i64, Vec<u8>>> = Llrb::new_lsm("test-llrb");
+    for key in 0..100 {
+        let value = vec![0xAB; if key % 10 == 0 { 10_000 } else { 10 }];
+        llrb.set(key, value).unwrap();
+    }
+    let ref_entries: Vec<Entry<i64, Vec<u8>>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+
+    for (batch_bytes, n_pieces) in [(usize::MAX, 1), (10_000, 11), (1, 100)].iter() {
+        let n = Arc::new(AtomicUsize::new(0));
+        let reader = Pieces {
+            reader: llrb.to_reader().unwrap(),
+            n: Arc::clone(&n),
+        };
+        let mut scanner = SkipScan::new(reader);
+        scanner.set_batch_bytes(*batch_bytes).unwrap();
+        let entries: Vec<Entry<i64, Vec<u8>>> = scanner.map(|e| e.unwrap()).collect();
+
+        assert_eq!(entries.len(), ref_entries.len());
+        for (entry, ref_entry) in entries.iter().zip(ref_entries.iter()) {
+            assert_eq!(entry.to_key(), ref_entry.to_key());
+            assert_eq!(entry.to_native_value(), ref_entry.to_native_value());
+        }
+        let n_scans = n.load(SeqCst);
+        assert!(n_scans >= *n_pieces, "{} {}", batch_bytes, n_scans);
+    }
+}
+
 #[test]
 fn test_filter_scan() {
     use std::ops::Bound;
@@ -548,6 +673,151 @@ fn check_node(entry: &Entry<i64, i64>, ref_entry: &Entry<i64, i64>) {
     }
 }
 
+#[test]
+fn test_window_scan() {
+    let seed: u128 = random();
+    println!("seed {}", seed);
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(10_000, 2_000, seed, &mut llrb);
+    let seqno = llrb.to_seqno().unwrap();
+
+    let size = 1000;
+    let n_windows = seqno / size;
+    let mut ref_versions: Vec<(i64, u64)> = vec![];
+    for entry in llrb.iter().unwrap() {
+        let entry = entry.unwrap();
+        for (s, _) in entry.versions_iter() {
+            if s <= (n_windows * size) {
+                ref_versions.push((entry.to_key(), s));
+            }
+        }
+    }
+    ref_versions.sort();
+
+    assert!(WindowScan::new(&mut *llrb, 0, seqno).is_err());
+
+    let mut versions: Vec<(i64, u64)> = vec![];
+    let mut last_window = None;
+    let iter = WindowScan::new(&mut *llrb, size, seqno).unwrap();
+    for item in iter {
+        let (window, entries) = item.unwrap();
+        assert!(window < n_windows, "{} {}", window, n_windows);
+        assert!(last_window.map_or(true, |w| w < window));
+        last_window = Some(window);
+        assert!(entries.len() > 0);
+        for entry in entries.into_iter() {
+            for (s, _) in entry.versions_iter() {
+                assert!(s > (window * size) && s <= ((window + 1) * size));
+                versions.push((entry.to_key(), s));
+            }
+        }
+    }
+    versions.sort();
+    assert_eq!(versions, ref_versions);
+
+    // resume from a later window.
+    let mut iter = WindowScan::new(&mut *llrb, size, seqno).unwrap();
+    iter.set_start_window(n_windows - 1).unwrap();
+    let (window, _) = iter.next().unwrap().unwrap();
+    assert_eq!(window, n_windows - 1);
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_map_scan() {
+    let seed: u128 = random();
+    println!("seed {}", seed);
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(6_000, 2_000, seed, &mut llrb);
+
+    let map = |_key: &i64, value: i64| -> Result<i64> { Ok(!value) };
+
+    let refs: Vec<Entry<i64, i64>> = llrb.iter().unwrap().map(|e| e.unwrap()).collect();
+    let iter = MapScan::new(llrb.iter().unwrap(), map);
+    let entries: Vec<Entry<i64, i64>> = iter.map(|e| e.unwrap()).collect();
+    assert_eq!(entries.len(), refs.len());
+    for (entry, refn) in entries.iter().zip(refs.iter()) {
+        assert_eq!(entry.to_key(), refn.to_key());
+        assert_eq!(entry.to_seqno(), refn.to_seqno());
+        assert_eq!(entry.is_deleted(), refn.is_deleted());
+        assert_eq!(entry.to_flags(), refn.to_flags());
+        let versions: Vec<(u64, Option<i64>)> = entry.versions_iter().collect();
+        let ref_versions: Vec<(u64, Option<i64>)> = refn
+            .versions_iter()
+            .map(|(seqno, value)| (seqno, value.map(|v| !v)))
+            .collect();
+        assert_eq!(versions, ref_versions);
+    }
+
+    // as commit iterator.
+    let entries: Vec<Entry<i64, i64>> = {
+        let mut iter = MapScan::new(&mut *llrb, map);
+        let scans = iter.scans(4, ..).unwrap();
+        scans
+            .into_iter()
+            .flat_map(|s| s.map(|e| e.unwrap()))
+            .collect()
+    };
+    assert_eq!(entries.len(), llrb.len());
+    for entry in entries.into_iter() {
+        let refn = llrb.get(entry.as_key()).unwrap();
+        assert_eq!(entry.to_native_value(), refn.to_native_value().map(|v| !v));
+    }
+
+    // error from transform.
+    let key = refs[refs.len() / 2].to_key();
+    let fail = |k: &i64, value: i64| -> Result<i64> {
+        match k {
+            k if *k == key => err_at!(InvalidInput, msg: format!("key {}", k)),
+            _ => Ok(value),
+        }
+    };
+    let mut iter = MapScan::new(llrb.iter().unwrap(), fail);
+    match iter.find(|e| e.is_err()) {
+        Some(Err(Error::InvalidInput(_))) => (),
+        res => panic!("{:?}", res.map(|e| e.map(|e| e.to_key()))),
+    }
+}
+
+#[test]
+fn test_progress_scan() {
+    let seed: u128 = random();
+    println!("seed {}", seed);
+
+    let mut llrb: Box<Llrb<i64, i64>> = Llrb::new_lsm("test-llrb");
+    random_llrb(10_000, 1_000, seed, &mut llrb);
+    let n = llrb.len() as u64;
+
+    let mut reports: Vec<Progress> = vec![];
+    {
+        let callback = |p: &Progress| reports.push(p.clone());
+        let mut iter = ProgressScan::new(llrb.iter().unwrap(), 100, callback).unwrap();
+        iter.set_total(n).unwrap();
+        assert_eq!(iter.by_ref().count() as u64, n);
+        assert!(iter.next().is_none());
+        let p = iter.to_progress();
+        assert_eq!(p.processed, n);
+        assert!(p.bytes > 0);
+    }
+    assert_eq!(reports.len() as u64, (n / 100) + 1);
+    for (i, p) in reports.iter().take((n / 100) as usize).enumerate() {
+        assert_eq!(p.processed, ((i as u64) + 1) * 100);
+        assert_eq!(p.total, Some(n));
+        assert!(p.eta.is_some());
+    }
+    let last = reports.last().unwrap();
+    assert_eq!(last.processed, n);
+    assert_eq!(last.eta, Some(Default::default()));
+
+    let callback = |_: &Progress| ();
+    match ProgressScan::new(llrb.iter().unwrap(), 0, callback) {
+        Err(Error::InvalidInput(_)) => (),
+        _ => panic!("expected invalid input"),
+    }
+}
+
 fn random_llrb(n_ops: i64, key_max: i64, seed: u128, llrb: &mut Llrb<i64, i64>) {
     let mut rng = SmallRng::from_seed(seed.to_le_bytes());
     for _i in 0..n_ops {
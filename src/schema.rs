@@ -0,0 +1,374 @@
+//! Module `schema` define versioned schema for statistics.
+//!
+//! Statistics are either persisted, like [robt::Stats] in the index file,
+//! or consumed by external tools, like dashboards scraping [Rdms] metrics.
+//! Field names and their types are declared once as a [Schema], and
+//! statistics are encoded to and decoded from JSON via [Record], so that
+//! the encoder and the decoder cannot drift apart.
+//!
+//! Every record carries a `schema_version`. Fields introduced in a later
+//! version of the schema are defaulted while decoding records of an older
+//! version, while a missing field that is expected in the record's version
+//! is an error. Records without `schema_version` are treated as version
+//! ZERO.
+//!
+//! [robt::Stats]: crate::robt::Stats
+//! [Rdms]: crate::rdms::Rdms
+
+use std::{convert::TryInto, fmt, result};
+
+use crate::{
+    core::{Result, ToJson},
+    error::Error,
+};
+
+/// Key used to persist the schema version along with the record.
+pub const VERSION_KEY: &str = "schema_version";
+
+/// Type of a field in statistics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Bool,
+    Int,
+    Str,
+    /// String that can be missing, encoded as JSON `null`.
+    OptStr,
+    /// List of strings.
+    List,
+}
+
+/// Field in statistics, identified by its name.
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: Kind,
+    /// Schema version in which this field was introduced.
+    pub since: u32,
+}
+
+impl Field {
+    pub const fn new(name: &'static str, kind: Kind, since: u32) -> Field {
+        Field { name, kind, since }
+    }
+}
+
+/// Schema for a statistics type, fields are encoded in the same order
+/// as they are declared.
+#[derive(Debug)]
+pub struct Schema {
+    pub name: &'static str,
+    pub version: u32,
+    pub fields: &'static [Field],
+}
+
+impl Schema {
+    fn to_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
+}
+
+/// Value of a field in statistics.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Bool(bool),
+    Int(i128),
+    Str(String),
+    Null,
+    List(Vec<String>),
+}
+
+impl Item {
+    fn new(kind: Kind) -> Item {
+        match kind {
+            Kind::Bool => Item::Bool(false),
+            Kind::Int => Item::Int(0),
+            Kind::Str => Item::Str("".to_string()),
+            Kind::OptStr => Item::Null,
+            Kind::List => Item::List(vec![]),
+        }
+    }
+
+    fn is_kind(&self, kind: Kind) -> bool {
+        match (self, kind) {
+            (Item::Bool(_), Kind::Bool) => true,
+            (Item::Int(_), Kind::Int) => true,
+            (Item::Str(_), Kind::Str) => true,
+            (Item::Str(_), Kind::OptStr) => true,
+            (Item::Null, Kind::OptStr) => true,
+            (Item::List(_), Kind::List) => true,
+            _ => false,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        match self {
+            Item::Bool(val) => val.to_string(),
+            Item::Int(val) => val.to_string(),
+            Item::Str(val) => to_json_string(val),
+            Item::Null => "null".to_string(),
+            Item::List(vals) => {
+                let vals: Vec<String> = vals.iter().map(|v| to_json_string(v)).collect();
+                format!("[{}]", vals.join(", "))
+            }
+        }
+    }
+}
+
+impl From<bool> for Item {
+    fn from(val: bool) -> Item {
+        Item::Bool(val)
+    }
+}
+
+macro_rules! impl_item_int {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Item {
+                fn from(val: $t) -> Item {
+                    Item::Int(val as i128)
+                }
+            }
+        )*
+    };
+}
+
+impl_item_int!(u32, u64, usize, isize, i64, i128);
+
+impl From<String> for Item {
+    fn from(val: String) -> Item {
+        Item::Str(val)
+    }
+}
+
+impl From<&str> for Item {
+    fn from(val: &str) -> Item {
+        Item::Str(val.to_string())
+    }
+}
+
+impl From<Option<String>> for Item {
+    fn from(val: Option<String>) -> Item {
+        match val {
+            Some(val) => Item::Str(val),
+            None => Item::Null,
+        }
+    }
+}
+
+impl From<Vec<String>> for Item {
+    fn from(vals: Vec<String>) -> Item {
+        Item::List(vals)
+    }
+}
+
+/// Statistics record, conforming to a [Schema].
+#[derive(Clone, Debug)]
+pub struct Record {
+    schema: &'static Schema,
+    version: u32,
+    items: Vec<Item>,
+}
+
+impl Record {
+    /// Create a new record for the latest version of `schema`, all
+    /// fields are set to their default value.
+    pub fn new(schema: &'static Schema) -> Record {
+        Record {
+            schema,
+            version: schema.version,
+            items: schema.fields.iter().map(|f| Item::new(f.kind)).collect(),
+        }
+    }
+
+    /// Return the schema version of this record.
+    pub fn to_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Set field `name` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is not declared in the schema, or if `value` does not
+    /// match the field's type. Both are programming errors.
+    pub fn set<T: Into<Item>>(&mut self, name: &str, value: T) -> &mut Self {
+        let value: Item = value.into();
+        match self.schema.to_index(name) {
+            Some(i) if value.is_kind(self.schema.fields[i].kind) => self.items[i] = value,
+            Some(_) => panic!("{}, type mismatch for {:?}", self.schema.name, name),
+            None => panic!("{}, unknown field {:?}", self.schema.name, name),
+        }
+        self
+    }
+
+    fn get(&self, name: &str) -> Result<&Item> {
+        match self.schema.to_index(name) {
+            Some(i) => Ok(&self.items[i]),
+            None => {
+                let msg = format!("{}, unknown field {:?}", self.schema.name, name);
+                err_at!(InvalidInput, msg: msg)
+            }
+        }
+    }
+
+    fn mismatch<T>(&self, name: &str) -> Result<T> {
+        let msg = format!("{}, type mismatch for {:?}", self.schema.name, name);
+        err_at!(InvalidInput, msg: msg)
+    }
+
+    pub fn get_bool(&self, name: &str) -> Result<bool> {
+        match self.get(name)? {
+            Item::Bool(val) => Ok(*val),
+            _ => self.mismatch(name),
+        }
+    }
+
+    pub fn get_i128(&self, name: &str) -> Result<i128> {
+        match self.get(name)? {
+            Item::Int(val) => Ok(*val),
+            _ => self.mismatch(name),
+        }
+    }
+
+    pub fn get_u64(&self, name: &str) -> Result<u64> {
+        let n = self.get_i128(name)?;
+        convert_at!(n)
+    }
+
+    pub fn get_usize(&self, name: &str) -> Result<usize> {
+        let n = self.get_i128(name)?;
+        convert_at!(n)
+    }
+
+    pub fn get_isize(&self, name: &str) -> Result<isize> {
+        let n = self.get_i128(name)?;
+        convert_at!(n)
+    }
+
+    pub fn get_string(&self, name: &str) -> Result<String> {
+        match self.get(name)? {
+            Item::Str(val) => Ok(val.clone()),
+            _ => self.mismatch(name),
+        }
+    }
+
+    /// Return None if field is `null` or an empty string.
+    pub fn get_opt_string(&self, name: &str) -> Result<Option<String>> {
+        match self.get(name)? {
+            Item::Str(val) if val.len() == 0 => Ok(None),
+            Item::Str(val) => Ok(Some(val.clone())),
+            Item::Null => Ok(None),
+            _ => self.mismatch(name),
+        }
+    }
+
+    pub fn get_list(&self, name: &str) -> Result<Vec<String>> {
+        match self.get(name)? {
+            Item::List(vals) => Ok(vals.clone()),
+            _ => self.mismatch(name),
+        }
+    }
+
+    /// Decode JSON text `s` into a record of `schema`.
+    pub fn from_json(schema: &'static Schema, s: &str) -> Result<Record> {
+        use jsondata::Json;
+
+        let js: Json = err_at!(InvalidInput, s.parse())?;
+
+        let version: u32 = match js.get(&format!("/{}", VERSION_KEY)) {
+            Ok(val) => match val.to_integer() {
+                Some(n) => convert_at!(n)?,
+                None => err_at!(InvalidInput, msg: format!("key:{}", VERSION_KEY))?,
+            },
+            Err(_) => 0,
+        };
+
+        let mut items = vec![];
+        for field in schema.fields.iter() {
+            let key = format!("/{}", field.name);
+            let val = match js.get(&key) {
+                Ok(val) => val,
+                Err(_) if field.since > version => {
+                    items.push(Item::new(field.kind));
+                    continue;
+                }
+                Err(_) => {
+                    let msg = format!("{} v{}, missing key:{}", schema.name, version, key);
+                    err_at!(InvalidInput, msg: msg)?
+                }
+            };
+            let item = match field.kind {
+                Kind::Bool => val.to_bool().map(Item::Bool),
+                Kind::Int => val.to_integer().map(|n| Item::Int(n.into())),
+                Kind::Str => val.as_str().map(|s| Item::Str(s.to_string())),
+                Kind::OptStr => match val.as_str() {
+                    Some(s) => Some(Item::Str(s.to_string())),
+                    None => Some(Item::Null),
+                },
+                Kind::List => {
+                    let mut vals = vec![];
+                    while let Ok(val) = js.get(&format!("{}/{}", key, vals.len())) {
+                        match val.as_str() {
+                            Some(s) => vals.push(s.to_string()),
+                            None => err_at!(InvalidInput, msg: format!("key:{}", key))?,
+                        }
+                    }
+                    Some(Item::List(vals))
+                }
+            };
+            match item {
+                Some(item) => items.push(item),
+                None => err_at!(InvalidInput, msg: format!("key:{}", key))?,
+            }
+        }
+
+        Ok(Record {
+            schema,
+            version,
+            items,
+        })
+    }
+}
+
+impl ToJson for Record {
+    fn to_json(&self) -> String {
+        let mut props = vec![format!(r#""{}": {}"#, VERSION_KEY, self.version)];
+        for (field, item) in self.schema.fields.iter().zip(self.items.iter()) {
+            props.push(format!(r#""{}": {}"#, field.name, item.to_json()));
+        }
+        format!(r#"{{ {} }}"#, props.join(", "))
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let props: Vec<String> = {
+            let iter = self.schema.fields.iter().zip(self.items.iter());
+            iter.map(|(field, item)| format!("{}={}", field.name, item.to_json()))
+                .collect()
+        };
+        write!(f, "{} = {{ {} }}", self.schema.name, props.join(", "))
+    }
+}
+
+fn to_json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+#[path = "schema_test.rs"]
+mod schema_test;
@@ -0,0 +1,94 @@
+use super::*;
+
+static TEST_SCHEMA: Schema = Schema {
+    name: "test.stats",
+    version: 2,
+    fields: &[
+        Field::new("name", Kind::Str, 0),
+        Field::new("n_count", Kind::Int, 0),
+        Field::new("lsm", Kind::Bool, 0),
+        Field::new("file", Kind::OptStr, 1),
+        Field::new("sources", Kind::List, 1),
+        Field::new("epoch", Kind::Int, 2),
+    ],
+};
+
+#[test]
+fn test_record() {
+    let mut r = Record::new(&TEST_SCHEMA);
+    assert_eq!(r.to_version(), 2);
+    r.set("name", "with \"quote\" and \\ slash")
+        .set("n_count", 1000_usize)
+        .set("lsm", true)
+        .set("file", Some("/tmp/file".to_string()))
+        .set("sources", vec!["a".to_string(), "b".to_string()])
+        .set("epoch", -10_i128);
+
+    let s = r.to_json();
+    println!("{}", s);
+    assert!(s.starts_with(r#"{ "schema_version": 2, "name": "#), "{}", s);
+
+    let r = Record::from_json(&TEST_SCHEMA, &s).unwrap();
+    assert_eq!(r.to_version(), 2);
+    assert_eq!(r.get_string("name").unwrap(), "with \"quote\" and \\ slash");
+    assert_eq!(r.get_usize("n_count").unwrap(), 1000);
+    assert_eq!(r.get_bool("lsm").unwrap(), true);
+    assert_eq!(
+        r.get_opt_string("file").unwrap(),
+        Some("/tmp/file".to_string())
+    );
+    assert_eq!(r.get_list("sources").unwrap(), vec!["a", "b"]);
+    assert_eq!(r.get_i128("epoch").unwrap(), -10);
+
+    // type mismatch and unknown fields.
+    match r.get_bool("n_count") {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res),
+    }
+    match r.get_bool("unknown") {
+        Err(Error::InvalidInput(_)) => (),
+        res => panic!("{:?}", res),
+    }
+    match r.get_usize("epoch") {
+        Err(Error::ConversionFail(_)) => (),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn test_record_versions() {
+    // version-0 record, without schema_version.
+    let s = r#"{ "name": "v0", "n_count": 10, "lsm": false }"#;
+    let r = Record::from_json(&TEST_SCHEMA, s).unwrap();
+    assert_eq!(r.to_version(), 0);
+    assert_eq!(r.get_string("name").unwrap(), "v0");
+    assert_eq!(r.get_opt_string("file").unwrap(), None);
+    assert_eq!(r.get_list("sources").unwrap().len(), 0);
+    assert_eq!(r.get_i128("epoch").unwrap(), 0);
+
+    // version-1 record shall have fields introduced in version-1.
+    let s = r#"{ "schema_version": 1, "name": "v1", "n_count": 10, "lsm": false }"#;
+    match Record::from_json(&TEST_SCHEMA, s) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("/file"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+    let s = r#"{ "schema_version": 1, "name": "v1", "n_count": 10, "lsm": false,
+        "file": null, "sources": [] }"#;
+    let r = Record::from_json(&TEST_SCHEMA, s).unwrap();
+    assert_eq!(r.to_version(), 1);
+    assert_eq!(r.get_opt_string("file").unwrap(), None);
+
+    // type mismatch in json.
+    let s = r#"{ "name": "v0", "n_count": "10", "lsm": false }"#;
+    match Record::from_json(&TEST_SCHEMA, s) {
+        Err(Error::InvalidInput(msg)) => assert!(msg.contains("/n_count"), "{}", msg),
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_record_set_mismatch() {
+    let mut r = Record::new(&TEST_SCHEMA);
+    r.set("n_count", "not a number");
+}
@@ -1416,6 +1416,44 @@ where
         }
     }
 
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        'outer: loop {
+            let mut readers = self.as_readers()?;
+
+            match Self::find(key, readers.as_mut_slice())? {
+                (_, ShardReader::Active { r, .. }) => break r.contains(key),
+                _ => {
+                    mem::drop(readers);
+                    thread::sleep(RETRY_INTERVAL);
+                    continue 'outer;
+                }
+            }
+        }
+    }
+
+    fn to_range_deleted<Q>(&mut self, key: &Q) -> Result<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        'outer: loop {
+            let mut readers = self.as_readers()?;
+
+            match Self::find(key, readers.as_mut_slice())? {
+                (_, ShardReader::Active { r, .. }) => break r.to_range_deleted(key),
+                _ => {
+                    mem::drop(readers);
+                    thread::sleep(RETRY_INTERVAL);
+                    continue 'outer;
+                }
+            }
+        }
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         'outer: loop {
             let mut iter = {
@@ -1552,6 +1590,15 @@ where
         }
     }
 
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
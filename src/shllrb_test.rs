@@ -541,6 +541,26 @@ fn test_range() {
     }
 }
 
+#[test]
+fn test_range_count() {
+    let config: Config = Default::default();
+    let mut index: Box<ShLlrb<i64, i64>> = ShLlrb::new("test-shllrb", config);
+
+    let mut w = index.to_writer().unwrap();
+    for key in 0..100 {
+        w.set(key, key * 10).unwrap();
+    }
+    for key in (0..100).filter(|key| key % 10 == 0) {
+        w.delete(&key).unwrap();
+    }
+
+    let mut r = index.to_reader().unwrap();
+    assert_eq!(r.range_count(..).unwrap(), 90);
+    assert_eq!(r.range_count(10..20).unwrap(), 9);
+    assert_eq!(r.range_count(95..=200).unwrap(), 5);
+    assert_eq!(r.to_range_deleted(&10).unwrap(), None);
+}
+
 #[test]
 fn test_crud() {
     let size = 1000;
@@ -14,7 +14,7 @@ use std::{
     ops::{Bound, RangeBounds},
     path, result,
     sync::{Arc, Mutex, MutexGuard},
-    thread,
+    thread, time,
 };
 
 use crate::{
@@ -23,7 +23,7 @@ use crate::{
     core::{Entry, Footprint, Index, IndexIter, Reader, Result, Serialize},
     error::Error,
     lsm,
-    panic::Panic,
+    panic::Unsupported,
     robt::{self, Robt},
     scans, util,
 };
@@ -747,7 +747,7 @@ where
     B: 'static + Send + Sync + Bloom,
 {
     type R = ShrobtReader<K, V, B>;
-    type W = Panic;
+    type W = Unsupported;
 
     #[inline]
     fn to_name(&self) -> Result<String> {
@@ -764,6 +764,26 @@ where
         Ok(self.seqno)
     }
 
+    fn to_reclaimable(&self, cutoff: bool) -> Result<usize> {
+        let shards = self.as_shards()?;
+
+        let mut reclaimable = 0;
+        for shard in shards.iter() {
+            reclaimable += shard.as_robt().to_reclaimable(cutoff)?
+        }
+        Ok(reclaimable)
+    }
+
+    fn to_bytes_written(&self) -> Result<usize> {
+        let shards = self.as_shards()?;
+
+        let mut written = 0;
+        for shard in shards.iter() {
+            written += shard.as_robt().to_bytes_written()?
+        }
+        Ok(written)
+    }
+
     #[inline]
     fn set_seqno(&mut self, _seqno: u64) -> Result<()> {
         // no-op
@@ -789,7 +809,7 @@ where
     }
 
     fn to_writer(&mut self) -> Result<Self::W> {
-        Ok(Panic::new("shrobt"))
+        Ok(Unsupported::new("shrobt"))
     }
 
     // holds global lock. no other operations are allowed.
@@ -1328,6 +1348,33 @@ where
         reader.snapshot.get(key)
     }
 
+    fn contains<Q>(&mut self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let (_, reader) = Self::find(key, self.readers.as_mut_slice())?;
+        reader.snapshot.contains(key)
+    }
+
+    fn get_deadline<Q>(&mut self, key: &Q, timeout: time::Duration) -> Result<Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let (_, reader) = Self::find(key, self.readers.as_mut_slice())?;
+        reader.snapshot.get_deadline(key, timeout)
+    }
+
+    fn to_range_deleted<Q>(&mut self, key: &Q) -> Result<Option<u64>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + Hash,
+    {
+        let (_, reader) = Self::find(key, self.readers.as_mut_slice())?;
+        reader.snapshot.to_range_deleted(key)
+    }
+
     fn iter(&mut self) -> Result<IndexIter<K, V>> {
         let mut iters = vec![];
         for reader in self.readers.iter_mut() {
@@ -1400,6 +1447,15 @@ where
         Ok(Box::new(Iter::new(iters)))
     }
 
+    fn range_count<'a, R, Q>(&'a mut self, range: R) -> Result<usize>
+    where
+        K: Borrow<Q>,
+        R: 'a + Clone + RangeBounds<Q>,
+        Q: 'a + Ord + ?Sized,
+    {
+        util::count_live(self.range(range)?)
+    }
+
     fn get_with_versions<Q>(&mut self, key: &Q) -> Result<Entry<K, V>>
     where
         K: Borrow<Q>,
@@ -230,6 +230,18 @@ fn test_shrobt_commit_compact() {
         check_entry2(&e, &re);
     }
 
+    //// test range_count
+    {
+        let mut refr = mindex.to_reader().unwrap();
+        let mut r = index.to_reader().unwrap();
+        for _ in 0..10 {
+            let low = rng.gen::<i64>().abs() % 2_000_000;
+            let high = low + (rng.gen::<i64>().abs() % 100_000);
+            let count = refr.range_count(low..high).unwrap();
+            assert_eq!(r.range_count(low..high).unwrap(), count);
+        }
+    }
+
     match index.validate() {
         Err(Error::EmptyIndex) if mindex.len() == 0 => (),
         Err(err) => panic!("{:?}", err),
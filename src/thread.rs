@@ -63,12 +63,14 @@ struct Inner<Q, R, T> {
 }
 
 impl<Q, R, T> Inner<Q, R, T> {
-    fn close_wait(self) -> Result<T> {
+    fn close_wait(self, name: &str) -> Result<T> {
         mem::drop(self.tx); // drop input channel to thread.
 
-        match self.handle.join().unwrap() {
-            Ok(exit) => Ok(exit),
-            Err(err) => Err(err),
+        // a panic in thread's main loop is propagated as Fatal error.
+        match self.handle.join() {
+            Ok(Ok(exit)) => Ok(exit),
+            Ok(Err(err)) => Err(err),
+            Err(_) => err_at!(Fatal, msg: format!("thread {} panicked", name)),
         }
     }
 }
@@ -79,7 +81,9 @@ impl<Q, R, T> Drop for Thread<Q, R, T> {
             match Arc::get_mut(&mut self.refn) {
                 Some(_) => match self.inner.take() {
                     Some(inner) => {
-                        inner.close_wait().ok();
+                        if let Err(err) = inner.close_wait(&self.name) {
+                            error!(target: "thread", "{} exit {:?}", self.name, err);
+                        }
                         break;
                     }
                     None => break,
@@ -193,10 +197,10 @@ impl<Q, R, T> Thread<Q, R, T> {
     ///
     /// Even otherwise, when Thread value goes out of scope its drop
     /// implementation shall call this method to exit the thread, except
-    /// that any errors are ignored.
+    /// that any errors are only logged.
     pub fn close_wait(mut self) -> Result<T> {
         match self.inner.take() {
-            Some(inner) => inner.close_wait(),
+            Some(inner) => inner.close_wait(&self.name),
             None => err_at!(Fatal, msg: format!("Thread.close_wait()")),
         }
     }
@@ -1,11 +1,25 @@
 //! Module `types` implement core key-traits and value-traits for
 //! built-in types.
 
-use std::{convert::TryInto, ffi, marker};
+use std::{
+    borrow::Borrow,
+    cmp,
+    collections::HashSet,
+    convert::TryInto,
+    ffi, fmt,
+    hash::{Hash, Hasher},
+    marker,
+    mem::size_of,
+    ops::Deref,
+    rc::Rc,
+    result,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
-    core::{Diff, Entry, Footprint, Result, Serialize},
+    core::{Diff, Entry, Footprint, OrdSpec, Prefixed, Result, Serialize},
     error::Error,
+    util,
 };
 
 // TODO: instead of using resize()/copy_from_slice() can we use
@@ -91,6 +105,26 @@ impl Footprint for [u8; 20] {
     }
 }
 
+impl Prefixed for [u8; 20] {
+    type P = Vec<u8>;
+
+    fn prefix(&self, len: usize) -> Self::P {
+        self[..len.min(20)].to_vec()
+    }
+
+    fn prefix_len(prefix: &Self::P) -> usize {
+        prefix.len()
+    }
+
+    /// Prefix padded with ZEROs.
+    fn prefix_start(prefix: &Self::P) -> Self {
+        let mut key = [0_u8; 20];
+        let n = prefix.len().min(20);
+        key[..n].copy_from_slice(&prefix[..n]);
+        key
+    }
+}
+
 //-------------------------------------------------------------------
 
 impl Diff for Vec<u8> {
@@ -144,6 +178,22 @@ impl Footprint for Vec<u8> {
     }
 }
 
+impl Prefixed for Vec<u8> {
+    type P = Vec<u8>;
+
+    fn prefix(&self, len: usize) -> Self::P {
+        self[..len.min(self.len())].to_vec()
+    }
+
+    fn prefix_len(prefix: &Self::P) -> usize {
+        prefix.len()
+    }
+
+    fn prefix_start(prefix: &Self::P) -> Self {
+        prefix.clone()
+    }
+}
+
 //-------------------------------------------------------------------
 
 impl Diff for i32 {
@@ -230,6 +280,612 @@ impl Footprint for i64 {
 
 //-------------------------------------------------------------------
 
+impl Diff for u64 {
+    type D = u64;
+
+    /// D = C - P
+    fn diff(&self, old: &Self) -> Self::D {
+        old.clone()
+    }
+
+    /// P = C - D
+    fn merge(&self, delta: &Self::D) -> Self {
+        delta.clone()
+    }
+}
+
+impl Serialize for u64 {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let n = buf.len();
+        buf.resize(n + 8, 0);
+        buf[n..n + 8].copy_from_slice(&self.to_be_bytes());
+        Ok(8)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.len() >= 8 {
+            let mut scratch = [0_u8; 8];
+            scratch.copy_from_slice(&buf[..8]);
+            *self = u64::from_be_bytes(scratch);
+            Ok(8)
+        } else {
+            err_at!(DecodeFail, msg: format!("type-u64, len {}", buf.len()))
+        }
+    }
+}
+
+//-------------------------------------------------------------------
+
+impl Diff for u128 {
+    type D = u128;
+
+    /// D = C - P
+    fn diff(&self, old: &Self) -> Self::D {
+        old.clone()
+    }
+
+    /// P = C - D
+    fn merge(&self, delta: &Self::D) -> Self {
+        delta.clone()
+    }
+}
+
+impl Serialize for u128 {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let n = buf.len();
+        buf.resize(n + 16, 0);
+        buf[n..n + 16].copy_from_slice(&self.to_be_bytes());
+        Ok(16)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.len() >= 16 {
+            let mut scratch = [0_u8; 16];
+            scratch.copy_from_slice(&buf[..16]);
+            *self = u128::from_be_bytes(scratch);
+            Ok(16)
+        } else {
+            err_at!(DecodeFail, msg: format!("type-u128, len {}", buf.len()))
+        }
+    }
+}
+
+impl Footprint for u128 {
+    fn footprint(&self) -> Result<isize> {
+        Ok(0)
+    }
+}
+
+//-------------------------------------------------------------------
+
+// Fixed size keys, like u64, u128 and [u8; 16], are stored inline
+// within the index node, hence they own no heap memory.
+impl Diff for [u8; 16] {
+    type D = [u8; 16];
+
+    /// D = C - P
+    fn diff(&self, old: &Self) -> Self::D {
+        old.clone()
+    }
+
+    /// P = C - D
+    fn merge(&self, delta: &Self::D) -> Self {
+        delta.clone()
+    }
+}
+
+impl Serialize for [u8; 16] {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let m = buf.len();
+        buf.resize(m + 16, 0);
+        buf[m..(m + 16)].copy_from_slice(self);
+        Ok(16)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.len() >= 16 {
+            self.copy_from_slice(&buf[..16]);
+            Ok(16)
+        } else {
+            err_at!(DecodeFail, msg: format!("type-[u8;16], len {}", buf.len()))
+        }
+    }
+}
+
+impl Footprint for [u8; 16] {
+    fn footprint(&self) -> Result<isize> {
+        Ok(0)
+    }
+}
+
+//-------------------------------------------------------------------
+
+/// Value type that shares the underlying value via [Arc].
+///
+/// Iterating over memory indexes, like [Mvcc][crate::mvcc::Mvcc], clone
+/// every entry, which deep copies the value. With `ArcValue` only the
+/// handle is cloned, useful for large values like multi-KB documents.
+/// Older versions are preserved as full values, not as diffs, and
+/// serialized bytes are same as that of `V`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArcValue<V>(Arc<V>);
+
+impl<V> Clone for ArcValue<V> {
+    fn clone(&self) -> ArcValue<V> {
+        ArcValue(Arc::clone(&self.0))
+    }
+}
+
+impl<V> ArcValue<V> {
+    /// Create a new shared value.
+    pub fn new(value: V) -> ArcValue<V> {
+        ArcValue(Arc::new(value))
+    }
+
+    /// Return the shared handle to value.
+    pub fn to_arc(&self) -> Arc<V> {
+        Arc::clone(&self.0)
+    }
+}
+
+impl<V> From<V> for ArcValue<V> {
+    fn from(value: V) -> ArcValue<V> {
+        ArcValue::new(value)
+    }
+}
+
+impl<V> Deref for ArcValue<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.0.as_ref()
+    }
+}
+
+impl<V: Footprint> Diff for ArcValue<V> {
+    type D = ArcValue<V>;
+
+    /// D = C - P
+    fn diff(&self, old: &Self) -> Self::D {
+        old.clone()
+    }
+
+    /// P = C - D
+    fn merge(&self, delta: &Self::D) -> Self {
+        delta.clone()
+    }
+}
+
+impl<V: Default + Serialize> Serialize for ArcValue<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        self.0.encode(buf)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut value: V = Default::default();
+        let n = value.decode(buf)?;
+        self.0 = Arc::new(value);
+        Ok(n)
+    }
+}
+
+impl<V: Footprint> Footprint for ArcValue<V> {
+    fn footprint(&self) -> Result<isize> {
+        self.0.footprint()
+    }
+}
+
+//-------------------------------------------------------------------
+
+/// Key type that shares the underlying key via [Arc].
+///
+/// Along with an [Interner], repeated keys, like the ones re-inserted
+/// after a delete or written into successive memory indexes, can share
+/// a single allocation. Useful for large keys like long strings.
+/// Serialized bytes are same as that of `K`.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArcKey<K>(Arc<K>);
+
+impl<K> Clone for ArcKey<K> {
+    fn clone(&self) -> ArcKey<K> {
+        ArcKey(Arc::clone(&self.0))
+    }
+}
+
+impl<K> ArcKey<K> {
+    /// Create a new shared key.
+    pub fn new(key: K) -> ArcKey<K> {
+        ArcKey(Arc::new(key))
+    }
+
+    /// Return the shared handle to key.
+    pub fn to_arc(&self) -> Arc<K> {
+        Arc::clone(&self.0)
+    }
+}
+
+impl<K> From<K> for ArcKey<K> {
+    fn from(key: K) -> ArcKey<K> {
+        ArcKey::new(key)
+    }
+}
+
+impl<K> Deref for ArcKey<K> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        self.0.as_ref()
+    }
+}
+
+impl<K> Borrow<K> for ArcKey<K> {
+    fn borrow(&self) -> &K {
+        self.0.as_ref()
+    }
+}
+
+impl<K: Default + Serialize> Serialize for ArcKey<K> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        self.0.encode(buf)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut key: K = Default::default();
+        let n = key.decode(buf)?;
+        self.0 = Arc::new(key);
+        Ok(n)
+    }
+}
+
+impl<K: Footprint> Footprint for ArcKey<K> {
+    fn footprint(&self) -> Result<isize> {
+        self.0.footprint()
+    }
+}
+
+/// Hash-consing table for [ArcKey], refer to [Llrb::set_interner].
+///
+/// Interning a key returns the handle already in the table, if any,
+/// so that the key passed in is dropped. Table holds a reference to
+/// every interned key, use [purge][Interner::purge] to release keys
+/// that are no more referred by any index.
+///
+/// [Llrb::set_interner]: crate::llrb::Llrb::set_interner
+pub struct Interner<K> {
+    inner: Mutex<InnerInterner<K>>,
+}
+
+struct InnerInterner<K> {
+    keys: HashSet<ArcKey<K>>,
+    stats: InternStats,
+}
+
+impl<K> Default for Interner<K>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Interner<K> {
+        Interner::new()
+    }
+}
+
+impl<K> Interner<K>
+where
+    K: Hash + Eq,
+{
+    /// Create an empty interner.
+    pub fn new() -> Interner<K> {
+        let inner = InnerInterner {
+            keys: HashSet::new(),
+            stats: Default::default(),
+        };
+        Interner {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    /// Return the interned handle for `key`, adding `key` to the table
+    /// if it is seen for the first time.
+    pub fn intern(&self, key: ArcKey<K>) -> Result<ArcKey<K>>
+    where
+        K: Footprint,
+    {
+        let mut inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err))?,
+        };
+        inner.stats.n_lookups += 1;
+        let ikey = inner.keys.get(key.deref()).cloned();
+        match ikey {
+            Some(ikey) => {
+                inner.stats.n_hits += 1;
+                if Arc::strong_count(&key.0) == 1 {
+                    inner.stats.saved_bytes += key.footprint()?;
+                }
+                Ok(ikey)
+            }
+            None => {
+                inner.keys.insert(key.clone());
+                Ok(key)
+            }
+        }
+    }
+
+    /// Drop interned keys that are not referred outside this table.
+    /// Return the number of keys dropped.
+    pub fn purge(&self) -> Result<usize> {
+        let mut inner = match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err))?,
+        };
+        let n = inner.keys.len();
+        inner.keys.retain(|key| Arc::strong_count(&key.0) > 1);
+        Ok(n - inner.keys.len())
+    }
+
+    /// Return interning statistics.
+    pub fn to_stats(&self) -> Result<InternStats> {
+        match self.inner.lock() {
+            Ok(inner) => {
+                let mut stats = inner.stats.clone();
+                stats.n_keys = inner.keys.len();
+                Ok(stats)
+            }
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+}
+
+impl<K> util::KeyIntern<ArcKey<K>> for Interner<K>
+where
+    K: Send + Sync + Hash + Eq + Footprint,
+{
+    fn intern(&self, key: ArcKey<K>) -> Result<ArcKey<K>> {
+        Interner::intern(self, key)
+    }
+}
+
+/// Statistic type, for [Interner].
+#[derive(Clone, Debug, Default)]
+pub struct InternStats {
+    /// Number of keys looked up in the table.
+    pub n_lookups: usize,
+    /// Number of lookups that returned an already interned key.
+    pub n_hits: usize,
+    /// Number of keys in the table.
+    pub n_keys: usize,
+    /// Footprint of keys dropped in favor of an interned key.
+    pub saved_bytes: isize,
+}
+
+impl fmt::Display for InternStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(
+            f,
+            "intern = {{ n_lookups={}, n_hits={}, n_keys={}, saved_bytes={} }}",
+            self.n_lookups, self.n_hits, self.n_keys, self.saved_bytes,
+        )
+    }
+}
+//-------------------------------------------------------------------
+
+/// Key type sorted using the [OrdSpec] `S`, instead of `K: Ord`.
+///
+/// Serialized bytes are same as that of `K`, hence encoding and
+/// decoding is forwarded to `K`.
+pub struct Ordered<K, S> {
+    key: K,
+    _phantom_spec: marker::PhantomData<S>,
+}
+
+impl<K, S> Ordered<K, S> {
+    /// Wrap `key` to be sorted using `S`.
+    pub fn new(key: K) -> Ordered<K, S> {
+        Ordered {
+            key,
+            _phantom_spec: marker::PhantomData,
+        }
+    }
+
+    /// Return a reference to the underlying key.
+    pub fn as_key(&self) -> &K {
+        &self.key
+    }
+
+    /// Return the underlying key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K: Clone, S> Clone for Ordered<K, S> {
+    fn clone(&self) -> Self {
+        Ordered::new(self.key.clone())
+    }
+}
+
+impl<K: Default, S> Default for Ordered<K, S> {
+    fn default() -> Self {
+        Ordered::new(K::default())
+    }
+}
+
+impl<K: fmt::Debug, S> fmt::Debug for Ordered<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        write!(f, "Ordered<{:?}>", self.key)
+    }
+}
+
+impl<K, S: OrdSpec<K>> PartialEq for Ordered<K, S> {
+    fn eq(&self, other: &Self) -> bool {
+        S::cmp(&self.key, &other.key) == cmp::Ordering::Equal
+    }
+}
+
+impl<K, S: OrdSpec<K>> Eq for Ordered<K, S> {}
+
+impl<K, S: OrdSpec<K>> PartialOrd for Ordered<K, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(S::cmp(&self.key, &other.key))
+    }
+}
+
+impl<K, S: OrdSpec<K>> Ord for Ordered<K, S> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        S::cmp(&self.key, &other.key)
+    }
+}
+
+impl<K, S: OrdSpec<K>> Hash for Ordered<K, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        S::hash(&self.key, state)
+    }
+}
+
+impl<K: Serialize, S> Serialize for Ordered<K, S> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        self.key.encode(buf)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        self.key.decode(buf)
+    }
+}
+
+impl<K: Footprint, S> Footprint for Ordered<K, S> {
+    fn footprint(&self) -> Result<isize> {
+        self.key.footprint()
+    }
+}
+
+/// Case-insensitive ordering for ASCII byte-string keys, refer to
+/// [Ordered].
+pub struct AsciiCaseless;
+
+impl OrdSpec<Vec<u8>> for AsciiCaseless {
+    const NAME: &'static str = "ascii-caseless";
+
+    fn cmp(a: &Vec<u8>, b: &Vec<u8>) -> cmp::Ordering {
+        let a = a.iter().map(|x| x.to_ascii_lowercase());
+        let b = b.iter().map(|x| x.to_ascii_lowercase());
+        a.cmp(b)
+    }
+
+    fn hash<H: Hasher>(key: &Vec<u8>, state: &mut H) {
+        key.len().hash(state);
+        for x in key.iter() {
+            x.to_ascii_lowercase().hash(state);
+        }
+    }
+}
+
+//-------------------------------------------------------------------
+
+// Footprint for composite types. Following the rule laid down by
+// [Footprint], only heap allocations owned by the value are counted,
+// inline size is accounted by the container holding the value.
+//
+// NOTE: there is no blanket implementation for `Box<T>`, index types
+// implement Footprint for their boxed handle.
+
+impl Footprint for u64 {
+    fn footprint(&self) -> Result<isize> {
+        Ok(0)
+    }
+}
+
+impl Footprint for String {
+    fn footprint(&self) -> Result<isize> {
+        Ok(convert_at!(self.capacity())?)
+    }
+}
+
+impl<T: Footprint> Footprint for Vec<T> {
+    fn footprint(&self) -> Result<isize> {
+        let mut fp: isize = convert_at!(self.capacity() * size_of::<T>())?;
+        for item in self.iter() {
+            fp += item.footprint()?;
+        }
+        Ok(fp)
+    }
+}
+
+impl<T: Footprint> Footprint for Option<T> {
+    fn footprint(&self) -> Result<isize> {
+        match self {
+            Some(item) => item.footprint(),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Shared value is counted in full for every handle, hence the
+/// footprint of a collection holding clones of same `Rc` is over
+/// estimated.
+impl<T: Footprint> Footprint for Rc<T> {
+    fn footprint(&self) -> Result<isize> {
+        let fp: isize = convert_at!(size_of::<T>())?;
+        Ok(fp + self.as_ref().footprint()?)
+    }
+}
+
+/// Shared value is counted in full for every handle, hence the
+/// footprint of a collection holding clones of same `Arc` is over
+/// estimated.
+impl<T: Footprint> Footprint for Arc<T> {
+    fn footprint(&self) -> Result<isize> {
+        let fp: isize = convert_at!(size_of::<T>())?;
+        Ok(fp + self.as_ref().footprint()?)
+    }
+}
+
+macro_rules! tuple_footprint {
+    ($(($($t:ident : $i:tt),+)),+) => {
+        $(
+            impl<$($t: Footprint),+> Footprint for ($($t,)+) {
+                fn footprint(&self) -> Result<isize> {
+                    Ok(0 $(+ self.$i.footprint()?)+)
+                }
+            }
+        )+
+    };
+}
+
+tuple_footprint!(
+    (A: 0, B: 1),
+    (A: 0, B: 1, C: 2),
+    (A: 0, B: 1, C: 2, D: 3)
+);
+
+/// Implement [Footprint] for a struct by summing up the footprint of
+/// its fields. Every listed field shall implement Footprint, fields
+/// that own no heap memory can be skipped.
+///
+/// ```ignore
+/// struct Doc {
+///     id: u64,
+///     name: String,
+///     tags: Vec<String>,
+/// }
+///
+/// rdms::impl_footprint!(Doc { name, tags });
+/// ```
+#[macro_export]
+macro_rules! impl_footprint {
+    ($t:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::core::Footprint for $t {
+            fn footprint(&self) -> $crate::core::Result<isize> {
+                #[allow(unused_imports)]
+                use $crate::core::Footprint;
+
+                Ok(0 $(+ self.$field.footprint()?)*)
+            }
+        }
+    };
+}
+
+//-------------------------------------------------------------------
+
 // TODO: Is this required ??
 pub(crate) struct EmptyIter<'a, K, V>
 where
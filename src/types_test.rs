@@ -1,6 +1,13 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    mem::size_of,
+    sync::Arc,
+};
+
 use crate::{
-    core::{Diff, Footprint, Serialize},
-    types::Empty,
+    core::{Diff, Footprint, OrdSpec, Prefixed, Serialize},
+    types::{ArcKey, ArcValue, AsciiCaseless, Empty, Interner, Ordered},
 };
 
 #[test]
@@ -37,6 +44,24 @@ fn test_bytes_footprint() {
     assert_eq!(value.footprint().unwrap(), 3);
 }
 
+#[test]
+fn test_bytes_prefix() {
+    let key = "tenant/doc".as_bytes().to_vec();
+    assert_eq!(key.prefix(7), "tenant/".as_bytes().to_vec());
+    assert_eq!(key.prefix(100), key);
+    let prefix = "tenant/".as_bytes().to_vec();
+    assert_eq!(<Vec<u8>>::prefix_len(&prefix), 7);
+    assert_eq!(<Vec<u8>>::prefix_start(&prefix), prefix);
+
+    let mut key = [0xFF_u8; 20];
+    key[..3].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(key.prefix(3), vec![1, 2, 3]);
+    assert_eq!(key.prefix(30).len(), 20);
+    let mut start = [0_u8; 20];
+    start[..3].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(<[u8; 20]>::prefix_start(&vec![1, 2, 3]), start);
+}
+
 #[test]
 fn test_empty_diff() {
     let old = Empty;
@@ -117,3 +142,183 @@ fn test_i64_footprint() {
     let value = 0_i64;
     assert_eq!(value.footprint().unwrap(), 0);
 }
+
+#[test]
+fn test_fixed_keys() {
+    let (old, new) = (10_u64, 20_u64);
+    assert_eq!(old, new.merge(&new.diff(&old)));
+    let mut buf = vec![];
+    assert_eq!(new.encode(&mut buf).unwrap(), 8);
+    let mut out: u64 = Default::default();
+    assert_eq!(out.decode(&buf).unwrap(), 8);
+    assert_eq!(out, new);
+
+    let (old, new) = (10_u128, u128::MAX);
+    assert_eq!(old, new.merge(&new.diff(&old)));
+    let mut buf = vec![];
+    assert_eq!(new.encode(&mut buf).unwrap(), 16);
+    let mut out: u128 = Default::default();
+    assert_eq!(out.decode(&buf).unwrap(), 16);
+    assert_eq!(out, new);
+    assert_eq!(new.footprint().unwrap(), 0);
+    assert!(out.decode(&buf[..15]).is_err());
+
+    let (old, new) = ([1_u8; 16], [2_u8; 16]);
+    assert_eq!(old, new.merge(&new.diff(&old)));
+    let mut buf = vec![];
+    assert_eq!(new.encode(&mut buf).unwrap(), 16);
+    let mut out = [0_u8; 16];
+    assert_eq!(out.decode(&buf).unwrap(), 16);
+    assert_eq!(out, new);
+    assert_eq!(new.footprint().unwrap(), 0);
+    assert!(out.decode(&buf[..15]).is_err());
+
+    // big-endian encoding preserves the key order.
+    let (mut a, mut b) = (vec![], vec![]);
+    1_u128.encode(&mut a).unwrap();
+    256_u128.encode(&mut b).unwrap();
+    assert!(a < b);
+}
+
+#[test]
+fn test_arc_value() {
+    use crate::{
+        core::{Reader, Writer},
+        mvcc::Mvcc,
+    };
+
+    let old = ArcValue::new(vec![1_u8; 4096]);
+    let new = ArcValue::new(vec![2_u8; 4096]);
+    let diff = new.diff(&old);
+    assert!(Arc::ptr_eq(&diff.to_arc(), &old.to_arc()));
+    assert_eq!(new.merge(&diff), old);
+
+    let mut buf = vec![];
+    assert_eq!(new.encode(&mut buf).unwrap(), 4100);
+    let mut value: ArcValue<Vec<u8>> = Default::default();
+    assert_eq!(value.decode(&buf).unwrap(), 4100);
+    assert_eq!(value, new);
+    assert_eq!(
+        new.footprint().unwrap(),
+        vec![2_u8; 4096].footprint().unwrap() + 24
+    );
+
+    // iteration shall yield handles to the same value.
+    let mut index: Box<Mvcc<i64, ArcValue<Vec<u8>>>> = Mvcc::new_lsm("test-arc-value");
+    for key in 0..100 {
+        index
+            .set(key, ArcValue::new(vec![key as u8; 4096]))
+            .unwrap();
+    }
+    let values: Vec<ArcValue<Vec<u8>>> = index
+        .iter()
+        .unwrap()
+        .map(|e| e.unwrap().to_native_value().unwrap())
+        .collect();
+    for (key, value) in values.into_iter().enumerate() {
+        let entry = index.get(&(key as i64)).unwrap();
+        let arc = entry.to_native_value().unwrap().to_arc();
+        assert!(Arc::ptr_eq(&arc, &value.to_arc()));
+        assert_eq!(value.len(), 4096);
+    }
+}
+
+#[test]
+fn test_arc_key() {
+    let key = ArcKey::new(vec![1_u8; 90]);
+    let mut buf = vec![];
+    assert_eq!(key.encode(&mut buf).unwrap(), 94);
+    let mut out: ArcKey<Vec<u8>> = Default::default();
+    assert_eq!(out.decode(&buf).unwrap(), 94);
+    assert_eq!(out, key);
+    assert_eq!(
+        key.footprint().unwrap(),
+        vec![1_u8; 90].footprint().unwrap()
+    );
+
+    let interner: Interner<Vec<u8>> = Interner::new();
+    let first = interner.intern(key.clone()).unwrap();
+    assert!(Arc::ptr_eq(&first.to_arc(), &key.to_arc()));
+    // fresh allocation of the same key is dropped.
+    let second = interner.intern(ArcKey::new(vec![1_u8; 90])).unwrap();
+    assert!(Arc::ptr_eq(&second.to_arc(), &key.to_arc()));
+    let third = interner.intern(ArcKey::new(vec![2_u8; 90])).unwrap();
+
+    let stats = interner.to_stats().unwrap();
+    assert_eq!((stats.n_lookups, stats.n_hits, stats.n_keys), (3, 2, 2));
+    assert_eq!(stats.saved_bytes, key.footprint().unwrap());
+
+    assert_eq!(interner.purge().unwrap(), 0);
+    std::mem::drop(third);
+    assert_eq!(interner.purge().unwrap(), 1);
+    assert_eq!(interner.to_stats().unwrap().n_keys, 1);
+}
+
+#[test]
+fn test_ordered() {
+    type Key = Ordered<Vec<u8>, AsciiCaseless>;
+
+    assert_eq!(AsciiCaseless::NAME, "ascii-caseless");
+
+    let (a, b, c) = (
+        Key::new(b"Hello".to_vec()),
+        Key::new(b"hELLO".to_vec()),
+        Key::new(b"help".to_vec()),
+    );
+    assert!(a == b);
+    assert!(a < c && b < c);
+    assert!(Key::new(b"Z".to_vec()) > Key::new(b"a".to_vec()));
+
+    let hash = |key: &Key| {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&a), hash(&b));
+
+    let (mut buf, mut ref_buf) = (vec![], vec![]);
+    let n = a.encode(&mut buf).unwrap();
+    assert_eq!(n, a.as_key().encode(&mut ref_buf).unwrap());
+    assert_eq!(buf, ref_buf);
+    let mut out: Key = Default::default();
+    assert_eq!(out.decode(&buf).unwrap(), n);
+    assert_eq!(out.as_key(), a.as_key());
+    assert_eq!(out.footprint().unwrap(), out.as_key().footprint().unwrap());
+}
+
+#[test]
+fn test_composite_footprint() {
+    let name = String::with_capacity(10);
+    assert_eq!(name.footprint().unwrap(), 10);
+
+    let names: Vec<String> = vec!["abc".to_string(), "defg".to_string()];
+    let n: isize = (names.capacity() * size_of::<String>()) as isize;
+    assert_eq!(names.footprint().unwrap(), n + 7);
+
+    let mut opt: Option<Vec<u8>> = None;
+    assert_eq!(opt.footprint().unwrap(), 0);
+    opt = Some(vec![1, 2, 3]);
+    assert_eq!(opt.footprint().unwrap(), 3);
+
+    let shared = Arc::new("abc".to_string());
+    let n: isize = size_of::<String>() as isize;
+    assert_eq!(shared.footprint().unwrap(), n + 3);
+
+    let tuple = (10_u64, "abc".to_string(), vec![1_u8; 4]);
+    assert_eq!(tuple.footprint().unwrap(), 7);
+
+    struct Doc {
+        _id: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+    crate::impl_footprint!(Doc { name, tags });
+
+    let doc = Doc {
+        _id: 10,
+        name: "doc".to_string(),
+        tags: Vec::with_capacity(2),
+    };
+    let n: isize = (2 * size_of::<String>()) as isize;
+    assert_eq!(doc.footprint().unwrap(), n + 3);
+}
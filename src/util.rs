@@ -1,13 +1,20 @@
+use lazy_static::lazy_static;
+
 use std::{
     borrow::Borrow,
+    cmp,
     convert::TryInto,
     ffi, fs,
+    hash::{Hash, Hasher},
+    marker,
     ops::{Bound, RangeBounds},
     path,
+    sync::{Arc, Mutex, RwLock},
+    time,
 };
 
 use crate::{
-    core::{Footprint, Result},
+    core::{Bloom, Diff, Footprint, IndexIter, Result},
     error::Error,
 };
 
@@ -66,6 +73,43 @@ macro_rules! read_file {
     };
 }
 
+// Emit a structured event for operation `op`, under target "rdms". Only
+// compiled in with `tracing` feature, field values are not evaluated
+// otherwise.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($op:expr, $($field:ident = $value:expr),* $(,)?) => {
+        tracing::event!(target: "rdms", tracing::Level::DEBUG, op = $op, $($field = $value),*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($op:expr, $($field:ident = $value:expr),* $(,)?) => {
+        ()
+    };
+}
+
+// Enter a span for long running operation, like flush and compaction,
+// span is exited when the returned guard is dropped.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($name:expr, $($field:ident = $value:expr),* $(,)?) => {
+        tracing::debug_span!(target: "rdms", $name, $($field = $value),*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($name:expr, $($field:ident = $value:expr),* $(,)?) => {
+        crate::util::NoSpan
+    };
+}
+
+// Placeholder guard for trace_span!() without `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
 // create a file in append mode for writing.
 pub(crate) fn create_file_a(file: ffi::OsString) -> Result<fs::File> {
     let os_file = {
@@ -96,6 +140,13 @@ pub(crate) fn open_file_w(file: &ffi::OsString) -> Result<fs::File> {
     Ok(err_at!(IoError, opts.append(true).open(os_file))?)
 }
 
+// open existing file for writing at arbitrary positions.
+pub(crate) fn open_file_pw(file: &ffi::OsStr) -> Result<fs::File> {
+    let os_file = path::Path::new(file);
+    let mut opts = fs::OpenOptions::new();
+    Ok(err_at!(IoError, opts.write(true).open(os_file))?)
+}
+
 // open file for reading.
 pub(crate) fn open_file_r(file: &ffi::OsStr) -> Result<fs::File> {
     let os_file = path::Path::new(file);
@@ -105,6 +156,81 @@ pub(crate) fn open_file_r(file: &ffi::OsStr) -> Result<fs::File> {
     )?)
 }
 
+/// Return `Error::TimedOut` if `deadline` is set and has already passed.
+pub(crate) fn check_deadline(deadline: Option<time::Instant>) -> Result<()> {
+    match deadline {
+        Some(deadline) if time::Instant::now() >= deadline => Err(Error::TimedOut),
+        _ => Ok(()),
+    }
+}
+
+/// Generate a random 128-bit identifier, formatted as 32 hex digits.
+pub(crate) fn new_uuid() -> String {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    let nanos = time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let mut words = [0_u64; 2];
+    for word in words.iter_mut() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        *word = hasher.finish();
+    }
+    format!("{:016x}{:016x}", words[0], words[1])
+}
+
+// maximum number of buffers held in the buffer pool.
+const POOL_BUFFERS: usize = 64;
+// buffers larger than this are released to the allocator.
+const POOL_MAX_CAPACITY: usize = 16 * 1024 * 1024;
+
+lazy_static! {
+    // crate-wide freelist of byte buffers. Blocks are typically encoded
+    // on one thread and released by another, like the flush thread,
+    // hence a shared pool instead of a thread-local one.
+    static ref BUFFER_POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::with_capacity(POOL_BUFFERS));
+}
+
+// Get an empty buffer, with at least `capacity` bytes, from the buffer
+// pool. Allocate a new one if pool has no such buffer. Buffers are
+// returned back to the pool via put_buffer().
+pub(crate) fn get_buffer(capacity: usize) -> Vec<u8> {
+    let buf = match BUFFER_POOL.lock() {
+        Ok(mut pool) => match pool.iter().rposition(|b| b.capacity() >= capacity) {
+            Some(off) => Some(pool.swap_remove(off)),
+            None => None,
+        },
+        Err(_) => None,
+    };
+    buf.unwrap_or_else(|| Vec::with_capacity(capacity))
+}
+
+// Same as get_buffer(), with a copy of `data`.
+pub(crate) fn copy_buffer(data: &[u8]) -> Vec<u8> {
+    let mut buf = get_buffer(data.len());
+    buf.extend_from_slice(data);
+    buf
+}
+
+// Return `buf` back to the buffer pool, for reuse. Buffer is dropped
+// if the pool is full or if buffer is too large.
+pub(crate) fn put_buffer(mut buf: Vec<u8>) {
+    if buf.capacity() == 0 || buf.capacity() > POOL_MAX_CAPACITY {
+        return;
+    }
+    buf.clear();
+    if let Ok(mut pool) = BUFFER_POOL.lock() {
+        if pool.len() < POOL_BUFFERS {
+            pool.push(buf);
+        }
+    }
+}
+
 pub(crate) fn to_start_end<G, K>(within: G) -> (Bound<K>, Bound<K>)
 where
     K: Clone,
@@ -123,6 +249,146 @@ where
     (start, end)
 }
 
+// intersection of two seqno windows.
+pub(crate) fn intersect_seqnos(
+    a: (Bound<u64>, Bound<u64>),
+    b: (Bound<u64>, Bound<u64>),
+) -> (Bound<u64>, Bound<u64>) {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let start = match (a.0, b.0) {
+        (Unbounded, x) | (x, Unbounded) => x,
+        (Included(x), Included(y)) => Included(cmp::max(x, y)),
+        (Excluded(x), Excluded(y)) => Excluded(cmp::max(x, y)),
+        (Included(x), Excluded(y)) | (Excluded(y), Included(x)) if x > y => Included(x),
+        (Included(_), Excluded(y)) | (Excluded(y), Included(_)) => Excluded(y),
+    };
+    let end = match (a.1, b.1) {
+        (Unbounded, x) | (x, Unbounded) => x,
+        (Included(x), Included(y)) => Included(cmp::min(x, y)),
+        (Excluded(x), Excluded(y)) => Excluded(cmp::min(x, y)),
+        (Included(x), Excluded(y)) | (Excluded(y), Included(x)) if x < y => Included(x),
+        (Included(_), Excluded(y)) | (Excluded(y), Included(_)) => Excluded(y),
+    };
+    (start, end)
+}
+
+// return whether seqno window cannot contain any seqno.
+pub(crate) fn is_empty_seqnos(window: &(Bound<u64>, Bound<u64>)) -> bool {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    match window {
+        (Included(x), Included(y)) => x > y,
+        (Included(x), Excluded(y)) | (Excluded(x), Included(y)) => x >= y,
+        (Excluded(x), Excluded(y)) => x.saturating_add(1) >= *y,
+        (Excluded(x), Unbounded) => *x == u64::MAX,
+        (_, Excluded(0)) => true,
+        _ => false,
+    }
+}
+
+// count entries from iterator, skipping entries marked as deleted.
+pub(crate) fn count_live<K, V>(iter: IndexIter<K, V>) -> Result<usize>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    let mut n = 0;
+    for entry in iter {
+        if !entry?.is_deleted() {
+            n += 1;
+        }
+    }
+    Ok(n)
+}
+
+// collect keys from iterator, skipping entries marked as deleted.
+pub(crate) fn live_keys<K, V>(iter: IndexIter<K, V>) -> Result<Vec<K>>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+{
+    let mut keys = vec![];
+    for entry in iter {
+        let entry = entry?;
+        if !entry.is_deleted() {
+            keys.push(entry.to_key());
+        }
+    }
+    Ok(keys)
+}
+
+// Type erased, thread safe, handle to a Bloom bitmap. Used by memory
+// indexes to fail fast on missing keys, refer to Llrb::set_bitmap.
+pub(crate) trait KeyBitmap<K>: Send + Sync {
+    // `hash` shall feed the key, being looked up, into the hasher.
+    fn contains_hash(&self, hash: &dyn Fn(&mut dyn Hasher)) -> bool;
+
+    fn add_key(&self, key: &K);
+}
+
+// Type erased, thread safe, handle to a key interner. Used by memory
+// indexes to share one allocation across repeated keys, refer to
+// Llrb::set_interner.
+pub(crate) trait KeyIntern<K>: Send + Sync {
+    fn intern(&self, key: K) -> Result<K>;
+}
+
+pub(crate) fn new_key_bitmap<K, B>(bitmap: B) -> Arc<dyn KeyBitmap<K>>
+where
+    K: 'static + Hash,
+    B: 'static + Send + Sync + Bloom,
+{
+    Arc::new(SyncBitmap {
+        bitmap: RwLock::new(bitmap),
+        _phantom_key: marker::PhantomData,
+    })
+}
+
+// return false only if `key` is known to be missing in `bitmap`.
+pub(crate) fn bitmap_contains<K, Q>(bitmap: &Option<Arc<dyn KeyBitmap<K>>>, key: &Q) -> bool
+where
+    Q: ?Sized + Hash,
+{
+    match bitmap {
+        Some(bitmap) => bitmap.contains_hash(&|mut h: &mut dyn Hasher| key.hash(&mut h)),
+        None => true,
+    }
+}
+
+struct SyncBitmap<K, B> {
+    bitmap: RwLock<B>,
+    _phantom_key: marker::PhantomData<fn(&K)>,
+}
+
+impl<K, B> KeyBitmap<K> for SyncBitmap<K, B>
+where
+    K: Hash,
+    B: Send + Sync + Bloom,
+{
+    fn contains_hash(&self, hash: &dyn Fn(&mut dyn Hasher)) -> bool {
+        match self.bitmap.read() {
+            Ok(bitmap) => bitmap.contains(&DynHash(hash)),
+            Err(_) => true, // poisoned lock, can't rule out the key.
+        }
+    }
+
+    fn add_key(&self, key: &K) {
+        if let Ok(mut bitmap) = self.bitmap.write() {
+            bitmap.add_key(key)
+        }
+    }
+}
+
+// Hash a key, whose type is erased, same as the key itself.
+struct DynHash<'a>(&'a dyn Fn(&mut dyn Hasher));
+
+impl<'a> Hash for DynHash<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0)(state)
+    }
+}
+
 pub(crate) fn key_footprint<K>(key: &K) -> Result<isize>
 where
     K: Footprint,
@@ -112,3 +112,70 @@ fn test_as_sharded_array() {
         }
     }
 }
+
+#[test]
+fn test_buffer_pool() {
+    // pool is shared across test threads, only check for invariants.
+    for capacity in vec![0, 10, 1024, 1024 * 1024] {
+        let buf = get_buffer(capacity);
+        assert!(buf.is_empty());
+        assert!(buf.capacity() >= capacity);
+        put_buffer(buf);
+    }
+
+    let data: Vec<u8> = (0..255).collect();
+    let buf = copy_buffer(&data);
+    assert_eq!(buf, data);
+    put_buffer(buf);
+
+    let buf = get_buffer(data.len());
+    assert!(buf.is_empty());
+    assert!(buf.capacity() >= data.len());
+}
+
+#[test]
+fn test_intersect_seqnos() {
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    let testcases = vec![
+        (
+            (Unbounded, Unbounded),
+            (Unbounded, Unbounded),
+            (Unbounded, Unbounded),
+        ),
+        (
+            (Included(10), Unbounded),
+            (Unbounded, Included(20)),
+            (Included(10), Included(20)),
+        ),
+        (
+            (Included(10), Included(30)),
+            (Excluded(10), Excluded(30)),
+            (Excluded(10), Excluded(30)),
+        ),
+        (
+            (Included(11), Included(29)),
+            (Excluded(10), Excluded(30)),
+            (Included(11), Included(29)),
+        ),
+        (
+            (Excluded(5), Included(8)),
+            (Excluded(7), Unbounded),
+            (Excluded(7), Included(8)),
+        ),
+    ];
+    for (a, b, refw) in testcases.into_iter() {
+        assert_eq!(intersect_seqnos(a, b), refw, "{:?} {:?}", a, b);
+        assert_eq!(intersect_seqnos(b, a), refw, "{:?} {:?}", b, a);
+    }
+
+    assert!(!is_empty_seqnos(&(Unbounded, Unbounded)));
+    assert!(!is_empty_seqnos(&(Included(10), Included(10))));
+    assert!(is_empty_seqnos(&(Included(10), Included(9))));
+    assert!(is_empty_seqnos(&(Included(10), Excluded(10))));
+    assert!(is_empty_seqnos(&(Excluded(10), Included(10))));
+    assert!(is_empty_seqnos(&(Excluded(10), Excluded(11))));
+    assert!(!is_empty_seqnos(&(Excluded(10), Excluded(12))));
+    assert!(is_empty_seqnos(&(Unbounded, Excluded(0))));
+    assert!(is_empty_seqnos(&(Excluded(u64::MAX), Unbounded)));
+}
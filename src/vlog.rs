@@ -1,14 +1,108 @@
-use std::{
-    convert::TryInto,
-    fs,
-    io::{self, Read, Seek},
-};
+use std::{convert::TryInto, ffi, fs, io};
 
 use crate::{
     core::{self, Diff, Footprint, Result, Serialize},
     error::Error,
+    robt_cipher::FileCipher,
+    robt_compress::ValueDict,
 };
 
+/// Value-log can be split into several segment files. File-position of
+/// a value or delta carries the segment number in the upper bits and its
+/// offset within the segment file in the lower `SEGMENT_SHIFT` bits.
+/// Un-segmented value-log is treated as segment ZERO.
+pub(crate) const SEGMENT_SHIFT: u64 = 40;
+
+/// Split value-log file-position into segment number and offset.
+pub(crate) fn to_segment(fpos: u64) -> Result<(usize, u64)> {
+    let segment = convert_at!(fpos >> SEGMENT_SHIFT)?;
+    Ok((segment, fpos & ((1 << SEGMENT_SHIFT) - 1)))
+}
+
+/// Compose value-log file-position from segment number and offset.
+pub(crate) fn from_segment(segment: usize, offset: u64) -> Result<u64> {
+    if offset >= (1 << SEGMENT_SHIFT) {
+        err_at!(Fatal, msg: format!("segment offset {} overflow", offset))?;
+    }
+    let segment: u64 = convert_at!(segment)?;
+    Ok((segment << SEGMENT_SHIFT) | offset)
+}
+
+/// Set in the header of a value or delta, if its payload is compressed
+/// using the build's dictionary, refer to [compress_payload].
+const COMPRESSED_FLAG: u64 = 0x2000000000000000;
+
+/// Compress the payload of a value or delta, encoded at `buf[m..]`, and
+/// return the size of header + payload. Payload is left uncompressed if
+/// compression does not save space.
+pub(crate) fn compress_payload(buf: &mut Vec<u8>, m: usize, dict: &ValueDict) -> Result<usize> {
+    let payload = dict.compress(&buf[m + 8..])?;
+    if payload.len() < (buf.len() - m - 8) {
+        let hdr1 = u64::from_be_bytes(array_at!(buf[m..m + 8])?);
+        let mut n: u64 = convert_at!(payload.len())?;
+        n |= (hdr1 & !0x0FFFFFFFFFFFFFFF) | COMPRESSED_FLAG;
+        buf.truncate(m);
+        buf.extend_from_slice(&n.to_be_bytes());
+        buf.extend_from_slice(&payload);
+    }
+    Ok(buf.len() - m)
+}
+
+// return the payload of a value or delta read from value-log,
+// decompressed if required.
+fn to_payload(block: Vec<u8>, dict: Option<&ValueDict>) -> Result<Vec<u8>> {
+    check_remaining!(block, 8, "vlog header")?;
+    let hdr1 = u64::from_be_bytes(array_at!(block[..8])?);
+    match dict {
+        _ if (hdr1 & COMPRESSED_FLAG) == 0 => Ok(block[8..].to_vec()),
+        Some(dict) => dict.decompress(&block[8..]),
+        None => err_at!(InvalidFile, msg: format!("compressed payload without dictionary")),
+    }
+}
+
+fn read_segment(fpos: u64, n: u64, fds: &[fs::File], msg: &str) -> Result<Vec<u8>> {
+    let (segment, offset) = to_segment(fpos)?;
+    match fds.get(segment) {
+        Some(fd) => read_at(fd, offset, n, msg),
+        None => err_at!(InvalidFile, msg: format!("missing vlog segment {}", segment)),
+    }
+}
+
+// positional read, leaves the file cursor untouched, hence the same
+// vlog file-descriptor can serve concurrent fetches without seeking.
+fn read_at(fd: &fs::File, fpos: u64, n: u64, msg: &str) -> Result<Vec<u8>> {
+    let mut buf = vec![0; convert_at!(n)?];
+
+    #[cfg(unix)]
+    let res = {
+        use std::os::unix::fs::FileExt;
+        fd.read_exact_at(&mut buf, fpos)
+    };
+    #[cfg(windows)]
+    let res = {
+        use std::os::windows::fs::FileExt;
+        let mut m = 0;
+        loop {
+            if m == buf.len() {
+                break Ok(());
+            }
+            match fd.seek_read(&mut buf[m..], fpos + (m as u64)) {
+                Ok(0) => break Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                Ok(k) => m += k,
+                Err(err) => break Err(err),
+            }
+        }
+    };
+
+    match res {
+        Ok(()) => Ok(buf),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            err_at!(Fatal, msg: format!("{}, {} bytes at {}", msg, n, fpos))
+        }
+        Err(err) => err_at!(IoError, Err(err)),
+    }
+}
+
 // *-----*------------------------------------*
 // |flags|        60-bit length               |
 // *-----*------------------------------------*
@@ -17,7 +111,7 @@ use crate::{
 //
 // Flags:
 // * bit 60 shall be set.
-// * bit 61 reserved
+// * bit 61 set if payload is compressed.
 // * bit 62 reserved
 // * bit 63 reserved
 
@@ -134,13 +228,24 @@ where
     }
 }
 
-pub(crate) fn fetch_value<V>(fpos: u64, n: u64, fd: &mut fs::File) -> Result<Value<V>>
+pub(crate) fn fetch_value<V>(
+    fpos: u64,
+    n: u64,
+    file: &ffi::OsStr, // value-log file, segments are opened as `fds`
+    fds: &[fs::File],
+    cipher: Option<&FileCipher>,
+    dict: Option<&ValueDict>,
+) -> Result<Value<V>>
 where
     V: Default + Serialize,
 {
-    let block = read_file!(fd, fpos, n, "reading value from vlog")?;
+    let mut block = read_segment(fpos, n, fds, "reading value from vlog")
+        .map_err(|err| err.into_file_fail(Some(file.to_os_string()), Some(fpos), None))?;
+    if let Some(cipher) = cipher {
+        cipher.decrypt(fpos, &mut block)?;
+    }
     let mut value: V = Default::default();
-    value.decode(&block[8..])?;
+    value.decode(&to_payload(block, dict)?)?;
     Ok(Value::new_native(value))
 }
 
@@ -152,7 +257,7 @@ where
 //
 // Flags:
 // * bit 60 shall be clear.
-// * bit 61 reserved
+// * bit 61 set if payload is compressed.
 // * bit 62 reserved
 // * bit 63 reserved
 
@@ -246,14 +351,25 @@ where
     }
 }
 
-pub(crate) fn fetch_delta<V>(fpos: u64, n: u64, fd: &mut fs::File) -> Result<Delta<V>>
+pub(crate) fn fetch_delta<V>(
+    fpos: u64,
+    n: u64,
+    file: &ffi::OsStr, // value-log file, segments are opened as `fds`
+    fds: &[fs::File],
+    cipher: Option<&FileCipher>,
+    dict: Option<&ValueDict>,
+) -> Result<Delta<V>>
 where
     V: Diff,
     <V as Diff>::D: Default + Serialize,
 {
-    let block = read_file!(fd, fpos, n, "reading delta from vlog")?;
+    let mut block = read_segment(fpos, n, fds, "reading delta from vlog")
+        .map_err(|err| err.into_file_fail(Some(file.to_os_string()), Some(fpos), None))?;
+    if let Some(cipher) = cipher {
+        cipher.decrypt(fpos, &mut block)?;
+    }
     let mut delta: <V as Diff>::D = Default::default();
-    delta.decode(&block[8..])?;
+    delta.decode(&to_payload(block, dict)?)?;
     Ok(Delta::new_native(delta))
 }
 
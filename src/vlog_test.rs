@@ -1,4 +1,4 @@
-use crate::{core::Footprint, vlog};
+use crate::{core::Footprint, error::Error, vlog};
 
 #[test]
 fn test_value() {
@@ -39,6 +39,73 @@ fn test_fetch_value() {
     assert_eq!(refb, out);
 }
 
+#[test]
+fn test_fetch_value_concurrent() {
+    use std::{fs, sync::Arc, thread};
+
+    let mut path = std::env::temp_dir();
+    path.push("test_fetch_value_concurrent.data");
+
+    let (mut buf, mut refs) = (vec![], vec![]);
+    for i in 0..100_u8 {
+        let value = vlog::Value::new_native(vec![i; (i as usize) + 1]);
+        let fpos = buf.len() as u64;
+        let (_, n) = value.encode(&mut buf).unwrap();
+        refs.push((fpos, n as u64, vec![i; (i as usize) + 1]));
+    }
+    fs::write(path.clone(), &buf).expect("io failure");
+
+    // all threads share the same file-descriptor.
+    let fds = Arc::new(vec![fs::File::open(&path).unwrap()]);
+    let refs = Arc::new(refs);
+    let mut handles = vec![];
+    for id in 0..4 {
+        let (fds, refs) = (Arc::clone(&fds), Arc::clone(&refs));
+        let file = path.clone().into_os_string();
+        handles.push(thread::spawn(move || {
+            for j in 0..refs.len() {
+                let (fpos, n, value) = &refs[(j * 7 + id) % refs.len()];
+                let v: vlog::Value<Vec<u8>> =
+                    vlog::fetch_value(*fpos, *n, &file, &fds, None, None).unwrap();
+                assert_eq!(v.to_native_value().as_ref(), Some(value));
+            }
+        }));
+    }
+    for handle in handles.into_iter() {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn test_fetch_file_fail() {
+    use std::fs;
+
+    let mut path = std::env::temp_dir();
+    path.push("test_fetch_file_fail.data");
+
+    let value = vlog::Value::new_native(vec![10_u8, 20, 30]);
+    let mut buf = vec![];
+    let (_, n) = value.encode(&mut buf).unwrap();
+    fs::write(path.clone(), &buf).expect("io failure");
+
+    // read beyond the end of value-log shall report the file.
+    let file = path.into_os_string();
+    let fds = vec![fs::File::open(&file).unwrap()];
+    let n = (n as u64) + 10;
+    match vlog::fetch_value::<Vec<u8>>(0, n, &file, &fds, None, None) {
+        Err(Error::FileFail {
+            file: Some(f),
+            fpos: Some(0),
+            ..
+        }) => assert_eq!(f, file),
+        res => panic!("unexpected {:?}", res.map(|v| v.to_native_value())),
+    }
+    match vlog::fetch_delta::<Vec<u8>>(0, n, &file, &fds, None, None) {
+        Err(Error::FileFail { file: Some(f), .. }) => assert_eq!(f, file),
+        res => panic!("unexpected {:?}", res.map(|d| d.into_native_delta())),
+    }
+}
+
 #[test]
 fn test_delta() {
     let delta = vlog::Delta::<i32>::new_native(10);
@@ -77,3 +144,16 @@ fn test_fetch_delta() {
     let out = std::fs::read(path).unwrap();
     assert_eq!(refb, out);
 }
+
+#[test]
+fn test_segment() {
+    let fpos = vlog::from_segment(0, 1000).unwrap();
+    assert_eq!(fpos, 1000);
+    assert_eq!(vlog::to_segment(fpos).unwrap(), (0, 1000));
+
+    let fpos = vlog::from_segment(3, 64).unwrap();
+    assert_eq!(fpos, (3 << vlog::SEGMENT_SHIFT) | 64);
+    assert_eq!(vlog::to_segment(fpos).unwrap(), (3, 64));
+
+    assert!(vlog::from_segment(1, 1 << vlog::SEGMENT_SHIFT).is_err());
+}
@@ -35,6 +35,9 @@
 //!     Dlog::load() -> Wal-> replay() -> close()
 //! ```
 //!
+//! Use `replay_parallel()` instead of `replay()` to replay each shard
+//! concurrently into its own writer.
+//!
 //! **Purge cycle**:
 //!
 //! ```compile_fail
@@ -45,29 +48,45 @@
 //!     +---------------+
 //! ```
 //!
+//! Applications can log their own events, like schema changes, in the
+//! same durability stream as index mutations, refer to
+//! [Writer::append_app] and [Wal::set_app_handler].
+//!
+//! Replication senders can observe every batch committed to disk, its
+//! seqno range, size and latency, using [Wal::set_commit_hook].
+//!
+//! Replication metadata, like term, leader and committed seqno, can be
+//! driven by an external consensus module, refer to [Wal::set_term] and
+//! [Wal::set_committed]. It is persisted with every batch and restored
+//! when [Wal] is loaded, refer to [Wal::to_state].
+//!
 //! [wal-link]: https://en.wikipedia.org/wiki/Write-ahead_logging
 
 use log::debug;
 
 use std::{
     borrow::Borrow,
+    cmp,
     convert::{self, TryInto},
     ffi, fmt, fs,
     hash::{BuildHasher, Hash, Hasher},
+    mem,
     ops::Bound,
     result,
-    sync::{atomic::AtomicU64, atomic::Ordering::SeqCst, Arc},
+    sync::{atomic::AtomicU64, atomic::Ordering::SeqCst, Arc, Mutex, MutexGuard},
+    thread, time, vec,
 };
 
 use crate::{
-    core::{Diff, Replay, Result, Serialize},
-    dlog::{Dlog, DlogState, OpRequest, OpResponse},
-    dlog_entry::DEntry,
-    dlog_journal::Shard,
+    core::{CasPolicy, Diff, Replay, Result, Serialize},
+    dlog::{CommitHook, Dlog, DlogState, OpRequest, OpResponse, Retention},
+    dlog_entry::{Batch, DEntry},
+    dlog_journal::{Journal, Shard},
     error::Error,
     thread as rt,
 };
 
+pub use crate::dlog::BatchSummary;
 #[allow(unused_imports)]
 use crate::rdms::Rdms;
 
@@ -81,8 +100,15 @@ where
     dir: ffi::OsString,
     name: String,
     hash_builder: H,
-
-    seqno: Arc<AtomicU64>, // seqno
+    cas_policy: Option<CasPolicy<V>>, // explicitly configured.
+    replay_order: ReplayOrder,
+    app_handler: Option<AppHandler>,
+
+    seqno: Arc<AtomicU64>,      // seqno
+    checkpoint: Arc<AtomicU64>, // seqno
+    purged: u64,                // seqno
+    retention: bool,
+    state: Arc<Mutex<State>>,
     threads: Vec<rt::Thread<OpRequest<Op<K, V>>, OpResponse, Shard<State, Op<K, V>>>>,
 }
 
@@ -110,13 +136,21 @@ where
             name: dl.name,
 
             hash_builder: h,
+            cas_policy: None,
+            replay_order: Default::default(),
+            app_handler: None,
             seqno: dl.seqno,
+            checkpoint: Arc::new(AtomicU64::new(0)),
+            purged: 0,
+            retention: false,
+            state: Arc::new(Mutex::new(dl.state)),
             threads: Default::default(),
         };
 
         debug!(target: "wal   ", "{:?}/{} from dlog", wl.dir, wl.name);
 
-        for shard in dl.shards {
+        for mut shard in dl.shards {
+            shard.set_state(Arc::clone(&wl.state));
             wl.threads.push(shard.into_thread())
         }
 
@@ -130,6 +164,44 @@ where
         Ok(self)
     }
 
+    /// Set the compare-and-set policy, expected to be same as the
+    /// policy configured on the index. Set-cas operations logged in
+    /// this [Wal] shall be replayed using the same policy. If not set,
+    /// replay shall use the policy already configured on the index.
+    pub fn set_cas_policy(&mut self, policy: CasPolicy<V>) -> Result<&mut Self> {
+        debug!(
+            target: "wal   ",
+            "{:?}/{} cas policy {:?}", self.dir, self.name, policy
+        );
+        self.cas_policy = Some(policy);
+        Ok(self)
+    }
+
+    /// Set the order in which logged mutations shall be replayed, refer
+    /// to [ReplayOrder]. Default is [ReplayOrder::Global].
+    pub fn set_replay_order(&mut self, order: ReplayOrder) -> Result<&mut Self> {
+        debug!(
+            target: "wal   ",
+            "{:?}/{} replay order {:?}", self.dir, self.name, order
+        );
+        self.replay_order = order;
+        Ok(self)
+    }
+
+    /// Set the callback for application defined operations, logged using
+    /// [Writer::append_app] or [Router::append_app]. Handler is called
+    /// with the seqno, op-code and payload of each such operation during
+    /// [replay][Wal::replay], interleaved with index mutations in replay
+    /// order. Without a handler, application operations are skipped.
+    pub fn set_app_handler<F>(&mut self, handler: F) -> Result<&mut Self>
+    where
+        F: 'static + Send + FnMut(u64, u32, &[u8]) -> Result<()>,
+    {
+        debug!(target: "wal   ", "{:?}/{} app handler set", self.dir, self.name);
+        self.app_handler = Some(Box::new(handler));
+        Ok(self)
+    }
+
     /// Close the [Wal] instance. To purge the instance use [Wal::purge] api.
     pub fn close(&mut self) -> Result<u64> {
         for thread in self.threads.drain(..).into_iter() {
@@ -168,14 +240,177 @@ where
             thread.request(OpRequest::new_purge_till(before))?;
         }
 
+        let purged = match before {
+            Bound::Included(before) => before,
+            Bound::Excluded(before) => before.saturating_sub(1),
+            Bound::Unbounded => self.to_seqno().saturating_sub(1),
+        };
+        self.purged = cmp::max(self.purged, purged);
+
         Ok(before)
     }
 
+    /// Set retention policy for journal files. Once the total size of
+    /// journal files exceed `bytes`, or once a journal file is older
+    /// than `age`, oldest journals shall be purged automatically.
+    /// `bytes` is split evenly across shards. Journals are purged only
+    /// when all its entries are at or before the checkpoint, refer to
+    /// [Wal::set_checkpoint]. Passing None for both disables retention.
+    pub fn set_retention(
+        &mut self,
+        bytes: Option<usize>,
+        age: Option<time::Duration>,
+    ) -> Result<&mut Self> {
+        let bytes: Option<u64> = match bytes {
+            Some(bytes) => {
+                let n: u64 = convert_at!(self.threads.len().max(1))?;
+                let bytes: u64 = convert_at!(bytes)?;
+                Some(bytes / n)
+            }
+            None => None,
+        };
+        let retention = Retention {
+            bytes,
+            age,
+            checkpoint: Arc::clone(&self.checkpoint),
+        };
+        for thread in self.threads.iter() {
+            thread.request(OpRequest::new_retention(retention.clone()))?;
+        }
+        self.retention = bytes.is_some() || age.is_some();
+
+        debug!(
+            target: "wal   ",
+            "{:?}/{} retention bytes:{:?} age:{:?}", self.dir, self.name, bytes, age
+        );
+        Ok(self)
+    }
+
+    /// Set an observer, called after every batch of mutations is
+    /// appended to its shard's journal and, if the [Dlog] is configured
+    /// with `fsync`, synced to disk. Observer is called from the shard's
+    /// thread with a [BatchSummary], hence it should return quickly,
+    /// say by handing the summary off to a replication sender.
+    pub fn set_commit_hook<F>(&mut self, hook: F) -> Result<&mut Self>
+    where
+        F: 'static + Send + Sync + Fn(&BatchSummary),
+    {
+        let hook: CommitHook = Arc::new(hook);
+        for thread in self.threads.iter() {
+            let req = OpRequest::new_commit_hook(Some(Arc::clone(&hook)));
+            thread.request(req)?;
+        }
+
+        debug!(target: "wal   ", "{:?}/{} commit hook set", self.dir, self.name);
+        Ok(self)
+    }
+
+    /// Notify that all mutations till `seqno` are persisted by the
+    /// index, hence journals containing them can be purged as and when
+    /// retention limits are exceeded. Checkpoint can't go backward.
+    pub fn set_checkpoint(&mut self, seqno: u64) -> Result<&mut Self> {
+        self.checkpoint.fetch_max(seqno, SeqCst);
+        {
+            let mut state = self.as_state()?;
+            state.persisted = cmp::max(state.persisted, seqno);
+        }
+        Ok(self)
+    }
+
+    /// Set the current `term` and its `leader`, as decided by an external
+    /// consensus module. Term can't go backward, and a term can have
+    /// only one leader. Subsequent batches are persisted with this term,
+    /// refer to [State].
+    pub fn set_term(&mut self, term: u64, leader: &str) -> Result<&mut Self> {
+        {
+            let mut state = self.as_state()?;
+            if term < state.term {
+                let msg = format!("term {} < {}", term, state.term);
+                err_at!(InvalidInput, msg: msg)?;
+            } else if term == state.term && !state.votedfor.is_empty() && state.votedfor != leader {
+                let msg = format!("term {} leader {:?}", term, state.votedfor);
+                err_at!(InvalidInput, msg: msg)?;
+            }
+            state.term = term;
+            state.votedfor = leader.to_string();
+        }
+
+        debug!(
+            target: "wal   ",
+            "{:?}/{} term:{} leader:{:?}", self.dir, self.name, term, leader
+        );
+        Ok(self)
+    }
+
+    /// Notify that all mutations till `seqno` are replicated to majority
+    /// of participating nodes. Committed seqno can't go backward and
+    /// can't go beyond the last logged mutation.
+    pub fn set_committed(&mut self, seqno: u64) -> Result<&mut Self> {
+        let last_seqno = self.to_last_seqno();
+        if seqno > last_seqno {
+            let msg = format!("committed {} > {}", seqno, last_seqno);
+            err_at!(InvalidInput, msg: msg)?;
+        }
+        {
+            let mut state = self.as_state()?;
+            state.committed = cmp::max(state.committed, seqno);
+        }
+        Ok(self)
+    }
+
+    /// Set the list of participating nodes, as decided by an external
+    /// consensus module.
+    pub fn set_config(&mut self, config: Vec<String>) -> Result<&mut Self> {
+        self.as_state()?.config = config;
+        Ok(self)
+    }
+
+    /// Return the current replication metadata. For a loaded [Wal], this
+    /// is restored from the latest batch persisted in its journals.
+    pub fn to_state(&self) -> Result<State> {
+        Ok(self.as_state()?.clone())
+    }
+
+    fn as_state(&self) -> Result<MutexGuard<State>> {
+        match self.state.lock() {
+            Ok(state) => Ok(state),
+            Err(err) => err_at!(Fatal, msg: format!("poisened lock {}", err)),
+        }
+    }
+
+    // Move seqno forward, so that next mutation is logged after `seqno`.
+    // Seqno can't go backward.
+    pub(crate) fn fast_forward(&mut self, seqno: u64) {
+        self.seqno.fetch_max(seqno + 1, SeqCst);
+    }
+
     /// Return the current seqno.
     pub fn to_seqno(&mut self) -> u64 {
         self.seqno.load(SeqCst)
     }
 
+    /// Return the seqno of the last mutation logged, ZERO if nothing is
+    /// logged yet. For a loaded [Wal], this is restored from the last
+    /// batch of each shard's journals, so that new mutations are never
+    /// logged with a seqno that is already present in the journals.
+    pub fn to_last_seqno(&mut self) -> u64 {
+        self.seqno.load(SeqCst).saturating_sub(1)
+    }
+
+    /// Return the window of seqno, as `(low, high)`, that can be
+    /// replayed from this log. Mutations after `low` and upto `high`
+    /// are guaranteed to be in the log. Mutations at or before `low`
+    /// might have been purged, either via [Wal::purge_till] or by the
+    /// retention policy. Purges done by an earlier instance of this
+    /// log, before it was loaded, are not accounted.
+    pub fn to_replay_window(&mut self) -> (u64, u64) {
+        let low = match self.retention {
+            true => cmp::max(self.purged, self.checkpoint.load(SeqCst)),
+            false => self.purged,
+        };
+        (low, self.to_seqno().saturating_sub(1))
+    }
+
     /// Create a new writer handle.
     pub fn to_writer(&mut self) -> Result<Writer<K, V, H>> {
         debug!(target: "wal   ", "new writer for {:?}/{}", self.dir, self.name);
@@ -189,6 +424,27 @@ where
                 .collect(),
         })
     }
+
+    /// Create a new router handle, that shall route mutations on a key
+    /// to the shard picked by `hash_fn`. Unlike [Writer], whose routing
+    /// depends on the hash-builder, `hash_fn` is expected to be
+    /// deterministic across process restarts, refer to [Router] for the
+    /// sequencing rules.
+    pub fn to_router<F>(&mut self, hash_fn: F) -> Result<Router<K, V, F>>
+    where
+        F: Fn(&K) -> u64,
+    {
+        debug!(target: "wal   ", "new router for {:?}/{}", self.dir, self.name);
+
+        Ok(Router {
+            hash_fn,
+            shards: self
+                .threads
+                .iter()
+                .map(|thread| thread.to_client())
+                .collect(),
+        })
+    }
 }
 
 impl<K, V, H> Wal<K, V, H>
@@ -201,8 +457,9 @@ where
     /// can be used to fetch the latest set of mutations and replay them on
     /// DB. Only mutations greater-than `seqno` will be re-applied on db.
     ///
-    /// Return total number of operations replayed on DB.
-    pub fn replay<P>(self, db: &mut P, seqno: u64) -> Result<usize>
+    /// Return total number of operations replayed on DB, including
+    /// application operations, refer to [Wal::set_app_handler].
+    pub fn replay<P>(mut self, db: &mut P, seqno: u64) -> Result<usize>
     where
         V: Diff,
         P: Replay<K, V>,
@@ -217,57 +474,132 @@ where
             "replay from seqno:{} for {:?}/{}", seqno, self.dir, self.name
         );
 
-        let mut ops = 0;
+        if let Some(policy) = self.cas_policy {
+            db.set_cas_policy(policy)?;
+        }
+        let mut on_app = self.app_handler.take();
 
-        // println!("threads: {} seqno:{}", self.threads.len(), seqno);
+        let mut iters = vec![];
         for thread in self.threads.into_iter() {
             let journals = thread.close_wait()?.into_journals();
-            for journal in journals.into_iter() {
-                // println!("journal li:{:?}", journal.to_last_seqno());
-                if journal.is_cold() {
-                    continue;
-                }
-                match journal.to_last_seqno()? {
-                    Some(last_seqno) if last_seqno <= seqno => continue,
-                    _ => (),
-                }
+            iters.push(ReplayIter::new(journals, seqno));
+        }
 
-                let mut fd = {
-                    let file_path = journal.to_file_path();
-                    let mut opts = fs::OpenOptions::new();
-                    err_at!(IoError, opts.read(true).write(false).open(file_path))?
-                };
-
-                for batch in journal.into_batches()? {
-                    // println!("batch li:{:?}", batch.to_last_seqno());
-                    match batch.to_last_seqno() {
-                        Some(last_seqno) if last_seqno <= seqno => continue,
-                        _ => (),
-                    }
-                    for entry in batch.into_active(&mut fd)?.into_entries()? {
-                        let (e_seqno, op) = entry.into_seqno_op();
-                        if e_seqno <= seqno {
-                            continue;
-                        }
-                        // println!("seqno {}", e_seqno);
-                        match op {
-                            Op::Set { key, value } => {
-                                db.set_index(key, value, e_seqno)?;
-                            }
-                            Op::SetCAS { key, value, cas } => {
-                                db.set_cas_index(key, value, cas, e_seqno)?;
-                            }
-                            Op::Delete { key } => {
-                                db.delete_index(key, e_seqno)?;
-                            }
-                        }
+        match self.replay_order {
+            ReplayOrder::PerShard => {
+                let mut ops = 0;
+                for iter in iters.into_iter() {
+                    for item in iter {
+                        let (e_seqno, op) = item?;
+                        replay_op(db, &mut on_app, e_seqno, op)?;
                         ops += 1;
                     }
                 }
+                Ok(ops)
+            }
+            ReplayOrder::Global => Self::replay_merge(db, &mut on_app, iters),
+        }
+    }
+
+    /// Replay each shard's journals concurrently, `writers[i]` receives
+    /// the mutations logged in shard `i`. Only mutations greater-than
+    /// `seqno` will be re-applied. Replay order is always per shard,
+    /// refer to [ReplayOrder::PerShard].
+    ///
+    /// Safe only when keys are routed to shards, refer to
+    /// [Wal::to_router], so that mutations on a key never span across
+    /// writers. Application operations are serialized across shards, but
+    /// not in seqno order.
+    ///
+    /// Return the writers back, along with the maximum seqno applied.
+    pub fn replay_parallel<P>(mut self, writers: Vec<P>, seqno: u64) -> Result<(Vec<P>, u64)>
+    where
+        V: Diff,
+        P: 'static + Send + Replay<K, V>,
+    {
+        // validate
+        if self.is_active() {
+            return err_at!(APIMisuse, msg: format!("active-shards"));
+        }
+        if writers.len() != self.threads.len() {
+            let (n, m) = (writers.len(), self.threads.len());
+            return err_at!(InvalidInput, msg: format!("writers {} shards {}", n, m));
+        }
+
+        debug!(
+            target: "wal   ",
+            "parallel replay from seqno:{} for {:?}/{}", seqno, self.dir, self.name
+        );
+
+        // gather journals from all shards before spawning any replay
+        // thread, failing to close a shard shall not leave replay threads
+        // running detached.
+        let mut iters = vec![];
+        for thread in self.threads.into_iter() {
+            let journals = thread.close_wait()?.into_journals();
+            iters.push(ReplayIter::new(journals, seqno));
+        }
+
+        let on_app = Arc::new(Mutex::new(self.app_handler.take()));
+        let mut handles = vec![];
+        for (iter, db) in iters.into_iter().zip(writers.into_iter()) {
+            let (cas_policy, on_app) = (self.cas_policy, Arc::clone(&on_app));
+            handles.push(thread::spawn(move || {
+                replay_shard(db, cas_policy, on_app, iter)
+            }));
+        }
+
+        let (mut writers, mut max_seqno) = (vec![], 0);
+        let mut res = Ok(());
+        for handle in handles.into_iter() {
+            match handle.join() {
+                Ok(Ok((db, seqno))) => {
+                    writers.push(db);
+                    max_seqno = cmp::max(max_seqno, seqno);
+                }
+                Ok(Err(err)) if res.is_ok() => res = Err(err),
+                Err(_) if res.is_ok() => {
+                    res = err_at!(Fatal, msg: format!("replay shard panicked"));
+                }
+                _ => (),
             }
         }
+        res.map(|_| (writers, max_seqno))
+    }
 
-        Ok(ops)
+    // k-way merge across shards, each shard is already sorted on seqno.
+    fn replay_merge<P>(
+        db: &mut P,
+        on_app: &mut Option<AppHandler>,
+        mut iters: Vec<ReplayIter<K, V>>,
+    ) -> Result<usize>
+    where
+        V: Diff,
+        P: Replay<K, V>,
+    {
+        let mut heads = vec![];
+        for iter in iters.iter_mut() {
+            heads.push(iter.next().transpose()?);
+        }
+
+        let mut ops = 0;
+        loop {
+            let next = heads
+                .iter()
+                .enumerate()
+                .filter_map(|(i, head)| head.as_ref().map(|(seqno, _)| (*seqno, i)))
+                .min();
+            let i = match next {
+                Some((_, i)) => i,
+                None => break Ok(ops),
+            };
+            let next_head = iters[i].next().transpose()?;
+            match mem::replace(&mut heads[i], next_head) {
+                Some((e_seqno, op)) => replay_op(db, on_app, e_seqno, op)?,
+                None => err_at!(Fatal, msg: format!("unreachable"))?,
+            }
+            ops += 1;
+        }
     }
 
     fn is_active(&self) -> bool {
@@ -278,6 +610,174 @@ where
     }
 }
 
+/// Order in which mutations are replayed, refer to [Wal::replay].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayOrder {
+    /// Merge mutations from all shards and replay them in seqno order.
+    /// Required when the index expects its seqno to increase
+    /// monotonically.
+    Global,
+    /// Replay mutations shard by shard. Mutations are applied in seqno
+    /// order within a shard, but not across shards.
+    PerShard,
+}
+
+impl Default for ReplayOrder {
+    fn default() -> Self {
+        ReplayOrder::Global
+    }
+}
+
+/// Callback for application defined operations, refer to
+/// [Wal::set_app_handler].
+pub type AppHandler = Box<dyn FnMut(u64, u32, &[u8]) -> Result<()> + Send>;
+
+fn replay_op<K, V, P>(
+    db: &mut P,
+    on_app: &mut Option<AppHandler>,
+    seqno: u64,
+    op: Op<K, V>,
+) -> Result<()>
+where
+    K: Clone + Ord,
+    V: Clone + Diff,
+    P: Replay<K, V>,
+{
+    match op {
+        Op::Set { key, value } => {
+            db.set_index(key, value, seqno)?;
+        }
+        Op::SetCAS { key, value, cas } => {
+            db.set_cas_index(key, value, cas, seqno)?;
+        }
+        Op::Delete { key } => {
+            db.delete_index(key, seqno)?;
+        }
+        Op::App { code, payload } => match on_app {
+            Some(on_app) => on_app(seqno, code, &payload)?,
+            None => (),
+        },
+    }
+    Ok(())
+}
+
+// Replay a single shard into `db`, return `db` back along with the
+// maximum seqno applied.
+fn replay_shard<K, V, P>(
+    mut db: P,
+    cas_policy: Option<CasPolicy<V>>,
+    on_app: Arc<Mutex<Option<AppHandler>>>,
+    iter: ReplayIter<K, V>,
+) -> Result<(P, u64)>
+where
+    K: Clone + Ord + Default + Serialize,
+    V: Clone + Diff + Default + Serialize,
+    P: Replay<K, V>,
+{
+    if let Some(policy) = cas_policy {
+        db.set_cas_policy(policy)?;
+    }
+
+    let mut max_seqno = 0;
+    for item in iter {
+        let (e_seqno, op) = item?;
+        match op {
+            Op::App { .. } => match on_app.lock() {
+                Ok(mut on_app) => replay_op(&mut db, &mut *on_app, e_seqno, op)?,
+                Err(err) => err_at!(Fatal, msg: format!("poisoned lock {}", err))?,
+            },
+            op => replay_op(&mut db, &mut None, e_seqno, op)?,
+        }
+        max_seqno = cmp::max(max_seqno, e_seqno);
+    }
+    Ok((db, max_seqno))
+}
+
+// Iterate over mutations logged in a shard, in seqno order, skipping
+// mutations less-than or equal-to `seqno`. Batches are read from disk
+// lazily.
+struct ReplayIter<K, V> {
+    seqno: u64,
+    journals: vec::IntoIter<Journal<State, Op<K, V>>>,
+    fd: Option<(ffi::OsString, fs::File)>,
+    batches: vec::IntoIter<Batch<State, Op<K, V>>>,
+    entries: vec::IntoIter<DEntry<Op<K, V>>>,
+}
+
+impl<K, V> ReplayIter<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    fn new(journals: Vec<Journal<State, Op<K, V>>>, seqno: u64) -> Self {
+        ReplayIter {
+            seqno,
+            journals: journals.into_iter(),
+            fd: None,
+            batches: vec![].into_iter(),
+            entries: vec![].into_iter(),
+        }
+    }
+
+    // return false once all the journals are exhausted.
+    fn next_batch(&mut self) -> Result<bool> {
+        loop {
+            if let (Some((file, fd)), Some(batch)) = (self.fd.as_mut(), self.batches.next()) {
+                match batch.to_last_seqno() {
+                    Some(last_seqno) if last_seqno <= self.seqno => continue,
+                    _ => (),
+                }
+                let batch = batch.into_active(fd, file)?;
+                self.entries = batch.into_entries()?.into_iter();
+                break Ok(true);
+            }
+
+            let journal = match self.journals.next() {
+                Some(journal) if journal.is_cold() => continue,
+                Some(journal) => journal,
+                None => break Ok(false),
+            };
+            match journal.to_last_seqno()? {
+                Some(last_seqno) if last_seqno <= self.seqno => continue,
+                _ => (),
+            }
+            let file_path = journal.to_file_path();
+            let fd = {
+                let mut opts = fs::OpenOptions::new();
+                err_at!(IoError, opts.read(true).write(false).open(&file_path))?
+            };
+            self.fd = Some((file_path, fd));
+            self.batches = journal.into_batches_from(self.seqno)?.into_iter();
+        }
+    }
+}
+
+impl<K, V> Iterator for ReplayIter<K, V>
+where
+    K: Default + Serialize,
+    V: Default + Serialize,
+{
+    type Item = Result<(u64, Op<K, V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.next() {
+                Some(entry) => {
+                    let (seqno, op) = entry.into_seqno_op();
+                    if seqno > self.seqno {
+                        break Some(Ok((seqno, op)));
+                    }
+                }
+                None => match self.next_batch() {
+                    Ok(true) => (),
+                    Ok(false) => break None,
+                    Err(err) => break Some(Err(err)),
+                },
+            }
+        }
+    }
+}
+
 /// Writer handle for [Wal] instance.
 pub struct Writer<K, V, H>
 where
@@ -337,6 +837,23 @@ where
         }
     }
 
+    /// Append an application defined operation, identified by `code`,
+    /// into the log. Payload is opaque to [Wal], and surfaced back during
+    /// [replay][Wal::set_app_handler]. Application operations are logged
+    /// in the first shard. Return the sequence-no for this operation.
+    pub fn append_app(&mut self, code: u32, payload: Vec<u8>) -> Result<u64> {
+        let shard = match self.shards.first_mut() {
+            Some(shard) => shard,
+            None => err_at!(InvalidInput, msg: format!("no shards to route"))?,
+        };
+
+        let op = Op::new_app(code, payload);
+        match shard.request(OpRequest::new_op(op))? {
+            OpResponse::Seqno(seqno) => Ok(seqno),
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+
     fn as_shard<'a>(
         &'a mut self,
         key: &K,
@@ -347,15 +864,126 @@ where
             hasher.finish()
         };
 
-        let n: u64 = convert_at!(self.shards.len())?;
-        let n: usize = convert_at!((hash % n))?;
+        let n = to_shard_index(hash, self.shards.len())?;
         Ok(&mut self.shards[n])
     }
 }
 
-/// Wal state, expected by Dlog implementation.
-#[derive(Clone, Default, PartialEq)]
-pub struct State;
+/// Router handle for [Wal] instance.
+///
+/// Router consistently routes a key to the same shard, using the hash
+/// function supplied via [Wal::to_router]. Mutations logged through a
+/// router follow these sequencing rules:
+///
+/// * Seqno is allocated from a counter shared by all shards, hence every
+///   mutation gets a unique seqno across the [Wal].
+/// * Within a shard, seqno is strictly increasing in journal order, and
+///   batches are flushed in seqno order.
+/// * Across shards, journal order does not imply seqno order, entries
+///   from different shards can interleave.
+/// * All mutations on a key are logged in the same shard, provided the
+///   hash function and the number of shards remain unchanged between
+///   restarts. Hence, replaying a shard shall preserve per-key ordering.
+pub struct Router<K, V, F>
+where
+    K: 'static + Send + Default + Serialize,
+    V: 'static + Send + Default + Serialize,
+    F: Fn(&K) -> u64,
+{
+    hash_fn: F,
+    shards: Vec<rt::Client<OpRequest<Op<K, V>>, OpResponse>>,
+}
+
+impl<K, V, F> Router<K, V, F>
+where
+    K: 'static + Send + Default + Serialize,
+    V: 'static + Send + Default + Serialize,
+    F: Fn(&K) -> u64,
+{
+    /// Return the number of shards managed by this router.
+    pub fn to_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Return the shard-id, that mutations on `key` are routed to.
+    pub fn to_shard_id(&self, key: &K) -> Result<usize> {
+        to_shard_index((self.hash_fn)(key), self.shards.len())
+    }
+
+    /// Append `set` operation into the log. Return the shard-id and the
+    /// sequence-no for this mutation.
+    pub fn set(&mut self, key: K, value: V) -> Result<(usize, u64)> {
+        let shard_id = self.to_shard_id(&key)?;
+        let op = Op::new_set(key, value);
+        Ok((shard_id, self.request(shard_id, op)?))
+    }
+
+    /// Append `set_cas` operation into the log. Return the shard-id and
+    /// the sequence-no for this mutation.
+    pub fn set_cas(&mut self, key: K, value: V, cas: u64) -> Result<(usize, u64)> {
+        let shard_id = self.to_shard_id(&key)?;
+        let op = Op::new_set_cas(key, value, cas);
+        Ok((shard_id, self.request(shard_id, op)?))
+    }
+
+    /// Append `delete` operation into the log. Return the shard-id and
+    /// the sequence-no for this mutation.
+    pub fn delete<Q>(&mut self, key: &Q) -> Result<(usize, u64)>
+    where
+        K: Borrow<Q>,
+        Q: ToOwned<Owned = K> + ?Sized,
+    {
+        let key: K = key.to_owned();
+        let shard_id = self.to_shard_id(&key)?;
+        let op = Op::new_delete(key);
+        Ok((shard_id, self.request(shard_id, op)?))
+    }
+
+    /// Append an application defined operation, identified by `code`,
+    /// into the first shard, refer to [Writer::append_app]. Return the
+    /// shard-id and the sequence-no for this operation.
+    pub fn append_app(&mut self, code: u32, payload: Vec<u8>) -> Result<(usize, u64)> {
+        if self.shards.len() == 0 {
+            return err_at!(InvalidInput, msg: format!("no shards to route"));
+        }
+        let op = Op::new_app(code, payload);
+        Ok((0, self.request(0, op)?))
+    }
+
+    fn request(&mut self, shard_id: usize, op: Op<K, V>) -> Result<u64> {
+        match self.shards[shard_id].request(OpRequest::new_op(op))? {
+            OpResponse::Seqno(seqno) => Ok(seqno),
+            _ => err_at!(Fatal, msg: format!("unreachable")),
+        }
+    }
+}
+
+fn to_shard_index(hash: u64, n_shards: usize) -> Result<usize> {
+    if n_shards == 0 {
+        return err_at!(InvalidInput, msg: format!("no shards to route"));
+    }
+    let n: u64 = convert_at!(n_shards)?;
+    Ok(convert_at!((hash % n))?)
+}
+
+/// Wal state, expected by Dlog implementation. Carries replication
+/// metadata, driven by an external consensus module, that is persisted
+/// with every batch, refer to [Wal::set_term] and [Wal::set_committed].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct State {
+    /// Current term, for entries in the batch.
+    pub term: u64,
+    /// Mutations till this seqno are replicated to majority of
+    /// participating nodes.
+    pub committed: u64,
+    /// Mutations till this seqno are persisted by the index, refer to
+    /// [Wal::set_checkpoint].
+    pub persisted: u64,
+    /// List of participating nodes.
+    pub config: Vec<String>,
+    /// Leader of the current term.
+    pub votedfor: String,
+}
 
 impl<K, V> DlogState<Op<K, V>> for State
 where
@@ -375,20 +1003,75 @@ where
 }
 
 impl Serialize for State {
-    fn encode(&self, _buf: &mut Vec<u8>) -> Result<usize> {
-        Ok(0)
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        buf.extend_from_slice(&self.term.to_be_bytes());
+        buf.extend_from_slice(&self.committed.to_be_bytes());
+        buf.extend_from_slice(&self.persisted.to_be_bytes());
+        let mut n = 24;
+
+        let count: u16 = convert_at!(self.config.len())?;
+        buf.extend_from_slice(&count.to_be_bytes());
+        n += 2;
+        for cnf in self.config.iter() {
+            n += encode_str(buf, cnf)?;
+        }
+        n += encode_str(buf, &self.votedfor)?;
+
+        Ok(n)
     }
 
-    fn decode(&mut self, _buf: &[u8]) -> Result<usize> {
-        Ok(0)
+    fn decode(&mut self, buf: &[u8]) -> Result<usize> {
+        // batches persisted before replication metadata carry no state.
+        if buf.is_empty() {
+            *self = Default::default();
+            return Ok(0);
+        }
+
+        check_remaining!(buf, 26, "wal-batch-state")?;
+        self.term = u64::from_be_bytes(array_at!(buf[0..8])?);
+        self.committed = u64::from_be_bytes(array_at!(buf[8..16])?);
+        self.persisted = u64::from_be_bytes(array_at!(buf[16..24])?);
+        let mut n = 24;
+
+        let count = u16::from_be_bytes(array_at!(buf[n..n + 2])?);
+        n += 2;
+        self.config = Vec::with_capacity(convert_at!(count)?);
+        for _i in 0..count {
+            let (cnf, m) = decode_str(&buf[n..], "wal-batch-config")?;
+            self.config.push(cnf);
+            n += m;
+        }
+        let (votedfor, m) = decode_str(&buf[n..], "wal-batch-votedfor")?;
+        self.votedfor = votedfor;
+        n += m;
+
+        Ok(n)
     }
 }
+
+fn encode_str(buf: &mut Vec<u8>, s: &str) -> Result<usize> {
+    let b = s.as_bytes();
+    let len: u16 = convert_at!(b.len())?;
+    buf.extend_from_slice(&len.to_be_bytes());
+    buf.extend_from_slice(b);
+    Ok(2 + b.len())
+}
+
+fn decode_str(buf: &[u8], msg: &str) -> Result<(String, usize)> {
+    check_remaining!(buf, 2, msg)?;
+    let m: usize = convert_at!(u16::from_be_bytes(array_at!(buf[..2])?))?;
+    check_remaining!(buf, 2 + m, msg)?;
+    let s = err_at!(InvalidInput, std::str::from_utf8(&buf[2..2 + m]))?;
+    Ok((s.to_string(), 2 + m))
+}
 #[derive(PartialEq, Debug)]
 enum OpType {
     // Data operations
     Set = 1,
     SetCAS,
     Delete,
+    // Application operations
+    App,
     // Config operations
     // TBD
 }
@@ -399,6 +1082,7 @@ impl From<u64> for OpType {
             1 => OpType::Set,
             2 => OpType::SetCAS,
             3 => OpType::Delete,
+            4 => OpType::App,
             _ => unreachable!(),
         }
     }
@@ -411,6 +1095,8 @@ pub enum Op<K, V> {
     Set { key: K, value: V },
     SetCAS { key: K, value: V, cas: u64 },
     Delete { key: K },
+    // Application operations, opaque to Wal.
+    App { code: u32, payload: Vec<u8> },
 }
 
 impl<K, V> Default for Op<K, V>
@@ -451,6 +1137,13 @@ where
                 },
             ) => key.eq(k) && value.eq(v) && cas.eq(c),
             (Op::Delete { key }, Op::Delete { key: k }) => key == k,
+            (
+                Op::App { code, payload },
+                Op::App {
+                    code: c,
+                    payload: p,
+                },
+            ) => code == c && payload == p,
             _ => false,
         }
     }
@@ -476,6 +1169,9 @@ where
             Op::Delete { key } => {
                 write!(f, "Op::Set< key: {:?}>", key)?;
             }
+            Op::App { code, payload } => {
+                write!(f, "Op::App<code:{} payload:{} bytes>", code, payload.len())?;
+            }
         }
         Ok(())
     }
@@ -494,6 +1190,10 @@ impl<K, V> Op<K, V> {
         Op::Delete { key }
     }
 
+    pub(crate) fn new_app(code: u32, payload: Vec<u8>) -> Op<K, V> {
+        Op::App { code, payload }
+    }
+
     fn op_type(buf: &[u8]) -> Result<OpType> {
         check_remaining!(buf, 8, "wal-op-type")?;
         let hdr1 = u64::from_be_bytes(array_at!(buf[..8])?);
@@ -520,6 +1220,10 @@ where
                 let n = Self::encode_delete(buf, key)?;
                 n
             }
+            Op::App { code, payload } => {
+                let n = Self::encode_app(buf, *code, payload)?;
+                n
+            }
         })
     }
 
@@ -529,12 +1233,14 @@ where
             OpType::Set => Op::new_set(key, Default::default()),
             OpType::SetCAS => Op::new_set_cas(key, Default::default(), Default::default()),
             OpType::Delete => Op::new_delete(key),
+            OpType::App => Op::new_app(Default::default(), vec![]),
         };
 
         match self {
             Op::Set { key, value } => Self::decode_set(buf, key, value),
             Op::SetCAS { key, value, cas } => Self::decode_set_cas(buf, key, value, cas),
             Op::Delete { key } => Self::decode_delete(buf, key),
+            Op::App { code, payload } => Self::decode_app(buf, code, payload),
         }
     }
 }
@@ -723,6 +1429,49 @@ where
     }
 }
 
+// +--------------------------------+-------------------------------+
+// | reserved |         op-type     |       op-code                 |
+// +--------------------------------+-------------------------------+
+// |                           payload-len                          |
+// +----------------------------------------------------------------+
+// |                             payload                            |
+// +----------------------------------------------------------------+
+//
+// reserved:    bits 63, 62, 61, 60, 59, 58, 57, 56
+// op-type:     24-bit
+// op-code:     32-bit, application defined.
+// payload-len: 64-bit
+//
+impl<K, V> Op<K, V> {
+    fn encode_app(buf: &mut Vec<u8>, code: u32, payload: &[u8]) -> Result<usize> {
+        let hdr1: u64 = ((OpType::App as u64) << 32) | (code as u64);
+        let plen: u64 = convert_at!(payload.len())?;
+
+        buf.extend_from_slice(&hdr1.to_be_bytes());
+        buf.extend_from_slice(&plen.to_be_bytes());
+        buf.extend_from_slice(payload);
+
+        Ok(convert_at!((plen + 16))?)
+    }
+
+    fn decode_app(buf: &[u8], code: &mut u32, payload: &mut Vec<u8>) -> Result<usize> {
+        let n = 16;
+        let plen = {
+            check_remaining!(buf, n, "wal-op-app-hdr")?;
+            let hdr1 = u64::from_be_bytes(array_at!(buf[..8])?);
+            *code = convert_at!((hdr1 & 0xFFFFFFFF))?;
+            let plen = u64::from_be_bytes(array_at!(buf[8..16])?);
+            let plen: usize = convert_at!(plen)?;
+            plen
+        };
+
+        check_remaining!(buf[n..], plen, "wal-op-app-payload")?;
+        *payload = buf[n..n + plen].to_vec();
+
+        Ok(n + plen)
+    }
+}
+
 #[cfg(test)]
 #[path = "wal_test.rs"]
 mod wal_test;
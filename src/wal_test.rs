@@ -7,6 +7,7 @@ use crate::{
     core::{Entry, Index, Reader, Writer},
     dlog_journal::Journal,
     llrb::Llrb,
+    rdms::{Rdms, WalConfig},
 };
 
 // TODO: fine tune Wal test cases
@@ -18,10 +19,22 @@ fn test_state() {
     state.on_add_entry(&entry);
 
     let mut buf = vec![];
-    assert_eq!(state.encode(&mut buf).unwrap(), 0);
+    assert_eq!(state.encode(&mut buf).unwrap(), 28);
     let mut dec_state: State = Default::default();
-    dec_state.decode(&buf).unwrap();
+    assert_eq!(dec_state.decode(&buf).unwrap(), 28);
     assert!(dec_state == state);
+
+    state.term = 10;
+    state.committed = 100;
+    state.persisted = 90;
+    state.config = vec!["n1".to_string(), "n2".to_string()];
+    state.votedfor = "n1".to_string();
+
+    let mut buf = vec![];
+    assert_eq!(state.encode(&mut buf).unwrap(), 38);
+    let mut dec_state: State = Default::default();
+    assert_eq!(dec_state.decode(&buf).unwrap(), 38);
+    assert_eq!(dec_state, state);
 }
 
 #[test]
@@ -32,6 +45,8 @@ fn test_op_type() {
     assert_eq!(op_type, OpType::SetCAS);
     let op_type: OpType = From::from(3_u64);
     assert_eq!(op_type, OpType::Delete);
+    let op_type: OpType = From::from(4_u64);
+    assert_eq!(op_type, OpType::App);
 }
 
 #[test]
@@ -74,6 +89,15 @@ fn test_op() {
         Op::Delete { key: 34 } => (),
         _ => unreachable!(),
     }
+
+    let op: Op<i32, i32> = Op::new_app(0xABCD, vec![1, 2, 3]);
+    out.resize(0, 0);
+    op.encode(&mut out).unwrap();
+    assert_eq!(Op::<i32, i32>::op_type(&out).unwrap(), OpType::App);
+    let n = res.decode(&out).expect("op-app decode failed");
+    assert_eq!(n, 19);
+    assert_eq!(res, op);
+    assert!(res.decode(&out[..18]).is_err());
 }
 
 #[test]
@@ -207,6 +231,78 @@ fn test_wal() {
     }
 }
 
+#[test]
+fn test_wal_retention() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-retention");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let (nshards, journal_limit, batch_size, fsync) = (1, 1_000, 1, false);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            fsync,
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 0..2000 {
+            w.set(key, key * 10).unwrap();
+        }
+    }
+
+    let n_journals = || {
+        let items = fs::read_dir(&dir).unwrap();
+        let files = items.map(|item| item.unwrap().file_name());
+        files
+            .filter(|f| f.to_str().unwrap().ends_with(".dlog"))
+            .count()
+    };
+    let n1 = n_journals();
+    assert!(n1 > 10, "{}", n1);
+
+    // nothing to purge, till checkpoint is set.
+    wl.set_retention(Some(5_000), None).unwrap();
+    assert_eq!(n_journals(), n1);
+
+    wl.set_checkpoint(1000).unwrap();
+    thread::sleep(time::Duration::from_millis(1500));
+    let n2 = n_journals();
+    assert!(n2 < n1, "{} {}", n2, n1);
+
+    wl.close().unwrap();
+
+    let dl = Dlog::<State, Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        nshards,
+        journal_limit,
+        batch_size,
+        fsync,
+    )
+    .unwrap();
+    let mut lis: Vec<u64> = vec![];
+    let iter = dl.shards.into_iter().map(|shard| shard.into_journals());
+    for journal in iter.flatten() {
+        lis.push(journal.to_last_seqno().unwrap().unwrap_or(std::u64::MAX));
+    }
+    assert!(lis.into_iter().all(|x| x > 1000));
+}
+
 #[test]
 fn test_wal_replay() {
     let seed: u128 = random();
@@ -296,6 +392,728 @@ fn test_wal_replay() {
     }
 }
 
+#[test]
+fn test_wal_router() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-router");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let nshards = (rng.gen::<usize>() % 4) + 1;
+    let journal_limit = (rng.gen::<usize>() % 100_000) + 1_000;
+    let batch_size = (rng.gen::<usize>() % 100) + 1;
+
+    println!(
+        "seed:{} nshards:{} journal_limit:{} batch_size:{}",
+        seed, nshards, journal_limit, batch_size
+    );
+
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    let mut threads = vec![];
+    for i in 0..16 {
+        let mut r = wl.to_router(|key: &i64| *key as u64).unwrap();
+        assert_eq!(r.to_shards(), nshards);
+        let thread = thread::spawn(move || {
+            let mut rng = {
+                let seed = seed + (i as u128);
+                SmallRng::from_seed(seed.to_le_bytes())
+            };
+            let mut items = vec![];
+            for _ in 0..1000 {
+                let key = (rng.gen::<i64>() % 1000).abs();
+                let (shard_id, _seqno) = match rng.gen::<u8>() % 3 {
+                    0 | 1 => r.set(key, rng.gen()).unwrap(),
+                    _ => r.delete(&key).unwrap(),
+                };
+                assert_eq!(shard_id, r.to_shard_id(&key).unwrap());
+                assert_eq!(shard_id, (key as usize) % nshards);
+                items.push(shard_id);
+            }
+            items
+        });
+        threads.push(thread)
+    }
+    let mut n_items = 0;
+    for thread in threads.into_iter() {
+        n_items += thread.join().unwrap().len();
+    }
+    wl.close().unwrap();
+
+    let dl = Dlog::<State, Op<i64, i64>>::load(
+        dir.clone(),
+        name.clone(),
+        nshards,
+        journal_limit,
+        batch_size,
+        false, /*fsync*/
+    )
+    .unwrap();
+    let mut seqnos = vec![];
+    for (shard_id, shard) in dl.shards.into_iter().enumerate() {
+        let mut last_seqno = 0;
+        for journal in shard.into_journals().into_iter() {
+            let file_path = journal.to_file_path();
+            let mut fd = {
+                let mut opts = fs::OpenOptions::new();
+                opts.read(true).open(&file_path).unwrap()
+            };
+            for batch in journal.into_batches().unwrap().into_iter() {
+                let entries = {
+                    let a = batch.into_active(&mut fd, &file_path).unwrap();
+                    a.into_entries().unwrap()
+                };
+                for entry in entries.into_iter() {
+                    let (seqno, op) = entry.into_seqno_op();
+                    let key = match op {
+                        Op::Set { key, .. } => key,
+                        Op::SetCAS { key, .. } => key,
+                        Op::Delete { key } => key,
+                    };
+                    assert_eq!((key as usize) % nshards, shard_id);
+                    assert!(seqno > last_seqno, "{} {}", seqno, last_seqno);
+                    last_seqno = seqno;
+                    seqnos.push(seqno);
+                }
+            }
+        }
+    }
+    seqnos.sort();
+    assert_eq!(seqnos.len(), n_items);
+    assert_eq!(seqnos, (1..=(n_items as u64)).collect::<Vec<u64>>());
+}
+
+#[test]
+fn test_wal_replay_order() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-replay-order");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let nshards = 4;
+    let journal_limit = (rng.gen::<usize>() % 100_000) + 1_000;
+    let batch_size = (rng.gen::<usize>() % 100) + 1;
+    println!("seed:{} journal_limit:{}", seed, journal_limit);
+
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    {
+        let mut r = wl.to_router(|key: &i64| *key as u64).unwrap();
+        for _ in 0..10_000 {
+            let key = (rng.gen::<i64>() % 1000).abs();
+            r.set(key, rng.gen()).unwrap();
+        }
+    }
+    wl.close().unwrap();
+
+    let skip = rng.gen::<u64>() % 10_000;
+    for order in vec![ReplayOrder::Global, ReplayOrder::PerShard].into_iter() {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        let mut wl: Wal<i64, i64, RandomState> = Wal::from_dlog(dl, RandomState::new());
+        wl.set_replay_order(order).unwrap();
+
+        let mut db = ReplaySeqnos(vec![]);
+        let n = wl.replay(&mut db, skip).unwrap();
+        assert_eq!(n, db.0.len());
+        assert_eq!(n as u64, 10_000 - skip, "{:?}", order);
+        if order == ReplayOrder::PerShard {
+            db.0.sort();
+        }
+        let seqnos: Vec<u64> = ((skip + 1)..=10_000).collect();
+        assert_eq!(db.0, seqnos, "{:?}", order);
+    }
+}
+
+#[test]
+fn test_wal_replay_parallel() {
+    let seed: u128 = random();
+    let mut rng = SmallRng::from_seed(seed.to_le_bytes());
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-replay-parallel");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let name = "users".to_string();
+    let nshards = 4;
+    let journal_limit = (rng.gen::<usize>() % 100_000) + 1_000;
+    let batch_size = (rng.gen::<usize>() % 100) + 1;
+    println!("seed:{} journal_limit:{}", seed, journal_limit);
+
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    {
+        let mut r = wl.to_router(|key: &i64| *key as u64).unwrap();
+        for _ in 0..10_000 {
+            let key = (rng.gen::<i64>() % 1000).abs();
+            r.set(key, rng.gen()).unwrap();
+        }
+    }
+    wl.close().unwrap();
+
+    let load = || {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        let wl: Wal<i64, i64, RandomState> = Wal::from_dlog(dl, RandomState::new());
+        wl
+    };
+
+    let writers = vec![ReplaySeqnos(vec![])];
+    match load().replay_parallel(writers, 0) {
+        Err(Error::InvalidInput(_)) => (),
+        _ => panic!("expected invalid input"),
+    }
+
+    let skip = rng.gen::<u64>() % 10_000;
+    let writers: Vec<ReplaySeqnos> = (0..nshards).map(|_| ReplaySeqnos(vec![])).collect();
+    let (writers, max_seqno) = load().replay_parallel(writers, skip).unwrap();
+    assert_eq!(writers.len(), nshards);
+    assert_eq!(max_seqno, 10_000);
+
+    let mut seqnos = vec![];
+    for db in writers.into_iter() {
+        let mut sorted = db.0.clone();
+        sorted.sort();
+        assert_eq!(db.0, sorted);
+        seqnos.extend_from_slice(&db.0);
+    }
+    seqnos.sort();
+    let ref_seqnos: Vec<u64> = ((skip + 1)..=10_000).collect();
+    assert_eq!(seqnos, ref_seqnos);
+}
+
+#[test]
+fn test_wal_app_ops() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-app-ops");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("users".to_string(), 2, 10_000, 10);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    let mut ref_apps = vec![];
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 0..1000 {
+            w.set(key, key * 10).unwrap();
+            if key % 100 == 0 {
+                let (code, payload) = (key as u32, format!("schema-{}", key).into_bytes());
+                let seqno = w.append_app(code, payload.clone()).unwrap();
+                ref_apps.push((seqno, code, payload));
+            }
+        }
+        let mut r = wl.to_router(|key: &i64| *key as u64).unwrap();
+        let (shard_id, seqno) = r.append_app(7, vec![]).unwrap();
+        assert_eq!(shard_id, 0);
+        ref_apps.push((seqno, 7, vec![]));
+    }
+    wl.close().unwrap();
+
+    for handler in vec![true, false].into_iter() {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            false, /*fsync*/
+        )
+        .unwrap();
+        let mut wl: Wal<i64, i64, RandomState> = Wal::from_dlog(dl, RandomState::new());
+        let apps = Arc::new(std::sync::Mutex::new(vec![]));
+        if handler {
+            let apps = Arc::clone(&apps);
+            wl.set_app_handler(move |seqno, code, payload: &[u8]| {
+                apps.lock().unwrap().push((seqno, code, payload.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+        }
+
+        let mut db = ReplaySeqnos(vec![]);
+        let n = wl.replay(&mut db, 0).unwrap();
+        assert_eq!(n, 1000 + ref_apps.len());
+        assert_eq!(db.0.len(), 1000);
+        let apps = apps.lock().unwrap().clone();
+        if handler {
+            assert_eq!(apps, ref_apps);
+        } else {
+            assert!(apps.is_empty());
+        }
+    }
+}
+
+#[test]
+fn test_wal_commit_hook() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-commit-hook");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("users".to_string(), 2, 1_000, 10);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    let summaries = Arc::new(std::sync::Mutex::new(vec![]));
+    {
+        let summaries = Arc::clone(&summaries);
+        wl.set_commit_hook(move |summary: &BatchSummary| {
+            summaries.lock().unwrap().push(summary.clone());
+        })
+        .unwrap();
+    }
+
+    let mut ref_seqnos = vec![];
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 0..1000 {
+            ref_seqnos.push(w.set(key, key * 10).unwrap());
+        }
+    }
+    wl.close().unwrap();
+
+    let summaries = summaries.lock().unwrap().clone();
+    let mut seqnos = vec![];
+    for shard_id in 0..nshards {
+        let mut last_seqno = 0;
+        let iter = summaries.iter().filter(|s| s.shard_id == shard_id);
+        for summary in iter {
+            assert!(summary.start_seqno > last_seqno, "{:?}", summary);
+            assert!(summary.last_seqno >= summary.start_seqno, "{:?}", summary);
+            assert!(summary.bytes > 0, "{:?}", summary);
+            seqnos.push((summary.start_seqno, summary.last_seqno));
+            last_seqno = summary.last_seqno;
+        }
+    }
+    // committed batches shall cover all logged mutations.
+    for seqno in ref_seqnos.iter() {
+        let ok = seqnos.iter().any(|(a, z)| a <= seqno && seqno <= z);
+        assert!(ok, "{}", seqno);
+    }
+    let last_seqno = seqnos.into_iter().map(|(_, z)| z).max();
+    assert_eq!(last_seqno, ref_seqnos.last().cloned());
+}
+
+#[test]
+fn test_wal_term() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-term");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("users".to_string(), 2, 1_000, 10);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    assert_eq!(wl.to_state().unwrap(), Default::default());
+
+    let config = vec!["n1".to_string(), "n2".to_string(), "n3".to_string()];
+    wl.set_config(config.clone()).unwrap();
+    wl.set_term(2, "n1").unwrap();
+    assert!(wl.set_term(1, "n1").is_err());
+    assert!(wl.set_term(2, "n2").is_err());
+
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 0..100 {
+            w.set(key, key * 10).unwrap();
+        }
+    }
+    assert!(wl.set_committed(1000).is_err());
+    wl.set_committed(50).unwrap();
+    wl.set_committed(40).unwrap(); // can't go backward
+    wl.set_checkpoint(30).unwrap();
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 100..200 {
+            w.set(key, key * 10).unwrap();
+        }
+    }
+
+    let state = wl.to_state().unwrap();
+    assert_eq!(state.term, 2);
+    assert_eq!(state.committed, 50);
+    assert_eq!(state.persisted, 30);
+    assert_eq!(state.config, config);
+    assert_eq!(state.votedfor, "n1".to_string());
+    wl.close().unwrap();
+
+    // state shall be restored from the latest batch, across rotations.
+    let wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    assert_eq!(wl.to_state().unwrap(), state);
+    wl.purge().unwrap();
+}
+
+#[test]
+fn test_wal_replay_cas_policy() {
+    use crate::{core::CasPolicy, mvcc::Mvcc};
+
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-replay-cas-policy");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("users".to_string(), 1, 10_000, 1);
+    {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        let mut wl: Wal<i64, i64, RandomState> = Wal::from_dlog(dl, RandomState::new());
+        {
+            // valid only with CasPolicy::CreateIfAbsent.
+            let mut w = wl.to_writer().unwrap();
+            w.set_cas(10, 100, 5).unwrap();
+        }
+        wl.close().unwrap();
+    }
+    let load = || -> Wal<i64, i64, RandomState> {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+
+    // policy configured on the index shall be preserved.
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("twal-cas-policy");
+    index.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+    assert_eq!(load().replay(index.as_mut(), 0).unwrap(), 1);
+    assert_eq!(
+        format!("{:?}", index.to_cas_policy()),
+        "CasPolicy::CreateIfAbsent"
+    );
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(100));
+
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("twal-cas-policy");
+    index.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+    assert_eq!(load().replay(index.as_mut(), 0).unwrap(), 1);
+    assert_eq!(
+        format!("{:?}", index.to_cas_policy()),
+        "CasPolicy::CreateIfAbsent"
+    );
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(100));
+
+    let mut index: Box<Llrb<i64, i64>> = Llrb::new_lsm("twal-cas-policy");
+    match load().replay(index.as_mut(), 0) {
+        Err(Error::InvalidCAS(0)) => (),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    // policy explicitly configured on the wal shall be pushed to index.
+    let mut index: Box<Mvcc<i64, i64>> = Mvcc::new_lsm("twal-cas-policy");
+    let mut wl = load();
+    wl.set_cas_policy(CasPolicy::CreateIfAbsent).unwrap();
+    assert_eq!(wl.replay(index.as_mut(), 0).unwrap(), 1);
+    assert_eq!(
+        format!("{:?}", index.to_cas_policy()),
+        "CasPolicy::CreateIfAbsent"
+    );
+    assert_eq!(index.get(&10).unwrap().to_native_value(), Some(100));
+
+    load().purge().unwrap();
+}
+
+#[test]
+fn test_wal_legacy_journal() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-wal-legacy-journal");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (name, nshards, journal_limit, batch_size) = ("users".to_string(), 1, 10_000_000, 10);
+    let mut wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::create(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    {
+        let mut w = wl.to_writer().unwrap();
+        for key in 0..100 {
+            w.set(key, key * 10).unwrap();
+        }
+    }
+    wl.close().unwrap();
+
+    // rewrite journals in the baseline batch format.
+    for item in fs::read_dir(&dir).unwrap() {
+        let file_path = item.unwrap().path().into_os_string();
+        into_legacy_journal(&file_path);
+    }
+
+    let wl: Wal<i64, i64, RandomState> = {
+        let dl = Dlog::<State, Op<i64, i64>>::load(
+            dir.clone(),
+            name.clone(),
+            nshards,
+            journal_limit,
+            batch_size,
+            true, /*fsync*/
+        )
+        .unwrap();
+        Wal::from_dlog(dl, RandomState::new())
+    };
+    assert_eq!(wl.to_state().unwrap(), State::default());
+
+    let mut db = ReplaySeqnos(vec![]);
+    assert_eq!(wl.replay(&mut db, 0).unwrap(), 100);
+    assert_eq!(db.0, (1..=100).collect::<Vec<u64>>());
+}
+
+// rewrite journal as a sequence of batches without state-length and
+// state-bytes, as persisted before batches were versioned.
+fn into_legacy_journal(file_path: &ffi::OsStr) {
+    use std::convert::TryInto;
+
+    let buf = fs::read(file_path).unwrap();
+    let (mut fpos, mut out) = (0, vec![]);
+    while (fpos + 8) <= buf.len() {
+        let word = u64::from_be_bytes(buf[fpos..fpos + 8].try_into().unwrap());
+        let (version, length) = (word >> 48, (word & 0xFFFF_FFFF_FFFF) as usize);
+        if version != 1 || (fpos + length) > buf.len() {
+            break;
+        }
+        let batch = &buf[fpos..fpos + length];
+        let slen = u64::from_be_bytes(batch[24..32].try_into().unwrap()) as usize;
+        let n = ((length - 8 - slen) as u64).to_be_bytes();
+        out.extend_from_slice(&n);
+        out.extend_from_slice(&batch[8..24]);
+        out.extend_from_slice(&batch[32 + slen..length - 8]);
+        out.extend_from_slice(&n);
+        fpos += length;
+    }
+    assert!(out.len() > 0);
+    fs::write(file_path, &out).unwrap();
+}
+
+struct ReplaySeqnos(Vec<u64>);
+
+impl Replay<i64, i64> for ReplaySeqnos {
+    fn set_index(&mut self, _key: i64, _value: i64, index: u64) -> Result<()> {
+        self.0.push(index);
+        Ok(())
+    }
+
+    fn set_cas_index(&mut self, _: i64, _: i64, _: u64, index: u64) -> Result<()> {
+        self.0.push(index);
+        Ok(())
+    }
+
+    fn delete_index(&mut self, _key: i64, index: u64) -> Result<()> {
+        self.0.push(index);
+        Ok(())
+    }
+
+    fn set_cas_policy(&mut self, _policy: CasPolicy<i64>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rdms_wal() {
+    let dir = {
+        let mut dir_path = path::PathBuf::new();
+        dir_path.push(std::env::temp_dir().into_os_string());
+        dir_path.push("test-rdms-wal");
+        let dir: &ffi::OsStr = dir_path.as_ref();
+        dir.clone().to_os_string()
+    };
+    fs::remove_dir_all(&dir).ok();
+    let name = "users";
+
+    let mut rdms: Box<Rdms<i64, i64, Box<Llrb<i64, i64>>>> =
+        Rdms::new(name, Llrb::new(name)).unwrap();
+    assert_eq!(rdms.set_wal(WalConfig::new(&dir)).unwrap(), 0);
+    assert!(rdms.set_wal(WalConfig::new(&dir)).is_err());
+    match rdms.to_writer() {
+        Err(Error::APIMisuse(_)) => (),
+        Err(err) => panic!("unexpected {:?}", err),
+        Ok(_) => panic!("expected APIMisuse"),
+    }
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        for key in 0..10 {
+            w.set(key, key * 10).unwrap();
+        }
+        w.set_cas(5, 500, 6).unwrap();
+        assert!(w.set_cas(6, 600, 100).is_err());
+        w.delete(&3).unwrap();
+    }
+    let seqno = rdms.to_seqno().unwrap();
+    rdms.close().unwrap();
+
+    let mut rdms: Box<Rdms<i64, i64, Box<Llrb<i64, i64>>>> =
+        Rdms::new(name, Llrb::new(name)).unwrap();
+    assert_eq!(rdms.set_wal(WalConfig::new(&dir)).unwrap(), 13);
+    assert_eq!(rdms.to_seqno().unwrap(), seqno);
+    {
+        let mut r = rdms.to_reader().unwrap();
+        for key in 0..10 {
+            let value = match key {
+                3 => None,
+                5 => Some(500),
+                key => Some(key * 10),
+            };
+            let entry = r.get(&key).ok();
+            assert_eq!(entry.map(|e| e.to_native_value()).flatten(), value);
+        }
+    }
+    {
+        let mut w = rdms.to_wal_writer().unwrap();
+        w.set(100, 1000).unwrap();
+    }
+    assert_eq!(rdms.to_seqno().unwrap(), seqno + 1);
+    rdms.purge().unwrap();
+}
+
 fn create_wal(
     seed: u128,
     wl: &mut Wal<i64, i64, RandomState>, // wal
@@ -304,6 +1122,7 @@ fn create_wal(
     let (tx, rx) = mpsc::channel();
     let mut last_seqno = wl.to_seqno();
     assert_eq!(last_seqno, 1);
+    assert_eq!(wl.to_last_seqno(), 0);
 
     let mut threads = vec![];
     for i in 0..1000 {
@@ -373,6 +1192,7 @@ fn load_wal(
     let (tx, rx) = mpsc::channel();
     let last_seqno = wl.to_seqno();
     assert_eq!(last_seqno, 100_001);
+    assert_eq!(wl.to_last_seqno(), 100_000);
 
     let mut threads = vec![];
     for i in 0..1000 {
@@ -443,15 +1263,15 @@ fn validate_dlog1(dl: Dlog<State, Op<i64, i64>>, items: Vec<(u64, Op<i64, i64>)>
         .flatten()
         .collect();
     for journal in journals.into_iter() {
+        let file_path = journal.to_file_path();
         let mut fd = {
-            let file_path = journal.to_file_path();
             let mut opts = fs::OpenOptions::new();
             opts.read(true).open(&file_path).unwrap()
         };
         let mut es: Vec<DEntry<Op<i64, i64>>> = vec![];
         for batch in journal.into_batches().unwrap().into_iter() {
             let a = {
-                let a = batch.into_active(&mut fd).unwrap();
+                let a = batch.into_active(&mut fd, &file_path).unwrap();
                 a.into_entries().unwrap()
             };
             es.extend_from_slice(&a);
@@ -480,15 +1300,15 @@ fn validate_dlog2(
         .flatten()
         .collect();
     for journal in journals.into_iter() {
+        let file_path = journal.to_file_path();
         let mut fd = {
-            let file_path = journal.to_file_path();
             let mut opts = fs::OpenOptions::new();
             opts.read(true).open(&file_path).unwrap()
         };
         let mut es: Vec<DEntry<Op<i64, i64>>> = vec![];
         for batch in journal.into_batches().unwrap().into_iter() {
             let a = {
-                let a = batch.into_active(&mut fd).unwrap();
+                let a = batch.into_active(&mut fd, &file_path).unwrap();
                 a.into_entries().unwrap()
             };
             es.extend_from_slice(&a);